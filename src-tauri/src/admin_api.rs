@@ -0,0 +1,384 @@
+use crate::danmaku::{DanmakuSource, RoomManager, RoomStatus};
+use crate::error::{AppError, AppResult};
+use crate::file_server::port;
+use crate::file_server::BIND_LOCALHOST;
+use axum::extract::{Path, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio_util::sync::CancellationToken;
+
+// 保存配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "admin_api.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+const DEFAULT_PORT: u16 = 23582;
+// 停止服务器时等待请求处理完成的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 收到重新加载配置的请求时通知前端重新读取并应用设置
+pub const RELOAD_EVENT: &str = "admin://reload-config";
+
+// 管理接口的配置，只监听本机地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdminApiConfig {
+    // 启用后随应用启动
+    pub enabled: bool,
+    pub port: u16,
+    // 请求需要携带 Authorization: Bearer <token>，首次加载时自动生成
+    pub token: String,
+}
+
+impl Default for AdminApiConfig {
+    fn default() -> Self {
+        AdminApiConfig {
+            enabled: false,
+            port: DEFAULT_PORT,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminApiStatus {
+    #[serde(flatten)]
+    pub config: AdminApiConfig,
+    pub running: bool,
+    pub active_port: Option<u16>,
+}
+
+// GET /api/status 的返回值
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientStatus {
+    version: String,
+    // 是否有已连接或正在连接的直播间
+    fetching: bool,
+    // 是否有暂停后可以继续的直播间
+    paused: bool,
+    rooms: Vec<RoomStatus>,
+}
+
+struct Running {
+    cancel: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+struct Shared {
+    app: AppHandle,
+    config: RwLock<AdminApiConfig>,
+    running: tokio::sync::Mutex<Option<Running>>,
+    active_port: Mutex<Option<u16>>,
+}
+
+impl Shared {
+    async fn start(self: &Arc<Self>) -> AppResult<AdminApiStatus> {
+        let mut running = self.running.lock().await;
+        if running.is_some() {
+            return Err(AppError::AlreadyRunning);
+        }
+        let config = self.config.read().unwrap().clone();
+        let ip: IpAddr = BIND_LOCALHOST
+            .parse()
+            .map_err(|_| AppError::InvalidConfig("无效的监听地址".to_string()))?;
+        let listener = port::bind(ip, config.port, false).await?;
+        let addr = listener.local_addr().map_err(AppError::Bind)?;
+        log::info!("管理接口启动在 http://{}", addr);
+
+        let router = Router::new()
+            .route("/api/status", get(status))
+            .route("/api/fetching/start", post(start_fetching))
+            .route("/api/fetching/stop", post(stop_fetching))
+            .route("/api/fetching/toggle", post(toggle_fetching))
+            .route("/api/rooms/:room_id", post(add_room).delete(remove_room))
+            .route("/api/config/reload", post(reload_config))
+            .layer(middleware::from_fn_with_state(self.clone(), authorize))
+            .with_state(self.clone());
+        let cancel = CancellationToken::new();
+        let shutdown = cancel.clone();
+        let task = tauri::async_runtime::spawn(async move {
+            let result = axum::serve(listener, router)
+                .with_graceful_shutdown(async move { shutdown.cancelled().await })
+                .await;
+            if let Err(err) = result {
+                log::warn!("管理接口异常退出: {}", err);
+            }
+        });
+        *running = Some(Running { cancel, task });
+        *self.active_port.lock().unwrap() = Some(addr.port());
+        drop(running);
+        Ok(self.status())
+    }
+
+    async fn stop(&self) -> AppResult<AdminApiStatus> {
+        let Some(running) = self.running.lock().await.take() else {
+            return Err(AppError::NotRunning);
+        };
+        running.cancel.cancel();
+        let abort_handle = running.task.inner().abort_handle();
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, running.task)
+            .await
+            .is_err()
+        {
+            abort_handle.abort();
+        }
+        *self.active_port.lock().unwrap() = None;
+        log::info!("管理接口已停止");
+        Ok(self.status())
+    }
+
+    fn status(&self) -> AdminApiStatus {
+        let active_port = *self.active_port.lock().unwrap();
+        AdminApiStatus {
+            config: self.config.read().unwrap().clone(),
+            running: active_port.is_some(),
+            active_port,
+        }
+    }
+}
+
+// 本机 HTTP 管理接口，供 Stream Deck 插件和脚本在不打开界面的情况下控制客户端
+pub struct AdminApi {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl AdminApi {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            config: RwLock::new(AdminApiConfig::default()),
+            running: tokio::sync::Mutex::new(None),
+            active_port: Mutex::new(None),
+        });
+        let api = AdminApi { shared, store };
+        api.load();
+        Ok(api)
+    }
+
+    // 从设置文件读取配置，没有令牌时生成新的令牌
+    fn load(&self) {
+        let mut config: AdminApiConfig = self
+            .store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        if config.token.is_empty() {
+            config.token = generate_token();
+            self.save(&config);
+        }
+        *self.shared.config.write().unwrap() = config;
+    }
+
+    fn save(&self, config: &AdminApiConfig) {
+        match serde_json::to_value(config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存管理接口配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化管理接口配置失败: {}", err),
+        }
+    }
+
+    // 配置为启用时在后台启动
+    pub fn auto_start(&self) {
+        if !self.shared.config.read().unwrap().enabled {
+            return;
+        }
+        let shared = self.shared.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = shared.start().await {
+                log::warn!("管理接口启动失败: {}", err);
+            }
+        });
+    }
+
+    pub async fn stop(&self) -> AppResult<AdminApiStatus> {
+        self.shared.stop().await
+    }
+
+    // 保存配置，按新配置重新启动或停止服务器，令牌为空时保留原来的令牌
+    pub async fn update_config(&self, config: AdminApiConfig) -> AppResult<AdminApiStatus> {
+        if config.port == 0 {
+            return Err(AppError::InvalidConfig("端口不能为 0".to_string()));
+        }
+        let token = config.token.trim().to_string();
+        let config = AdminApiConfig {
+            token: if token.is_empty() {
+                self.shared.config.read().unwrap().token.clone()
+            } else {
+                token
+            },
+            ..config
+        };
+        self.save(&config);
+        let enabled = config.enabled;
+        *self.shared.config.write().unwrap() = config;
+
+        if self.shared.running.lock().await.is_some() {
+            self.shared.stop().await?;
+        }
+        if enabled {
+            self.shared.start().await
+        } else {
+            Ok(self.shared.status())
+        }
+    }
+
+    // 生成新的令牌，之前的令牌立即失效
+    pub fn regenerate_token(&self) -> AdminApiStatus {
+        let config = {
+            let mut config = self.shared.config.write().unwrap();
+            config.token = generate_token();
+            config.clone()
+        };
+        self.save(&config);
+        self.shared.status()
+    }
+
+    pub fn status(&self) -> AdminApiStatus {
+        self.shared.status()
+    }
+}
+
+fn generate_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+// 校验 Authorization: Bearer <token>
+async fn authorize(State(shared): State<Arc<Shared>>, request: Request, next: Next) -> Response {
+    let token = shared.config.read().unwrap().token.clone();
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|value| !token.is_empty() && value.trim() == token);
+    if !authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "code": "UNAUTHORIZED", "message": "访问令牌无效" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+fn error_response(err: AppError) -> Response {
+    let status = match err {
+        AppError::RoomExists(_) | AppError::AlreadyRunning => StatusCode::CONFLICT,
+        AppError::RoomNotFound(_) => StatusCode::NOT_FOUND,
+        AppError::InvalidConfig(_) => StatusCode::BAD_REQUEST,
+        AppError::BilibiliApi { .. } | AppError::Http(_) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(err)).into_response()
+}
+
+fn rooms(shared: &Shared) -> Result<tauri::State<'_, RoomManager>, Response> {
+    shared
+        .app
+        .try_state::<RoomManager>()
+        .ok_or_else(|| StatusCode::SERVICE_UNAVAILABLE.into_response())
+}
+
+fn client_status(shared: &Shared, rooms: &RoomManager) -> ClientStatus {
+    let statuses = rooms.rooms_status();
+    ClientStatus {
+        version: shared.app.package_info().version.to_string(),
+        fetching: !statuses.is_empty(),
+        paused: rooms.is_stopped(),
+        rooms: statuses,
+    }
+}
+
+// GET /api/status
+async fn status(State(shared): State<Arc<Shared>>) -> Response {
+    match rooms(&shared) {
+        Ok(rooms) => Json(client_status(&shared, &rooms)).into_response(),
+        Err(response) => response,
+    }
+}
+
+// POST /api/fetching/start：重新连接暂停的直播间，返回连接失败的错误
+async fn start_fetching(State(shared): State<Arc<Shared>>) -> Response {
+    let rooms = match rooms(&shared) {
+        Ok(rooms) => rooms,
+        Err(response) => return response,
+    };
+    let errors = rooms.start_all().await;
+    Json(json!({ "status": client_status(&shared, &rooms), "errors": errors })).into_response()
+}
+
+// POST /api/fetching/stop：断开所有直播间，之后可以用 start 继续
+async fn stop_fetching(State(shared): State<Arc<Shared>>) -> Response {
+    let rooms = match rooms(&shared) {
+        Ok(rooms) => rooms,
+        Err(response) => return response,
+    };
+    rooms.stop_all();
+    Json(json!({ "status": client_status(&shared, &rooms), "errors": [] })).into_response()
+}
+
+// POST /api/fetching/toggle：正在抓取时停止，否则继续，适合绑定到一个按键
+async fn toggle_fetching(state: State<Arc<Shared>>) -> Response {
+    let fetching = match rooms(&state) {
+        Ok(rooms) => !rooms.list_rooms().is_empty(),
+        Err(response) => return response,
+    };
+    if fetching {
+        stop_fetching(state).await
+    } else {
+        start_fetching(state).await
+    }
+}
+
+// POST /api/rooms/{room_id}：以游客身份连接直播间
+async fn add_room(State(shared): State<Arc<Shared>>, Path(room_id): Path<u64>) -> Response {
+    let rooms = match rooms(&shared) {
+        Ok(rooms) => rooms,
+        Err(response) => return response,
+    };
+    let source = DanmakuSource::Direct {
+        room_id,
+        cookie: None,
+        credential_id: None,
+    };
+    match rooms.add_room(source).await {
+        Ok(status) => Json(status).into_response(),
+        Err(err) => error_response(err),
+    }
+}
+
+// DELETE /api/rooms/{room_id}
+async fn remove_room(State(shared): State<Arc<Shared>>, Path(room_id): Path<u64>) -> Response {
+    let rooms = match rooms(&shared) {
+        Ok(rooms) => rooms,
+        Err(response) => return response,
+    };
+    match rooms.remove_room(room_id) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => error_response(err),
+    }
+}
+
+// POST /api/config/reload：通知前端从设置文件重新加载并应用配置
+async fn reload_config(State(shared): State<Arc<Shared>>) -> Response {
+    log::info!("管理接口请求重新加载配置");
+    let _ = shared.app.emit(RELOAD_EVENT, ());
+    StatusCode::ACCEPTED.into_response()
+}
@@ -0,0 +1,337 @@
+use crate::bilibili::{self, get_api};
+use crate::danmaku::{ConnectionState, ConnectionStateChanged, DanmakuEvent};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::inflight::{Inflight, InflightFuture};
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_http::reqwest::{Client, Url};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+mod files;
+
+// 保存缓存设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "asset_cache.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 直播间礼物列表，包含礼物名称和图标地址
+const GIFT_CONFIG_URL: &str =
+    "https://api.live.bilibili.com/xlive/web-room/v1/giftPanel/giftConfig";
+
+// 只缓存 B 站图片服务器上的资源，避免被当作任意地址的代理
+const ALLOWED_HOSTS: &[&str] = &["hdslb.com", "biliimg.com"];
+
+const MIN_SIZE_MB: u64 = 16;
+const MAX_SIZE_MB: u64 = 10 * 1024;
+// 每下载多少个文件检查一次缓存体积
+const EVICT_EVERY: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetCacheConfig {
+    // 缓存目录的最大体积，超过时删除最久未使用的文件
+    pub max_size_mb: u64,
+    // 连接直播间后预先下载该直播间的礼物图标
+    pub prefetch_gifts: bool,
+    // 收到表情弹幕时预先下载表情图片
+    pub prefetch_emotes: bool,
+}
+
+impl Default for AssetCacheConfig {
+    fn default() -> Self {
+        AssetCacheConfig {
+            max_size_mb: 500,
+            prefetch_gifts: true,
+            prefetch_emotes: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetCacheStats {
+    pub files: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    // 已知图标地址的礼物数量
+    pub gifts: usize,
+}
+
+struct Inner {
+    http: Client,
+    dir: PathBuf,
+    config: RwLock<AssetCacheConfig>,
+    downloads: Inflight<String, PathBuf>,
+    download_count: AtomicUsize,
+    // 礼物名称和图标地址
+    gifts: RwLock<HashMap<String, String>>,
+    // 本次运行中已获取过礼物列表的直播间
+    gift_rooms: Mutex<HashSet<u64>>,
+}
+
+impl Inner {
+    fn max_bytes(&self) -> u64 {
+        self.config.read().unwrap().max_size_mb * 1024 * 1024
+    }
+
+    async fn download(&self, url: &str) -> AppResult<PathBuf> {
+        let path = files::cache_path(&self.dir, url);
+        files::download(&self.http, url, &path).await?;
+        if self.download_count.fetch_add(1, Ordering::Relaxed) % EVICT_EVERY == 0 {
+            self.evict().await;
+        }
+        Ok(path)
+    }
+
+    async fn evict(&self) {
+        let dir = self.dir.clone();
+        let max_bytes = self.max_bytes();
+        let removed = tokio::task::spawn_blocking(move || files::evict(&dir, max_bytes))
+            .await
+            .unwrap_or(0);
+        if removed > 0 {
+            log::info!("资源缓存超过上限，删除了 {} 个最久未使用的文件", removed);
+        }
+    }
+
+    // 获取直播间的礼物列表，记录图标地址并按设置预先下载
+    async fn load_gifts(self: &Arc<Self>, room_id: u64) {
+        if !self.gift_rooms.lock().unwrap().insert(room_id) {
+            return;
+        }
+        let url = format!("{}?platform=pc&room_id={}", GIFT_CONFIG_URL, room_id);
+        let data = match get_api(&self.http, &url, None).await {
+            Ok(data) => data,
+            Err(err) => {
+                log::warn!(room_id; "获取直播间 {} 的礼物列表失败: {}", room_id, err);
+                self.gift_rooms.lock().unwrap().remove(&room_id);
+                return;
+            }
+        };
+        let icons: Vec<(String, String)> = data["list"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|gift| {
+                let name = gift["name"].as_str().filter(|name| !name.is_empty())?;
+                let icon = gift["img_basic"].as_str().filter(|icon| !icon.is_empty())?;
+                Some((name.to_string(), icon.to_string()))
+            })
+            .collect();
+        let new_icons: Vec<String> = {
+            let mut gifts = self.gifts.write().unwrap();
+            icons
+                .into_iter()
+                .filter_map(|(name, icon)| {
+                    let changed = gifts.get(&name) != Some(&icon);
+                    gifts.insert(name, icon.clone());
+                    changed.then_some(icon)
+                })
+                .collect()
+        };
+        if !self.config.read().unwrap().prefetch_gifts {
+            return;
+        }
+        // 依次下载，避免同时发出大量请求
+        let mut downloaded = 0;
+        for icon in new_icons {
+            if fetch(self, &icon).await.is_ok() {
+                downloaded += 1;
+            }
+        }
+        if downloaded > 0 {
+            log::info!(room_id; "已缓存直播间 {} 的 {} 个礼物图标", room_id, downloaded);
+        }
+    }
+}
+
+// 相同地址的并发请求合并为一次，命中缓存时直接返回
+async fn fetch(inner: &Arc<Inner>, url: &str) -> AppResult<PathBuf> {
+    let url = normalize_url(url)?;
+    let path = files::cache_path(&inner.dir, &url);
+    if files::touch(&path) {
+        return Ok(path);
+    }
+    download(inner, &url)
+        .await
+        .ok_or_else(|| AppError::Asset(format!("无法下载 {}", url)))
+}
+
+fn download(inner: &Arc<Inner>, url: &str) -> InflightFuture<PathBuf> {
+    inner.downloads.get_or_start(url.to_string(), || {
+        let inner = inner.clone();
+        let url = url.to_string();
+        async move {
+            let result = inner.download(&url).await;
+            inner.downloads.finish(&url);
+            result
+                .inspect_err(|err| log::warn!("下载 {} 失败: {}", url, err))
+                .ok()
+        }
+        .boxed()
+    })
+}
+
+// 缓存礼物图标、表情和头像等 B 站图片，由文件服务器的 /api/cache/ 接口提供给覆盖层
+// 观众到 B 站图片服务器的连接不稳定时，覆盖层仍然可以从本机加载图片
+#[derive(Clone)]
+pub struct AssetCache {
+    inner: Arc<Inner>,
+    store: Arc<Store<Wry>>,
+}
+
+impl AssetCache {
+    pub fn new(app: &AppHandle, dir: PathBuf) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let inner = Arc::new(Inner {
+            http: bilibili::client(),
+            dir,
+            config: RwLock::new(config),
+            downloads: Inflight::new(),
+            download_count: AtomicUsize::new(0),
+            gifts: RwLock::new(HashMap::new()),
+            gift_rooms: Mutex::new(HashSet::new()),
+        });
+        Ok(AssetCache { inner, store })
+    }
+
+    // 开始根据直播间事件和连接状态预先下载表情和礼物图标
+    pub fn prefetch(
+        &self,
+        events: Subscriber<DanmakuEvent>,
+        states: broadcast::Receiver<ConnectionStateChanged>,
+    ) {
+        tauri::async_runtime::spawn(run(self.inner.clone(), events, states));
+    }
+
+    pub fn get_config(&self) -> AssetCacheConfig {
+        self.inner.config.read().unwrap().clone()
+    }
+
+    pub async fn update_config(&self, config: AssetCacheConfig) -> AppResult<AssetCacheConfig> {
+        if !(MIN_SIZE_MB..=MAX_SIZE_MB).contains(&config.max_size_mb) {
+            return Err(AppError::InvalidConfig(format!(
+                "资源缓存大小应在 {} 到 {} MB 之间",
+                MIN_SIZE_MB, MAX_SIZE_MB
+            )));
+        }
+        *self.inner.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存资源缓存设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化资源缓存设置失败: {}", err),
+        }
+        // 调小上限后立即删除超出的部分
+        self.inner.evict().await;
+        Ok(config)
+    }
+
+    // 返回缓存在磁盘上的文件，不存在时下载
+    pub async fn fetch(&self, url: &str) -> AppResult<PathBuf> {
+        fetch(&self.inner, url).await
+    }
+
+    // 根据礼物名称查找图标地址，连接直播间后才能获取到
+    pub fn gift_icon(&self, name: &str) -> Option<String> {
+        self.inner.gifts.read().unwrap().get(name).cloned()
+    }
+
+    pub async fn stats(&self) -> AssetCacheStats {
+        let dir = self.inner.dir.clone();
+        let (count, bytes) = tokio::task::spawn_blocking(move || files::usage(&dir))
+            .await
+            .unwrap_or_default();
+        AssetCacheStats {
+            files: count,
+            bytes,
+            max_bytes: self.inner.max_bytes(),
+            gifts: self.inner.gifts.read().unwrap().len(),
+        }
+    }
+
+    // 删除所有缓存的文件，之后访问时重新下载
+    pub async fn purge(&self) -> AssetCacheStats {
+        let dir = self.inner.dir.clone();
+        let removed = tokio::task::spawn_blocking(move || files::clear(&dir))
+            .await
+            .unwrap_or(0);
+        log::info!("已清空资源缓存，删除了 {} 个文件", removed);
+        self.stats().await
+    }
+}
+
+async fn run(
+    inner: Arc<Inner>,
+    mut events: Subscriber<DanmakuEvent>,
+    mut states: broadcast::Receiver<ConnectionStateChanged>,
+) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if !event.replay && !event.simulated => {
+                    let Some(emoji) = event.emoji else {
+                        continue;
+                    };
+                    if !inner.config.read().unwrap().prefetch_emotes {
+                        continue;
+                    }
+                    let inner = inner.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let _ = fetch(&inner, &emoji).await;
+                    });
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            change = states.recv() => match change {
+                Ok(change) if change.state == ConnectionState::Connected
+                    && change.previous == ConnectionState::Connecting =>
+                {
+                    let inner = inner.clone();
+                    tauri::async_runtime::spawn(async move {
+                        inner.load_gifts(change.room_id).await;
+                    });
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+}
+
+// 统一为 https 地址，并检查是否为允许缓存的图片服务器
+fn normalize_url(url: &str) -> AppResult<String> {
+    let url = url.trim();
+    let url = if let Some(rest) = url.strip_prefix("//") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        format!("https://{}", rest)
+    } else {
+        url.to_string()
+    };
+    let allowed = Url::parse(&url).ok().is_some_and(|parsed| {
+        parsed.scheme() == "https"
+            && parsed.host_str().is_some_and(|host| {
+                ALLOWED_HOSTS
+                    .iter()
+                    .any(|allowed| host == *allowed || host.ends_with(&format!(".{}", allowed)))
+            })
+    });
+    if !allowed {
+        return Err(AppError::Asset(format!("不支持缓存该地址: {}", url)));
+    }
+    Ok(url)
+}
@@ -0,0 +1,103 @@
+use crate::error::AppResult;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri_plugin_http::reqwest::Client;
+
+// 根据资源地址生成缓存文件名，地址中的扩展名决定文件类型
+pub(super) fn cache_path(dir: &Path, url: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let extension = url
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.split('?').next())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .filter(|extension| matches!(extension.as_str(), "jpg" | "jpeg" | "png" | "gif" | "webp"))
+        .unwrap_or_else(|| "jpg".to_string());
+    dir.join(format!("{:016x}.{}", hasher.finish(), extension))
+}
+
+// 命中缓存时更新修改时间，淘汰时按修改时间判断最近是否使用过
+pub(super) fn touch(path: &Path) -> bool {
+    File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()))
+        .is_ok()
+}
+
+pub(super) async fn download(http: &Client, url: &str, path: &Path) -> AppResult<()> {
+    let bytes = http
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    // 先写入临时文件再重命名，避免读取到下载了一半的文件
+    let partial = path.with_extension("part");
+    tokio::fs::write(&partial, &bytes).await?;
+    tokio::fs::rename(&partial, path).await?;
+    Ok(())
+}
+
+fn list(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())?;
+            Some((entry.path(), metadata.len(), metadata.modified().ok()?))
+        })
+        .collect()
+}
+
+// 返回缓存的文件数量和总大小
+pub(super) fn usage(dir: &Path) -> (usize, u64) {
+    let files = list(dir);
+    (files.len(), files.iter().map(|(_, size, _)| size).sum())
+}
+
+// 缓存超过 max_bytes 时删除最久未使用的文件，返回删除的数量
+pub(super) fn evict(dir: &Path, max_bytes: u64) -> usize {
+    let mut files = list(dir);
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= max_bytes {
+        return 0;
+    }
+    files.sort_by_key(|(_, _, modified)| *modified);
+    let mut removed = 0;
+    for (path, size, _) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= size;
+            removed += 1;
+        }
+    }
+    removed
+}
+
+// 删除所有缓存的文件，返回删除的数量
+pub(super) fn clear(dir: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+        .count()
+}
@@ -0,0 +1,368 @@
+use crate::danmaku::{self, DanmakuEvent, DanmakuSender, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::template;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存自动感谢设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "auto_thank.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 发送或模拟发送感谢弹幕时发送给前端的事件
+pub const LOG_EVENT: &str = "auto-thank://log";
+
+// 保留的发送记录数量
+const MAX_LOGS: usize = 200;
+// 检查连击礼物是否结束的间隔
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_COMBO_WINDOW_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThankRule {
+    pub enabled: bool,
+    // 支持 {{uname}}、{{message}}（礼物或大航海名称）、{{num}}、{{price}} 等事件字段
+    pub template: String,
+    // 金额不低于该值时才感谢，单位为元，连击礼物按合并后的金额计算
+    pub min_price: f64,
+}
+
+impl ThankRule {
+    fn new(enabled: bool, template: &str, min_price: f64) -> Self {
+        ThankRule {
+            enabled,
+            template: template.to_string(),
+            min_price,
+        }
+    }
+}
+
+impl Default for ThankRule {
+    fn default() -> Self {
+        ThankRule::new(false, "", 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoThankConfig {
+    pub enabled: bool,
+    // 只记录将要发送的内容，不实际发送
+    pub dry_run: bool,
+    // 发送使用的账号，为空时使用连接直播间的账号
+    pub credential_id: Option<String>,
+    pub gift: ThankRule,
+    pub guard: ThankRule,
+    pub super_chat: ThankRule,
+    // 同一用户连续赠送同一礼物时，等待该时间没有新的礼物后合并感谢一次
+    pub combo_window_secs: u64,
+    // 同一用户在该时间内只感谢一次
+    pub user_cooldown_secs: u64,
+    // 同一直播间在该时间内只发送一条感谢
+    pub room_cooldown_secs: u64,
+    // 每条弹幕的最大字数，超出时拆分为多条
+    pub max_length: usize,
+}
+
+impl Default for AutoThankConfig {
+    fn default() -> Self {
+        AutoThankConfig {
+            enabled: false,
+            dry_run: true,
+            credential_id: None,
+            gift: ThankRule::new(true, "感谢{{uname}}的{{message}}x{{num}}", 0.0),
+            guard: ThankRule::new(true, "感谢{{uname}}开通{{message}}", 0.0),
+            super_chat: ThankRule::new(true, "感谢{{uname}}的醒目留言", 0.0),
+            combo_window_secs: 5,
+            user_cooldown_secs: 30,
+            room_cooldown_secs: 0,
+            max_length: danmaku::DEFAULT_MAX_LENGTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThankResult {
+    Sent,
+    // 模拟模式，没有实际发送
+    DryRun,
+    // 处于冷却时间内，没有发送
+    Cooldown,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThankLog {
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    pub room_id: u64,
+    pub uid: u64,
+    pub kind: EventKind,
+    pub text: String,
+    pub result: ThankResult,
+    pub error: Option<String>,
+}
+
+// 等待合并的连击礼物
+struct Combo {
+    event: DanmakuEvent,
+    updated_at: Instant,
+}
+
+struct Shared {
+    app: AppHandle,
+    config: RwLock<AutoThankConfig>,
+    // 按直播间、用户和礼物名称合并
+    combos: Mutex<HashMap<(u64, u64, String), Combo>>,
+    // 每个用户和直播间上次感谢的时间
+    thanked_users: Mutex<HashMap<(u64, u64), Instant>>,
+    thanked_rooms: Mutex<HashMap<u64, Instant>>,
+    logs: Mutex<VecDeque<ThankLog>>,
+}
+
+impl Shared {
+    fn log(&self, entry: ThankLog) {
+        let _ = self.app.emit(LOG_EVENT, &entry);
+        let mut logs = self.logs.lock().unwrap();
+        if logs.len() >= MAX_LOGS {
+            logs.pop_front();
+        }
+        logs.push_back(entry);
+    }
+
+    fn on_event(self: &Arc<Self>, event: DanmakuEvent) {
+        if event.replay || event.muted {
+            return;
+        }
+        let config = self.config.read().unwrap().clone();
+        // 模拟事件只在模拟发送时感谢，不会向直播间发送真实弹幕
+        if !config.enabled || (event.simulated && !config.dry_run) {
+            return;
+        }
+        match event.kind {
+            EventKind::Gift if config.gift.enabled => {
+                let key = (event.room_id, event.uid, event.message.clone());
+                let mut combos = self.combos.lock().unwrap();
+                match combos.get_mut(&key) {
+                    Some(combo) => {
+                        combo.event.num += event.num;
+                        combo.event.price += event.price;
+                        combo.updated_at = Instant::now();
+                    }
+                    None => {
+                        combos.insert(
+                            key,
+                            Combo {
+                                event,
+                                updated_at: Instant::now(),
+                            },
+                        );
+                    }
+                }
+            }
+            EventKind::Guard if config.guard.enabled => self.thank(&config.guard, event),
+            EventKind::SuperChat if config.super_chat.enabled => {
+                self.thank(&config.super_chat, event)
+            }
+            _ => {}
+        }
+    }
+
+    // 感谢等待时间内没有新礼物的连击
+    fn flush_combos(self: &Arc<Self>) {
+        let config = self.config.read().unwrap().clone();
+        let window = Duration::from_secs(config.combo_window_secs);
+        let finished: Vec<DanmakuEvent> = {
+            let mut combos = self.combos.lock().unwrap();
+            let keys: Vec<(u64, u64, String)> = combos
+                .iter()
+                .filter(|(_, combo)| combo.updated_at.elapsed() >= window)
+                .map(|(key, _)| key.clone())
+                .collect();
+            keys.iter()
+                .filter_map(|key| combos.remove(key))
+                .map(|combo| combo.event)
+                .collect()
+        };
+        for event in finished {
+            self.thank(&config.gift, event);
+        }
+    }
+
+    fn thank(self: &Arc<Self>, rule: &ThankRule, event: DanmakuEvent) {
+        if event.price < rule.min_price || rule.template.trim().is_empty() {
+            return;
+        }
+        let config = self.config.read().unwrap().clone();
+        let text = template::render(&rule.template, &event);
+        let mut entry = ThankLog {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            room_id: event.room_id,
+            uid: event.uid,
+            kind: event.kind,
+            text,
+            result: ThankResult::DryRun,
+            error: None,
+        };
+        if !self.try_acquire(&config, event.room_id, event.uid) {
+            entry.result = ThankResult::Cooldown;
+            self.log(entry);
+            return;
+        }
+        if config.dry_run {
+            log::info!(room_id = event.room_id; "模拟发送感谢弹幕: {}", entry.text);
+            self.log(entry);
+            return;
+        }
+        let shared = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let result = shared.send(&config, &entry).await;
+            match result {
+                Ok(()) => entry.result = ThankResult::Sent,
+                Err(err) => {
+                    log::warn!(room_id = entry.room_id; "发送感谢弹幕失败: {}", err);
+                    entry.result = ThankResult::Failed;
+                    entry.error = Some(err.to_string());
+                }
+            }
+            shared.log(entry);
+        });
+    }
+
+    // 检查冷却时间，允许发送时记录本次发送
+    fn try_acquire(&self, config: &AutoThankConfig, room_id: u64, uid: u64) -> bool {
+        let now = Instant::now();
+        let user_cooldown = Duration::from_secs(config.user_cooldown_secs);
+        let room_cooldown = Duration::from_secs(config.room_cooldown_secs);
+        let mut users = self.thanked_users.lock().unwrap();
+        let mut rooms = self.thanked_rooms.lock().unwrap();
+        if users
+            .get(&(room_id, uid))
+            .is_some_and(|at| now.duration_since(*at) < user_cooldown)
+            || rooms
+                .get(&room_id)
+                .is_some_and(|at| now.duration_since(*at) < room_cooldown)
+        {
+            return false;
+        }
+        let longest = user_cooldown.max(room_cooldown);
+        users.retain(|_, at| now.duration_since(*at) < longest);
+        users.insert((room_id, uid), now);
+        rooms.insert(room_id, now);
+        true
+    }
+
+    async fn send(&self, config: &AutoThankConfig, entry: &ThankLog) -> AppResult<()> {
+        let cookie =
+            danmaku::resolve_cookie(&self.app, entry.room_id, config.credential_id.clone())?;
+        let sender = self
+            .app
+            .try_state::<DanmakuSender>()
+            .ok_or_else(|| AppError::InvalidConfig("弹幕发送未初始化".to_string()))?;
+        sender
+            .send(entry.room_id, &entry.text, &cookie, config.max_length)
+            .await?;
+        Ok(())
+    }
+}
+
+// 按规则自动发送感谢弹幕，连击礼物合并后感谢一次
+pub struct AutoThank {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl AutoThank {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            config: RwLock::new(config),
+            combos: Mutex::new(HashMap::new()),
+            thanked_users: Mutex::new(HashMap::new()),
+            thanked_rooms: Mutex::new(HashMap::new()),
+            logs: Mutex::new(VecDeque::new()),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(AutoThank { shared, store })
+    }
+
+    pub fn get_config(&self) -> AutoThankConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: AutoThankConfig) -> AppResult<AutoThankConfig> {
+        if config.combo_window_secs > MAX_COMBO_WINDOW_SECS {
+            return Err(AppError::InvalidConfig(format!(
+                "连击合并时间不能超过 {} 秒",
+                MAX_COMBO_WINDOW_SECS
+            )));
+        }
+        for rule in [&config.gift, &config.guard, &config.super_chat] {
+            if !rule.min_price.is_finite() || rule.min_price < 0.0 {
+                return Err(AppError::InvalidConfig(
+                    "感谢的金额门槛不能小于 0".to_string(),
+                ));
+            }
+        }
+        if config.max_length == 0 {
+            return Err(AppError::InvalidConfig(
+                "弹幕最大字数必须大于 0".to_string(),
+            ));
+        }
+        *self.shared.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存自动感谢设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化自动感谢设置失败: {}", err),
+        }
+        Ok(config)
+    }
+
+    // 最近的发送记录，按时间倒序排列
+    pub fn logs(&self, limit: usize) -> Vec<ThankLog> {
+        self.shared
+            .logs
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => shared.on_event(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("自动感谢处理不及时，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = interval.tick() => shared.flush_combos(),
+        }
+    }
+}
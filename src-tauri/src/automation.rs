@@ -0,0 +1,508 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::device_output::{DeviceOutput, MidiMessage};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::live_control::LiveControl;
+use crate::obs::{ObsAction, ObsClient};
+use crate::template;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_http::reqwest::Client;
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存自动化规则的文件，位于应用数据目录
+const STORE_FILE: &str = "automation.json";
+const RULES_KEY: &str = "rules";
+
+// 执行记录发生时发送给前端的事件
+pub const LOG_EVENT: &str = "automation://log";
+// 播放声音的操作交给前端执行
+pub const PLAY_SOUND_EVENT: &str = "automation://play-sound";
+
+// 内存中保留的执行记录数量
+const MAX_LOGS: usize = 1000;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 自动化规则：事件满足条件时依次执行操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomationRule {
+    // 新建规则时可以为空，保存时生成
+    #[serde(default)]
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub condition: RuleCondition,
+    pub actions: Vec<RuleAction>,
+    // 频率限制：window_secs 秒内最多执行 max_executions 次，任一为 0 时不限制
+    #[serde(default)]
+    pub max_executions: u32,
+    #[serde(default)]
+    pub window_secs: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 触发条件，设置的条件需要全部满足
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleCondition {
+    // 为空时匹配所有类型
+    pub kinds: Vec<EventKind>,
+    pub room_id: Option<u64>,
+    // 金额不低于该值，单位为元
+    pub min_price: Option<f64>,
+    // 弹幕、醒目留言内容或礼物名称包含该文本，不区分大小写
+    pub keyword: Option<String>,
+    // 大航海等级，1 为总督、2 为提督、3 为舰长，只匹配该等级及以上的用户
+    pub guard_level: Option<u8>,
+}
+
+impl RuleCondition {
+    fn matches(&self, event: &DanmakuEvent) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.contains(&event.kind) {
+            return false;
+        }
+        if self.room_id.is_some_and(|room_id| room_id != event.room_id) {
+            return false;
+        }
+        if self
+            .min_price
+            .is_some_and(|min_price| event.price < min_price)
+        {
+            return false;
+        }
+        if let Some(keyword) = self
+            .keyword
+            .as_deref()
+            .filter(|keyword| !keyword.is_empty())
+        {
+            if !event
+                .message
+                .to_lowercase()
+                .contains(&keyword.to_lowercase())
+            {
+                return false;
+            }
+        }
+        if let Some(level) = self.guard_level {
+            if event.guard_level == 0 || event.guard_level > level {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// 规则触发后执行的操作，文本字段支持 {{uname}} 等事件字段模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    // 系统通知
+    Notify {
+        title: String,
+        body: String,
+    },
+    Obs {
+        action: ObsAction,
+    },
+    // 以 POST 发送事件 JSON
+    Webhook {
+        url: String,
+    },
+    // 由前端播放的声音文件，volume 为 0 到 1
+    PlaySound {
+        path: String,
+        #[serde(default = "default_volume")]
+        volume: f32,
+    },
+    // 以开播设置中的账号和直播间开播，area_id 为空时使用设置中的分区
+    StartLive {
+        #[serde(default)]
+        area_id: Option<u32>,
+    },
+    StopLive,
+    // 修改直播间标题
+    SetRoomTitle {
+        title: String,
+    },
+    // 发送 MIDI 消息，port 为系统中的设备名称
+    Midi {
+        port: String,
+        message: MidiMessage,
+    },
+    // 通过设备信号服务器通知 Stream Deck 插件等客户端，name 支持模板
+    DeviceSignal {
+        name: String,
+    },
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+impl RuleAction {
+    fn name(&self) -> &'static str {
+        match self {
+            RuleAction::Notify { .. } => "notify",
+            RuleAction::Obs { .. } => "obs",
+            RuleAction::Webhook { .. } => "webhook",
+            RuleAction::PlaySound { .. } => "play_sound",
+            RuleAction::StartLive { .. } => "start_live",
+            RuleAction::StopLive => "stop_live",
+            RuleAction::SetRoomTitle { .. } => "set_room_title",
+            RuleAction::Midi { .. } => "midi",
+            RuleAction::DeviceSignal { .. } => "device_signal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionResult {
+    Success,
+    Failed,
+    // 超出频率限制，没有执行
+    RateLimited,
+}
+
+// 一次操作的执行记录
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionLog {
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub event_id: String,
+    pub event_kind: EventKind,
+    // 操作类型，频率限制时为空
+    pub action: Option<&'static str>,
+    pub result: ExecutionResult,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PlaySound {
+    rule_id: String,
+    path: String,
+    volume: f32,
+}
+
+struct Shared {
+    app: AppHandle,
+    http: Client,
+    rules: RwLock<Vec<AutomationRule>>,
+    // 每条规则最近的执行时间，用于频率限制
+    executions: Mutex<HashMap<String, VecDeque<i64>>>,
+    logs: Mutex<VecDeque<ExecutionLog>>,
+}
+
+impl Shared {
+    fn log(&self, entry: ExecutionLog) {
+        let _ = self.app.emit(LOG_EVENT, &entry);
+        let mut logs = self.logs.lock().unwrap();
+        if logs.len() >= MAX_LOGS {
+            logs.pop_front();
+        }
+        logs.push_back(entry);
+    }
+
+    // 检查频率限制，允许执行时记录本次执行
+    fn try_acquire(&self, rule: &AutomationRule, now: i64) -> bool {
+        if rule.max_executions == 0 || rule.window_secs == 0 {
+            return true;
+        }
+        let window = rule.window_secs as i64 * 1000;
+        let mut executions = self.executions.lock().unwrap();
+        let history = executions.entry(rule.id.clone()).or_default();
+        while history.front().is_some_and(|time| now - time >= window) {
+            history.pop_front();
+        }
+        if history.len() >= rule.max_executions as usize {
+            return false;
+        }
+        history.push_back(now);
+        true
+    }
+
+    async fn execute(&self, rule: &AutomationRule, event: &DanmakuEvent) {
+        for action in &rule.actions {
+            let result = self.run_action(rule, action, event).await;
+            if let Err(err) = &result {
+                log::warn!(
+                    "自动化规则 {} 执行 {} 失败: {}",
+                    rule.name,
+                    action.name(),
+                    err
+                );
+            }
+            self.log(ExecutionLog {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                rule_id: rule.id.clone(),
+                rule_name: rule.name.clone(),
+                event_id: event.id.clone(),
+                event_kind: event.kind,
+                action: Some(action.name()),
+                result: if result.is_ok() {
+                    ExecutionResult::Success
+                } else {
+                    ExecutionResult::Failed
+                },
+                error: result.err().map(|err| err.to_string()),
+            });
+        }
+    }
+
+    async fn run_action(
+        &self,
+        rule: &AutomationRule,
+        action: &RuleAction,
+        event: &DanmakuEvent,
+    ) -> AppResult<()> {
+        match action {
+            RuleAction::Notify { title, body } => self
+                .app
+                .notification()
+                .builder()
+                .title(template::render(title, event))
+                .body(template::render(body, event))
+                .show()
+                .map_err(|err| AppError::Automation(err.to_string())),
+            RuleAction::Obs { action } => {
+                let obs = self
+                    .app
+                    .try_state::<ObsClient>()
+                    .ok_or(AppError::ObsNotConnected)?;
+                obs.run_action(action).await
+            }
+            RuleAction::Webhook { url } => {
+                self.http
+                    .post(template::render(url, event))
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .json(event)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(())
+            }
+            RuleAction::PlaySound { path, volume } => self
+                .app
+                .emit(
+                    PLAY_SOUND_EVENT,
+                    &PlaySound {
+                        rule_id: rule.id.clone(),
+                        path: path.clone(),
+                        volume: volume.clamp(0.0, 1.0),
+                    },
+                )
+                .map_err(|err| AppError::Automation(err.to_string())),
+            RuleAction::StartLive { area_id } => {
+                self.live_control()?.start(None, *area_id, None).await?;
+                Ok(())
+            }
+            RuleAction::StopLive => {
+                self.live_control()?.stop(None, None).await?;
+                Ok(())
+            }
+            RuleAction::SetRoomTitle { title } => {
+                self.live_control()?
+                    .update_room(None, Some(template::render(title, event)), None, None)
+                    .await
+            }
+            RuleAction::Midi { port, message } => self.device_output()?.send_midi(port, message),
+            RuleAction::DeviceSignal { name } => {
+                self.device_output()?.send_signal(
+                    &template::render(name, event),
+                    Some(&rule.id),
+                    Some(event),
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    fn live_control(&self) -> AppResult<tauri::State<'_, LiveControl>> {
+        self.app
+            .try_state::<LiveControl>()
+            .ok_or_else(|| AppError::Automation("开播控制未初始化".to_string()))
+    }
+
+    fn device_output(&self) -> AppResult<tauri::State<'_, DeviceOutput>> {
+        self.app
+            .try_state::<DeviceOutput>()
+            .ok_or_else(|| AppError::Automation("设备输出未初始化".to_string()))
+    }
+}
+
+// 按用户定义的规则对直播间事件执行操作
+pub struct AutomationEngine {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl AutomationEngine {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let rules: Vec<AutomationRule> = store
+            .get(RULES_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            http: crate::proxy::client_builder().build().unwrap_or_default(),
+            rules: RwLock::new(rules),
+            executions: Mutex::new(HashMap::new()),
+            logs: Mutex::new(VecDeque::new()),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(AutomationEngine { shared, store })
+    }
+
+    pub fn rules(&self) -> Vec<AutomationRule> {
+        self.shared.rules.read().unwrap().clone()
+    }
+
+    // 新增或按 ID 替换规则
+    pub fn save_rule(&self, rule: AutomationRule) -> AppResult<AutomationRule> {
+        if rule.actions.is_empty() {
+            return Err(AppError::InvalidConfig(
+                "自动化规则至少需要一个操作".to_string(),
+            ));
+        }
+        let mut rule = rule;
+        if rule.id.is_empty() {
+            rule.id = uuid::Uuid::new_v4().to_string();
+        }
+        {
+            let mut rules = self.shared.rules.write().unwrap();
+            match rules.iter_mut().find(|existing| existing.id == rule.id) {
+                Some(existing) => *existing = rule.clone(),
+                None => rules.push(rule.clone()),
+            }
+        }
+        // 修改后重新计算频率限制
+        self.shared.executions.lock().unwrap().remove(&rule.id);
+        self.save();
+        Ok(rule)
+    }
+
+    // 导入配置或切换方案时替换所有规则
+    pub fn replace_rules(&self, rules: Vec<AutomationRule>) -> AppResult<Vec<AutomationRule>> {
+        let mut rules = rules;
+        for rule in &mut rules {
+            if rule.actions.is_empty() {
+                return Err(AppError::InvalidConfig(format!(
+                    "自动化规则“{}”至少需要一个操作",
+                    rule.name
+                )));
+            }
+            if rule.id.is_empty() {
+                rule.id = uuid::Uuid::new_v4().to_string();
+            }
+        }
+        *self.shared.rules.write().unwrap() = rules;
+        self.shared.executions.lock().unwrap().clear();
+        self.save();
+        Ok(self.rules())
+    }
+
+    pub fn delete_rule(&self, id: &str) -> AppResult<()> {
+        {
+            let mut rules = self.shared.rules.write().unwrap();
+            let index = rules
+                .iter()
+                .position(|rule| rule.id == id)
+                .ok_or_else(|| AppError::AutomationRuleNotFound(id.to_string()))?;
+            rules.remove(index);
+        }
+        self.shared.executions.lock().unwrap().remove(id);
+        self.save();
+        Ok(())
+    }
+
+    // 最近的执行记录，按时间倒序排列
+    pub fn logs(&self, rule_id: Option<&str>, limit: usize) -> Vec<ExecutionLog> {
+        self.shared
+            .logs
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|entry| rule_id.is_none() || rule_id == Some(entry.rule_id.as_str()))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear_logs(&self) {
+        self.shared.logs.lock().unwrap().clear();
+    }
+
+    fn save(&self) {
+        let rules = self.rules();
+        match serde_json::to_value(&rules) {
+            Ok(value) => {
+                self.store.set(RULES_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存自动化规则失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化自动化规则失败: {}", err),
+        }
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                log::warn!("自动化规则处理不及时，跳过了 {} 个事件", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let rules: Vec<AutomationRule> = shared
+            .rules
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|rule| rule.enabled && rule.condition.matches(&event))
+            .cloned()
+            .collect();
+        let now = chrono::Utc::now().timestamp_millis();
+        for rule in rules {
+            if !shared.try_acquire(&rule, now) {
+                shared.log(ExecutionLog {
+                    timestamp: now,
+                    rule_id: rule.id.clone(),
+                    rule_name: rule.name.clone(),
+                    event_id: event.id.clone(),
+                    event_kind: event.kind,
+                    action: None,
+                    result: ExecutionResult::RateLimited,
+                    error: None,
+                });
+                continue;
+            }
+            // 操作可能需要等待网络请求，不阻塞后续事件
+            let shared = shared.clone();
+            let event = event.clone();
+            tauri::async_runtime::spawn(async move {
+                shared.execute(&rule, &event).await;
+            });
+        }
+    }
+}
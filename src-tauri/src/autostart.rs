@@ -0,0 +1,157 @@
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_autostart::ManagerExt;
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存开机启动选项的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "autostart.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 注册到系统的启动参数，标记本次是开机自动启动
+// 是否隐藏窗口和延迟时间在启动时从设置读取，修改选项后不需要重新注册
+pub const AUTOSTART_ARG: &str = "--autostart";
+// 启动时只显示托盘图标，也可以在快捷方式中手动添加
+pub const MINIMIZED_ARG: &str = "--minimized";
+
+// 延迟时间的上限，单位为秒
+const MAX_DELAY_SECONDS: u32 = 600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutostartConfig {
+    // 开机启动时隐藏主窗口
+    pub start_minimized: bool,
+    // 开机启动后等待一段时间再启动服务器和连接 OBS，避免网络尚未就绪
+    pub delay_seconds: u32,
+}
+
+impl Default for AutostartConfig {
+    fn default() -> Self {
+        AutostartConfig {
+            start_minimized: true,
+            delay_seconds: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub config: AutostartConfig,
+    // 本次是否由开机启动运行
+    pub launched_by_autostart: bool,
+}
+
+pub struct Autostart {
+    app: AppHandle,
+    store: Arc<Store<Wry>>,
+    config: RwLock<AutostartConfig>,
+    launched_by_autostart: bool,
+    minimized_arg: bool,
+}
+
+impl Autostart {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        Ok(Autostart {
+            app: app.clone(),
+            store,
+            config: RwLock::new(config),
+            launched_by_autostart: args.iter().any(|arg| arg == AUTOSTART_ARG),
+            minimized_arg: args.iter().any(|arg| arg == MINIMIZED_ARG),
+        })
+    }
+
+    pub fn get_config(&self) -> AutostartConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn status(&self) -> AppResult<AutostartStatus> {
+        Ok(AutostartStatus {
+            enabled: self
+                .app
+                .autolaunch()
+                .is_enabled()
+                .map_err(|err| AppError::Autostart(err.to_string()))?,
+            config: self.get_config(),
+            launched_by_autostart: self.launched_by_autostart,
+        })
+    }
+
+    pub fn set(&self, enabled: bool, config: AutostartConfig) -> AppResult<AutostartStatus> {
+        self.update_config(config)?;
+        let autolaunch = self.app.autolaunch();
+        let result = if enabled {
+            autolaunch.enable()
+        } else {
+            autolaunch.disable()
+        };
+        result.map_err(|err| AppError::Autostart(err.to_string()))?;
+        log::info!("开机启动已{}", if enabled { "开启" } else { "关闭" });
+        self.status()
+    }
+
+    // 只修改选项，不改变是否开机启动
+    pub fn update_config(&self, config: AutostartConfig) -> AppResult<AutostartConfig> {
+        if config.delay_seconds > MAX_DELAY_SECONDS {
+            return Err(AppError::InvalidConfig(format!(
+                "启动延迟不能超过 {} 秒",
+                MAX_DELAY_SECONDS
+            )));
+        }
+        *self.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存开机启动设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化开机启动设置失败: {}", err),
+        }
+        Ok(config)
+    }
+
+    // 启动时是否隐藏主窗口
+    pub fn start_hidden(&self) -> bool {
+        self.minimized_arg
+            || (self.launched_by_autostart && self.config.read().unwrap().start_minimized)
+    }
+
+    // 在启动延迟之后执行，手动启动时立即执行
+    pub fn after_delay(&self, task: impl FnOnce(&AppHandle) + Send + 'static) {
+        let delay = if self.launched_by_autostart {
+            self.config.read().unwrap().delay_seconds
+        } else {
+            0
+        };
+        if delay == 0 {
+            task(&self.app);
+            return;
+        }
+        log::info!("开机启动，{} 秒后启动服务器", delay);
+        let app = self.app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(delay as u64)).await;
+            task(&app);
+        });
+    }
+}
+
+// 在 setup 中所有模块加载之后调用，按启动参数决定是否显示主窗口
+pub fn show_main_window(app: &AppHandle) {
+    if app.state::<Autostart>().start_hidden() {
+        log::info!("以最小化方式启动，主窗口隐藏在托盘");
+        return;
+    }
+    crate::tray::show_window(app);
+}
@@ -0,0 +1,113 @@
+use crate::error::{AppError, AppResult};
+use serde_json::Value;
+use tauri_plugin_http::reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+
+mod rate_limit;
+mod wbi;
+
+pub use rate_limit::{available, budgets, ApiGroup, RateBudget};
+
+// 接口返回的风控错误码：-412 请求被拦截，-352 风控校验失败
+const RISK_CONTROL_CODES: &[i64] = &[-412, -352];
+
+// 请求 B 站接口时使用的 User-Agent
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+// 请求 B 站接口使用的客户端
+pub fn client() -> Client {
+    crate::proxy::client_builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .unwrap_or_default()
+}
+
+// 按接口分组的额度排队发出请求，返回 412/429 时暂停该分组的请求一段时间
+pub async fn send(request: RequestBuilder) -> AppResult<Response> {
+    let (http, request) = request.build_split();
+    let request = request?;
+    let group = ApiGroup::of(request.url());
+    rate_limit::acquire(group).await?;
+    let response = http.execute(request).await?;
+    if matches!(
+        response.status(),
+        StatusCode::PRECONDITION_FAILED | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return Err(rate_limit::throttled(group));
+    }
+    rate_limit::succeeded(group);
+    Ok(response)
+}
+
+// 请求 B 站接口并返回 data 字段，code 不为 0 时返回错误
+// 需要 wbi 签名的接口自动加上签名参数
+pub async fn get_api(http: &Client, url: &str, cookie: Option<&str>) -> AppResult<Value> {
+    let signed = Url::parse(url).is_ok_and(|parsed| wbi::needs_signing(&parsed));
+    let url = if signed {
+        wbi::sign(http, url).await?
+    } else {
+        url.to_string()
+    };
+    let mut request = http.get(url);
+    if let Some(cookie) = cookie {
+        request = request.header("Cookie", cookie);
+    }
+    let response = send(request).await?;
+    let result = parse_response(response).await;
+    // 签名密钥过期时同样返回风控错误码
+    if signed && matches!(result, Err(AppError::RateLimited { .. })) {
+        wbi::invalidate();
+    }
+    result
+}
+
+// 以 cookie 中的 bili_jct 作为 csrf 参数提交表单，返回 data 字段
+pub async fn post_api(
+    http: &Client,
+    url: &str,
+    cookie: &str,
+    form: &[(&str, &str)],
+) -> AppResult<Value> {
+    let csrf = cookie_value(cookie, "bili_jct")
+        .ok_or_else(|| AppError::Credential("账号 cookie 中缺少 bili_jct".to_string()))?;
+    let mut form = form.to_vec();
+    form.push(("csrf", csrf));
+    form.push(("csrf_token", csrf));
+    let request = http
+        .post(url)
+        .header("Cookie", cookie)
+        .header("Referer", "https://link.bilibili.com/")
+        .form(&form);
+    parse_response(send(request).await?).await
+}
+
+async fn parse_response(response: Response) -> AppResult<Value> {
+    let group = ApiGroup::of(response.url());
+    let text = response.text().await?;
+    let body: Value = serde_json::from_str(&text).map_err(|err| AppError::BilibiliApi {
+        code: -1,
+        message: format!("无法解析接口返回: {}", err),
+    })?;
+    let code = body["code"].as_i64().unwrap_or(-1);
+    if RISK_CONTROL_CODES.contains(&code) {
+        return Err(rate_limit::throttled(group));
+    }
+    if code != 0 {
+        return Err(AppError::BilibiliApi {
+            code,
+            message: body["message"]
+                .as_str()
+                .or_else(|| body["msg"].as_str())
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    Ok(body["data"].clone())
+}
+
+// 从 cookie 字符串中读取指定字段
+pub fn cookie_value<'a>(cookie: &'a str, name: &str) -> Option<&'a str> {
+    cookie.split(';').find_map(|pair| {
+        let (key, value) = pair.trim().split_once('=')?;
+        (key == name).then_some(value)
+    })
+}
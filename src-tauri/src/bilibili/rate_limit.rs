@@ -0,0 +1,211 @@
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri_plugin_http::reqwest::Url;
+
+// 触发风控后第一次暂停请求的时间，连续触发时翻倍直到上限
+const BACKOFF_BASE: Duration = Duration::from_secs(30);
+const BACKOFF_MAX: Duration = Duration::from_secs(600);
+
+// 按域名划分的接口分组，每组有独立的请求额度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiGroup {
+    // api.live.bilibili.com：直播间信息、弹幕服务器、礼物列表
+    Live,
+    // api.bilibili.com：用户名片、登录状态
+    Web,
+    // passport.bilibili.com：扫码登录和刷新 cookie
+    Passport,
+    Other,
+}
+
+impl ApiGroup {
+    const ALL: [ApiGroup; 4] = [
+        ApiGroup::Live,
+        ApiGroup::Web,
+        ApiGroup::Passport,
+        ApiGroup::Other,
+    ];
+
+    pub fn of(url: &Url) -> Self {
+        match url.host_str() {
+            Some("api.live.bilibili.com") => ApiGroup::Live,
+            Some("api.bilibili.com") => ApiGroup::Web,
+            Some("passport.bilibili.com") => ApiGroup::Passport,
+            _ => ApiGroup::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiGroup::Live => "live",
+            ApiGroup::Web => "web",
+            ApiGroup::Passport => "passport",
+            ApiGroup::Other => "other",
+        }
+    }
+
+    // 令牌桶容量和每秒补充的数量，容量决定短时间内最多连续发出的请求数
+    fn budget(&self) -> (f64, f64) {
+        match self {
+            ApiGroup::Live => (20.0, 2.0),
+            ApiGroup::Web => (10.0, 1.0),
+            ApiGroup::Passport => (5.0, 1.0),
+            ApiGroup::Other => (10.0, 1.0),
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    // 触发风控后暂停请求直到该时间
+    paused_until: Option<Instant>,
+    // 连续触发风控的次数，请求成功后清零
+    strikes: u32,
+    requests: u64,
+    throttled: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, group: ApiGroup) {
+        let (capacity, rate) = group.budget();
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.refilled_at = now;
+    }
+
+    fn paused_for(&self) -> Option<Duration> {
+        self.paused_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RateBudget {
+    pub group: ApiGroup,
+    pub capacity: f64,
+    // 当前剩余的请求额度
+    pub available: f64,
+    pub refill_per_sec: f64,
+    pub requests: u64,
+    // 返回 412/429 等风控响应的次数
+    pub throttled: u64,
+    // 暂停请求的剩余秒数，未暂停时为空
+    pub paused_secs: Option<u64>,
+}
+
+// 所有 B 站接口请求共用的额度，各模块通过 bilibili::send 和 get_api 发出请求时自动排队
+static BUCKETS: Mutex<Vec<Bucket>> = Mutex::new(Vec::new());
+
+fn with_bucket<T>(group: ApiGroup, f: impl FnOnce(&mut Bucket) -> T) -> T {
+    let mut buckets = BUCKETS.lock().unwrap();
+    if buckets.is_empty() {
+        let now = Instant::now();
+        buckets.extend(ApiGroup::ALL.iter().map(|group| Bucket {
+            tokens: group.budget().0,
+            refilled_at: now,
+            paused_until: None,
+            strikes: 0,
+            requests: 0,
+            throttled: 0,
+        }));
+    }
+    let bucket = &mut buckets[group.index()];
+    bucket.refill(group);
+    f(bucket)
+}
+
+fn rate_limited(group: ApiGroup, remaining: Duration) -> AppError {
+    AppError::RateLimited {
+        group: group.as_str().to_string(),
+        retry_after: remaining.as_secs().max(1),
+    }
+}
+
+// 等待分组中有可用额度，暂停期间直接返回错误，避免请求长时间挂起
+pub(super) async fn acquire(group: ApiGroup) -> AppResult<()> {
+    loop {
+        let wait = with_bucket(group, |bucket| {
+            if let Some(remaining) = bucket.paused_for() {
+                return Err(rate_limited(group, remaining));
+            }
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+                bucket.requests += 1;
+                return Ok(None);
+            }
+            let (_, rate) = group.budget();
+            Ok(Some(Duration::from_secs_f64((1.0 - bucket.tokens) / rate)))
+        })?;
+        match wait {
+            Some(wait) => tokio::time::sleep(wait).await,
+            None => return Ok(()),
+        }
+    }
+}
+
+// 收到风控响应后暂停该分组的请求，返回对应的错误
+pub(super) fn throttled(group: ApiGroup) -> AppError {
+    let pause = with_bucket(group, |bucket| {
+        let pause = BACKOFF_BASE
+            .saturating_mul(2u32.saturating_pow(bucket.strikes))
+            .min(BACKOFF_MAX);
+        bucket.strikes = bucket.strikes.saturating_add(1);
+        bucket.throttled += 1;
+        bucket.tokens = 0.0;
+        bucket.paused_until = Some(Instant::now() + pause);
+        pause
+    });
+    log::warn!(
+        "B 站 {} 接口触发风控，暂停请求 {} 秒",
+        group.as_str(),
+        pause.as_secs()
+    );
+    rate_limited(group, pause)
+}
+
+pub(super) fn succeeded(group: ApiGroup) {
+    with_bucket(group, |bucket| bucket.strikes = 0);
+}
+
+// 分组当前剩余的额度，可选的后台请求在额度不足时跳过，留给用户操作触发的请求
+pub fn available(group: ApiGroup) -> f64 {
+    with_bucket(group, |bucket| {
+        if bucket.paused_for().is_some() {
+            0.0
+        } else {
+            bucket.tokens
+        }
+    })
+}
+
+pub fn budgets() -> Vec<RateBudget> {
+    ApiGroup::ALL
+        .iter()
+        .map(|group| {
+            with_bucket(*group, |bucket| {
+                let (capacity, rate) = group.budget();
+                RateBudget {
+                    group: *group,
+                    capacity,
+                    available: bucket.tokens,
+                    refill_per_sec: rate,
+                    requests: bucket.requests,
+                    throttled: bucket.throttled,
+                    paused_secs: bucket
+                        .paused_for()
+                        .map(|remaining| remaining.as_secs().max(1)),
+                }
+            })
+        })
+        .collect()
+}
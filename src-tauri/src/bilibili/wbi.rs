@@ -0,0 +1,159 @@
+use crate::error::{AppError, AppResult};
+use md5::{Digest, Md5};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri_plugin_http::reqwest::{Client, Url};
+
+// 登录状态接口，未登录时同样返回 wbi 密钥
+const NAV_URL: &str = "https://api.bilibili.com/x/web-interface/nav";
+
+// 需要签名的接口，路径中包含 /wbi/ 的接口也会自动签名
+const SIGNED_PATHS: &[&str] = &["/xlive/web-room/v1/index/getDanmuInfo"];
+
+// 密钥每天更换，缓存一段时间后重新获取
+const KEY_TTL: Duration = Duration::from_secs(3600);
+
+// 由 img_key 和 sub_key 拼接后按该顺序重排得到 mixin_key
+const MIXIN_KEY_ENC_TAB: [usize; 64] = [
+    46, 47, 18, 2, 53, 8, 23, 32, 15, 50, 10, 31, 58, 3, 45, 35, 27, 43, 5, 49, 33, 9, 42, 19, 29,
+    28, 14, 39, 12, 38, 41, 13, 37, 48, 7, 16, 24, 55, 40, 61, 26, 17, 0, 1, 60, 51, 30, 4, 22, 25,
+    54, 21, 56, 59, 6, 63, 57, 62, 11, 36, 20, 34, 44, 52,
+];
+
+// 与 encodeURIComponent 一致，只保留字母、数字和 -_.~
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+static MIXIN_KEY: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+pub(super) fn needs_signing(url: &Url) -> bool {
+    let path = url.path();
+    path.contains("/wbi/") || SIGNED_PATHS.contains(&path)
+}
+
+// 为地址加上 wts 和 w_rid 参数
+pub(super) async fn sign(http: &Client, url: &str) -> AppResult<String> {
+    let mut url = Url::parse(url).map_err(|err| AppError::BilibiliApi {
+        code: -1,
+        message: format!("无效的接口地址 {}: {}", url, err),
+    })?;
+    let mixin_key = mixin_key(http).await?;
+    let wts = chrono::Utc::now().timestamp();
+    let query = signed_query(&url, wts, &mixin_key);
+    url.set_query(Some(&query));
+    Ok(url.into())
+}
+
+// 接口返回签名错误时丢弃缓存的密钥，下次请求时重新获取
+pub(super) fn invalidate() {
+    *MIXIN_KEY.lock().unwrap() = None;
+}
+
+async fn mixin_key(http: &Client) -> AppResult<String> {
+    if let Some((key, fetched_at)) = MIXIN_KEY.lock().unwrap().as_ref() {
+        if fetched_at.elapsed() < KEY_TTL {
+            return Ok(key.clone());
+        }
+    }
+    // 未登录时接口返回 -101，但 data 中仍然包含密钥，因此不经过 get_api 的错误检查
+    let body: Value = super::send(http.get(NAV_URL)).await?.json().await?;
+    let key_of = |field: &str| {
+        body["data"]["wbi_img"][field]
+            .as_str()
+            .and_then(|url| url.rsplit('/').next())
+            .and_then(|name| name.split('.').next())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let key =
+        mix_keys(&key_of("img_url"), &key_of("sub_url")).ok_or_else(|| AppError::BilibiliApi {
+            code: body["code"].as_i64().unwrap_or(-1),
+            message: "无法获取 wbi 签名密钥".to_string(),
+        })?;
+    *MIXIN_KEY.lock().unwrap() = Some((key.clone(), Instant::now()));
+    Ok(key)
+}
+
+// 拼接后的密钥长度不是 64 时返回 None
+fn mix_keys(img_key: &str, sub_key: &str) -> Option<String> {
+    let raw = format!("{}{}", img_key, sub_key);
+    if raw.len() != 64 {
+        return None;
+    }
+    Some(
+        MIXIN_KEY_ENC_TAB
+            .iter()
+            .take(32)
+            .map(|&index| raw.as_bytes()[index] as char)
+            .collect(),
+    )
+}
+
+// 参数按名称排序，去掉值中的 !'()* 后编码，w_rid = md5(query + mixin_key)
+fn signed_query(url: &Url, wts: i64, mixin_key: &str) -> String {
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != "w_rid" && key != "wts")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    params.push(("wts".to_string(), wts.to_string()));
+    params.sort_by(|a, b| a.0.cmp(&b.0));
+    let query = params
+        .iter()
+        .map(|(key, value)| {
+            let value: String = value.chars().filter(|c| !"!'()*".contains(*c)).collect();
+            format!(
+                "{}={}",
+                utf8_percent_encode(key, COMPONENT),
+                utf8_percent_encode(&value, COMPONENT)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    let w_rid = format!("{:x}", Md5::digest(format!("{}{}", query, mixin_key)));
+    format!("{}&w_rid={}", query, w_rid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mix_keys, signed_query};
+    use tauri_plugin_http::reqwest::Url;
+
+    // 签名文档中的示例
+    const IMG_KEY: &str = "7cd084941338484aae1ad9425b84077c";
+    const SUB_KEY: &str = "4932caff0ff746eab6f01bf08b70ac45";
+    const MIXIN_KEY: &str = "ea1db124af3c7062474693fa704f4ff8";
+
+    #[test]
+    fn mixes_keys() {
+        assert_eq!(mix_keys(IMG_KEY, SUB_KEY).as_deref(), Some(MIXIN_KEY));
+        assert_eq!(mix_keys(IMG_KEY, ""), None);
+    }
+
+    #[test]
+    fn signs_query() {
+        let url =
+            Url::parse("https://api.bilibili.com/x/test?foo=114&bar=514&zab=1919810").unwrap();
+        assert_eq!(
+            signed_query(&url, 1702204169, MIXIN_KEY),
+            "bar=514&foo=114&wts=1702204169&zab=1919810&w_rid=8f6f2b5b3d485fe1886cec6a0be8c5d4"
+        );
+    }
+
+    #[test]
+    fn replaces_existing_signature() {
+        let url = Url::parse(
+            "https://api.bilibili.com/x/test?zab=1919810&wts=1&w_rid=old&foo=114&bar=514",
+        )
+        .unwrap();
+        assert_eq!(
+            signed_query(&url, 1702204169, MIXIN_KEY),
+            "bar=514&foo=114&wts=1702204169&zab=1919810&w_rid=8f6f2b5b3d485fe1886cec6a0be8c5d4"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use crate::error::{AppError, AppResult};
+use crate::relay::Relay;
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::future::Future;
+use std::panic::{AssertUnwindSafe, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存崩溃报告设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "crash.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 最多保留的崩溃报告数量，超过时删除最旧的
+const MAX_REPORTS: usize = 100;
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+// 后台任务报告错误的通道，由写入线程保存到崩溃目录
+static ERRORS: OnceLock<mpsc::Sender<CrashReport>> = OnceLock::new();
+// 生成报告时需要的程序信息，安装时设置
+static APP_VERSION: OnceLock<String> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrashKind {
+    // 程序或后台任务 panic
+    Panic,
+    // 后台任务报告的错误
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    pub kind: CrashKind,
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    // 发生错误的线程或后台任务名称
+    pub source: Option<String>,
+    pub message: String,
+    // panic 发生的源码位置
+    pub location: Option<String>,
+    pub backtrace: Option<String>,
+    pub app_version: String,
+    pub os: String,
+    pub os_version: Option<String>,
+    pub arch: String,
+    // 上传到 vtsuru 的 Unix 毫秒时间戳，未上传时为空
+    #[serde(default)]
+    pub uploaded_at: Option<i64>,
+}
+
+impl CrashReport {
+    fn new(kind: CrashKind, source: Option<String>, message: String) -> Self {
+        CrashReport {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            source,
+            message,
+            location: None,
+            backtrace: None,
+            app_version: APP_VERSION.get().cloned().unwrap_or_default(),
+            os: std::env::consts::OS.to_string(),
+            os_version: sysinfo::System::long_os_version(),
+            arch: std::env::consts::ARCH.to_string(),
+            uploaded_at: None,
+        }
+    }
+}
+
+// 上传设置，默认不上传
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashConfig {
+    // 启动时自动上传未上传的报告
+    pub upload_enabled: bool,
+    pub upload_url: String,
+}
+
+// 记录 panic 和后台任务错误，便于排查难以复现的崩溃
+pub struct CrashReporter {
+    app: AppHandle,
+    dir: PathBuf,
+    config: RwLock<CrashConfig>,
+    store: Arc<Store<Wry>>,
+}
+
+impl CrashReporter {
+    // 安装 panic 钩子并启动错误写入线程，报告保存在 dir 下
+    pub fn install(app: &AppHandle, dir: PathBuf) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: CrashConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let _ = APP_VERSION.set(app.package_info().version.to_string());
+
+        let hook_dir = dir.clone();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            write_report(&hook_dir, &panic_report(info));
+            previous(info);
+        }));
+
+        let (sender, receiver) = mpsc::channel::<CrashReport>();
+        let writer_dir = dir.clone();
+        std::thread::spawn(move || {
+            for report in receiver {
+                write_report(&writer_dir, &report);
+            }
+        });
+        let _ = ERRORS.set(sender);
+
+        let reporter = CrashReporter {
+            app: app.clone(),
+            dir,
+            config: RwLock::new(config),
+            store,
+        };
+        if reporter.get_config().upload_enabled {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Some(reporter) = app.try_state::<CrashReporter>() {
+                    if let Err(err) = reporter.upload_pending().await {
+                        log::warn!("上传崩溃报告失败: {}", err);
+                    }
+                }
+            });
+        }
+        Ok(reporter)
+    }
+
+    pub fn get_config(&self) -> CrashConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: CrashConfig) -> AppResult<CrashConfig> {
+        let config = CrashConfig {
+            upload_url: config.upload_url.trim().to_string(),
+            ..config
+        };
+        if config.upload_enabled
+            && !config.upload_url.starts_with("https://")
+            && !config.upload_url.starts_with("http://")
+        {
+            return Err(AppError::InvalidConfig(
+                "上传地址必须以 http:// 或 https:// 开头".to_string(),
+            ));
+        }
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存崩溃报告设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化崩溃报告设置失败: {}", err),
+        }
+        *self.config.write().unwrap() = config.clone();
+        Ok(config)
+    }
+
+    // 所有崩溃报告，最新的在前
+    pub fn list(&self) -> Vec<CrashReport> {
+        read_reports(&self.dir)
+    }
+
+    pub fn clear(&self) -> AppResult<()> {
+        if self.dir.is_dir() {
+            std::fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+
+    // 上传所有未上传的报告，返回上传的数量
+    pub async fn upload_pending(&self) -> AppResult<usize> {
+        let config = self.get_config();
+        if config.upload_url.is_empty() {
+            return Err(AppError::InvalidConfig("未设置上传地址".to_string()));
+        }
+        let token = self
+            .app
+            .try_state::<Relay>()
+            .map(|relay| relay.get_config().token)
+            .unwrap_or_default();
+        let http = crate::proxy::client_builder()
+            .timeout(UPLOAD_TIMEOUT)
+            .build()?;
+
+        let mut uploaded = 0;
+        for mut report in self
+            .list()
+            .into_iter()
+            .filter(|report| report.uploaded_at.is_none())
+        {
+            let mut request = http.post(&config.upload_url).json(&report);
+            if !token.is_empty() {
+                request = request.bearer_auth(&token);
+            }
+            request.send().await?.error_for_status()?;
+            report.uploaded_at = Some(chrono::Utc::now().timestamp_millis());
+            write_report(&self.dir, &report);
+            uploaded += 1;
+        }
+        Ok(uploaded)
+    }
+}
+
+// 后台任务遇到无法恢复的错误时调用，报告由写入线程保存
+pub fn report_error(source: &str, message: impl ToString) {
+    let report = CrashReport::new(
+        CrashKind::Error,
+        Some(source.to_string()),
+        message.to_string(),
+    );
+    match ERRORS.get() {
+        Some(sender) => {
+            let _ = sender.send(report);
+        }
+        None => eprintln!("{}: {}", source, report.message),
+    }
+}
+
+// 包装后台任务，任务 panic 时报告任务名称，panic 的详细信息由钩子保存
+pub fn guard<F>(source: String, future: F) -> impl Future<Output = ()> + Send
+where
+    F: Future<Output = ()> + Send,
+{
+    AssertUnwindSafe(future).catch_unwind().map(move |result| {
+        if result.is_err() {
+            report_error(&source, "后台任务因 panic 退出");
+        }
+    })
+}
+
+fn panic_report(info: &PanicHookInfo) -> CrashReport {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "未知错误".to_string());
+    let thread = std::thread::current();
+    let mut report = CrashReport::new(CrashKind::Panic, thread.name().map(str::to_string), message);
+    report.location = info.location().map(|location| {
+        format!(
+            "{}:{}:{}",
+            location.file(),
+            location.line(),
+            location.column()
+        )
+    });
+    report.backtrace = Some(Backtrace::force_capture().to_string());
+    report
+}
+
+// 每个报告一个 JSON 文件，写入失败时只能输出到控制台
+fn write_report(dir: &Path, report: &CrashReport) {
+    let result = std::fs::create_dir_all(dir).and_then(|_| {
+        let json = serde_json::to_vec_pretty(report).unwrap_or_default();
+        std::fs::write(dir.join(format!("{}.json", report.id)), json)
+    });
+    if let Err(err) = result {
+        eprintln!("保存崩溃报告失败: {}, 报告内容: {:?}", err, report);
+        return;
+    }
+    prune(dir);
+}
+
+fn read_reports(dir: &Path) -> Vec<CrashReport> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<CrashReport> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| std::fs::read(entry.path()).ok())
+        .filter_map(|data| serde_json::from_slice(&data).ok())
+        .collect();
+    reports.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    reports
+}
+
+// 删除超出数量的旧报告
+fn prune(dir: &Path) {
+    for report in read_reports(dir).into_iter().skip(MAX_REPORTS) {
+        let _ = std::fs::remove_file(dir.join(format!("{}.json", report.id)));
+    }
+}
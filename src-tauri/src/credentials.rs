@@ -0,0 +1,600 @@
+use crate::bilibili::{self, cookie_value};
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_http::reqwest::Client;
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_store::{Store, StoreExt};
+
+mod cipher;
+mod qr_login;
+mod refresh;
+
+pub use qr_login::QrLogin;
+use qr_login::{PollResult, QrLoginState, QrLoginStatus};
+
+// 保存加密凭据的文件，位于应用数据目录
+const STORE_FILE: &str = "credentials.json";
+const CREDENTIALS_KEY: &str = "credentials";
+
+// 扫码登录状态变化时发送给前端的事件
+pub const QR_LOGIN_EVENT: &str = "credentials://qr-login";
+
+// 账号需要重新登录时发送给前端的事件
+pub const RELOGIN_EVENT: &str = "credentials://relogin-required";
+
+// 启动后等待一段时间再检查账号，避免与启动时的其他请求挤在一起
+const MONITOR_DELAY: Duration = Duration::from_secs(60);
+// 定时检查账号状态的间隔
+const MONITOR_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// 查询当前登录用户，用于验证 cookie 是否有效
+const NAV_URL: &str = "https://api.bilibili.com/x/web-interface/nav";
+// 未登录时接口返回的代码
+const NOT_LOGGED_IN: i64 = -101;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    // B 站网页 cookie，用于直连直播间
+    BilibiliCookie,
+    // 开放平台身份码
+    OpenLiveCode,
+}
+
+// 前端添加凭据时提交的内容
+#[derive(Debug, Clone, Deserialize)]
+pub struct CredentialInput {
+    pub kind: CredentialKind,
+    #[serde(default)]
+    pub label: String,
+    pub secret: String,
+}
+
+// 保存在磁盘上的凭据，secret 只以密文形式保存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredCredential {
+    id: String,
+    kind: CredentialKind,
+    label: String,
+    // 脱敏后的内容，列表中展示时无需解密
+    masked: String,
+    uid: Option<u64>,
+    uname: Option<String>,
+    created_at: i64,
+    validated_at: Option<i64>,
+    secret: cipher::Sealed,
+    // 扫码登录时获得，刷新 cookie 时需要
+    #[serde(default)]
+    refresh_token: Option<cipher::Sealed>,
+}
+
+// 返回给前端的凭据信息，不包含明文
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialInfo {
+    pub id: String,
+    pub kind: CredentialKind,
+    pub label: String,
+    pub masked: String,
+    pub uid: Option<u64>,
+    pub uname: Option<String>,
+    pub created_at: i64,
+    pub validated_at: Option<i64>,
+}
+
+impl From<&StoredCredential> for CredentialInfo {
+    fn from(credential: &StoredCredential) -> Self {
+        CredentialInfo {
+            id: credential.id.clone(),
+            kind: credential.kind,
+            label: credential.label.clone(),
+            masked: credential.masked.clone(),
+            uid: credential.uid,
+            uname: credential.uname.clone(),
+            created_at: credential.created_at,
+            validated_at: credential.validated_at,
+        }
+    }
+}
+
+// 验证结果
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialValidation {
+    pub valid: bool,
+    pub uid: Option<u64>,
+    pub uname: Option<String>,
+    // 无效时的原因
+    pub message: Option<String>,
+}
+
+// 需要重新登录的账号
+#[derive(Debug, Clone, Serialize)]
+struct ReloginRequired {
+    id: String,
+    uid: Option<u64>,
+    uname: Option<String>,
+    reason: String,
+}
+
+// 管理 B 站 cookie 和开放平台身份码，加密保存在本地
+pub struct CredentialManager {
+    app: AppHandle,
+    http: Client,
+    store: Arc<Store<Wry>>,
+    credentials: Mutex<Vec<StoredCredential>>,
+    // 首次使用时才读取钥匙串，避免钥匙串不可用时影响应用启动
+    cipher: Mutex<Option<Arc<cipher::Cipher>>>,
+    // 正在进行的扫码登录
+    qr_task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl CredentialManager {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let credentials = store
+            .get(CREDENTIALS_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Ok(CredentialManager {
+            app: app.clone(),
+            http: bilibili::client(),
+            store,
+            credentials: Mutex::new(credentials),
+            cipher: Mutex::new(None),
+            qr_task: Mutex::new(None),
+        })
+    }
+
+    pub fn list(&self) -> Vec<CredentialInfo> {
+        self.credentials
+            .lock()
+            .unwrap()
+            .iter()
+            .map(CredentialInfo::from)
+            .collect()
+    }
+
+    // 加密保存新的凭据
+    pub fn add(&self, input: CredentialInput) -> AppResult<CredentialInfo> {
+        let secret = input.secret.trim();
+        check_format(input.kind, secret)?;
+        self.insert(input.kind, input.label.trim(), secret, None)
+    }
+
+    // 保存凭据，同一个 B 站账号重复添加时替换旧的凭据并保留备注
+    fn insert(
+        &self,
+        kind: CredentialKind,
+        label: &str,
+        secret: &str,
+        refresh_token: Option<&str>,
+    ) -> AppResult<CredentialInfo> {
+        let cipher = self.cipher()?;
+        let uid = match kind {
+            CredentialKind::BilibiliCookie => {
+                cookie_value(secret, "DedeUserID").and_then(|uid| uid.parse().ok())
+            }
+            CredentialKind::OpenLiveCode => None,
+        };
+        let mut credential = StoredCredential {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            label: label.to_string(),
+            masked: mask(kind, secret),
+            uid,
+            uname: None,
+            created_at: chrono::Utc::now().timestamp_millis(),
+            validated_at: None,
+            secret: cipher.encrypt(secret)?,
+            refresh_token: refresh_token
+                .filter(|token| !token.is_empty())
+                .map(|token| cipher.encrypt(token))
+                .transpose()?,
+        };
+
+        let info = {
+            let mut credentials = self.credentials.lock().unwrap();
+            let existing = credentials.iter().position(|existing| {
+                kind == CredentialKind::BilibiliCookie
+                    && existing.kind == kind
+                    && uid.is_some()
+                    && existing.uid == uid
+            });
+            if let Some(index) = existing {
+                let existing = credentials.remove(index);
+                credential.id = existing.id;
+                credential.uname = existing.uname;
+                if credential.label.is_empty() {
+                    credential.label = existing.label;
+                }
+            }
+            let info = CredentialInfo::from(&credential);
+            credentials.push(credential);
+            info
+        };
+        self.save();
+        Ok(info)
+    }
+
+    pub fn remove(&self, id: &str) -> AppResult<()> {
+        {
+            let mut credentials = self.credentials.lock().unwrap();
+            let len = credentials.len();
+            credentials.retain(|credential| credential.id != id);
+            if credentials.len() == len {
+                return Err(AppError::CredentialNotFound(id.to_string()));
+            }
+        }
+        self.save();
+        Ok(())
+    }
+
+    // 检查凭据是否仍然有效，cookie 会请求 B 站接口并更新用户信息
+    pub async fn validate(&self, id: &str) -> AppResult<CredentialValidation> {
+        let (kind, secret) = self.secret(id)?;
+        let validation = match kind {
+            CredentialKind::BilibiliCookie => self.validate_cookie(&secret).await?,
+            // 身份码只能由 vtsuru 服务端调用开放平台验证，这里只检查格式
+            CredentialKind::OpenLiveCode => CredentialValidation {
+                valid: check_format(kind, &secret).is_ok(),
+                uid: None,
+                uname: None,
+                message: None,
+            },
+        };
+
+        if validation.valid {
+            let mut credentials = self.credentials.lock().unwrap();
+            if let Some(credential) = credentials
+                .iter_mut()
+                .find(|credential| credential.id == id)
+            {
+                credential.validated_at = Some(chrono::Utc::now().timestamp_millis());
+                if validation.uid.is_some() {
+                    credential.uid = validation.uid;
+                    credential.uname = validation.uname.clone();
+                }
+            }
+            drop(credentials);
+            self.save();
+        }
+        Ok(validation)
+    }
+
+    // 在后台定时检查所有 B 站账号，需要时刷新 cookie
+    pub fn start_monitor(&self) {
+        tauri::async_runtime::spawn(monitor(self.app.clone()));
+    }
+
+    // 检查账号是否有效，服务端要求刷新时自动刷新 cookie，无法恢复时提醒用户重新登录
+    async fn check(&self, id: &str) -> AppResult<()> {
+        let (_, cookie) = self.secret(id)?;
+        let validation = self.validate(id).await?;
+        if !validation.valid {
+            self.notify_relogin(id, validation.message.unwrap_or_default());
+            return Ok(());
+        }
+
+        let Some(timestamp) = refresh::needs_refresh(&self.http, &cookie).await? else {
+            return Ok(());
+        };
+        let refresh_token = self.refresh_token(id)?;
+        let Some(refresh_token) = refresh_token else {
+            self.notify_relogin(
+                id,
+                "cookie 即将过期，手动添加的账号无法自动刷新".to_string(),
+            );
+            return Ok(());
+        };
+        match refresh::refresh(&self.http, &cookie, &refresh_token, timestamp).await {
+            Ok((cookie, refresh_token)) => {
+                self.update_secret(id, &cookie, &refresh_token)?;
+                log::info!("已刷新账号 {} 的 cookie", id);
+            }
+            Err(err) => self.notify_relogin(id, format!("刷新 cookie 失败: {}", err)),
+        }
+        Ok(())
+    }
+
+    fn refresh_token(&self, id: &str) -> AppResult<Option<String>> {
+        let sealed = self
+            .credentials
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|credential| credential.id == id)
+            .ok_or_else(|| AppError::CredentialNotFound(id.to_string()))?
+            .refresh_token
+            .clone();
+        sealed
+            .map(|sealed| self.cipher()?.decrypt(&sealed))
+            .transpose()
+    }
+
+    // 保存刷新后的 cookie 和 refresh_token
+    fn update_secret(&self, id: &str, cookie: &str, refresh_token: &str) -> AppResult<()> {
+        let cipher = self.cipher()?;
+        let secret = cipher.encrypt(cookie)?;
+        let refresh_token = cipher.encrypt(refresh_token)?;
+        {
+            let mut credentials = self.credentials.lock().unwrap();
+            let credential = credentials
+                .iter_mut()
+                .find(|credential| credential.id == id)
+                .ok_or_else(|| AppError::CredentialNotFound(id.to_string()))?;
+            credential.secret = secret;
+            credential.refresh_token = Some(refresh_token);
+            credential.masked = mask(CredentialKind::BilibiliCookie, cookie);
+        }
+        self.save();
+        Ok(())
+    }
+
+    // 通知前端并发送系统通知
+    fn notify_relogin(&self, id: &str, reason: String) {
+        let Some(info) = self
+            .list()
+            .into_iter()
+            .find(|credential| credential.id == id)
+        else {
+            return;
+        };
+        let name = info
+            .uname
+            .clone()
+            .or_else(|| (!info.label.is_empty()).then(|| info.label.clone()))
+            .unwrap_or_else(|| info.masked.clone());
+        log::warn!("账号 {} 需要重新登录: {}", name, reason);
+        let _ = self.app.emit(
+            RELOGIN_EVENT,
+            &ReloginRequired {
+                id: info.id,
+                uid: info.uid,
+                uname: info.uname,
+                reason: reason.clone(),
+            },
+        );
+        if let Err(err) = self
+            .app
+            .notification()
+            .builder()
+            .title("B 站账号需要重新登录")
+            .body(format!("{}: {}", name, reason))
+            .show()
+        {
+            log::warn!("发送系统通知失败: {}", err);
+        }
+    }
+
+    // 第一个保存的 B 站 cookie 账号
+    pub fn default_cookie_id(&self) -> Option<String> {
+        self.credentials
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|credential| credential.kind == CredentialKind::BilibiliCookie)
+            .map(|credential| credential.id.clone())
+    }
+
+    // 读取 B 站 cookie 的明文，仅供后端连接直播间和发送弹幕时使用
+    pub fn cookie(&self, id: &str) -> AppResult<String> {
+        match self.secret(id)? {
+            (CredentialKind::BilibiliCookie, cookie) => Ok(cookie),
+            _ => Err(AppError::InvalidConfig(
+                "该账号不是 B 站 cookie".to_string(),
+            )),
+        }
+    }
+
+    fn secret(&self, id: &str) -> AppResult<(CredentialKind, String)> {
+        let (kind, secret) = self
+            .credentials
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|credential| credential.id == id)
+            .map(|credential| (credential.kind, credential.secret.clone()))
+            .ok_or_else(|| AppError::CredentialNotFound(id.to_string()))?;
+        Ok((kind, self.cipher()?.decrypt(&secret)?))
+    }
+
+    // 申请登录二维码，并在后台轮询扫码状态，已有的扫码登录会被取消
+    pub async fn start_qr_login(&self) -> AppResult<QrLogin> {
+        let login = qr_login::generate(&self.http).await?;
+        let task = tauri::async_runtime::spawn(run_qr_login(
+            self.app.clone(),
+            self.http.clone(),
+            login.qrcode_key.clone(),
+        ));
+        if let Some(old) = self.qr_task.lock().unwrap().replace(task) {
+            old.abort();
+        }
+        Ok(login)
+    }
+
+    pub fn cancel_qr_login(&self) {
+        if let Some(task) = self.qr_task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    // 保存扫码登录得到的 cookie，并读取用户名
+    async fn add_login(&self, cookie: &str, refresh_token: &str) -> AppResult<CredentialInfo> {
+        let info = self.insert(
+            CredentialKind::BilibiliCookie,
+            "",
+            cookie,
+            Some(refresh_token),
+        )?;
+        match self.validate(&info.id).await {
+            Ok(_) => Ok(self
+                .list()
+                .into_iter()
+                .find(|credential| credential.id == info.id)
+                .unwrap_or(info)),
+            Err(err) => {
+                log::warn!("读取登录账号信息失败: {}", err);
+                Ok(info)
+            }
+        }
+    }
+
+    async fn validate_cookie(&self, cookie: &str) -> AppResult<CredentialValidation> {
+        match bilibili::get_api(&self.http, NAV_URL, Some(cookie)).await {
+            Ok(data) if data["isLogin"].as_bool() == Some(true) => Ok(CredentialValidation {
+                valid: true,
+                uid: data["mid"].as_u64(),
+                uname: data["uname"].as_str().map(str::to_string),
+                message: None,
+            }),
+            Ok(_)
+            | Err(AppError::BilibiliApi {
+                code: NOT_LOGGED_IN,
+                ..
+            }) => Ok(CredentialValidation {
+                valid: false,
+                uid: None,
+                uname: None,
+                message: Some("cookie 已失效，请重新登录".to_string()),
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn cipher(&self) -> AppResult<Arc<cipher::Cipher>> {
+        let mut cipher = self.cipher.lock().unwrap();
+        if let Some(cipher) = cipher.as_ref() {
+            return Ok(cipher.clone());
+        }
+        let loaded = Arc::new(cipher::Cipher::load()?);
+        *cipher = Some(loaded.clone());
+        Ok(loaded)
+    }
+
+    fn save(&self) {
+        let value = match serde_json::to_value(&*self.credentials.lock().unwrap()) {
+            Ok(value) => value,
+            Err(err) => {
+                log::warn!("序列化账号凭据失败: {}", err);
+                return;
+            }
+        };
+        self.store.set(CREDENTIALS_KEY, value);
+        if let Err(err) = self.store.save() {
+            log::warn!("保存账号凭据失败: {}", err);
+        }
+    }
+}
+
+fn check_format(kind: CredentialKind, secret: &str) -> AppResult<()> {
+    match kind {
+        CredentialKind::BilibiliCookie if cookie_value(secret, "SESSDATA").is_none() => Err(
+            AppError::InvalidConfig("cookie 中缺少 SESSDATA".to_string()),
+        ),
+        CredentialKind::OpenLiveCode
+            if secret.is_empty() || !secret.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            Err(AppError::InvalidConfig("身份码格式无效".to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+// 列表中展示的脱敏内容，cookie 只展示 SESSDATA 的首尾几位
+fn mask(kind: CredentialKind, secret: &str) -> String {
+    let value = match kind {
+        CredentialKind::BilibiliCookie => cookie_value(secret, "SESSDATA").unwrap_or(secret),
+        CredentialKind::OpenLiveCode => secret,
+    };
+    let chars: Vec<char> = value.chars().collect();
+    let masked = if chars.len() <= 8 {
+        "****".to_string()
+    } else {
+        format!(
+            "{}****{}",
+            chars[..4].iter().collect::<String>(),
+            chars[chars.len() - 4..].iter().collect::<String>()
+        )
+    };
+    match kind {
+        CredentialKind::BilibiliCookie => format!("SESSDATA={}", masked),
+        CredentialKind::OpenLiveCode => masked,
+    }
+}
+
+// 轮询扫码状态直到登录成功、二维码失效或超时，状态变化时通知前端
+async fn run_qr_login(app: AppHandle, http: Client, qrcode_key: String) {
+    let deadline = tokio::time::Instant::now() + qr_login::POLL_TIMEOUT;
+    let mut last_state = None;
+    let emit =
+        |state: QrLoginState, message: Option<String>, credential: Option<CredentialInfo>| {
+            let _ = app.emit(
+                QR_LOGIN_EVENT,
+                &QrLoginStatus {
+                    qrcode_key: qrcode_key.clone(),
+                    state,
+                    message,
+                    credential,
+                },
+            );
+        };
+
+    loop {
+        tokio::time::sleep(qr_login::POLL_INTERVAL).await;
+        if tokio::time::Instant::now() >= deadline {
+            emit(QrLoginState::Expired, None, None);
+            return;
+        }
+
+        match qr_login::poll(&http, &qrcode_key).await {
+            Ok(PollResult::Pending(state)) => {
+                if last_state != Some(state) {
+                    last_state = Some(state);
+                    emit(state, None, None);
+                }
+            }
+            Ok(PollResult::Expired) => {
+                emit(QrLoginState::Expired, None, None);
+                return;
+            }
+            Ok(PollResult::Success {
+                cookie,
+                refresh_token,
+            }) => {
+                let credentials = app.state::<CredentialManager>();
+                match credentials.add_login(&cookie, &refresh_token).await {
+                    Ok(info) => emit(QrLoginState::Success, None, Some(info)),
+                    Err(err) => emit(QrLoginState::Failed, Some(err.to_string()), None),
+                }
+                return;
+            }
+            // 网络波动时继续轮询，直到超时
+            Err(err) => log::warn!("查询扫码状态失败: {}", err),
+        }
+    }
+}
+
+// 定时检查所有 B 站账号
+async fn monitor(app: AppHandle) {
+    tokio::time::sleep(MONITOR_DELAY).await;
+    let mut ticker = tokio::time::interval(MONITOR_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let credentials = app.state::<CredentialManager>();
+        let ids: Vec<String> = credentials
+            .list()
+            .into_iter()
+            .filter(|credential| credential.kind == CredentialKind::BilibiliCookie)
+            .map(|credential| credential.id)
+            .collect();
+        for id in ids {
+            // 网络错误时等下一轮再检查，不提醒用户
+            if let Err(err) = credentials.check(&id).await {
+                log::warn!("检查账号 {} 失败: {}", id, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error::{AppError, AppResult};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+// 主密钥在系统钥匙串中的位置
+const KEYRING_SERVICE: &str = "vtsuru-fetcher-client";
+const KEYRING_USER: &str = "credential-key";
+
+// 加密后的内容，均为 base64 编码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+// 使用保存在系统钥匙串（Windows 凭据管理器、macOS 钥匙串、Secret Service）中的主密钥
+// 对凭据进行 AES-256-GCM 加密，磁盘上只保存密文
+pub(super) struct Cipher {
+    cipher: Aes256Gcm,
+}
+
+impl Cipher {
+    // 读取主密钥，首次使用时生成并写入钥匙串
+    pub fn load() -> AppResult<Self> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(keyring_error)?;
+        let key = match entry.get_password() {
+            Ok(encoded) => BASE64
+                .decode(encoded)
+                .ok()
+                .filter(|key| key.len() == 32)
+                .ok_or_else(|| AppError::Credential("钥匙串中的密钥已损坏".to_string()))?,
+            Err(keyring::Error::NoEntry) => {
+                let key = Aes256Gcm::generate_key(OsRng);
+                entry
+                    .set_password(&BASE64.encode(key))
+                    .map_err(keyring_error)?;
+                key.to_vec()
+            }
+            Err(err) => return Err(keyring_error(err)),
+        };
+        Ok(Cipher {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> AppResult<Sealed> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| AppError::Credential("加密失败".to_string()))?;
+        Ok(Sealed {
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, sealed: &Sealed) -> AppResult<String> {
+        let invalid = || AppError::Credential("无法解密已保存的凭据".to_string());
+        let nonce = BASE64.decode(&sealed.nonce).map_err(|_| invalid())?;
+        let ciphertext = BASE64.decode(&sealed.ciphertext).map_err(|_| invalid())?;
+        if nonce.len() != 12 {
+            return Err(invalid());
+        }
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| invalid())?;
+        String::from_utf8(plaintext).map_err(|_| invalid())
+    }
+}
+
+fn keyring_error(err: keyring::Error) -> AppError {
+    AppError::Credential(format!("无法访问系统钥匙串: {}", err))
+}
@@ -0,0 +1,133 @@
+use super::CredentialInfo;
+use crate::bilibili;
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tauri_plugin_http::reqwest::header::SET_COOKIE;
+use tauri_plugin_http::reqwest::Client;
+
+// 申请登录二维码
+const GENERATE_URL: &str = "https://passport.bilibili.com/x/passport-login/web/qrcode/generate";
+// 查询扫码状态
+const POLL_URL: &str = "https://passport.bilibili.com/x/passport-login/web/qrcode/poll";
+// 获取 buvid3，直连直播间时需要
+const SPI_URL: &str = "https://api.bilibili.com/x/frontend/finger/spi";
+
+// 轮询扫码状态的间隔
+pub(super) const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// 二维码 180 秒后失效，多等一会儿以便收到失效状态
+pub(super) const POLL_TIMEOUT: Duration = Duration::from_secs(200);
+
+// 扫码状态码
+const CODE_SUCCESS: i64 = 0;
+const CODE_EXPIRED: i64 = 86038;
+const CODE_SCANNED: i64 = 86090;
+const CODE_WAITING: i64 = 86101;
+
+// 返回给前端用于生成二维码的信息
+#[derive(Debug, Clone, Serialize)]
+pub struct QrLogin {
+    pub url: String,
+    pub qrcode_key: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QrLoginState {
+    // 等待扫码
+    Waiting,
+    // 已扫码，等待在手机上确认
+    Scanned,
+    Expired,
+    Success,
+    Failed,
+}
+
+// 扫码状态变化时发送给前端的内容
+#[derive(Debug, Clone, Serialize)]
+pub struct QrLoginStatus {
+    pub qrcode_key: String,
+    pub state: QrLoginState,
+    pub message: Option<String>,
+    // 登录成功后保存的账号
+    pub credential: Option<CredentialInfo>,
+}
+
+// 一次轮询的结果
+pub(super) enum PollResult {
+    Pending(QrLoginState),
+    Expired,
+    Success {
+        cookie: String,
+        refresh_token: String,
+    },
+}
+
+pub(super) async fn generate(http: &Client) -> AppResult<QrLogin> {
+    let data = bilibili::get_api(http, GENERATE_URL, None).await?;
+    match (data["url"].as_str(), data["qrcode_key"].as_str()) {
+        (Some(url), Some(key)) => Ok(QrLogin {
+            url: url.to_string(),
+            qrcode_key: key.to_string(),
+        }),
+        _ => Err(AppError::BilibiliApi {
+            code: -1,
+            message: "未返回登录二维码".to_string(),
+        }),
+    }
+}
+
+// 查询扫码状态，登录成功时从响应头中收集 cookie
+pub(super) async fn poll(http: &Client, qrcode_key: &str) -> AppResult<PollResult> {
+    let response = bilibili::send(http.get(POLL_URL).query(&[("qrcode_key", qrcode_key)])).await?;
+    let mut cookies: Vec<String> = response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next())
+        .map(|pair| pair.trim().to_string())
+        .collect();
+    let body: Value = response.json().await?;
+    if body["code"].as_i64() != Some(0) {
+        return Err(AppError::BilibiliApi {
+            code: body["code"].as_i64().unwrap_or(-1),
+            message: body["message"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+
+    let data = &body["data"];
+    match data["code"].as_i64().unwrap_or(-1) {
+        CODE_SUCCESS => {
+            if let Some(buvid3) = fetch_buvid3(http).await {
+                cookies.push(format!("buvid3={}", buvid3));
+            }
+            Ok(PollResult::Success {
+                cookie: cookies.join("; "),
+                refresh_token: data["refresh_token"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        }
+        CODE_SCANNED => Ok(PollResult::Pending(QrLoginState::Scanned)),
+        CODE_WAITING => Ok(PollResult::Pending(QrLoginState::Waiting)),
+        CODE_EXPIRED => Ok(PollResult::Expired),
+        code => Err(AppError::BilibiliApi {
+            code,
+            message: data["message"].as_str().unwrap_or_default().to_string(),
+        }),
+    }
+}
+
+// 获取失败时忽略，不影响登录
+async fn fetch_buvid3(http: &Client) -> Option<String> {
+    match bilibili::get_api(http, SPI_URL, None).await {
+        Ok(data) => data["b_3"].as_str().map(str::to_string),
+        Err(err) => {
+            log::warn!("获取 buvid3 失败: {}", err);
+            None
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::bilibili::{self, cookie_value};
+use crate::error::{AppError, AppResult};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Oaep, RsaPublicKey};
+use serde_json::Value;
+use std::fmt::Write;
+use tauri_plugin_http::reqwest::header::SET_COOKIE;
+use tauri_plugin_http::reqwest::{Client, Response};
+
+// 检查是否需要刷新 cookie
+const COOKIE_INFO_URL: &str = "https://passport.bilibili.com/x/passport-login/web/cookie/info";
+// 获取 refresh_csrf 的页面，路径为加密后的时间戳
+const CORRESPOND_URL: &str = "https://www.bilibili.com/correspond/1";
+const REFRESH_URL: &str = "https://passport.bilibili.com/x/passport-login/web/cookie/refresh";
+// 刷新后确认，使旧的 refresh_token 失效
+const CONFIRM_URL: &str = "https://passport.bilibili.com/x/passport-login/web/confirm/refresh";
+
+// 生成 correspondPath 使用的公钥
+const PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDLgd2OAkcGVtoE3ThUREbio0Eg
+Uc/prcajMKXvkCKFCWhJYJcLkcM2DKKcSeFpD/j6Boy538YXnR6VhcuUJOhH2x71
+nzPjfdTcqMz7djHum0qSZA0AyCBDABUqCrfNgCiJ00Ra7GmRj+YCK1NJEuewlb40
+JNrRuoEUXpabUzGB8QIDAQAB
+-----END PUBLIC KEY-----";
+
+// 查询 cookie 是否需要刷新，需要时返回服务端给出的时间戳
+pub(super) async fn needs_refresh(http: &Client, cookie: &str) -> AppResult<Option<i64>> {
+    let csrf = cookie_value(cookie, "bili_jct").unwrap_or_default();
+    let url = format!("{}?csrf={}", COOKIE_INFO_URL, csrf);
+    let data = bilibili::get_api(http, &url, Some(cookie)).await?;
+    if data["refresh"].as_bool() == Some(true) {
+        Ok(Some(
+            data["timestamp"]
+                .as_i64()
+                .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+        ))
+    } else {
+        Ok(None)
+    }
+}
+
+// 执行 cookie 刷新流程，返回新的 cookie 和 refresh_token
+pub(super) async fn refresh(
+    http: &Client,
+    cookie: &str,
+    refresh_token: &str,
+    timestamp: i64,
+) -> AppResult<(String, String)> {
+    let refresh_csrf = fetch_refresh_csrf(http, cookie, timestamp).await?;
+    let csrf = cookie_value(cookie, "bili_jct").unwrap_or_default();
+
+    let response = bilibili::send(http.post(REFRESH_URL).header("Cookie", cookie).form(&[
+        ("csrf", csrf),
+        ("refresh_csrf", refresh_csrf.as_str()),
+        ("source", "main_web"),
+        ("refresh_token", refresh_token),
+    ]))
+    .await?;
+    let new_cookie = merge_cookies(cookie, &response);
+    let data = check_response(response).await?;
+    let new_refresh_token = data["refresh_token"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    // 确认失败不影响新 cookie 的使用，旧的 refresh_token 会在一段时间后自动失效
+    let new_csrf = cookie_value(&new_cookie, "bili_jct").unwrap_or_default();
+    let confirm = bilibili::send(
+        http.post(CONFIRM_URL)
+            .header("Cookie", &new_cookie)
+            .form(&[("csrf", new_csrf), ("refresh_token", refresh_token)]),
+    )
+    .await;
+    match confirm {
+        Ok(response) => {
+            if let Err(err) = check_response(response).await {
+                log::warn!("确认刷新 cookie 失败: {}", err);
+            }
+        }
+        Err(err) => log::warn!("确认刷新 cookie 失败: {}", err),
+    }
+    Ok((new_cookie, new_refresh_token))
+}
+
+// 请求 correspond 页面，从中读取 refresh_csrf
+async fn fetch_refresh_csrf(http: &Client, cookie: &str, timestamp: i64) -> AppResult<String> {
+    let url = format!("{}/{}", CORRESPOND_URL, correspond_path(timestamp)?);
+    let html = bilibili::send(http.get(url).header("Cookie", cookie))
+        .await?
+        .text()
+        .await?;
+    let start = html
+        .find("<div id=\"1-name\">")
+        .map(|index| index + "<div id=\"1-name\">".len());
+    start
+        .and_then(|start| {
+            let end = html[start..].find("</div>")?;
+            Some(html[start..start + end].trim().to_string())
+        })
+        .filter(|csrf| !csrf.is_empty())
+        .ok_or_else(|| AppError::Credential("无法获取 refresh_csrf".to_string()))
+}
+
+// 使用 RSA-OAEP 加密 "refresh_{timestamp}"，结果以十六进制表示
+fn correspond_path(timestamp: i64) -> AppResult<String> {
+    let key = RsaPublicKey::from_public_key_pem(PUBLIC_KEY)
+        .map_err(|err| AppError::Credential(err.to_string()))?;
+    let encrypted = key
+        .encrypt(
+            &mut rand::thread_rng(),
+            Oaep::new::<sha2::Sha256>(),
+            format!("refresh_{}", timestamp).as_bytes(),
+        )
+        .map_err(|err| AppError::Credential(err.to_string()))?;
+    let mut path = String::with_capacity(encrypted.len() * 2);
+    for byte in encrypted {
+        let _ = write!(path, "{:02x}", byte);
+    }
+    Ok(path)
+}
+
+// 用响应中新设置的 cookie 替换旧值，其余字段（例如 buvid3）保持不变
+fn merge_cookies(cookie: &str, response: &Response) -> String {
+    let mut pairs: Vec<(String, String)> = cookie
+        .split(';')
+        .filter_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect();
+    let updates = response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next()?.trim().split_once('='));
+    for (key, value) in updates {
+        match pairs.iter_mut().find(|(existing, _)| existing == key) {
+            Some(pair) => pair.1 = value.to_string(),
+            None => pairs.push((key.to_string(), value.to_string())),
+        }
+    }
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+async fn check_response(response: Response) -> AppResult<Value> {
+    let body: Value = response.json().await?;
+    let code = body["code"].as_i64().unwrap_or(-1);
+    if code != 0 {
+        return Err(AppError::BilibiliApi {
+            code,
+            message: body["message"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+    Ok(body["data"].clone())
+}
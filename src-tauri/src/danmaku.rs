@@ -0,0 +1,884 @@
+use crate::error::{AppError, AppResult};
+use crate::event_bus::{self, DropPolicy, EventBus, EventBusMetrics, Subscriber};
+use crate::plugin::PluginHost;
+use crate::user_info::UserInfoCache;
+use crate::watchdog::Heartbeat;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_http::reqwest::Client;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+mod direct;
+mod filter;
+mod moderation;
+mod open_live;
+mod packet;
+mod send;
+
+pub use filter::{FilterRule, FilterRuleStatus};
+pub use moderation::{FlaggedEvent, ModerationRule, ModerationRuleStatus, ReviewStatus};
+pub use send::{resolve_cookie, DanmakuSender, DEFAULT_MAX_LENGTH};
+
+// 收到直播间事件时发送给前端的事件
+pub const DANMAKU_EVENT: &str = "danmaku://event";
+// 直播间连接状态变化时发送给前端的事件
+pub const STATUS_EVENT: &str = "danmaku://status";
+// 定时发送所有直播间状态的事件
+pub const ROOMS_EVENT: &str = "danmaku://rooms";
+// 直播间连接状态切换时发送给前端的事件，包含重连进度
+pub const CONNECTION_STATE_EVENT: &str = "connection-state-changed";
+
+// 等待认证回复的最长时间
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+// 定时发送所有直播间状态的间隔
+const ROOMS_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+// 连续多少次心跳没有收到回复时认为连接已失效
+const MAX_MISSED_HEARTBEATS: u32 = 3;
+// 重连的等待时间，每次失败翻倍直到上限，并加上随机抖动避免多个直播间同时重连
+const RECONNECT_BASE: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+// 连接状态广播通道容量，订阅者落后太多时会丢弃旧状态
+const STATE_CHANNEL_CAPACITY: usize = 64;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// 弹幕来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DanmakuSource {
+    // 直连直播间，未提供 cookie 时以游客身份连接
+    Direct {
+        room_id: u64,
+        cookie: Option<String>,
+        // 使用已保存的账号，由命令层解密后填入 cookie
+        credential_id: Option<String>,
+    },
+    // 开放平台长连，连接信息由 vtsuru 使用身份码获取
+    OpenLive {
+        auth_body: String,
+        wss_links: Vec<String>,
+    },
+}
+
+impl DanmakuSource {
+    fn kind(&self) -> SourceKind {
+        match self {
+            DanmakuSource::Direct { .. } => SourceKind::Direct,
+            DanmakuSource::OpenLive { .. } => SourceKind::OpenLive,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Direct,
+    OpenLive,
+}
+
+// 事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Danmaku,
+    Gift,
+    SuperChat,
+    Guard,
+    Like,
+    Enter,
+}
+
+impl EventKind {
+    // 与序列化结果一致的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Danmaku => "danmaku",
+            EventKind::Gift => "gift",
+            EventKind::SuperChat => "super_chat",
+            EventKind::Guard => "guard",
+            EventKind::Like => "like",
+            EventKind::Enter => "enter",
+        }
+    }
+}
+
+// 两种来源统一后的直播间事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanmakuEvent {
+    // 客户端生成的 ULID，按生成时间排序，用于本地存储和上传时去重
+    #[serde(default)]
+    pub id: String,
+    pub kind: EventKind,
+    pub room_id: u64,
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    // 开放平台不提供 uid 时为 0
+    pub uid: u64,
+    pub open_id: Option<String>,
+    pub uname: String,
+    pub uface: Option<String>,
+    // 弹幕内容、醒目留言内容、礼物名称或大航海名称
+    pub message: String,
+    // 礼物数量、大航海月数或点赞次数
+    pub num: u32,
+    // 金额，单位为元，免费礼物为 0
+    pub price: f64,
+    pub guard_level: u8,
+    pub fans_medal_level: u32,
+    pub fans_medal_name: String,
+    // 账号等级，只有直连的弹幕携带
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_level: Option<u32>,
+    pub msg_id: Option<String>,
+    // 表情弹幕的图片地址
+    pub emoji: Option<String>,
+    // 回放的历史事件，不会再次保存、上传或计入统计
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub replay: bool,
+    // 模拟生成的测试事件，同样不会保存、上传或计入统计
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub simulated: bool,
+    // 被审核规则静默的事件，仍然保存、上传并显示在应用中，但不发送给覆盖层、朗读和提示音
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub muted: bool,
+}
+
+impl DanmakuEvent {
+    // 区分用户的键，开放平台不提供 uid，使用 open_id 区分用户，两者都没有时返回 None
+    pub fn user_key(&self) -> Option<String> {
+        user_key(self.uid, self.open_id.as_deref())
+    }
+}
+
+// 按保存的 uid 和 open_id 生成与 DanmakuEvent::user_key 一致的键
+pub fn user_key(uid: u64, open_id: Option<&str>) -> Option<String> {
+    match (uid, open_id) {
+        (0, open_id) => open_id.map(str::to_string),
+        (uid, _) => Some(uid.to_string()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Connected,
+    Reconnecting,
+}
+
+// 单个直播间的连接状态
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomStatus {
+    pub room_id: u64,
+    pub source: SourceKind,
+    pub state: ConnectionState,
+    // 最近一次心跳回复中的人气值
+    pub popularity: u64,
+    // 最近一条消息的 Unix 毫秒时间戳
+    pub last_message_at: Option<i64>,
+    // 上一次断开的原因
+    pub error: Option<String>,
+    // 连续重连失败的次数，连接成功后清零
+    pub reconnect_attempts: u32,
+    // 下一次重连的 Unix 毫秒时间戳
+    pub next_retry_at: Option<i64>,
+}
+
+// 连接状态切换事件的内容
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStateChanged {
+    pub room_id: u64,
+    pub previous: ConnectionState,
+    pub state: ConnectionState,
+    pub reconnect_attempts: u32,
+    pub next_retry_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+// 建立连接所需的信息
+struct Endpoint {
+    room_id: u64,
+    // 依次尝试的弹幕服务器地址
+    urls: Vec<String>,
+    auth_body: String,
+    heartbeat_interval: Duration,
+}
+
+// 直播间的连接任务和状态
+struct Room {
+    source: DanmakuSource,
+    status: RoomStatus,
+    task: JoinHandle<()>,
+    // 连接任务的心跳，用于发现卡住的连接
+    heartbeat: Heartbeat,
+}
+
+// 连接任务与管理器共享的状态
+struct Shared {
+    app: AppHandle,
+    http: Client,
+    events: EventBus<DanmakuEvent>,
+    state_changes: broadcast::Sender<ConnectionStateChanged>,
+    rooms: Mutex<HashMap<u64, Room>>,
+    // 暂停获取时断开的直播间，恢复时重新连接
+    stopped: Mutex<Vec<DanmakuSource>>,
+    filter: filter::EventFilter,
+    moderation: moderation::Moderation,
+    plugins: PluginHost,
+    users: UserInfoCache,
+}
+
+impl Shared {
+    // 修改直播间状态并通知前端，直播间已被移除时忽略
+    fn update_status(&self, room_id: u64, update: impl FnOnce(&mut RoomStatus)) {
+        let (previous, status) = {
+            let mut rooms = self.rooms.lock().unwrap();
+            let Some(room) = rooms.get_mut(&room_id) else {
+                return;
+            };
+            let previous = room.status.state;
+            update(&mut room.status);
+            (previous, room.status.clone())
+        };
+        let _ = self.app.emit(STATUS_EVENT, &status);
+        if previous != status.state || status.state == ConnectionState::Reconnecting {
+            let change = ConnectionStateChanged {
+                room_id,
+                previous,
+                state: status.state,
+                reconnect_attempts: status.reconnect_attempts,
+                next_retry_at: status.next_retry_at,
+                error: status.error,
+            };
+            let _ = self.app.emit(CONNECTION_STATE_EVENT, &change);
+            let _ = self.state_changes.send(change);
+        }
+    }
+
+    fn statuses(&self) -> Vec<RoomStatus> {
+        let mut statuses: Vec<RoomStatus> = self
+            .rooms
+            .lock()
+            .unwrap()
+            .values()
+            .map(|room| room.status.clone())
+            .collect();
+        statuses.sort_by_key(|status| status.room_id);
+        statuses
+    }
+
+    // 经过过滤规则、用户信息补全、审核规则和插件处理后，分发事件给前端和其他订阅者
+    fn publish(&self, mut event: DanmakuEvent) {
+        if !self.filter.accept(&event, &self.users) {
+            return;
+        }
+        event.id = ulid::Ulid::new().to_string();
+        // 模拟事件的用户并不存在，不查询用户信息
+        if !event.simulated {
+            self.users.enrich(&mut event);
+        }
+        self.moderation.review(&mut event, &self.users);
+        let Some(event) = self.plugins.process(event) else {
+            return;
+        };
+        let _ = self.app.emit(DANMAKU_EVENT, &event);
+        self.events.publish(event);
+    }
+}
+
+// 管理多个直播间的弹幕连接，每个直播间有独立的连接任务
+pub struct RoomManager {
+    shared: Arc<Shared>,
+}
+
+impl RoomManager {
+    pub fn new(
+        app: AppHandle,
+        plugins: PluginHost,
+        users: UserInfoCache,
+    ) -> tauri_plugin_store::Result<Self> {
+        let (state_changes, _) = broadcast::channel(STATE_CHANNEL_CAPACITY);
+        let filter = filter::EventFilter::load(&app)?;
+        let moderation = moderation::Moderation::load(&app)?;
+        let shared = Arc::new(Shared {
+            app,
+            http: crate::bilibili::client(),
+            events: EventBus::new(),
+            state_changes,
+            rooms: Mutex::new(HashMap::new()),
+            stopped: Mutex::new(Vec::new()),
+            filter,
+            moderation,
+            plugins,
+            users,
+        });
+        tauri::async_runtime::spawn(report_rooms(shared.clone()));
+        Ok(RoomManager { shared })
+    }
+
+    // 添加直播间并开始连接
+    // 获取连接信息失败时直接返回错误，之后的连接过程在后台进行
+    pub async fn add_room(&self, source: DanmakuSource) -> AppResult<RoomStatus> {
+        let kind = source.kind();
+        let endpoint = prepare(&self.shared.http, &source).await?;
+        let room_id = endpoint.room_id;
+
+        let status = {
+            let mut rooms = self.shared.rooms.lock().unwrap();
+            if rooms.contains_key(&room_id) {
+                return Err(AppError::RoomExists(room_id));
+            }
+            let status = RoomStatus {
+                room_id,
+                source: kind,
+                state: ConnectionState::Connecting,
+                popularity: 0,
+                last_message_at: None,
+                error: None,
+                reconnect_attempts: 0,
+                next_retry_at: None,
+            };
+            let heartbeat = Heartbeat::new();
+            let task = spawn_supervise(
+                self.shared.clone(),
+                source.clone(),
+                Some(endpoint),
+                room_id,
+                heartbeat.clone(),
+            );
+            rooms.insert(
+                room_id,
+                Room {
+                    source,
+                    status: status.clone(),
+                    task,
+                    heartbeat,
+                },
+            );
+            status
+        };
+        let _ = self.shared.app.emit(STATUS_EVENT, &status);
+        Ok(status)
+    }
+
+    // 断开并移除直播间
+    pub fn remove_room(&self, room_id: u64) -> AppResult<()> {
+        let room = self
+            .shared
+            .rooms
+            .lock()
+            .unwrap()
+            .remove(&room_id)
+            .ok_or(AppError::RoomNotFound(room_id))?;
+        room.task.abort();
+        let _ = self.shared.app.emit(
+            STATUS_EVENT,
+            &RoomStatus {
+                state: ConnectionState::Disconnected,
+                error: None,
+                ..room.status
+            },
+        );
+        Ok(())
+    }
+
+    // 连接任务超过 timeout 没有心跳的直播间及经过的时间
+    pub fn stalled_rooms(&self, timeout: Duration) -> Vec<(u64, Duration)> {
+        self.shared
+            .rooms
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(room_id, room)| Some((*room_id, room.heartbeat.stalled(timeout)?)))
+            .collect()
+    }
+
+    // 终止直播间的连接任务并重新开始连接，保留直播间的设置
+    pub fn restart_room(&self, room_id: u64) -> AppResult<()> {
+        {
+            let mut rooms = self.shared.rooms.lock().unwrap();
+            let room = rooms
+                .get_mut(&room_id)
+                .ok_or(AppError::RoomNotFound(room_id))?;
+            room.task.abort();
+            room.heartbeat = Heartbeat::new();
+            room.task = spawn_supervise(
+                self.shared.clone(),
+                room.source.clone(),
+                None,
+                room_id,
+                room.heartbeat.clone(),
+            );
+        }
+        self.shared.update_status(room_id, |status| {
+            status.state = ConnectionState::Connecting;
+            status.next_retry_at = None;
+        });
+        Ok(())
+    }
+
+    // 断开所有直播间，之后可以用 start_all 重新连接
+    pub fn stop_all(&self) {
+        let rooms: Vec<Room> = self
+            .shared
+            .rooms
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, room)| room)
+            .collect();
+        let mut stopped = self.shared.stopped.lock().unwrap();
+        for room in rooms {
+            room.task.abort();
+            let _ = self.shared.app.emit(
+                STATUS_EVENT,
+                &RoomStatus {
+                    state: ConnectionState::Disconnected,
+                    error: None,
+                    ..room.status
+                },
+            );
+            stopped.push(room.source);
+        }
+    }
+
+    // 重新连接 stop_all 断开的直播间，连接失败的直播间保留到下次重试
+    pub async fn start_all(&self) -> Vec<AppError> {
+        let sources = std::mem::take(&mut *self.shared.stopped.lock().unwrap());
+        let mut errors = Vec::new();
+        for source in sources {
+            match self.add_room(source.clone()).await {
+                Ok(_) | Err(AppError::RoomExists(_)) => {}
+                Err(err) => {
+                    errors.push(err);
+                    self.shared.stopped.lock().unwrap().push(source);
+                }
+            }
+        }
+        errors
+    }
+
+    // 是否有已暂停、可以重新连接的直播间
+    pub fn is_stopped(&self) -> bool {
+        !self.shared.stopped.lock().unwrap().is_empty()
+    }
+
+    // 直连直播间时使用的账号，用于以同一账号发送弹幕
+    pub fn credential_id(&self, room_id: u64) -> Option<String> {
+        match &self.shared.rooms.lock().unwrap().get(&room_id)?.source {
+            DanmakuSource::Direct { credential_id, .. } => credential_id.clone(),
+            DanmakuSource::OpenLive { .. } => None,
+        }
+    }
+
+    // 所有直播间的房间号
+    pub fn list_rooms(&self) -> Vec<u64> {
+        let mut rooms: Vec<u64> = self.shared.rooms.lock().unwrap().keys().copied().collect();
+        rooms.sort();
+        rooms
+    }
+
+    pub fn rooms_status(&self) -> Vec<RoomStatus> {
+        self.shared.statuses()
+    }
+
+    pub fn filter_rules(&self) -> Vec<FilterRuleStatus> {
+        self.shared.filter.rules()
+    }
+
+    pub fn set_filter_rules(&self, rules: Vec<FilterRule>) -> AppResult<Vec<FilterRuleStatus>> {
+        self.shared.filter.set_rules(rules)
+    }
+
+    pub fn reset_filter_hits(&self) -> Vec<FilterRuleStatus> {
+        self.shared.filter.reset_hits();
+        self.shared.filter.rules()
+    }
+
+    pub fn moderation_rules(&self) -> Vec<ModerationRuleStatus> {
+        self.shared.moderation.rules()
+    }
+
+    pub fn set_moderation_rules(
+        &self,
+        rules: Vec<ModerationRule>,
+    ) -> AppResult<Vec<ModerationRuleStatus>> {
+        self.shared.moderation.set_rules(rules)
+    }
+
+    // 把审核规则移到过滤规则的末尾，之后命中的事件会被直接丢弃
+    pub fn promote_moderation_rule(&self, id: &str) -> AppResult<Vec<FilterRuleStatus>> {
+        let rule = self.shared.moderation.to_filter_rule(id)?;
+        let mut filters: Vec<FilterRule> = self
+            .shared
+            .filter
+            .rules()
+            .into_iter()
+            .map(|status| status.rule)
+            .collect();
+        if filters.iter().any(|filter| filter.id == rule.id) {
+            return Err(AppError::InvalidConfig(format!(
+                "过滤规则 ID 已存在: {}",
+                rule.id
+            )));
+        }
+        filters.push(rule);
+        let statuses = self.shared.filter.set_rules(filters)?;
+        let remaining = self
+            .shared
+            .moderation
+            .rules()
+            .into_iter()
+            .map(|status| status.rule)
+            .filter(|rule| rule.id != id)
+            .collect();
+        self.shared.moderation.set_rules(remaining)?;
+        Ok(statuses)
+    }
+
+    pub fn flagged_events(&self, pending_only: bool, limit: usize) -> Vec<FlaggedEvent> {
+        self.shared.moderation.flagged(pending_only, limit)
+    }
+
+    pub fn review_flagged_event(
+        &self,
+        event_id: &str,
+        status: ReviewStatus,
+    ) -> AppResult<FlaggedEvent> {
+        self.shared.moderation.set_review_status(event_id, status)
+    }
+
+    pub fn clear_flagged_events(&self) {
+        self.shared.moderation.clear_flagged()
+    }
+
+    // 订阅之后收到的所有直播间事件，处理不及时时丢弃最旧的事件
+    pub fn subscribe(&self, name: &str) -> Subscriber<DanmakuEvent> {
+        self.subscribe_with(name, event_bus::DEFAULT_CAPACITY, DropPolicy::DropOldest)
+    }
+
+    pub fn subscribe_with(
+        &self,
+        name: &str,
+        capacity: usize,
+        policy: DropPolicy,
+    ) -> Subscriber<DanmakuEvent> {
+        self.shared.events.subscribe(name, capacity, policy)
+    }
+
+    // 各订阅者的积压和丢弃统计
+    pub fn bus_metrics(&self) -> EventBusMetrics {
+        self.shared.events.metrics()
+    }
+
+    // 订阅之后的连接状态切换
+    pub fn subscribe_states(&self) -> broadcast::Receiver<ConnectionStateChanged> {
+        self.shared.state_changes.subscribe()
+    }
+
+    // 分发模拟的测试事件，与真实事件一样经过过滤规则和插件处理
+    pub fn publish_simulated(&self, mut event: DanmakuEvent) {
+        event.simulated = true;
+        self.shared.publish(event);
+    }
+
+    // 分发回放的事件，保存时已经过过滤规则和插件处理，不再重复处理
+    pub fn publish_replay(&self, event: DanmakuEvent) {
+        let _ = self.shared.app.emit(DANMAKU_EVENT, &event);
+        self.shared.events.publish(event);
+    }
+}
+
+// 定时发送所有直播间的状态，前端据此刷新人气值和最后消息时间
+async fn report_rooms(shared: Arc<Shared>) {
+    let mut ticker = tokio::time::interval(ROOMS_REPORT_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let statuses = shared.statuses();
+        if !statuses.is_empty() {
+            let _ = shared.app.emit(ROOMS_EVENT, &statuses);
+        }
+    }
+}
+
+// 获取连接信息
+async fn prepare(http: &Client, source: &DanmakuSource) -> AppResult<Endpoint> {
+    match source {
+        DanmakuSource::Direct {
+            room_id, cookie, ..
+        } => direct::prepare(http, *room_id, cookie.as_deref()).await,
+        DanmakuSource::OpenLive {
+            auth_body,
+            wss_links,
+        } => open_live::prepare(auth_body, wss_links),
+    }
+}
+
+fn spawn_supervise(
+    shared: Arc<Shared>,
+    source: DanmakuSource,
+    endpoint: Option<Endpoint>,
+    room_id: u64,
+    heartbeat: Heartbeat,
+) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(crate::crash::guard(
+        format!("直播间 {}", room_id),
+        supervise(shared, source, endpoint, room_id, heartbeat),
+    ))
+}
+
+// 维持直播间的连接，直到直播间被移除
+// 连接断开或所有服务器都连接失败后等待一段时间再重连，重连前重新获取连接信息，
+// 并从下一个服务器开始尝试，避免一直连接同一台有问题的服务器
+// endpoint 为空时先获取连接信息
+async fn supervise(
+    shared: Arc<Shared>,
+    source: DanmakuSource,
+    mut endpoint: Option<Endpoint>,
+    room_id: u64,
+    heartbeat: Heartbeat,
+) {
+    let kind = source.kind();
+    let mut server_index = 0;
+    let mut attempts = 0;
+
+    loop {
+        heartbeat.beat();
+        let result = match endpoint.take() {
+            Some(endpoint) => Ok(endpoint),
+            None => prepare(&shared.http, &source).await,
+        };
+        let error = match result {
+            Ok(endpoint) => {
+                match connect_any(&endpoint, &mut server_index).await {
+                    Ok(socket) => {
+                        attempts = 0;
+                        shared.update_status(room_id, |status| {
+                            status.state = ConnectionState::Connected;
+                            status.error = None;
+                            status.reconnect_attempts = 0;
+                            status.next_retry_at = None;
+                        });
+                        let result = receive(&shared, socket, &endpoint, kind, &heartbeat).await;
+                        // 下一次从另一台服务器开始尝试
+                        server_index += 1;
+                        match result {
+                            Ok(()) => "连接被服务器关闭".to_string(),
+                            Err(err) => err.to_string(),
+                        }
+                    }
+                    Err(err) => err.to_string(),
+                }
+            }
+            Err(err) => err.to_string(),
+        };
+
+        attempts += 1;
+        let delay = reconnect_delay(attempts);
+        log::warn!(
+            room_id,
+            event_type = "reconnect";
+            "直播间 {} 连接断开，{} 毫秒后第 {} 次重连: {}",
+            room_id,
+            delay.as_millis(),
+            attempts,
+            error
+        );
+        shared.update_status(room_id, |status| {
+            status.state = ConnectionState::Reconnecting;
+            status.error = Some(error);
+            status.reconnect_attempts = attempts;
+            status.next_retry_at =
+                Some(chrono::Utc::now().timestamp_millis() + delay.as_millis() as i64);
+        });
+        // 等待重连期间不检查心跳
+        heartbeat.idle();
+        tokio::time::sleep(delay).await;
+        shared.update_status(room_id, |status| {
+            status.state = ConnectionState::Connecting;
+            status.next_retry_at = None;
+        });
+    }
+}
+
+// 从 server_index 指向的服务器开始依次尝试，返回第一个认证成功的连接
+async fn connect_any(endpoint: &Endpoint, server_index: &mut usize) -> AppResult<Socket> {
+    let mut last_error = AppError::WebSocket("没有可用的弹幕服务器".to_string());
+    for _ in 0..endpoint.urls.len() {
+        let url = &endpoint.urls[*server_index % endpoint.urls.len()];
+        match open(url, &endpoint.auth_body).await {
+            Ok(socket) => {
+                log::info!(
+                    room_id = endpoint.room_id,
+                    event_type = "connected";
+                    "直播间 {} 已连接弹幕服务器 {}",
+                    endpoint.room_id,
+                    url
+                );
+                return Ok(socket);
+            }
+            Err(err) => {
+                log::warn!(
+                    room_id = endpoint.room_id,
+                    event_type = "connect_failed";
+                    "直播间 {} 连接弹幕服务器 {} 失败: {}",
+                    endpoint.room_id, url, err
+                );
+                last_error = err;
+                *server_index += 1;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+// 指数退避加上最多 50% 的随机抖动
+fn reconnect_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    let delay = RECONNECT_BASE
+        .saturating_mul(1 << exponent)
+        .min(RECONNECT_MAX);
+    delay.mul_f64(1.0 + rand::random::<f64>() * 0.5)
+}
+
+// 建立 WebSocket 连接并完成认证
+async fn open(url: &str, auth_body: &str) -> AppResult<Socket> {
+    let (mut socket, _) = crate::proxy::connect_websocket(url).await?;
+    socket
+        .send(Message::Binary(packet::encode(
+            packet::OP_AUTH,
+            auth_body.as_bytes(),
+        )))
+        .await?;
+
+    let reply = tokio::time::timeout(AUTH_TIMEOUT, async {
+        while let Some(message) = socket.next().await {
+            if let Message::Binary(data) = message? {
+                return Ok(data);
+            }
+        }
+        Err(AppError::WebSocket("连接在认证前关闭".to_string()))
+    })
+    .await
+    .map_err(|_| AppError::WebSocket("等待认证回复超时".to_string()))??;
+
+    let authenticated = packet::decode(&reply)?.iter().any(|packet| {
+        packet.op == packet::OP_AUTH_REPLY
+            && serde_json::from_slice::<serde_json::Value>(&packet.body)
+                .map(|body| body["code"].as_i64() == Some(0))
+                .unwrap_or(false)
+    });
+    if !authenticated {
+        return Err(AppError::WebSocket("弹幕服务器认证失败".to_string()));
+    }
+    Ok(socket)
+}
+
+// 定时发送心跳，并将收到的消息转换为事件
+async fn receive(
+    shared: &Shared,
+    socket: Socket,
+    endpoint: &Endpoint,
+    kind: SourceKind,
+    heartbeat: &Heartbeat,
+) -> AppResult<()> {
+    let (mut sink, mut stream) = socket.split();
+    let mut ticker = tokio::time::interval(endpoint.heartbeat_interval);
+    // 上一次心跳之后是否收到了回复
+    let mut replied = true;
+    let mut missed = 0;
+
+    loop {
+        heartbeat.beat();
+        tokio::select! {
+            _ = ticker.tick() => {
+                if replied {
+                    missed = 0;
+                } else {
+                    missed += 1;
+                    if missed >= MAX_MISSED_HEARTBEATS {
+                        return Err(AppError::WebSocket(format!(
+                            "连续 {} 次心跳没有回复",
+                            missed
+                        )));
+                    }
+                }
+                replied = false;
+                sink.send(Message::Binary(packet::encode(packet::OP_HEARTBEAT, &[])))
+                    .await?;
+            }
+            message = stream.next() => {
+                let data = match message {
+                    Some(Ok(Message::Binary(data))) => data,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => return Err(err.into()),
+                };
+                for packet in packet::decode(&data)? {
+                    if packet.op == packet::OP_HEARTBEAT_REPLY {
+                        replied = true;
+                    }
+                    handle_packet(shared, endpoint.room_id, kind, packet);
+                }
+            }
+        }
+    }
+}
+
+fn handle_packet(shared: &Shared, room_id: u64, kind: SourceKind, packet: packet::Packet) {
+    // 协议级别的跟踪日志，默认不输出，可以用 set_log_level 单独为 danmaku 模块开启
+    log::trace!(
+        room_id,
+        event_type = "packet";
+        "收到数据包 op={} 长度={}",
+        packet.op,
+        packet.body.len()
+    );
+    match packet.op {
+        packet::OP_HEARTBEAT_REPLY if packet.body.len() >= 4 => {
+            let popularity = u32::from_be_bytes([
+                packet.body[0],
+                packet.body[1],
+                packet.body[2],
+                packet.body[3],
+            ]);
+            shared.update_status(room_id, |status| status.popularity = popularity as u64);
+        }
+        packet::OP_MESSAGE => {
+            let message: serde_json::Value = match serde_json::from_slice(&packet.body) {
+                Ok(message) => message,
+                Err(err) => {
+                    log::debug!(room_id, event_type = "invalid_message"; "无法解析消息: {}", err);
+                    return;
+                }
+            };
+            log::trace!(
+                room_id,
+                event_type = message["cmd"].as_str().unwrap_or("unknown");
+                "{}",
+                message
+            );
+            let event = match kind {
+                SourceKind::Direct => direct::parse_message(room_id, &message),
+                SourceKind::OpenLive => open_live::parse_message(room_id, &message),
+            };
+            if let Some(event) = event {
+                // 消息很频繁，只更新状态而不单独通知前端，由定时上报的状态同步给前端
+                if let Some(room) = shared.rooms.lock().unwrap().get_mut(&room_id) {
+                    room.status.last_message_at = Some(event.timestamp);
+                }
+                shared.publish(event);
+            }
+        }
+        _ => {}
+    }
+}
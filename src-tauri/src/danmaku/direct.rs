@@ -0,0 +1,273 @@
+use super::{DanmakuEvent, Endpoint, EventKind};
+use crate::bilibili::{cookie_value, get_api};
+use crate::error::{AppError, AppResult};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri_plugin_http::reqwest::Client;
+
+// 短号转换为真实房间号
+const ROOM_INIT_URL: &str = "https://api.live.bilibili.com/room/v1/Room/room_init";
+// 获取弹幕服务器地址和连接令牌
+const DANMU_INFO_URL: &str = "https://api.live.bilibili.com/xlive/web-room/v1/index/getDanmuInfo";
+// 获取连接信息失败时使用的默认弹幕服务器
+const DEFAULT_HOST: &str = "wss://broadcastlv.chat.bilibili.com/sub";
+// 直连时的心跳间隔
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+// 准备直连直播间所需的连接信息
+// 未提供 cookie 时以游客身份连接，用户名会被打码
+pub(super) async fn prepare(
+    http: &Client,
+    room_id: u64,
+    cookie: Option<&str>,
+) -> AppResult<Endpoint> {
+    let room_id = resolve_room_id(http, room_id, cookie).await?;
+
+    let (token, mut urls) = match danmu_info(http, room_id, cookie).await {
+        Ok(info) => info,
+        Err(err) => {
+            log::warn!("获取弹幕服务器信息失败，使用默认服务器: {}", err);
+            (String::new(), Vec::new())
+        }
+    };
+    if urls.is_empty() {
+        urls.push(DEFAULT_HOST.to_string());
+    }
+
+    let auth_body = json!({
+        "uid": cookie.and_then(|cookie| cookie_value(cookie, "DedeUserID")).and_then(|uid| uid.parse::<u64>().ok()).unwrap_or(0),
+        "roomid": room_id,
+        "protover": 3,
+        "buvid": cookie.and_then(|cookie| cookie_value(cookie, "buvid3")).unwrap_or_default(),
+        "platform": "web",
+        "type": 2,
+        "key": token,
+    });
+    Ok(Endpoint {
+        room_id,
+        urls,
+        auth_body: auth_body.to_string(),
+        heartbeat_interval: HEARTBEAT_INTERVAL,
+    })
+}
+
+async fn resolve_room_id(http: &Client, room_id: u64, cookie: Option<&str>) -> AppResult<u64> {
+    let data = get_api(http, &format!("{}?id={}", ROOM_INIT_URL, room_id), cookie).await?;
+    data["room_id"]
+        .as_u64()
+        .ok_or_else(|| AppError::BilibiliApi {
+            code: -1,
+            message: format!("无法获取直播间 {} 的真实房间号", room_id),
+        })
+}
+
+async fn danmu_info(
+    http: &Client,
+    room_id: u64,
+    cookie: Option<&str>,
+) -> AppResult<(String, Vec<String>)> {
+    let data = get_api(
+        http,
+        &format!("{}?id={}&type=0", DANMU_INFO_URL, room_id),
+        cookie,
+    )
+    .await?;
+    let token = data["token"].as_str().unwrap_or_default().to_string();
+    let urls = data["host_list"]
+        .as_array()
+        .map(|hosts| {
+            hosts
+                .iter()
+                .filter_map(|host| {
+                    let name = host["host"].as_str()?;
+                    let port = host["wss_port"].as_u64().unwrap_or(443);
+                    Some(format!("wss://{}:{}/sub", name, port))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok((token, urls))
+}
+
+// 将直播间推送的消息转换为统一的事件，不关心的消息返回 None
+pub(super) fn parse_message(room_id: u64, message: &Value) -> Option<DanmakuEvent> {
+    let cmd = message["cmd"].as_str()?;
+    // 部分消息的 cmd 会带有 ":" 后缀，例如 DANMU_MSG:4:0:2:2:2:0
+    let cmd = cmd.split(':').next().unwrap_or(cmd);
+    let data = &message["data"];
+    let now = chrono::Utc::now().timestamp_millis();
+
+    let event = match cmd {
+        "DANMU_MSG" => {
+            let info = &message["info"];
+            let extra = &info[0];
+            // 表情弹幕的图片地址
+            let emoji = if extra[12].as_u64() == Some(1) {
+                extra[13]["url"].as_str().map(str::to_string)
+            } else {
+                None
+            };
+            DanmakuEvent {
+                id: String::new(),
+                kind: EventKind::Danmaku,
+                room_id,
+                timestamp: extra[4].as_i64().unwrap_or(now),
+                uid: info[2][0].as_u64().unwrap_or(0),
+                open_id: None,
+                uname: info[2][1].as_str().unwrap_or_default().to_string(),
+                uface: extra[15]["user"]["base"]["face"]
+                    .as_str()
+                    .map(str::to_string),
+                message: info[1].as_str().unwrap_or_default().to_string(),
+                num: 1,
+                price: 0.0,
+                guard_level: info[7].as_u64().unwrap_or(0) as u8,
+                fans_medal_level: info[3][0].as_u64().unwrap_or(0) as u32,
+                fans_medal_name: info[3][1].as_str().unwrap_or_default().to_string(),
+                user_level: info[4][0].as_u64().map(|level| level as u32),
+                msg_id: extra[7].as_str().map(str::to_string),
+                emoji,
+                replay: false,
+                simulated: false,
+                muted: false,
+            }
+        }
+        "SEND_GIFT" => {
+            // 金瓜子 1000 = 1 元，银瓜子礼物不计价
+            let price = if data["coin_type"].as_str() == Some("gold") {
+                data["total_coin"].as_f64().unwrap_or(0.0) / 1000.0
+            } else {
+                0.0
+            };
+            DanmakuEvent {
+                id: String::new(),
+                kind: EventKind::Gift,
+                room_id,
+                timestamp: data["timestamp"].as_i64().map(|t| t * 1000).unwrap_or(now),
+                uid: data["uid"].as_u64().unwrap_or(0),
+                open_id: None,
+                uname: data["uname"].as_str().unwrap_or_default().to_string(),
+                uface: data["face"].as_str().map(str::to_string),
+                message: data["giftName"].as_str().unwrap_or_default().to_string(),
+                num: data["num"].as_u64().unwrap_or(1) as u32,
+                price,
+                guard_level: data["guard_level"].as_u64().unwrap_or(0) as u8,
+                fans_medal_level: data["medal_info"]["medal_level"].as_u64().unwrap_or(0) as u32,
+                fans_medal_name: data["medal_info"]["medal_name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                user_level: None,
+                msg_id: data["tid"].as_str().map(str::to_string),
+                emoji: None,
+                replay: false,
+                simulated: false,
+                muted: false,
+            }
+        }
+        "SUPER_CHAT_MESSAGE" => DanmakuEvent {
+            id: String::new(),
+            kind: EventKind::SuperChat,
+            room_id,
+            timestamp: data["start_time"].as_i64().map(|t| t * 1000).unwrap_or(now),
+            uid: data["uid"].as_u64().unwrap_or(0),
+            open_id: None,
+            uname: data["user_info"]["uname"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            uface: data["user_info"]["face"].as_str().map(str::to_string),
+            message: data["message"].as_str().unwrap_or_default().to_string(),
+            num: 1,
+            price: data["price"].as_f64().unwrap_or(0.0),
+            guard_level: data["user_info"]["guard_level"].as_u64().unwrap_or(0) as u8,
+            fans_medal_level: data["medal_info"]["medal_level"].as_u64().unwrap_or(0) as u32,
+            fans_medal_name: data["medal_info"]["medal_name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            user_level: None,
+            msg_id: data["id"].as_u64().map(|id| id.to_string()),
+            emoji: None,
+            replay: false,
+            simulated: false,
+            muted: false,
+        },
+        "GUARD_BUY" => DanmakuEvent {
+            id: String::new(),
+            kind: EventKind::Guard,
+            room_id,
+            timestamp: data["start_time"].as_i64().map(|t| t * 1000).unwrap_or(now),
+            uid: data["uid"].as_u64().unwrap_or(0),
+            open_id: None,
+            uname: data["username"].as_str().unwrap_or_default().to_string(),
+            uface: None,
+            message: data["gift_name"].as_str().unwrap_or_default().to_string(),
+            num: data["num"].as_u64().unwrap_or(1) as u32,
+            price: data["price"].as_f64().unwrap_or(0.0) * data["num"].as_f64().unwrap_or(1.0)
+                / 1000.0,
+            guard_level: data["guard_level"].as_u64().unwrap_or(0) as u8,
+            fans_medal_level: 0,
+            fans_medal_name: String::new(),
+            user_level: None,
+            msg_id: None,
+            emoji: None,
+            replay: false,
+            simulated: false,
+            muted: false,
+        },
+        "LIKE_INFO_V3_CLICK" => DanmakuEvent {
+            id: String::new(),
+            kind: EventKind::Like,
+            room_id,
+            timestamp: now,
+            uid: data["uid"].as_u64().unwrap_or(0),
+            open_id: None,
+            uname: data["uname"].as_str().unwrap_or_default().to_string(),
+            uface: data["uface"].as_str().map(str::to_string),
+            message: String::new(),
+            num: 1,
+            price: 0.0,
+            guard_level: data["fans_medal"]["guard_level"].as_u64().unwrap_or(0) as u8,
+            fans_medal_level: data["fans_medal"]["medal_level"].as_u64().unwrap_or(0) as u32,
+            fans_medal_name: data["fans_medal"]["medal_name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            user_level: None,
+            msg_id: None,
+            emoji: None,
+            replay: false,
+            simulated: false,
+            muted: false,
+        },
+        // msg_type 1 为进入直播间，2 为关注
+        "INTERACT_WORD" if data["msg_type"].as_u64() == Some(1) => DanmakuEvent {
+            id: String::new(),
+            kind: EventKind::Enter,
+            room_id,
+            timestamp: data["timestamp"].as_i64().map(|t| t * 1000).unwrap_or(now),
+            uid: data["uid"].as_u64().unwrap_or(0),
+            open_id: None,
+            uname: data["uname"].as_str().unwrap_or_default().to_string(),
+            uface: None,
+            message: String::new(),
+            num: 1,
+            price: 0.0,
+            guard_level: data["fans_medal"]["guard_level"].as_u64().unwrap_or(0) as u8,
+            fans_medal_level: data["fans_medal"]["medal_level"].as_u64().unwrap_or(0) as u32,
+            fans_medal_name: data["fans_medal"]["medal_name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            user_level: None,
+            msg_id: None,
+            emoji: None,
+            replay: false,
+            simulated: false,
+            muted: false,
+        },
+        _ => return None,
+    };
+    Some(event)
+}
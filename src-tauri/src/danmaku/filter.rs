@@ -0,0 +1,274 @@
+use super::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::user_info::UserInfoCache;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存过滤规则的文件，位于应用数据目录
+const STORE_FILE: &str = "filters.json";
+const RULES_KEY: &str = "rules";
+
+// 去重记录超过这个数量时清理过期的记录
+const DEDUP_CLEANUP_THRESHOLD: usize = 10_000;
+
+// 过滤规则，命中时事件会被丢弃，不会发送给前端、上传或保存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterRule {
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: RuleKind,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleKind {
+    // 弹幕或醒目留言包含任意关键词，不区分大小写
+    Keyword {
+        keywords: Vec<String>,
+    },
+    // 弹幕或醒目留言匹配正则表达式
+    Regex {
+        pattern: String,
+    },
+    // 来自指定用户的所有事件
+    User {
+        #[serde(default)]
+        uids: Vec<u64>,
+        #[serde(default)]
+        unames: Vec<String>,
+    },
+    // 价值低于指定金额（元）的礼物，免费礼物的价值为 0
+    MinGiftValue {
+        min_price: f64,
+    },
+    // 同一用户在时间窗口内发送的重复弹幕
+    Dedup {
+        window_secs: u64,
+    },
+    // 账号等级低于该等级的用户，没有发过弹幕的用户等级未知，不会被过滤
+    UserLevel {
+        below: u32,
+    },
+    // uid 不小于该值的新账号
+    AccountAge {
+        min_uid: u64,
+    },
+}
+
+// 返回给前端的规则和命中次数
+#[derive(Debug, Clone, Serialize)]
+pub struct FilterRuleStatus {
+    #[serde(flatten)]
+    pub rule: FilterRule,
+    pub hits: u64,
+}
+
+struct CompiledRule {
+    rule: FilterRule,
+    // 预先处理好的匹配条件
+    keywords: Vec<String>,
+    regex: Option<Regex>,
+    hits: AtomicU64,
+}
+
+impl CompiledRule {
+    fn compile(rule: FilterRule) -> AppResult<Self> {
+        let (keywords, regex) = match &rule.kind {
+            RuleKind::Keyword { keywords } => (
+                keywords
+                    .iter()
+                    .map(|keyword| keyword.trim().to_lowercase())
+                    .filter(|keyword| !keyword.is_empty())
+                    .collect(),
+                None,
+            ),
+            RuleKind::Regex { pattern } => {
+                let regex = Regex::new(pattern).map_err(|err| {
+                    AppError::InvalidConfig(format!("规则 {} 的正则表达式无效: {}", rule.id, err))
+                })?;
+                (Vec::new(), Some(regex))
+            }
+            _ => (Vec::new(), None),
+        };
+        Ok(CompiledRule {
+            rule,
+            keywords,
+            regex,
+            hits: AtomicU64::new(0),
+        })
+    }
+}
+
+// 在事件分发前按顺序应用过滤规则
+pub(super) struct EventFilter {
+    store: Arc<Store<Wry>>,
+    rules: RwLock<Vec<CompiledRule>>,
+    // 去重规则记录的最近弹幕：规则 ID 和内容 -> 时间戳
+    recent: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl EventFilter {
+    pub fn load(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let rules: Vec<FilterRule> = store
+            .get(RULES_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let rules = rules
+            .into_iter()
+            .filter_map(|rule| match CompiledRule::compile(rule) {
+                Ok(rule) => Some(rule),
+                Err(err) => {
+                    log::warn!("加载过滤规则失败: {}", err);
+                    None
+                }
+            })
+            .collect();
+        Ok(EventFilter {
+            store,
+            rules: RwLock::new(rules),
+            recent: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn rules(&self) -> Vec<FilterRuleStatus> {
+        self.rules
+            .read()
+            .unwrap()
+            .iter()
+            .map(|rule| FilterRuleStatus {
+                rule: rule.rule.clone(),
+                hits: rule.hits.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    // 替换所有规则并保存，未修改的规则保留命中次数
+    pub fn set_rules(&self, rules: Vec<FilterRule>) -> AppResult<Vec<FilterRuleStatus>> {
+        let mut ids = HashSet::new();
+        for rule in &rules {
+            if rule.id.is_empty() || !ids.insert(rule.id.as_str()) {
+                return Err(AppError::InvalidConfig(format!(
+                    "过滤规则 ID 为空或重复: {}",
+                    rule.id
+                )));
+            }
+        }
+        let mut compiled = rules
+            .iter()
+            .cloned()
+            .map(CompiledRule::compile)
+            .collect::<AppResult<Vec<_>>>()?;
+
+        {
+            let mut current = self.rules.write().unwrap();
+            for rule in &mut compiled {
+                if let Some(old) = current.iter().find(|old| old.rule.id == rule.rule.id) {
+                    rule.hits = AtomicU64::new(old.hits.load(Ordering::Relaxed));
+                }
+            }
+            *current = compiled;
+        }
+        self.recent.lock().unwrap().clear();
+
+        match serde_json::to_value(&rules) {
+            Ok(value) => {
+                self.store.set(RULES_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存过滤规则失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化过滤规则失败: {}", err),
+        }
+        Ok(self.rules())
+    }
+
+    pub fn reset_hits(&self) {
+        for rule in self.rules.read().unwrap().iter() {
+            rule.hits.store(0, Ordering::Relaxed);
+        }
+    }
+
+    // 事件是否通过所有规则，命中的规则会增加命中次数
+    pub fn accept(&self, event: &DanmakuEvent, users: &UserInfoCache) -> bool {
+        let rules = self.rules.read().unwrap();
+        for rule in rules.iter().filter(|rule| rule.rule.enabled) {
+            if self.matches(rule, event, users) {
+                rule.hits.fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn matches(&self, rule: &CompiledRule, event: &DanmakuEvent, users: &UserInfoCache) -> bool {
+        let has_text = matches!(event.kind, EventKind::Danmaku | EventKind::SuperChat);
+        match &rule.rule.kind {
+            RuleKind::Keyword { .. } => {
+                has_text && {
+                    let message = event.message.to_lowercase();
+                    rule.keywords
+                        .iter()
+                        .any(|keyword| message.contains(keyword.as_str()))
+                }
+            }
+            RuleKind::Regex { .. } => {
+                has_text
+                    && rule
+                        .regex
+                        .as_ref()
+                        .is_some_and(|regex| regex.is_match(&event.message))
+            }
+            RuleKind::User { uids, unames } => {
+                (event.uid != 0 && uids.contains(&event.uid)) || unames.contains(&event.uname)
+            }
+            RuleKind::MinGiftValue { min_price } => {
+                event.kind == EventKind::Gift && event.price < *min_price
+            }
+            RuleKind::Dedup { window_secs } => {
+                event.kind == EventKind::Danmaku
+                    && self.is_duplicate(&rule.rule.id, *window_secs, event)
+            }
+            RuleKind::UserLevel { below } => {
+                users.level_of(event).is_some_and(|level| level < *below)
+            }
+            RuleKind::AccountAge { min_uid } => event.uid != 0 && event.uid >= *min_uid,
+        }
+    }
+
+    // 记录弹幕并判断窗口内是否已经出现过
+    fn is_duplicate(&self, rule_id: &str, window_secs: u64, event: &DanmakuEvent) -> bool {
+        let window = window_secs as i64 * 1000;
+        let user = event
+            .open_id
+            .clone()
+            .unwrap_or_else(|| event.uid.to_string());
+        let key = (
+            rule_id.to_string(),
+            format!("{}:{}:{}", event.room_id, user, event.message),
+        );
+        let now = event.timestamp;
+
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() > DEDUP_CLEANUP_THRESHOLD {
+            recent.retain(|(id, _), timestamp| id != rule_id || now - *timestamp < window);
+        }
+        match recent.insert(key, now) {
+            Some(previous) => now - previous < window,
+            None => false,
+        }
+    }
+}
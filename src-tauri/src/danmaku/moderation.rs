@@ -0,0 +1,305 @@
+use super::filter::{FilterRule, RuleKind};
+use super::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::user_info::UserInfoCache;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存审核规则的文件，位于应用数据目录
+const STORE_FILE: &str = "moderation.json";
+const RULES_KEY: &str = "rules";
+
+// 事件被标记时发送给前端的事件，内容为 FlaggedEvent
+pub const FLAGGED_EVENT: &str = "moderation://flagged";
+
+// 内存中保留的标记记录数量，事件本身仍然保存在本地事件记录中
+const MAX_FLAGGED: usize = 500;
+
+// 审核规则，命中时标记事件等待审核，与过滤规则不同，事件不会被丢弃
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationRule {
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub name: String,
+    // 静默命中的事件：仍然保存、上传并显示在应用中，但不发送给覆盖层、朗读和提示音
+    #[serde(default)]
+    pub mute: bool,
+    #[serde(flatten)]
+    pub kind: ModerationRuleKind,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ModerationRuleKind {
+    // 弹幕或醒目留言匹配正则表达式
+    Regex { pattern: String },
+    // 账号等级低于该等级的用户，没有发过弹幕的用户等级未知，不会被标记
+    UserLevel { below: u32 },
+    // B 站接口不提供注册时间，uid 按注册顺序分配，不小于该值的账号视为新账号
+    AccountAge { min_uid: u64 },
+}
+
+// 返回给前端的规则和命中次数
+#[derive(Debug, Clone, Serialize)]
+pub struct ModerationRuleStatus {
+    #[serde(flatten)]
+    pub rule: ModerationRule,
+    pub hits: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    Pending,
+    // 确认违规
+    Confirmed,
+    // 误判
+    Dismissed,
+}
+
+// 被审核规则标记的事件
+#[derive(Debug, Clone, Serialize)]
+pub struct FlaggedEvent {
+    pub event: DanmakuEvent,
+    pub rule_id: String,
+    pub rule_name: String,
+    // 是否已在覆盖层和朗读中静默
+    pub muted: bool,
+    // Unix 毫秒时间戳
+    pub flagged_at: i64,
+    pub status: ReviewStatus,
+}
+
+struct CompiledRule {
+    rule: ModerationRule,
+    regex: Option<Regex>,
+    hits: AtomicU64,
+}
+
+impl CompiledRule {
+    fn compile(rule: ModerationRule) -> AppResult<Self> {
+        let regex = match &rule.kind {
+            ModerationRuleKind::Regex { pattern } => Some(Regex::new(pattern).map_err(|err| {
+                AppError::InvalidConfig(format!("规则 {} 的正则表达式无效: {}", rule.id, err))
+            })?),
+            _ => None,
+        };
+        Ok(CompiledRule {
+            rule,
+            regex,
+            hits: AtomicU64::new(0),
+        })
+    }
+
+    fn matches(&self, event: &DanmakuEvent, users: &UserInfoCache) -> bool {
+        match &self.rule.kind {
+            ModerationRuleKind::Regex { .. } => {
+                matches!(event.kind, EventKind::Danmaku | EventKind::SuperChat)
+                    && self
+                        .regex
+                        .as_ref()
+                        .is_some_and(|regex| regex.is_match(&event.message))
+            }
+            ModerationRuleKind::UserLevel { below } => {
+                users.level_of(event).is_some_and(|level| level < *below)
+            }
+            ModerationRuleKind::AccountAge { min_uid } => event.uid != 0 && event.uid >= *min_uid,
+        }
+    }
+}
+
+// 在事件分发前检查审核规则，标记命中的事件供主播审核
+pub(super) struct Moderation {
+    app: AppHandle,
+    store: Arc<Store<Wry>>,
+    rules: RwLock<Vec<CompiledRule>>,
+    // 最近标记的在后
+    flagged: Mutex<VecDeque<FlaggedEvent>>,
+}
+
+impl Moderation {
+    pub fn load(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let rules: Vec<ModerationRule> = store
+            .get(RULES_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let rules = rules
+            .into_iter()
+            .filter_map(|rule| match CompiledRule::compile(rule) {
+                Ok(rule) => Some(rule),
+                Err(err) => {
+                    log::warn!("加载审核规则失败: {}", err);
+                    None
+                }
+            })
+            .collect();
+        Ok(Moderation {
+            app: app.clone(),
+            store,
+            rules: RwLock::new(rules),
+            flagged: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub fn rules(&self) -> Vec<ModerationRuleStatus> {
+        self.rules
+            .read()
+            .unwrap()
+            .iter()
+            .map(|rule| ModerationRuleStatus {
+                rule: rule.rule.clone(),
+                hits: rule.hits.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    // 替换所有规则并保存，未修改的规则保留命中次数
+    pub fn set_rules(&self, rules: Vec<ModerationRule>) -> AppResult<Vec<ModerationRuleStatus>> {
+        let mut ids = HashSet::new();
+        for rule in &rules {
+            if rule.id.is_empty() || !ids.insert(rule.id.as_str()) {
+                return Err(AppError::InvalidConfig(format!(
+                    "审核规则 ID 为空或重复: {}",
+                    rule.id
+                )));
+            }
+        }
+        let mut compiled = rules
+            .iter()
+            .cloned()
+            .map(CompiledRule::compile)
+            .collect::<AppResult<Vec<_>>>()?;
+
+        {
+            let mut current = self.rules.write().unwrap();
+            for rule in &mut compiled {
+                if let Some(old) = current.iter().find(|old| old.rule.id == rule.rule.id) {
+                    rule.hits = AtomicU64::new(old.hits.load(Ordering::Relaxed));
+                }
+            }
+            *current = compiled;
+        }
+
+        match serde_json::to_value(&rules) {
+            Ok(value) => {
+                self.store.set(RULES_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存审核规则失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化审核规则失败: {}", err),
+        }
+        Ok(self.rules())
+    }
+
+    // 把审核规则转换为过滤规则，之后命中的事件会被直接丢弃
+    pub fn to_filter_rule(&self, id: &str) -> AppResult<FilterRule> {
+        let rules = self.rules.read().unwrap();
+        let rule = rules
+            .iter()
+            .find(|rule| rule.rule.id == id)
+            .ok_or_else(|| AppError::ModerationRuleNotFound(id.to_string()))?;
+        let kind = match &rule.rule.kind {
+            ModerationRuleKind::Regex { pattern } => RuleKind::Regex {
+                pattern: pattern.clone(),
+            },
+            ModerationRuleKind::UserLevel { below } => RuleKind::UserLevel { below: *below },
+            ModerationRuleKind::AccountAge { min_uid } => {
+                RuleKind::AccountAge { min_uid: *min_uid }
+            }
+        };
+        Ok(FilterRule {
+            id: rule.rule.id.clone(),
+            enabled: true,
+            name: rule.rule.name.clone(),
+            kind,
+        })
+    }
+
+    // 按顺序检查规则，命中第一条规则时标记事件，需要静默时设置事件的 muted
+    pub fn review(&self, event: &mut DanmakuEvent, users: &UserInfoCache) {
+        if event.replay || event.simulated {
+            return;
+        }
+        let hit = {
+            let rules = self.rules.read().unwrap();
+            rules
+                .iter()
+                .filter(|rule| rule.rule.enabled)
+                .find(|rule| rule.matches(event, users))
+                .map(|rule| {
+                    rule.hits.fetch_add(1, Ordering::Relaxed);
+                    rule.rule.clone()
+                })
+        };
+        let Some(rule) = hit else {
+            return;
+        };
+        event.muted = rule.mute;
+        log::info!(
+            event_type = "moderation_flagged";
+            "事件被审核规则 {} 标记{}: {} {}",
+            if rule.name.is_empty() { &rule.id } else { &rule.name },
+            if rule.mute { "并静默" } else { "" },
+            event.uname,
+            event.message
+        );
+        let flagged = FlaggedEvent {
+            event: event.clone(),
+            rule_id: rule.id,
+            rule_name: rule.name,
+            muted: rule.mute,
+            flagged_at: chrono::Utc::now().timestamp_millis(),
+            status: ReviewStatus::Pending,
+        };
+        let _ = self.app.emit(FLAGGED_EVENT, &flagged);
+        let mut list = self.flagged.lock().unwrap();
+        if list.len() >= MAX_FLAGGED {
+            list.pop_front();
+        }
+        list.push_back(flagged);
+    }
+
+    // 最近标记的事件，按时间倒序排列，pending_only 为 true 时只返回未审核的
+    pub fn flagged(&self, pending_only: bool, limit: usize) -> Vec<FlaggedEvent> {
+        self.flagged
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|item| !pending_only || item.status == ReviewStatus::Pending)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_review_status(
+        &self,
+        event_id: &str,
+        status: ReviewStatus,
+    ) -> AppResult<FlaggedEvent> {
+        let mut list = self.flagged.lock().unwrap();
+        let item = list
+            .iter_mut()
+            .find(|item| item.event.id == event_id)
+            .ok_or_else(|| AppError::FlaggedEventNotFound(event_id.to_string()))?;
+        item.status = status;
+        Ok(item.clone())
+    }
+
+    pub fn clear_flagged(&self) {
+        self.flagged.lock().unwrap().clear();
+    }
+}
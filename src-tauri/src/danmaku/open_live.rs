@@ -0,0 +1,206 @@
+use super::{DanmakuEvent, Endpoint, EventKind};
+use crate::error::{AppError, AppResult};
+use serde_json::Value;
+use std::time::Duration;
+
+// 开放平台长连的心跳间隔
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(20);
+
+// 开放平台长连的连接信息
+// auth_body 和 wss_links 来自开放平台 app/start 接口的 websocket_info，
+// 由 vtsuru 服务端使用身份码调用后返回，客户端不持有开放平台的密钥。
+// 项目心跳（app/heartbeat）同样由 vtsuru 服务端维持
+pub(super) fn prepare(auth_body: &str, wss_links: &[String]) -> AppResult<Endpoint> {
+    let auth: Value = serde_json::from_str(auth_body)
+        .map_err(|_| AppError::InvalidConfig("开放平台 auth_body 格式无效".to_string()))?;
+    if wss_links.is_empty() {
+        return Err(AppError::InvalidConfig(
+            "开放平台未返回长连地址".to_string(),
+        ));
+    }
+    Ok(Endpoint {
+        room_id: auth["roomid"].as_u64().unwrap_or(0),
+        urls: wss_links.to_vec(),
+        auth_body: auth_body.to_string(),
+        heartbeat_interval: HEARTBEAT_INTERVAL,
+    })
+}
+
+// 将开放平台推送的消息转换为统一的事件，不关心的消息返回 None
+pub(super) fn parse_message(room_id: u64, message: &Value) -> Option<DanmakuEvent> {
+    let cmd = message["cmd"].as_str()?;
+    let data = &message["data"];
+    let now = chrono::Utc::now().timestamp_millis();
+    let room_id = data["room_id"].as_u64().unwrap_or(room_id);
+    let timestamp = data["timestamp"].as_i64().map(|t| t * 1000).unwrap_or(now);
+    let open_id = data["open_id"].as_str().map(str::to_string);
+    let medal_level = data["fans_medal_level"].as_u64().unwrap_or(0) as u32;
+    let medal_name = data["fans_medal_name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let msg_id = data["msg_id"].as_str().map(str::to_string);
+
+    let event = match cmd {
+        "LIVE_OPEN_PLATFORM_DM" => DanmakuEvent {
+            id: String::new(),
+            kind: EventKind::Danmaku,
+            room_id,
+            timestamp,
+            uid: data["uid"].as_u64().unwrap_or(0),
+            open_id,
+            uname: data["uname"].as_str().unwrap_or_default().to_string(),
+            uface: data["uface"].as_str().map(str::to_string),
+            message: data["msg"].as_str().unwrap_or_default().to_string(),
+            num: 1,
+            price: 0.0,
+            guard_level: data["guard_level"].as_u64().unwrap_or(0) as u8,
+            fans_medal_level: medal_level,
+            fans_medal_name: medal_name,
+            user_level: None,
+            msg_id,
+            // dm_type 为 1 时是表情弹幕
+            emoji: data["emoji_img_url"]
+                .as_str()
+                .filter(|_| data["dm_type"].as_u64() == Some(1))
+                .map(str::to_string),
+            replay: false,
+            simulated: false,
+            muted: false,
+        },
+        "LIVE_OPEN_PLATFORM_SEND_GIFT" => {
+            // price 单位为 1/1000 元，免费礼物不计价
+            let paid = data["paid"].as_bool().unwrap_or(false);
+            let num = data["gift_num"].as_u64().unwrap_or(1);
+            let price = if paid {
+                data["price"].as_f64().unwrap_or(0.0) * num as f64 / 1000.0
+            } else {
+                0.0
+            };
+            DanmakuEvent {
+                id: String::new(),
+                kind: EventKind::Gift,
+                room_id,
+                timestamp,
+                uid: data["uid"].as_u64().unwrap_or(0),
+                open_id,
+                uname: data["uname"].as_str().unwrap_or_default().to_string(),
+                uface: data["uface"].as_str().map(str::to_string),
+                message: data["gift_name"].as_str().unwrap_or_default().to_string(),
+                num: num as u32,
+                price,
+                guard_level: data["guard_level"].as_u64().unwrap_or(0) as u8,
+                fans_medal_level: medal_level,
+                fans_medal_name: medal_name,
+                user_level: None,
+                msg_id,
+                emoji: None,
+                replay: false,
+                simulated: false,
+                muted: false,
+            }
+        }
+        "LIVE_OPEN_PLATFORM_SUPER_CHAT" => DanmakuEvent {
+            id: String::new(),
+            kind: EventKind::SuperChat,
+            room_id,
+            timestamp,
+            uid: data["uid"].as_u64().unwrap_or(0),
+            open_id,
+            uname: data["uname"].as_str().unwrap_or_default().to_string(),
+            uface: data["uface"].as_str().map(str::to_string),
+            message: data["message"].as_str().unwrap_or_default().to_string(),
+            num: 1,
+            price: data["rmb"].as_f64().unwrap_or(0.0),
+            guard_level: data["guard_level"].as_u64().unwrap_or(0) as u8,
+            fans_medal_level: medal_level,
+            fans_medal_name: medal_name,
+            user_level: None,
+            msg_id,
+            emoji: None,
+            replay: false,
+            simulated: false,
+            muted: false,
+        },
+        "LIVE_OPEN_PLATFORM_GUARD" => {
+            let user = &data["user_info"];
+            let num = data["guard_num"].as_u64().unwrap_or(1);
+            DanmakuEvent {
+                id: String::new(),
+                kind: EventKind::Guard,
+                room_id,
+                timestamp,
+                uid: user["uid"].as_u64().unwrap_or(0),
+                open_id: user["open_id"].as_str().map(str::to_string),
+                uname: user["uname"].as_str().unwrap_or_default().to_string(),
+                uface: user["uface"].as_str().map(str::to_string),
+                message: guard_name(data["guard_level"].as_u64().unwrap_or(0)).to_string(),
+                num: num as u32,
+                price: data["price"].as_f64().unwrap_or(0.0) * num as f64 / 1000.0,
+                guard_level: data["guard_level"].as_u64().unwrap_or(0) as u8,
+                fans_medal_level: medal_level,
+                fans_medal_name: medal_name,
+                user_level: None,
+                msg_id,
+                emoji: None,
+                replay: false,
+                simulated: false,
+                muted: false,
+            }
+        }
+        "LIVE_OPEN_PLATFORM_LIKE" => DanmakuEvent {
+            id: String::new(),
+            kind: EventKind::Like,
+            room_id,
+            timestamp,
+            uid: data["uid"].as_u64().unwrap_or(0),
+            open_id,
+            uname: data["uname"].as_str().unwrap_or_default().to_string(),
+            uface: data["uface"].as_str().map(str::to_string),
+            message: String::new(),
+            num: data["like_count"].as_u64().unwrap_or(1) as u32,
+            price: 0.0,
+            guard_level: 0,
+            fans_medal_level: medal_level,
+            fans_medal_name: medal_name,
+            user_level: None,
+            msg_id,
+            emoji: None,
+            replay: false,
+            simulated: false,
+            muted: false,
+        },
+        "LIVE_OPEN_PLATFORM_LIVE_ROOM_ENTER" => DanmakuEvent {
+            id: String::new(),
+            kind: EventKind::Enter,
+            room_id,
+            timestamp,
+            uid: data["uid"].as_u64().unwrap_or(0),
+            open_id,
+            uname: data["uname"].as_str().unwrap_or_default().to_string(),
+            uface: data["uface"].as_str().map(str::to_string),
+            message: String::new(),
+            num: 1,
+            price: 0.0,
+            guard_level: 0,
+            fans_medal_level: medal_level,
+            fans_medal_name: medal_name,
+            user_level: None,
+            msg_id,
+            emoji: None,
+            replay: false,
+            simulated: false,
+            muted: false,
+        },
+        _ => return None,
+    };
+    Some(event)
+}
+
+fn guard_name(level: u64) -> &'static str {
+    match level {
+        1 => "总督",
+        2 => "提督",
+        _ => "舰长",
+    }
+}
@@ -0,0 +1,90 @@
+use flate2::read::ZlibDecoder;
+use std::io::{self, Read};
+
+// 数据包头部长度
+const HEADER_LEN: usize = 16;
+
+// 数据包操作码
+pub const OP_HEARTBEAT: u32 = 2;
+pub const OP_HEARTBEAT_REPLY: u32 = 3;
+pub const OP_MESSAGE: u32 = 5;
+pub const OP_AUTH: u32 = 7;
+pub const OP_AUTH_REPLY: u32 = 8;
+
+// 协议版本：0 为 JSON，1 为心跳/人气值，2 为 zlib 压缩，3 为 brotli 压缩
+const PROTOVER_PLAIN: u16 = 1;
+const PROTOVER_ZLIB: u16 = 2;
+const PROTOVER_BROTLI: u16 = 3;
+
+// 解析后的数据包
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub op: u32,
+    pub body: Vec<u8>,
+}
+
+// 编码一个数据包：16 字节大端头部（总长度、头部长度、协议版本、操作码、序号）+ 数据
+pub fn encode(op: u32, body: &[u8]) -> Vec<u8> {
+    let packet_len = HEADER_LEN + body.len();
+    let mut buf = Vec::with_capacity(packet_len);
+    buf.extend_from_slice(&(packet_len as u32).to_be_bytes());
+    buf.extend_from_slice(&(HEADER_LEN as u16).to_be_bytes());
+    buf.extend_from_slice(&PROTOVER_PLAIN.to_be_bytes());
+    buf.extend_from_slice(&op.to_be_bytes());
+    buf.extend_from_slice(&1u32.to_be_bytes());
+    buf.extend_from_slice(body);
+    buf
+}
+
+// 解析一条 WebSocket 消息中的所有数据包，压缩的消息包会被解压后继续解析
+pub fn decode(data: &[u8]) -> io::Result<Vec<Packet>> {
+    let mut packets = Vec::new();
+    decode_into(data, &mut packets)?;
+    Ok(packets)
+}
+
+fn decode_into(mut data: &[u8], packets: &mut Vec<Packet>) -> io::Result<()> {
+    while data.len() >= HEADER_LEN {
+        let packet_len = read_u32(data, 0) as usize;
+        let header_len = read_u16(data, 4) as usize;
+        let protover = read_u16(data, 6);
+        let op = read_u32(data, 8);
+        if header_len < HEADER_LEN || packet_len < header_len || packet_len > data.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "数据包长度无效"));
+        }
+
+        let body = &data[header_len..packet_len];
+        match (op, protover) {
+            (OP_MESSAGE, PROTOVER_ZLIB) => {
+                let mut decompressed = Vec::new();
+                ZlibDecoder::new(body).read_to_end(&mut decompressed)?;
+                decode_into(&decompressed, packets)?;
+            }
+            (OP_MESSAGE, PROTOVER_BROTLI) => {
+                let mut decompressed = Vec::new();
+                brotli_decompressor::Decompressor::new(body, 4096)
+                    .read_to_end(&mut decompressed)?;
+                decode_into(&decompressed, packets)?;
+            }
+            _ => packets.push(Packet {
+                op,
+                body: body.to_vec(),
+            }),
+        }
+        data = &data[packet_len..];
+    }
+    Ok(())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
@@ -0,0 +1,154 @@
+use super::RoomManager;
+use crate::bilibili::{self, cookie_value};
+use crate::credentials::CredentialManager;
+use crate::error::{AppError, AppResult};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_http::reqwest::Client;
+
+// 发送弹幕
+const SEND_URL: &str = "https://api.live.bilibili.com/msg/send";
+
+// 同一直播间两条弹幕之间的最小间隔，过快会被拒绝
+const SEND_INTERVAL: Duration = Duration::from_millis(1500);
+
+// 普通用户每条弹幕最多 20 个字，部分用户可以发送更长的弹幕
+pub const DEFAULT_MAX_LENGTH: usize = 20;
+const MAX_LENGTH_LIMIT: usize = 100;
+
+// 接口返回的错误码
+const NOT_LOGGED_IN: i64 = -101;
+const TOO_FAST_CODES: &[i64] = &[10030, 10031];
+
+// 以已保存的账号在直播间发送弹幕，超过长度的内容拆分为多条依次发送
+pub struct DanmakuSender {
+    http: Client,
+    // 每个直播间下一次允许发送的时间，同一直播间的弹幕排队发送
+    next_send: Mutex<HashMap<u64, Arc<tokio::sync::Mutex<Instant>>>>,
+}
+
+impl DanmakuSender {
+    pub fn new() -> Self {
+        DanmakuSender {
+            http: bilibili::client(),
+            next_send: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // 返回实际发送的每一条内容，中途失败时之前的部分已经发出
+    pub async fn send(
+        &self,
+        room_id: u64,
+        text: &str,
+        cookie: &str,
+        max_length: usize,
+    ) -> AppResult<Vec<String>> {
+        let parts = split(text, max_length.clamp(1, MAX_LENGTH_LIMIT));
+        if parts.is_empty() {
+            return Err(AppError::InvalidConfig("弹幕内容不能为空".to_string()));
+        }
+        let slot = self
+            .next_send
+            .lock()
+            .unwrap()
+            .entry(room_id)
+            .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(Instant::now())))
+            .clone();
+        let mut next = slot.lock().await;
+        for part in &parts {
+            tokio::time::sleep_until((*next).into()).await;
+            let result = self.send_one(room_id, part, cookie).await;
+            *next = Instant::now() + SEND_INTERVAL;
+            result?;
+        }
+        Ok(parts)
+    }
+
+    async fn send_one(&self, room_id: u64, message: &str, cookie: &str) -> AppResult<()> {
+        let csrf = cookie_value(cookie, "bili_jct").ok_or_else(|| AppError::DanmakuRejected {
+            reason: "not_logged_in",
+            message: "账号 cookie 中缺少 bili_jct".to_string(),
+        })?;
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let room_id = room_id.to_string();
+        let request = self
+            .http
+            .post(SEND_URL)
+            .header("Cookie", cookie)
+            .header("Referer", format!("https://live.bilibili.com/{}", room_id))
+            .form(&[
+                ("bubble", "0"),
+                ("msg", message),
+                ("color", "16777215"),
+                ("mode", "1"),
+                ("fontsize", "25"),
+                ("rnd", timestamp.as_str()),
+                ("roomid", room_id.as_str()),
+                ("csrf", csrf),
+                ("csrf_token", csrf),
+            ]);
+        let body: Value = bilibili::send(request).await?.json().await?;
+        let code = body["code"].as_i64().unwrap_or(-1);
+        let text = body["message"]
+            .as_str()
+            .or_else(|| body["msg"].as_str())
+            .unwrap_or_default();
+        // 被屏蔽词拦截时 code 为 0，message 为 f 或 k
+        if code == 0 && text.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::DanmakuRejected {
+            reason: rejection_reason(code, text),
+            message: if text.is_empty() {
+                format!("错误码 {}", code)
+            } else {
+                text.to_string()
+            },
+        })
+    }
+}
+
+// 未指定账号时使用连接该直播间的账号，直播间不是用账号连接的则使用第一个 B 站账号
+pub fn resolve_cookie(
+    app: &AppHandle,
+    room_id: u64,
+    credential_id: Option<String>,
+) -> AppResult<String> {
+    let credentials = app.state::<CredentialManager>();
+    let id = credential_id
+        .or_else(|| {
+            app.try_state::<RoomManager>()
+                .and_then(|rooms| rooms.credential_id(room_id))
+        })
+        .or_else(|| credentials.default_cookie_id())
+        .ok_or_else(|| AppError::Credential("没有可用于发送弹幕的 B 站账号".to_string()))?;
+    credentials.cookie(&id)
+}
+
+fn rejection_reason(code: i64, message: &str) -> &'static str {
+    if code == NOT_LOGGED_IN {
+        "not_logged_in"
+    } else if TOO_FAST_CODES.contains(&code) || message.contains("频率") {
+        "too_fast"
+    } else if message.contains("禁言") {
+        "muted"
+    } else if message.contains("等级") {
+        "level_restricted"
+    } else if matches!(message, "f" | "k") || message.contains("屏蔽") {
+        "filtered"
+    } else {
+        "other"
+    }
+}
+
+// 按字符数拆分，去掉首尾空白后为空时返回空列表
+fn split(text: &str, max_length: usize) -> Vec<String> {
+    let chars: Vec<char> = text.trim().chars().collect();
+    chars
+        .chunks(max_length)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
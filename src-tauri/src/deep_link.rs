@@ -0,0 +1,137 @@
+use crate::danmaku::{DanmakuSource, RoomManager};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+// 注册到系统的链接协议，例如 vtsuru://connect-room/1234
+const SCHEME: &str = "vtsuru";
+
+// 再次启动程序时传入的参数，转发给已运行的实例
+pub const ARGS_EVENT: &str = "single-instance://args";
+// 收到的所有链接，前端据此跳转页面
+pub const OPEN_EVENT: &str = "deep-link://open";
+// 网站登录后回调的链接，参数由前端完成登录
+pub const AUTH_EVENT: &str = "deep-link://auth";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForwardedArgs {
+    pub args: Vec<String>,
+    pub cwd: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLink {
+    pub url: String,
+    // 链接的主机部分，例如 connect-room、auth
+    pub action: String,
+    pub path: Vec<String>,
+    pub params: HashMap<String, String>,
+}
+
+impl DeepLink {
+    fn parse(url: &Url) -> Self {
+        DeepLink {
+            url: url.to_string(),
+            action: url.host_str().unwrap_or_default().to_string(),
+            path: url
+                .path_segments()
+                .map(|segments| {
+                    segments
+                        .filter(|segment| !segment.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            params: url.query_pairs().into_owned().collect(),
+        }
+    }
+}
+
+// 在 setup 的最后调用，处理启动程序时携带的链接
+pub fn init(app: &AppHandle) {
+    // 便携版和开发环境没有经过安装程序注册协议，启动时注册到当前程序
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(err) = app.deep_link().register_all() {
+        log::warn!("注册 {}:// 链接协议失败: {}", SCHEME, err);
+    }
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            open(&handle, &url);
+        }
+    });
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => {
+            for url in urls {
+                open(app, &url);
+            }
+        }
+        Ok(None) => {}
+        Err(err) => log::warn!("读取启动链接失败: {}", err),
+    }
+}
+
+// 单实例插件的回调，第二个实例退出前会把参数交给已运行的实例
+// 链接参数由链接插件处理，这里只转发其他参数
+pub fn on_second_instance(app: &AppHandle, args: Vec<String>, cwd: String) {
+    crate::tray::show_window(app);
+    let prefix = format!("{}://", SCHEME);
+    // 第一个参数是程序路径
+    let args: Vec<String> = args
+        .into_iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with(&prefix))
+        .collect();
+    if !args.is_empty() {
+        let _ = app.emit(ARGS_EVENT, ForwardedArgs { args, cwd });
+    }
+}
+
+fn open(app: &AppHandle, url: &Url) {
+    if url.scheme() != SCHEME {
+        return;
+    }
+    let link = DeepLink::parse(url);
+    // 日志会随诊断包和崩溃报告上传，auth 等链接的参数中可能有授权码，只记录动作
+    log::info!("收到链接: {}://{}", SCHEME, link.action);
+    crate::tray::show_window(app);
+    match link.action.as_str() {
+        "connect-room" => connect_room(app, &link),
+        "auth" => {
+            let _ = app.emit(AUTH_EVENT, &link);
+        }
+        _ => {}
+    }
+    let _ = app.emit(OPEN_EVENT, &link);
+}
+
+// vtsuru://connect-room/<房间号>，以游客身份连接直播间
+fn connect_room(app: &AppHandle, link: &DeepLink) {
+    let Some(room_id) = link
+        .path
+        .first()
+        .and_then(|room_id| room_id.parse::<u64>().ok())
+    else {
+        log::warn!("链接中的房间号无效: {:?}", link.path.first());
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(rooms) = app.try_state::<RoomManager>() else {
+            return;
+        };
+        if rooms.list_rooms().contains(&room_id) {
+            return;
+        }
+        let source = DanmakuSource::Direct {
+            room_id,
+            cookie: None,
+            credential_id: None,
+        };
+        if let Err(err) = rooms.add_room(source).await {
+            log::warn!(room_id; "通过链接连接直播间 {} 失败: {}", room_id, err);
+        }
+    });
+}
@@ -0,0 +1,437 @@
+use crate::danmaku::{DanmakuEvent, EventKind, RoomManager};
+use crate::error::{AppError, AppResult};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存演示模式设置的文件，位于应用数据目录
+const STORE_FILE: &str = "demo.json";
+const CONFIG_KEY: &str = "config";
+
+// 演示模式开始或停止时发送给前端的事件
+pub const STATE_EVENT: &str = "demo://state";
+
+// 生成事件的检查间隔，每次按速率随机决定是否生成各类事件
+const TICK_INTERVAL: Duration = Duration::from_millis(200);
+// 每种事件每分钟最多生成的数量
+const MAX_RATE: f64 = 600.0;
+// 没有连接直播间且没有设置直播间号时使用的直播间号
+const DEMO_ROOM_ID: u64 = 1;
+// 模拟用户的 uid 从这里开始，避免与常见的真实 uid 混淆
+const DEMO_UID_BASE: u64 = 900_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DemoConfig {
+    // 事件所属的直播间，为空时使用第一个已连接的直播间
+    pub room_id: Option<u64>,
+    // 各类事件每分钟生成的数量，0 表示不生成
+    pub danmaku_per_min: f64,
+    pub gift_per_min: f64,
+    pub super_chat_per_min: f64,
+    pub guard_per_min: f64,
+    pub like_per_min: f64,
+    pub enter_per_min: f64,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        DemoConfig {
+            room_id: None,
+            danmaku_per_min: 30.0,
+            gift_per_min: 6.0,
+            super_chat_per_min: 0.5,
+            guard_per_min: 0.2,
+            like_per_min: 10.0,
+            enter_per_min: 12.0,
+        }
+    }
+}
+
+impl DemoConfig {
+    fn rates(&self) -> [(EventKind, f64); 6] {
+        [
+            (EventKind::Danmaku, self.danmaku_per_min),
+            (EventKind::Gift, self.gift_per_min),
+            (EventKind::SuperChat, self.super_chat_per_min),
+            (EventKind::Guard, self.guard_per_min),
+            (EventKind::Like, self.like_per_min),
+            (EventKind::Enter, self.enter_per_min),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DemoStatus {
+    pub running: bool,
+    // 本次演示生成的事件数量
+    pub generated: u64,
+    pub config: DemoConfig,
+}
+
+// 模拟用户，同一用户的名称和粉丝牌保持一致
+struct DemoUser {
+    uid: u64,
+    uname: &'static str,
+    guard_level: u8,
+    fans_medal_level: u32,
+}
+
+const USERS: &[DemoUser] = &[
+    DemoUser {
+        uid: 1,
+        uname: "路过的观众",
+        guard_level: 0,
+        fans_medal_level: 0,
+    },
+    DemoUser {
+        uid: 2,
+        uname: "今天也要早睡",
+        guard_level: 0,
+        fans_medal_level: 3,
+    },
+    DemoUser {
+        uid: 3,
+        uname: "奶茶三分糖",
+        guard_level: 0,
+        fans_medal_level: 8,
+    },
+    DemoUser {
+        uid: 4,
+        uname: "摸鱼小能手",
+        guard_level: 0,
+        fans_medal_level: 12,
+    },
+    DemoUser {
+        uid: 5,
+        uname: "星星点灯",
+        guard_level: 3,
+        fans_medal_level: 21,
+    },
+    DemoUser {
+        uid: 6,
+        uname: "一只咸鱼",
+        guard_level: 0,
+        fans_medal_level: 1,
+    },
+    DemoUser {
+        uid: 7,
+        uname: "深夜食堂",
+        guard_level: 3,
+        fans_medal_level: 24,
+    },
+    DemoUser {
+        uid: 8,
+        uname: "月色真美",
+        guard_level: 2,
+        fans_medal_level: 27,
+    },
+    DemoUser {
+        uid: 9,
+        uname: "不会起名字",
+        guard_level: 0,
+        fans_medal_level: 0,
+    },
+    DemoUser {
+        uid: 10,
+        uname: "舰长大人",
+        guard_level: 1,
+        fans_medal_level: 31,
+    },
+];
+
+const DANMAKU: &[&str] = &[
+    "来了来了",
+    "晚上好",
+    "哈哈哈哈哈",
+    "主播今天好早",
+    "？？？",
+    "好耶",
+    "草",
+    "这个操作可以",
+    "打卡",
+    "888888",
+    "主播唱首歌吧",
+    "下次一定",
+];
+
+const SUPER_CHAT: &[&str] = &[
+    "主播辛苦了，注意休息",
+    "第一次来，关注了",
+    "生日快乐！",
+    "能唱一首晴天吗",
+];
+
+// 礼物名称和单价，单位为元
+const GIFTS: &[(&str, f64)] = &[
+    ("小花花", 0.1),
+    ("牛哇牛哇", 0.1),
+    ("打call", 0.5),
+    ("这个好诶", 1.0),
+    ("礼花", 28.0),
+    ("告白气球", 52.0),
+    ("小电视飞船", 1245.0),
+];
+
+// 大航海等级、名称和单月价格
+const GUARDS: &[(u8, &str, f64)] = &[
+    (3, "舰长", 198.0),
+    (2, "提督", 1998.0),
+    (1, "总督", 19998.0),
+];
+
+const SUPER_CHAT_PRICES: &[f64] = &[30.0, 50.0, 100.0, 500.0];
+
+// 生成一个随机的模拟事件
+fn generate(kind: EventKind, room_id: u64) -> DanmakuEvent {
+    let mut rng = rand::thread_rng();
+    let user = USERS.choose(&mut rng).unwrap_or(&USERS[0]);
+    let mut event = DanmakuEvent {
+        id: String::new(),
+        kind,
+        room_id,
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        uid: DEMO_UID_BASE + user.uid,
+        open_id: None,
+        uname: user.uname.to_string(),
+        uface: None,
+        message: String::new(),
+        num: 1,
+        price: 0.0,
+        guard_level: user.guard_level,
+        fans_medal_level: user.fans_medal_level,
+        fans_medal_name: if user.fans_medal_level > 0 {
+            "演示".to_string()
+        } else {
+            String::new()
+        },
+        user_level: None,
+        msg_id: None,
+        emoji: None,
+        replay: false,
+        simulated: true,
+        muted: false,
+    };
+    match kind {
+        EventKind::Danmaku => {
+            event.message = DANMAKU
+                .choose(&mut rng)
+                .copied()
+                .unwrap_or_default()
+                .to_string();
+        }
+        EventKind::Gift => {
+            let (name, price) = GIFTS.choose(&mut rng).copied().unwrap_or(GIFTS[0]);
+            // 便宜的礼物更容易连续赠送多个
+            event.num = if price < 1.0 {
+                rng.gen_range(1..=10)
+            } else {
+                1
+            };
+            event.message = name.to_string();
+            event.price = price * event.num as f64;
+        }
+        EventKind::SuperChat => {
+            event.message = SUPER_CHAT
+                .choose(&mut rng)
+                .copied()
+                .unwrap_or_default()
+                .to_string();
+            event.price = SUPER_CHAT_PRICES.choose(&mut rng).copied().unwrap_or(30.0);
+        }
+        EventKind::Guard => {
+            // 舰长远多于提督和总督
+            let index = match rng.gen_range(0..100) {
+                0 => 2,
+                1..=9 => 1,
+                _ => 0,
+            };
+            let (level, name, price) = GUARDS[index];
+            event.message = name.to_string();
+            event.price = price;
+            event.guard_level = level;
+        }
+        EventKind::Like => event.num = rng.gen_range(1..=20),
+        EventKind::Enter => {}
+    }
+    event
+}
+
+// 用 JSON 对象中的字段覆盖生成的事件
+fn apply_overrides(event: DanmakuEvent, overrides: Value) -> AppResult<DanmakuEvent> {
+    let Value::Object(overrides) = overrides else {
+        return Err(AppError::InvalidConfig(
+            "事件字段必须是 JSON 对象".to_string(),
+        ));
+    };
+    let mut value = serde_json::to_value(&event)
+        .map_err(|err| AppError::InvalidConfig(format!("事件字段无效: {}", err)))?;
+    if let Value::Object(fields) = &mut value {
+        fields.extend(overrides);
+    }
+    let mut event: DanmakuEvent = serde_json::from_value(value)
+        .map_err(|err| AppError::InvalidConfig(format!("事件字段无效: {}", err)))?;
+    event.simulated = true;
+    event.replay = false;
+    Ok(event)
+}
+
+struct Shared {
+    app: AppHandle,
+    config: RwLock<DemoConfig>,
+    generated: AtomicU64,
+}
+
+impl Shared {
+    fn room_id(&self) -> u64 {
+        self.config.read().unwrap().room_id.unwrap_or_else(|| {
+            self.app
+                .try_state::<RoomManager>()
+                .and_then(|rooms| rooms.list_rooms().first().copied())
+                .unwrap_or(DEMO_ROOM_ID)
+        })
+    }
+
+    fn publish(&self, event: DanmakuEvent) -> AppResult<()> {
+        let rooms = self
+            .app
+            .try_state::<RoomManager>()
+            .ok_or_else(|| AppError::InvalidConfig("弹幕连接未初始化".to_string()))?;
+        rooms.publish_simulated(event);
+        self.generated.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+// 生成模拟的直播间事件，经过与真实事件相同的处理流程，用于不开播时测试浮窗、TTS 和自动化
+// 模拟事件不会保存、上传或计入统计
+pub struct DemoMode {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl DemoMode {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Ok(DemoMode {
+            shared: Arc::new(Shared {
+                app: app.clone(),
+                config: RwLock::new(config),
+                generated: AtomicU64::new(0),
+            }),
+            store,
+            task: Mutex::new(None),
+        })
+    }
+
+    pub fn status(&self) -> DemoStatus {
+        DemoStatus {
+            running: self.task.lock().unwrap().is_some(),
+            generated: self.shared.generated.load(Ordering::Relaxed),
+            config: self.shared.config.read().unwrap().clone(),
+        }
+    }
+
+    // 修改演示设置，运行中修改后立即按新的速率生成
+    pub fn update_config(&self, config: DemoConfig) -> AppResult<DemoStatus> {
+        if config
+            .rates()
+            .iter()
+            .any(|(_, rate)| !rate.is_finite() || !(0.0..=MAX_RATE).contains(rate))
+        {
+            return Err(AppError::InvalidConfig(format!(
+                "每分钟生成的事件数量应在 0 到 {} 之间",
+                MAX_RATE
+            )));
+        }
+        if config.room_id == Some(0) {
+            return Err(AppError::InvalidConfig("直播间号无效".to_string()));
+        }
+        *self.shared.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存演示设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化演示设置失败: {}", err),
+        }
+        Ok(self.status())
+    }
+
+    // 生成并分发一个模拟事件，overrides 中的字段会覆盖随机生成的值
+    pub fn emit_test_event(
+        &self,
+        kind: EventKind,
+        overrides: Option<Value>,
+    ) -> AppResult<DanmakuEvent> {
+        let mut event = generate(kind, self.shared.room_id());
+        if let Some(overrides) = overrides {
+            event = apply_overrides(event, overrides)?;
+        }
+        self.shared.publish(event.clone())?;
+        Ok(event)
+    }
+
+    pub fn start(&self) -> DemoStatus {
+        {
+            let mut task = self.task.lock().unwrap();
+            if task.is_none() {
+                self.shared.generated.store(0, Ordering::Relaxed);
+                *task = Some(tauri::async_runtime::spawn(run(self.shared.clone())));
+                log::info!("演示模式已开始");
+            }
+        }
+        let status = self.status();
+        let _ = self.shared.app.emit(STATE_EVENT, &status);
+        status
+    }
+
+    pub fn stop(&self) -> DemoStatus {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+            log::info!("演示模式已停止");
+        }
+        let status = self.status();
+        let _ = self.shared.app.emit(STATE_EVENT, &status);
+        status
+    }
+}
+
+// 按设置的速率随机生成事件，每个间隔内各类事件的生成概率为 速率 × 间隔
+async fn run(shared: Arc<Shared>) {
+    let mut ticker = tokio::time::interval(TICK_INTERVAL);
+    let tick_minutes = TICK_INTERVAL.as_secs_f64() / 60.0;
+    loop {
+        ticker.tick().await;
+        let rates = shared.config.read().unwrap().rates();
+        let room_id = shared.room_id();
+        for (kind, rate) in rates {
+            // 速率很高时一个间隔内可能生成多个事件
+            let expected = rate * tick_minutes;
+            let mut count = expected.floor() as u32;
+            if rand::thread_rng().gen_bool(expected.fract()) {
+                count += 1;
+            }
+            for _ in 0..count {
+                if let Err(err) = shared.publish(generate(kind, room_id)) {
+                    log::warn!("生成模拟事件失败: {}", err);
+                    return;
+                }
+            }
+        }
+    }
+}
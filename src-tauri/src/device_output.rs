@@ -0,0 +1,182 @@
+mod midi;
+mod signal;
+
+pub use midi::{MidiMessage, MidiPort};
+pub use signal::SignalClient;
+
+use crate::danmaku::DanmakuEvent;
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "device_output.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+const DEFAULT_SIGNAL_PORT: u16 = 23582;
+
+// 外部设备输出的配置，MIDI 设备在自动化操作中单独指定，不需要配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceOutputConfig {
+    // 启用后随应用启动信号服务器
+    pub signal_enabled: bool,
+    pub signal_port: u16,
+    // 允许局域网中的其他设备连接信号服务器
+    pub allow_lan: bool,
+}
+
+impl Default for DeviceOutputConfig {
+    fn default() -> Self {
+        DeviceOutputConfig {
+            signal_enabled: false,
+            signal_port: DEFAULT_SIGNAL_PORT,
+            allow_lan: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceOutputStatus {
+    #[serde(flatten)]
+    pub config: DeviceOutputConfig,
+    pub running: bool,
+    pub active_port: Option<u16>,
+    pub clients: Vec<SignalClient>,
+    // 已经打开连接的 MIDI 设备
+    pub midi_connections: Vec<String>,
+}
+
+// 发送给信号服务器客户端的信号
+#[derive(Debug, Clone, Serialize)]
+pub struct Signal {
+    pub name: String,
+    // 由自动化规则触发时为规则 ID
+    pub rule_id: Option<String>,
+    pub event: Option<DanmakuEvent>,
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+}
+
+// 自动化规则触发时控制外部设备：发送 MIDI 消息给灯光、音频软件，或通过 TCP 通知 Stream Deck 插件
+pub struct DeviceOutput {
+    midi: midi::MidiOutputs,
+    server: Arc<signal::SignalServer>,
+    store: Arc<Store<Wry>>,
+}
+
+impl DeviceOutput {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: DeviceOutputConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Ok(DeviceOutput {
+            midi: midi::MidiOutputs::default(),
+            server: Arc::new(signal::SignalServer::new(config)),
+            store,
+        })
+    }
+
+    // 配置为启用时在后台启动信号服务器
+    pub fn auto_start(&self) {
+        if !self.server.config.read().unwrap().signal_enabled {
+            return;
+        }
+        let server = self.server.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = server.start().await {
+                log::warn!("设备信号服务器启动失败: {}", err);
+            }
+        });
+    }
+
+    pub async fn start(&self) -> AppResult<DeviceOutputStatus> {
+        self.server.start().await?;
+        Ok(self.status())
+    }
+
+    pub async fn stop(&self) -> AppResult<DeviceOutputStatus> {
+        self.server.stop().await?;
+        Ok(self.status())
+    }
+
+    // 保存配置，按新配置重新启动或停止信号服务器
+    pub async fn update_config(&self, config: DeviceOutputConfig) -> AppResult<DeviceOutputStatus> {
+        if config.signal_port == 0 {
+            return Err(AppError::InvalidConfig("端口不能为 0".to_string()));
+        }
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存设备输出配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化设备输出配置失败: {}", err),
+        }
+        let enabled = config.signal_enabled;
+        *self.server.config.write().unwrap() = config;
+
+        if self.server.is_running().await {
+            self.server.stop().await?;
+        }
+        if enabled {
+            self.server.start().await?;
+        }
+        Ok(self.status())
+    }
+
+    pub fn status(&self) -> DeviceOutputStatus {
+        let active_port = self.server.active_port();
+        DeviceOutputStatus {
+            config: self.server.config.read().unwrap().clone(),
+            running: active_port.is_some(),
+            active_port,
+            clients: self.server.clients(),
+            midi_connections: self.midi.connected(),
+        }
+    }
+
+    pub fn midi_ports(&self) -> AppResult<Vec<MidiPort>> {
+        self.midi.ports()
+    }
+
+    pub fn send_midi(&self, port: &str, message: &MidiMessage) -> AppResult<()> {
+        self.midi.send(port, message)
+    }
+
+    // 关闭所有 MIDI 连接，下次发送时重新打开，用于设备重新插入后刷新
+    pub fn close_midi(&self) {
+        self.midi.close_all();
+    }
+
+    pub fn signal_clients(&self) -> Vec<SignalClient> {
+        self.server.clients()
+    }
+
+    // 向信号服务器的所有客户端发送信号，返回收到信号的客户端数量
+    pub fn send_signal(
+        &self,
+        name: &str,
+        rule_id: Option<&str>,
+        event: Option<&DanmakuEvent>,
+    ) -> AppResult<usize> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::InvalidConfig("信号名称不能为空".to_string()));
+        }
+        if self.server.active_port().is_none() {
+            return Err(AppError::NotRunning);
+        }
+        Ok(self.server.send(&Signal {
+            name: name.to_string(),
+            rule_id: rule_id.map(str::to_string),
+            event: event.cloned(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        }))
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::{AppError, AppResult};
+use midir::{MidiOutput, MidiOutputConnection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// 在系统 MIDI 设备列表中显示的客户端名称
+const CLIENT_NAME: &str = "VTsuru Fetcher";
+const CONNECTION_NAME: &str = "vtsuru-output";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MidiPort {
+    pub name: String,
+    // 是否已经打开连接
+    pub connected: bool,
+}
+
+// 发送给 MIDI 设备的消息，channel 为 1 到 16，其余数值为 0 到 127
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MidiMessage {
+    NoteOn {
+        channel: u8,
+        note: u8,
+        #[serde(default = "default_velocity")]
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+}
+
+fn default_velocity() -> u8 {
+    127
+}
+
+impl MidiMessage {
+    fn bytes(&self) -> AppResult<Vec<u8>> {
+        let (status, channel, data) = match *self {
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => (0x90, channel, vec![note, velocity]),
+            MidiMessage::NoteOff { channel, note } => (0x80, channel, vec![note, 0]),
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => (0xB0, channel, vec![controller, value]),
+            MidiMessage::ProgramChange { channel, program } => (0xC0, channel, vec![program]),
+        };
+        if !(1..=16).contains(&channel) {
+            return Err(AppError::InvalidConfig(
+                "MIDI 通道应在 1 到 16 之间".to_string(),
+            ));
+        }
+        if data.iter().any(|value| *value > 127) {
+            return Err(AppError::InvalidConfig(
+                "MIDI 数值应在 0 到 127 之间".to_string(),
+            ));
+        }
+        let mut bytes = vec![status | (channel - 1)];
+        bytes.extend(data);
+        Ok(bytes)
+    }
+}
+
+// 打开的 MIDI 输出设备，按设备名称复用连接
+#[derive(Default)]
+pub(super) struct MidiOutputs {
+    connections: Mutex<HashMap<String, MidiOutputConnection>>,
+}
+
+impl MidiOutputs {
+    // 系统中的 MIDI 输出设备
+    pub fn ports(&self) -> AppResult<Vec<MidiPort>> {
+        let output = open()?;
+        let connections = self.connections.lock().unwrap();
+        Ok(output
+            .ports()
+            .iter()
+            .filter_map(|port| output.port_name(port).ok())
+            .map(|name| MidiPort {
+                connected: connections.contains_key(&name),
+                name,
+            })
+            .collect())
+    }
+
+    // 已经打开连接的设备名称
+    pub fn connected(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.connections.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    // 第一次发送时打开设备，之后复用连接
+    pub fn send(&self, port: &str, message: &MidiMessage) -> AppResult<()> {
+        let port = port.trim();
+        if port.is_empty() {
+            return Err(AppError::InvalidConfig("请选择 MIDI 设备".to_string()));
+        }
+        let bytes = message.bytes()?;
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(connection) = connections.get_mut(port) {
+            if connection.send(&bytes).is_ok() {
+                return Ok(());
+            }
+            // 设备可能已经拔出，丢弃旧连接后重新打开
+            connections.remove(port);
+        }
+        let mut connection = connect(port)?;
+        connection
+            .send(&bytes)
+            .map_err(|err| AppError::Midi(err.to_string()))?;
+        log::info!("已打开 MIDI 设备: {}", port);
+        connections.insert(port.to_string(), connection);
+        Ok(())
+    }
+
+    pub fn close_all(&self) {
+        for (_, connection) in self.connections.lock().unwrap().drain() {
+            connection.close();
+        }
+    }
+}
+
+fn open() -> AppResult<MidiOutput> {
+    MidiOutput::new(CLIENT_NAME).map_err(|err| AppError::Midi(err.to_string()))
+}
+
+fn connect(name: &str) -> AppResult<MidiOutputConnection> {
+    let output = open()?;
+    let port = output
+        .ports()
+        .into_iter()
+        .find(|port| {
+            output
+                .port_name(port)
+                .is_ok_and(|port_name| port_name == name)
+        })
+        .ok_or_else(|| AppError::MidiPortNotFound(name.to_string()))?;
+    output
+        .connect(&port, CONNECTION_NAME)
+        .map_err(|err| AppError::Midi(err.to_string()))
+}
@@ -0,0 +1,241 @@
+use super::{DeviceOutputConfig, Signal};
+use crate::error::{AppError, AppResult};
+use crate::file_server::port;
+use crate::file_server::{BIND_ALL_INTERFACES, BIND_LOCALHOST};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+const CHANNEL_CAPACITY: usize = 256;
+// 停止服务器时等待连接关闭的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 当前连接的客户端
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalClient {
+    #[serde(skip)]
+    id: u64,
+    pub addr: String,
+    // Unix 毫秒时间戳
+    pub connected_at: i64,
+}
+
+// 服务器发送的消息，每行一个 JSON
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    // 连接后立即发送
+    Hello { version: &'static str },
+    Signal(&'a Signal),
+    Pong,
+    Error { message: String },
+}
+
+// 客户端发送的消息，例如 {"type":"ping"}
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Ping,
+}
+
+struct Running {
+    cancel: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+// 以换行分隔的 JSON 推送信号的 TCP 服务器，供 Stream Deck 插件等不支持 WebSocket 的工具连接
+pub(super) struct SignalServer {
+    pub config: RwLock<DeviceOutputConfig>,
+    // 已经编码好的消息行
+    lines: broadcast::Sender<Arc<String>>,
+    clients: Mutex<Vec<SignalClient>>,
+    next_client_id: AtomicU64,
+    running: tokio::sync::Mutex<Option<Running>>,
+    active_port: Mutex<Option<u16>>,
+}
+
+impl SignalServer {
+    pub fn new(config: DeviceOutputConfig) -> Self {
+        let (lines, _) = broadcast::channel(CHANNEL_CAPACITY);
+        SignalServer {
+            config: RwLock::new(config),
+            lines,
+            clients: Mutex::new(Vec::new()),
+            next_client_id: AtomicU64::new(0),
+            running: tokio::sync::Mutex::new(None),
+            active_port: Mutex::new(None),
+        }
+    }
+
+    pub async fn start(self: &Arc<Self>) -> AppResult<()> {
+        let mut running = self.running.lock().await;
+        if running.is_some() {
+            return Err(AppError::AlreadyRunning);
+        }
+        let config = self.config.read().unwrap().clone();
+        let ip: IpAddr = if config.allow_lan {
+            BIND_ALL_INTERFACES
+        } else {
+            BIND_LOCALHOST
+        }
+        .parse()
+        .map_err(|_| AppError::InvalidConfig("无效的监听地址".to_string()))?;
+        let listener = port::bind(ip, config.signal_port, false).await?;
+        let addr = listener.local_addr().map_err(AppError::Bind)?;
+        log::info!("设备信号服务器启动在 tcp://{}", addr);
+
+        let cancel = CancellationToken::new();
+        let task = tauri::async_runtime::spawn(serve(listener, self.clone(), cancel.clone()));
+        *running = Some(Running { cancel, task });
+        *self.active_port.lock().unwrap() = Some(addr.port());
+        Ok(())
+    }
+
+    pub async fn stop(&self) -> AppResult<()> {
+        let Some(running) = self.running.lock().await.take() else {
+            return Err(AppError::NotRunning);
+        };
+        running.cancel.cancel();
+        let abort_handle = running.task.inner().abort_handle();
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, running.task)
+            .await
+            .is_err()
+        {
+            abort_handle.abort();
+        }
+        *self.active_port.lock().unwrap() = None;
+        log::info!("设备信号服务器已停止");
+        Ok(())
+    }
+
+    pub async fn is_running(&self) -> bool {
+        self.running.lock().await.is_some()
+    }
+
+    pub fn active_port(&self) -> Option<u16> {
+        *self.active_port.lock().unwrap()
+    }
+
+    pub fn clients(&self) -> Vec<SignalClient> {
+        self.clients.lock().unwrap().clone()
+    }
+
+    // 发送给所有客户端，返回收到信号的客户端数量
+    pub fn send(&self, signal: &Signal) -> usize {
+        match encode(&ServerMessage::Signal(signal)) {
+            // 没有客户端时发送会失败
+            Some(line) => self.lines.send(Arc::new(line)).unwrap_or(0),
+            None => 0,
+        }
+    }
+}
+
+async fn serve(listener: TcpListener, server: Arc<SignalServer>, cancel: CancellationToken) {
+    loop {
+        let accepted = tokio::select! {
+            _ = cancel.cancelled() => break,
+            accepted = listener.accept() => accepted,
+        };
+        match accepted {
+            Ok((stream, addr)) => {
+                tauri::async_runtime::spawn(handle_client(
+                    server.clone(),
+                    stream,
+                    addr,
+                    cancel.child_token(),
+                ));
+            }
+            Err(err) => log::warn!("接受设备信号连接失败: {}", err),
+        }
+    }
+}
+
+async fn handle_client(
+    server: Arc<SignalServer>,
+    stream: TcpStream,
+    addr: SocketAddr,
+    cancel: CancellationToken,
+) {
+    let id = server.next_client_id.fetch_add(1, Ordering::Relaxed);
+    server.clients.lock().unwrap().push(SignalClient {
+        id,
+        addr: addr.to_string(),
+        connected_at: chrono::Utc::now().timestamp_millis(),
+    });
+    log::info!("设备信号客户端已连接: {}", addr);
+
+    let mut signals = server.lines.subscribe();
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader).lines();
+    let hello = ServerMessage::Hello {
+        version: env!("CARGO_PKG_VERSION"),
+    };
+    if write(&mut writer, &hello).await {
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                line = signals.recv() => match line {
+                    Ok(line) => {
+                        if writer.write_all(line.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("设备信号客户端 {} 处理过慢，跳过了 {} 个信号", addr, skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                line = reader.next_line() => match line {
+                    Ok(Some(line)) if line.trim().is_empty() => {}
+                    Ok(Some(line)) => {
+                        let reply = match serde_json::from_str::<ClientMessage>(line.trim()) {
+                            Ok(ClientMessage::Ping) => ServerMessage::Pong,
+                            Err(err) => ServerMessage::Error { message: err.to_string() },
+                        };
+                        if !write(&mut writer, &reply).await {
+                            break;
+                        }
+                    }
+                    Ok(None) | Err(_) => break,
+                },
+            }
+        }
+    }
+
+    let _ = writer.shutdown().await;
+    server
+        .clients
+        .lock()
+        .unwrap()
+        .retain(|client| client.id != id);
+    log::info!("设备信号客户端已断开: {}", addr);
+}
+
+// 写入一行消息，连接断开时返回 false
+async fn write(writer: &mut OwnedWriteHalf, message: &ServerMessage<'_>) -> bool {
+    match encode(message) {
+        Some(line) => writer.write_all(line.as_bytes()).await.is_ok(),
+        None => true,
+    }
+}
+
+fn encode(message: &ServerMessage<'_>) -> Option<String> {
+    match serde_json::to_string(message) {
+        Ok(mut line) => {
+            line.push('\n');
+            Some(line)
+        }
+        Err(err) => {
+            log::warn!("序列化设备信号失败: {}", err);
+            None
+        }
+    }
+}
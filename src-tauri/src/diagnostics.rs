@@ -0,0 +1,270 @@
+use crate::danmaku::RoomManager;
+use crate::error::{AppError, AppResult};
+use crate::relay::Relay;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use serde::Serialize;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_http::reqwest::{Client, Url};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+// 获取弹幕期间定时发送的延迟事件
+pub const LATENCY_EVENT: &str = "diagnostics://latency";
+
+// B 站接口和默认弹幕服务器
+const BILIBILI_API: &str = "https://api.live.bilibili.com";
+const BILIBILI_DANMAKU: &str = "https://broadcastlv.chat.bilibili.com";
+
+// 每个检测步骤的超时时间
+const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+// 获取弹幕期间测量延迟的间隔
+const LATENCY_INTERVAL: Duration = Duration::from_secs(30);
+
+// 对一个地址的检测结果，耗时单位为毫秒，失败之后的步骤为空
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    pub name: String,
+    pub url: String,
+    pub address: Option<String>,
+    pub dns_ms: Option<u64>,
+    pub tcp_ms: Option<u64>,
+    pub tls_ms: Option<u64>,
+    pub http_ms: Option<u64>,
+    pub http_status: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkReport {
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    pub results: Vec<ProbeResult>,
+}
+
+// 定时测量的 TCP 连接延迟，连接失败时为空
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySample {
+    pub name: String,
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyReport {
+    pub timestamp: i64,
+    pub samples: Vec<LatencySample>,
+}
+
+struct Target {
+    name: String,
+    url: Url,
+}
+
+// 检测到 B 站和 vtsuru 服务器的网络状况，排查获取或上传失败的原因
+pub struct NetworkDiagnostics {
+    app: AppHandle,
+    http: Client,
+    tls: TlsConnector,
+}
+
+impl NetworkDiagnostics {
+    pub fn new(app: &AppHandle) -> Self {
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let diagnostics = NetworkDiagnostics {
+            app: app.clone(),
+            http: Client::builder()
+                .timeout(STEP_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            tls: TlsConnector::from(Arc::new(config)),
+        };
+        tauri::async_runtime::spawn(watch_latency(app.clone()));
+        diagnostics
+    }
+
+    // 检测默认地址和额外指定的地址
+    pub async fn run_check(&self, extra: Vec<String>) -> AppResult<NetworkReport> {
+        let mut targets = self.default_targets();
+        for url in extra {
+            let parsed = Url::parse(url.trim())
+                .map_err(|_| AppError::InvalidConfig(format!("无效的地址: {}", url)))?;
+            targets.push(Target {
+                name: url,
+                url: parsed,
+            });
+        }
+
+        let results =
+            futures_util::future::join_all(targets.iter().map(|target| self.probe(target))).await;
+        Ok(NetworkReport {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            results,
+        })
+    }
+
+    // B 站接口、弹幕服务器，以及配置了上传时的 vtsuru 接口
+    fn default_targets(&self) -> Vec<Target> {
+        let mut targets = vec![
+            Target {
+                name: "B 站接口".to_string(),
+                url: Url::parse(BILIBILI_API).expect("无效的默认地址"),
+            },
+            Target {
+                name: "B 站弹幕服务器".to_string(),
+                url: Url::parse(BILIBILI_DANMAKU).expect("无效的默认地址"),
+            },
+        ];
+        if let Some(relay) = self.app.try_state::<Relay>() {
+            if let Ok(url) = Url::parse(&relay.get_config().endpoint) {
+                targets.push(Target {
+                    name: "vtsuru 接口".to_string(),
+                    url,
+                });
+            }
+        }
+        targets
+    }
+
+    // 依次检测 DNS 解析、TCP 连接、TLS 握手和 HTTP 请求，任一步失败时停止
+    async fn probe(&self, target: &Target) -> ProbeResult {
+        let mut result = ProbeResult {
+            name: target.name.clone(),
+            url: target.url.to_string(),
+            address: None,
+            dns_ms: None,
+            tcp_ms: None,
+            tls_ms: None,
+            http_ms: None,
+            http_status: None,
+            error: None,
+        };
+        if let Err(err) = self.run_steps(target, &mut result).await {
+            result.error = Some(err.to_string());
+        }
+        result
+    }
+
+    async fn run_steps(&self, target: &Target, result: &mut ProbeResult) -> AppResult<()> {
+        let host = target
+            .url
+            .host_str()
+            .ok_or_else(|| AppError::InvalidConfig("地址中没有主机名".to_string()))?
+            .to_string();
+        let port = target.url.port_or_known_default().unwrap_or(443);
+
+        let started = Instant::now();
+        let address = timed(resolve(&host, port)).await?;
+        result.dns_ms = Some(elapsed_ms(started));
+        result.address = Some(address.to_string());
+
+        let started = Instant::now();
+        let stream = timed(async {
+            TcpStream::connect(address).await.map_err(|err| {
+                AppError::Io(io::Error::new(err.kind(), format!("TCP 连接失败: {}", err)))
+            })
+        })
+        .await?;
+        result.tcp_ms = Some(elapsed_ms(started));
+
+        if target.url.scheme() == "https" {
+            let server_name = ServerName::try_from(host.clone())
+                .map_err(|_| AppError::Tls("无效的主机名".to_string()))?;
+            let started = Instant::now();
+            timed(async {
+                self.tls
+                    .connect(server_name, stream)
+                    .await
+                    .map_err(|err| AppError::Tls(format!("TLS 握手失败: {}", err)))
+            })
+            .await?;
+            result.tls_ms = Some(elapsed_ms(started));
+        }
+
+        // 服务器返回任何状态码都说明网络可达
+        let started = Instant::now();
+        let response = self.http.get(target.url.clone()).send().await?;
+        result.http_ms = Some(elapsed_ms(started));
+        result.http_status = Some(response.status().as_u16());
+        Ok(())
+    }
+}
+
+// 有直播间在获取弹幕时定时测量到 B 站服务器的连接延迟
+async fn watch_latency(app: AppHandle) {
+    let mut interval = tokio::time::interval(LATENCY_INTERVAL);
+    loop {
+        interval.tick().await;
+        let fetching = app
+            .try_state::<RoomManager>()
+            .is_some_and(|rooms| !rooms.list_rooms().is_empty());
+        if !fetching {
+            continue;
+        }
+        let mut samples = Vec::new();
+        for (name, url) in [
+            ("B 站接口", BILIBILI_API),
+            ("B 站弹幕服务器", BILIBILI_DANMAKU),
+        ] {
+            samples.push(LatencySample {
+                name: name.to_string(),
+                latency_ms: connect_latency(url).await,
+            });
+        }
+        let _ = app.emit(
+            LATENCY_EVENT,
+            &LatencyReport {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                samples,
+            },
+        );
+    }
+}
+
+async fn connect_latency(url: &str) -> Option<u64> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    let address = timed(resolve(host, url.port_or_known_default()?))
+        .await
+        .ok()?;
+    let started = Instant::now();
+    tokio::time::timeout(STEP_TIMEOUT, TcpStream::connect(address))
+        .await
+        .ok()?
+        .ok()?;
+    Some(elapsed_ms(started))
+}
+
+// 使用和实际连接相同的 DNS 设置
+async fn resolve(host: &str, port: u16) -> AppResult<SocketAddr> {
+    crate::dns::resolve(host, port)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            AppError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("DNS 解析没有返回 {} 的地址", host),
+            ))
+        })
+}
+
+async fn timed<T>(future: impl std::future::Future<Output = AppResult<T>>) -> AppResult<T> {
+    tokio::time::timeout(STEP_TIMEOUT, future)
+        .await
+        .map_err(|_| AppError::Timeout {
+            secs: STEP_TIMEOUT.as_secs(),
+        })?
+}
+
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
@@ -0,0 +1,288 @@
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_http::reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use tauri_plugin_http::reqwest::Client;
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存 DNS 配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "dns.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 默认使用阿里 DNS 的 JSON 接口，直接用 IP 访问避免解析 DoH 服务器本身
+const DEFAULT_DOH_URL: &str = "https://223.5.5.5/resolve";
+const DOH_TIMEOUT: Duration = Duration::from_secs(5);
+// DoH 结果的缓存时间范围，实际时间取记录的 TTL
+const MIN_CACHE_TTL: u64 = 60;
+const MAX_CACHE_TTL: u64 = 3600;
+
+// DNS 记录类型
+const TYPE_A: u64 = 1;
+const TYPE_AAAA: u64 = 28;
+
+// 当前生效的配置，所有出站连接在解析域名时读取
+static CURRENT: RwLock<Option<DnsConfig>> = RwLock::new(None);
+static CACHE: Mutex<Option<HashMap<String, CacheEntry>>> = Mutex::new(None);
+static DOH_CLIENT: OnceLock<Client> = OnceLock::new();
+
+// 把域名固定解析到指定的 IP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostOverride {
+    // 完整域名，或以 *. 开头匹配所有子域名
+    pub host: String,
+    pub ips: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsConfig {
+    pub overrides: Vec<HostOverride>,
+    // 使用 DNS over HTTPS 解析没有固定的域名，失败时回退到系统 DNS
+    pub doh_enabled: bool,
+    // 兼容 Google JSON 格式的 DoH 地址
+    pub doh_url: String,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        DnsConfig {
+            overrides: Vec::new(),
+            doh_enabled: false,
+            doh_url: DEFAULT_DOH_URL.to_string(),
+        }
+    }
+}
+
+impl DnsConfig {
+    fn override_for(&self, host: &str) -> Option<Vec<IpAddr>> {
+        let host = host.trim_end_matches('.').to_lowercase();
+        self.overrides
+            .iter()
+            .find(|entry| {
+                let pattern = entry.host.trim().to_lowercase();
+                match pattern.strip_prefix("*.") {
+                    Some(domain) => host.ends_with(&format!(".{}", domain)),
+                    None => pattern == host,
+                }
+            })
+            .map(|entry| {
+                entry
+                    .ips
+                    .iter()
+                    .filter_map(|ip| ip.trim().parse().ok())
+                    .collect()
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolveSource {
+    Override,
+    Doh,
+    System,
+}
+
+// 测试解析的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolveResult {
+    pub host: String,
+    pub source: ResolveSource,
+    pub addresses: Vec<String>,
+}
+
+struct CacheEntry {
+    ips: Vec<IpAddr>,
+    expires_at: Instant,
+}
+
+// DNS 设置，固定解析用于绕过运营商 DNS 污染
+pub struct DnsSettings {
+    config: RwLock<DnsConfig>,
+    store: Arc<Store<Wry>>,
+}
+
+impl DnsSettings {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: DnsConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        *CURRENT.write().unwrap() = Some(config.clone());
+        Ok(DnsSettings {
+            config: RwLock::new(config),
+            store,
+        })
+    }
+
+    pub fn get_config(&self) -> DnsConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: DnsConfig) -> AppResult<DnsConfig> {
+        for entry in &config.overrides {
+            if entry.host.trim().is_empty() || entry.ips.is_empty() {
+                return Err(AppError::InvalidConfig(
+                    "固定解析必须填写域名和 IP".to_string(),
+                ));
+            }
+            if let Some(ip) = entry
+                .ips
+                .iter()
+                .find(|ip| ip.trim().parse::<IpAddr>().is_err())
+            {
+                return Err(AppError::InvalidConfig(format!("无效的 IP 地址: {}", ip)));
+            }
+        }
+        if config.doh_enabled && !config.doh_url.starts_with("https://") {
+            return Err(AppError::InvalidConfig(
+                "DoH 地址必须以 https:// 开头".to_string(),
+            ));
+        }
+
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存 DNS 配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化 DNS 配置失败: {}", err),
+        }
+        *CURRENT.write().unwrap() = Some(config.clone());
+        *CACHE.lock().unwrap() = None;
+        *self.config.write().unwrap() = config.clone();
+        Ok(config)
+    }
+}
+
+// 按当前配置解析域名，依次使用固定解析、DoH 和系统 DNS
+pub async fn lookup(host: &str) -> AppResult<ResolveResult> {
+    let config = CURRENT.read().unwrap().clone().unwrap_or_default();
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(result(host, ResolveSource::System, vec![ip]));
+    }
+    if let Some(ips) = config.override_for(host) {
+        return Ok(result(host, ResolveSource::Override, ips));
+    }
+    if config.doh_enabled {
+        match doh_lookup(&config.doh_url, host).await {
+            Ok(ips) if !ips.is_empty() => return Ok(result(host, ResolveSource::Doh, ips)),
+            Ok(_) => log::warn!("DoH 没有返回 {} 的地址，使用系统 DNS", host),
+            Err(err) => log::warn!("DoH 解析 {} 失败，使用系统 DNS: {}", host, err),
+        }
+    }
+    let ips = tokio::net::lookup_host((host, 0))
+        .await?
+        .map(|addr| addr.ip())
+        .collect();
+    Ok(result(host, ResolveSource::System, ips))
+}
+
+// 解析域名并加上端口
+pub async fn resolve(host: &str, port: u16) -> AppResult<Vec<SocketAddr>> {
+    let resolved = lookup(host).await?;
+    let addrs: Vec<SocketAddr> = resolved
+        .addresses
+        .iter()
+        .filter_map(|ip| ip.parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, port))
+        .collect();
+    if addrs.is_empty() {
+        return Err(AppError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("无法解析域名: {}", host),
+        )));
+    }
+    Ok(addrs)
+}
+
+fn result(host: &str, source: ResolveSource, ips: Vec<IpAddr>) -> ResolveResult {
+    ResolveResult {
+        host: host.to_string(),
+        source,
+        addresses: ips.iter().map(IpAddr::to_string).collect(),
+    }
+}
+
+async fn doh_lookup(doh_url: &str, host: &str) -> AppResult<Vec<IpAddr>> {
+    let key = host.to_lowercase();
+    if let Some(entry) = CACHE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|cache| cache.get(&key))
+    {
+        if entry.expires_at > Instant::now() {
+            return Ok(entry.ips.clone());
+        }
+    }
+
+    let client = DOH_CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(DOH_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    });
+    let mut ips = Vec::new();
+    let mut ttl = MAX_CACHE_TTL;
+    for record_type in [TYPE_A, TYPE_AAAA] {
+        let type_param = record_type.to_string();
+        let body: Value = client
+            .get(doh_url)
+            .query(&[("name", host), ("type", type_param.as_str())])
+            .header("Accept", "application/dns-json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        // CNAME 等其他类型的记录直接跳过
+        for answer in body["Answer"].as_array().into_iter().flatten() {
+            if answer["type"].as_u64() != Some(record_type) {
+                continue;
+            }
+            if let Some(ip) = answer["data"].as_str().and_then(|data| data.parse().ok()) {
+                ips.push(ip);
+                ttl = ttl.min(answer["TTL"].as_u64().unwrap_or(MIN_CACHE_TTL));
+            }
+        }
+    }
+
+    if !ips.is_empty() {
+        CACHE
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(
+                key,
+                CacheEntry {
+                    ips: ips.clone(),
+                    expires_at: Instant::now()
+                        + Duration::from_secs(ttl.clamp(MIN_CACHE_TTL, MAX_CACHE_TTL)),
+                },
+            );
+    }
+    Ok(ips)
+}
+
+// 供 HTTP 客户端使用的解析器
+pub struct Resolver;
+
+impl Resolve for Resolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            // 端口由 HTTP 客户端填写
+            let addrs = resolve(&host, 0).await?;
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(addrs)
+        })
+    }
+}
@@ -0,0 +1,251 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use std::io;
+
+// 后端命令返回给前端的错误
+// 序列化为 { code, message, details }，前端根据 code 做翻译和处理，message 仅作兜底显示
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("文件服务器不存在: {0}")]
+    ServerNotFound(String),
+    #[error("文件服务器已存在: {0}")]
+    ServerExists(String),
+    #[error("不能删除默认文件服务器")]
+    DefaultServerProtected,
+    #[error("服务器已经在运行中")]
+    AlreadyRunning,
+    #[error("服务器未运行")]
+    NotRunning,
+    #[error("文件夹不存在: {0}")]
+    FolderNotFound(String),
+    #[error("{}", port_in_use_message(*port, *pid, process_name.as_deref()))]
+    PortInUse {
+        port: u16,
+        pid: Option<u32>,
+        process_name: Option<String>,
+    },
+    #[error("无法启动服务器: {0}")]
+    Bind(io::Error),
+    #[error("{0}")]
+    InvalidConfig(String),
+    #[error("{0}")]
+    Tls(String),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("网络请求失败: {0}")]
+    Http(#[from] tauri_plugin_http::reqwest::Error),
+    #[error("超过 {secs} 秒没有响应")]
+    Timeout { secs: u64 },
+    #[error("B 站接口返回错误 ({code}): {message}")]
+    BilibiliApi { code: i64, message: String },
+    #[error("B 站接口请求过于频繁，{retry_after} 秒后重试")]
+    RateLimited { group: String, retry_after: u64 },
+    #[error("上传事件失败: {0}")]
+    Relay(String),
+    #[error("直播间已添加: {0}")]
+    RoomExists(u64),
+    #[error("直播间不存在: {0}")]
+    RoomNotFound(u64),
+    #[error("弹幕连接错误: {0}")]
+    WebSocket(String),
+    #[error("弹幕发送失败: {message}")]
+    DanmakuRejected {
+        // not_logged_in、too_fast、muted、level_restricted、filtered 或 other
+        reason: &'static str,
+        message: String,
+    },
+    #[error("开播操作失败: {0}")]
+    LiveControl(String),
+    #[error("账号不存在: {0}")]
+    CredentialNotFound(String),
+    #[error("账号凭据错误: {0}")]
+    Credential(String),
+    #[error("插件不存在: {0}")]
+    PluginNotFound(String),
+    #[error("插件错误: {0}")]
+    Plugin(String),
+    #[error("未连接到 OBS")]
+    ObsNotConnected,
+    #[error("OBS 错误: {0}")]
+    Obs(String),
+    #[error("自动化规则不存在: {0}")]
+    AutomationRuleNotFound(String),
+    #[error("自动化操作失败: {0}")]
+    Automation(String),
+    #[error("webhook 不存在: {0}")]
+    WebhookNotFound(String),
+    #[error("语音朗读失败: {0}")]
+    Tts(String),
+    #[error("音频播放失败: {0}")]
+    Audio(String),
+    #[error("MIDI 设备不存在: {0}")]
+    MidiPortNotFound(String),
+    #[error("MIDI 输出失败: {0}")]
+    Midi(String),
+    #[error("点歌不存在: {0}")]
+    SongRequestNotFound(String),
+    #[error("排队记录不存在: {0}")]
+    QueueEntryNotFound(String),
+    #[error("已有进行中的投票")]
+    PollInProgress,
+    #[error("当前没有进行中的投票")]
+    NoActivePoll,
+    #[error("已有进行中的抽奖")]
+    RaffleInProgress,
+    #[error("当前没有进行中的抽奖")]
+    NoActiveRaffle,
+    #[error("观众不存在: {0}")]
+    ViewerNotFound(String),
+    #[error("审核规则不存在: {0}")]
+    ModerationRuleNotFound(String),
+    #[error("标记的事件不存在: {0}")]
+    FlaggedEventNotFound(String),
+    #[error("直播记录不存在: {0}")]
+    SessionNotFound(i64),
+    #[error("弹幕录制不存在: {0}")]
+    RecordingNotFound(String),
+    #[error("检查更新失败: {0}")]
+    Update(String),
+    #[error("计划任务不存在: {0}")]
+    ScheduledTaskNotFound(String),
+    #[error("发送系统通知失败: {0}")]
+    Notification(String),
+    #[error("开机启动设置失败: {0}")]
+    Autostart(String),
+    #[error("资源缓存错误: {0}")]
+    Asset(String),
+    #[error("配置方案不存在: {0}")]
+    ProfileNotFound(String),
+    #[error("数据库错误: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("图片处理失败: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for AppError {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        AppError::WebSocket(err.to_string())
+    }
+}
+
+impl AppError {
+    // 稳定的错误代码，前端依赖这些值，不要修改已有的代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::ServerNotFound(_) => "SERVER_NOT_FOUND",
+            AppError::ServerExists(_) => "SERVER_EXISTS",
+            AppError::DefaultServerProtected => "DEFAULT_SERVER_PROTECTED",
+            AppError::AlreadyRunning => "ALREADY_RUNNING",
+            AppError::NotRunning => "NOT_RUNNING",
+            AppError::FolderNotFound(_) => "FOLDER_NOT_FOUND",
+            AppError::PortInUse { .. } => "PORT_IN_USE",
+            AppError::Bind(_) => "BIND_FAILED",
+            AppError::InvalidConfig(_) => "INVALID_CONFIG",
+            AppError::Tls(_) => "TLS_ERROR",
+            AppError::Io(_) => "IO_ERROR",
+            AppError::Http(_) => "HTTP_ERROR",
+            AppError::Timeout { .. } => "TIMEOUT",
+            AppError::BilibiliApi { .. } => "BILIBILI_API_ERROR",
+            AppError::RateLimited { .. } => "RATE_LIMITED",
+            AppError::Relay(_) => "RELAY_ERROR",
+            AppError::RoomExists(_) => "ROOM_EXISTS",
+            AppError::RoomNotFound(_) => "ROOM_NOT_FOUND",
+            AppError::WebSocket(_) => "WEBSOCKET_ERROR",
+            AppError::DanmakuRejected { .. } => "DANMAKU_REJECTED",
+            AppError::LiveControl(_) => "LIVE_CONTROL_ERROR",
+            AppError::CredentialNotFound(_) => "CREDENTIAL_NOT_FOUND",
+            AppError::Credential(_) => "CREDENTIAL_ERROR",
+            AppError::PluginNotFound(_) => "PLUGIN_NOT_FOUND",
+            AppError::Plugin(_) => "PLUGIN_ERROR",
+            AppError::ObsNotConnected => "OBS_NOT_CONNECTED",
+            AppError::Obs(_) => "OBS_ERROR",
+            AppError::AutomationRuleNotFound(_) => "AUTOMATION_RULE_NOT_FOUND",
+            AppError::Automation(_) => "AUTOMATION_ERROR",
+            AppError::WebhookNotFound(_) => "WEBHOOK_NOT_FOUND",
+            AppError::Tts(_) => "TTS_ERROR",
+            AppError::Audio(_) => "AUDIO_ERROR",
+            AppError::MidiPortNotFound(_) => "MIDI_PORT_NOT_FOUND",
+            AppError::Midi(_) => "MIDI_ERROR",
+            AppError::SongRequestNotFound(_) => "SONG_REQUEST_NOT_FOUND",
+            AppError::QueueEntryNotFound(_) => "QUEUE_ENTRY_NOT_FOUND",
+            AppError::PollInProgress => "POLL_IN_PROGRESS",
+            AppError::NoActivePoll => "NO_ACTIVE_POLL",
+            AppError::RaffleInProgress => "RAFFLE_IN_PROGRESS",
+            AppError::NoActiveRaffle => "NO_ACTIVE_RAFFLE",
+            AppError::ViewerNotFound(_) => "VIEWER_NOT_FOUND",
+            AppError::ModerationRuleNotFound(_) => "MODERATION_RULE_NOT_FOUND",
+            AppError::FlaggedEventNotFound(_) => "FLAGGED_EVENT_NOT_FOUND",
+            AppError::SessionNotFound(_) => "SESSION_NOT_FOUND",
+            AppError::RecordingNotFound(_) => "RECORDING_NOT_FOUND",
+            AppError::Update(_) => "UPDATE_ERROR",
+            AppError::ScheduledTaskNotFound(_) => "SCHEDULED_TASK_NOT_FOUND",
+            AppError::Notification(_) => "NOTIFICATION_ERROR",
+            AppError::Autostart(_) => "AUTOSTART_ERROR",
+            AppError::Asset(_) => "ASSET_CACHE_ERROR",
+            AppError::ProfileNotFound(_) => "PROFILE_NOT_FOUND",
+            AppError::Database(_) => "DATABASE_ERROR",
+            AppError::Image(_) => "IMAGE_ERROR",
+        }
+    }
+
+    // 供前端格式化错误信息的参数
+    fn details(&self) -> serde_json::Value {
+        match self {
+            AppError::ServerNotFound(name)
+            | AppError::ServerExists(name)
+            | AppError::ProfileNotFound(name)
+            | AppError::MidiPortNotFound(name) => {
+                json!({ "name": name })
+            }
+            AppError::FolderNotFound(path) => json!({ "path": path }),
+            AppError::CredentialNotFound(id)
+            | AppError::PluginNotFound(id)
+            | AppError::AutomationRuleNotFound(id)
+            | AppError::WebhookNotFound(id)
+            | AppError::SongRequestNotFound(id)
+            | AppError::QueueEntryNotFound(id)
+            | AppError::ViewerNotFound(id)
+            | AppError::ModerationRuleNotFound(id)
+            | AppError::FlaggedEventNotFound(id)
+            | AppError::RecordingNotFound(id)
+            | AppError::ScheduledTaskNotFound(id) => json!({ "id": id }),
+            AppError::RoomExists(room_id) | AppError::RoomNotFound(room_id) => {
+                json!({ "roomId": room_id })
+            }
+            AppError::SessionNotFound(id) => json!({ "id": id }),
+            AppError::PortInUse {
+                port,
+                pid,
+                process_name,
+            } => json!({ "port": port, "pid": pid, "processName": process_name }),
+            AppError::BilibiliApi { code, .. } => json!({ "code": code }),
+            AppError::DanmakuRejected { reason, .. } => json!({ "reason": reason }),
+            AppError::RateLimited { group, retry_after } => {
+                json!({ "group": group, "retryAfter": retry_after })
+            }
+            AppError::Timeout { secs } => json!({ "secs": secs }),
+            _ => serde_json::Value::Null,
+        }
+    }
+}
+
+fn port_in_use_message(port: u16, pid: Option<u32>, process_name: Option<&str>) -> String {
+    match (pid, process_name) {
+        (Some(pid), Some(name)) => format!("端口 {} 已被 {} (PID {}) 占用", port, name, pid),
+        (Some(pid), None) => format!("端口 {} 已被进程 (PID {}) 占用", port, pid),
+        _ => format!("端口 {} 已被占用", port),
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+pub type AppResult<T> = Result<T, AppError>;
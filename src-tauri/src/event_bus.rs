@@ -0,0 +1,377 @@
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock, Weak};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Notify;
+
+// 未指定时每个订阅者最多积压的事件数量
+pub const DEFAULT_CAPACITY: usize = 1024;
+// 保存和上传事件的订阅者使用更大的队列，尽量不丢失事件
+pub const PERSIST_CAPACITY: usize = 8192;
+
+// 订阅者积压的事件达到上限后如何处理新事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropPolicy {
+    // 丢弃最旧的事件，保证能处理到最新的事件
+    DropOldest,
+    // 丢弃新到的事件，保证已积压的事件按顺序处理完
+    DropNewest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriberMetrics {
+    pub name: String,
+    pub policy: DropPolicy,
+    pub capacity: usize,
+    // 当前积压的事件数量
+    pub depth: usize,
+    // 积压数量的最大值
+    pub high_water: usize,
+    pub delivered: u64,
+    // 因积压达到上限丢弃的事件数量
+    pub dropped: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventBusMetrics {
+    pub published: u64,
+    pub subscribers: Vec<SubscriberMetrics>,
+}
+
+struct Queue<T> {
+    events: VecDeque<T>,
+    // 还没有通过 Lagged 告知订阅者的丢弃数量
+    lagged: u64,
+    closed: bool,
+}
+
+// 一个订阅者独立的有界队列
+struct Slot<T> {
+    id: u64,
+    name: String,
+    policy: DropPolicy,
+    capacity: usize,
+    queue: Mutex<Queue<T>>,
+    // 分别唤醒异步等待和阻塞等待的订阅者
+    notify: Notify,
+    condvar: Condvar,
+    high_water: AtomicUsize,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl<T> Slot<T> {
+    fn push(&self, event: T) {
+        {
+            let mut queue = self.queue.lock().unwrap();
+            if queue.events.len() >= self.capacity {
+                queue.lagged += 1;
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                match self.policy {
+                    DropPolicy::DropOldest => {
+                        queue.events.pop_front();
+                    }
+                    DropPolicy::DropNewest => return,
+                }
+            }
+            queue.events.push_back(event);
+            self.high_water
+                .fetch_max(queue.events.len(), Ordering::Relaxed);
+        }
+        self.notify.notify_one();
+        self.condvar.notify_one();
+    }
+
+    fn close(&self) {
+        self.queue.lock().unwrap().closed = true;
+        self.notify.notify_one();
+        self.condvar.notify_all();
+    }
+
+    // 先报告丢弃的数量，再按顺序返回事件，队列为空时返回 None
+    fn take(&self, queue: &mut Queue<T>) -> Option<Result<T, RecvError>> {
+        if queue.lagged > 0 {
+            let lagged = std::mem::take(&mut queue.lagged);
+            return Some(Err(RecvError::Lagged(lagged)));
+        }
+        if let Some(event) = queue.events.pop_front() {
+            self.delivered.fetch_add(1, Ordering::Relaxed);
+            return Some(Ok(event));
+        }
+        queue.closed.then_some(Err(RecvError::Closed))
+    }
+
+    fn metrics(&self) -> SubscriberMetrics {
+        SubscriberMetrics {
+            name: self.name.clone(),
+            policy: self.policy,
+            capacity: self.capacity,
+            depth: self.queue.lock().unwrap().events.len(),
+            high_water: self.high_water.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct Inner<T> {
+    slots: RwLock<Vec<Arc<Slot<T>>>>,
+    next_id: AtomicU64,
+    published: AtomicU64,
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        for slot in self.slots.get_mut().unwrap().iter() {
+            slot.close();
+        }
+    }
+}
+
+// 把事件分发给多个订阅者，每个订阅者有独立的有界队列
+// 发布事件不会等待任何订阅者，处理慢的订阅者只会丢弃自己队列中的事件
+pub struct EventBus<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for EventBus<T> {
+    fn clone(&self) -> Self {
+        EventBus {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Default for EventBus<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> EventBus<T> {
+    pub fn new() -> Self {
+        EventBus {
+            inner: Arc::new(Inner {
+                slots: RwLock::new(Vec::new()),
+                next_id: AtomicU64::new(0),
+                published: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    // 订阅之后发布的事件，name 用于在统计中区分订阅者
+    pub fn subscribe(&self, name: &str, capacity: usize, policy: DropPolicy) -> Subscriber<T> {
+        let slot = Arc::new(Slot {
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+            name: name.to_string(),
+            policy,
+            capacity: capacity.max(1),
+            queue: Mutex::new(Queue {
+                events: VecDeque::new(),
+                lagged: 0,
+                closed: false,
+            }),
+            notify: Notify::new(),
+            condvar: Condvar::new(),
+            high_water: AtomicUsize::new(0),
+            delivered: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        });
+        self.inner.slots.write().unwrap().push(slot.clone());
+        Subscriber {
+            slot,
+            bus: Arc::downgrade(&self.inner),
+        }
+    }
+
+    pub fn publish(&self, event: T) {
+        self.inner.published.fetch_add(1, Ordering::Relaxed);
+        let slots = self.inner.slots.read().unwrap();
+        if let Some((last, rest)) = slots.split_last() {
+            for slot in rest {
+                slot.push(event.clone());
+            }
+            last.push(event);
+        }
+    }
+
+    pub fn metrics(&self) -> EventBusMetrics {
+        EventBusMetrics {
+            published: self.inner.published.load(Ordering::Relaxed),
+            subscribers: self
+                .inner
+                .slots
+                .read()
+                .unwrap()
+                .iter()
+                .map(|slot| slot.metrics())
+                .collect(),
+        }
+    }
+}
+
+// 接收事件的一端，与 broadcast::Receiver 一样在丢弃事件后返回 Lagged，总线关闭后返回 Closed
+// 丢弃时取消订阅
+pub struct Subscriber<T> {
+    slot: Arc<Slot<T>>,
+    bus: Weak<Inner<T>>,
+}
+
+impl<T> Subscriber<T> {
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            let result = {
+                let mut queue = self.slot.queue.lock().unwrap();
+                self.slot.take(&mut queue)
+            };
+            if let Some(result) = result {
+                return result;
+            }
+            self.slot.notify.notified().await;
+        }
+    }
+
+    // 在阻塞线程中等待下一个事件
+    pub fn blocking_recv(&mut self) -> Result<T, RecvError> {
+        let mut queue = self.slot.queue.lock().unwrap();
+        loop {
+            if let Some(result) = self.slot.take(&mut queue) {
+                return result;
+            }
+            queue = self.slot.condvar.wait(queue).unwrap();
+        }
+    }
+
+    // 转换为事件流，总线关闭时结束
+    pub fn into_stream(self) -> impl Stream<Item = Result<T, RecvError>>
+    where
+        T: Send + 'static,
+    {
+        futures_util::stream::unfold(self, |mut subscriber| async move {
+            match subscriber.recv().await {
+                Err(RecvError::Closed) => None,
+                result => Some((result, subscriber)),
+            }
+        })
+    }
+}
+
+impl<T> Drop for Subscriber<T> {
+    fn drop(&mut self) {
+        if let Some(bus) = self.bus.upgrade() {
+            bus.slots
+                .write()
+                .unwrap()
+                .retain(|slot| slot.id != self.slot.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DropPolicy, EventBus, Subscriber};
+    use tokio::sync::broadcast::error::RecvError;
+
+    fn drain(subscriber: &mut Subscriber<u32>, count: usize) -> Vec<Result<u32, RecvError>> {
+        (0..count).map(|_| subscriber.blocking_recv()).collect()
+    }
+
+    #[test]
+    fn drop_oldest_keeps_latest_events() {
+        let bus = EventBus::new();
+        let mut subscriber = bus.subscribe("test", 2, DropPolicy::DropOldest);
+        for event in 1..=5 {
+            bus.publish(event);
+        }
+        // 丢弃的数量先于剩余的事件报告
+        assert_eq!(
+            drain(&mut subscriber, 3),
+            [Err(RecvError::Lagged(3)), Ok(4), Ok(5)]
+        );
+
+        let metrics = &bus.metrics().subscribers[0];
+        assert_eq!(metrics.dropped, 3);
+        assert_eq!(metrics.delivered, 2);
+        assert_eq!(metrics.high_water, 2);
+        assert_eq!(metrics.depth, 0);
+    }
+
+    #[test]
+    fn drop_newest_keeps_queued_events() {
+        let bus = EventBus::new();
+        let mut subscriber = bus.subscribe("test", 2, DropPolicy::DropNewest);
+        for event in 1..=5 {
+            bus.publish(event);
+        }
+        assert_eq!(
+            drain(&mut subscriber, 3),
+            [Err(RecvError::Lagged(3)), Ok(1), Ok(2)]
+        );
+    }
+
+    #[test]
+    fn lagged_is_reported_before_next_event() {
+        let bus = EventBus::new();
+        let mut subscriber = bus.subscribe("test", 1, DropPolicy::DropOldest);
+        bus.publish(1);
+        assert_eq!(subscriber.blocking_recv(), Ok(1));
+        bus.publish(2);
+        bus.publish(3);
+        assert_eq!(subscriber.blocking_recv(), Err(RecvError::Lagged(1)));
+        assert_eq!(subscriber.blocking_recv(), Ok(3));
+        // 报告过的丢弃数量不会重复报告
+        bus.publish(4);
+        assert_eq!(subscriber.blocking_recv(), Ok(4));
+    }
+
+    #[test]
+    fn subscribers_have_independent_queues() {
+        let bus = EventBus::new();
+        let mut slow = bus.subscribe("slow", 1, DropPolicy::DropNewest);
+        let mut fast = bus.subscribe("fast", 4, DropPolicy::DropNewest);
+        bus.publish(1);
+        bus.publish(2);
+        assert_eq!(drain(&mut fast, 2), [Ok(1), Ok(2)]);
+        assert_eq!(drain(&mut slow, 2), [Err(RecvError::Lagged(1)), Ok(1)]);
+        assert_eq!(bus.metrics().published, 2);
+    }
+
+    #[test]
+    fn closed_after_last_bus_dropped() {
+        let bus = EventBus::new();
+        let mut subscriber = bus.subscribe("test", 4, DropPolicy::DropOldest);
+        let clone = bus.clone();
+        bus.publish(1);
+        drop(bus);
+        clone.publish(2);
+        drop(clone);
+        // 关闭前积压的事件仍然可以取出
+        assert_eq!(
+            drain(&mut subscriber, 3),
+            [Ok(1), Ok(2), Err(RecvError::Closed)]
+        );
+        assert_eq!(subscriber.blocking_recv(), Err(RecvError::Closed));
+    }
+
+    #[tokio::test]
+    async fn async_recv_wakes_on_close() {
+        let bus = EventBus::<u32>::new();
+        let mut subscriber = bus.subscribe("test", 4, DropPolicy::DropOldest);
+        let task = tokio::spawn(async move { subscriber.recv().await });
+        tokio::task::yield_now().await;
+        drop(bus);
+        assert_eq!(task.await.unwrap(), Err(RecvError::Closed));
+    }
+
+    #[test]
+    fn dropped_subscriber_unsubscribes() {
+        let bus = EventBus::<u32>::new();
+        let subscriber = bus.subscribe("test", 4, DropPolicy::DropOldest);
+        assert_eq!(bus.metrics().subscribers.len(), 1);
+        drop(subscriber);
+        assert!(bus.metrics().subscribers.is_empty());
+    }
+}
@@ -0,0 +1,381 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::AppResult;
+use crate::event_bus::Subscriber;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+mod export;
+mod leaderboard;
+mod revenue;
+mod sessions;
+
+pub use export::ExportFormat;
+pub use leaderboard::{today_start, LeaderboardQuery, Leaderboards};
+pub use revenue::RevenueBucket;
+pub use sessions::{Session, SessionSource, SessionStats};
+
+// 单次查询最多返回的事件数量
+const MAX_QUERY_LIMIT: u32 = 1000;
+const DEFAULT_QUERY_LIMIT: u32 = 100;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    room_id INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    uid INTEGER NOT NULL,
+    uname TEXT NOT NULL,
+    data TEXT NOT NULL,
+    uploaded_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_events_timestamp ON events (timestamp);
+CREATE INDEX IF NOT EXISTS idx_events_room ON events (room_id, timestamp);
+CREATE INDEX IF NOT EXISTS idx_events_uid ON events (uid, timestamp);
+";
+
+// 查询条件，未设置的条件不参与过滤
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EventQuery {
+    pub room_id: Option<u64>,
+    // Unix 毫秒时间戳，包含边界
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub uid: Option<u64>,
+    // 按用户名模糊匹配
+    pub uname: Option<String>,
+    // 为空时查询所有类型
+    pub kinds: Vec<EventKind>,
+    pub limit: Option<u32>,
+    pub offset: u32,
+}
+
+// 保存在本地的事件
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredEvent {
+    #[serde(flatten)]
+    pub event: DanmakuEvent,
+    // 成功上传到 vtsuru 的 Unix 毫秒时间戳，未上传时为空
+    pub uploaded_at: Option<i64>,
+}
+
+// 一页查询结果
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPage {
+    // 符合条件的事件总数
+    pub total: u64,
+    pub events: Vec<StoredEvent>,
+}
+
+// 一段时间内的事件统计
+#[derive(Debug, Clone, Serialize)]
+pub struct EventStats {
+    // 统计范围，Unix 毫秒时间戳
+    pub start: i64,
+    pub end: i64,
+    pub total: u64,
+    // 各类型的事件数量
+    pub counts: BTreeMap<String, u64>,
+    // 礼物、醒目留言和大航海的金额，单位为元
+    pub gift_value: f64,
+    pub super_chat_value: f64,
+    pub guard_value: f64,
+    // 产生过事件的不同用户数量，开放平台的用户按 open_id 区分
+    pub unique_users: u64,
+}
+
+// 记录所有抓取到的事件的本地数据库
+#[derive(Clone)]
+pub struct EventStore {
+    path: PathBuf,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl EventStore {
+    pub fn open(path: &Path) -> AppResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        conn.execute_batch(sessions::SCHEMA)?;
+        Ok(EventStore {
+            path: path.to_path_buf(),
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    // 把 WAL 日志合并回数据库文件，退出程序前调用
+    pub fn checkpoint(&self) -> AppResult<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        Ok(())
+    }
+
+    // 在后台线程中写入订阅到的事件
+    pub fn record(&self, mut events: Subscriber<DanmakuEvent>) {
+        let store = self.clone();
+        std::thread::spawn(move || loop {
+            match events.blocking_recv() {
+                Ok(event) if event.replay || event.simulated => {}
+                Ok(event) => {
+                    if let Err(err) = store.insert(&event) {
+                        log::warn!("保存事件失败: {}", err);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(count)) => {
+                    log::warn!("事件写入不及时，丢弃了 {} 条事件", count);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        });
+    }
+
+    fn insert(&self, event: &DanmakuEvent) -> AppResult<()> {
+        let data = serde_json::to_string(event).unwrap_or_default();
+        self.conn.lock().unwrap().execute(
+            "INSERT OR IGNORE INTO events (id, kind, room_id, timestamp, uid, uname, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                event.id,
+                event.kind.as_str(),
+                event.room_id as i64,
+                event.timestamp,
+                event.uid as i64,
+                event.uname,
+                data
+            ],
+        )?;
+        Ok(())
+    }
+
+    // 记录事件已成功上传
+    pub fn mark_uploaded(&self, ids: &[&str], uploaded_at: i64) -> AppResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "UPDATE events SET uploaded_at = ?1 WHERE id = ?2 AND uploaded_at IS NULL",
+            )?;
+            for id in ids {
+                stmt.execute(params![uploaded_at, id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // 按类型统计一段时间内保存的事件数量和其中已上传的数量
+    pub fn upload_counts(&self, start: i64, end: i64) -> AppResult<BTreeMap<String, (u64, u64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "SELECT kind, COUNT(*), COUNT(uploaded_at) FROM events
+             WHERE timestamp >= ?1 AND timestamp <= ?2 GROUP BY kind",
+        )?;
+        let rows = stmt.query_map(params![start, end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, i64>(2)? as u64,
+            ))
+        })?;
+        let mut counts = BTreeMap::new();
+        for row in rows {
+            let (kind, total, uploaded) = row?;
+            counts.insert(kind, (total, uploaded));
+        }
+        Ok(counts)
+    }
+
+    // 按写入顺序返回指定事件之后的事件，用于断线重连后补发
+    // 找不到该事件时返回空列表
+    pub fn events_after(&self, id: &str, limit: u32) -> AppResult<Vec<DanmakuEvent>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "SELECT data FROM events
+             WHERE rowid > (SELECT rowid FROM events WHERE id = ?1)
+             ORDER BY rowid LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![id, limit], |row| row.get::<_, String>(0))?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            match serde_json::from_str(&row?) {
+                Ok(event) => events.push(event),
+                Err(err) => log::warn!("解析已保存的事件失败: {}", err),
+            }
+        }
+        Ok(events)
+    }
+
+    // 按条件查询事件，按时间倒序排列
+    pub fn query(&self, query: &EventQuery) -> AppResult<EventPage> {
+        let (where_clause, mut values) = filter_clause(query);
+        let conn = self.conn.lock().unwrap();
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM events {}", where_clause),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+        values.push(Value::Integer(limit as i64));
+        values.push(Value::Integer(query.offset as i64));
+        let mut stmt = conn.prepare(&format!(
+            "SELECT data, uploaded_at FROM events {} ORDER BY timestamp DESC LIMIT ? OFFSET ?",
+            where_clause
+        ))?;
+        let rows = stmt.query_map(params_from_iter(values.iter()), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?))
+        })?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let (data, uploaded_at) = row?;
+            match serde_json::from_str(&data) {
+                Ok(event) => events.push(StoredEvent { event, uploaded_at }),
+                Err(err) => log::warn!("解析已保存的事件失败: {}", err),
+            }
+        }
+        Ok(EventPage {
+            total: total as u64,
+            events,
+        })
+    }
+
+    // 统计时间范围内的事件数量和金额
+    pub fn stats(&self, room_id: Option<u64>, start: i64, end: i64) -> AppResult<EventStats> {
+        let (where_clause, values) = filter_clause(&EventQuery {
+            room_id,
+            start: Some(start),
+            end: Some(end),
+            ..Default::default()
+        });
+        let conn = self.conn.lock().unwrap();
+        let mut stats = EventStats {
+            start,
+            end,
+            total: 0,
+            counts: BTreeMap::new(),
+            gift_value: 0.0,
+            super_chat_value: 0.0,
+            guard_value: 0.0,
+            unique_users: 0,
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT kind, COUNT(*), COALESCE(SUM(json_extract(data, '$.price')), 0)
+             FROM events {} GROUP BY kind",
+            where_clause
+        ))?;
+        let rows = stmt.query_map(params_from_iter(values.iter()), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, f64>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (kind, count, value) = row?;
+            match kind.as_str() {
+                "gift" => stats.gift_value = value,
+                "super_chat" => stats.super_chat_value = value,
+                "guard" => stats.guard_value = value,
+                _ => {}
+            }
+            stats.total += count as u64;
+            stats.counts.insert(kind, count as u64);
+        }
+
+        let unique_users: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(DISTINCT CASE WHEN uid != 0 THEN uid ELSE json_extract(data, '$.open_id') END)
+                 FROM events {}",
+                where_clause
+            ),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+        stats.unique_users = unique_users as u64;
+        Ok(stats)
+    }
+
+    // 将符合条件的所有事件按时间顺序导出到文件，返回导出的数量
+    // 忽略分页参数；使用单独的只读连接，导出大量数据时不会阻塞事件写入
+    pub fn export(&self, query: &EventQuery, format: ExportFormat, path: &Path) -> AppResult<u64> {
+        let (where_clause, values) = filter_clause(query);
+        let conn = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT data, uploaded_at FROM events {} ORDER BY timestamp ASC",
+            where_clause
+        ))?;
+        let mut rows = stmt.query(params_from_iter(values.iter()))?;
+
+        let mut writer = export::Writer::create(path, format)?;
+        while let Some(row) = rows.next()? {
+            let data: String = row.get(0)?;
+            let uploaded_at: Option<i64> = row.get(1)?;
+            match serde_json::from_str(&data) {
+                Ok(event) => writer.write(&StoredEvent { event, uploaded_at })?,
+                Err(err) => log::warn!("解析已保存的事件失败: {}", err),
+            }
+        }
+        writer.finish()
+    }
+}
+
+// 根据查询条件生成 WHERE 子句和对应的参数
+fn filter_clause(query: &EventQuery) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+    if let Some(room_id) = query.room_id {
+        conditions.push("room_id = ?".to_string());
+        values.push(Value::Integer(room_id as i64));
+    }
+    if let Some(start) = query.start {
+        conditions.push("timestamp >= ?".to_string());
+        values.push(Value::Integer(start));
+    }
+    if let Some(end) = query.end {
+        conditions.push("timestamp <= ?".to_string());
+        values.push(Value::Integer(end));
+    }
+    if let Some(uid) = query.uid {
+        conditions.push("uid = ?".to_string());
+        values.push(Value::Integer(uid as i64));
+    }
+    if let Some(uname) = query.uname.as_deref().filter(|uname| !uname.is_empty()) {
+        conditions.push("uname LIKE ?".to_string());
+        values.push(Value::Text(format!("%{}%", uname)));
+    }
+    if !query.kinds.is_empty() {
+        conditions.push(format!(
+            "kind IN ({})",
+            vec!["?"; query.kinds.len()].join(", ")
+        ));
+        values.extend(
+            query
+                .kinds
+                .iter()
+                .map(|kind| Value::Text(kind.as_str().to_string())),
+        );
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (where_clause, values)
+}
@@ -0,0 +1,126 @@
+use super::StoredEvent;
+use crate::error::AppResult;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// CSV 表头，与 write_csv_row 中的字段顺序一致
+const CSV_HEADER: &str =
+    "time,kind,room_id,uid,uname,message,num,price,guard_level,fans_medal_name,fans_medal_level,uploaded_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+// 逐条写入导出文件，避免一次性把所有事件读入内存
+pub(super) struct Writer {
+    out: BufWriter<File>,
+    format: ExportFormat,
+    count: u64,
+}
+
+impl Writer {
+    pub fn create(path: &Path, format: ExportFormat) -> AppResult<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            // 写入 BOM，Excel 才能正确识别 UTF-8 编码的中文
+            ExportFormat::Csv => {
+                out.write_all(b"\xEF\xBB\xBF")?;
+                writeln!(out, "{}", CSV_HEADER)?;
+            }
+            ExportFormat::Json => out.write_all(b"[\n")?,
+            ExportFormat::Ndjson => {}
+        }
+        Ok(Writer {
+            out,
+            format,
+            count: 0,
+        })
+    }
+
+    pub fn write(&mut self, event: &StoredEvent) -> AppResult<()> {
+        match self.format {
+            ExportFormat::Csv => self.write_csv_row(event)?,
+            ExportFormat::Json => {
+                if self.count > 0 {
+                    self.out.write_all(b",\n")?;
+                }
+                serde_json::to_writer(&mut self.out, event).map_err(std::io::Error::from)?;
+            }
+            ExportFormat::Ndjson => {
+                serde_json::to_writer(&mut self.out, event).map_err(std::io::Error::from)?;
+                self.out.write_all(b"\n")?;
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    // 写入结尾并刷新缓冲区，返回写入的事件数量
+    pub fn finish(mut self) -> AppResult<u64> {
+        if self.format == ExportFormat::Json {
+            self.out.write_all(b"\n]\n")?;
+        }
+        self.out.flush()?;
+        Ok(self.count)
+    }
+
+    fn write_csv_row(&mut self, stored: &StoredEvent) -> AppResult<()> {
+        let event = &stored.event;
+        let fields = [
+            format_time(event.timestamp),
+            event.kind.as_str().to_string(),
+            event.room_id.to_string(),
+            event.uid.to_string(),
+            event.uname.clone(),
+            event.message.clone(),
+            event.num.to_string(),
+            event.price.to_string(),
+            event.guard_level.to_string(),
+            event.fans_medal_name.clone(),
+            event.fans_medal_level.to_string(),
+            stored.uploaded_at.map(format_time).unwrap_or_default(),
+        ];
+        let line = fields
+            .iter()
+            .map(|field| csv_escape(field))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(self.out, "{}", line)?;
+        Ok(())
+    }
+}
+
+// 本地时间，便于在表格中直接阅读
+fn format_time(timestamp: i64) -> String {
+    Local
+        .timestamp_millis_opt(timestamp)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+// 包含逗号、引号或换行的字段需要用引号包裹，引号本身需要转义
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,173 @@
+use super::EventStore;
+use crate::error::{AppError, AppResult};
+use chrono::{Local, TimeZone};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Row};
+use serde::{Deserialize, Serialize};
+
+// 每个排行榜默认和最多返回的人数
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
+
+// 区分用户的键，开放平台的用户没有 uid，按 open_id 区分
+const USER_KEY: &str =
+    "CASE WHEN uid != 0 THEN CAST(uid AS TEXT) ELSE json_extract(data, '$.open_id') END";
+
+// 排行榜的查询条件，未设置时间范围时统计本地时间今天零点到现在
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LeaderboardQuery {
+    pub room_id: Option<u64>,
+    // Unix 毫秒时间戳，包含边界
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    pub uid: u64,
+    pub open_id: Option<String>,
+    // 用户最近一次使用的名称
+    pub uname: String,
+    // 事件数量
+    pub count: u64,
+    // 礼物、醒目留言和大航海的总金额，单位为元，弹幕排行中为 0
+    pub value: f64,
+    // 范围内第一次发送的 Unix 毫秒时间戳
+    pub first_at: i64,
+}
+
+impl LeaderboardEntry {
+    // 依次为 uid、open_id、uname、数量、金额、首次时间
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(LeaderboardEntry {
+            uid: row.get::<_, i64>(0)? as u64,
+            open_id: row.get(1)?,
+            uname: row.get(2)?,
+            count: row.get::<_, i64>(3)? as u64,
+            value: row.get(4)?,
+            first_at: row.get(5)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Leaderboards {
+    pub start: i64,
+    pub end: i64,
+    // 按金额排列的送礼用户，包含礼物、醒目留言和大航海
+    pub top_gifters: Vec<LeaderboardEntry>,
+    // 按弹幕数量排列的用户
+    pub top_chatters: Vec<LeaderboardEntry>,
+    // 今天第一次在直播间发弹幕的用户，按首次发言时间倒序排列
+    pub first_time_chatters: Vec<LeaderboardEntry>,
+}
+
+// 本地时间今天零点的 Unix 毫秒时间戳
+pub fn today_start() -> i64 {
+    let now = Local::now();
+    now.date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .unwrap_or(now)
+        .timestamp_millis()
+}
+
+impl EventStore {
+    // 统计时间范围内的送礼排行、弹幕排行和今天的新观众
+    pub fn leaderboards(&self, query: &LeaderboardQuery) -> AppResult<Leaderboards> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let today = today_start();
+        let start = query.start.unwrap_or(today);
+        let end = query.end.unwrap_or(now);
+        if start > end {
+            return Err(AppError::InvalidConfig(
+                "开始时间不能晚于结束时间".to_string(),
+            ));
+        }
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let mut conditions = vec!["timestamp >= ?", "timestamp <= ?"];
+        let mut values = vec![Value::Integer(start), Value::Integer(end)];
+        if let Some(room_id) = query.room_id {
+            conditions.push("room_id = ?");
+            values.push(Value::Integer(room_id as i64));
+        }
+        let range = conditions.join(" AND ");
+
+        let conn = self.conn.lock().unwrap();
+        let rank = |sql: String, values: &[Value]| -> AppResult<Vec<LeaderboardEntry>> {
+            let mut stmt = conn.prepare(&sql)?;
+            let rows =
+                stmt.query_map(params_from_iter(values.iter()), LeaderboardEntry::from_row)?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        };
+
+        let mut ranked = values.clone();
+        ranked.push(Value::Integer(limit as i64));
+        let top_gifters = rank(
+            ranking(
+                &format!(
+                    "{} AND kind IN ('gift', 'super_chat', 'guard') AND json_extract(data, '$.price') > 0",
+                    range
+                ),
+                "SUM(json_extract(data, '$.price'))",
+                "",
+                "amount DESC",
+            ),
+            &ranked,
+        )?;
+        let top_chatters = rank(
+            ranking(
+                &format!("{} AND kind = 'danmaku'", range),
+                "0.0",
+                "",
+                "total DESC",
+            ),
+            &ranked,
+        )?;
+
+        // 新观众需要与全部历史比较，只按直播间过滤
+        let mut history = Vec::new();
+        let mut filter = "kind = 'danmaku'".to_string();
+        if let Some(room_id) = query.room_id {
+            filter.push_str(" AND room_id = ?");
+            history.push(Value::Integer(room_id as i64));
+        }
+        history.push(Value::Integer(today));
+        history.push(Value::Integer(limit as i64));
+        let first_time_chatters = rank(
+            ranking(&filter, "0.0", "HAVING first_at >= ?", "first_at DESC"),
+            &history,
+        )?;
+
+        Ok(Leaderboards {
+            start,
+            end,
+            top_gifters,
+            top_chatters,
+            first_time_chatters,
+        })
+    }
+}
+
+// 按用户分组统计并排序，再从每个用户最近的一个事件中取名称
+// 返回的列与 LeaderboardEntry::from_row 对应，最后一个参数为返回的人数
+fn ranking(filter: &str, amount: &str, having: &str, order: &str) -> String {
+    format!(
+        "SELECT g.uid, g.open_id,
+                COALESCE((SELECT e.uname FROM events e
+                          WHERE e.uid = g.uid AND e.timestamp = g.last_at
+                            AND (g.uid != 0 OR json_extract(e.data, '$.open_id') = g.open_id)
+                          LIMIT 1), ''),
+                g.total, g.amount, g.first_at
+         FROM (SELECT uid, json_extract(data, '$.open_id') AS open_id, COUNT(*) AS total,
+                      COALESCE({amount}, 0) AS amount,
+                      MIN(timestamp) AS first_at, MAX(timestamp) AS last_at
+               FROM events WHERE {filter}
+               GROUP BY {USER_KEY} {having}
+               ORDER BY {order} LIMIT ?) g
+         ORDER BY {order}"
+    )
+}
@@ -0,0 +1,58 @@
+use super::EventStore;
+use crate::error::AppResult;
+use rusqlite::params_from_iter;
+use rusqlite::types::Value;
+
+// 某一时间段内某一类型付费事件的数量和金额
+#[derive(Debug, Clone)]
+pub struct RevenueBucket {
+    // 本地时间的日期或月份，格式由查询时的 strftime 格式决定
+    pub period: String,
+    pub kind: String,
+    pub count: u64,
+    // 单位为元
+    pub value: f64,
+}
+
+impl EventStore {
+    // 按本地时间分组统计礼物、醒目留言和大航海的金额，免费礼物不计入
+    // period_format 为 SQLite strftime 格式，例如 %Y-%m-%d 或 %Y-%m
+    pub fn revenue_buckets(
+        &self,
+        room_id: Option<u64>,
+        start: i64,
+        end: i64,
+        period_format: &str,
+    ) -> AppResult<Vec<RevenueBucket>> {
+        let mut conditions = vec!["timestamp >= ?", "timestamp <= ?"];
+        let mut values = vec![
+            Value::Text(period_format.to_string()),
+            Value::Integer(start),
+            Value::Integer(end),
+        ];
+        if let Some(room_id) = room_id {
+            conditions.push("room_id = ?");
+            values.push(Value::Integer(room_id as i64));
+        }
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT strftime(?, timestamp / 1000, 'unixepoch', 'localtime') AS period, kind,
+                    COUNT(*), COALESCE(SUM(json_extract(data, '$.price')), 0)
+             FROM events
+             WHERE {} AND kind IN ('gift', 'super_chat', 'guard')
+               AND json_extract(data, '$.price') > 0
+             GROUP BY period, kind
+             ORDER BY period",
+            conditions.join(" AND ")
+        ))?;
+        let rows = stmt.query_map(params_from_iter(values.iter()), |row| {
+            Ok(RevenueBucket {
+                period: row.get(0)?,
+                kind: row.get(1)?,
+                count: row.get::<_, i64>(2)? as u64,
+                value: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
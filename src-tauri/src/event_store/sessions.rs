@@ -0,0 +1,197 @@
+use super::{EventQuery, EventStats, EventStore, ExportFormat};
+use crate::danmaku::DanmakuEvent;
+use crate::error::{AppError, AppResult};
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub(super) const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    room_id INTEGER NOT NULL,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER,
+    source TEXT NOT NULL,
+    title TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_sessions_room ON sessions (room_id, started_at);
+";
+
+const SESSION_COLUMNS: &str = "id, room_id, started_at, ended_at, source, title";
+
+// 判断开播和下播的依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSource {
+    // B 站直播间接口返回的开播状态
+    Api,
+    // 接口不可用时根据事件间隔推断
+    Gap,
+}
+
+impl SessionSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SessionSource::Api => "api",
+            SessionSource::Gap => "gap",
+        }
+    }
+}
+
+// 一场直播，期间保存的事件属于这场直播
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub id: i64,
+    pub room_id: u64,
+    // Unix 毫秒时间戳，直播进行中时 end 为空
+    pub start: i64,
+    pub end: Option<i64>,
+    pub source: SessionSource,
+    pub title: Option<String>,
+}
+
+impl Session {
+    // 直播进行中时统计到现在
+    fn end_or_now(&self) -> i64 {
+        self.end
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis())
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Session {
+            id: row.get(0)?,
+            room_id: row.get::<_, i64>(1)? as u64,
+            start: row.get(2)?,
+            end: row.get(3)?,
+            source: match row.get::<_, String>(4)?.as_str() {
+                "api" => SessionSource::Api,
+                _ => SessionSource::Gap,
+            },
+            title: row.get(5)?,
+        })
+    }
+}
+
+// 一场直播的信息和统计
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStats {
+    #[serde(flatten)]
+    pub session: Session,
+    pub stats: EventStats,
+}
+
+impl EventStore {
+    // 记录开播，返回新直播的 ID
+    pub fn start_session(
+        &self,
+        room_id: u64,
+        start: i64,
+        source: SessionSource,
+        title: Option<&str>,
+    ) -> AppResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO sessions (room_id, started_at, source, title) VALUES (?1, ?2, ?3, ?4)",
+            params![room_id as i64, start, source.as_str(), title],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn end_session(&self, id: i64, end: i64) -> AppResult<()> {
+        self.conn.lock().unwrap().execute(
+            "UPDATE sessions SET ended_at = ?2 WHERE id = ?1 AND ended_at IS NULL",
+            params![id, end],
+        )?;
+        Ok(())
+    }
+
+    // 尚未结束的直播，用于程序重启后继续跟踪
+    pub fn open_sessions(&self) -> AppResult<Vec<Session>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE ended_at IS NULL ORDER BY started_at",
+            SESSION_COLUMNS
+        ))?;
+        let sessions = stmt
+            .query_map([], Session::from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(sessions)
+    }
+
+    // 按开播时间倒序排列
+    pub fn sessions(
+        &self,
+        room_id: Option<u64>,
+        limit: u32,
+        offset: u32,
+    ) -> AppResult<Vec<Session>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE ?1 IS NULL OR room_id = ?1
+             ORDER BY started_at DESC LIMIT ?2 OFFSET ?3",
+            SESSION_COLUMNS
+        ))?;
+        let sessions = stmt
+            .query_map(
+                params![room_id.map(|id| id as i64), limit, offset],
+                Session::from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(sessions)
+    }
+
+    pub fn session(&self, id: i64) -> AppResult<Session> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                &format!("SELECT {} FROM sessions WHERE id = ?1", SESSION_COLUMNS),
+                params![id],
+                Session::from_row,
+            )
+            .optional()?
+            .ok_or(AppError::SessionNotFound(id))
+    }
+
+    pub fn session_stats(&self, id: i64) -> AppResult<SessionStats> {
+        let session = self.session(id)?;
+        let stats = self.stats(Some(session.room_id), session.start, session.end_or_now())?;
+        Ok(SessionStats { session, stats })
+    }
+
+    // 一场直播期间的所有事件，按时间顺序排列
+    pub fn session_events(&self, id: i64) -> AppResult<Vec<DanmakuEvent>> {
+        let session = self.session(id)?;
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT data FROM events
+             WHERE room_id = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+             ORDER BY timestamp, rowid",
+        )?;
+        let rows = stmt.query_map(
+            params![session.room_id as i64, session.start, session.end_or_now()],
+            |row| row.get::<_, String>(0),
+        )?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            match serde_json::from_str(&row?) {
+                Ok(event) => events.push(event),
+                Err(err) => log::warn!("解析已保存的事件失败: {}", err),
+            }
+        }
+        Ok(events)
+    }
+
+    // 导出一场直播期间的所有事件，返回导出的数量
+    pub fn export_session(&self, id: i64, format: ExportFormat, path: &Path) -> AppResult<u64> {
+        let session = self.session(id)?;
+        let query = EventQuery {
+            room_id: Some(session.room_id),
+            start: Some(session.start),
+            end: Some(session.end_or_now()),
+            ..Default::default()
+        };
+        self.export(&query, format, path)
+    }
+}
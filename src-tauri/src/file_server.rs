@@ -1,278 +1,2227 @@
+use crate::error::{AppError, AppResult};
+use axum::body::Body;
+use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
+use axum::extract::{ConnectInfo, DefaultBodyLimit, FromRequest, Multipart, Query, Request, State};
+use axum::http::{header, Extensions, HeaderMap, HeaderValue, Method, StatusCode, Version};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::{Json, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use base64::prelude::*;
+use chrono::{Local, TimeZone};
+use futures_util::StreamExt;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::io;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use tiny_http::{Response, Server};
-use tokio::sync::oneshot;
+use std::io::{self, SeekFrom};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sysinfo::Networks;
+use tauri::async_runtime::JoinHandle;
+use tauri::AppHandle;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, oneshot};
+use tokio_util::io::{ReaderStream, StreamReader};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+mod access_log;
+mod api;
+mod archive;
+mod cache;
+mod ip_filter;
+mod metrics;
+mod mount;
+mod persist;
+pub(crate) mod port;
+mod throttle;
+mod thumbnail;
+mod tls;
+mod watcher;
+mod webdav;
+
+pub use access_log::{AccessLog, AccessLogEntry};
+pub use mount::MountConfig;
+
+// 新建文件服务器实例时使用的默认端口
+const DEFAULT_PORT: u16 = 8080;
+
+// 目录首页文件名
+const INDEX_FILE: &str = "index.html";
+
+// 生成链接时路径中每一段需要编码的字符
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// 停止服务器时等待正在进行的请求完成的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+// 检查服务器能否响应时等待的时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 仅本机访问时使用的监听地址
+pub const BIND_LOCALHOST: &str = "127.0.0.1";
+// 允许局域网访问时使用的监听地址
+pub const BIND_ALL_INTERFACES: &str = "0.0.0.0";
 
 // 文件服务器的配置
+// 缺少的字段使用默认值，以便读取旧版本保存的配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct FileServerConfig {
     pub folder_path: String,
     pub port: u16,
+    // 端口被占用时自动选择其他空闲端口
+    pub auto_port: bool,
+    pub bind_address: String,
+    // 访问令牌，设置后所有请求都需要携带该令牌
+    pub access_token: Option<String>,
+    // 对文本类响应启用 gzip/brotli 压缩
+    pub compression: bool,
+    // 允许跨域访问的来源列表，"*" 表示允许任意来源，为空时不添加 CORS 头
+    pub cors_origins: Vec<String>,
+    // 允许通过 PUT / POST /upload 写入文件，仅在设置了访问令牌时生效
+    pub allow_upload: bool,
+    // WebDAV 模式，可将共享文件夹挂载为网络驱动器，写操作同样受 allow_upload 限制
+    pub webdav: bool,
+    // 单页应用模式，不存在的页面路径回退到根目录的 index.html
+    pub spa_fallback: bool,
+    // 最大并发连接数，0 表示不限制
+    pub max_connections: u32,
+    // 单个连接的速度上限，单位 KB/s，0 表示不限制
+    pub connection_speed_limit: u64,
+    // 所有连接合计的速度上限，单位 KB/s，0 表示不限制
+    pub global_speed_limit: u64,
+    // 启用 HTTPS，未指定证书时使用自动生成的自签名证书
+    pub https: bool,
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    // 监视文件夹变化并通过 /events 推送给页面
+    pub watch_folder: bool,
+    // 额外的虚拟挂载点，folder_path 作为根目录，为空时只能访问挂载点
+    pub mounts: Vec<MountConfig>,
+    // 允许访问的客户端 IP 或 CIDR 网段，为空时不限制
+    pub ip_allowlist: Vec<String>,
+    // 拒绝访问的客户端 IP 或 CIDR 网段，优先于允许列表
+    pub ip_denylist: Vec<String>,
+    // 应用启动时自动启动该服务器
+    pub auto_start: bool,
+}
+
+impl Default for FileServerConfig {
+    fn default() -> Self {
+        FileServerConfig {
+            folder_path: String::new(),
+            port: DEFAULT_PORT,
+            auto_port: false,
+            bind_address: BIND_LOCALHOST.to_string(),
+            access_token: None,
+            compression: true,
+            cors_origins: Vec::new(),
+            allow_upload: false,
+            webdav: false,
+            spa_fallback: false,
+            max_connections: 0,
+            connection_speed_limit: 0,
+            global_speed_limit: 0,
+            https: false,
+            tls_cert_path: None,
+            tls_key_path: None,
+            watch_folder: true,
+            mounts: Vec::new(),
+            ip_allowlist: Vec::new(),
+            ip_denylist: Vec::new(),
+            auto_start: false,
+        }
+    }
+}
+
+// 更新文件服务器配置时传入的字段，未传入的字段保持不变
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FileServerConfigUpdate {
+    pub folder_path: Option<String>,
+    pub port: Option<u16>,
+    pub auto_port: Option<bool>,
+    pub bind_address: Option<String>,
+    pub allow_lan: Option<bool>,
+    pub access_token: Option<String>,
+    pub compression: Option<bool>,
+    pub cors_origins: Option<Vec<String>>,
+    pub allow_upload: Option<bool>,
+    pub webdav: Option<bool>,
+    pub spa_fallback: Option<bool>,
+    pub max_connections: Option<u32>,
+    pub connection_speed_limit: Option<u64>,
+    pub global_speed_limit: Option<u64>,
+    pub https: Option<bool>,
+    // 传入空字符串表示改回使用自签名证书
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    pub watch_folder: Option<bool>,
+    // 整体替换挂载点列表
+    pub mounts: Option<Vec<MountConfig>>,
+    pub auto_start: Option<bool>,
 }
 
 // 文件服务器的状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileServerStatus {
+    pub name: String,
     pub running: bool,
     pub folder_path: String,
+    // 配置的端口
     pub port: u16,
+    // 实际监听的端口，自动选择端口时可能与配置不同，未运行时为空
+    pub active_port: Option<u16>,
+    pub bind_address: String,
+    // 局域网内其他设备可以用来访问本服务器的地址
+    pub lan_ips: Vec<String>,
+    pub auth_enabled: bool,
+    pub https: bool,
+    pub mounts: Vec<MountConfig>,
+    pub ip_allowlist: Vec<String>,
+    pub ip_denylist: Vec<String>,
+}
+
+impl FileServerStatus {
+    fn from_config(name: &str, config: FileServerConfig, active_port: Option<u16>) -> Self {
+        let lan_ips = lan_ips_for(&config.bind_address);
+        FileServerStatus {
+            name: name.to_string(),
+            running: active_port.is_some(),
+            folder_path: config.folder_path,
+            port: config.port,
+            active_port,
+            bind_address: config.bind_address,
+            lan_ips,
+            auth_enabled: config.access_token.is_some(),
+            https: config.https,
+            mounts: config.mounts,
+            ip_allowlist: config.ip_allowlist,
+            ip_denylist: config.ip_denylist,
+        }
+    }
+}
+
+// 请求处理函数共享的状态
+struct ServeState {
+    name: String,
+    // 用于读取直播间事件等应用状态
+    app: AppHandle,
+    // 按前缀长度倒序排列的挂载点，包含根目录
+    mounts: Vec<mount::Mount>,
+    access_token: Option<String>,
+    allow_upload: bool,
+    webdav: bool,
+    spa_fallback: bool,
+    thumbnail_dir: PathBuf,
+    access_log: Arc<AccessLog>,
+    limits: throttle::Limits,
+    // 与管理器共享，运行中修改后立即生效
+    ip_filter: Arc<RwLock<ip_filter::IpFilter>>,
+    // 文件夹变化通知，未开启监视时为空
+    folder_changes: Option<broadcast::Sender<watcher::FolderChange>>,
 }
 
 // 用于管理服务器的结构体
 pub struct FileServerManager {
+    name: String,
+    app: AppHandle,
+    // 实例的缓存目录，存放缩略图和自签名证书
+    cache_dir: PathBuf,
+    access_log: Arc<AccessLog>,
     config: Arc<Mutex<FileServerConfig>>,
+    // 根据配置中的 IP 列表生成的过滤规则
+    ip_filter: Arc<RwLock<ip_filter::IpFilter>>,
     shutdown_sender: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    server_task: Arc<Mutex<Option<JoinHandle<()>>>>,
     running: Arc<Mutex<bool>>,
+    // 运行中实际监听的端口
+    active_port: Arc<Mutex<Option<u16>>>,
 }
 
 impl FileServerManager {
-    pub fn new() -> Self {
+    pub fn new(
+        app: AppHandle,
+        name: &str,
+        config: FileServerConfig,
+        cache_dir: PathBuf,
+        access_log: Arc<AccessLog>,
+    ) -> Self {
+        // 保存的列表已经校验过，解析失败时退回不限制
+        let ip_filter =
+            ip_filter::IpFilter::new(&config.ip_allowlist, &config.ip_denylist).unwrap_or_default();
         FileServerManager {
-            config: Arc::new(Mutex::new(FileServerConfig {
-                folder_path: String::from(""),
-                port: 8080,
-            })),
+            name: name.to_string(),
+            app,
+            cache_dir,
+            access_log,
+            config: Arc::new(Mutex::new(config)),
+            ip_filter: Arc::new(RwLock::new(ip_filter)),
             shutdown_sender: Arc::new(Mutex::new(None)),
+            server_task: Arc::new(Mutex::new(None)),
             running: Arc::new(Mutex::new(false)),
+            active_port: Arc::new(Mutex::new(None)),
         }
     }
 
     // 启动文件服务器
-    pub fn start_server(&self) -> Result<FileServerStatus, String> {
-        let mut running = self.running.lock().unwrap();
-        if *running {
-            return Err("服务器已经在运行中".to_string());
+    pub async fn start_server(&self) -> AppResult<FileServerStatus> {
+        if *self.running.lock().unwrap() {
+            return Err(AppError::AlreadyRunning);
         }
 
         let config = self.config.lock().unwrap().clone();
-        if config.folder_path.is_empty() {
-            return Err("文件夹路径未设置".to_string());
-        }
+        // 检查根目录和各挂载点的文件夹是否存在
+        let mounts = mount::resolve_mounts(&config)?;
+
+        // 在当前任务中绑定端口，这样端口被占用等错误可以直接返回给调用方
+        let ip: IpAddr = config.bind_address.parse().map_err(|_| {
+            AppError::InvalidConfig(format!("无效的监听地址: {}", config.bind_address))
+        })?;
+        // 证书同样在启动前加载，加载失败时不占用端口
+        let tls = if config.https {
+            Some(tls::load_tls_config(&config, &self.cache_dir.join("tls")).await?)
+        } else {
+            None
+        };
+        let listener = port::bind(ip, config.port, config.auto_port).await?;
+        let addr = listener.local_addr().map_err(AppError::Bind)?;
 
-        // 检查文件夹是否存在
-        let path = PathBuf::from(&config.folder_path);
-        if !path.exists() || !path.is_dir() {
-            return Err(format!("文件夹不存在: {}", config.folder_path));
+        {
+            let mut running = self.running.lock().unwrap();
+            if *running {
+                return Err(AppError::AlreadyRunning);
+            }
+            *running = true;
         }
+        *self.active_port.lock().unwrap() = Some(addr.port());
+
+        // 监视文件夹变化，watcher 需要在服务器运行期间一直存活
+        let (folder_watcher, folder_changes) = if config.watch_folder {
+            match watcher::watch_folder(&mounts) {
+                Ok((folder_watcher, sender)) => (Some(folder_watcher), Some(sender)),
+                Err(err) => {
+                    log::warn!("无法监视文件夹变化: {}", err);
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
 
         // 创建关闭通道
-        let (tx, rx) = oneshot::channel();
+        let (tx, rx) = oneshot::channel::<()>();
         *self.shutdown_sender.lock().unwrap() = Some(tx);
 
-        // 复制变量用于线程
-        let folder_path = config.folder_path.clone();
-        let port = config.port;
+        let state = Arc::new(ServeState {
+            name: self.name.clone(),
+            app: self.app.clone(),
+            mounts,
+            access_token: config.access_token.clone(),
+            allow_upload: config.allow_upload && config.access_token.is_some(),
+            webdav: config.webdav,
+            spa_fallback: config.spa_fallback,
+            thumbnail_dir: self.cache_dir.join("thumbnails"),
+            access_log: self.access_log.clone(),
+            limits: throttle::Limits::new(
+                config.max_connections,
+                config.connection_speed_limit,
+                config.global_speed_limit,
+            ),
+            ip_filter: self.ip_filter.clone(),
+            folder_changes,
+        });
+        let mut app = Router::new()
+            .fallback(handle_request)
+            .with_state(state.clone())
+            // 上传的文件可能很大，由访问令牌保护，不限制请求体大小
+            .layer(DefaultBodyLimit::disable());
+        if config.compression {
+            app = app.layer(
+                CompressionLayer::new()
+                    .gzip(true)
+                    .br(true)
+                    .compress_when(is_compressible_response),
+            );
+        }
+        if let Some(cors) = build_cors_layer(&config.cors_origins) {
+            app = app.layer(cors);
+        }
+        app = app.layer(middleware::from_fn_with_state(
+            state.clone(),
+            throttle::limit_request,
+        ));
+        // IP 过滤在限流之前进行，被拒绝的客户端不占用连接数
+        app = app.layer(middleware::from_fn_with_state(
+            state.clone(),
+            ip_filter::filter_request,
+        ));
+        // 访问日志放在最外层，记录所有请求的最终响应
+        let app = app
+            .layer(middleware::from_fn_with_state(state, log_request))
+            .into_make_service_with_connect_info::<SocketAddr>();
         let running_arc = self.running.clone();
+        let active_port = self.active_port.clone();
 
-        // 启动服务器线程
-        thread::spawn(move || {
-            let addr = format!("127.0.0.1:{}", port);
-            let server = match Server::http(&addr) {
-                Ok(server) => server,
-                Err(err) => {
-                    eprintln!("无法启动服务器: {}", err);
-                    *running_arc.lock().unwrap() = false;
-                    return;
+        // 服务器运行在应用自身的 tokio 运行时上
+        let task = tauri::async_runtime::spawn(async move {
+            let result = match tls {
+                Some(tls) => {
+                    log::info!("文件服务器启动在 https://{}", addr);
+                    serve_tls(listener, tls, app, rx).await
                 }
-            };
-
-            println!("文件服务器启动在 http://{}", addr);
-            *running_arc.lock().unwrap() = true;
-
-            // 创建一个异步运行时来处理关闭信号
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            let shutdown_future = async {
-                if let Err(_) = rx.await {
-                    // 关闭信号发送失败，可能发送端已关闭
+                None => {
+                    log::info!("文件服务器启动在 http://{}", addr);
+                    axum::serve(listener, app)
+                        .with_graceful_shutdown(async {
+                            let _ = rx.await;
+                        })
+                        .await
                 }
             };
-
-            // 在另一个线程中等待关闭信号
-            let server_ref = Arc::new(server);
-            let server_clone = server_ref.clone();
-            rt.spawn(async move {
-                shutdown_future.await;
-                let _ = server_clone.unblock(); // 解除阻塞，使服务器循环退出
-            });
-
-            // 处理请求
-            for request in server_ref.incoming_requests() {
-                let url_path = request.url();
-                let file_path = path.join(&url_path[1..]); // 移除前导斜杠
-
-                let response = if file_path.is_file() {
-                    match fs::read(&file_path) {
-                        Ok(content) => {
-                            // 简单的MIME类型检测
-                            let mime_type = match file_path.extension().and_then(|e| e.to_str()) {
-                                Some("html") => "text/html",
-                                Some("css") => "text/css",
-                                Some("js") => "application/javascript",
-                                Some("jpg") | Some("jpeg") => "image/jpeg",
-                                Some("png") => "image/png",
-                                Some("gif") => "image/gif",
-                                Some("svg") => "image/svg+xml",
-                                Some("json") => "application/json",
-                                _ => "application/octet-stream",
-                            };
-                            Response::from_data(content).with_header(tiny_http::Header {
-                                field: "Content-Type".parse().unwrap(),
-                                value: mime_type.parse().unwrap(),
-                            })
-                        }
-                        Err(err) => Response::from_string(format!("Error reading file: {}", err))
-                            .with_status_code(500),
-                    }
-                } else if file_path.is_dir() {
-                    // 生成目录列表
-                    match generate_directory_listing(&file_path, &folder_path, url_path) {
-                        Ok(listing) => {
-                            Response::from_string(listing).with_header(tiny_http::Header {
-                                field: "Content-Type".parse().unwrap(),
-                                value: "text/html; charset=utf-8".parse().unwrap(),
-                            })
-                        }
-                        Err(err) => {
-                            Response::from_string(format!("Error listing directory: {}", err))
-                                .with_status_code(500)
-                        }
-                    }
-                } else {
-                    Response::from_string("File not found").with_status_code(404)
-                };
-
-                if let Err(err) = request.respond(response) {
-                    eprintln!("Error sending response: {}", err);
-                }
+            if let Err(err) = result {
+                log::warn!("文件服务器异常退出: {}", err);
             }
 
             // 服务器停止
+            drop(folder_watcher);
             *running_arc.lock().unwrap() = false;
-            println!("文件服务器已停止");
+            *active_port.lock().unwrap() = None;
+            log::info!("文件服务器已停止");
         });
+        *self.server_task.lock().unwrap() = Some(task);
 
-        *running = true;
-        Ok(FileServerStatus {
-            running: true,
-            folder_path: config.folder_path,
-            port: config.port,
-        })
+        Ok(FileServerStatus::from_config(
+            &self.name,
+            config,
+            Some(addr.port()),
+        ))
     }
 
     // 停止文件服务器
-    pub fn stop_server(&self) -> Result<FileServerStatus, String> {
-        let mut running = self.running.lock().unwrap();
-        if !*running {
-            return Err("服务器未运行".to_string());
+    pub async fn stop_server(&self) -> AppResult<FileServerStatus> {
+        if !*self.running.lock().unwrap() {
+            return Err(AppError::NotRunning);
         }
 
         // 发送关闭信号
-        let mut sender = self.shutdown_sender.lock().unwrap();
-        if let Some(tx) = sender.take() {
+        let sender = self.shutdown_sender.lock().unwrap().take();
+        if let Some(tx) = sender {
             let _ = tx.send(());
         }
 
-        *running = false;
+        // 等待服务器任务退出，超时则强制终止，保证随后可以立即重新启动
+        let task = self.server_task.lock().unwrap().take();
+        if let Some(task) = task {
+            let abort_handle = task.inner().abort_handle();
+            if tokio::time::timeout(SHUTDOWN_TIMEOUT, task).await.is_err() {
+                abort_handle.abort();
+            }
+        }
+
+        *self.running.lock().unwrap() = false;
+        *self.active_port.lock().unwrap() = None;
         let config = self.config.lock().unwrap().clone();
 
-        Ok(FileServerStatus {
-            running: false,
-            folder_path: config.folder_path,
-            port: config.port,
-        })
+        Ok(FileServerStatus::from_config(&self.name, config, None))
     }
 
-    // 更新服务器配置
-    pub fn update_config(
-        &self,
-        folder_path: Option<String>,
-        port: Option<u16>,
-    ) -> Result<FileServerConfig, String> {
+    // 更新服务器配置，只修改传入的字段
+    // 任意字段校验失败时整个更新都不会生效
+    pub fn update_config(&self, update: FileServerConfigUpdate) -> AppResult<FileServerConfig> {
         let mut config = self.config.lock().unwrap();
+        let mut updated = config.clone();
 
-        if let Some(path) = folder_path {
-            config.folder_path = path;
+        if let Some(path) = update.folder_path {
+            updated.folder_path = path;
         }
 
-        if let Some(p) = port {
-            if p < 1024 || p > 65535 {
-                return Err("端口号必须在1024到65535之间".to_string());
+        if let Some(p) = update.port {
+            if p < 1024 {
+                return Err(AppError::InvalidConfig(
+                    "端口号必须在1024到65535之间".to_string(),
+                ));
             }
-            config.port = p;
+            updated.port = p;
         }
 
+        if let Some(auto_port) = update.auto_port {
+            updated.auto_port = auto_port;
+        }
+
+        if let Some(address) = update.bind_address {
+            if address.parse::<IpAddr>().is_err() {
+                return Err(AppError::InvalidConfig(format!(
+                    "无效的监听地址: {}",
+                    address
+                )));
+            }
+            updated.bind_address = address;
+        }
+
+        // "仅本机" 与 "所有网卡" 之间的快捷切换
+        if let Some(allow_lan) = update.allow_lan {
+            updated.bind_address = if allow_lan {
+                BIND_ALL_INTERFACES
+            } else {
+                BIND_LOCALHOST
+            }
+            .to_string();
+        }
+
+        // 传入空字符串表示关闭访问验证
+        if let Some(token) = update.access_token {
+            if token.is_empty() {
+                updated.access_token = None;
+            } else {
+                if !token
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    return Err(AppError::InvalidConfig(
+                        "访问令牌只能包含字母、数字、- 和 _".to_string(),
+                    ));
+                }
+                updated.access_token = Some(token);
+            }
+        }
+
+        if let Some(compression) = update.compression {
+            updated.compression = compression;
+        }
+
+        if let Some(origins) = update.cors_origins {
+            let origins: Vec<String> = origins
+                .into_iter()
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect();
+            for origin in &origins {
+                let valid = origin == "*"
+                    || ((origin.starts_with("http://") || origin.starts_with("https://"))
+                        && HeaderValue::from_str(origin).is_ok());
+                if !valid {
+                    return Err(AppError::InvalidConfig(format!(
+                        "无效的跨域来源: {}",
+                        origin
+                    )));
+                }
+            }
+            updated.cors_origins = origins;
+        }
+
+        if let Some(allow_upload) = update.allow_upload {
+            updated.allow_upload = allow_upload;
+        }
+        if let Some(webdav) = update.webdav {
+            updated.webdav = webdav;
+        }
+
+        if let Some(spa_fallback) = update.spa_fallback {
+            updated.spa_fallback = spa_fallback;
+        }
+
+        if let Some(max_connections) = update.max_connections {
+            updated.max_connections = max_connections;
+        }
+        if let Some(speed) = update.connection_speed_limit {
+            updated.connection_speed_limit = speed;
+        }
+        if let Some(speed) = update.global_speed_limit {
+            updated.global_speed_limit = speed;
+        }
+
+        if let Some(https) = update.https {
+            updated.https = https;
+        }
+        if let Some(cert_path) = update.tls_cert_path {
+            updated.tls_cert_path = (!cert_path.is_empty()).then_some(cert_path);
+        }
+        if let Some(key_path) = update.tls_key_path {
+            updated.tls_key_path = (!key_path.is_empty()).then_some(key_path);
+        }
+        if updated.tls_cert_path.is_some() != updated.tls_key_path.is_some() {
+            return Err(AppError::InvalidConfig(
+                "证书和私钥路径需要同时设置".to_string(),
+            ));
+        }
+
+        if let Some(watch_folder) = update.watch_folder {
+            updated.watch_folder = watch_folder;
+        }
+
+        if let Some(mounts) = update.mounts {
+            updated.mounts = mount::validate_mounts(mounts)?;
+        }
+
+        if let Some(auto_start) = update.auto_start {
+            updated.auto_start = auto_start;
+        }
+
+        if updated.allow_upload && updated.access_token.is_none() {
+            return Err(AppError::InvalidConfig(
+                "开启上传前需要先设置访问令牌".to_string(),
+            ));
+        }
+
+        *config = updated;
         Ok(config.clone())
     }
 
+    // 更新客户端 IP 过滤列表，运行中的服务器立即生效
+    pub fn update_ip_filter(
+        &self,
+        allowlist: Vec<String>,
+        denylist: Vec<String>,
+    ) -> AppResult<FileServerStatus> {
+        let allowlist = ip_filter::normalize_list(allowlist)?;
+        let denylist = ip_filter::normalize_list(denylist)?;
+        let filter = ip_filter::IpFilter::new(&allowlist, &denylist)?;
+
+        let mut config = self.config.lock().unwrap();
+        config.ip_allowlist = allowlist;
+        config.ip_denylist = denylist;
+        *self.ip_filter.write().unwrap() = filter;
+        let active_port = *self.active_port.lock().unwrap();
+        Ok(FileServerStatus::from_config(
+            &self.name,
+            config.clone(),
+            active_port,
+        ))
+    }
+
+    // 获取当前配置
+    pub fn get_config(&self) -> FileServerConfig {
+        self.config.lock().unwrap().clone()
+    }
+
     // 获取当前服务器状态
     pub fn get_status(&self) -> FileServerStatus {
-        let running = *self.running.lock().unwrap();
+        let active_port = *self.active_port.lock().unwrap();
         let config = self.config.lock().unwrap().clone();
 
-        FileServerStatus {
-            running,
-            folder_path: config.folder_path,
-            port: config.port,
+        FileServerStatus::from_config(&self.name, config, active_port)
+    }
+}
+
+// 默认文件服务器实例的名称
+pub const DEFAULT_SERVER_NAME: &str = "default";
+
+// 实例名称同时用作缓存目录名，只允许字母（包括中文等文字）、数字、下划线和短横线
+fn is_valid_server_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+// 管理多个具名文件服务器实例
+pub struct FileServerRegistry {
+    app: AppHandle,
+    servers: Mutex<HashMap<String, Arc<FileServerManager>>>,
+    cache_dir: PathBuf,
+    access_log: Arc<AccessLog>,
+    // 实例配置在每次修改后写入磁盘，下次启动时恢复
+    store: persist::ConfigStore,
+}
+
+impl FileServerRegistry {
+    // 创建注册表，恢复保存的实例，并保证包含一个默认实例
+    // cache_dir 下按实例名称存放缩略图等缓存
+    pub fn new(
+        app: &AppHandle,
+        cache_dir: PathBuf,
+        access_log: Arc<AccessLog>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = persist::ConfigStore::open(app)?;
+        let mut configs = store.load();
+        configs.retain(|name, _| {
+            let valid = is_valid_server_name(name);
+            if !valid {
+                log::warn!("忽略名称无效的文件服务器实例: {}", name);
+            }
+            valid
+        });
+        configs.entry(DEFAULT_SERVER_NAME.to_string()).or_default();
+
+        let servers = configs
+            .into_iter()
+            .map(|(name, config)| {
+                let server = Arc::new(FileServerManager::new(
+                    app.clone(),
+                    &name,
+                    config,
+                    cache_dir.join(&name),
+                    access_log.clone(),
+                ));
+                (name, server)
+            })
+            .collect();
+        Ok(FileServerRegistry {
+            app: app.clone(),
+            servers: Mutex::new(servers),
+            cache_dir,
+            access_log,
+            store,
+        })
+    }
+
+    // 清理所有实例中过期的缩略图缓存，返回删除的文件数量
+    pub fn purge_thumbnails(&self, max_age: Duration) -> usize {
+        let servers: Vec<Arc<FileServerManager>> =
+            self.servers.lock().unwrap().values().cloned().collect();
+        servers
+            .iter()
+            .map(|server| thumbnail::purge(&server.cache_dir.join("thumbnails"), max_age))
+            .sum()
+    }
+
+    // 启动所有开启了自动启动的实例，失败时只记录错误
+    pub fn auto_start(&self) {
+        let servers: Vec<Arc<FileServerManager>> = self
+            .servers
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|server| server.get_config().auto_start)
+            .cloned()
+            .collect();
+        for server in servers {
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = server.start_server().await {
+                    log::warn!("自动启动文件服务器 {} 失败: {}", server.name, err);
+                }
+            });
+        }
+    }
+
+    // 停止所有运行中的实例，退出程序前调用
+    pub async fn stop_all(&self) {
+        let servers: Vec<Arc<FileServerManager>> =
+            self.servers.lock().unwrap().values().cloned().collect();
+        for server in servers {
+            if !server.get_status().running {
+                continue;
+            }
+            if let Err(err) = server.stop_server().await {
+                log::warn!("停止文件服务器 {} 失败: {}", server.name, err);
+            }
+        }
+    }
+
+    // 将所有实例的配置写入磁盘
+    fn save(&self) {
+        let configs: HashMap<String, FileServerConfig> = self
+            .servers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, server)| (name.clone(), server.get_config()))
+            .collect();
+        self.store.save(&configs);
+    }
+
+    // 获取指定名称的实例，未指定名称时返回默认实例
+    pub fn get(&self, name: Option<&str>) -> AppResult<Arc<FileServerManager>> {
+        let name = name.unwrap_or(DEFAULT_SERVER_NAME);
+        self.servers
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AppError::ServerNotFound(name.to_string()))
+    }
+
+    // 创建新的文件服务器实例
+    pub fn create(
+        &self,
+        name: String,
+        folder_path: Option<String>,
+        port: Option<u16>,
+    ) -> AppResult<FileServerStatus> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(AppError::InvalidConfig(
+                "文件服务器名称不能为空".to_string(),
+            ));
+        }
+        if !is_valid_server_name(&name) {
+            return Err(AppError::InvalidConfig(
+                "文件服务器名称只能包含字母、数字、下划线和短横线".to_string(),
+            ));
+        }
+
+        let mut servers = self.servers.lock().unwrap();
+        if servers.contains_key(&name) {
+            return Err(AppError::ServerExists(name));
+        }
+
+        let port = port.unwrap_or(DEFAULT_PORT);
+        if port < 1024 {
+            return Err(AppError::InvalidConfig(
+                "端口号必须在1024到65535之间".to_string(),
+            ));
+        }
+        if servers
+            .values()
+            .any(|server| server.get_status().port == port)
+        {
+            return Err(AppError::InvalidConfig(format!(
+                "端口 {} 已被其他文件服务器使用",
+                port
+            )));
+        }
+
+        let config = FileServerConfig {
+            folder_path: folder_path.unwrap_or_default(),
+            port,
+            ..Default::default()
+        };
+        let server = Arc::new(FileServerManager::new(
+            self.app.clone(),
+            &name,
+            config,
+            self.cache_dir.join(&name),
+            self.access_log.clone(),
+        ));
+        let status = server.get_status();
+        servers.insert(name, server);
+        drop(servers);
+        self.save();
+        Ok(status)
+    }
+
+    // 更新实例配置并保存
+    pub fn update_config(
+        &self,
+        name: Option<&str>,
+        update: FileServerConfigUpdate,
+    ) -> AppResult<FileServerConfig> {
+        let config = self.get(name)?.update_config(update)?;
+        self.save();
+        Ok(config)
+    }
+
+    // 更新实例的 IP 过滤列表并保存
+    pub fn update_ip_filter(
+        &self,
+        name: Option<&str>,
+        allowlist: Vec<String>,
+        denylist: Vec<String>,
+    ) -> AppResult<FileServerStatus> {
+        let status = self.get(name)?.update_ip_filter(allowlist, denylist)?;
+        self.save();
+        Ok(status)
+    }
+
+    // 列出所有实例的状态
+    pub fn list(&self) -> Vec<FileServerStatus> {
+        let mut statuses: Vec<FileServerStatus> = self
+            .servers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|server| server.get_status())
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    // 查询访问日志，未指定名称时返回所有实例的记录
+    pub fn query_logs(&self, name: Option<&str>, limit: usize) -> Vec<AccessLogEntry> {
+        self.access_log.query(name, limit)
+    }
+
+    // 启动以来各实例按状态码统计的请求数量
+    pub fn request_counts(&self) -> Vec<(String, u16, u64)> {
+        self.access_log.request_counts()
+    }
+
+    // 运行中但无法在本机连接或不响应请求的实例名称
+    pub async fn unresponsive(&self) -> Vec<String> {
+        let servers: Vec<Arc<FileServerManager>> =
+            self.servers.lock().unwrap().values().cloned().collect();
+        let mut names = Vec::new();
+        for server in servers {
+            let Some(port) = *server.active_port.lock().unwrap() else {
+                continue;
+            };
+            let bind_address = server.get_config().bind_address;
+            if !probe(&bind_address, port).await {
+                names.push(server.name.clone());
+            }
         }
+        names
+    }
+
+    // 停止并重新启动运行中的实例
+    pub async fn restart(&self, name: &str) -> AppResult<FileServerStatus> {
+        let server = self.get(Some(name))?;
+        if server.get_status().running {
+            server.stop_server().await?;
+        }
+        server.start_server().await
+    }
+
+    // 删除实例，运行中的实例会先被停止
+    pub async fn delete(&self, name: &str) -> AppResult<()> {
+        if name == DEFAULT_SERVER_NAME {
+            return Err(AppError::DefaultServerProtected);
+        }
+
+        let server = self
+            .servers
+            .lock()
+            .unwrap()
+            .remove(name)
+            .ok_or_else(|| AppError::ServerNotFound(name.to_string()))?;
+        self.save();
+        if server.get_status().running {
+            server.stop_server().await?;
+        }
+        Ok(())
+    }
+}
+
+// 连接服务器并发送一行无效的请求，服务器回复错误或关闭连接都说明仍在处理连接
+// 无效请求在进入路由前就被拒绝，不会写入访问日志
+async fn probe(bind_address: &str, port: u16) -> bool {
+    let ip = match bind_address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) if ip.is_unspecified() => IpAddr::V4(std::net::Ipv4Addr::LOCALHOST),
+        Ok(IpAddr::V6(ip)) if ip.is_unspecified() => IpAddr::V6(std::net::Ipv6Addr::LOCALHOST),
+        Ok(ip) => ip,
+        Err(_) => return true,
+    };
+    let result = tokio::time::timeout(PROBE_TIMEOUT, async {
+        let mut stream = tokio::net::TcpStream::connect(SocketAddr::new(ip, port)).await?;
+        // 连接被重置同样说明服务器处理了这个连接
+        let _ = stream.write_all(b"PROBE\r\n\r\n").await;
+        let mut buf = [0u8; 1];
+        let _ = stream.read(&mut buf).await;
+        io::Result::Ok(())
+    })
+    .await;
+    matches!(result, Ok(Ok(())))
+}
+
+// 以 HTTPS 方式运行服务器，收到关闭信号后等待正在进行的请求完成
+async fn serve_tls(
+    listener: TcpListener,
+    tls: RustlsConfig,
+    app: IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+    shutdown: oneshot::Receiver<()>,
+) -> io::Result<()> {
+    let listener = listener.into_std()?;
+    listener.set_nonblocking(true)?;
+
+    let handle = axum_server::Handle::new();
+    let shutdown_handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let _ = shutdown.await;
+        shutdown_handle.graceful_shutdown(Some(SHUTDOWN_TIMEOUT));
+    });
+
+    axum_server::from_tcp_rustls(listener, tls)
+        .handle(handle)
+        .serve(app)
+        .await
+}
+
+// 根据监听地址计算局域网可访问的 IP 列表
+fn lan_ips_for(bind_address: &str) -> Vec<String> {
+    match bind_address.parse::<IpAddr>() {
+        Ok(ip) if ip.is_loopback() => Vec::new(),
+        Ok(ip) if ip.is_unspecified() => detect_lan_ips(),
+        Ok(ip) => vec![ip.to_string()],
+        Err(_) => Vec::new(),
+    }
+}
+
+// 检测本机所有网卡上的局域网 IPv4 地址
+fn detect_lan_ips() -> Vec<String> {
+    let networks = Networks::new_with_refreshed_list();
+    let mut ips: Vec<String> = networks
+        .iter()
+        .flat_map(|(_, data)| data.ip_networks().iter())
+        .filter_map(|network| match network.addr {
+            IpAddr::V4(ip) if !ip.is_loopback() && !ip.is_link_local() => Some(ip.to_string()),
+            _ => None,
+        })
+        .collect();
+    ips.sort();
+    ips.dedup();
+    ips
+}
+
+// 记录每个请求的访问日志
+async fn log_request(
+    State(state): State<Arc<ServeState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+
+    let response = next.run(request).await;
+
+    let bytes = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    state.access_log.record(AccessLogEntry {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        server: state.name.clone(),
+        client_ip: client.ip().to_string(),
+        method,
+        path,
+        status: response.status().as_u16(),
+        bytes,
+        duration_ms: started.elapsed().as_millis() as u64,
+    });
+    response
+}
+
+// 处理所有请求
+async fn handle_request(
+    State(state): State<Arc<ServeState>>,
+    Query(params): Query<HashMap<String, String>>,
+    request: Request,
+) -> Response {
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+    let headers = request.headers().clone();
+
+    // 访问验证，文件和目录列表都需要通过
+    let mut link_token = None;
+    if let Some(token) = &state.access_token {
+        let query_token = params.get("token").map(String::as_str);
+        if query_token == Some(token.as_str()) {
+            // 通过查询参数验证时，目录列表中的链接需要带上令牌
+            link_token = Some(token.clone());
+        } else if !header_token_matches(&headers, token) {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Basic realm=\"vtsuru\"")],
+                "Unauthorized",
+            )
+                .into_response();
+        }
+    }
+
+    let url_path = uri.path();
+
+    // WebDAV 模式下优先处理 PROPFIND 等扩展方法
+    if state.webdav {
+        if let Some(response) = webdav::handle(&state, &method, url_path, &headers).await {
+            return response;
+        }
+    }
+
+    // 运行指标，供 Prometheus 采集
+    if method == Method::GET && url_path == metrics::METRICS_PATH {
+        return metrics::handle(&state.app);
+    }
+
+    // 礼物图标、表情等 B 站图片的本地缓存，供浏览器源浮窗使用
+    if method == Method::GET && url_path.starts_with(cache::CACHE_PREFIX) {
+        return cache::handle(&state.app, url_path, &headers).await;
+    }
+
+    // 直播间事件等数据接口，供浏览器源浮窗使用
+    if url_path.starts_with(api::API_PREFIX) {
+        return api::handle(&state, &method, url_path, &params, &headers);
+    }
+
+    // 文件夹变化通知
+    if method == Method::GET && url_path == watcher::EVENTS_PATH {
+        return watcher::handle(&state);
+    }
+
+    // 缩略图接口
+    if method == Method::GET && url_path.starts_with(thumbnail::THUMB_PREFIX) {
+        return thumbnail::handle(&state, url_path, &params, &headers).await;
+    }
+
+    let located = mount::find(&state.mounts, url_path);
+
+    // 写入类请求
+    match method {
+        Method::GET | Method::HEAD => {}
+        Method::PUT | Method::POST if !state.allow_upload => {
+            return (StatusCode::FORBIDDEN, "Upload is disabled").into_response();
+        }
+        Method::PUT => {
+            return match located {
+                Some((mount, _)) if mount.read_only => {
+                    (StatusCode::FORBIDDEN, "Mount is read-only").into_response()
+                }
+                Some((mount, sub_path)) => handle_put_upload(&mount.root, sub_path, request).await,
+                None => (StatusCode::NOT_FOUND, "File not found").into_response(),
+            };
+        }
+        Method::POST if url_path == "/upload" => {
+            return handle_multipart_upload(&state, &params, request).await;
+        }
+        _ => return StatusCode::METHOD_NOT_ALLOWED.into_response(),
+    }
+
+    // 只由挂载点构成的虚拟目录（例如未设置根目录时的 /）
+    let resolved =
+        located.map(|(mount, sub_path)| (mount, resolve_request_path(&mount.root, sub_path)));
+    let (mount, mut file_path) = match resolved {
+        Some((mount, Ok(file_path))) => (mount, file_path),
+        // SPA 模式下，不存在的页面路径回退到挂载点根目录的 index.html，交给前端路由处理
+        Some((mount, Err(PathResolveError::NotFound)))
+            if state.spa_fallback && is_page_path(url_path) =>
+        {
+            (mount, mount.root.join(INDEX_FILE))
+        }
+        Some((_, Err(PathResolveError::NotFound))) | None => {
+            let entries = mount::child_entries(&state.mounts, url_path);
+            if entries.is_empty() {
+                return (StatusCode::NOT_FOUND, "File not found").into_response();
+            }
+            return directory_response(Ok(entries), url_path, &params, &headers, link_token);
+        }
+        Some((_, Err(err))) => return err.into_response(),
+    };
+
+    // 目录中存在 index.html 时直接返回该页面，而不是目录列表
+    // 显式请求 JSON 列表或 ZIP 下载时除外
+    let index_path = file_path.join(INDEX_FILE);
+    if file_path.is_dir()
+        && index_path.is_file()
+        && !params.contains_key("download")
+        && !wants_json(&params, &headers)
+    {
+        // 补全结尾的斜杠，保证页面中的相对路径指向该目录
+        if !url_path.ends_with('/') {
+            let location = match uri.query() {
+                Some(query) => format!("{}/?{}", url_path, query),
+                None => format!("{}/", url_path),
+            };
+            return Redirect::permanent(&location).into_response();
+        }
+        file_path = index_path;
+    }
+
+    let metadata = match tokio::fs::metadata(&file_path).await {
+        Ok(metadata) => metadata,
+        Err(_) => return (StatusCode::NOT_FOUND, "File not found").into_response(),
+    };
+
+    if metadata.is_file() {
+        match serve_file(&file_path, &headers).await {
+            Ok(response) => response,
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error reading file: {}", err),
+            )
+                .into_response(),
+        }
+    } else if metadata.is_dir() {
+        if params.get("download").map(String::as_str) == Some("zip") {
+            return archive::zip_directory_response(file_path);
+        }
+
+        // 挂载在该目录下的挂载点以子目录的形式一并列出
+        let entries = list_directory_entries(&file_path).map(|mut entries| {
+            if mount.prefix.is_empty() {
+                mount::merge_child_entries(&state.mounts, url_path, &mut entries);
+            }
+            entries
+        });
+        directory_response(entries, url_path, &params, &headers, link_token)
+    } else {
+        (StatusCode::NOT_FOUND, "File not found").into_response()
+    }
+}
+
+// 返回目录列表，客户端请求 JSON 时返回结构化数据，否则返回 HTML 页面
+fn directory_response(
+    entries: io::Result<Vec<DirectoryEntry>>,
+    url_path: &str,
+    params: &HashMap<String, String>,
+    headers: &HeaderMap,
+    link_token: Option<String>,
+) -> Response {
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error listing directory: {}", err),
+            )
+                .into_response()
+        }
+    };
+
+    if wants_json(params, headers) {
+        return Json(DirectoryListing {
+            path: url_path.to_string(),
+            entries,
+        })
+        .into_response();
+    }
+
+    let options = ListingOptions::from_params(params, link_token);
+    (
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        generate_directory_listing(entries, url_path, &options),
+    )
+        .into_response()
+}
+
+// 判断路径是否像页面路由（最后一段不带扩展名），静态资源缺失时仍然返回 404
+fn is_page_path(url_path: &str) -> bool {
+    url_path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .map(|segment| !segment.contains('.'))
+        .unwrap_or(true)
+}
+
+// PUT /<path>：将请求体写入对应文件，已存在的文件会被覆盖
+async fn handle_put_upload(root: &Path, url_path: &str, request: Request) -> Response {
+    let target = match resolve_upload_path(root, url_path) {
+        Ok(target) => target,
+        Err(err) => return err.into_response(),
+    };
+
+    let stream = request
+        .into_body()
+        .into_data_stream()
+        .map(|chunk| chunk.map_err(io::Error::other));
+    match write_upload(&target, StreamReader::new(stream)).await {
+        Ok(()) => StatusCode::CREATED.into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Error writing file: {}", err),
+        )
+            .into_response(),
+    }
+}
+
+// POST /upload：multipart 表单上传，可通过 ?dir= 指定保存的子目录
+async fn handle_multipart_upload(
+    state: &Arc<ServeState>,
+    params: &HashMap<String, String>,
+    request: Request,
+) -> Response {
+    let dir = params.get("dir").map(String::as_str).unwrap_or("/");
+    let (mount, sub_path) = match mount::find(&state.mounts, dir) {
+        Some(located) => located,
+        None => return (StatusCode::NOT_FOUND, "Directory not found").into_response(),
+    };
+    if mount.read_only {
+        return (StatusCode::FORBIDDEN, "Mount is read-only").into_response();
+    }
+    let target_dir = match resolve_request_path(&mount.root, sub_path) {
+        Ok(target_dir) if target_dir.is_dir() => target_dir,
+        Ok(_) => return (StatusCode::BAD_REQUEST, "Not a directory").into_response(),
+        Err(err) => return err.into_response(),
+    };
+
+    let mut multipart = match Multipart::from_request(request, state).await {
+        Ok(multipart) => multipart,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let mut saved = Vec::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+        };
+
+        // 只保留文件名部分，忽略客户端传来的目录
+        let file_name = match field
+            .file_name()
+            .and_then(|name| Path::new(name).file_name())
+            .and_then(|name| name.to_str())
+        {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let stream = field.map(|chunk| chunk.map_err(io::Error::other));
+        if let Err(err) =
+            write_upload(&target_dir.join(&file_name), StreamReader::new(stream)).await
+        {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error writing file: {}", err),
+            )
+                .into_response();
+        }
+        saved.push(file_name);
+    }
+
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({ "files": saved })),
+    )
+        .into_response()
+}
+
+// 先写入临时文件，完成后再重命名，避免中途失败留下不完整的文件
+async fn write_upload<R>(target: &Path, reader: R) -> io::Result<()>
+where
+    R: AsyncRead,
+{
+    let mut reader = std::pin::pin!(reader);
+    let mut temp_name = target.as_os_str().to_owned();
+    temp_name.push(".uploading");
+    let temp_path = PathBuf::from(temp_name);
+
+    let result = async {
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+        tokio::io::copy(&mut reader, &mut file).await?;
+        file.flush().await?;
+        drop(file);
+        tokio::fs::rename(&temp_path, target).await
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+    result
+}
+
+// 请求路径解析失败的原因
+#[derive(Debug, PartialEq, Eq)]
+pub enum PathResolveError {
+    // 路径无法解码
+    BadRequest,
+    // 路径指向根目录之外
+    Forbidden,
+    // 路径不存在
+    NotFound,
+}
+
+impl IntoResponse for PathResolveError {
+    fn into_response(self) -> Response {
+        match self {
+            PathResolveError::BadRequest => (StatusCode::BAD_REQUEST, "Bad request"),
+            PathResolveError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            PathResolveError::NotFound => (StatusCode::NOT_FOUND, "File not found"),
+        }
+        .into_response()
+    }
+}
+
+// 将请求的 URL 路径解析为根目录下的真实路径
+// root 必须是已经规范化的路径；解码后的路径会被再次规范化，
+// 任何解析到 root 之外的路径（包括 ..%2f 之类的编码和指向外部的符号链接）都会被拒绝
+pub fn resolve_request_path(root: &Path, url_path: &str) -> Result<PathBuf, PathResolveError> {
+    let joined = join_request_path(root, url_path)?;
+    let resolved = joined
+        .canonicalize()
+        .map_err(|_| PathResolveError::NotFound)?;
+    if !resolved.starts_with(root) {
+        return Err(PathResolveError::Forbidden);
+    }
+    Ok(resolved)
+}
+
+// 解析上传目标路径，目标文件可以不存在，但其所在目录必须存在且位于 root 之内
+pub fn resolve_upload_path(root: &Path, url_path: &str) -> Result<PathBuf, PathResolveError> {
+    let joined = join_request_path(root, url_path)?;
+    let file_name = match joined.file_name() {
+        Some(file_name) if joined != root => file_name.to_owned(),
+        _ => return Err(PathResolveError::BadRequest),
+    };
+    let parent = joined
+        .parent()
+        .ok_or(PathResolveError::BadRequest)?
+        .canonicalize()
+        .map_err(|_| PathResolveError::NotFound)?;
+    if !parent.starts_with(root) {
+        return Err(PathResolveError::Forbidden);
+    }
+    let target = parent.join(file_name);
+    if target.is_dir() {
+        return Err(PathResolveError::BadRequest);
+    }
+    Ok(target)
+}
+
+// 解码 URL 路径并逐段拼接到 root 上，拒绝 .. 等越界片段
+fn join_request_path(root: &Path, url_path: &str) -> Result<PathBuf, PathResolveError> {
+    let decoded = percent_decode_str(url_path)
+        .decode_utf8()
+        .map_err(|_| PathResolveError::BadRequest)?;
+    if decoded.contains('\0') {
+        return Err(PathResolveError::BadRequest);
+    }
+
+    let mut joined = root.to_path_buf();
+    for segment in decoded.split(['/', '\\']) {
+        match segment {
+            "" | "." => continue,
+            ".." => return Err(PathResolveError::Forbidden),
+            // 拒绝 Windows 盘符等会替换整个路径的片段
+            _ if Path::new(segment).has_root() || segment.contains(':') => {
+                return Err(PathResolveError::Forbidden)
+            }
+            _ => joined.push(segment),
+        }
+    }
+    Ok(joined)
+}
+
+// 检查 Authorization 请求头中的令牌
+// 支持 "Bearer <token>"，以及密码为令牌的 Basic 验证（用户名任意）
+fn header_token_matches(headers: &HeaderMap, token: &str) -> bool {
+    let value = match headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(value) => value.trim(),
+        None => return false,
+    };
+
+    if let Some(bearer) = value.strip_prefix("Bearer ") {
+        return bearer.trim() == token;
+    }
+
+    if let Some(basic) = value.strip_prefix("Basic ") {
+        let decoded = match BASE64_STANDARD.decode(basic.trim()) {
+            Ok(decoded) => decoded,
+            Err(_) => return false,
+        };
+        return match String::from_utf8(decoded) {
+            Ok(credentials) => credentials
+                .split_once(':')
+                .map(|(_, password)| password == token)
+                .unwrap_or(false),
+            Err(_) => false,
+        };
+    }
+
+    false
+}
+
+// 根据配置构造 CORS 中间件，同时负责处理 OPTIONS 预检请求
+fn build_cors_layer(origins: &[String]) -> Option<CorsLayer> {
+    if origins.is_empty() {
+        return None;
+    }
+
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(AllowMethods::mirror_request())
+            .allow_headers(AllowHeaders::mirror_request())
+            .expose_headers([
+                header::CONTENT_LENGTH,
+                header::CONTENT_RANGE,
+                header::ACCEPT_RANGES,
+                header::ETAG,
+                header::LAST_MODIFIED,
+            ]),
+    )
+}
+
+// 只压缩完整返回的文本类响应，Range 响应和已压缩的媒体文件保持原样
+fn is_compressible_response(
+    status: StatusCode,
+    _version: Version,
+    headers: &HeaderMap,
+    _extensions: &Extensions,
+) -> bool {
+    if status != StatusCode::OK {
+        return false;
+    }
+    let content_type = match headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(content_type) => content_type,
+        None => return false,
+    };
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    // SSE 需要逐条推送，压缩会导致事件被缓冲
+    if mime == "text/event-stream" {
+        return false;
+    }
+    mime.starts_with("text/")
+        || mime == "application/javascript"
+        || mime == "application/json"
+        || mime == "image/svg+xml"
+}
+
+// 根据扩展名检测MIME类型
+// 常用的媒体、字体类型在这里固定下来，其余交给 mime_guess 的数据库
+fn mime_type_for(path: &Path) -> String {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let mime = match extension.as_deref() {
+        Some("html") | Some("htm") => "text/html".to_string(),
+        Some("js") | Some("mjs") => "application/javascript".to_string(),
+        Some("json") => "application/json".to_string(),
+        Some("mp4") => "video/mp4".to_string(),
+        Some("webm") => "video/webm".to_string(),
+        Some("mp3") => "audio/mpeg".to_string(),
+        Some("ogg") => "audio/ogg".to_string(),
+        Some("woff2") => "font/woff2".to_string(),
+        Some("woff") => "font/woff".to_string(),
+        Some("wasm") => "application/wasm".to_string(),
+        Some("webp") => "image/webp".to_string(),
+        _ => mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .essence_str()
+            .to_string(),
+    };
+
+    // 文本类型统一声明为 UTF-8，避免中文内容乱码
+    if mime.starts_with("text/")
+        || mime == "application/javascript"
+        || mime == "application/json"
+        || mime == "image/svg+xml"
+    {
+        format!("{}; charset=utf-8", mime)
+    } else {
+        mime
+    }
+}
+
+// 解析 Range 请求头，返回闭区间 (start, end)
+// Ok(None) 表示忽略该请求头按完整文件返回，Err 表示范围无法满足 (416)
+fn parse_range(value: &str, file_len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let spec = match value.trim().strip_prefix("bytes=") {
+        Some(spec) => spec.trim(),
+        None => return Ok(None),
+    };
+    // 多区间请求较少见，直接返回完整文件
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let (start, end) = match spec.split_once('-') {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+
+    let (start, end) = if start.is_empty() {
+        // bytes=-N 表示最后 N 个字节
+        let suffix: u64 = match end.parse() {
+            Ok(n) => n,
+            Err(_) => return Ok(None),
+        };
+        if suffix == 0 || file_len == 0 {
+            return Err(());
+        }
+        (file_len.saturating_sub(suffix), file_len - 1)
+    } else {
+        let start: u64 = match start.parse() {
+            Ok(n) => n,
+            Err(_) => return Ok(None),
+        };
+        let end: u64 = if end.is_empty() {
+            file_len.saturating_sub(1)
+        } else {
+            match end.parse::<u64>() {
+                Ok(n) => n.min(file_len.saturating_sub(1)),
+                Err(_) => return Ok(None),
+            }
+        };
+        (start, end)
+    };
+
+    if file_len == 0 || start >= file_len || start > end {
+        return Err(());
+    }
+    Ok(Some((start, end)))
+}
+
+// 根据文件大小和修改时间计算 ETag
+fn compute_etag(file_len: u64, modified: SystemTime) -> String {
+    let mtime = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", file_len, mtime)
+}
+
+// 判断条件请求是否可以直接返回 304
+// If-None-Match 优先于 If-Modified-Since
+fn is_not_modified(headers: &HeaderMap, etag: &str, modified: SystemTime) -> bool {
+    if let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    {
+        return if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag);
+    }
+
+    if let Some(since) = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+    {
+        // HTTP 日期只精确到秒
+        let modified_secs = modified
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(u64::MAX);
+        let since_secs = since
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        return modified_secs <= since_secs;
+    }
+
+    false
+}
+
+// 以流的方式构造文件响应，支持单区间 Range 请求和条件请求
+// 文件内容不会一次性读入内存，而是在发送时分块读取
+async fn serve_file(file_path: &Path, request_headers: &HeaderMap) -> io::Result<Response> {
+    let mut file = tokio::fs::File::open(file_path).await?;
+    let metadata = file.metadata().await?;
+    let file_len = metadata.len();
+    let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+    let mime_type = mime_type_for(file_path);
+    let etag = compute_etag(file_len, modified);
+    let last_modified = httpdate::fmt_http_date(modified);
+
+    if is_not_modified(request_headers, &etag, modified) {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::LAST_MODIFIED, last_modified)],
+        )
+            .into_response());
+    }
+
+    // If-Range 与当前 ETag 不一致时忽略 Range，返回完整文件
+    let range_header = request_headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| {
+            request_headers
+                .get(header::IF_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .map(|if_range| if_range.trim() == etag)
+                .unwrap_or(true)
+        });
+
+    let range = match range_header.map(|value| parse_range(value, file_len)) {
+        Some(Ok(range)) => range,
+        Some(Err(())) => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", file_len))],
+                "Range Not Satisfiable",
+            )
+                .into_response());
+        }
+        None => None,
+    };
+
+    let mut response = match range {
+        Some((start, end)) => {
+            let length = end - start + 1;
+            file.seek(SeekFrom::Start(start)).await?;
+            let body = Body::from_stream(ReaderStream::new(file.take(length)));
+            (
+                StatusCode::PARTIAL_CONTENT,
+                [
+                    (
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", start, end, file_len),
+                    ),
+                    (header::CONTENT_LENGTH, length.to_string()),
+                ],
+                body,
+            )
+                .into_response()
+        }
+        None => {
+            let body = Body::from_stream(ReaderStream::new(file));
+            ([(header::CONTENT_LENGTH, file_len.to_string())], body).into_response()
+        }
+    };
+
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&mime_type) {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&last_modified) {
+        headers.insert(header::LAST_MODIFIED, value);
+    }
+    Ok(response)
+}
+
+// 目录中的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryEntry {
+    pub name: String,
+    pub size: u64,
+    // 修改时间，Unix 毫秒时间戳
+    pub mtime: u64,
+    pub is_dir: bool,
+}
+
+// JSON 格式的目录列表
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryListing {
+    pub path: String,
+    pub entries: Vec<DirectoryEntry>,
+}
+
+// 判断请求是否需要 JSON 格式的目录列表（?format=json 或 Accept: application/json）
+fn wants_json(params: &HashMap<String, String>, headers: &HeaderMap) -> bool {
+    if let Some(format) = params.get("format") {
+        return format == "json";
+    }
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|accept| accept.contains("application/json") && !accept.contains("text/html"))
+        .unwrap_or(false)
+}
+
+// 读取目录内容，目录在前，其余按名称排序
+fn list_directory_entries(dir_path: &Path) -> io::Result<Vec<DirectoryEntry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir_path)?.flatten() {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        // 跟随符号链接获取元数据，失败时跳过该项
+        let metadata = match fs::metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+        entries.push(DirectoryEntry {
+            name,
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            mtime,
+            is_dir: metadata.is_dir(),
+        });
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+// 目录列表每页显示的条目数
+const LISTING_PAGE_SIZE: usize = 200;
+
+// 目录列表页面模板
+const LISTING_TEMPLATE: &str = include_str!("file_server/listing.html");
+
+// 目录列表的排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListingSort {
+    Name,
+    Size,
+    Date,
+}
+
+impl ListingSort {
+    fn as_str(self) -> &'static str {
+        match self {
+            ListingSort::Name => "name",
+            ListingSort::Size => "size",
+            ListingSort::Date => "date",
+        }
+    }
+}
+
+// 目录列表的排序、分页参数
+struct ListingOptions {
+    sort: ListingSort,
+    descending: bool,
+    page: usize,
+    // 通过查询参数验证时，页面中的链接需要带上令牌
+    token: Option<String>,
+}
+
+impl ListingOptions {
+    fn from_params(params: &HashMap<String, String>, token: Option<String>) -> Self {
+        let sort = match params.get("sort").map(String::as_str) {
+            Some("size") => ListingSort::Size,
+            Some("date") => ListingSort::Date,
+            _ => ListingSort::Name,
+        };
+        ListingOptions {
+            sort,
+            descending: params.get("order").map(String::as_str) == Some("desc"),
+            page: params
+                .get("page")
+                .and_then(|page| page.parse().ok())
+                .unwrap_or(1)
+                .max(1),
+            token,
+        }
+    }
+
+    // 生成指定排序和页码的查询字符串
+    fn query(&self, sort: ListingSort, descending: bool, page: usize) -> String {
+        let mut query = format!(
+            "?sort={}&order={}",
+            sort.as_str(),
+            if descending { "desc" } else { "asc" }
+        );
+        if page > 1 {
+            query.push_str(&format!("&page={}", page));
+        }
+        if let Some(token) = &self.token {
+            query.push_str(&format!("&token={}", token));
+        }
+        query
+    }
+
+    // 普通链接只需要带上令牌
+    fn token_query(&self, first: bool) -> String {
+        match &self.token {
+            Some(token) => format!("{}token={}", if first { '?' } else { '&' }, token),
+            None => String::new(),
+        }
+    }
+}
+
+// 转义 HTML / XML 文本
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 将字节数格式化为易读的大小
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", size)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+// 根据文件类型选择图标
+fn icon_for(entry: &DirectoryEntry) -> &'static str {
+    if entry.is_dir {
+        return "📁";
+    }
+    let mime = mime_type_for(Path::new(&entry.name));
+    if mime.starts_with("image/") {
+        "🖼️"
+    } else if mime.starts_with("video/") {
+        "🎬"
+    } else if mime.starts_with("audio/") {
+        "🎵"
+    } else if mime.starts_with("font/") {
+        "🔤"
+    } else {
+        "📄"
     }
 }
 
 // 生成目录列表HTML
 fn generate_directory_listing(
-    dir_path: &PathBuf,
-    base_path: &str,
+    mut entries: Vec<DirectoryEntry>,
     url_path: &str,
-) -> io::Result<String> {
-    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<title>目录列表</title>\n");
-    html.push_str("<style>body{font-family:Arial,sans-serif;margin:20px;}h1{color:#333;}ul{list-style-type:none;padding:0;}li{margin:5px 0;}a{text-decoration:none;color:#0077cc;}a:hover{text-decoration:underline;}</style>\n");
-    html.push_str("</head>\n<body>\n");
-    html.push_str(&format!("<h1>目录: {}</h1>\n<ul>\n", url_path));
+    options: &ListingOptions,
+) -> String {
+    entries.sort_by(|a, b| {
+        let ordering = match options.sort {
+            ListingSort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            ListingSort::Size => a.size.cmp(&b.size),
+            ListingSort::Date => a.mtime.cmp(&b.mtime),
+        };
+        let ordering = if options.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        // 目录始终排在文件前面
+        b.is_dir.cmp(&a.is_dir).then(ordering)
+    });
+
+    let total = entries.len();
+    let pages = total.div_ceil(LISTING_PAGE_SIZE).max(1);
+    let page = options.page.min(pages);
+    let dir_url = url_path.trim_end_matches('/');
 
+    let mut rows = String::new();
     // 如果不是根目录，添加返回上级目录的链接
-    if url_path != "/" {
-        let parent = url_path.rsplitn(2, '/').collect::<Vec<&str>>();
-        if parent.len() > 1 {
-            let parent_url = if parent[1].is_empty() { "/" } else { parent[1] };
-            html.push_str(&format!(
-                "<li><a href=\"{}\">..</a> (上级目录)</li>\n",
-                parent_url
+    if !dir_url.is_empty() {
+        let parent_url = match dir_url.rsplit_once('/') {
+            Some((parent, _)) if !parent.is_empty() => format!("{}/", parent),
+            _ => "/".to_string(),
+        };
+        rows.push_str(&format!(
+            "<tr><td><span class=\"icon\">⬆️</span><a href=\"{}{}\">..</a> (上级目录)</td><td></td><td></td></tr>\n",
+            parent_url,
+            options.token_query(true)
+        ));
+    }
+
+    for entry in entries
+        .iter()
+        .skip((page - 1) * LISTING_PAGE_SIZE)
+        .take(LISTING_PAGE_SIZE)
+    {
+        let encoded = utf8_percent_encode(&entry.name, PATH_SEGMENT).to_string();
+        let file_url = format!(
+            "{}/{}{}",
+            dir_url,
+            encoded,
+            if entry.is_dir { "/" } else { "" }
+        );
+        let icon = if !entry.is_dir && thumbnail::is_thumbnailable(Path::new(&entry.name)) {
+            format!(
+                "<img class=\"thumb\" src=\"/thumb{}/{}?w=64{}\" loading=\"lazy\">",
+                dir_url,
+                encoded,
+                options.token_query(false)
+            )
+        } else {
+            format!("<span class=\"icon\">{}</span>", icon_for(entry))
+        };
+        let size = if entry.is_dir {
+            "-".to_string()
+        } else {
+            format_size(entry.size)
+        };
+        let date = Local
+            .timestamp_millis_opt(entry.mtime as i64)
+            .single()
+            .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        rows.push_str(&format!(
+            "<tr><td>{}<a href=\"{}{}\">{}</a></td><td class=\"size\">{}</td><td class=\"date\">{}</td></tr>\n",
+            icon,
+            file_url,
+            options.token_query(true),
+            escape_html(&entry.name),
+            size,
+            date
+        ));
+    }
+
+    // 表头排序链接，点击当前排序列时切换升降序
+    let sort_link = |sort: ListingSort, label: &str| {
+        let active = options.sort == sort;
+        let descending = active && !options.descending;
+        let arrow = match (active, options.descending) {
+            (true, false) => " ▲",
+            (true, true) => " ▼",
+            _ => "",
+        };
+        format!(
+            "<a href=\"{}\">{}{}</a>",
+            options.query(sort, descending, 1),
+            label,
+            arrow
+        )
+    };
+
+    let mut pagination = String::new();
+    if pages > 1 {
+        if page > 1 {
+            pagination.push_str(&format!(
+                "<a href=\"{}\">上一页</a>",
+                options.query(options.sort, options.descending, page - 1)
+            ));
+        }
+        pagination.push_str(&format!("<span>第 {} / {} 页</span>", page, pages));
+        if page < pages {
+            pagination.push_str(&format!(
+                "<a href=\"{}\">下一页</a>",
+                options.query(options.sort, options.descending, page + 1)
             ));
         }
     }
 
-    // 列出目录内容
-    let entries = fs::read_dir(dir_path)?;
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if let Some(file_name) = path.file_name() {
-                if let Some(file_name_str) = file_name.to_str() {
-                    let file_url = format!(
-                        "{}{}{}",
-                        url_path.trim_end_matches('/'),
-                        if url_path.ends_with('/') { "" } else { "/" },
-                        file_name_str
-                    );
-
-                    let file_type = if path.is_dir() { "目录" } else { "文件" };
-                    html.push_str(&format!(
-                        "<li><a href=\"{}\">{}</a> ({})</li>\n",
-                        file_url, file_name_str, file_type
-                    ));
-                }
-            }
+    let zip_link = match &options.token {
+        Some(token) => format!("?token={}&download=zip", token),
+        None => "?download=zip".to_string(),
+    };
+
+    LISTING_TEMPLATE
+        .replace("{{title}}", &escape_html(url_path))
+        .replace("{{summary}}", &format!("共 {} 项", total))
+        .replace("{{zip_link}}", &zip_link)
+        .replace("{{sort_name}}", &sort_link(ListingSort::Name, "名称"))
+        .replace("{{sort_size}}", &sort_link(ListingSort::Size, "大小"))
+        .replace("{{sort_date}}", &sort_link(ListingSort::Date, "修改时间"))
+        .replace("{{pagination}}", &pagination)
+        .replace("{{rows}}", &rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_request_path, resolve_upload_path, PathResolveError};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    // 临时目录结构：
+    // <base>/root/dir/file.txt
+    // <base>/outside/secret.txt
+    struct Fixture {
+        base: PathBuf,
+        root: PathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let base = std::env::temp_dir().join(format!(
+                "vtsuru-path-test-{}",
+                uuid::Uuid::new_v4().simple()
+            ));
+            fs::create_dir_all(base.join("root/dir")).unwrap();
+            fs::create_dir_all(base.join("outside")).unwrap();
+            fs::write(base.join("root/dir/file.txt"), "ok").unwrap();
+            fs::write(base.join("outside/secret.txt"), "secret").unwrap();
+            let base = base.canonicalize().unwrap();
+            let root = base.join("root");
+            Fixture { base, root }
+        }
+
+        fn root(&self) -> &Path {
+            &self.root
         }
     }
 
-    html.push_str("</ul>\n</body>\n</html>");
-    Ok(html)
-}
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.base);
+        }
+    }
+
+    #[test]
+    fn resolves_existing_file() {
+        let fixture = Fixture::new();
+        assert_eq!(
+            resolve_request_path(fixture.root(), "/dir/file.txt"),
+            Ok(fixture.root().join("dir/file.txt"))
+        );
+        assert_eq!(
+            resolve_request_path(fixture.root(), "/"),
+            Ok(fixture.root().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn rejects_parent_segments() {
+        let fixture = Fixture::new();
+        for path in [
+            "/../outside/secret.txt",
+            "/dir/../../outside/secret.txt",
+            "/dir/..%2f..%2foutside/secret.txt",
+            "/dir/..%2F..%2Foutside/secret.txt",
+            "/%2e%2e/outside/secret.txt",
+            "/%2E%2E/outside/secret.txt",
+            // 即使最终仍在根目录内也拒绝
+            "/dir/../dir/file.txt",
+        ] {
+            assert_eq!(
+                resolve_request_path(fixture.root(), path),
+                Err(PathResolveError::Forbidden),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_backslash_traversal() {
+        let fixture = Fixture::new();
+        for path in [
+            "/dir\\..\\..\\outside\\secret.txt",
+            "/dir%5c..%5c..%5coutside%5csecret.txt",
+            "/%5C..%5Coutside%5Csecret.txt",
+        ] {
+            assert_eq!(
+                resolve_request_path(fixture.root(), path),
+                Err(PathResolveError::Forbidden),
+                "{}",
+                path
+            );
+        }
+        // 反斜杠作为分隔符处理
+        assert_eq!(
+            resolve_request_path(fixture.root(), "/dir%5cfile.txt"),
+            Ok(fixture.root().join("dir/file.txt"))
+        );
+    }
+
+    #[test]
+    fn rejects_drive_letters() {
+        let fixture = Fixture::new();
+        for path in ["/C:/Windows", "/C:%5cWindows", "/dir/D:", "/c%3a/Windows"] {
+            assert_eq!(
+                resolve_request_path(fixture.root(), path),
+                Err(PathResolveError::Forbidden),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn absolute_paths_stay_inside_root() {
+        let fixture = Fixture::new();
+        let secret = fixture.base.join("outside/secret.txt");
+        let url = format!("/{}", secret.display());
+        // 绝对路径按根目录下的相对路径处理，不会读取到根目录外的文件
+        assert_eq!(
+            resolve_request_path(fixture.root(), &url),
+            Err(PathResolveError::NotFound)
+        );
+        assert_eq!(
+            resolve_request_path(fixture.root(), "//dir//file.txt"),
+            Ok(fixture.root().join("dir/file.txt"))
+        );
+    }
+
+    #[test]
+    fn rejects_nul_and_invalid_encoding() {
+        let fixture = Fixture::new();
+        for path in ["/dir/file.txt%00", "/dir/file%00.txt", "/%ff%fe"] {
+            assert_eq!(
+                resolve_request_path(fixture.root(), path),
+                Err(PathResolveError::BadRequest),
+                "{}",
+                path
+            );
+            assert_eq!(
+                resolve_upload_path(fixture.root(), path),
+                Err(PathResolveError::BadRequest),
+                "{}",
+                path
+            );
+        }
+    }
 
-// 创建文件服务器管理器的单例
-lazy_static::lazy_static! {
-    pub static ref FILE_SERVER: FileServerManager = FileServerManager::new();
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlink_escaping_root() {
+        let fixture = Fixture::new();
+        std::os::unix::fs::symlink(fixture.base.join("outside"), fixture.root().join("link"))
+            .unwrap();
+        assert_eq!(
+            resolve_request_path(fixture.root(), "/link/secret.txt"),
+            Err(PathResolveError::Forbidden)
+        );
+        assert_eq!(
+            resolve_request_path(fixture.root(), "/link"),
+            Err(PathResolveError::Forbidden)
+        );
+        assert_eq!(
+            resolve_upload_path(fixture.root(), "/link/new.txt"),
+            Err(PathResolveError::Forbidden)
+        );
+    }
+
+    #[test]
+    fn distinguishes_not_found_from_forbidden() {
+        let fixture = Fixture::new();
+        assert_eq!(
+            resolve_request_path(fixture.root(), "/missing.txt"),
+            Err(PathResolveError::NotFound)
+        );
+        assert_eq!(
+            resolve_request_path(fixture.root(), "/dir/missing/file.txt"),
+            Err(PathResolveError::NotFound)
+        );
+        assert_eq!(
+            resolve_request_path(fixture.root(), "/../missing.txt"),
+            Err(PathResolveError::Forbidden)
+        );
+    }
+
+    #[test]
+    fn resolves_upload_targets() {
+        let fixture = Fixture::new();
+        assert_eq!(
+            resolve_upload_path(fixture.root(), "/dir/new.txt"),
+            Ok(fixture.root().join("dir/new.txt"))
+        );
+        // 覆盖已有文件
+        assert_eq!(
+            resolve_upload_path(fixture.root(), "/dir/file.txt"),
+            Ok(fixture.root().join("dir/file.txt"))
+        );
+        // 上级目录还不存在
+        assert_eq!(
+            resolve_upload_path(fixture.root(), "/missing/new.txt"),
+            Err(PathResolveError::NotFound)
+        );
+        // 根目录和已有目录不能作为上传目标
+        assert_eq!(
+            resolve_upload_path(fixture.root(), "/"),
+            Err(PathResolveError::BadRequest)
+        );
+        assert_eq!(
+            resolve_upload_path(fixture.root(), "/dir"),
+            Err(PathResolveError::BadRequest)
+        );
+        assert_eq!(
+            resolve_upload_path(fixture.root(), "/../new.txt"),
+            Err(PathResolveError::Forbidden)
+        );
+        assert_eq!(
+            resolve_upload_path(fixture.root(), "/dir/..%2f..%2fnew.txt"),
+            Err(PathResolveError::Forbidden)
+        );
+    }
+
+    #[test]
+    fn validates_server_names() {
+        for name in ["default", "obs-2", "直播_素材"] {
+            assert!(is_valid_server_name(name), "{}", name);
+        }
+        for name in ["", "..", "a/b", "a\\b", "C:", "name with space", "a.b"] {
+            assert!(!is_valid_server_name(name), "{}", name);
+        }
+    }
 }
@@ -0,0 +1,151 @@
+use super::ip_filter::{BlockedClient, BLOCKED_EVENT};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+// 内存中保留的最近访问记录数量
+const RING_BUFFER_SIZE: usize = 1000;
+// 单个日志文件的最大大小，超过后轮转
+const MAX_LOG_FILE_SIZE: u64 = 5 * 1024 * 1024;
+// 保留的历史日志文件数量
+const MAX_LOG_FILES: usize = 3;
+// 每条请求完成后发送给前端的事件
+pub const REQUEST_EVENT: &str = "file-server://request";
+
+// 一条访问记录
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLogEntry {
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    pub server: String,
+    pub client_ip: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    // 响应体大小，流式响应无法提前得知时为空
+    pub bytes: Option<u64>,
+    pub duration_ms: u64,
+}
+
+// 所有文件服务器实例共享的访问日志
+pub struct AccessLog {
+    app: AppHandle,
+    entries: Mutex<VecDeque<AccessLogEntry>>,
+    // 启动以来按实例和状态码统计的请求数量
+    counts: Mutex<BTreeMap<(String, u16), u64>>,
+    log_dir: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl AccessLog {
+    pub fn new(app: AppHandle, log_dir: PathBuf) -> Self {
+        AccessLog {
+            app,
+            entries: Mutex::new(VecDeque::with_capacity(RING_BUFFER_SIZE)),
+            counts: Mutex::new(BTreeMap::new()),
+            log_dir,
+            file: Mutex::new(None),
+        }
+    }
+
+    // 记录一次请求：写入环形缓冲区和日志文件，并通知前端
+    pub fn record(&self, entry: AccessLogEntry) {
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .entry((entry.server.clone(), entry.status))
+            .or_default() += 1;
+        {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.len() >= RING_BUFFER_SIZE {
+                entries.pop_front();
+            }
+            entries.push_back(entry.clone());
+        }
+
+        if let Err(err) = self.append_to_file(&entry) {
+            log::warn!("写入文件服务器访问日志失败: {}", err);
+        }
+        let _ = self.app.emit(REQUEST_EVENT, &entry);
+    }
+
+    // 通知前端有客户端因 IP 过滤被拒绝访问
+    pub fn notify_blocked(&self, blocked: BlockedClient) {
+        let _ = self.app.emit(BLOCKED_EVENT, &blocked);
+    }
+
+    // 启动以来的请求数量，按实例名称和状态码分组
+    pub fn request_counts(&self) -> Vec<(String, u16, u64)> {
+        self.counts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((server, status), count)| (server.clone(), *status, *count))
+            .collect()
+    }
+
+    // 查询最近的访问记录，按时间倒序返回
+    pub fn query(&self, server: Option<&str>, limit: usize) -> Vec<AccessLogEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|entry| server.map(|name| entry.server == name).unwrap_or(true))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn log_path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.log_dir.join("access.log")
+        } else {
+            self.log_dir.join(format!("access.{}.log", index))
+        }
+    }
+
+    fn append_to_file(&self, entry: &AccessLogEntry) -> std::io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            fs::create_dir_all(&self.log_dir)?;
+            *file = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.log_path(0))?,
+            );
+        }
+
+        // 超过大小限制时轮转：access.log -> access.1.log -> access.2.log ...
+        if let Some(current) = file.as_ref() {
+            if current.metadata()?.len() >= MAX_LOG_FILE_SIZE {
+                *file = None;
+                for index in (0..MAX_LOG_FILES).rev() {
+                    let from = self.log_path(index);
+                    if from.exists() {
+                        let _ = fs::rename(&from, self.log_path(index + 1));
+                    }
+                }
+                let _ = fs::remove_file(self.log_path(MAX_LOG_FILES + 1));
+                *file = Some(
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(self.log_path(0))?,
+                );
+            }
+        }
+
+        if let Some(current) = file.as_mut() {
+            let line = serde_json::to_string(entry)?;
+            writeln!(current, "{}", line)?;
+        }
+        Ok(())
+    }
+}
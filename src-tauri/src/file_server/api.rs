@@ -0,0 +1,305 @@
+use super::ServeState;
+use crate::danmaku::{DanmakuEvent, EventKind, RoomManager};
+use crate::error::AppError;
+use crate::event_store::{self, EventQuery, EventStore, LeaderboardQuery};
+use crate::points::{PointsLookup, PointsManager};
+use crate::poll::{Poll, PollManager};
+use crate::raffle::RaffleManager;
+use crate::song_request::SongRequestManager;
+use crate::viewer_queue::ViewerQueueManager;
+use axum::http::{HeaderMap, Method, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use tauri::Manager;
+use tokio_stream::wrappers::BroadcastStream;
+
+// 数据接口的路径前缀，该前缀下的路径不会映射到共享文件夹
+pub(super) const API_PREFIX: &str = "/api/";
+
+const EVENTS_STREAM_PATH: &str = "/api/events/stream";
+const RECENT_EVENTS_PATH: &str = "/api/events/recent";
+const STATS_TODAY_PATH: &str = "/api/stats/today";
+const LEADERBOARD_PATH: &str = "/api/stats/leaderboard";
+const ROOMS_PATH: &str = "/api/rooms";
+const SONG_QUEUE_PATH: &str = "/api/song-requests";
+const VIEWER_QUEUE_PATH: &str = "/api/queue";
+const POLL_PATH: &str = "/api/poll";
+const POLL_STREAM_PATH: &str = "/api/poll/stream";
+const RAFFLE_PATH: &str = "/api/raffle";
+const POINTS_PATH: &str = "/api/points";
+const POINTS_TOP_PATH: &str = "/api/points/top";
+
+// 最近事件接口默认返回的数量
+const DEFAULT_RECENT_LIMIT: u32 = 50;
+
+// 断线重连时最多补发的事件数量
+const MAX_RESUME_EVENTS: u32 = 1000;
+
+pub(super) fn handle(
+    state: &ServeState,
+    method: &Method,
+    url_path: &str,
+    params: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Response {
+    if method != Method::GET {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+    match url_path {
+        EVENTS_STREAM_PATH => event_stream(state, params, headers),
+        RECENT_EVENTS_PATH => recent_events(state, params),
+        STATS_TODAY_PATH => stats_today(state, params),
+        LEADERBOARD_PATH => leaderboard(state, params),
+        ROOMS_PATH => rooms(state),
+        SONG_QUEUE_PATH => song_queue(state),
+        VIEWER_QUEUE_PATH => viewer_queue(state),
+        POLL_PATH => poll(state),
+        POLL_STREAM_PATH => poll_stream(state),
+        RAFFLE_PATH => raffle(state),
+        POINTS_PATH => points_balance(state, params),
+        POINTS_TOP_PATH => points_top(state, params),
+        _ => (StatusCode::NOT_FOUND, "Not found").into_response(),
+    }
+}
+
+// GET /api/events/stream?types=danmaku,gift：以 Server-Sent Events 推送直播间事件
+// 每条消息的 event 为事件类型，id 为事件 ID；浏览器重连时携带 Last-Event-ID，
+// 服务器从本地记录中补发断开期间的事件。首次连接也可以通过 lastEventId 参数指定
+fn event_stream(
+    state: &ServeState,
+    params: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Response {
+    let Some(rooms) = state.app.try_state::<RoomManager>() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    // 先订阅实时事件再读取补发的事件，两者之间的事件不会丢失
+    let receiver = rooms.subscribe("sse");
+
+    let types: HashSet<String> = parse_list(params.get("types")).collect();
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .or(params.get("lastEventId").map(String::as_str))
+        .filter(|id| !id.is_empty());
+    let backlog = match (last_event_id, state.app.try_state::<EventStore>()) {
+        (Some(id), Some(store)) => {
+            store
+                .events_after(id, MAX_RESUME_EVENTS)
+                .unwrap_or_else(|err| {
+                    log::warn!("读取补发事件失败: {}", err);
+                    Vec::new()
+                })
+        }
+        _ => Vec::new(),
+    };
+
+    // 补发的事件可能也出现在实时事件中，跳过已经发送过的
+    let resent: HashSet<String> = backlog.iter().map(|event| event.id.clone()).collect();
+    let live = receiver.into_stream().filter_map(move |event| {
+        let event = event.ok().filter(|event| !resent.contains(&event.id));
+        async move { event }
+    });
+    let stream = futures_util::stream::iter(backlog)
+        .chain(live)
+        .filter_map(move |event| {
+            // 被审核规则静默的事件不发送给覆盖层
+            let message = if event.muted {
+                None
+            } else if types.is_empty() || types.contains(event.kind.as_str()) {
+                sse_event(&event)
+            } else {
+                None
+            };
+            async move { message.map(Ok::<_, Infallible>) }
+        });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn sse_event(event: &DanmakuEvent) -> Option<Event> {
+    Event::default()
+        .id(&event.id)
+        .event(event.kind.as_str())
+        .json_data(event)
+        .ok()
+}
+
+// GET /api/events/recent?limit=50&type=danmaku,gift&room_id=：最近的事件，按时间倒序排列
+fn recent_events(state: &ServeState, params: &HashMap<String, String>) -> Response {
+    let Some(store) = state.app.try_state::<EventStore>() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let kinds = match parse_list(params.get("type"))
+        .map(|kind| serde_json::from_value::<EventKind>(serde_json::Value::String(kind)))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(kinds) => kinds,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid type").into_response(),
+    };
+    let limit = params
+        .get("limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_RECENT_LIMIT);
+    let query = EventQuery {
+        room_id: params.get("room_id").and_then(|id| id.parse().ok()),
+        kinds,
+        limit: Some(limit),
+        ..Default::default()
+    };
+    // 被审核规则静默的事件不发送给覆盖层
+    json_response(store.query(&query).map(|mut page| {
+        page.events.retain(|stored| !stored.event.muted);
+        page
+    }))
+}
+
+// GET /api/stats/today?room_id=：本地时间今天零点到现在的事件统计
+fn stats_today(state: &ServeState, params: &HashMap<String, String>) -> Response {
+    let Some(store) = state.app.try_state::<EventStore>() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let room_id = params.get("room_id").and_then(|id| id.parse().ok());
+    json_response(store.stats(
+        room_id,
+        event_store::today_start(),
+        chrono::Utc::now().timestamp_millis(),
+    ))
+}
+
+// GET /api/stats/leaderboard?room_id=&start=&end=&limit=10：送礼排行、弹幕排行和今天的新观众
+// 未指定时间范围时统计今天零点到现在
+fn leaderboard(state: &ServeState, params: &HashMap<String, String>) -> Response {
+    let Some(store) = state.app.try_state::<EventStore>() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let query = LeaderboardQuery {
+        room_id: params.get("room_id").and_then(|id| id.parse().ok()),
+        start: params.get("start").and_then(|start| start.parse().ok()),
+        end: params.get("end").and_then(|end| end.parse().ok()),
+        limit: params.get("limit").and_then(|limit| limit.parse().ok()),
+    };
+    match store.leaderboards(&query) {
+        Err(AppError::InvalidConfig(message)) => (StatusCode::BAD_REQUEST, message).into_response(),
+        result => json_response(result),
+    }
+}
+
+// GET /api/rooms：所有直播间的连接状态
+fn rooms(state: &ServeState) -> Response {
+    match state.app.try_state::<RoomManager>() {
+        Some(rooms) => Json(rooms.rooms_status()).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+// GET /api/song-requests：点歌队列，供 OBS 浏览器源显示
+fn song_queue(state: &ServeState) -> Response {
+    match state.app.try_state::<SongRequestManager>() {
+        Some(songs) => Json(songs.queue()).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+// GET /api/queue：观众排队队列，供 OBS 浏览器源显示
+fn viewer_queue(state: &ServeState) -> Response {
+    match state.app.try_state::<ViewerQueueManager>() {
+        Some(queue) => Json(queue.queue()).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+// GET /api/poll：进行中或最近结束的投票，没有投票时为 null
+fn poll(state: &ServeState) -> Response {
+    match state.app.try_state::<PollManager>() {
+        Some(polls) => Json(polls.current()).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+// GET /api/poll/stream：以 Server-Sent Events 推送投票进度，连接时先发送当前的投票
+// 进行中的消息 event 为 progress，结束时为 ended
+fn poll_stream(state: &ServeState) -> Response {
+    let Some(polls) = state.app.try_state::<PollManager>() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    // 先订阅再读取当前的投票，两者之间的变化不会丢失
+    let receiver = polls.subscribe();
+    let current = polls.current();
+    let stream = futures_util::stream::iter(current)
+        .chain(BroadcastStream::new(receiver).filter_map(|poll| async move { poll.ok() }))
+        .filter_map(|poll| async move { poll_event(&poll).map(Ok::<_, Infallible>) });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn poll_event(poll: &Poll) -> Option<Event> {
+    let kind = if poll.is_active() {
+        "progress"
+    } else {
+        "ended"
+    };
+    Event::default()
+        .id(&poll.id)
+        .event(kind)
+        .json_data(poll)
+        .ok()
+}
+
+// GET /api/raffle：进行中的抽奖和最近一次的开奖结果
+fn raffle(state: &ServeState) -> Response {
+    match state.app.try_state::<RaffleManager>() {
+        Some(raffles) => Json(raffles.status()).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+// GET /api/points?uid=&open_id=&uname=：观众的积分余额，没有记录时为 null
+fn points_balance(state: &ServeState, params: &HashMap<String, String>) -> Response {
+    let Some(points) = state.app.try_state::<PointsManager>() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let lookup = PointsLookup {
+        uid: params.get("uid").and_then(|uid| uid.parse().ok()),
+        open_id: params.get("open_id").cloned(),
+        uname: params.get("uname").cloned(),
+    };
+    if lookup.uid.is_none() && lookup.open_id.is_none() && lookup.uname.is_none() {
+        return (StatusCode::BAD_REQUEST, "Missing uid, open_id or uname").into_response();
+    }
+    json_response(points.find(&lookup))
+}
+
+// GET /api/points/top?limit=10：积分排行
+fn points_top(state: &ServeState, params: &HashMap<String, String>) -> Response {
+    let Some(points) = state.app.try_state::<PointsManager>() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let limit = params
+        .get("limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(10);
+    json_response(points.top(limit, 0))
+}
+
+fn json_response<T: serde::Serialize>(result: Result<T, AppError>) -> Response {
+    match result {
+        Ok(value) => Json(value).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response(),
+    }
+}
+
+// 逗号分隔的列表，例如 "danmaku,super_chat"
+fn parse_list(value: Option<&String>) -> impl Iterator<Item = String> + '_ {
+    value
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+}
@@ -0,0 +1,83 @@
+use axum::body::Body;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tokio_util::io::{ReaderStream, SyncIoBridge};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// 压缩线程与响应流之间的缓冲区大小
+const PIPE_BUFFER_SIZE: usize = 256 * 1024;
+
+// 将目录打包为 ZIP 并以流的方式返回
+// 打包在阻塞线程中进行，边打包边发送，不会在内存或磁盘上生成完整的压缩包
+pub(super) fn zip_directory_response(dir_path: PathBuf) -> Response {
+    let archive_name = dir_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "files".to_string());
+
+    let (writer, reader) = tokio::io::duplex(PIPE_BUFFER_SIZE);
+    tokio::task::spawn_blocking(move || {
+        let writer = SyncIoBridge::new(writer);
+        if let Err(err) = write_zip(&dir_path, writer) {
+            log::warn!("打包目录失败: {}", err);
+        }
+    });
+
+    let disposition = format!(
+        "attachment; filename*=UTF-8''{}.zip",
+        utf8_percent_encode(&archive_name, NON_ALPHANUMERIC)
+    );
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response()
+}
+
+fn write_zip<W: Write>(dir_path: &Path, writer: W) -> zip::result::ZipResult<()> {
+    let mut zip = ZipWriter::new_stream(writer);
+    // 共享文件夹中多为已压缩的媒体文件，直接存储可以节省大量 CPU
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+
+    let mut pending = vec![dir_path.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)?.flatten() {
+            // 不跟随符号链接，避免打包到共享文件夹之外的内容
+            let file_type = match entry.file_type() {
+                Ok(file_type) if !file_type.is_symlink() => file_type,
+                _ => continue,
+            };
+            let path = entry.path();
+            let name = match path.strip_prefix(dir_path) {
+                Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+                Err(_) => continue,
+            };
+
+            if file_type.is_dir() {
+                zip.add_directory(name, options)?;
+                pending.push(path);
+            } else if file_type.is_file() {
+                let mut file = match fs::File::open(&path) {
+                    Ok(file) => file,
+                    Err(_) => continue,
+                };
+                zip.start_file(name, options)?;
+                io::copy(&mut file, &mut zip)?;
+            }
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
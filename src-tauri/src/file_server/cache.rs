@@ -0,0 +1,52 @@
+use super::serve_file;
+use crate::asset_cache::AssetCache;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use percent_encoding::percent_decode_str;
+use tauri::{AppHandle, Manager};
+
+// 资源缓存接口的路径前缀
+pub(super) const CACHE_PREFIX: &str = "/api/cache/";
+
+// 按礼物名称获取图标的路径前缀
+const GIFT_PREFIX: &str = "gift/";
+
+// 缓存的资源地址不变时内容不变，允许浏览器长期缓存
+const CACHE_CONTROL: &str = "public, max-age=86400";
+
+// GET /api/cache/<host>/<path>：返回 B 站图片服务器上的资源，例如 /api/cache/i0.hdslb.com/bfs/live/xxx.png
+// GET /api/cache/gift/<礼物名称>：返回礼物图标，需要先连接直播间获取礼物列表
+pub(super) async fn handle(app: &AppHandle, url_path: &str, headers: &HeaderMap) -> Response {
+    let Some(assets) = app.try_state::<AssetCache>() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Cache unavailable").into_response();
+    };
+    let remote = &url_path[CACHE_PREFIX.len()..];
+    let url = match remote.strip_prefix(GIFT_PREFIX) {
+        Some(name) => {
+            let name = percent_decode_str(name).decode_utf8_lossy();
+            match assets.gift_icon(&name) {
+                Some(url) => url,
+                None => return (StatusCode::NOT_FOUND, "Gift not found").into_response(),
+            }
+        }
+        None => format!("https://{}", remote),
+    };
+    let path = match assets.fetch(&url).await {
+        Ok(path) => path,
+        Err(err) => return (StatusCode::BAD_GATEWAY, err.to_string()).into_response(),
+    };
+    match serve_file(&path, headers).await {
+        Ok(mut response) => {
+            response.headers_mut().insert(
+                header::CACHE_CONTROL,
+                HeaderValue::from_static(CACHE_CONTROL),
+            );
+            response
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Error reading file: {}", err),
+        )
+            .into_response(),
+    }
+}
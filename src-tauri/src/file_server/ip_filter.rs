@@ -0,0 +1,142 @@
+use super::ServeState;
+use crate::error::{AppError, AppResult};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+// 拒绝访问时发送给前端的事件
+pub const BLOCKED_EVENT: &str = "file-server://blocked";
+
+// 被拦截的访问
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockedClient {
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    pub server: String,
+    pub client_ip: String,
+    pub path: String,
+}
+
+// CIDR 网段，单个地址视为 /32 或 /128
+#[derive(Debug, Clone, Copy)]
+struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    fn parse(value: &str) -> AppResult<Self> {
+        let invalid = || AppError::InvalidConfig(format!("无效的 IP 地址或网段: {}", value));
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+        let addr: IpAddr = addr.trim().parse().map_err(|_| invalid())?;
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.trim().parse::<u8>().map_err(|_| invalid())?,
+            None => max_prefix,
+        };
+        if prefix > max_prefix {
+            return Err(invalid());
+        }
+        Ok(IpNet { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, normalize(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+// 双栈监听时 IPv4 客户端会以 ::ffff:a.b.c.d 的形式出现，统一转换为 IPv4
+fn normalize(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6
+            .to_ipv4_mapped()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V6(v6)),
+        ip => ip,
+    }
+}
+
+// 客户端 IP 过滤规则
+// 命中拒绝列表的地址总是被拒绝；允许列表不为空时，只有命中允许列表的地址可以访问
+#[derive(Debug, Clone, Default)]
+pub(super) struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl IpFilter {
+    pub fn new(allowlist: &[String], denylist: &[String]) -> AppResult<Self> {
+        let parse = |list: &[String]| {
+            list.iter()
+                .map(|value| IpNet::parse(value))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(IpFilter {
+            allow: parse(allowlist)?,
+            deny: parse(denylist)?,
+        })
+    }
+
+    // 本机访问始终允许，避免把自己挡在外面
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        let ip = normalize(ip);
+        if ip.is_loopback() {
+            return true;
+        }
+        if self.deny.iter().any(|net| net.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(ip))
+    }
+}
+
+// 整理用户输入的列表：去除空白和空项，并校验格式
+pub(super) fn normalize_list(list: Vec<String>) -> AppResult<Vec<String>> {
+    let list: Vec<String> = list
+        .into_iter()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect();
+    for value in &list {
+        IpNet::parse(value)?;
+    }
+    Ok(list)
+}
+
+// 在处理请求之前检查客户端 IP，被拒绝的访问会通知前端
+pub(super) async fn filter_request(
+    State(state): State<Arc<ServeState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let allowed = state.ip_filter.read().unwrap().is_allowed(client.ip());
+    if allowed {
+        return next.run(request).await;
+    }
+
+    state.access_log.notify_blocked(BlockedClient {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        server: state.name.clone(),
+        client_ip: normalize(client.ip()).to_string(),
+        path: request.uri().path().to_string(),
+    });
+    (StatusCode::FORBIDDEN, "Access denied").into_response()
+}
@@ -0,0 +1,18 @@
+use crate::metrics::Metrics;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tauri::{AppHandle, Manager};
+
+// Prometheus 采集运行指标的路径
+pub(super) const METRICS_PATH: &str = "/api/metrics";
+
+// Prometheus 文本格式的 Content-Type
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// GET /api/metrics：返回事件数量、重连次数、上传队列、请求数量和内存占用等指标
+pub(super) fn handle(app: &AppHandle) -> Response {
+    let Some(metrics) = app.try_state::<Metrics>() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Metrics unavailable").into_response();
+    };
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], metrics.render(app)).into_response()
+}
@@ -0,0 +1,194 @@
+use super::{DirectoryEntry, FileServerConfig};
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+// 虚拟挂载点配置：将 url_prefix 下的请求映射到另一个文件夹
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MountConfig {
+    pub url_prefix: String,
+    pub folder_path: String,
+    // 只读挂载点不接受上传、WebDAV 写入等修改操作
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+// 启动服务器时解析好的挂载点
+#[derive(Debug, Clone)]
+pub(super) struct Mount {
+    // 规范化后的 URL 前缀，根目录为空字符串
+    pub prefix: String,
+    // 规范化后的文件夹路径
+    pub root: PathBuf,
+    pub read_only: bool,
+}
+
+// 规范化挂载路径为 "/a/b" 的形式
+// 每一段只允许字母、数字、-、_ 和 .，这样请求中的编码路径可以直接与之比较
+fn normalize_prefix(prefix: &str) -> AppResult<String> {
+    let segments: Vec<&str> = prefix.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.is_empty() {
+        return Err(AppError::InvalidConfig("挂载路径不能是根目录".to_string()));
+    }
+    for segment in &segments {
+        let valid = *segment != "."
+            && *segment != ".."
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        if !valid {
+            return Err(AppError::InvalidConfig(format!(
+                "无效的挂载路径: {}，只能包含字母、数字、-、_ 和 .",
+                prefix
+            )));
+        }
+    }
+    Ok(format!("/{}", segments.join("/")))
+}
+
+// 校验挂载点配置，返回规范化后的列表
+// 不允许重复的路径，也不允许一个挂载点嵌套在另一个挂载点之下
+pub(super) fn validate_mounts(mounts: Vec<MountConfig>) -> AppResult<Vec<MountConfig>> {
+    let mut normalized: Vec<MountConfig> = Vec::with_capacity(mounts.len());
+    for mount in mounts {
+        let url_prefix = normalize_prefix(&mount.url_prefix)?;
+        if mount.folder_path.trim().is_empty() {
+            return Err(AppError::InvalidConfig(format!(
+                "挂载点 {} 的文件夹路径未设置",
+                url_prefix
+            )));
+        }
+        for existing in &normalized {
+            if existing.url_prefix == url_prefix {
+                return Err(AppError::InvalidConfig(format!(
+                    "挂载路径重复: {}",
+                    url_prefix
+                )));
+            }
+            if is_nested(&existing.url_prefix, &url_prefix)
+                || is_nested(&url_prefix, &existing.url_prefix)
+            {
+                return Err(AppError::InvalidConfig(format!(
+                    "挂载路径冲突: {} 与 {}",
+                    existing.url_prefix, url_prefix
+                )));
+            }
+        }
+        normalized.push(MountConfig {
+            url_prefix,
+            folder_path: mount.folder_path,
+            read_only: mount.read_only,
+        });
+    }
+    Ok(normalized)
+}
+
+// child 是否位于 parent 之下
+fn is_nested(parent: &str, child: &str) -> bool {
+    child
+        .strip_prefix(parent)
+        .map(|rest| rest.starts_with('/'))
+        .unwrap_or(false)
+}
+
+// 检查并规范化所有挂载点的文件夹，根目录（folder_path）不为空时作为 "/" 挂载
+// 结果按前缀长度倒序排列，查找时优先匹配更具体的挂载点
+pub(super) fn resolve_mounts(config: &FileServerConfig) -> AppResult<Vec<Mount>> {
+    let mut mounts = Vec::with_capacity(config.mounts.len() + 1);
+    if !config.folder_path.is_empty() {
+        mounts.push(Mount {
+            prefix: String::new(),
+            root: canonical_folder(&config.folder_path)?,
+            read_only: false,
+        });
+    }
+    for mount in &config.mounts {
+        mounts.push(Mount {
+            prefix: mount.url_prefix.clone(),
+            root: canonical_folder(&mount.folder_path)?,
+            read_only: mount.read_only,
+        });
+    }
+    if mounts.is_empty() {
+        return Err(AppError::InvalidConfig("文件夹路径未设置".to_string()));
+    }
+    mounts.sort_by(|a, b| b.prefix.len().cmp(&a.prefix.len()));
+    Ok(mounts)
+}
+
+fn canonical_folder(folder_path: &str) -> AppResult<PathBuf> {
+    let path = PathBuf::from(folder_path);
+    if !path.is_dir() {
+        return Err(AppError::FolderNotFound(folder_path.to_string()));
+    }
+    // 规范化根目录，之后所有请求路径都会与之比较
+    Ok(path.canonicalize()?)
+}
+
+// 查找请求路径所属的挂载点，返回挂载点及其内部的路径（以 / 开头）
+pub(super) fn find<'a>(mounts: &'a [Mount], url_path: &'a str) -> Option<(&'a Mount, &'a str)> {
+    mounts.iter().find_map(|mount| {
+        if mount.prefix.is_empty() {
+            return Some((mount, url_path));
+        }
+        match url_path.strip_prefix(mount.prefix.as_str()) {
+            Some("") => Some((mount, "/")),
+            Some(rest) if rest.starts_with('/') => Some((mount, rest)),
+            _ => None,
+        }
+    })
+}
+
+// 直接位于该目录下的挂载点，以虚拟目录的形式出现在目录列表中
+// 多级挂载路径（如 /assets/music）的中间目录同样会被列出
+pub(super) fn child_entries(mounts: &[Mount], url_path: &str) -> Vec<DirectoryEntry> {
+    let dir = url_path.trim_end_matches('/');
+    let mut children: Vec<DirectoryEntry> = Vec::new();
+    for mount in mounts {
+        let rest = match mount.prefix.strip_prefix(dir) {
+            Some(rest) if rest.starts_with('/') => &rest[1..],
+            _ => continue,
+        };
+        let (name, is_mount_root) = match rest.split_once('/') {
+            Some((name, _)) => (name, false),
+            None => (rest, true),
+        };
+        if children.iter().any(|child| child.name == name) {
+            continue;
+        }
+        let mtime = if is_mount_root {
+            fs::metadata(&mount.root)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        children.push(DirectoryEntry {
+            name: name.to_string(),
+            size: 0,
+            mtime,
+            is_dir: true,
+        });
+    }
+    children
+}
+
+// 将挂载点合并进真实的目录列表，同名的真实条目会被挂载点覆盖
+pub(super) fn merge_child_entries(
+    mounts: &[Mount],
+    url_path: &str,
+    entries: &mut Vec<DirectoryEntry>,
+) {
+    let children = child_entries(mounts, url_path);
+    if children.is_empty() {
+        return;
+    }
+    entries.retain(|entry| !children.iter().any(|child| child.name == entry.name));
+    entries.extend(children);
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+}
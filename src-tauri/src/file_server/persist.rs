@@ -0,0 +1,55 @@
+use super::FileServerConfig;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存文件服务器配置的文件，位于应用数据目录
+const STORE_FILE: &str = "file_servers.json";
+const SERVERS_KEY: &str = "servers";
+
+// 文件服务器配置的持久化存储
+pub(super) struct ConfigStore {
+    store: Arc<Store<Wry>>,
+}
+
+impl ConfigStore {
+    pub fn open(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        Ok(ConfigStore {
+            store: app.store(STORE_FILE)?,
+        })
+    }
+
+    // 读取所有实例的配置，无法解析的实例会被跳过
+    pub fn load(&self) -> HashMap<String, FileServerConfig> {
+        let servers = match self.store.get(SERVERS_KEY) {
+            Some(serde_json::Value::Object(servers)) => servers,
+            _ => return HashMap::new(),
+        };
+        servers
+            .into_iter()
+            .filter_map(|(name, value)| match serde_json::from_value(value) {
+                Ok(config) => Some((name, config)),
+                Err(err) => {
+                    log::warn!("读取文件服务器配置 {} 失败: {}", name, err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // 保存所有实例的配置并立即写入磁盘
+    pub fn save(&self, configs: &HashMap<String, FileServerConfig>) {
+        let value = match serde_json::to_value(configs) {
+            Ok(value) => value,
+            Err(err) => {
+                log::warn!("序列化文件服务器配置失败: {}", err);
+                return;
+            }
+        };
+        self.store.set(SERVERS_KEY, value);
+        if let Err(err) = self.store.save() {
+            log::warn!("保存文件服务器配置失败: {}", err);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::error::{AppError, AppResult};
+use netstat2::{get_sockets_info, AddressFamilyFlags, ProtocolFlags, ProtocolSocketInfo, TcpState};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use sysinfo::{Pid, ProcessesToUpdate, System};
+use tokio::net::TcpListener;
+
+// 端口被占用时依次尝试的后续端口数量，全部失败后交给系统分配
+const AUTO_PORT_ATTEMPTS: u16 = 20;
+
+// 绑定监听端口，auto_port 开启时端口被占用会自动换用其他空闲端口
+pub(crate) async fn bind(ip: IpAddr, port: u16, auto_port: bool) -> AppResult<TcpListener> {
+    let err = match TcpListener::bind(SocketAddr::new(ip, port)).await {
+        Ok(listener) => return Ok(listener),
+        Err(err) => err,
+    };
+    if err.kind() != io::ErrorKind::AddrInUse {
+        return Err(AppError::Bind(err));
+    }
+
+    if auto_port {
+        let candidates = (1..=AUTO_PORT_ATTEMPTS)
+            .filter_map(|offset| port.checked_add(offset))
+            .chain(std::iter::once(0));
+        for candidate in candidates {
+            if let Ok(listener) = TcpListener::bind(SocketAddr::new(ip, candidate)).await {
+                return Ok(listener);
+            }
+        }
+    }
+
+    Err(port_in_use(port))
+}
+
+fn port_in_use(port: u16) -> AppError {
+    let pid = find_port_owner(port);
+    AppError::PortInUse {
+        port,
+        pid,
+        process_name: pid.and_then(process_name),
+    }
+}
+
+// 查找正在监听该 TCP 端口的进程
+fn find_port_owner(port: u16) -> Option<u32> {
+    let sockets = get_sockets_info(
+        AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6,
+        ProtocolFlags::TCP,
+    )
+    .ok()?;
+    sockets
+        .into_iter()
+        .find_map(|socket| match socket.protocol_socket_info {
+            ProtocolSocketInfo::Tcp(tcp)
+                if tcp.local_port == port && tcp.state == TcpState::Listen =>
+            {
+                socket.associated_pids.first().copied()
+            }
+            _ => None,
+        })
+}
+
+fn process_name(pid: u32) -> Option<String> {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    system
+        .process(pid)
+        .map(|process| process.name().to_string_lossy().into_owned())
+}
@@ -0,0 +1,136 @@
+use super::ServeState;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures_util::StreamExt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// 简单的带宽限制器：按发送的字节数推进"下一次可发送时间"
+// 不累积空闲期间的额度，因此不会出现突发流量
+pub struct Bandwidth {
+    bytes_per_sec: u64,
+    next_available: Mutex<Instant>,
+}
+
+impl Bandwidth {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Bandwidth {
+            bytes_per_sec,
+            next_available: Mutex::new(Instant::now()),
+        }
+    }
+
+    // 预约发送 bytes 个字节，返回发送前需要等待的时间
+    fn reserve(&self, bytes: usize) -> Duration {
+        let mut next = self.next_available.lock().unwrap();
+        let now = Instant::now();
+        if *next < now {
+            *next = now;
+        }
+        let wait = *next - now;
+        *next += Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        wait
+    }
+}
+
+// 单个服务器实例的连接数和带宽限制
+pub struct Limits {
+    // 同时进行的请求数，不包括 SSE 事件流
+    connections: Option<Arc<Semaphore>>,
+    // 每个连接的带宽上限，字节/秒
+    connection_speed: Option<u64>,
+    // 所有连接共享的带宽
+    global: Option<Arc<Bandwidth>>,
+}
+
+impl Limits {
+    // speed 参数单位为 KB/s，0 表示不限制
+    pub fn new(max_connections: u32, connection_speed_kb: u64, global_speed_kb: u64) -> Self {
+        Limits {
+            connections: (max_connections > 0)
+                .then(|| Arc::new(Semaphore::new(max_connections as usize))),
+            connection_speed: (connection_speed_kb > 0).then_some(connection_speed_kb * 1024),
+            global: (global_speed_kb > 0).then(|| Arc::new(Bandwidth::new(global_speed_kb * 1024))),
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.connections.is_none() && self.connection_speed.is_none() && self.global.is_none()
+    }
+}
+
+// 限制同时进行的请求数并对响应体限速的中间件
+// 请求名额会一直保持到响应体发送完毕；SSE 事件流会一直保持，不占用名额，否则打开的浮窗会占满名额
+pub(super) async fn limit_request(
+    State(state): State<Arc<ServeState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limits = &state.limits;
+    if limits.is_unlimited() {
+        return next.run(request).await;
+    }
+
+    let streaming = is_event_stream(request.headers().get(header::ACCEPT));
+    let permit = match &limits.connections {
+        Some(semaphore) if !streaming => match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                return (StatusCode::SERVICE_UNAVAILABLE, "Too many connections").into_response()
+            }
+        },
+        _ => None,
+    };
+
+    let mut bandwidths = Vec::new();
+    if let Some(speed) = limits.connection_speed {
+        bandwidths.push(Arc::new(Bandwidth::new(speed)));
+    }
+    if let Some(global) = &limits.global {
+        bandwidths.push(global.clone());
+    }
+
+    let response = next.run(request).await;
+    let (parts, body) = response.into_parts();
+    // 请求头没有声明但返回的是事件流时同样释放名额
+    let permit = permit.filter(|_| !is_event_stream(parts.headers.get(header::CONTENT_TYPE)));
+    Response::from_parts(parts, throttle_body(body, bandwidths, permit))
+}
+
+fn is_event_stream(value: Option<&HeaderValue>) -> bool {
+    value
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("text/event-stream"))
+}
+
+fn throttle_body(
+    body: Body,
+    bandwidths: Vec<Arc<Bandwidth>>,
+    permit: Option<OwnedSemaphorePermit>,
+) -> Body {
+    let stream = body.into_data_stream().then(move |chunk| {
+        let wait = match &chunk {
+            Ok(bytes) => bandwidths
+                .iter()
+                .map(|bandwidth| bandwidth.reserve(bytes.len()))
+                .max()
+                .unwrap_or_default(),
+            Err(_) => Duration::ZERO,
+        };
+        async move {
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            chunk
+        }
+    });
+    // 响应体被丢弃时才释放请求名额
+    Body::from_stream(stream.map(move |chunk| {
+        let _permit = &permit;
+        chunk
+    }))
+}
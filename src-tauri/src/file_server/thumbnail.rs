@@ -0,0 +1,144 @@
+use super::{mount, resolve_request_path, serve_file, ServeState};
+use crate::error::{AppError, AppResult};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use image::{DynamicImage, ImageError, ImageFormat};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 缩略图接口的路径前缀
+pub(super) const THUMB_PREFIX: &str = "/thumb/";
+
+const DEFAULT_WIDTH: u32 = 256;
+const MIN_WIDTH: u32 = 16;
+const MAX_WIDTH: u32 = 1024;
+
+// 可以生成缩略图的图片扩展名
+const SUPPORTED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "bmp"];
+
+// 判断文件是否可以生成缩略图
+pub(super) fn is_thumbnailable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| SUPPORTED_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+// 删除生成时间超过 max_age 的缓存缩略图，返回删除的数量，再次访问时会重新生成
+pub(super) fn purge(cache_dir: &Path, max_age: Duration) -> usize {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return 0;
+    };
+    let now = SystemTime::now();
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| now.duration_since(modified).is_ok_and(|age| age > max_age))
+        })
+        .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+// GET /thumb/<path>?w=256：返回缩放后的图片，结果缓存在磁盘上
+pub(super) async fn handle(
+    state: &ServeState,
+    url_path: &str,
+    params: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Response {
+    let (mount, sub_path) = match mount::find(&state.mounts, &url_path[THUMB_PREFIX.len() - 1..]) {
+        Some(located) => located,
+        None => return (StatusCode::NOT_FOUND, "File not found").into_response(),
+    };
+    let image_path = match resolve_request_path(&mount.root, sub_path) {
+        Ok(path) if path.is_file() && is_thumbnailable(&path) => path,
+        Ok(_) => return (StatusCode::BAD_REQUEST, "Not an image").into_response(),
+        Err(err) => return err.into_response(),
+    };
+    let width = params
+        .get("w")
+        .and_then(|w| w.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_WIDTH)
+        .clamp(MIN_WIDTH, MAX_WIDTH);
+
+    let cache_dir = state.thumbnail_dir.clone();
+    let result =
+        tokio::task::spawn_blocking(move || get_or_create(&cache_dir, &image_path, width)).await;
+    let thumb_path = match result {
+        Ok(Ok(thumb_path)) => thumb_path,
+        // 扩展名是图片但内容无法解码
+        Ok(Err(AppError::Image(err @ (ImageError::Decoding(_) | ImageError::Unsupported(_))))) => {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("Error decoding image: {}", err),
+            )
+                .into_response()
+        }
+        Ok(Err(err)) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error generating thumbnail: {}", err),
+            )
+                .into_response()
+        }
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    };
+
+    match serve_file(&thumb_path, headers).await {
+        Ok(response) => response,
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Error reading file: {}", err),
+        )
+            .into_response(),
+    }
+}
+
+// 查找缓存的缩略图，不存在时生成
+// 缓存文件名由原图路径、大小、修改时间和宽度决定，原图变化后自动失效
+fn get_or_create(cache_dir: &Path, image_path: &Path, width: u32) -> AppResult<PathBuf> {
+    let metadata = std::fs::metadata(image_path)?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let mut hasher = DefaultHasher::new();
+    image_path.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    modified.hash(&mut hasher);
+    width.hash(&mut hasher);
+    let key = format!("{:016x}", hasher.finish());
+
+    // 带透明通道的图片保存为 PNG，其余保存为体积更小的 JPEG
+    for extension in ["jpg", "png"] {
+        let cached = cache_dir.join(format!("{}.{}", key, extension));
+        if cached.is_file() {
+            return Ok(cached);
+        }
+    }
+
+    std::fs::create_dir_all(cache_dir)?;
+    let image = image::open(image_path)?;
+    let thumb = image.thumbnail(width, width.saturating_mul(4));
+    let (thumb_path, result) = if thumb.color().has_alpha() {
+        let path = cache_dir.join(format!("{}.png", key));
+        let result = thumb.save_with_format(&path, ImageFormat::Png);
+        (path, result)
+    } else {
+        let path = cache_dir.join(format!("{}.jpg", key));
+        let result =
+            DynamicImage::ImageRgb8(thumb.to_rgb8()).save_with_format(&path, ImageFormat::Jpeg);
+        (path, result)
+    };
+    result?;
+    Ok(thumb_path)
+}
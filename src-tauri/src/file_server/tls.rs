@@ -0,0 +1,52 @@
+use super::{detect_lan_ips, FileServerConfig};
+use crate::error::{AppError, AppResult};
+use axum_server::tls_rustls::RustlsConfig;
+use std::fs;
+use std::path::Path;
+
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+
+// 根据配置加载 TLS 证书
+// 指定了证书和私钥时直接使用，否则使用（必要时生成）缓存目录中的自签名证书
+pub(super) async fn load_tls_config(
+    config: &FileServerConfig,
+    tls_dir: &Path,
+) -> AppResult<RustlsConfig> {
+    match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => RustlsConfig::from_pem_file(cert_path, key_path)
+            .await
+            .map_err(|err| AppError::Tls(format!("无法加载证书: {}", err))),
+        (None, None) => {
+            let (cert, key) = self_signed_certificate(tls_dir)?;
+            RustlsConfig::from_pem(cert, key)
+                .await
+                .map_err(|err| AppError::Tls(format!("无法加载自签名证书: {}", err)))
+        }
+        _ => Err(AppError::InvalidConfig(
+            "证书和私钥路径需要同时设置".to_string(),
+        )),
+    }
+}
+
+// 读取缓存的自签名证书，不存在时生成新的证书
+fn self_signed_certificate(tls_dir: &Path) -> AppResult<(Vec<u8>, Vec<u8>)> {
+    let cert_path = tls_dir.join(CERT_FILE);
+    let key_path = tls_dir.join(KEY_FILE);
+    if let (Ok(cert), Ok(key)) = (fs::read(&cert_path), fs::read(&key_path)) {
+        return Ok((cert, key));
+    }
+
+    // 证书包含 localhost 和当前的局域网地址，方便其他设备访问
+    let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    names.extend(detect_lan_ips());
+    let certified = rcgen::generate_simple_self_signed(names)
+        .map_err(|err| AppError::Tls(format!("生成自签名证书失败: {}", err)))?;
+    let cert = certified.cert.pem();
+    let key = certified.key_pair.serialize_pem();
+
+    fs::create_dir_all(tls_dir)?;
+    fs::write(&cert_path, &cert)?;
+    fs::write(&key_path, &key)?;
+    Ok((cert.into_bytes(), key.into_bytes()))
+}
@@ -0,0 +1,109 @@
+use super::mount::Mount;
+use super::{ServeState, PATH_SEGMENT};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::StreamExt;
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use percent_encoding::utf8_percent_encode;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::path::Path;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+// 文件变化事件的 SSE 路径
+pub(super) const EVENTS_PATH: &str = "/events";
+
+// 广播通道容量，订阅者落后太多时会丢弃旧事件
+const CHANNEL_CAPACITY: usize = 256;
+
+// 推送给页面的文件变化通知
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct FolderChange {
+    // create / modify / remove / rename
+    pub kind: &'static str,
+    // 发生变化的文件的 URL 路径
+    pub paths: Vec<String>,
+}
+
+// 监视所有挂载点文件夹的变化，并通过广播通道分发给所有 SSE 连接
+// 返回的 watcher 需要在服务器运行期间一直持有
+pub(super) fn watch_folder(
+    mounts: &[Mount],
+) -> notify::Result<(RecommendedWatcher, broadcast::Sender<FolderChange>)> {
+    let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+    let event_sender = sender.clone();
+    // 挂载点的文件夹可能互相包含，优先匹配更深的文件夹
+    let mut event_mounts = mounts.to_vec();
+    event_mounts.sort_by(|a, b| {
+        b.root
+            .components()
+            .count()
+            .cmp(&a.root.components().count())
+    });
+
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let event = match result {
+            Ok(event) => event,
+            Err(err) => {
+                log::warn!("文件夹监视出错: {}", err);
+                return;
+            }
+        };
+        let kind = match event.kind {
+            EventKind::Create(_) => "create",
+            EventKind::Modify(ModifyKind::Name(_)) => "rename",
+            EventKind::Modify(_) => "modify",
+            EventKind::Remove(_) => "remove",
+            _ => return,
+        };
+        let paths: Vec<String> = event
+            .paths
+            .iter()
+            .filter_map(|path| url_path_for(&event_mounts, path))
+            .collect();
+        if paths.is_empty() {
+            return;
+        }
+        // 没有订阅者时发送会失败，直接忽略
+        let _ = event_sender.send(FolderChange { kind, paths });
+    })?;
+    for mount in mounts {
+        watcher.watch(&mount.root, RecursiveMode::Recursive)?;
+    }
+    Ok((watcher, sender))
+}
+
+// GET /events：以 Server-Sent Events 推送文件变化
+pub(super) fn handle(state: &ServeState) -> Response {
+    let receiver = match &state.folder_changes {
+        Some(sender) => sender.subscribe(),
+        None => return axum::http::StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let stream = BroadcastStream::new(receiver).filter_map(|change| async move {
+        let change = change.ok()?;
+        let event = Event::default().event("change").json_data(&change).ok()?;
+        Some(Ok::<_, Infallible>(event))
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+// 将文件系统路径转换为访问该文件的 URL 路径
+fn url_path_for(mounts: &[Mount], path: &Path) -> Option<String> {
+    let (mount, relative) = mounts
+        .iter()
+        .find_map(|mount| Some((mount, path.strip_prefix(&mount.root).ok()?)))?;
+    let mut url = mount.prefix.clone();
+    for component in relative.components() {
+        url.push('/');
+        url.push_str(
+            &utf8_percent_encode(&component.as_os_str().to_string_lossy(), PATH_SEGMENT)
+                .to_string(),
+        );
+    }
+    Some(if url.is_empty() { "/".to_string() } else { url })
+}
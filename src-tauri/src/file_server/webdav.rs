@@ -0,0 +1,360 @@
+use super::mount::{self, Mount};
+use super::{
+    escape_html, mime_type_for, resolve_request_path, resolve_upload_path, DirectoryEntry,
+    PathResolveError, ServeState, PATH_SEGMENT,
+};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use percent_encoding::utf8_percent_encode;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// WebDAV 允许的方法，OPTIONS 响应中返回
+const ALLOW_METHODS: &str = "OPTIONS, GET, HEAD, PUT, PROPFIND, MKCOL, MOVE, DELETE";
+
+// 处理 WebDAV 请求，返回 None 表示交给普通的文件处理逻辑
+pub(super) async fn handle(
+    state: &ServeState,
+    method: &Method,
+    url_path: &str,
+    headers: &HeaderMap,
+) -> Option<Response> {
+    let response = match method.as_str() {
+        "OPTIONS" => options(),
+        "PROPFIND" => propfind(state, url_path, headers).await,
+        "MKCOL" | "MOVE" | "DELETE" if !state.allow_upload => {
+            (StatusCode::FORBIDDEN, "Write access is disabled").into_response()
+        }
+        "MKCOL" | "MOVE" | "DELETE" => match mount::find(&state.mounts, url_path) {
+            // 挂载点构成的虚拟目录不可修改
+            None => StatusCode::FORBIDDEN.into_response(),
+            Some((mount, _)) if mount.read_only => {
+                (StatusCode::FORBIDDEN, "Mount is read-only").into_response()
+            }
+            Some((mount, sub_path)) => match method.as_str() {
+                "MKCOL" => mkcol(&mount.root, sub_path).await,
+                "MOVE" => move_resource(state, mount, sub_path, headers).await,
+                _ => delete(&mount.root, sub_path).await,
+            },
+        },
+        _ => return None,
+    };
+    Some(response)
+}
+
+fn options() -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::ALLOW, ALLOW_METHODS),
+            (header::HeaderName::from_static("dav"), "1"),
+        ],
+    )
+        .into_response()
+}
+
+// PROPFIND：返回资源及其直接子项的属性（仅支持 Depth 0 和 1）
+async fn propfind(state: &ServeState, url_path: &str, headers: &HeaderMap) -> Response {
+    let depth = headers
+        .get("Depth")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("1");
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n",
+    );
+
+    let located = mount::find(&state.mounts, url_path)
+        .map(|(mount, sub_path)| (mount, resolve_request_path(&mount.root, sub_path)));
+    let (mount, path) = match located {
+        Some((mount, Ok(path))) => (mount, path),
+        // 只由挂载点构成的虚拟目录
+        Some((_, Err(PathResolveError::NotFound))) | None => {
+            let children = mount::child_entries(&state.mounts, url_path);
+            if children.is_empty() {
+                return StatusCode::NOT_FOUND.into_response();
+            }
+            let dir_href = format!("{}/", url_path.trim_end_matches('/'));
+            let display_name = dir_href
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or("");
+            push_collection(&mut xml, &dir_href, display_name, UNIX_EPOCH);
+            if depth != "0" {
+                push_mount_children(&mut xml, &dir_href, &children);
+            }
+            return multistatus(xml);
+        }
+        Some((_, Err(err))) => return err.into_response(),
+    };
+    let metadata = match tokio::fs::metadata(&path).await {
+        Ok(metadata) => metadata,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let base_href = href_for(mount, &path, metadata.is_dir());
+    push_response(&mut xml, &base_href, &path, &metadata);
+
+    if metadata.is_dir() && depth != "0" {
+        // 挂载在根目录某个子目录上的挂载点会覆盖同名的真实目录
+        let children = if mount.prefix.is_empty() {
+            mount::child_entries(&state.mounts, url_path)
+        } else {
+            Vec::new()
+        };
+        push_mount_children(&mut xml, &base_href, &children);
+
+        let mut entries = match tokio::fs::read_dir(&path).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+            }
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let entry_path = entry.path();
+            let shadowed = entry
+                .file_name()
+                .to_str()
+                .map(|name| children.iter().any(|child| child.name == name))
+                .unwrap_or(false);
+            if shadowed {
+                continue;
+            }
+            let entry_metadata = match tokio::fs::metadata(&entry_path).await {
+                Ok(entry_metadata) => entry_metadata,
+                Err(_) => continue,
+            };
+            let href = href_for(mount, &entry_path, entry_metadata.is_dir());
+            push_response(&mut xml, &href, &entry_path, &entry_metadata);
+        }
+    }
+
+    multistatus(xml)
+}
+
+fn multistatus(mut xml: String) -> Response {
+    xml.push_str("</D:multistatus>\n");
+    (
+        StatusCode::MULTI_STATUS,
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/xml; charset=utf-8"),
+        )],
+        xml,
+    )
+        .into_response()
+}
+
+// MKCOL：创建目录
+async fn mkcol(root: &Path, url_path: &str) -> Response {
+    if resolve_request_path(root, url_path).is_ok() {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+    let target = match resolve_upload_path(root, url_path) {
+        Ok(target) => target,
+        Err(err) => return err.into_response(),
+    };
+    match tokio::fs::create_dir(&target).await {
+        Ok(()) => StatusCode::CREATED.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+// MOVE：移动或重命名文件/目录，目标由 Destination 请求头指定
+// 目标必须与源位于同一个挂载点内
+async fn move_resource(
+    state: &ServeState,
+    mount: &Mount,
+    url_path: &str,
+    headers: &HeaderMap,
+) -> Response {
+    let root = mount.root.as_path();
+    let source = match resolve_request_path(root, url_path) {
+        Ok(source) if source != root => source,
+        Ok(_) => return StatusCode::FORBIDDEN.into_response(),
+        Err(err) => return err.into_response(),
+    };
+
+    let destination = match headers
+        .get("Destination")
+        .and_then(|value| value.to_str().ok())
+        .map(destination_path)
+    {
+        Some(destination) => destination,
+        None => return (StatusCode::BAD_REQUEST, "Missing Destination").into_response(),
+    };
+    let destination = match mount::find(&state.mounts, destination) {
+        Some((target_mount, sub_path)) if target_mount.prefix == mount.prefix => sub_path,
+        _ => return (StatusCode::FORBIDDEN, "Cannot move across mounts").into_response(),
+    };
+    let (target, existing) = match resolve_request_path(root, destination) {
+        // 目标已存在
+        Ok(existing) => {
+            let overwrite = headers
+                .get("Overwrite")
+                .and_then(|value| value.to_str().ok())
+                .map(|value| !value.eq_ignore_ascii_case("F"))
+                .unwrap_or(true);
+            if !overwrite || existing == root {
+                return StatusCode::PRECONDITION_FAILED.into_response();
+            }
+            (existing, true)
+        }
+        Err(_) => match resolve_upload_path(root, destination) {
+            Ok(target) => (target, false),
+            Err(err) => return err.into_response(),
+        },
+    };
+    // 目标与源相同、位于源内或包含源时，覆盖会连同源一起删除
+    if target.starts_with(&source) || source.starts_with(&target) {
+        return (StatusCode::FORBIDDEN, "Destination overlaps source").into_response();
+    }
+    if !existing {
+        return match tokio::fs::rename(&source, &target).await {
+            Ok(()) => StatusCode::CREATED.into_response(),
+            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+        };
+    }
+
+    // 先把旧目标改名，移动成功后再删除，失败时恢复
+    let backup = backup_path(&target);
+    if let Err(err) = tokio::fs::rename(&target, &backup).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+    }
+    if let Err(err) = tokio::fs::rename(&source, &target).await {
+        if let Err(restore_err) = tokio::fs::rename(&backup, &target).await {
+            log::warn!("恢复被覆盖的文件失败 {}: {}", backup.display(), restore_err);
+        }
+        return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+    }
+    let removed = if backup.is_dir() {
+        tokio::fs::remove_dir_all(&backup).await
+    } else {
+        tokio::fs::remove_file(&backup).await
+    };
+    if let Err(err) = removed {
+        log::warn!("删除被覆盖的文件失败 {}: {}", backup.display(), err);
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
+
+// 与目标位于同一目录的临时名称，保证改名不会跨文件系统
+fn backup_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    target.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4().simple()))
+}
+
+// DELETE：删除文件或整个目录，不允许删除根目录
+async fn delete(root: &Path, url_path: &str) -> Response {
+    let path = match resolve_request_path(root, url_path) {
+        Ok(path) if path != root => path,
+        Ok(_) => return StatusCode::FORBIDDEN.into_response(),
+        Err(err) => return err.into_response(),
+    };
+    let result = if path.is_dir() {
+        tokio::fs::remove_dir_all(&path).await
+    } else {
+        tokio::fs::remove_file(&path).await
+    };
+    match result {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+// Destination 可能是完整 URL，只取其中的路径部分
+fn destination_path(destination: &str) -> &str {
+    match destination.find("://") {
+        Some(index) => {
+            let rest = &destination[index + 3..];
+            rest.find('/').map(|slash| &rest[slash..]).unwrap_or("/")
+        }
+        None => destination,
+    }
+}
+
+// 根据真实路径生成编码后的 href，包含挂载点前缀
+fn href_for(mount: &Mount, path: &Path, is_dir: bool) -> String {
+    let relative = path.strip_prefix(&mount.root).unwrap_or(path);
+    let mut href = mount.prefix.clone();
+    for component in relative.components() {
+        href.push('/');
+        href.push_str(
+            &utf8_percent_encode(&component.as_os_str().to_string_lossy(), PATH_SEGMENT)
+                .to_string(),
+        );
+    }
+    if is_dir || href.is_empty() {
+        href.push('/');
+    }
+    href
+}
+
+// 将挂载点作为子目录加入 PROPFIND 结果
+fn push_mount_children(xml: &mut String, dir_href: &str, children: &[DirectoryEntry]) {
+    for child in children {
+        let href = format!("{}{}/", dir_href, child.name);
+        let modified = UNIX_EPOCH + Duration::from_millis(child.mtime);
+        push_collection(xml, &href, &child.name, modified);
+    }
+}
+
+// 没有对应真实目录的集合（虚拟目录）
+fn push_collection(xml: &mut String, href: &str, display_name: &str, modified: SystemTime) {
+    xml.push_str("<D:response>\n");
+    xml.push_str(&format!("<D:href>{}</D:href>\n", escape_html(href)));
+    xml.push_str("<D:propstat>\n<D:prop>\n");
+    xml.push_str(&format!(
+        "<D:displayname>{}</D:displayname>\n",
+        escape_html(display_name)
+    ));
+    xml.push_str(&format!(
+        "<D:getlastmodified>{}</D:getlastmodified>\n",
+        httpdate::fmt_http_date(modified)
+    ));
+    xml.push_str("<D:resourcetype><D:collection/></D:resourcetype>\n");
+    xml.push_str("</D:prop>\n<D:status>HTTP/1.1 200 OK</D:status>\n</D:propstat>\n");
+    xml.push_str("</D:response>\n");
+}
+
+fn push_response(xml: &mut String, href: &str, path: &Path, metadata: &Metadata) {
+    let display_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let last_modified = metadata
+        .modified()
+        .map(httpdate::fmt_http_date)
+        .unwrap_or_else(|_| httpdate::fmt_http_date(UNIX_EPOCH));
+
+    xml.push_str("<D:response>\n");
+    xml.push_str(&format!("<D:href>{}</D:href>\n", escape_html(href)));
+    xml.push_str("<D:propstat>\n<D:prop>\n");
+    xml.push_str(&format!(
+        "<D:displayname>{}</D:displayname>\n",
+        escape_html(&display_name)
+    ));
+    xml.push_str(&format!(
+        "<D:getlastmodified>{}</D:getlastmodified>\n",
+        last_modified
+    ));
+    if metadata.is_dir() {
+        xml.push_str("<D:resourcetype><D:collection/></D:resourcetype>\n");
+    } else {
+        xml.push_str("<D:resourcetype/>\n");
+        xml.push_str(&format!(
+            "<D:getcontentlength>{}</D:getcontentlength>\n",
+            metadata.len()
+        ));
+        xml.push_str(&format!(
+            "<D:getcontenttype>{}</D:getcontenttype>\n",
+            escape_html(&mime_type_for(path))
+        ));
+    }
+    xml.push_str("</D:prop>\n<D:status>HTTP/1.1 200 OK</D:status>\n</D:propstat>\n");
+    xml.push_str("</D:response>\n");
+}
@@ -0,0 +1,240 @@
+use crate::error::{AppError, AppResult};
+use crate::tts::TtsManager;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存快捷键的文件，位于应用数据目录
+const STORE_FILE: &str = "hotkeys.json";
+const BINDINGS_KEY: &str = "bindings";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    // 断开或重新连接所有直播间
+    ToggleFetching,
+    // 暂停或恢复朗读
+    MuteTts,
+    // 跳过正在朗读的文本
+    SkipTts,
+    // 显示或隐藏主窗口
+    ToggleWindow,
+}
+
+impl HotkeyAction {
+    const ALL: [HotkeyAction; 4] = [
+        HotkeyAction::ToggleFetching,
+        HotkeyAction::MuteTts,
+        HotkeyAction::SkipTts,
+        HotkeyAction::ToggleWindow,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            HotkeyAction::ToggleFetching => "开始/停止获取",
+            HotkeyAction::MuteTts => "暂停/恢复朗读",
+            HotkeyAction::SkipTts => "跳过当前朗读",
+            HotkeyAction::ToggleWindow => "显示/隐藏窗口",
+        }
+    }
+}
+
+// 快捷键格式与前端相同，例如 Ctrl+Shift+F9，为空表示不设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotkeyBinding {
+    pub action: HotkeyAction,
+    pub shortcut: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HotkeyStatus {
+    pub action: HotkeyAction,
+    pub shortcut: Option<String>,
+    pub registered: bool,
+    // 注册失败的原因，通常是已被其他程序占用
+    pub error: Option<String>,
+}
+
+// 全局快捷键，在 OBS 或游戏位于前台时也能控制客户端
+pub struct Hotkeys {
+    app: AppHandle,
+    store: Arc<Store<Wry>>,
+    statuses: Mutex<Vec<HotkeyStatus>>,
+    // 当前注册的快捷键，修改时先全部取消注册
+    registered: Mutex<Vec<Shortcut>>,
+}
+
+impl Hotkeys {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let bindings: Vec<HotkeyBinding> = store
+            .get(BINDINGS_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let hotkeys = Hotkeys {
+            app: app.clone(),
+            store,
+            statuses: Mutex::new(Vec::new()),
+            registered: Mutex::new(Vec::new()),
+        };
+        // 保存的快捷键可能已被其他程序占用，启动时不因此失败
+        let bindings = normalize(&bindings);
+        let parsed = bindings
+            .iter()
+            .map(|binding| {
+                (
+                    binding.action,
+                    parse(binding.shortcut.as_deref()).ok().flatten(),
+                )
+            })
+            .collect();
+        hotkeys.register_all(bindings, parsed);
+        Ok(hotkeys)
+    }
+
+    pub fn statuses(&self) -> Vec<HotkeyStatus> {
+        self.statuses.lock().unwrap().clone()
+    }
+
+    pub fn bindings(&self) -> Vec<HotkeyBinding> {
+        self.statuses
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|status| HotkeyBinding {
+                action: status.action,
+                shortcut: status.shortcut.clone(),
+            })
+            .collect()
+    }
+
+    // 检查格式和冲突后替换所有快捷键
+    pub fn update(&self, bindings: Vec<HotkeyBinding>) -> AppResult<Vec<HotkeyStatus>> {
+        let bindings = normalize(&bindings);
+        let mut parsed: Vec<(HotkeyAction, Option<Shortcut>)> = Vec::new();
+        for binding in &bindings {
+            let shortcut = parse(binding.shortcut.as_deref())?;
+            if let Some(shortcut) = shortcut {
+                if let Some((other, _)) = parsed
+                    .iter()
+                    .find(|(_, existing)| existing.as_ref() == Some(&shortcut))
+                {
+                    return Err(AppError::InvalidConfig(format!(
+                        "快捷键 {} 同时用于“{}”和“{}”",
+                        binding.shortcut.as_deref().unwrap_or_default(),
+                        other.label(),
+                        binding.action.label()
+                    )));
+                }
+            }
+            parsed.push((binding.action, shortcut));
+        }
+
+        match serde_json::to_value(&bindings) {
+            Ok(value) => {
+                self.store.set(BINDINGS_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存快捷键失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化快捷键失败: {}", err),
+        }
+        self.register_all(bindings, parsed);
+        Ok(self.statuses())
+    }
+
+    fn register_all(
+        &self,
+        bindings: Vec<HotkeyBinding>,
+        parsed: Vec<(HotkeyAction, Option<Shortcut>)>,
+    ) {
+        let global_shortcut = self.app.global_shortcut();
+        let mut registered = self.registered.lock().unwrap();
+        for shortcut in registered.drain(..) {
+            if let Err(err) = global_shortcut.unregister(shortcut) {
+                log::warn!("取消注册快捷键失败: {}", err);
+            }
+        }
+
+        let mut statuses = Vec::new();
+        for (binding, (action, shortcut)) in bindings.into_iter().zip(parsed) {
+            let mut status = HotkeyStatus {
+                action,
+                shortcut: binding.shortcut,
+                registered: false,
+                error: None,
+            };
+            if let Some(shortcut) = shortcut {
+                match global_shortcut.on_shortcut(shortcut, move |app, _, event| {
+                    if event.state() == ShortcutState::Pressed {
+                        run(app, action);
+                    }
+                }) {
+                    Ok(()) => {
+                        registered.push(shortcut);
+                        status.registered = true;
+                    }
+                    Err(err) => {
+                        log::warn!("注册快捷键 {:?} 失败: {}", status.shortcut, err);
+                        status.error = Some(err.to_string());
+                    }
+                }
+            } else if status.shortcut.is_some() {
+                status.error = Some("快捷键格式无效".to_string());
+            }
+            statuses.push(status);
+        }
+        *self.statuses.lock().unwrap() = statuses;
+    }
+}
+
+// 每个动作一项，没有保存的动作不设置快捷键
+fn normalize(bindings: &[HotkeyBinding]) -> Vec<HotkeyBinding> {
+    HotkeyAction::ALL
+        .iter()
+        .map(|action| HotkeyBinding {
+            action: *action,
+            shortcut: bindings
+                .iter()
+                .find(|binding| binding.action == *action)
+                .and_then(|binding| binding.shortcut.as_deref())
+                .map(str::trim)
+                .filter(|shortcut| !shortcut.is_empty())
+                .map(str::to_string),
+        })
+        .collect()
+}
+
+fn parse(shortcut: Option<&str>) -> AppResult<Option<Shortcut>> {
+    shortcut
+        .map(|shortcut| {
+            shortcut.parse::<Shortcut>().map_err(|err| {
+                AppError::InvalidConfig(format!("无效的快捷键 {}: {}", shortcut, err))
+            })
+        })
+        .transpose()
+}
+
+fn run(app: &AppHandle, action: HotkeyAction) {
+    log::info!("快捷键触发: {}", action.label());
+    match action {
+        HotkeyAction::ToggleFetching => crate::tray::toggle_fetching(app),
+        HotkeyAction::ToggleWindow => crate::tray::toggle_window(app),
+        HotkeyAction::MuteTts => {
+            if let Some(tts) = app.try_state::<TtsManager>() {
+                if tts.status().paused {
+                    tts.resume();
+                } else {
+                    tts.pause();
+                }
+            }
+        }
+        HotkeyAction::SkipTts => {
+            if let Some(tts) = app.try_state::<TtsManager>() {
+                tts.skip();
+            }
+        }
+    }
+}
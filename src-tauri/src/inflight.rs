@@ -0,0 +1,42 @@
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+pub type InflightFuture<V> = Shared<BoxFuture<'static, Option<V>>>;
+
+// 合并相同键的并发请求，例如同一个用户或同一张图片同时只请求一次
+// 请求完成前需要调用 finish 移除，之后的请求会重新发出
+pub struct Inflight<K, V: Clone> {
+    tasks: Mutex<HashMap<K, InflightFuture<V>>>,
+}
+
+impl<K: Eq + Hash, V: Clone> Inflight<K, V> {
+    pub fn new() -> Self {
+        Inflight {
+            tasks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.lock().unwrap().len()
+    }
+
+    // 已有相同键的请求时返回该请求，否则调用 start 发出新的请求
+    pub fn get_or_start(
+        &self,
+        key: K,
+        start: impl FnOnce() -> BoxFuture<'static, Option<V>>,
+    ) -> InflightFuture<V> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| start().shared())
+            .clone()
+    }
+
+    pub fn finish(&self, key: &K) {
+        self.tasks.lock().unwrap().remove(key);
+    }
+}
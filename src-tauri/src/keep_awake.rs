@@ -0,0 +1,209 @@
+use crate::danmaku::RoomManager;
+use crate::error::AppResult;
+use crate::file_server::FileServerRegistry;
+use crate::ws_server::WsServer;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存防休眠设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "power.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 开始或停止阻止休眠时发送给前端的事件
+pub const STATUS_EVENT: &str = "keep-awake://status";
+
+// 检查是否正在获取弹幕或运行服务器的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeepAwakeConfig {
+    pub enabled: bool,
+    // 连接了直播间时阻止休眠
+    pub while_fetching: bool,
+    // 文件服务器或事件广播服务器运行时阻止休眠
+    pub while_serving: bool,
+    // 同时保持屏幕常亮
+    pub keep_display_on: bool,
+}
+
+impl Default for KeepAwakeConfig {
+    fn default() -> Self {
+        KeepAwakeConfig {
+            enabled: true,
+            while_fetching: true,
+            while_serving: true,
+            keep_display_on: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct KeepAwakeStatus {
+    // 当前是否正在阻止系统休眠
+    pub active: bool,
+    // 阻止休眠的原因，例如“获取弹幕”
+    pub reasons: Vec<String>,
+    // 向系统申请失败时的错误
+    pub error: Option<String>,
+}
+
+struct Shared {
+    app: AppHandle,
+    config: RwLock<KeepAwakeConfig>,
+    status: Mutex<KeepAwakeStatus>,
+}
+
+// 获取弹幕或提供服务期间阻止系统自动休眠，避免笔记本合盖前待机导致断开
+pub struct KeepAwake {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+    // 修改设置后通知后台线程立即重新检查
+    wake: Mutex<Sender<()>>,
+}
+
+impl KeepAwake {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            config: RwLock::new(config),
+            status: Mutex::new(KeepAwakeStatus::default()),
+        });
+        let (wake, receiver) = mpsc::channel();
+        // 系统返回的句柄不一定能跨线程使用，在同一个线程中申请和释放
+        let thread_shared = shared.clone();
+        if let Err(err) = std::thread::Builder::new()
+            .name("keep-awake".to_string())
+            .spawn(move || watch(thread_shared, receiver))
+        {
+            log::warn!("启动防休眠线程失败: {}", err);
+        }
+        Ok(KeepAwake {
+            shared,
+            store,
+            wake: Mutex::new(wake),
+        })
+    }
+
+    pub fn get_config(&self) -> KeepAwakeConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn status(&self) -> KeepAwakeStatus {
+        self.shared.status.lock().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: KeepAwakeConfig) -> AppResult<KeepAwakeConfig> {
+        *self.shared.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存防休眠设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化防休眠设置失败: {}", err),
+        }
+        let _ = self.wake.lock().unwrap().send(());
+        Ok(config)
+    }
+}
+
+fn watch(shared: Arc<Shared>, receiver: mpsc::Receiver<()>) {
+    // 持有期间系统不会自动休眠，释放后恢复
+    let mut held: Option<(keepawake::KeepAwake, bool)> = None;
+    loop {
+        let config = shared.config.read().unwrap().clone();
+        let reasons = if config.enabled {
+            reasons(&shared.app, &config)
+        } else {
+            Vec::new()
+        };
+
+        let mut error = None;
+        if reasons.is_empty() {
+            if held.take().is_some() {
+                log::info!("已恢复系统自动休眠");
+            }
+        } else if held
+            .as_ref()
+            .is_none_or(|(_, display)| *display != config.keep_display_on)
+        {
+            // 先释放旧的申请，修改了屏幕常亮设置时重新申请
+            held = None;
+            match keepawake::Builder::default()
+                .idle(true)
+                .sleep(true)
+                .display(config.keep_display_on)
+                .reason("正在获取直播弹幕")
+                .app_name("VTsuru Eventfetcher")
+                .app_reverse_domain("live.vtsuru.fetcher.client")
+                .create()
+            {
+                Ok(guard) => {
+                    log::info!("已阻止系统自动休眠: {}", reasons.join("、"));
+                    held = Some((guard, config.keep_display_on));
+                }
+                Err(err) => {
+                    log::warn!("阻止系统休眠失败: {}", err);
+                    error = Some(err.to_string());
+                }
+            }
+        }
+
+        let status = KeepAwakeStatus {
+            active: held.is_some(),
+            reasons,
+            error,
+        };
+        let changed = {
+            let mut current = shared.status.lock().unwrap();
+            let changed = current.active != status.active
+                || current.reasons != status.reasons
+                || current.error != status.error;
+            *current = status.clone();
+            changed
+        };
+        if changed {
+            let _ = shared.app.emit(STATUS_EVENT, &status);
+        }
+
+        match receiver.recv_timeout(CHECK_INTERVAL) {
+            Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+fn reasons(app: &AppHandle, config: &KeepAwakeConfig) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if config.while_fetching {
+        if let Some(rooms) = app.try_state::<RoomManager>() {
+            if !rooms.list_rooms().is_empty() {
+                reasons.push("获取弹幕".to_string());
+            }
+        }
+    }
+    if config.while_serving {
+        if let Some(registry) = app.try_state::<FileServerRegistry>() {
+            if registry.list().iter().any(|server| server.running) {
+                reasons.push("文件服务器".to_string());
+            }
+        }
+        if let Some(ws_server) = app.try_state::<WsServer>() {
+            if ws_server.status().running {
+                reasons.push("事件广播服务器".to_string());
+            }
+        }
+    }
+    reasons
+}
@@ -10,9 +10,235 @@ use tauri::Manager;
 use serde::Serialize;
 use sysinfo::System;
 
+mod error;
+use error::AppError;
+
+// B 站接口的公共方法
+mod bilibili;
+
+// 账号凭据
+mod credentials;
+use credentials::{
+    CredentialInfo, CredentialInput, CredentialManager, CredentialValidation, QrLogin,
+};
+
+// 弹幕连接
+mod danmaku;
+use danmaku::{
+    DanmakuEvent, DanmakuSender, DanmakuSource, EventKind, FilterRule, FilterRuleStatus,
+    FlaggedEvent, ModerationRule, ModerationRuleStatus, ReviewStatus, RoomManager, RoomStatus,
+};
+
+// 事件分发总线
+mod event_bus;
+use event_bus::{DropPolicy, EventBusMetrics, PERSIST_CAPACITY};
+
+// Prometheus 运行指标
+mod metrics;
+use metrics::Metrics;
+
+// 本地事件记录
+mod event_store;
+use event_store::{
+    EventPage, EventQuery, EventStats, EventStore, ExportFormat, LeaderboardQuery, Leaderboards,
+    Session, SessionStats,
+};
+
+// 收益统计
+mod revenue;
+use revenue::{Revenue, RevenueConfig, RevenueRange, RevenueReport};
+
+// WASM 插件
+mod plugin;
+use plugin::{PluginHost, PluginInfo};
+
+// 事件上传
+mod relay;
+use relay::{ReconcileReport, Relay, RelayBacklog, RelayConfig, RelayStatus};
+
+// OBS 控制
+mod obs;
+use obs::{ObsAction, ObsClient, ObsConfig, ObsStatus};
+
+// 事件模板
+mod template;
+
+// 自动化规则
+mod automation;
+use automation::{AutomationEngine, AutomationRule, ExecutionLog};
+
+// webhook 推送
+mod webhook;
+use webhook::{DeliveryRecord, WebhookConfig, WebhookDispatcher};
+
+// 语音朗读
+mod tts;
+use crate::tts::{TtsConfig, TtsManager, TtsStatus};
+
+// 事件提示音
+mod sound;
+use sound::{AudioDevice, SoundConfig, SoundPlayer};
+
+// 点歌队列
+mod song_request;
+use song_request::{SongQueue, SongRequestConfig, SongRequestManager};
+
+// 观众排队
+mod viewer_queue;
+use viewer_queue::{ViewerQueue, ViewerQueueConfig, ViewerQueueManager};
+
+// 弹幕投票
+mod poll;
+use poll::{Poll, PollManager, StartPoll};
+
+// 弹幕抽奖
+mod raffle;
+use raffle::{RaffleConfig, RaffleDraw, RaffleManager, RaffleStatus, StartRaffle};
+
+// 观众积分
+mod points;
+use points::{PointsBalance, PointsConfig, PointsLookup, PointsManager, PointsTransaction};
+
+// 分钟统计
+mod stats;
+use stats::{MinuteStats, StatsRecorder, StreamSummary};
+
+// 礼物图标、表情和头像等图片的本地缓存
+mod asset_cache;
+use asset_cache::{AssetCache, AssetCacheConfig, AssetCacheStats};
+
+// 合并相同的并发请求
+mod inflight;
+
+// 通过 B 站接口开播、下播和修改直播间信息
+mod live_control;
+use live_control::{LiveArea, LiveControl, LiveControlConfig, LiveStarted};
+
+// 礼物、大航海和醒目留言的自动感谢弹幕
+mod auto_thank;
+use auto_thank::{AutoThank, AutoThankConfig, ThankLog};
+
+// 用户信息和头像缓存
+mod user_info;
+use user_info::{UserInfo, UserInfoCache};
+
+// 直播间信息
+mod room_info;
+use room_info::{RoomInfo, RoomInfoCache};
+
+// 直播场次
+mod session;
+use session::SessionTracker;
+
+// 弹幕录制
+mod recorder;
+use recorder::{DanmakuRecorder, RecordingInfo, RecordingOptions};
+
+// 事件回放
+mod replay;
+use replay::{ReplayManager, ReplayStatus};
+
+// 托盘图标
+mod tray;
+use tray::{CloseBehavior, CloseSettings, WindowBehavior};
+
+// 开机启动和启动参数
+mod autostart;
+use autostart::{Autostart, AutostartConfig, AutostartStatus};
+
+// 按规则发送系统通知
+mod notifications;
+use notifications::{NotificationConfig, NotificationManager};
+
+// 按 cron 表达式执行的计划任务
+mod scheduler;
+use scheduler::{ScheduledTask, Scheduler, TaskRun, TaskStatus};
+
+// 获取弹幕期间阻止系统休眠
+mod keep_awake;
+use keep_awake::{KeepAwake, KeepAwakeConfig, KeepAwakeStatus};
+
+// 退出程序前的清理
+mod shutdown;
+
+// 主机 CPU、磁盘、网络等信息
+mod system_info;
+use system_info::{
+    CpuInfo, DiskInfo, GpuReport, MetricsStreamStatus, NetworkInfo, SystemMonitor, UptimeInfo,
+};
+
+// 内存中的日志缓冲，供前端查询和实时查看
+mod logs;
+use logs::{LevelConfig, LogEntry, LogLevels, LogQuery, LogRetention, LogRetentionConfig};
+
+// panic 和后台任务错误报告
+mod crash;
+use crash::{CrashConfig, CrashReport, CrashReporter};
+
+// 全局代理设置
+mod proxy;
+use proxy::{ProxyConfig, ProxySettings, ProxyTestResult};
+
+// 固定解析和 DNS over HTTPS
+mod dns;
+use dns::{DnsConfig, DnsSettings, ResolveResult};
+
+// 检查和安装更新
+mod update;
+use update::{InstallResult, UpdateChannel, UpdateConfig, UpdateInfo, UpdateManager};
+
+// 网络状况检测
+mod diagnostics;
+use diagnostics::{NetworkDiagnostics, NetworkReport};
+
+// 直播软件进程监控
+mod process_watch;
+use process_watch::{ProcessInfo, ProcessWatchConfig, ProcessWatcher, WatchedStatus};
+
+// 模拟事件和演示模式
+mod demo;
+use demo::{DemoConfig, DemoMode, DemoStatus};
+
+// 检查后台任务心跳，自动重启卡住的模块
+mod watchdog;
+use watchdog::{SubsystemRecovered, Watchdog};
+
+// vtsuru:// 链接和单实例参数转发
+mod deep_link;
+
+// 本地事件广播服务器
+mod ws_server;
+use ws_server::{WsServer, WsServerConfig, WsServerStatus};
+
+// 自动化规则触发的 MIDI 输出和 Stream Deck 信号服务器
+mod device_output;
+use device_output::{
+    DeviceOutput, DeviceOutputConfig, DeviceOutputStatus, MidiMessage, MidiPort, SignalClient,
+};
+
+// 全局快捷键
+mod hotkeys;
+use hotkeys::{HotkeyBinding, HotkeyStatus, Hotkeys};
+
+// 本机 HTTP 管理接口
+mod admin_api;
+use admin_api::{AdminApi, AdminApiConfig, AdminApiStatus};
+
+// 统一读取和修改各模块的设置
+mod settings;
+use settings::{ProfileList, Profiles, Settings, SettingsUpdate};
+
+// 首次运行引导时的环境检查
+mod setup_check;
+use setup_check::SetupReport;
+
 // 引入文件服务器模块
 mod file_server;
-use file_server::{FileServerConfig, FileServerStatus, FILE_SERVER};
+use file_server::{
+    AccessLog, AccessLogEntry, FileServerConfig, FileServerConfigUpdate, FileServerRegistry,
+    FileServerStatus,
+};
+use std::sync::Arc;
 
 // Define a struct to represent the data we want to send to the frontend.
 // It needs `Serialize` to be convertible to JSON.
@@ -48,8 +274,283 @@ fn get_memory_info() -> MemoryInfo {
 }
 
 #[tauri::command]
-fn quit_app() {
-    std::process::exit(0);
+fn get_cpu_info(monitor: tauri::State<'_, SystemMonitor>) -> CpuInfo {
+    monitor.cpu_info()
+}
+
+#[tauri::command]
+fn get_disk_info(monitor: tauri::State<'_, SystemMonitor>) -> Vec<DiskInfo> {
+    monitor.disk_info()
+}
+
+#[tauri::command]
+fn get_network_info(monitor: tauri::State<'_, SystemMonitor>) -> Vec<NetworkInfo> {
+    monitor.network_info()
+}
+
+// 第一次调用时需要加载驱动，放到后台线程执行
+#[tauri::command]
+async fn get_gpu_info(app: tauri::AppHandle) -> Result<GpuReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || app.state::<SystemMonitor>().gpu_info())
+        .await
+        .map_err(|err| AppError::Io(std::io::Error::other(err)))
+}
+
+#[tauri::command]
+fn get_uptime(monitor: tauri::State<'_, SystemMonitor>) -> UptimeInfo {
+    monitor.uptime()
+}
+
+// 默认每秒推送一次
+#[tauri::command]
+fn start_metrics_stream(
+    app: tauri::AppHandle,
+    monitor: tauri::State<'_, SystemMonitor>,
+    interval_ms: Option<u64>,
+) -> Result<MetricsStreamStatus, AppError> {
+    monitor.start_stream(&app, interval_ms.unwrap_or(1000))
+}
+
+#[tauri::command]
+fn stop_metrics_stream(monitor: tauri::State<'_, SystemMonitor>) -> MetricsStreamStatus {
+    monitor.stop_stream()
+}
+
+#[tauri::command]
+fn query_logs(
+    level: Option<String>,
+    module: Option<String>,
+    since: Option<i64>,
+    room_id: Option<u64>,
+    limit: Option<usize>,
+) -> Vec<LogEntry> {
+    logs::query(&LogQuery {
+        level,
+        module,
+        since,
+        room_id,
+        limit,
+    })
+}
+
+#[tauri::command]
+fn get_log_levels(levels: tauri::State<'_, LogLevels>) -> LevelConfig {
+    levels.get_config()
+}
+
+// level 为空时恢复默认级别，module 为 * 时修改默认级别
+#[tauri::command]
+fn set_log_level(
+    levels: tauri::State<'_, LogLevels>,
+    module: String,
+    level: Option<String>,
+) -> Result<LevelConfig, AppError> {
+    levels.set_level(&module, level.as_deref())
+}
+
+#[tauri::command]
+fn get_log_retention(retention: tauri::State<'_, LogRetention>) -> LogRetentionConfig {
+    retention.get_config()
+}
+
+#[tauri::command]
+fn update_log_retention(
+    retention: tauri::State<'_, LogRetention>,
+    config: LogRetentionConfig,
+) -> Result<LogRetentionConfig, AppError> {
+    retention.update_config(config)
+}
+
+// 把最近 days 天（默认 7 天）的日志打包到 path，返回打包的文件数量
+#[tauri::command]
+async fn export_logs_zip(
+    app: tauri::AppHandle,
+    path: String,
+    days: Option<u32>,
+) -> Result<usize, AppError> {
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|err| AppError::Io(std::io::Error::other(err)))?;
+    logs::export_zip(log_dir, std::path::PathBuf::from(path), days.unwrap_or(7)).await
+}
+
+#[tauri::command]
+fn get_crash_reports(crash: tauri::State<'_, CrashReporter>) -> Vec<CrashReport> {
+    crash.list()
+}
+
+#[tauri::command]
+fn clear_crash_reports(crash: tauri::State<'_, CrashReporter>) -> Result<(), AppError> {
+    crash.clear()
+}
+
+#[tauri::command]
+fn get_crash_config(crash: tauri::State<'_, CrashReporter>) -> CrashConfig {
+    crash.get_config()
+}
+
+#[tauri::command]
+fn update_crash_config(
+    crash: tauri::State<'_, CrashReporter>,
+    config: CrashConfig,
+) -> Result<CrashConfig, AppError> {
+    crash.update_config(config)
+}
+
+// 返回上传的报告数量
+#[tauri::command]
+async fn upload_crash_reports(crash: tauri::State<'_, CrashReporter>) -> Result<usize, AppError> {
+    crash.upload_pending().await
+}
+
+#[tauri::command]
+fn get_proxy_config(proxy: tauri::State<'_, ProxySettings>) -> ProxyConfig {
+    proxy.get_config()
+}
+
+#[tauri::command]
+fn update_proxy_config(
+    proxy: tauri::State<'_, ProxySettings>,
+    config: ProxyConfig,
+) -> Result<ProxyConfig, AppError> {
+    proxy.update_config(config)
+}
+
+// 测试尚未保存的代理配置
+#[tauri::command]
+async fn test_proxy(config: ProxyConfig) -> Result<ProxyTestResult, AppError> {
+    proxy::test(&config).await
+}
+
+#[tauri::command]
+fn get_dns_config(dns: tauri::State<'_, DnsSettings>) -> DnsConfig {
+    dns.get_config()
+}
+
+#[tauri::command]
+fn update_dns_config(
+    dns: tauri::State<'_, DnsSettings>,
+    config: DnsConfig,
+) -> Result<DnsConfig, AppError> {
+    dns.update_config(config)
+}
+
+// 按当前 DNS 设置解析域名，用于检查固定解析和 DoH 是否生效
+#[tauri::command]
+async fn resolve_host(host: String) -> Result<ResolveResult, AppError> {
+    dns::lookup(host.trim()).await
+}
+
+// channel 为空时使用设置中的更新通道
+#[tauri::command]
+async fn check_update(
+    updates: tauri::State<'_, UpdateManager>,
+    channel: Option<UpdateChannel>,
+) -> Result<Option<UpdateInfo>, AppError> {
+    updates.check(channel).await
+}
+
+#[tauri::command]
+async fn install_update(
+    updates: tauri::State<'_, UpdateManager>,
+) -> Result<InstallResult, AppError> {
+    updates.install().await
+}
+
+#[tauri::command]
+fn get_pending_update(updates: tauri::State<'_, UpdateManager>) -> Option<UpdateInfo> {
+    updates.pending()
+}
+
+#[tauri::command]
+fn get_update_config(updates: tauri::State<'_, UpdateManager>) -> UpdateConfig {
+    updates.get_config()
+}
+
+#[tauri::command]
+fn set_update_config(
+    updates: tauri::State<'_, UpdateManager>,
+    config: UpdateConfig,
+) -> UpdateConfig {
+    updates.update_config(config)
+}
+
+// targets 为额外检测的地址
+#[tauri::command]
+async fn run_network_check(
+    diagnostics: tauri::State<'_, NetworkDiagnostics>,
+    targets: Option<Vec<String>>,
+) -> Result<NetworkReport, AppError> {
+    diagnostics.run_check(targets.unwrap_or_default()).await
+}
+
+#[tauri::command]
+fn emit_test_event(
+    demo: tauri::State<'_, DemoMode>,
+    kind: EventKind,
+    overrides: Option<serde_json::Value>,
+) -> Result<DanmakuEvent, AppError> {
+    demo.emit_test_event(kind, overrides)
+}
+
+#[tauri::command]
+fn get_demo_status(demo: tauri::State<'_, DemoMode>) -> DemoStatus {
+    demo.status()
+}
+
+#[tauri::command]
+fn update_demo_config(
+    demo: tauri::State<'_, DemoMode>,
+    config: DemoConfig,
+) -> Result<DemoStatus, AppError> {
+    demo.update_config(config)
+}
+
+#[tauri::command]
+fn start_demo(demo: tauri::State<'_, DemoMode>) -> DemoStatus {
+    demo.start()
+}
+
+#[tauri::command]
+fn stop_demo(demo: tauri::State<'_, DemoMode>) -> DemoStatus {
+    demo.stop()
+}
+
+#[tauri::command]
+fn get_watchdog_recoveries(
+    watchdog: tauri::State<'_, Watchdog>,
+    limit: Option<usize>,
+) -> Vec<SubsystemRecovered> {
+    watchdog.recoveries(limit.unwrap_or(50))
+}
+
+#[tauri::command]
+fn get_processes(watcher: tauri::State<'_, ProcessWatcher>) -> Vec<ProcessInfo> {
+    watcher.list_processes()
+}
+
+#[tauri::command]
+fn get_process_watch_config(watcher: tauri::State<'_, ProcessWatcher>) -> ProcessWatchConfig {
+    watcher.get_config()
+}
+
+#[tauri::command]
+fn update_process_watch_config(
+    watcher: tauri::State<'_, ProcessWatcher>,
+    config: ProcessWatchConfig,
+) -> Result<ProcessWatchConfig, AppError> {
+    watcher.update_config(config)
+}
+
+#[tauri::command]
+fn get_watched_processes(watcher: tauri::State<'_, ProcessWatcher>) -> Vec<WatchedStatus> {
+    watcher.status()
+}
+
+#[tauri::command]
+async fn quit_app(app: tauri::AppHandle) {
+    shutdown::shutdown(app).await;
 }
 
 #[tauri::command]
@@ -58,71 +559,1780 @@ fn open_dev_tools(webview_window: tauri::WebviewWindow) {
 }
 
 // 文件服务器相关命令
+// 未指定 name 的命令作用于默认实例
+#[tauri::command]
+fn create_file_server(
+    registry: tauri::State<'_, FileServerRegistry>,
+    name: String,
+    folder_path: Option<String>,
+    port: Option<u16>,
+) -> Result<FileServerStatus, AppError> {
+    registry.create(name, folder_path, port)
+}
+
+#[tauri::command]
+fn list_file_servers(registry: tauri::State<'_, FileServerRegistry>) -> Vec<FileServerStatus> {
+    registry.list()
+}
+
+#[tauri::command]
+async fn delete_file_server(
+    registry: tauri::State<'_, FileServerRegistry>,
+    name: String,
+) -> Result<(), AppError> {
+    registry.delete(&name).await
+}
+
 #[tauri::command]
-fn start_file_server() -> Result<FileServerStatus, String> {
-    FILE_SERVER.start_server()
+async fn start_file_server(
+    registry: tauri::State<'_, FileServerRegistry>,
+    name: Option<String>,
+) -> Result<FileServerStatus, AppError> {
+    let file_server = registry.get(name.as_deref())?;
+    file_server.start_server().await
 }
 
 #[tauri::command]
-fn stop_file_server() -> Result<FileServerStatus, String> {
-    FILE_SERVER.stop_server()
+async fn stop_file_server(
+    registry: tauri::State<'_, FileServerRegistry>,
+    name: Option<String>,
+) -> Result<FileServerStatus, AppError> {
+    let file_server = registry.get(name.as_deref())?;
+    file_server.stop_server().await
 }
 
 #[tauri::command]
 fn update_file_server_config(
-    folder_path: Option<String>,
-    port: Option<u16>,
-) -> Result<FileServerConfig, String> {
-    FILE_SERVER.update_config(folder_path, port)
+    registry: tauri::State<'_, FileServerRegistry>,
+    name: Option<String>,
+    update: FileServerConfigUpdate,
+) -> Result<FileServerConfig, AppError> {
+    registry.update_config(name.as_deref(), update)
 }
 
 #[tauri::command]
-fn get_file_server_status() -> FileServerStatus {
-    FILE_SERVER.get_status()
+fn update_file_server_ip_filter(
+    registry: tauri::State<'_, FileServerRegistry>,
+    name: Option<String>,
+    allowlist: Vec<String>,
+    denylist: Vec<String>,
+) -> Result<FileServerStatus, AppError> {
+    registry.update_ip_filter(name.as_deref(), allowlist, denylist)
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_process::init())
-        .plugin(tauri_plugin_notification::init())
-        .plugin(tauri_plugin_os::init())
-        .plugin(tauri_plugin_store::Builder::new().build())
-        .plugin(tauri_plugin_log::Builder::new().build())
-        .plugin(tauri_plugin_updater::Builder::new().build())
-        .plugin(
-            tauri_plugin_log::Builder::new()
-                .target(tauri_plugin_log::Target::new(
-                    tauri_plugin_log::TargetKind::LogDir {
-                        file_name: Some("logs".to_string()),
-                    },
-                ))
-                .target(tauri_plugin_log::Target::new(
-                    tauri_plugin_log::TargetKind::Webview,
-                ))
-                .max_file_size(50_000 /* bytes */)
-                .build(),
+#[tauri::command]
+fn get_file_server_status(
+    registry: tauri::State<'_, FileServerRegistry>,
+    name: Option<String>,
+) -> Result<FileServerStatus, AppError> {
+    Ok(registry.get(name.as_deref())?.get_status())
+}
+
+#[tauri::command]
+fn get_file_server_logs(
+    registry: tauri::State<'_, FileServerRegistry>,
+    name: Option<String>,
+    limit: Option<usize>,
+) -> Vec<AccessLogEntry> {
+    registry.query_logs(name.as_deref(), limit.unwrap_or(200))
+}
+
+// 弹幕连接相关命令
+#[tauri::command]
+async fn add_room(
+    rooms: tauri::State<'_, RoomManager>,
+    credentials: tauri::State<'_, CredentialManager>,
+    mut source: DanmakuSource,
+) -> Result<RoomStatus, AppError> {
+    // 使用已保存的账号时在后端解密 cookie，前端不接触明文
+    if let DanmakuSource::Direct {
+        cookie,
+        credential_id: Some(id),
+        ..
+    } = &mut source
+    {
+        *cookie = Some(credentials.cookie(id)?);
+    }
+    rooms.add_room(source).await
+}
+
+// 在直播间发送弹幕，未指定账号时使用连接该直播间的账号或第一个 B 站账号
+#[tauri::command]
+async fn send_danmaku(
+    app: tauri::AppHandle,
+    sender: tauri::State<'_, DanmakuSender>,
+    room_id: u64,
+    text: String,
+    credential_id: Option<String>,
+    max_length: Option<usize>,
+) -> Result<Vec<String>, AppError> {
+    let cookie = danmaku::resolve_cookie(&app, room_id, credential_id)?;
+    sender
+        .send(
+            room_id,
+            &text,
+            &cookie,
+            max_length.unwrap_or(danmaku::DEFAULT_MAX_LENGTH),
         )
-        .plugin(tauri_plugin_http::init())
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
-            let _ = app
-                .get_webview_window("main")
-                .expect("no main window")
-                .set_focus();
-        }))
-        .plugin(tauri_plugin_autostart::init(
-            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
-            Some(vec!["--flag1", "--flag2"]),
-        ))
-        .plugin(tauri_plugin_opener::init())
+        .await
+}
+
+#[tauri::command]
+fn remove_room(rooms: tauri::State<'_, RoomManager>, room_id: u64) -> Result<(), AppError> {
+    rooms.remove_room(room_id)
+}
+
+#[tauri::command]
+fn list_rooms(rooms: tauri::State<'_, RoomManager>) -> Vec<u64> {
+    rooms.list_rooms()
+}
+
+// 事件总线各订阅者的积压和丢弃统计
+#[tauri::command]
+fn get_event_bus_metrics(rooms: tauri::State<'_, RoomManager>) -> EventBusMetrics {
+    rooms.bus_metrics()
+}
+
+#[tauri::command]
+fn get_rooms_status(rooms: tauri::State<'_, RoomManager>) -> Vec<RoomStatus> {
+    rooms.rooms_status()
+}
+
+#[tauri::command]
+fn get_filter_rules(rooms: tauri::State<'_, RoomManager>) -> Vec<FilterRuleStatus> {
+    rooms.filter_rules()
+}
+
+#[tauri::command]
+fn set_filter_rules(
+    rooms: tauri::State<'_, RoomManager>,
+    rules: Vec<FilterRule>,
+) -> Result<Vec<FilterRuleStatus>, AppError> {
+    rooms.set_filter_rules(rules)
+}
+
+#[tauri::command]
+fn reset_filter_hits(rooms: tauri::State<'_, RoomManager>) -> Vec<FilterRuleStatus> {
+    rooms.reset_filter_hits()
+}
+
+#[tauri::command]
+fn get_moderation_rules(rooms: tauri::State<'_, RoomManager>) -> Vec<ModerationRuleStatus> {
+    rooms.moderation_rules()
+}
+
+#[tauri::command]
+fn set_moderation_rules(
+    rooms: tauri::State<'_, RoomManager>,
+    rules: Vec<ModerationRule>,
+) -> Result<Vec<ModerationRuleStatus>, AppError> {
+    rooms.set_moderation_rules(rules)
+}
+
+// 把审核规则转为过滤规则，返回新的过滤规则列表
+#[tauri::command]
+fn promote_moderation_rule(
+    rooms: tauri::State<'_, RoomManager>,
+    id: String,
+) -> Result<Vec<FilterRuleStatus>, AppError> {
+    rooms.promote_moderation_rule(&id)
+}
+
+#[tauri::command]
+fn list_flagged_events(
+    rooms: tauri::State<'_, RoomManager>,
+    pending_only: Option<bool>,
+    limit: Option<usize>,
+) -> Vec<FlaggedEvent> {
+    rooms.flagged_events(pending_only.unwrap_or(false), limit.unwrap_or(100))
+}
+
+#[tauri::command]
+fn review_flagged_event(
+    rooms: tauri::State<'_, RoomManager>,
+    id: String,
+    status: ReviewStatus,
+) -> Result<FlaggedEvent, AppError> {
+    rooms.review_flagged_event(&id, status)
+}
+
+#[tauri::command]
+fn clear_flagged_events(rooms: tauri::State<'_, RoomManager>) {
+    rooms.clear_flagged_events()
+}
+
+#[tauri::command]
+fn get_relay_config(relay: tauri::State<'_, Relay>) -> RelayConfig {
+    relay.get_config()
+}
+
+#[tauri::command]
+fn update_relay_config(
+    relay: tauri::State<'_, Relay>,
+    config: RelayConfig,
+) -> Result<RelayStatus, AppError> {
+    relay.update_config(config)
+}
+
+#[tauri::command]
+fn get_relay_status(relay: tauri::State<'_, Relay>) -> RelayStatus {
+    relay.status()
+}
+
+// 查看离线队列中等待上传的事件
+#[tauri::command]
+fn get_relay_backlog(relay: tauri::State<'_, Relay>, limit: Option<usize>) -> RelayBacklog {
+    relay.backlog(limit.unwrap_or(50))
+}
+
+// 对比一段时间内本地已上传与服务端收到的事件数量，时间为 Unix 毫秒时间戳
+#[tauri::command]
+async fn reconcile_relay(
+    relay: tauri::State<'_, Relay>,
+    start: i64,
+    end: i64,
+) -> Result<ReconcileReport, AppError> {
+    relay.reconcile(start, end).await
+}
+
+#[tauri::command]
+fn clear_relay_queue(relay: tauri::State<'_, Relay>) -> RelayStatus {
+    relay.clear_queue()
+}
+
+#[tauri::command]
+async fn query_events(
+    store: tauri::State<'_, EventStore>,
+    query: EventQuery,
+) -> Result<EventPage, AppError> {
+    store.query(&query)
+}
+
+// 各类型事件的数量和金额，未指定时间范围时统计今天零点到现在
+#[tauri::command]
+async fn get_event_stats(
+    store: tauri::State<'_, EventStore>,
+    room_id: Option<u64>,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> Result<EventStats, AppError> {
+    store.stats(
+        room_id,
+        start.unwrap_or_else(event_store::today_start),
+        end.unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+    )
+}
+
+#[tauri::command]
+async fn get_leaderboards(
+    store: tauri::State<'_, EventStore>,
+    query: LeaderboardQuery,
+) -> Result<Leaderboards, AppError> {
+    store.leaderboards(&query)
+}
+
+#[tauri::command]
+fn get_revenue_config(revenue: tauri::State<'_, Revenue>) -> RevenueConfig {
+    revenue.get_config()
+}
+
+#[tauri::command]
+fn update_revenue_config(
+    revenue: tauri::State<'_, Revenue>,
+    config: RevenueConfig,
+) -> Result<RevenueConfig, AppError> {
+    revenue.update_config(config)
+}
+
+#[tauri::command]
+async fn get_revenue_report(
+    revenue: tauri::State<'_, Revenue>,
+    range: RevenueRange,
+) -> Result<RevenueReport, AppError> {
+    revenue.report(&range)
+}
+
+// 返回导出的时间段数量
+#[tauri::command]
+async fn export_revenue_csv(
+    revenue: tauri::State<'_, Revenue>,
+    range: RevenueRange,
+    path: String,
+) -> Result<usize, AppError> {
+    revenue.export_csv(&range, std::path::Path::new(&path))
+}
+
+#[tauri::command]
+async fn export_events(
+    store: tauri::State<'_, EventStore>,
+    query: EventQuery,
+    format: ExportFormat,
+    path: String,
+) -> Result<u64, AppError> {
+    store.export(&query, format, std::path::Path::new(&path))
+}
+
+#[tauri::command]
+fn list_credentials(credentials: tauri::State<'_, CredentialManager>) -> Vec<CredentialInfo> {
+    credentials.list()
+}
+
+#[tauri::command]
+fn add_credential(
+    credentials: tauri::State<'_, CredentialManager>,
+    credential: CredentialInput,
+) -> Result<CredentialInfo, AppError> {
+    credentials.add(credential)
+}
+
+#[tauri::command]
+async fn validate_credential(
+    credentials: tauri::State<'_, CredentialManager>,
+    id: String,
+) -> Result<CredentialValidation, AppError> {
+    credentials.validate(&id).await
+}
+
+#[tauri::command]
+fn remove_credential(
+    credentials: tauri::State<'_, CredentialManager>,
+    id: String,
+) -> Result<(), AppError> {
+    credentials.remove(&id)
+}
+
+#[tauri::command]
+async fn start_qr_login(
+    credentials: tauri::State<'_, CredentialManager>,
+) -> Result<QrLogin, AppError> {
+    credentials.start_qr_login().await
+}
+
+#[tauri::command]
+fn cancel_qr_login(credentials: tauri::State<'_, CredentialManager>) {
+    credentials.cancel_qr_login()
+}
+
+#[tauri::command]
+fn list_plugins(plugins: tauri::State<'_, PluginHost>) -> Vec<PluginInfo> {
+    plugins.list()
+}
+
+#[tauri::command]
+async fn install_plugin(
+    plugins: tauri::State<'_, PluginHost>,
+    path: String,
+    name: Option<String>,
+) -> Result<PluginInfo, AppError> {
+    plugins.install(std::path::Path::new(&path), name)
+}
+
+#[tauri::command]
+fn uninstall_plugin(plugins: tauri::State<'_, PluginHost>, id: String) -> Result<(), AppError> {
+    plugins.uninstall(&id)
+}
+
+#[tauri::command]
+fn enable_plugin(
+    plugins: tauri::State<'_, PluginHost>,
+    id: String,
+) -> Result<PluginInfo, AppError> {
+    plugins.set_enabled(&id, true)
+}
+
+#[tauri::command]
+fn disable_plugin(
+    plugins: tauri::State<'_, PluginHost>,
+    id: String,
+) -> Result<PluginInfo, AppError> {
+    plugins.set_enabled(&id, false)
+}
+
+#[tauri::command]
+fn get_ws_server_status(server: tauri::State<'_, WsServer>) -> WsServerStatus {
+    server.status()
+}
+
+#[tauri::command]
+async fn update_ws_server_config(
+    server: tauri::State<'_, WsServer>,
+    config: WsServerConfig,
+) -> Result<WsServerStatus, AppError> {
+    server.update_config(config).await
+}
+
+#[tauri::command]
+async fn start_ws_server(server: tauri::State<'_, WsServer>) -> Result<WsServerStatus, AppError> {
+    server.start().await
+}
+
+#[tauri::command]
+async fn stop_ws_server(server: tauri::State<'_, WsServer>) -> Result<WsServerStatus, AppError> {
+    server.stop().await
+}
+
+#[tauri::command]
+fn get_device_output_status(output: tauri::State<'_, DeviceOutput>) -> DeviceOutputStatus {
+    output.status()
+}
+
+#[tauri::command]
+async fn update_device_output_config(
+    output: tauri::State<'_, DeviceOutput>,
+    config: DeviceOutputConfig,
+) -> Result<DeviceOutputStatus, AppError> {
+    output.update_config(config).await
+}
+
+#[tauri::command]
+async fn start_signal_server(
+    output: tauri::State<'_, DeviceOutput>,
+) -> Result<DeviceOutputStatus, AppError> {
+    output.start().await
+}
+
+#[tauri::command]
+async fn stop_signal_server(
+    output: tauri::State<'_, DeviceOutput>,
+) -> Result<DeviceOutputStatus, AppError> {
+    output.stop().await
+}
+
+#[tauri::command]
+fn list_signal_clients(output: tauri::State<'_, DeviceOutput>) -> Vec<SignalClient> {
+    output.signal_clients()
+}
+
+// 测试信号服务器，返回收到信号的客户端数量
+#[tauri::command]
+fn send_test_signal(
+    output: tauri::State<'_, DeviceOutput>,
+    name: String,
+) -> Result<usize, AppError> {
+    output.send_signal(&name, None, None)
+}
+
+#[tauri::command]
+fn list_midi_ports(output: tauri::State<'_, DeviceOutput>) -> Result<Vec<MidiPort>, AppError> {
+    output.midi_ports()
+}
+
+// 测试 MIDI 设备
+#[tauri::command]
+fn send_midi_message(
+    output: tauri::State<'_, DeviceOutput>,
+    port: String,
+    message: MidiMessage,
+) -> Result<(), AppError> {
+    output.send_midi(&port, &message)
+}
+
+#[tauri::command]
+fn reset_midi_connections(output: tauri::State<'_, DeviceOutput>) {
+    output.close_midi()
+}
+
+#[tauri::command]
+fn get_settings(app: tauri::AppHandle) -> Settings {
+    settings::get(&app)
+}
+
+// 只修改提供的部分，完成后发送 settings-changed 事件
+#[tauri::command]
+async fn update_settings(
+    app: tauri::AppHandle,
+    update: SettingsUpdate,
+) -> Result<Settings, AppError> {
+    settings::update(&app, update).await
+}
+
+// 默认不导出密码和令牌，便于分享给他人
+#[tauri::command]
+async fn export_config(
+    app: tauri::AppHandle,
+    path: String,
+    include_secrets: Option<bool>,
+) -> Result<(), AppError> {
+    settings::export(
+        &app,
+        std::path::Path::new(&path),
+        include_secrets.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+async fn import_config(app: tauri::AppHandle, path: String) -> Result<Settings, AppError> {
+    settings::import(&app, std::path::Path::new(&path)).await
+}
+
+// 检查网络、端口、账号、开机启动和防火墙，返回给引导页面显示的清单
+#[tauri::command]
+async fn run_setup_checks(app: tauri::AppHandle) -> SetupReport {
+    setup_check::run(&app).await
+}
+
+#[tauri::command]
+fn list_profiles(profiles: tauri::State<'_, Profiles>) -> ProfileList {
+    profiles.list()
+}
+
+#[tauri::command]
+fn save_profile(
+    profiles: tauri::State<'_, Profiles>,
+    name: String,
+) -> Result<ProfileList, AppError> {
+    profiles.save(&name)
+}
+
+#[tauri::command]
+async fn apply_profile(
+    profiles: tauri::State<'_, Profiles>,
+    name: String,
+) -> Result<Settings, AppError> {
+    profiles.apply(&name).await
+}
+
+#[tauri::command]
+fn delete_profile(
+    profiles: tauri::State<'_, Profiles>,
+    name: String,
+) -> Result<ProfileList, AppError> {
+    profiles.delete(&name)
+}
+
+#[tauri::command]
+fn get_autostart_status(
+    autostart: tauri::State<'_, Autostart>,
+) -> Result<AutostartStatus, AppError> {
+    autostart.status()
+}
+
+#[tauri::command]
+fn set_autostart(
+    autostart: tauri::State<'_, Autostart>,
+    enabled: bool,
+    start_minimized: bool,
+    delay_seconds: u32,
+) -> Result<AutostartStatus, AppError> {
+    autostart.set(
+        enabled,
+        AutostartConfig {
+            start_minimized,
+            delay_seconds,
+        },
+    )
+}
+
+#[tauri::command]
+fn get_notification_config(
+    notifications: tauri::State<'_, NotificationManager>,
+) -> NotificationConfig {
+    notifications.get_config()
+}
+
+#[tauri::command]
+fn update_notification_config(
+    notifications: tauri::State<'_, NotificationManager>,
+    config: NotificationConfig,
+) -> Result<NotificationConfig, AppError> {
+    notifications.update_config(config)
+}
+
+#[tauri::command]
+fn send_test_notification(
+    notifications: tauri::State<'_, NotificationManager>,
+) -> Result<(), AppError> {
+    notifications.send_test()
+}
+
+#[tauri::command]
+fn get_room_info(room_info: tauri::State<'_, RoomInfoCache>, room_id: u64) -> Option<RoomInfo> {
+    room_info.get(room_id)
+}
+
+#[tauri::command]
+fn list_room_info(room_info: tauri::State<'_, RoomInfoCache>) -> Vec<RoomInfo> {
+    room_info.list()
+}
+
+#[tauri::command]
+async fn refresh_room_info(
+    room_info: tauri::State<'_, RoomInfoCache>,
+    room_id: u64,
+) -> Result<RoomInfo, AppError> {
+    room_info.refresh(room_id).await
+}
+
+// B 站接口各分组剩余的请求额度
+#[tauri::command]
+fn get_api_budgets() -> Vec<bilibili::RateBudget> {
+    bilibili::budgets()
+}
+
+#[tauri::command]
+async fn get_user_info(
+    users: tauri::State<'_, UserInfoCache>,
+    uid: u64,
+    refresh: Option<bool>,
+) -> Result<UserInfo, AppError> {
+    users.get(uid, refresh.unwrap_or(false)).await
+}
+
+// 返回头像在本地缓存中的路径
+#[tauri::command]
+async fn get_user_avatar(
+    users: tauri::State<'_, UserInfoCache>,
+    uid: u64,
+) -> Result<String, AppError> {
+    let path = users.avatar(uid).await?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+fn clear_user_info_cache(users: tauri::State<'_, UserInfoCache>) {
+    users.clear()
+}
+
+#[tauri::command]
+fn get_asset_cache_config(assets: tauri::State<'_, AssetCache>) -> AssetCacheConfig {
+    assets.get_config()
+}
+
+#[tauri::command]
+async fn update_asset_cache_config(
+    assets: tauri::State<'_, AssetCache>,
+    config: AssetCacheConfig,
+) -> Result<AssetCacheConfig, AppError> {
+    assets.update_config(config).await
+}
+
+#[tauri::command]
+async fn get_asset_cache_stats(
+    assets: tauri::State<'_, AssetCache>,
+) -> Result<AssetCacheStats, AppError> {
+    Ok(assets.stats().await)
+}
+
+#[tauri::command]
+async fn purge_asset_cache(
+    assets: tauri::State<'_, AssetCache>,
+) -> Result<AssetCacheStats, AppError> {
+    Ok(assets.purge().await)
+}
+
+#[tauri::command]
+fn get_live_control_config(live: tauri::State<'_, LiveControl>) -> LiveControlConfig {
+    live.get_config()
+}
+
+#[tauri::command]
+fn update_live_control_config(
+    live: tauri::State<'_, LiveControl>,
+    config: LiveControlConfig,
+) -> Result<LiveControlConfig, AppError> {
+    live.update_config(config)
+}
+
+// 参数为空时使用开播设置中的账号、直播间和分区
+#[tauri::command]
+async fn start_live(
+    live: tauri::State<'_, LiveControl>,
+    room_id: Option<u64>,
+    area_id: Option<u32>,
+    credential_id: Option<String>,
+) -> Result<LiveStarted, AppError> {
+    live.start(room_id, area_id, credential_id).await
+}
+
+#[tauri::command]
+async fn stop_live(
+    live: tauri::State<'_, LiveControl>,
+    room_id: Option<u64>,
+    credential_id: Option<String>,
+) -> Result<u64, AppError> {
+    live.stop(room_id, credential_id).await
+}
+
+#[tauri::command]
+async fn update_live_room(
+    live: tauri::State<'_, LiveControl>,
+    room_id: Option<u64>,
+    title: Option<String>,
+    area_id: Option<u32>,
+    credential_id: Option<String>,
+) -> Result<(), AppError> {
+    live.update_room(room_id, title, area_id, credential_id)
+        .await
+}
+
+#[tauri::command]
+async fn list_live_areas(live: tauri::State<'_, LiveControl>) -> Result<Vec<LiveArea>, AppError> {
+    live.areas().await
+}
+
+#[tauri::command]
+fn get_auto_thank_config(auto_thank: tauri::State<'_, AutoThank>) -> AutoThankConfig {
+    auto_thank.get_config()
+}
+
+#[tauri::command]
+fn update_auto_thank_config(
+    auto_thank: tauri::State<'_, AutoThank>,
+    config: AutoThankConfig,
+) -> Result<AutoThankConfig, AppError> {
+    auto_thank.update_config(config)
+}
+
+#[tauri::command]
+fn get_auto_thank_logs(
+    auto_thank: tauri::State<'_, AutoThank>,
+    limit: Option<usize>,
+) -> Vec<ThankLog> {
+    auto_thank.logs(limit.unwrap_or(100))
+}
+
+#[tauri::command]
+fn list_scheduled_tasks(scheduler: tauri::State<'_, Scheduler>) -> Vec<TaskStatus> {
+    scheduler.list()
+}
+
+#[tauri::command]
+fn save_scheduled_task(
+    scheduler: tauri::State<'_, Scheduler>,
+    task: ScheduledTask,
+) -> Result<Vec<TaskStatus>, AppError> {
+    scheduler.save(task)
+}
+
+#[tauri::command]
+fn delete_scheduled_task(
+    scheduler: tauri::State<'_, Scheduler>,
+    id: String,
+) -> Result<Vec<TaskStatus>, AppError> {
+    scheduler.delete(&id)
+}
+
+#[tauri::command]
+async fn run_scheduled_task(
+    scheduler: tauri::State<'_, Scheduler>,
+    id: String,
+) -> Result<TaskRun, AppError> {
+    scheduler.run_now(&id).await
+}
+
+#[tauri::command]
+fn get_keep_awake_config(keep_awake: tauri::State<'_, KeepAwake>) -> KeepAwakeConfig {
+    keep_awake.get_config()
+}
+
+#[tauri::command]
+fn update_keep_awake_config(
+    keep_awake: tauri::State<'_, KeepAwake>,
+    config: KeepAwakeConfig,
+) -> Result<KeepAwakeConfig, AppError> {
+    keep_awake.update_config(config)
+}
+
+#[tauri::command]
+fn get_keep_awake_status(keep_awake: tauri::State<'_, KeepAwake>) -> KeepAwakeStatus {
+    keep_awake.status()
+}
+
+#[tauri::command]
+fn get_hotkeys(hotkeys: tauri::State<'_, Hotkeys>) -> Vec<HotkeyStatus> {
+    hotkeys.statuses()
+}
+
+#[tauri::command]
+fn update_hotkeys(
+    hotkeys: tauri::State<'_, Hotkeys>,
+    bindings: Vec<HotkeyBinding>,
+) -> Result<Vec<HotkeyStatus>, AppError> {
+    hotkeys.update(bindings)
+}
+
+#[tauri::command]
+fn get_admin_api_status(api: tauri::State<'_, AdminApi>) -> AdminApiStatus {
+    api.status()
+}
+
+#[tauri::command]
+async fn update_admin_api_config(
+    api: tauri::State<'_, AdminApi>,
+    config: AdminApiConfig,
+) -> Result<AdminApiStatus, AppError> {
+    api.update_config(config).await
+}
+
+#[tauri::command]
+fn regenerate_admin_api_token(api: tauri::State<'_, AdminApi>) -> AdminApiStatus {
+    api.regenerate_token()
+}
+
+#[tauri::command]
+fn get_obs_config(obs: tauri::State<'_, ObsClient>) -> ObsConfig {
+    obs.get_config()
+}
+
+#[tauri::command]
+fn update_obs_config(
+    obs: tauri::State<'_, ObsClient>,
+    config: ObsConfig,
+) -> Result<ObsConfig, AppError> {
+    obs.update_config(config)
+}
+
+#[tauri::command]
+fn get_obs_status(obs: tauri::State<'_, ObsClient>) -> ObsStatus {
+    obs.status()
+}
+
+#[tauri::command]
+async fn connect_obs(obs: tauri::State<'_, ObsClient>) -> Result<ObsStatus, AppError> {
+    obs.connect().await
+}
+
+#[tauri::command]
+fn disconnect_obs(obs: tauri::State<'_, ObsClient>) -> ObsStatus {
+    obs.disconnect()
+}
+
+#[tauri::command]
+async fn get_obs_scenes(obs: tauri::State<'_, ObsClient>) -> Result<Vec<String>, AppError> {
+    obs.scenes().await
+}
+
+#[tauri::command]
+async fn switch_obs_scene(obs: tauri::State<'_, ObsClient>, scene: String) -> Result<(), AppError> {
+    obs.run_action(&ObsAction::SwitchScene { scene }).await
+}
+
+// visible 为空时切换来源当前的显示状态
+#[tauri::command]
+async fn set_obs_source_visibility(
+    obs: tauri::State<'_, ObsClient>,
+    scene: String,
+    source: String,
+    visible: Option<bool>,
+) -> Result<(), AppError> {
+    obs.run_action(&ObsAction::SetSourceVisibility {
+        scene,
+        source,
+        visible,
+        duration_secs: None,
+    })
+    .await
+}
+
+#[tauri::command]
+async fn start_obs_recording(obs: tauri::State<'_, ObsClient>) -> Result<(), AppError> {
+    obs.run_action(&ObsAction::StartRecording).await
+}
+
+#[tauri::command]
+async fn stop_obs_recording(obs: tauri::State<'_, ObsClient>) -> Result<(), AppError> {
+    obs.run_action(&ObsAction::StopRecording).await
+}
+
+#[tauri::command]
+fn list_automation_rules(automation: tauri::State<'_, AutomationEngine>) -> Vec<AutomationRule> {
+    automation.rules()
+}
+
+#[tauri::command]
+fn save_automation_rule(
+    automation: tauri::State<'_, AutomationEngine>,
+    rule: AutomationRule,
+) -> Result<AutomationRule, AppError> {
+    automation.save_rule(rule)
+}
+
+#[tauri::command]
+fn delete_automation_rule(
+    automation: tauri::State<'_, AutomationEngine>,
+    id: String,
+) -> Result<(), AppError> {
+    automation.delete_rule(&id)
+}
+
+#[tauri::command]
+fn get_automation_logs(
+    automation: tauri::State<'_, AutomationEngine>,
+    rule_id: Option<String>,
+    limit: Option<usize>,
+) -> Vec<ExecutionLog> {
+    automation.logs(rule_id.as_deref(), limit.unwrap_or(200))
+}
+
+#[tauri::command]
+fn clear_automation_logs(automation: tauri::State<'_, AutomationEngine>) {
+    automation.clear_logs()
+}
+
+#[tauri::command]
+fn list_webhooks(webhooks: tauri::State<'_, WebhookDispatcher>) -> Vec<WebhookConfig> {
+    webhooks.list()
+}
+
+#[tauri::command]
+fn save_webhook(
+    webhooks: tauri::State<'_, WebhookDispatcher>,
+    webhook: WebhookConfig,
+) -> Result<WebhookConfig, AppError> {
+    webhooks.save_webhook(webhook)
+}
+
+#[tauri::command]
+fn delete_webhook(
+    webhooks: tauri::State<'_, WebhookDispatcher>,
+    id: String,
+) -> Result<(), AppError> {
+    webhooks.delete_webhook(&id)
+}
+
+#[tauri::command]
+fn get_webhook_deliveries(
+    webhooks: tauri::State<'_, WebhookDispatcher>,
+    webhook_id: Option<String>,
+    limit: Option<usize>,
+) -> Vec<DeliveryRecord> {
+    webhooks.deliveries(webhook_id.as_deref(), limit.unwrap_or(100))
+}
+
+#[tauri::command]
+fn get_tts_config(tts: tauri::State<'_, TtsManager>) -> TtsConfig {
+    tts.get_config()
+}
+
+#[tauri::command]
+fn update_tts_config(
+    tts: tauri::State<'_, TtsManager>,
+    config: TtsConfig,
+) -> Result<TtsConfig, AppError> {
+    tts.update_config(config)
+}
+
+#[tauri::command]
+fn get_tts_status(tts: tauri::State<'_, TtsManager>) -> TtsStatus {
+    tts.status()
+}
+
+#[tauri::command]
+fn pause_tts(tts: tauri::State<'_, TtsManager>) -> TtsStatus {
+    tts.pause()
+}
+
+#[tauri::command]
+fn resume_tts(tts: tauri::State<'_, TtsManager>) -> TtsStatus {
+    tts.resume()
+}
+
+#[tauri::command]
+fn skip_tts(tts: tauri::State<'_, TtsManager>) -> TtsStatus {
+    tts.skip()
+}
+
+#[tauri::command]
+fn clear_tts_queue(tts: tauri::State<'_, TtsManager>) -> TtsStatus {
+    tts.clear()
+}
+
+#[tauri::command]
+fn speak_text(tts: tauri::State<'_, TtsManager>, text: String) -> TtsStatus {
+    tts.speak(text)
+}
+
+#[tauri::command]
+fn list_audio_devices() -> Result<Vec<AudioDevice>, AppError> {
+    sound::list_devices()
+}
+
+#[tauri::command]
+fn get_sound_config(sounds: tauri::State<'_, SoundPlayer>) -> SoundConfig {
+    sounds.get_config()
+}
+
+#[tauri::command]
+fn update_sound_config(
+    sounds: tauri::State<'_, SoundPlayer>,
+    config: SoundConfig,
+) -> Result<SoundConfig, AppError> {
+    sounds.update_config(config)
+}
+
+#[tauri::command]
+fn play_sound(
+    sounds: tauri::State<'_, SoundPlayer>,
+    path: String,
+    volume: Option<f32>,
+) -> Result<(), AppError> {
+    sounds.play(path, volume.unwrap_or(1.0))
+}
+
+#[tauri::command]
+fn stop_sounds(sounds: tauri::State<'_, SoundPlayer>) {
+    sounds.stop()
+}
+
+#[tauri::command]
+fn get_song_request_config(songs: tauri::State<'_, SongRequestManager>) -> SongRequestConfig {
+    songs.get_config()
+}
+
+#[tauri::command]
+fn update_song_request_config(
+    songs: tauri::State<'_, SongRequestManager>,
+    config: SongRequestConfig,
+) -> Result<SongRequestConfig, AppError> {
+    songs.update_config(config)
+}
+
+#[tauri::command]
+fn list_song_requests(songs: tauri::State<'_, SongRequestManager>) -> SongQueue {
+    songs.queue()
+}
+
+#[tauri::command]
+fn move_song_request(
+    songs: tauri::State<'_, SongRequestManager>,
+    id: String,
+    index: usize,
+) -> Result<SongQueue, AppError> {
+    songs.move_to(&id, index)
+}
+
+#[tauri::command]
+fn mark_song_played(
+    songs: tauri::State<'_, SongRequestManager>,
+    id: String,
+) -> Result<SongQueue, AppError> {
+    songs.mark_played(&id)
+}
+
+#[tauri::command]
+fn remove_song_request(
+    songs: tauri::State<'_, SongRequestManager>,
+    id: String,
+) -> Result<SongQueue, AppError> {
+    songs.remove(&id)
+}
+
+#[tauri::command]
+fn clear_song_requests(songs: tauri::State<'_, SongRequestManager>) -> Result<SongQueue, AppError> {
+    songs.clear()
+}
+
+#[tauri::command]
+fn list_song_blacklist(songs: tauri::State<'_, SongRequestManager>) -> Vec<String> {
+    songs.blacklist()
+}
+
+#[tauri::command]
+fn add_song_blacklist(
+    songs: tauri::State<'_, SongRequestManager>,
+    song: String,
+) -> Result<Vec<String>, AppError> {
+    songs.add_blacklist(&song)
+}
+
+#[tauri::command]
+fn remove_song_blacklist(
+    songs: tauri::State<'_, SongRequestManager>,
+    song: String,
+) -> Result<Vec<String>, AppError> {
+    songs.remove_blacklist(&song)
+}
+
+#[tauri::command]
+fn get_viewer_queue_config(queue: tauri::State<'_, ViewerQueueManager>) -> ViewerQueueConfig {
+    queue.get_config()
+}
+
+#[tauri::command]
+fn update_viewer_queue_config(
+    queue: tauri::State<'_, ViewerQueueManager>,
+    config: ViewerQueueConfig,
+) -> Result<ViewerQueueConfig, AppError> {
+    queue.update_config(config)
+}
+
+#[tauri::command]
+fn get_viewer_queue(queue: tauri::State<'_, ViewerQueueManager>) -> ViewerQueue {
+    queue.queue()
+}
+
+#[tauri::command]
+fn pop_viewer_queue(queue: tauri::State<'_, ViewerQueueManager>) -> Result<ViewerQueue, AppError> {
+    queue.pop()
+}
+
+#[tauri::command]
+fn skip_viewer_queue_entry(
+    queue: tauri::State<'_, ViewerQueueManager>,
+    id: String,
+) -> Result<ViewerQueue, AppError> {
+    queue.skip(&id)
+}
+
+#[tauri::command]
+fn move_viewer_queue_entry(
+    queue: tauri::State<'_, ViewerQueueManager>,
+    id: String,
+    index: usize,
+) -> Result<ViewerQueue, AppError> {
+    queue.move_to(&id, index)
+}
+
+#[tauri::command]
+fn remove_viewer_queue_entry(
+    queue: tauri::State<'_, ViewerQueueManager>,
+    id: String,
+) -> Result<ViewerQueue, AppError> {
+    queue.remove(&id)
+}
+
+#[tauri::command]
+fn clear_viewer_queue(
+    queue: tauri::State<'_, ViewerQueueManager>,
+) -> Result<ViewerQueue, AppError> {
+    queue.clear()
+}
+
+#[tauri::command]
+fn start_poll(polls: tauri::State<'_, PollManager>, poll: StartPoll) -> Result<Poll, AppError> {
+    polls.start(poll)
+}
+
+#[tauri::command]
+fn end_poll(polls: tauri::State<'_, PollManager>) -> Result<Poll, AppError> {
+    polls.end()
+}
+
+#[tauri::command]
+fn get_current_poll(polls: tauri::State<'_, PollManager>) -> Option<Poll> {
+    polls.current()
+}
+
+#[tauri::command]
+fn list_poll_history(polls: tauri::State<'_, PollManager>, limit: Option<usize>) -> Vec<Poll> {
+    polls.history(limit.unwrap_or(20))
+}
+
+#[tauri::command]
+fn clear_poll_history(polls: tauri::State<'_, PollManager>) {
+    polls.clear_history()
+}
+
+#[tauri::command]
+fn get_raffle_config(raffles: tauri::State<'_, RaffleManager>) -> RaffleConfig {
+    raffles.get_config()
+}
+
+#[tauri::command]
+fn update_raffle_config(
+    raffles: tauri::State<'_, RaffleManager>,
+    config: RaffleConfig,
+) -> Result<RaffleConfig, AppError> {
+    raffles.update_config(config)
+}
+
+#[tauri::command]
+fn start_raffle(
+    raffles: tauri::State<'_, RaffleManager>,
+    raffle: StartRaffle,
+) -> Result<RaffleStatus, AppError> {
+    raffles.start(raffle)
+}
+
+#[tauri::command]
+fn draw_raffle(raffles: tauri::State<'_, RaffleManager>) -> Result<RaffleDraw, AppError> {
+    raffles.draw()
+}
+
+#[tauri::command]
+fn cancel_raffle(raffles: tauri::State<'_, RaffleManager>) -> Result<RaffleStatus, AppError> {
+    raffles.cancel()
+}
+
+#[tauri::command]
+fn get_raffle_status(raffles: tauri::State<'_, RaffleManager>) -> RaffleStatus {
+    raffles.status()
+}
+
+#[tauri::command]
+fn list_raffle_history(
+    raffles: tauri::State<'_, RaffleManager>,
+    limit: Option<usize>,
+) -> Vec<RaffleDraw> {
+    raffles.history(limit.unwrap_or(20))
+}
+
+#[tauri::command]
+fn get_points_config(points: tauri::State<'_, PointsManager>) -> PointsConfig {
+    points.get_config()
+}
+
+#[tauri::command]
+fn update_points_config(
+    points: tauri::State<'_, PointsManager>,
+    config: PointsConfig,
+) -> Result<PointsConfig, AppError> {
+    points.update_config(config)
+}
+
+#[tauri::command]
+fn get_points_balance(
+    points: tauri::State<'_, PointsManager>,
+    lookup: PointsLookup,
+) -> Result<Option<PointsBalance>, AppError> {
+    points.find(&lookup)
+}
+
+#[tauri::command]
+fn list_points_leaderboard(
+    points: tauri::State<'_, PointsManager>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<PointsBalance>, AppError> {
+    points.top(limit.unwrap_or(50), offset.unwrap_or(0))
+}
+
+#[tauri::command]
+fn get_points_ledger(
+    points: tauri::State<'_, PointsManager>,
+    user_key: String,
+    limit: Option<u32>,
+) -> Result<Vec<PointsTransaction>, AppError> {
+    points.ledger(&user_key, limit.unwrap_or(100))
+}
+
+#[tauri::command]
+fn adjust_points(
+    points: tauri::State<'_, PointsManager>,
+    user_key: String,
+    delta: i64,
+    note: Option<String>,
+) -> Result<PointsBalance, AppError> {
+    points.adjust(&user_key, delta, note)
+}
+
+#[tauri::command]
+async fn get_stats_series(
+    stats: tauri::State<'_, StatsRecorder>,
+    room_id: Option<u64>,
+    start: i64,
+    end: i64,
+) -> Result<Vec<MinuteStats>, AppError> {
+    stats.series(room_id, start, end)
+}
+
+#[tauri::command]
+async fn get_stream_summary(
+    stats: tauri::State<'_, StatsRecorder>,
+    room_id: u64,
+    start: i64,
+    end: Option<i64>,
+) -> Result<StreamSummary, AppError> {
+    stats.summary(room_id, start, end)
+}
+
+#[tauri::command]
+async fn list_sessions(
+    store: tauri::State<'_, EventStore>,
+    room_id: Option<u64>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<Session>, AppError> {
+    store.sessions(room_id, limit.unwrap_or(50), offset.unwrap_or(0))
+}
+
+#[tauri::command]
+async fn get_session_stats(
+    store: tauri::State<'_, EventStore>,
+    id: i64,
+) -> Result<SessionStats, AppError> {
+    store.session_stats(id)
+}
+
+#[tauri::command]
+async fn export_session(
+    store: tauri::State<'_, EventStore>,
+    id: i64,
+    format: ExportFormat,
+    path: String,
+) -> Result<u64, AppError> {
+    store.export_session(id, format, std::path::Path::new(&path))
+}
+
+#[tauri::command]
+fn start_danmaku_recording(
+    recorder: tauri::State<'_, DanmakuRecorder>,
+    options: RecordingOptions,
+) -> Result<RecordingInfo, AppError> {
+    recorder.start(options)
+}
+
+#[tauri::command]
+fn stop_danmaku_recording(
+    recorder: tauri::State<'_, DanmakuRecorder>,
+    id: String,
+) -> Result<RecordingInfo, AppError> {
+    recorder.stop(&id)
+}
+
+#[tauri::command]
+fn list_danmaku_recordings(recorder: tauri::State<'_, DanmakuRecorder>) -> Vec<RecordingInfo> {
+    recorder.list()
+}
+
+#[tauri::command]
+fn replay_session(
+    replay: tauri::State<'_, ReplayManager>,
+    session_id: i64,
+    speed: Option<f64>,
+) -> Result<ReplayStatus, AppError> {
+    replay.replay_session(session_id, speed.unwrap_or(1.0))
+}
+
+#[tauri::command]
+fn stop_replay(replay: tauri::State<'_, ReplayManager>) -> ReplayStatus {
+    replay.stop()
+}
+
+#[tauri::command]
+fn get_replay_status(replay: tauri::State<'_, ReplayManager>) -> ReplayStatus {
+    replay.status()
+}
+
+#[tauri::command]
+fn get_close_behavior(behavior: tauri::State<'_, WindowBehavior>) -> CloseSettings {
+    behavior.get()
+}
+
+#[tauri::command]
+fn set_close_behavior(
+    behavior: tauri::State<'_, WindowBehavior>,
+    close_behavior: CloseBehavior,
+    minimize_to_tray: Option<bool>,
+) -> CloseSettings {
+    let current = behavior.get();
+    behavior.set(CloseSettings {
+        close_behavior,
+        minimize_to_tray: minimize_to_tray.unwrap_or(current.minimize_to_tray),
+    })
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_os::init())
+        .plugin(tauri_plugin_store::Builder::new().build())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(
+            tauri_plugin_log::Builder::new()
+                .target(tauri_plugin_log::Target::new(
+                    tauri_plugin_log::TargetKind::LogDir {
+                        file_name: Some(logs::file_name().to_string()),
+                    },
+                ))
+                .target(tauri_plugin_log::Target::new(
+                    tauri_plugin_log::TargetKind::Webview,
+                ))
+                // 超过大小后轮转为新文件，旧文件由 LogRetention 按保留策略清理
+                .max_file_size(5 * 1024 * 1024 /* bytes */)
+                .rotation_strategy(tauri_plugin_log::RotationStrategy::KeepAll)
+                // 级别由 LogLevels 按模块控制，可以在运行时修改
+                .level(log::LevelFilter::Trace)
+                .filter(logs::enabled)
+                // 每行一条 JSON 记录，包含 target、room_id、event_type 等字段
+                .format(|out, message, record| {
+                    let entry = logs::record(record, message);
+                    out.finish(format_args!(
+                        "{}",
+                        serde_json::to_string(&entry).unwrap_or_default()
+                    ))
+                })
+                .build(),
+        )
+        .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            // 主窗口可能已隐藏到托盘，显示窗口后转发参数
+            deep_link::on_second_instance(app, args, cwd);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            Some(vec![autostart::AUTOSTART_ARG]),
+        ))
+        .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            // rustls 需要进程级的默认加密实现，HTTPS 文件服务器和弹幕连接共用
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            logs::init(app.handle());
+            settings::migrate(app.handle())?;
+            app.manage(LogLevels::new(app.handle())?);
+            let crash_dir = app.path().app_log_dir()?.join("crashes");
+            app.manage(CrashReporter::install(app.handle(), crash_dir)?);
+            app.manage(LogRetention::new(app.handle(), app.path().app_log_dir()?)?);
+            // 代理和 DNS 设置需要在发出任何请求之前加载
+            app.manage(ProxySettings::new(app.handle())?);
+            app.manage(DnsSettings::new(app.handle())?);
+            app.manage(UpdateManager::new(app.handle())?);
+            app.manage(Autostart::new(app.handle())?);
+
+            let cache_dir = app.path().app_cache_dir()?.join("file_server");
+            let log_dir = app.path().app_log_dir()?.join("file_server");
+            let access_log = Arc::new(AccessLog::new(app.handle().clone(), log_dir));
+            let registry = FileServerRegistry::new(app.handle(), cache_dir, access_log)?;
+            app.manage(registry);
+            let data_dir = app.path().app_data_dir()?;
+            let plugins = PluginHost::load(app.handle(), data_dir.join("plugins"))?;
+            let assets = AssetCache::new(app.handle(), app.path().app_cache_dir()?.join("assets"))?;
+            let users = UserInfoCache::new(app.handle(), assets.clone());
+            let rooms = RoomManager::new(app.handle().clone(), plugins.clone(), users.clone())?;
+            assets.prefetch(rooms.subscribe("asset_cache"), rooms.subscribe_states());
+            let store = EventStore::open(&data_dir.join("events.db"))?;
+            store.record(rooms.subscribe_with(
+                "event_store",
+                PERSIST_CAPACITY,
+                DropPolicy::DropOldest,
+            ));
+            let stats = StatsRecorder::open(
+                app.handle(),
+                &data_dir.join("stats.db"),
+                rooms.subscribe("stats"),
+            )?;
+            app.manage(stats);
+            let points = PointsManager::open(
+                app.handle(),
+                &data_dir.join("points.db"),
+                rooms.subscribe("points"),
+            )?;
+            app.manage(points);
+            let room_info = RoomInfoCache::new(app.handle(), rooms.subscribe_states());
+            SessionTracker::start(
+                app.handle(),
+                store.clone(),
+                rooms.subscribe("session"),
+                room_info.subscribe(),
+            )?;
+            app.manage(room_info);
+            app.manage(users);
+            app.manage(assets);
+            app.manage(DanmakuRecorder::new(rooms.subscribe("recorder")));
+            app.manage(ReplayManager::new(app.handle(), store.clone()));
+            app.manage(Revenue::new(app.handle(), store.clone())?);
+            let relay = Relay::new(
+                app.handle(),
+                rooms.subscribe_with("relay", PERSIST_CAPACITY, DropPolicy::DropOldest),
+                store.clone(),
+                data_dir,
+            )?;
+            let ws_server = WsServer::new(app.handle(), rooms.subscribe("ws_server"))?;
+            app.manage(ws_server);
+            app.manage(DeviceOutput::new(app.handle())?);
+            let admin_api = AdminApi::new(app.handle())?;
+            app.manage(admin_api);
+            let obs = ObsClient::new(app.handle(), rooms.subscribe("obs"))?;
+            let live = LiveControl::new(app.handle(), obs.subscribe_streaming())?;
+            app.manage(obs);
+            app.manage(live);
+            let automation = AutomationEngine::new(app.handle(), rooms.subscribe("automation"))?;
+            app.manage(automation);
+            let webhooks = WebhookDispatcher::new(app.handle(), rooms.subscribe("webhook"))?;
+            app.manage(webhooks);
+            let tts = TtsManager::new(app.handle(), rooms.subscribe("tts"))?;
+            app.manage(tts);
+            let sounds = SoundPlayer::new(app.handle(), rooms.subscribe("sound"))?;
+            app.manage(sounds);
+            let songs = SongRequestManager::new(app.handle(), rooms.subscribe("song_request"))?;
+            app.manage(songs);
+            let viewer_queue =
+                ViewerQueueManager::new(app.handle(), rooms.subscribe("viewer_queue"))?;
+            app.manage(viewer_queue);
+            let polls = PollManager::new(app.handle(), rooms.subscribe("poll"))?;
+            app.manage(polls);
+            let raffles = RaffleManager::new(app.handle(), rooms.subscribe("raffle"))?;
+            app.manage(raffles);
+            let notifications = NotificationManager::new(
+                app.handle(),
+                rooms.subscribe("notifications"),
+                rooms.subscribe_states(),
+            )?;
+            app.manage(notifications);
+            app.manage(Metrics::new(
+                rooms.subscribe("metrics"),
+                rooms.subscribe_states(),
+            ));
+            let auto_thank = AutoThank::new(app.handle(), rooms.subscribe("auto_thank"))?;
+            app.manage(auto_thank);
+            app.manage(rooms);
+            app.manage(DanmakuSender::new());
+            let credentials = CredentialManager::new(app.handle())?;
+            credentials.start_monitor();
+            app.manage(credentials);
+            app.manage(store);
+            app.manage(relay);
+            app.manage(plugins);
+            app.manage(WindowBehavior::new(app.handle())?);
+            app.manage(SystemMonitor::new());
+            app.manage(ProcessWatcher::new(app.handle())?);
+            app.manage(NetworkDiagnostics::new(app.handle()));
+            app.manage(Watchdog::new(app.handle()));
+            app.manage(DemoMode::new(app.handle())?);
+            app.manage(KeepAwake::new(app.handle())?);
+            app.manage(Scheduler::new(app.handle())?);
+            tray::create(app.handle())?;
+            app.manage(Hotkeys::new(app.handle())?);
+            app.manage(Profiles::new(app.handle())?);
+            app.state::<Autostart>().after_delay(|app| {
+                app.state::<FileServerRegistry>().auto_start();
+                app.state::<WsServer>().auto_start();
+                app.state::<DeviceOutput>().auto_start();
+                app.state::<AdminApi>().auto_start();
+                app.state::<ObsClient>().auto_connect();
+            });
+            autostart::show_main_window(app.handle());
+            deep_link::init(app.handle());
+            Ok(())
+        })
+        .on_window_event(tray::on_window_event)
         .invoke_handler(tauri::generate_handler![
             get_memory_info,
             quit_app,
             open_dev_tools,
+            create_file_server,
+            list_file_servers,
+            delete_file_server,
             start_file_server,
             stop_file_server,
             update_file_server_config,
-            get_file_server_status
+            update_file_server_ip_filter,
+            get_file_server_status,
+            get_file_server_logs,
+            add_room,
+            send_danmaku,
+            remove_room,
+            list_rooms,
+            get_event_bus_metrics,
+            get_rooms_status,
+            get_filter_rules,
+            set_filter_rules,
+            reset_filter_hits,
+            get_moderation_rules,
+            set_moderation_rules,
+            promote_moderation_rule,
+            list_flagged_events,
+            review_flagged_event,
+            clear_flagged_events,
+            get_relay_config,
+            update_relay_config,
+            get_relay_status,
+            get_relay_backlog,
+            reconcile_relay,
+            clear_relay_queue,
+            query_events,
+            get_event_stats,
+            get_leaderboards,
+            get_revenue_config,
+            update_revenue_config,
+            get_revenue_report,
+            export_revenue_csv,
+            export_events,
+            list_credentials,
+            add_credential,
+            validate_credential,
+            remove_credential,
+            start_qr_login,
+            cancel_qr_login,
+            list_plugins,
+            install_plugin,
+            uninstall_plugin,
+            enable_plugin,
+            disable_plugin,
+            get_ws_server_status,
+            update_ws_server_config,
+            start_ws_server,
+            stop_ws_server,
+            get_device_output_status,
+            update_device_output_config,
+            start_signal_server,
+            stop_signal_server,
+            list_signal_clients,
+            send_test_signal,
+            list_midi_ports,
+            send_midi_message,
+            reset_midi_connections,
+            get_admin_api_status,
+            update_admin_api_config,
+            regenerate_admin_api_token,
+            get_hotkeys,
+            update_hotkeys,
+            get_settings,
+            update_settings,
+            export_config,
+            import_config,
+            list_profiles,
+            save_profile,
+            apply_profile,
+            delete_profile,
+            run_setup_checks,
+            get_autostart_status,
+            set_autostart,
+            get_notification_config,
+            update_notification_config,
+            send_test_notification,
+            get_room_info,
+            list_room_info,
+            refresh_room_info,
+            get_api_budgets,
+            get_user_info,
+            get_user_avatar,
+            clear_user_info_cache,
+            get_asset_cache_config,
+            update_asset_cache_config,
+            get_asset_cache_stats,
+            purge_asset_cache,
+            get_live_control_config,
+            update_live_control_config,
+            start_live,
+            stop_live,
+            update_live_room,
+            list_live_areas,
+            get_auto_thank_config,
+            update_auto_thank_config,
+            get_auto_thank_logs,
+            list_scheduled_tasks,
+            save_scheduled_task,
+            delete_scheduled_task,
+            run_scheduled_task,
+            get_keep_awake_config,
+            update_keep_awake_config,
+            get_keep_awake_status,
+            get_obs_config,
+            update_obs_config,
+            get_obs_status,
+            connect_obs,
+            disconnect_obs,
+            get_obs_scenes,
+            switch_obs_scene,
+            set_obs_source_visibility,
+            start_obs_recording,
+            stop_obs_recording,
+            list_automation_rules,
+            save_automation_rule,
+            delete_automation_rule,
+            get_automation_logs,
+            clear_automation_logs,
+            list_webhooks,
+            save_webhook,
+            delete_webhook,
+            get_webhook_deliveries,
+            get_tts_config,
+            update_tts_config,
+            get_tts_status,
+            pause_tts,
+            resume_tts,
+            skip_tts,
+            clear_tts_queue,
+            speak_text,
+            list_audio_devices,
+            get_sound_config,
+            update_sound_config,
+            play_sound,
+            stop_sounds,
+            get_song_request_config,
+            update_song_request_config,
+            list_song_requests,
+            move_song_request,
+            mark_song_played,
+            remove_song_request,
+            clear_song_requests,
+            list_song_blacklist,
+            add_song_blacklist,
+            remove_song_blacklist,
+            get_viewer_queue_config,
+            update_viewer_queue_config,
+            get_viewer_queue,
+            pop_viewer_queue,
+            skip_viewer_queue_entry,
+            move_viewer_queue_entry,
+            remove_viewer_queue_entry,
+            clear_viewer_queue,
+            start_poll,
+            end_poll,
+            get_current_poll,
+            list_poll_history,
+            clear_poll_history,
+            get_raffle_config,
+            update_raffle_config,
+            start_raffle,
+            draw_raffle,
+            cancel_raffle,
+            get_raffle_status,
+            list_raffle_history,
+            get_points_config,
+            update_points_config,
+            get_points_balance,
+            list_points_leaderboard,
+            get_points_ledger,
+            adjust_points,
+            get_stats_series,
+            get_stream_summary,
+            list_sessions,
+            get_session_stats,
+            export_session,
+            start_danmaku_recording,
+            stop_danmaku_recording,
+            list_danmaku_recordings,
+            replay_session,
+            stop_replay,
+            get_replay_status,
+            get_close_behavior,
+            set_close_behavior,
+            get_cpu_info,
+            get_disk_info,
+            get_network_info,
+            get_uptime,
+            get_gpu_info,
+            start_metrics_stream,
+            stop_metrics_stream,
+            get_processes,
+            get_process_watch_config,
+            update_process_watch_config,
+            get_watched_processes,
+            run_network_check,
+            get_watchdog_recoveries,
+            emit_test_event,
+            get_demo_status,
+            update_demo_config,
+            start_demo,
+            stop_demo,
+            get_proxy_config,
+            update_proxy_config,
+            test_proxy,
+            check_update,
+            install_update,
+            get_dns_config,
+            update_dns_config,
+            resolve_host,
+            get_crash_reports,
+            clear_crash_reports,
+            get_crash_config,
+            update_crash_config,
+            upload_crash_reports,
+            query_logs,
+            get_log_retention,
+            update_log_retention,
+            export_logs_zip,
+            get_log_levels,
+            set_log_level,
+            get_pending_update,
+            get_update_config,
+            set_update_config
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,322 @@
+use crate::bilibili::{self, cookie_value, get_api, post_api};
+use crate::credentials::CredentialManager;
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_http::reqwest::Client;
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存开播设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "live_control.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+const START_LIVE_URL: &str = "https://api.live.bilibili.com/room/v1/Room/startLive";
+const STOP_LIVE_URL: &str = "https://api.live.bilibili.com/room/v1/Room/stopLive";
+const UPDATE_ROOM_URL: &str = "https://api.live.bilibili.com/room/v1/Room/update";
+const AREA_LIST_URL: &str = "https://api.live.bilibili.com/room/v1/Area/getList";
+// 根据用户 uid 查询其直播间
+const USER_ROOM_URL: &str = "https://api.live.bilibili.com/room/v1/Room/getRoomInfoOld";
+
+// 直播 PC 端的开播平台，其他平台的推流地址不能用于 OBS
+const PLATFORM: &str = "pc_link";
+
+// 开播或下播成功时发送给前端的事件
+pub const STATE_EVENT: &str = "live-control://state";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LiveControlConfig {
+    // 开播使用的账号，为空时使用第一个 B 站账号
+    pub credential_id: Option<String>,
+    // 为空时使用账号自己的直播间
+    pub room_id: Option<u64>,
+    // 开播时的分区，为空时使用直播间当前的分区
+    pub area_id: Option<u32>,
+    // OBS 开始推流时自动开播
+    pub start_with_obs: bool,
+    // OBS 停止推流时自动下播
+    pub stop_with_obs: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveStarted {
+    pub room_id: u64,
+    // 推流服务器和推流码，OBS 使用其他推流码时可以忽略
+    pub rtmp_addr: String,
+    pub rtmp_code: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct LiveStateChanged {
+    room_id: u64,
+    live: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveArea {
+    pub id: u32,
+    pub name: String,
+    pub parent_id: u32,
+    pub parent_name: String,
+}
+
+struct Shared {
+    app: AppHandle,
+    http: Client,
+    config: RwLock<LiveControlConfig>,
+}
+
+impl Shared {
+    // 返回使用的账号 cookie 和直播间号，参数为空时使用设置中的值
+    async fn target(
+        &self,
+        room_id: Option<u64>,
+        credential_id: Option<String>,
+    ) -> AppResult<(String, u64)> {
+        let config = self.config.read().unwrap().clone();
+        let credentials = self.app.state::<CredentialManager>();
+        let id = credential_id
+            .or(config.credential_id)
+            .or_else(|| credentials.default_cookie_id())
+            .ok_or_else(|| AppError::Credential("没有可用于开播的 B 站账号".to_string()))?;
+        let cookie = credentials.cookie(&id)?;
+        let room_id = match room_id.or(config.room_id) {
+            Some(room_id) => room_id,
+            None => self.user_room(&cookie).await?,
+        };
+        Ok((cookie, room_id))
+    }
+
+    async fn user_room(&self, cookie: &str) -> AppResult<u64> {
+        let uid = cookie_value(cookie, "DedeUserID")
+            .ok_or_else(|| AppError::Credential("账号 cookie 中缺少 DedeUserID".to_string()))?;
+        let data = get_api(&self.http, &format!("{}?mid={}", USER_ROOM_URL, uid), None).await?;
+        data["roomid"]
+            .as_u64()
+            .filter(|room_id| *room_id > 0)
+            .ok_or_else(|| AppError::LiveControl("该账号还没有开通直播间".to_string()))
+    }
+
+    async fn start(
+        &self,
+        room_id: Option<u64>,
+        area_id: Option<u32>,
+        credential_id: Option<String>,
+    ) -> AppResult<LiveStarted> {
+        let (cookie, room_id) = self.target(room_id, credential_id).await?;
+        let area_id = area_id.or(self.config.read().unwrap().area_id);
+        let room = room_id.to_string();
+        let area = area_id.map(|area_id| area_id.to_string());
+        let mut form = vec![("room_id", room.as_str()), ("platform", PLATFORM)];
+        if let Some(area) = area.as_deref() {
+            form.push(("area_v2", area));
+        }
+        let data = post_api(&self.http, START_LIVE_URL, &cookie, &form).await?;
+        log::info!(room_id; "直播间 {} 已开播", room_id);
+        let _ = self.app.emit(
+            STATE_EVENT,
+            LiveStateChanged {
+                room_id,
+                live: true,
+            },
+        );
+        Ok(LiveStarted {
+            room_id,
+            rtmp_addr: data["rtmp"]["addr"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            rtmp_code: data["rtmp"]["code"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+
+    async fn stop(&self, room_id: Option<u64>, credential_id: Option<String>) -> AppResult<u64> {
+        let (cookie, room_id) = self.target(room_id, credential_id).await?;
+        let room = room_id.to_string();
+        post_api(
+            &self.http,
+            STOP_LIVE_URL,
+            &cookie,
+            &[("room_id", room.as_str()), ("platform", PLATFORM)],
+        )
+        .await?;
+        log::info!(room_id; "直播间 {} 已下播", room_id);
+        let _ = self.app.emit(
+            STATE_EVENT,
+            LiveStateChanged {
+                room_id,
+                live: false,
+            },
+        );
+        Ok(room_id)
+    }
+
+    async fn update_room(
+        &self,
+        room_id: Option<u64>,
+        title: Option<String>,
+        area_id: Option<u32>,
+        credential_id: Option<String>,
+    ) -> AppResult<()> {
+        let title = title.map(|title| title.trim().to_string());
+        if title.as_deref().is_some_and(str::is_empty) {
+            return Err(AppError::InvalidConfig("直播间标题不能为空".to_string()));
+        }
+        if title.is_none() && area_id.is_none() {
+            return Ok(());
+        }
+        let (cookie, room_id) = self.target(room_id, credential_id).await?;
+        let room = room_id.to_string();
+        let area = area_id.map(|area_id| area_id.to_string());
+        let mut form = vec![("room_id", room.as_str())];
+        if let Some(title) = title.as_deref() {
+            form.push(("title", title));
+        }
+        if let Some(area) = area.as_deref() {
+            form.push(("area_id", area));
+        }
+        post_api(&self.http, UPDATE_ROOM_URL, &cookie, &form).await?;
+        log::info!(room_id; "已修改直播间 {} 的信息", room_id);
+        Ok(())
+    }
+}
+
+// 通过 B 站接口开播、下播和修改直播间标题与分区，可以跟随 OBS 的推流状态自动开播
+pub struct LiveControl {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl LiveControl {
+    pub fn new(
+        app: &AppHandle,
+        obs_streaming: broadcast::Receiver<bool>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            http: bilibili::client(),
+            config: RwLock::new(config),
+        });
+        tauri::async_runtime::spawn(follow_obs(shared.clone(), obs_streaming));
+        Ok(LiveControl { shared, store })
+    }
+
+    pub fn get_config(&self) -> LiveControlConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: LiveControlConfig) -> AppResult<LiveControlConfig> {
+        if config.room_id == Some(0) {
+            return Err(AppError::InvalidConfig("直播间号无效".to_string()));
+        }
+        *self.shared.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存开播设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化开播设置失败: {}", err),
+        }
+        Ok(config)
+    }
+
+    pub async fn start(
+        &self,
+        room_id: Option<u64>,
+        area_id: Option<u32>,
+        credential_id: Option<String>,
+    ) -> AppResult<LiveStarted> {
+        self.shared.start(room_id, area_id, credential_id).await
+    }
+
+    // 返回下播的直播间号
+    pub async fn stop(
+        &self,
+        room_id: Option<u64>,
+        credential_id: Option<String>,
+    ) -> AppResult<u64> {
+        self.shared.stop(room_id, credential_id).await
+    }
+
+    // 修改直播间标题和分区，为空的部分保持不变
+    pub async fn update_room(
+        &self,
+        room_id: Option<u64>,
+        title: Option<String>,
+        area_id: Option<u32>,
+        credential_id: Option<String>,
+    ) -> AppResult<()> {
+        self.shared
+            .update_room(room_id, title, area_id, credential_id)
+            .await
+    }
+
+    // 所有直播分区，按父分区分组排列
+    pub async fn areas(&self) -> AppResult<Vec<LiveArea>> {
+        let data = get_api(&self.shared.http, AREA_LIST_URL, None).await?;
+        let areas = data
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|parent| {
+                let parent_id = number(&parent["id"]).unwrap_or_default();
+                let parent_name = parent["name"].as_str().unwrap_or_default().to_string();
+                parent["list"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(move |area| {
+                        Some(LiveArea {
+                            id: number(&area["id"])?,
+                            name: area["name"].as_str()?.to_string(),
+                            parent_id,
+                            parent_name: parent_name.clone(),
+                        })
+                    })
+            })
+            .collect();
+        Ok(areas)
+    }
+}
+
+// 分区列表中的编号有时是字符串
+fn number(value: &Value) -> Option<u32> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str()?.parse().ok())
+        .and_then(|number| u32::try_from(number).ok())
+}
+
+// 按设置跟随 OBS 的推流状态开播和下播，失败时只记录日志
+async fn follow_obs(shared: Arc<Shared>, mut obs_streaming: broadcast::Receiver<bool>) {
+    loop {
+        let streaming = match obs_streaming.recv().await {
+            Ok(streaming) => streaming,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let config = shared.config.read().unwrap().clone();
+        if streaming && config.start_with_obs {
+            if let Err(err) = shared.start(None, None, None).await {
+                log::warn!("OBS 开始推流后自动开播失败: {}", err);
+            }
+        } else if !streaming && config.stop_with_obs {
+            if let Err(err) = shared.stop(None, None).await {
+                log::warn!("OBS 停止推流后自动下播失败: {}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt::Arguments;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
+
+mod archive;
+mod level;
+pub(crate) mod retention;
+
+pub use archive::export_zip;
+pub use level::{enabled, LevelConfig, LogLevels};
+pub use retention::{LogRetention, LogRetentionConfig};
+
+// 每条日志发送给前端的事件
+pub const LINE_EVENT: &str = "log://line";
+
+// 内存中保留的日志条数
+const MAX_ENTRIES: usize = 5000;
+const DEFAULT_QUERY_LIMIT: usize = 200;
+
+static BUFFER: Mutex<LogBuffer> = Mutex::new(LogBuffer {
+    entries: VecDeque::new(),
+    next_id: 1,
+});
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static FILE_NAME: OnceLock<String> = OnceLock::new();
+
+// 日志文件名的前缀，轮转后的文件也以它开头
+const FILE_PREFIX: &str = "logs";
+
+thread_local! {
+    // 发送事件时可能再次产生日志，避免递归
+    static EMITTING: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    // 递增的序号，前端据此去重和续读
+    pub id: u64,
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    pub level: String,
+    // 产生日志的模块
+    pub target: String,
+    pub message: String,
+    // 以下为日志中附带的结构化字段，例如 log::info!(room_id, event_type = "connected"; "...")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>,
+}
+
+// 查询条件，未设置的条件不参与过滤
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LogQuery {
+    // 最低级别，例如 warn 返回 warn 和 error
+    pub level: Option<String>,
+    // 模块名前缀，不含本程序的 crate 名，例如 danmaku
+    pub module: Option<String>,
+    // Unix 毫秒时间戳，只返回之后的日志
+    pub since: Option<i64>,
+    pub room_id: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+// 日志按时间顺序追加，时间戳和序号都是递增的，可以二分查找
+struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    next_id: u64,
+}
+
+// 当前写入的日志文件名（不含扩展名），按启动当天的日期命名，超过大小后由日志插件轮转
+pub fn file_name() -> &'static str {
+    FILE_NAME.get_or_init(|| {
+        format!(
+            "{}-{}",
+            FILE_PREFIX,
+            chrono::Local::now().format("%Y-%m-%d")
+        )
+    })
+}
+
+// 前端订阅实时日志需要 AppHandle，在 setup 中设置
+pub fn init(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+}
+
+// 由日志插件的格式化回调调用，每条日志调用一次，返回的记录以 JSON 写入日志文件
+pub fn record(record: &log::Record, message: &Arguments) -> LogEntry {
+    let entry = {
+        let mut buffer = BUFFER.lock().unwrap();
+        let mut entry = LogEntry {
+            id: buffer.next_id,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            level: record.level().as_str().to_lowercase(),
+            target: record.target().to_string(),
+            message: message.to_string(),
+            room_id: None,
+            event_type: None,
+        };
+        let _ = record.key_values().visit(&mut FieldVisitor(&mut entry));
+        buffer.next_id += 1;
+        if buffer.entries.len() >= MAX_ENTRIES {
+            buffer.entries.pop_front();
+        }
+        buffer.entries.push_back(entry.clone());
+        entry
+    };
+
+    let Some(app) = APP.get() else {
+        return entry;
+    };
+    if EMITTING.with(|emitting| emitting.replace(true)) {
+        return entry;
+    }
+    let _ = app.emit(LINE_EVENT, &entry);
+    EMITTING.with(|emitting| emitting.set(false));
+    entry
+}
+
+// 提取已知的结构化字段，其他字段忽略
+struct FieldVisitor<'a>(&'a mut LogEntry);
+
+impl<'kvs> log::kv::VisitSource<'kvs> for FieldVisitor<'_> {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        match key.as_str() {
+            "room_id" => self.0.room_id = value.to_u64(),
+            "event_type" => self.0.event_type = Some(value.to_string()),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+// 按条件查询，返回最新的 limit 条，按时间从旧到新排列
+pub fn query(query: &LogQuery) -> Vec<LogEntry> {
+    let min_level = query
+        .level
+        .as_deref()
+        .and_then(|level| level.parse::<log::Level>().ok());
+    let module = query.module.as_deref().filter(|module| !module.is_empty());
+    let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_ENTRIES);
+
+    let buffer = BUFFER.lock().unwrap();
+    let start = match query.since {
+        Some(since) => buffer
+            .entries
+            .partition_point(|entry| entry.timestamp <= since),
+        None => 0,
+    };
+    let mut entries: Vec<LogEntry> = buffer
+        .entries
+        .range(start..)
+        .rev()
+        .filter(|entry| {
+            // log::Level 中越严重的级别越小
+            min_level.is_none_or(|min| {
+                entry
+                    .level
+                    .parse::<log::Level>()
+                    .is_ok_and(|level| level <= min)
+            })
+        })
+        .filter(|entry| {
+            module.is_none_or(|module| level::module_of(&entry.target).starts_with(module))
+        })
+        .filter(|entry| {
+            query
+                .room_id
+                .is_none_or(|room_id| entry.room_id == Some(room_id))
+        })
+        .take(limit)
+        .cloned()
+        .collect();
+    entries.reverse();
+    entries
+}
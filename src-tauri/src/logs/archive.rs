@@ -0,0 +1,69 @@
+use crate::error::{AppError, AppResult};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const DAY: Duration = Duration::from_secs(24 * 3600);
+
+// 把日志目录中最近 days 天修改过的文件打包为 ZIP，包括运行日志、访问日志和崩溃报告
+// 返回打包的文件数量
+pub async fn export_zip(log_dir: PathBuf, path: PathBuf, days: u32) -> AppResult<usize> {
+    tokio::task::spawn_blocking(move || write_zip(&log_dir, &path, days))
+        .await
+        .map_err(|err| AppError::Io(io::Error::other(err)))?
+}
+
+fn write_zip(log_dir: &Path, path: &Path, days: u32) -> AppResult<usize> {
+    if !log_dir.is_dir() {
+        return Err(AppError::FolderNotFound(log_dir.display().to_string()));
+    }
+    let since = SystemTime::now() - DAY * days;
+    let mut zip = ZipWriter::new(fs::File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut count = 0;
+    let mut pending = vec![log_dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)?.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+            let recent = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified >= since);
+            let Ok(name) = path.strip_prefix(log_dir) else {
+                continue;
+            };
+            if !recent {
+                continue;
+            }
+            // 正在写入的日志可能被占用，读取失败时跳过
+            let mut file = match fs::File::open(&path) {
+                Ok(file) => file,
+                Err(_) => continue,
+            };
+            zip.start_file(name.to_string_lossy().replace('\\', "/"), options)
+                .map_err(zip_error)?;
+            io::copy(&mut file, &mut zip)?;
+            count += 1;
+        }
+    }
+    zip.finish().map_err(zip_error)?;
+    Ok(count)
+}
+
+fn zip_error(err: zip::result::ZipError) -> AppError {
+    AppError::Io(io::Error::other(err))
+}
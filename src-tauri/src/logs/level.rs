@@ -0,0 +1,137 @@
+use crate::error::{AppError, AppResult};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 与保留策略共用设置文件
+const STORE_FILE: &str = "logging.json";
+const LEVELS_KEY: &str = "levels";
+
+// 没有单独设置的模块使用的级别
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+// 设置默认级别时使用的模块名
+const DEFAULT_MODULE: &str = "*";
+
+// 当前生效的级别，日志插件过滤每条日志时读取
+static CURRENT: RwLock<Option<LevelConfig>> = RwLock::new(None);
+
+// 模块名到级别的映射，模块名为本程序的模块路径（如 danmaku::direct）或依赖库的名称（如 hyper）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelConfig {
+    pub default: Option<String>,
+    pub modules: BTreeMap<String, String>,
+}
+
+impl LevelConfig {
+    fn default_level(&self) -> LevelFilter {
+        self.default
+            .as_deref()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(DEFAULT_LEVEL)
+    }
+
+    // 按最长的模块前缀匹配
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let target = module_of(target);
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module.as_str()
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .and_then(|(_, level)| level.parse().ok())
+            .unwrap_or_else(|| self.default_level())
+    }
+}
+
+// 去掉本程序的 crate 名前缀，依赖库的 target 保持不变
+pub(super) fn module_of(target: &str) -> &str {
+    target
+        .strip_prefix(concat!(env!("CARGO_CRATE_NAME"), "::"))
+        .unwrap_or(target)
+}
+
+// 由日志插件的过滤回调调用
+pub fn enabled(metadata: &log::Metadata) -> bool {
+    let current = CURRENT.read().unwrap();
+    let level = match current.as_ref() {
+        Some(config) => config.level_for(metadata.target()),
+        None => DEFAULT_LEVEL,
+    };
+    metadata.level() <= level
+}
+
+// 运行时调整各模块的日志级别，无需重新编译即可为单个模块开启详细日志
+pub struct LogLevels {
+    config: RwLock<LevelConfig>,
+    store: Arc<Store<Wry>>,
+}
+
+impl LogLevels {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: LevelConfig = store
+            .get(LEVELS_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        *CURRENT.write().unwrap() = Some(config.clone());
+        Ok(LogLevels {
+            config: RwLock::new(config),
+            store,
+        })
+    }
+
+    pub fn get_config(&self) -> LevelConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    // level 为空时取消该模块的单独设置，module 为 * 时设置默认级别
+    pub fn set_level(&self, module: &str, level: Option<&str>) -> AppResult<LevelConfig> {
+        let module = module.trim().trim_end_matches("::");
+        if module.is_empty() {
+            return Err(AppError::InvalidConfig("模块名不能为空".to_string()));
+        }
+        let level = match level.map(str::trim).filter(|level| !level.is_empty()) {
+            Some(level) => Some(
+                level
+                    .parse::<LevelFilter>()
+                    .map_err(|_| AppError::InvalidConfig(format!("无效的日志级别: {}", level)))?
+                    .as_str()
+                    .to_lowercase(),
+            ),
+            None => None,
+        };
+
+        let config = {
+            let mut config = self.config.write().unwrap();
+            match (module, level) {
+                (DEFAULT_MODULE, level) => config.default = level,
+                (module, Some(level)) => {
+                    config.modules.insert(module.to_string(), level);
+                }
+                (module, None) => {
+                    config.modules.remove(module);
+                }
+            }
+            config.clone()
+        };
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(LEVELS_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存日志级别失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化日志级别失败: {}", err),
+        }
+        *CURRENT.write().unwrap() = Some(config.clone());
+        Ok(config)
+    }
+}
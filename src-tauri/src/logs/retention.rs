@@ -0,0 +1,147 @@
+use super::FILE_PREFIX;
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存日志保留设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "logging.json";
+pub(crate) const CONFIG_KEY: &str = "retention";
+
+// 检查并清理旧日志的间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+const DAY: Duration = Duration::from_secs(24 * 3600);
+const MB: u64 = 1024 * 1024;
+
+// 日志保留策略，为 0 时不限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogRetentionConfig {
+    pub retention_days: u32,
+    // 所有日志文件的总大小上限，单位为 MB
+    pub max_total_mb: u64,
+}
+
+impl Default for LogRetentionConfig {
+    fn default() -> Self {
+        LogRetentionConfig {
+            retention_days: 14,
+            max_total_mb: 200,
+        }
+    }
+}
+
+// 按保留策略定期删除轮转后的旧日志，正在写入的日志文件不会删除
+pub struct LogRetention {
+    dir: PathBuf,
+    config: Arc<RwLock<LogRetentionConfig>>,
+    store: Arc<Store<Wry>>,
+}
+
+impl LogRetention {
+    pub fn new(app: &AppHandle, dir: PathBuf) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: LogRetentionConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let config = Arc::new(RwLock::new(config));
+
+        let task_dir = dir.clone();
+        let task_config = config.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let config = task_config.read().unwrap().clone();
+                let dir = task_dir.clone();
+                let _ = tokio::task::spawn_blocking(move || cleanup(&dir, &config)).await;
+            }
+        });
+        Ok(LogRetention { dir, config, store })
+    }
+
+    pub fn get_config(&self) -> LogRetentionConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    // 保存后立即按新的策略清理一次
+    pub fn update_config(&self, config: LogRetentionConfig) -> AppResult<LogRetentionConfig> {
+        if config.max_total_mb != 0 && config.max_total_mb < 10 {
+            return Err(AppError::InvalidConfig(
+                "日志总大小上限不能小于 10 MB".to_string(),
+            ));
+        }
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存日志保留设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化日志保留设置失败: {}", err),
+        }
+        *self.config.write().unwrap() = config.clone();
+        cleanup(&self.dir, &config);
+        Ok(config)
+    }
+
+    // 立即按当前策略清理一次，用于计划任务
+    pub async fn cleanup_now(&self) {
+        let config = self.get_config();
+        let dir = self.dir.clone();
+        let _ = tokio::task::spawn_blocking(move || cleanup(&dir, &config)).await;
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+fn cleanup(dir: &std::path::Path, config: &LogRetentionConfig) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let active = format!("{}.log", super::file_name());
+    let mut files: Vec<LogFile> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with(FILE_PREFIX) && name.ends_with(".log") && name != active
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(LogFile {
+                path: entry.path(),
+                modified: metadata.modified().ok()?,
+                size: metadata.len(),
+            })
+        })
+        .collect();
+    // 从新到旧排列
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+
+    let now = SystemTime::now();
+    let max_age = DAY * config.retention_days;
+    let mut total = std::fs::metadata(dir.join(&active))
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    for file in files {
+        total += file.size;
+        let expired = config.retention_days != 0
+            && now
+                .duration_since(file.modified)
+                .is_ok_and(|age| age > max_age);
+        let oversized = config.max_total_mb != 0 && total > config.max_total_mb * MB;
+        if expired || oversized {
+            if let Err(err) = std::fs::remove_file(&file.path) {
+                log::warn!("删除旧日志 {} 失败: {}", file.path.display(), err);
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::danmaku::{ConnectionState, ConnectionStateChanged, DanmakuEvent, RoomManager};
+use crate::event_bus::Subscriber;
+use crate::file_server::FileServerRegistry;
+use crate::relay::Relay;
+use crate::system_info::SystemMonitor;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use tokio::sync::broadcast;
+
+// 所有指标名称的前缀
+const PREFIX: &str = "vtsuru";
+
+#[derive(Default)]
+struct Counters {
+    // 按事件类型统计收到的事件数量，不含回放和模拟事件
+    events: BTreeMap<&'static str, u64>,
+    // 按直播间统计断线重连的次数
+    reconnects: BTreeMap<u64, u64>,
+}
+
+// 统计运行指标，以 Prometheus 文本格式输出，供文件服务器的 /api/metrics 接口使用
+pub struct Metrics {
+    counters: Arc<Mutex<Counters>>,
+    started_at: Instant,
+}
+
+impl Metrics {
+    pub fn new(
+        events: Subscriber<DanmakuEvent>,
+        states: broadcast::Receiver<ConnectionStateChanged>,
+    ) -> Self {
+        let counters = Arc::new(Mutex::new(Counters::default()));
+        tauri::async_runtime::spawn(run(counters.clone(), events, states));
+        Metrics {
+            counters,
+            started_at: Instant::now(),
+        }
+    }
+
+    // 汇总各模块的状态，返回 Prometheus 文本格式
+    pub fn render(&self, app: &AppHandle) -> String {
+        let mut out = Writer::default();
+
+        out.header("uptime_seconds", "gauge", "应用运行时间");
+        out.sample("uptime_seconds", &[], self.started_at.elapsed().as_secs());
+
+        {
+            let counters = self.counters.lock().unwrap();
+            out.header("events_received_total", "counter", "收到的直播间事件数量");
+            for (kind, count) in &counters.events {
+                out.sample("events_received_total", &[("kind", kind)], *count);
+            }
+            out.header("reconnects_total", "counter", "直播间断线重连的次数");
+            for (room_id, count) in &counters.reconnects {
+                out.sample(
+                    "reconnects_total",
+                    &[("room_id", &room_id.to_string())],
+                    *count,
+                );
+            }
+        }
+
+        if let Some(rooms) = app.try_state::<RoomManager>() {
+            let bus = rooms.bus_metrics();
+            out.header("event_bus_published_total", "counter", "分发的事件数量");
+            out.sample("event_bus_published_total", &[], bus.published);
+            out.header("event_bus_queue_depth", "gauge", "订阅者积压的事件数量");
+            for subscriber in &bus.subscribers {
+                out.sample(
+                    "event_bus_queue_depth",
+                    &[("subscriber", &subscriber.name)],
+                    subscriber.depth,
+                );
+            }
+            out.header(
+                "event_bus_dropped_total",
+                "counter",
+                "订阅者因积压丢弃的事件数量",
+            );
+            for subscriber in &bus.subscribers {
+                out.sample(
+                    "event_bus_dropped_total",
+                    &[("subscriber", &subscriber.name)],
+                    subscriber.dropped,
+                );
+            }
+        }
+
+        if let Some(relay) = app.try_state::<Relay>() {
+            let status = relay.status();
+            out.header("upload_queue_depth", "gauge", "等待上传的事件数量");
+            out.sample("upload_queue_depth", &[], status.queue_depth);
+            out.header("upload_queue_bytes", "gauge", "离线队列占用的磁盘空间");
+            out.sample("upload_queue_bytes", &[], status.queue_bytes);
+            out.header(
+                "upload_queue_dropped_total",
+                "counter",
+                "离线队列已满时丢弃的事件数量",
+            );
+            out.sample("upload_queue_dropped_total", &[], status.dropped);
+        }
+
+        if let Some(registry) = app.try_state::<FileServerRegistry>() {
+            out.header(
+                "file_server_requests_total",
+                "counter",
+                "文件服务器处理的请求数量",
+            );
+            for (server, status, count) in registry.request_counts() {
+                out.sample(
+                    "file_server_requests_total",
+                    &[("server", &server), ("status", &status.to_string())],
+                    count,
+                );
+            }
+        }
+
+        if let Some(memory) = app
+            .try_state::<SystemMonitor>()
+            .and_then(|monitor| monitor.process_memory())
+        {
+            out.header(
+                "process_resident_memory_bytes",
+                "gauge",
+                "进程占用的物理内存",
+            );
+            out.sample("process_resident_memory_bytes", &[], memory);
+        }
+
+        out.text
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    text: String,
+}
+
+impl Writer {
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP {}_{} {}", PREFIX, name, help);
+        let _ = writeln!(self.text, "# TYPE {}_{} {}", PREFIX, name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+        let _ = write!(self.text, "{}_{}", PREFIX, name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+                .collect();
+            let _ = write!(self.text, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.text, " {}", value);
+    }
+}
+
+// 标签值中的反斜杠、双引号和换行需要转义
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+async fn run(
+    counters: Arc<Mutex<Counters>>,
+    mut events: Subscriber<DanmakuEvent>,
+    mut states: broadcast::Receiver<ConnectionStateChanged>,
+) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if !event.replay && !event.simulated => {
+                    *counters.lock().unwrap().events.entry(event.kind.as_str()).or_default() += 1;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("运行指标统计不及时，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            change = states.recv() => match change {
+                // 只在开始重连时计数，每次重试不重复计数
+                Ok(change)
+                    if change.state == ConnectionState::Reconnecting
+                        && change.previous != ConnectionState::Reconnecting =>
+                {
+                    *counters.lock().unwrap().reconnects.entry(change.room_id).or_default() += 1;
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::danmaku::{ConnectionState, ConnectionStateChanged, DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存通知规则的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "notifications.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+const MAX_COOLDOWN_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventRule {
+    pub enabled: bool,
+    // 金额不低于该值时才通知，单位为元
+    pub min_price: f64,
+}
+
+impl EventRule {
+    fn new(enabled: bool, min_price: f64) -> Self {
+        EventRule { enabled, min_price }
+    }
+
+    fn matches(&self, price: f64) -> bool {
+        self.enabled && price >= self.min_price
+    }
+}
+
+impl Default for EventRule {
+    fn default() -> Self {
+        EventRule::new(true, 0.0)
+    }
+}
+
+// 免打扰时段，格式为 HH:MM，结束时间早于开始时间表示跨过午夜
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: String,
+    pub end: String,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        QuietHours {
+            enabled: false,
+            start: "23:00".to_string(),
+            end: "08:00".to_string(),
+        }
+    }
+}
+
+impl QuietHours {
+    fn contains(&self, now: NaiveTime) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let (Ok(start), Ok(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    pub super_chat: EventRule,
+    pub guard: EventRule,
+    pub gift: EventRule,
+    // 直播间连接断开、开始重连时通知
+    pub connection_lost: bool,
+    pub quiet_hours: QuietHours,
+    // 相同内容在该时间内只通知一次，单位为秒
+    pub cooldown_secs: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        NotificationConfig {
+            enabled: true,
+            super_chat: EventRule::default(),
+            guard: EventRule::default(),
+            // 礼物数量较多，默认只通知高价礼物
+            gift: EventRule::new(false, 100.0),
+            connection_lost: true,
+            quiet_hours: QuietHours::default(),
+            cooldown_secs: 30,
+        }
+    }
+}
+
+struct Shared {
+    app: AppHandle,
+    config: RwLock<NotificationConfig>,
+    // 每条通知的去重键和上次发送的时间
+    recent: Mutex<HashMap<String, Instant>>,
+}
+
+impl Shared {
+    fn notify(&self, key: String, title: &str, body: &str) {
+        let config = self.config.read().unwrap().clone();
+        if !config.enabled || config.quiet_hours.contains(Local::now().time()) {
+            return;
+        }
+        {
+            let cooldown = Duration::from_secs(config.cooldown_secs);
+            let now = Instant::now();
+            let mut recent = self.recent.lock().unwrap();
+            recent.retain(|_, sent_at| now.duration_since(*sent_at) < cooldown);
+            if recent.contains_key(&key) {
+                return;
+            }
+            recent.insert(key, now);
+        }
+        if let Err(err) = self
+            .app
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+        {
+            log::warn!("发送系统通知失败: {}", err);
+        }
+    }
+
+    fn on_event(&self, event: &DanmakuEvent) {
+        if event.replay {
+            return;
+        }
+        let config = self.config.read().unwrap().clone();
+        let (rule, title, body) = match event.kind {
+            EventKind::SuperChat => (
+                &config.super_chat,
+                format!("醒目留言 ¥{}", event.price),
+                format!("{}: {}", event.uname, event.message),
+            ),
+            EventKind::Guard => (
+                &config.guard,
+                format!("{} 开通了{}", event.uname, event.message),
+                format!("直播间 {}，{} 个月", event.room_id, event.num),
+            ),
+            EventKind::Gift => (
+                &config.gift,
+                format!("{} 赠送了 {} x{}", event.uname, event.message, event.num),
+                format!("直播间 {}，价值 ¥{}", event.room_id, event.price),
+            ),
+            _ => return,
+        };
+        if !rule.matches(event.price) {
+            return;
+        }
+        // 同一条消息可能从多个来源收到，按内容去重
+        let key = format!(
+            "{}:{}:{}:{}",
+            event.kind.as_str(),
+            event.room_id,
+            event.uid,
+            event.message
+        );
+        self.notify(key, &title, &body);
+    }
+
+    fn on_state_change(&self, change: &ConnectionStateChanged) {
+        if !self.config.read().unwrap().connection_lost {
+            return;
+        }
+        // 只在刚断开时通知，重连中的重试不再重复通知
+        if change.previous != ConnectionState::Connected
+            || change.state != ConnectionState::Reconnecting
+        {
+            return;
+        }
+        let body = match &change.error {
+            Some(error) => format!("{}，正在重连", error),
+            None => "正在重连".to_string(),
+        };
+        self.notify(
+            format!("connection:{}", change.room_id),
+            &format!("直播间 {} 连接断开", change.room_id),
+            &body,
+        );
+    }
+}
+
+// 按规则发送系统通知，在后台处理事件，主窗口隐藏时同样生效
+pub struct NotificationManager {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl NotificationManager {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+        states: broadcast::Receiver<ConnectionStateChanged>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            config: RwLock::new(config),
+            recent: Mutex::new(HashMap::new()),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events, states));
+        Ok(NotificationManager { shared, store })
+    }
+
+    pub fn get_config(&self) -> NotificationConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: NotificationConfig) -> AppResult<NotificationConfig> {
+        for time in [&config.quiet_hours.start, &config.quiet_hours.end] {
+            parse_time(time).map_err(|_| {
+                AppError::InvalidConfig(format!("无效的免打扰时间: {}，格式应为 HH:MM", time))
+            })?;
+        }
+        for rule in [&config.super_chat, &config.guard, &config.gift] {
+            if !rule.min_price.is_finite() || rule.min_price < 0.0 {
+                return Err(AppError::InvalidConfig(
+                    "通知的金额门槛不能小于 0".to_string(),
+                ));
+            }
+        }
+        if config.cooldown_secs > MAX_COOLDOWN_SECS {
+            return Err(AppError::InvalidConfig(format!(
+                "通知冷却时间不能超过 {} 秒",
+                MAX_COOLDOWN_SECS
+            )));
+        }
+        *self.shared.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存通知规则失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化通知规则失败: {}", err),
+        }
+        Ok(config)
+    }
+
+    // 用于检查系统是否允许本程序发送通知，不受免打扰和冷却限制
+    pub fn send_test(&self) -> AppResult<()> {
+        self.shared
+            .app
+            .notification()
+            .builder()
+            .title("VTsuru 弹幕获取")
+            .body("这是一条测试通知")
+            .show()
+            .map_err(|err| AppError::Notification(err.to_string()))
+    }
+}
+
+async fn run(
+    shared: Arc<Shared>,
+    mut events: Subscriber<DanmakuEvent>,
+    mut states: broadcast::Receiver<ConnectionStateChanged>,
+) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => shared.on_event(&event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("通知处理不及时，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            change = states.recv() => match change {
+                Ok(change) => shared.on_state_change(&change),
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+}
+
+fn parse_time(time: &str) -> chrono::ParseResult<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M")
+}
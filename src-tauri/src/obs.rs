@@ -0,0 +1,589 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+mod protocol;
+
+// 保存 OBS 配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "obs.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 连接状态变化时发送给前端的事件
+pub const STATUS_EVENT: &str = "obs://status";
+// 收到 OBS 事件时发送给前端的事件
+pub const OBS_EVENT: &str = "obs://event";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// OBS 连接配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObsConfig {
+    // obs-websocket 地址，OBS 28 以上默认为 ws://127.0.0.1:4455
+    pub url: String,
+    pub password: String,
+    // 应用启动时自动连接
+    pub auto_connect: bool,
+    // 收到直播间事件时自动执行的操作
+    pub triggers: Vec<ObsTrigger>,
+}
+
+impl Default for ObsConfig {
+    fn default() -> Self {
+        ObsConfig {
+            url: "ws://127.0.0.1:4455".to_string(),
+            password: String::new(),
+            auto_connect: false,
+            triggers: Vec::new(),
+        }
+    }
+}
+
+// 可以对 OBS 执行的操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObsAction {
+    SwitchScene {
+        scene: String,
+    },
+    // visible 为空时切换当前的显示状态
+    // 设置 duration_secs 时显示指定时间后自动隐藏
+    SetSourceVisibility {
+        scene: String,
+        source: String,
+        #[serde(default)]
+        visible: Option<bool>,
+        #[serde(default)]
+        duration_secs: Option<u64>,
+    },
+    StartRecording,
+    StopRecording,
+}
+
+// 直播间事件触发 OBS 操作的规则，例如收到醒目留言时显示某个来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObsTrigger {
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub name: String,
+    // 为空时匹配所有类型
+    #[serde(default)]
+    pub kinds: Vec<EventKind>,
+    // 金额不低于该值时触发，单位为元
+    #[serde(default)]
+    pub min_price: f64,
+    pub action: ObsAction,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ObsTrigger {
+    fn matches(&self, event: &DanmakuEvent) -> bool {
+        self.enabled
+            && (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+            && event.price >= self.min_price
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ObsStatus {
+    pub connected: bool,
+    pub obs_version: Option<String>,
+    pub obs_websocket_version: Option<String>,
+    // 最近一次连接失败或断开的原因
+    pub error: Option<String>,
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type Pending = Arc<Mutex<HashMap<String, oneshot::Sender<AppResult<Value>>>>>;
+
+// 一个已完成验证的连接，丢弃后连接任务会关闭 WebSocket
+struct Connection {
+    generation: u64,
+    outgoing: mpsc::UnboundedSender<Message>,
+    pending: Pending,
+}
+
+struct Shared {
+    app: AppHandle,
+    config: RwLock<ObsConfig>,
+    connection: Mutex<Option<Connection>>,
+    status: Mutex<ObsStatus>,
+    // 每次连接加一，旧连接的任务退出时不会覆盖新连接的状态
+    generation: AtomicU64,
+    next_request_id: AtomicU64,
+    // OBS 开始或停止推流
+    streaming: broadcast::Sender<bool>,
+}
+
+impl Shared {
+    fn set_status(&self, status: ObsStatus) {
+        *self.status.lock().unwrap() = status.clone();
+        let _ = self.app.emit(STATUS_EVENT, &status);
+    }
+
+    async fn connect(self: &Arc<Self>) -> AppResult<ObsStatus> {
+        // 先断开旧连接
+        self.connection.lock().unwrap().take();
+        let config = self.config.read().unwrap().clone();
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+
+        let result = tokio::time::timeout(CONNECT_TIMEOUT, handshake(&config)).await;
+        let (ws, hello) = match result {
+            Ok(Ok(connected)) => connected,
+            Ok(Err(err)) => return Err(self.connect_failed(err)),
+            Err(_) => return Err(self.connect_failed(AppError::Obs("连接超时".to_string()))),
+        };
+
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        tauri::async_runtime::spawn(run(
+            self.clone(),
+            generation,
+            ws,
+            outgoing_rx,
+            pending.clone(),
+        ));
+        *self.connection.lock().unwrap() = Some(Connection {
+            generation,
+            outgoing,
+            pending,
+        });
+        let status = ObsStatus {
+            connected: true,
+            obs_version: None,
+            obs_websocket_version: hello["obsWebSocketVersion"].as_str().map(str::to_string),
+            error: None,
+        };
+        self.set_status(status);
+
+        // OBS 版本只能通过请求获取，失败不影响连接
+        if let Ok(version) = self.request("GetVersion", Value::Null).await {
+            let mut status = self.status.lock().unwrap().clone();
+            status.obs_version = version["obsVersion"].as_str().map(str::to_string);
+            self.set_status(status);
+        }
+        log::info!("已连接到 OBS: {}", config.url);
+        Ok(self.status.lock().unwrap().clone())
+    }
+
+    fn connect_failed(&self, err: AppError) -> AppError {
+        self.set_status(ObsStatus {
+            error: Some(err.to_string()),
+            ..Default::default()
+        });
+        err
+    }
+
+    fn disconnect(&self) -> ObsStatus {
+        if self.connection.lock().unwrap().take().is_some() {
+            self.set_status(ObsStatus::default());
+        }
+        self.status.lock().unwrap().clone()
+    }
+
+    // 发送请求并等待响应，返回 responseData
+    async fn request(&self, request_type: &str, data: Value) -> AppResult<Value> {
+        let (outgoing, pending) = {
+            let connection = self.connection.lock().unwrap();
+            let connection = connection.as_ref().ok_or(AppError::ObsNotConnected)?;
+            (connection.outgoing.clone(), connection.pending.clone())
+        };
+        let request_id = self
+            .next_request_id
+            .fetch_add(1, Ordering::Relaxed)
+            .to_string();
+        let (sender, receiver) = oneshot::channel();
+        pending.lock().unwrap().insert(request_id.clone(), sender);
+
+        let message = protocol::request(request_type, &request_id, data);
+        if outgoing.send(Message::Text(message.to_string())).is_err() {
+            pending.lock().unwrap().remove(&request_id);
+            return Err(AppError::ObsNotConnected);
+        }
+        match tokio::time::timeout(REQUEST_TIMEOUT, receiver).await {
+            Ok(Ok(result)) => result,
+            // 连接断开时未完成的请求会被丢弃
+            Ok(Err(_)) => Err(AppError::ObsNotConnected),
+            Err(_) => {
+                pending.lock().unwrap().remove(&request_id);
+                Err(AppError::Obs(format!("请求 {} 超时", request_type)))
+            }
+        }
+    }
+
+    async fn run_action(self: &Arc<Self>, action: &ObsAction) -> AppResult<()> {
+        match action {
+            ObsAction::SwitchScene { scene } => {
+                self.request("SetCurrentProgramScene", json!({ "sceneName": scene }))
+                    .await?;
+            }
+            ObsAction::SetSourceVisibility {
+                scene,
+                source,
+                visible,
+                duration_secs,
+            } => {
+                let item_id = self
+                    .request(
+                        "GetSceneItemId",
+                        json!({ "sceneName": scene, "sourceName": source }),
+                    )
+                    .await?["sceneItemId"]
+                    .as_i64()
+                    .ok_or_else(|| AppError::Obs(format!("场景中没有来源 {}", source)))?;
+                let visible = match visible {
+                    Some(visible) => *visible,
+                    None => !self
+                        .request(
+                            "GetSceneItemEnabled",
+                            json!({ "sceneName": scene, "sceneItemId": item_id }),
+                        )
+                        .await?["sceneItemEnabled"]
+                        .as_bool()
+                        .unwrap_or(false),
+                };
+                self.set_item_enabled(scene, item_id, visible).await?;
+
+                if let (true, Some(duration)) = (visible, duration_secs) {
+                    let shared = self.clone();
+                    let scene = scene.clone();
+                    let duration = Duration::from_secs(*duration);
+                    tauri::async_runtime::spawn(async move {
+                        tokio::time::sleep(duration).await;
+                        if let Err(err) = shared.set_item_enabled(&scene, item_id, false).await {
+                            log::warn!("隐藏 OBS 来源失败: {}", err);
+                        }
+                    });
+                }
+            }
+            ObsAction::StartRecording => {
+                self.request("StartRecord", Value::Null).await?;
+            }
+            ObsAction::StopRecording => {
+                self.request("StopRecord", Value::Null).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_item_enabled(&self, scene: &str, item_id: i64, enabled: bool) -> AppResult<()> {
+        self.request(
+            "SetSceneItemEnabled",
+            json!({
+                "sceneName": scene,
+                "sceneItemId": item_id,
+                "sceneItemEnabled": enabled,
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+// 通过 obs-websocket v5 控制 OBS
+pub struct ObsClient {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl ObsClient {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: ObsConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            config: RwLock::new(config),
+            connection: Mutex::new(None),
+            status: Mutex::new(ObsStatus::default()),
+            generation: AtomicU64::new(0),
+            next_request_id: AtomicU64::new(1),
+            streaming: broadcast::channel(16).0,
+        });
+        tauri::async_runtime::spawn(run_triggers(shared.clone(), events));
+        Ok(ObsClient { shared, store })
+    }
+
+    // 配置为自动连接时在后台连接，失败时只记录错误
+    pub fn auto_connect(&self) {
+        if !self.shared.config.read().unwrap().auto_connect {
+            return;
+        }
+        let shared = self.shared.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = shared.connect().await {
+                log::warn!("自动连接 OBS 失败: {}", err);
+            }
+        });
+    }
+
+    pub fn get_config(&self) -> ObsConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    // 保存配置，连接地址和密码在下次连接时生效
+    pub fn update_config(&self, config: ObsConfig) -> AppResult<ObsConfig> {
+        let config = ObsConfig {
+            url: config.url.trim().to_string(),
+            ..config
+        };
+        if !config.url.starts_with("ws://") && !config.url.starts_with("wss://") {
+            return Err(AppError::InvalidConfig(
+                "OBS 地址必须以 ws:// 或 wss:// 开头".to_string(),
+            ));
+        }
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存 OBS 配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化 OBS 配置失败: {}", err),
+        }
+        *self.shared.config.write().unwrap() = config.clone();
+        Ok(config)
+    }
+
+    pub async fn connect(&self) -> AppResult<ObsStatus> {
+        self.shared.connect().await
+    }
+
+    pub fn disconnect(&self) -> ObsStatus {
+        self.shared.disconnect()
+    }
+
+    pub fn status(&self) -> ObsStatus {
+        self.shared.status.lock().unwrap().clone()
+    }
+
+    // 订阅 OBS 推流状态的变化，true 为开始推流
+    pub fn subscribe_streaming(&self) -> broadcast::Receiver<bool> {
+        self.shared.streaming.subscribe()
+    }
+
+    // 所有场景的名称，按 OBS 中的显示顺序排列
+    pub async fn scenes(&self) -> AppResult<Vec<String>> {
+        let data = self.shared.request("GetSceneList", Value::Null).await?;
+        let mut scenes: Vec<String> = data["scenes"]
+            .as_array()
+            .map(|scenes| {
+                scenes
+                    .iter()
+                    .filter_map(|scene| scene["sceneName"].as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // OBS 返回的列表与界面中的顺序相反
+        scenes.reverse();
+        Ok(scenes)
+    }
+
+    pub async fn run_action(&self, action: &ObsAction) -> AppResult<()> {
+        self.shared.run_action(action).await
+    }
+}
+
+// 连接并完成 Hello / Identify 握手，返回连接和 Hello 消息的内容
+async fn handshake(config: &ObsConfig) -> AppResult<(Socket, Value)> {
+    let (mut ws, _) = tokio_tungstenite::connect_async(config.url.as_str()).await?;
+    let hello = loop {
+        let message = next_message(&mut ws).await?;
+        if message["op"].as_u64() == Some(protocol::OP_HELLO) {
+            break message["d"].clone();
+        }
+    };
+    let identify = protocol::identify(&hello, &config.password);
+    ws.send(Message::Text(identify.to_string())).await?;
+    loop {
+        let message = next_message(&mut ws).await?;
+        if message["op"].as_u64() == Some(protocol::OP_IDENTIFIED) {
+            return Ok((ws, hello));
+        }
+    }
+}
+
+// 读取下一条 JSON 消息，连接关闭时返回原因
+async fn next_message(ws: &mut Socket) -> AppResult<Value> {
+    loop {
+        match ws.next().await {
+            Some(Ok(Message::Text(text))) => {
+                return serde_json::from_str(&text).map_err(|err| AppError::Obs(err.to_string()));
+            }
+            Some(Ok(Message::Close(frame))) => {
+                return Err(match frame {
+                    Some(frame)
+                        if frame.code == CloseCode::from(protocol::CLOSE_AUTHENTICATION_FAILED) =>
+                    {
+                        AppError::Obs("OBS 密码错误".to_string())
+                    }
+                    Some(frame) => AppError::Obs(format!("OBS 关闭了连接: {}", frame.reason)),
+                    None => AppError::Obs("OBS 关闭了连接".to_string()),
+                });
+            }
+            Some(Ok(_)) => {}
+            Some(Err(err)) => return Err(err.into()),
+            None => return Err(AppError::Obs("OBS 关闭了连接".to_string())),
+        }
+    }
+}
+
+// 转发请求并分发响应和事件，直到连接断开或 Connection 被丢弃
+async fn run(
+    shared: Arc<Shared>,
+    generation: u64,
+    ws: Socket,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    pending: Pending,
+) {
+    let (mut sink, mut stream) = ws.split();
+    let error = loop {
+        tokio::select! {
+            message = outgoing.recv() => match message {
+                Some(message) => {
+                    if let Err(err) = sink.send(message).await {
+                        break Some(err.to_string());
+                    }
+                }
+                // 主动断开
+                None => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    break None;
+                }
+            },
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => handle_message(&shared, &pending, &text),
+                Some(Ok(Message::Close(_))) | None => break Some("OBS 关闭了连接".to_string()),
+                Some(Ok(_)) => {}
+                Some(Err(err)) => break Some(err.to_string()),
+            },
+        }
+    };
+    // 丢弃未完成的请求，等待中的调用会收到未连接的错误
+    pending.lock().unwrap().clear();
+
+    let current = {
+        let mut connection = shared.connection.lock().unwrap();
+        let current = connection
+            .as_ref()
+            .is_some_and(|connection| connection.generation == generation);
+        if current {
+            connection.take();
+        }
+        current
+    };
+    if current {
+        log::warn!("OBS 连接已断开: {}", error.as_deref().unwrap_or_default());
+        shared.set_status(ObsStatus {
+            error,
+            ..Default::default()
+        });
+    }
+}
+
+fn handle_message(shared: &Shared, pending: &Pending, text: &str) {
+    let Ok(message) = serde_json::from_str::<Value>(text) else {
+        return;
+    };
+    let data = &message["d"];
+    match message["op"].as_u64() {
+        Some(protocol::OP_REQUEST_RESPONSE) => {
+            let Some(sender) = data["requestId"]
+                .as_str()
+                .and_then(|id| pending.lock().unwrap().remove(id))
+            else {
+                return;
+            };
+            let status = &data["requestStatus"];
+            let result = if status["result"].as_bool() == Some(true) {
+                Ok(data["responseData"].clone())
+            } else {
+                Err(AppError::Obs(format!(
+                    "{} 失败 ({}): {}",
+                    data["requestType"].as_str().unwrap_or_default(),
+                    status["code"].as_i64().unwrap_or_default(),
+                    status["comment"].as_str().unwrap_or_default()
+                )))
+            };
+            let _ = sender.send(result);
+        }
+        Some(protocol::OP_EVENT) => {
+            if data["eventType"] == "StreamStateChanged" {
+                match data["eventData"]["outputState"].as_str() {
+                    Some(protocol::OUTPUT_STARTED) => {
+                        let _ = shared.streaming.send(true);
+                    }
+                    Some(protocol::OUTPUT_STOPPED) => {
+                        let _ = shared.streaming.send(false);
+                    }
+                    _ => {}
+                }
+            }
+            let _ = shared.app.emit(
+                OBS_EVENT,
+                json!({
+                    "eventType": data["eventType"],
+                    "eventData": data["eventData"],
+                }),
+            );
+        }
+        _ => {}
+    }
+}
+
+// 按配置的触发规则对直播间事件执行 OBS 操作
+async fn run_triggers(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if shared.connection.lock().unwrap().is_none() {
+            continue;
+        }
+        let actions: Vec<ObsAction> = shared
+            .config
+            .read()
+            .unwrap()
+            .triggers
+            .iter()
+            .filter(|trigger| trigger.matches(&event))
+            .map(|trigger| trigger.action.clone())
+            .collect();
+        for action in actions {
+            let shared = shared.clone();
+            // 操作可能需要多次请求，不阻塞后续事件
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = shared.run_action(&action).await {
+                    log::warn!("执行 OBS 操作失败: {}", err);
+                }
+            });
+        }
+    }
+}
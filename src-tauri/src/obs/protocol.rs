@@ -0,0 +1,53 @@
+use base64::prelude::*;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+// obs-websocket v5 的消息类型
+pub(super) const OP_HELLO: u64 = 0;
+pub(super) const OP_IDENTIFY: u64 = 1;
+pub(super) const OP_IDENTIFIED: u64 = 2;
+pub(super) const OP_EVENT: u64 = 5;
+pub(super) const OP_REQUEST: u64 = 6;
+pub(super) const OP_REQUEST_RESPONSE: u64 = 7;
+
+const RPC_VERSION: u64 = 1;
+// 订阅除高频事件以外的所有事件
+const EVENT_SUBSCRIPTION_ALL: u64 = 0x7FF;
+
+// StreamStateChanged 事件中推流开始和结束的状态
+pub(super) const OUTPUT_STARTED: &str = "OBS_WEBSOCKET_OUTPUT_STARTED";
+pub(super) const OUTPUT_STOPPED: &str = "OBS_WEBSOCKET_OUTPUT_STOPPED";
+
+// 密码错误时 OBS 关闭连接使用的代码
+pub(super) const CLOSE_AUTHENTICATION_FAILED: u16 = 4009;
+
+// 根据 Hello 消息生成 Identify 消息，服务端要求验证时计算验证字符串
+pub(super) fn identify(hello: &Value, password: &str) -> Value {
+    let mut data = json!({
+        "rpcVersion": RPC_VERSION,
+        "eventSubscriptions": EVENT_SUBSCRIPTION_ALL,
+    });
+    let auth = &hello["authentication"];
+    if let (Some(challenge), Some(salt)) = (auth["challenge"].as_str(), auth["salt"].as_str()) {
+        data["authentication"] = json!(authentication(password, salt, challenge));
+    }
+    json!({ "op": OP_IDENTIFY, "d": data })
+}
+
+// base64(sha256(base64(sha256(password + salt)) + challenge))
+fn authentication(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = BASE64_STANDARD.encode(Sha256::digest(format!("{}{}", password, salt)));
+    BASE64_STANDARD.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+// 没有参数的请求不发送 requestData
+pub(super) fn request(request_type: &str, request_id: &str, data: Value) -> Value {
+    let mut request = json!({
+        "requestType": request_type,
+        "requestId": request_id,
+    });
+    if !data.is_null() {
+        request["requestData"] = data;
+    }
+    json!({ "op": OP_REQUEST, "d": request })
+}
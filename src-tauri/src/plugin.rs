@@ -0,0 +1,278 @@
+use crate::danmaku::DanmakuEvent;
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use wasmtime::{Engine, Module};
+
+mod runtime;
+
+// 保存插件列表的文件，位于应用数据目录
+const STORE_FILE: &str = "plugins.json";
+const PLUGINS_KEY: &str = "plugins";
+
+// 保存在磁盘上的插件信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PluginMeta {
+    id: String,
+    name: String,
+    enabled: bool,
+    installed_at: i64,
+}
+
+// 返回给前端的插件信息和运行统计
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub installed_at: i64,
+    // 本次启动以来处理、丢弃的事件数量和出错次数
+    pub processed: u64,
+    pub dropped: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+}
+
+struct Plugin {
+    meta: PluginMeta,
+    module: Module,
+    // 首次处理事件时创建，出错后丢弃并在下次重新创建
+    runtime: Option<runtime::Runtime>,
+    processed: u64,
+    dropped: u64,
+    errors: u64,
+    last_error: Option<String>,
+}
+
+impl Plugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            id: self.meta.id.clone(),
+            name: self.meta.name.clone(),
+            enabled: self.meta.enabled,
+            installed_at: self.meta.installed_at,
+            processed: self.processed,
+            dropped: self.dropped,
+            errors: self.errors,
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    // 处理事件，返回 None 表示丢弃；插件出错时保留原事件
+    fn process(&mut self, engine: &Engine, event: DanmakuEvent) -> Option<DanmakuEvent> {
+        match self.try_process(engine, &event) {
+            Ok(Some(processed)) => {
+                self.processed += 1;
+                Some(processed)
+            }
+            Ok(None) => {
+                self.processed += 1;
+                self.dropped += 1;
+                None
+            }
+            Err(err) => {
+                log::warn!("插件 {} 处理事件失败: {}", self.meta.name, err);
+                self.errors += 1;
+                self.last_error = Some(err.to_string());
+                self.runtime = None;
+                Some(event)
+            }
+        }
+    }
+
+    fn try_process(
+        &mut self,
+        engine: &Engine,
+        event: &DanmakuEvent,
+    ) -> AppResult<Option<DanmakuEvent>> {
+        let runtime = match &mut self.runtime {
+            Some(runtime) => runtime,
+            None => self
+                .runtime
+                .insert(runtime::Runtime::instantiate(engine, &self.module)?),
+        };
+        let input = serde_json::to_vec(event).map_err(|err| AppError::Plugin(err.to_string()))?;
+        let Some(output) = runtime.process(&input)? else {
+            return Ok(None);
+        };
+        let mut processed: DanmakuEvent = serde_json::from_slice(&output)
+            .map_err(|err| AppError::Plugin(format!("插件返回的事件格式无效: {}", err)))?;
+        // 事件 ID 由客户端生成，插件不能修改
+        processed.id = event.id.clone();
+        Ok(Some(processed))
+    }
+}
+
+struct Inner {
+    engine: Engine,
+    dir: PathBuf,
+    store: Arc<Store<Wry>>,
+    plugins: Mutex<Vec<Plugin>>,
+}
+
+// 加载用户提供的 WASM 插件，按安装顺序依次处理事件
+#[derive(Clone)]
+pub struct PluginHost {
+    inner: Arc<Inner>,
+}
+
+impl PluginHost {
+    pub fn load(app: &AppHandle, dir: PathBuf) -> AppResult<Self> {
+        let engine = runtime::engine()?;
+        let store = app
+            .store(STORE_FILE)
+            .map_err(|err| AppError::Plugin(err.to_string()))?;
+        let metas: Vec<PluginMeta> = store
+            .get(PLUGINS_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+
+        let plugins = metas
+            .into_iter()
+            .filter_map(|meta| {
+                let result = fs::read(plugin_path(&dir, &meta.id))
+                    .map_err(AppError::from)
+                    .and_then(|bytes| runtime::compile(&engine, &bytes));
+                match result {
+                    Ok(module) => Some(Plugin {
+                        meta,
+                        module,
+                        runtime: None,
+                        processed: 0,
+                        dropped: 0,
+                        errors: 0,
+                        last_error: None,
+                    }),
+                    Err(err) => {
+                        log::warn!("加载插件 {} 失败: {}", meta.name, err);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Ok(PluginHost {
+            inner: Arc::new(Inner {
+                engine,
+                dir,
+                store,
+                plugins: Mutex::new(plugins),
+            }),
+        })
+    }
+
+    pub fn list(&self) -> Vec<PluginInfo> {
+        self.inner
+            .plugins
+            .lock()
+            .unwrap()
+            .iter()
+            .map(Plugin::info)
+            .collect()
+    }
+
+    // 安装插件，检查通过后复制到插件目录，默认不启用
+    pub fn install(&self, path: &Path, name: Option<String>) -> AppResult<PluginInfo> {
+        let bytes = fs::read(path)?;
+        let module = runtime::compile(&self.inner.engine, &bytes)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        fs::create_dir_all(&self.inner.dir)?;
+        fs::write(plugin_path(&self.inner.dir, &id), &bytes)?;
+
+        let name = name
+            .filter(|name| !name.trim().is_empty())
+            .or_else(|| {
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| id.clone());
+        let plugin = Plugin {
+            meta: PluginMeta {
+                id,
+                name,
+                enabled: false,
+                installed_at: chrono::Utc::now().timestamp_millis(),
+            },
+            module,
+            runtime: None,
+            processed: 0,
+            dropped: 0,
+            errors: 0,
+            last_error: None,
+        };
+        let info = plugin.info();
+        self.inner.plugins.lock().unwrap().push(plugin);
+        self.save();
+        Ok(info)
+    }
+
+    pub fn uninstall(&self, id: &str) -> AppResult<()> {
+        {
+            let mut plugins = self.inner.plugins.lock().unwrap();
+            let index = plugins
+                .iter()
+                .position(|plugin| plugin.meta.id == id)
+                .ok_or_else(|| AppError::PluginNotFound(id.to_string()))?;
+            plugins.remove(index);
+        }
+        if let Err(err) = fs::remove_file(plugin_path(&self.inner.dir, id)) {
+            log::warn!("删除插件文件失败: {}", err);
+        }
+        self.save();
+        Ok(())
+    }
+
+    pub fn set_enabled(&self, id: &str, enabled: bool) -> AppResult<PluginInfo> {
+        let info = {
+            let mut plugins = self.inner.plugins.lock().unwrap();
+            let plugin = plugins
+                .iter_mut()
+                .find(|plugin| plugin.meta.id == id)
+                .ok_or_else(|| AppError::PluginNotFound(id.to_string()))?;
+            plugin.meta.enabled = enabled;
+            // 重新启用时从干净的状态开始
+            plugin.runtime = None;
+            plugin.info()
+        };
+        self.save();
+        Ok(info)
+    }
+
+    // 依次交给启用的插件处理，任一插件丢弃时返回 None
+    pub fn process(&self, event: DanmakuEvent) -> Option<DanmakuEvent> {
+        let mut plugins = self.inner.plugins.lock().unwrap();
+        let mut event = event;
+        for plugin in plugins.iter_mut().filter(|plugin| plugin.meta.enabled) {
+            event = plugin.process(&self.inner.engine, event)?;
+        }
+        Some(event)
+    }
+
+    fn save(&self) {
+        let metas: Vec<PluginMeta> = self
+            .inner
+            .plugins
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|plugin| plugin.meta.clone())
+            .collect();
+        match serde_json::to_value(&metas) {
+            Ok(value) => {
+                self.inner.store.set(PLUGINS_KEY, value);
+                if let Err(err) = self.inner.store.save() {
+                    log::warn!("保存插件列表失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化插件列表失败: {}", err),
+        }
+    }
+}
+
+fn plugin_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.wasm", id))
+}
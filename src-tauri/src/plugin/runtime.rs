@@ -0,0 +1,127 @@
+use crate::error::{AppError, AppResult};
+use wasmtime::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+// 单次处理事件最多消耗的燃料，大致对应执行的指令数量，防止死循环卡住事件分发
+const FUEL_PER_CALL: u64 = 50_000_000;
+// 插件可以使用的最大内存
+const MAX_MEMORY: usize = 32 * 1024 * 1024;
+// 单次处理返回结果的最大长度
+const MAX_OUTPUT: usize = 4 * 1024 * 1024;
+
+// 插件需要导出的接口：
+// memory                                   线性内存
+// alloc(len: i32) -> i32                   分配 len 字节，返回地址
+// process_event(ptr: i32, len: i32) -> i64 处理 JSON 格式的事件，
+//     返回 0 表示丢弃事件，否则高 32 位为结果地址、低 32 位为结果长度
+// 插件不能导入任何宿主函数，因此无法访问文件、网络等系统资源
+const EXPORT_MEMORY: &str = "memory";
+const EXPORT_ALLOC: &str = "alloc";
+const EXPORT_PROCESS: &str = "process_event";
+
+pub(super) fn engine() -> AppResult<Engine> {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config).map_err(plugin_error)
+}
+
+// 编译并检查插件，确认导出了所需的接口
+pub(super) fn compile(engine: &Engine, bytes: &[u8]) -> AppResult<Module> {
+    let module = Module::new(engine, bytes).map_err(plugin_error)?;
+    if module.imports().next().is_some() {
+        return Err(AppError::Plugin("插件不能导入宿主函数".to_string()));
+    }
+    for name in [EXPORT_MEMORY, EXPORT_ALLOC, EXPORT_PROCESS] {
+        if module.get_export(name).is_none() {
+            return Err(AppError::Plugin(format!("插件缺少导出 {}", name)));
+        }
+    }
+    Ok(module)
+}
+
+// 一个插件的运行实例，插件可以在多次调用之间保留状态
+pub(super) struct Runtime {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    process: TypedFunc<(i32, i32), i64>,
+}
+
+impl Runtime {
+    pub fn instantiate(engine: &Engine, module: &Module) -> AppResult<Self> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .instances(1)
+            .build();
+        let mut store = Store::new(engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_CALL).map_err(plugin_error)?;
+
+        let instance = Linker::new(engine)
+            .instantiate(&mut store, module)
+            .map_err(plugin_error)?;
+        let memory = instance
+            .get_memory(&mut store, EXPORT_MEMORY)
+            .ok_or_else(|| AppError::Plugin("插件未导出内存".to_string()))?;
+        let alloc = instance
+            .get_typed_func(&mut store, EXPORT_ALLOC)
+            .map_err(plugin_error)?;
+        let process = instance
+            .get_typed_func(&mut store, EXPORT_PROCESS)
+            .map_err(plugin_error)?;
+        Ok(Runtime {
+            store,
+            memory,
+            alloc,
+            process,
+        })
+    }
+
+    // 处理一个 JSON 事件，返回 None 表示丢弃
+    pub fn process(&mut self, input: &[u8]) -> AppResult<Option<Vec<u8>>> {
+        self.store.set_fuel(FUEL_PER_CALL).map_err(plugin_error)?;
+
+        let len =
+            i32::try_from(input.len()).map_err(|_| AppError::Plugin("事件过大".to_string()))?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(plugin_error)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, input)
+            .map_err(plugin_error)?;
+
+        let result = self
+            .process
+            .call(&mut self.store, (ptr, len))
+            .map_err(plugin_error)?;
+        if result == 0 {
+            return Ok(None);
+        }
+        let out_ptr = (result as u64 >> 32) as usize;
+        let out_len = (result as u64 & 0xFFFF_FFFF) as usize;
+        // 先检查长度再分配，避免插件返回的长度让宿主分配过多内存
+        if out_len > MAX_OUTPUT {
+            return Err(AppError::Plugin(format!(
+                "插件返回的结果过大: {} 字节",
+                out_len
+            )));
+        }
+        let in_bounds = out_ptr
+            .checked_add(out_len)
+            .is_some_and(|end| end <= self.memory.data_size(&self.store));
+        if !in_bounds {
+            return Err(AppError::Plugin("插件返回的结果超出内存范围".to_string()));
+        }
+        let mut output = vec![0; out_len];
+        self.memory
+            .read(&self.store, out_ptr, &mut output)
+            .map_err(plugin_error)?;
+        Ok(Some(output))
+    }
+}
+
+fn plugin_error(err: impl std::fmt::Display) -> AppError {
+    AppError::Plugin(err.to_string())
+}
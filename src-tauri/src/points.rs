@@ -0,0 +1,543 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::event_store::today_start;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存积分设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "points.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 发放观看积分的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(60);
+// 单次查询最多返回的记录数量
+const MAX_QUERY_LIMIT: u32 = 500;
+
+// 余额表保存每个用户的当前积分，流水表保存每一次变动，便于之后同步到 vtsuru
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS points_balances (
+    user_key TEXT PRIMARY KEY,
+    uid INTEGER NOT NULL,
+    open_id TEXT,
+    uname TEXT NOT NULL,
+    balance INTEGER NOT NULL,
+    earned INTEGER NOT NULL,
+    last_checkin INTEGER,
+    updated_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_points_balances_balance ON points_balances (balance);
+CREATE TABLE IF NOT EXISTS points_ledger (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_key TEXT NOT NULL,
+    room_id INTEGER,
+    delta INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    note TEXT,
+    timestamp INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_points_ledger_user ON points_ledger (user_key, timestamp);
+";
+
+const BALANCE_COLUMNS: &str =
+    "user_key, uid, open_id, uname, balance, earned, last_checkin, updated_at";
+
+// 积分获取规则，各项为 0 时不发放对应的积分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PointsConfig {
+    pub enabled: bool,
+    // 每条弹幕获得的积分
+    pub danmaku_points: i64,
+    // 同一用户两次获得弹幕积分的最短间隔，防止刷屏
+    pub danmaku_cooldown_secs: u64,
+    // 观看时每分钟获得的积分
+    pub watch_points_per_minute: i64,
+    // 最近该时间内有过弹幕、进场、点赞或礼物的用户视为正在观看
+    pub watch_window_minutes: u64,
+    // 礼物、醒目留言和大航海每元获得的积分，向下取整
+    pub gift_points_per_yuan: f64,
+    // 每天第一次发送该弹幕时签到，为空时关闭签到
+    pub checkin_command: String,
+    pub checkin_points: i64,
+}
+
+impl Default for PointsConfig {
+    fn default() -> Self {
+        PointsConfig {
+            enabled: false,
+            danmaku_points: 1,
+            danmaku_cooldown_secs: 60,
+            watch_points_per_minute: 1,
+            watch_window_minutes: 10,
+            gift_points_per_yuan: 10.0,
+            checkin_command: "签到".to_string(),
+            checkin_points: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointsReason {
+    Danmaku,
+    Watch,
+    Gift,
+    Checkin,
+    // 主播手动调整
+    Adjust,
+}
+
+impl PointsReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PointsReason::Danmaku => "danmaku",
+            PointsReason::Watch => "watch",
+            PointsReason::Gift => "gift",
+            PointsReason::Checkin => "checkin",
+            PointsReason::Adjust => "adjust",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PointsBalance {
+    // uid，开放平台的用户为 open_id
+    pub user_key: String,
+    pub uid: u64,
+    pub open_id: Option<String>,
+    // 最近一次使用的名称
+    pub uname: String,
+    pub balance: i64,
+    // 累计获得的积分，不含手动扣除
+    pub earned: i64,
+    // 最近一次签到的 Unix 毫秒时间戳
+    pub last_checkin: Option<i64>,
+    pub updated_at: i64,
+}
+
+impl PointsBalance {
+    // 列的顺序与 BALANCE_COLUMNS 一致
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(PointsBalance {
+            user_key: row.get(0)?,
+            uid: row.get::<_, i64>(1)? as u64,
+            open_id: row.get(2)?,
+            uname: row.get(3)?,
+            balance: row.get(4)?,
+            earned: row.get(5)?,
+            last_checkin: row.get(6)?,
+            updated_at: row.get(7)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PointsTransaction {
+    pub id: i64,
+    pub user_key: String,
+    pub room_id: Option<u64>,
+    pub delta: i64,
+    pub reason: String,
+    pub note: Option<String>,
+    pub timestamp: i64,
+}
+
+// 查找用户的条件，按 uid、open_id、用户名的顺序使用第一个提供的条件
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PointsLookup {
+    pub uid: Option<u64>,
+    pub open_id: Option<String>,
+    // 按名称精确匹配，重名时返回最近活跃的用户
+    pub uname: Option<String>,
+}
+
+// 用户的身份信息，来自最近的事件
+#[derive(Debug, Clone)]
+struct Viewer {
+    uid: u64,
+    open_id: Option<String>,
+    uname: String,
+    room_id: u64,
+}
+
+impl Viewer {
+    fn from_event(event: &DanmakuEvent) -> Option<(String, Self)> {
+        let key = event.user_key()?;
+        Some((
+            key,
+            Viewer {
+                uid: event.uid,
+                open_id: event.open_id.clone(),
+                uname: event.uname.clone(),
+                room_id: event.room_id,
+            },
+        ))
+    }
+}
+
+#[derive(Default)]
+struct Activity {
+    // 最近活跃的用户和活跃时间
+    seen: HashMap<String, (Viewer, i64)>,
+    // 最近一次获得弹幕积分的时间
+    last_danmaku: HashMap<String, i64>,
+}
+
+struct Shared {
+    conn: Mutex<Connection>,
+    config: RwLock<PointsConfig>,
+    activity: Mutex<Activity>,
+}
+
+impl Shared {
+    // 增加或扣除积分，同时写入流水
+    fn apply(
+        &self,
+        key: &str,
+        viewer: &Viewer,
+        delta: i64,
+        reason: PointsReason,
+        note: Option<&str>,
+        now: i64,
+    ) -> AppResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO points_balances (user_key, uid, open_id, uname, balance, earned, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (user_key) DO UPDATE SET
+                 uname = excluded.uname,
+                 balance = balance + excluded.balance,
+                 earned = earned + excluded.earned,
+                 updated_at = excluded.updated_at",
+            params![
+                key,
+                viewer.uid as i64,
+                viewer.open_id,
+                viewer.uname,
+                delta,
+                if reason == PointsReason::Adjust { 0 } else { delta },
+                now
+            ],
+        )?;
+        if reason == PointsReason::Checkin {
+            tx.execute(
+                "UPDATE points_balances SET last_checkin = ?2 WHERE user_key = ?1",
+                params![key, now],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO points_ledger (user_key, room_id, delta, reason, note, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                key,
+                (viewer.room_id != 0).then_some(viewer.room_id as i64),
+                delta,
+                reason.as_str(),
+                note,
+                now
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn balance(&self, key: &str) -> AppResult<Option<PointsBalance>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM points_balances WHERE user_key = ?1",
+                    BALANCE_COLUMNS
+                ),
+                params![key],
+                PointsBalance::from_row,
+            )
+            .optional()?)
+    }
+
+    fn on_event(&self, event: &DanmakuEvent) {
+        let config = self.config.read().unwrap().clone();
+        if !config.enabled || event.replay || event.simulated {
+            return;
+        }
+        let Some((key, viewer)) = Viewer::from_event(event) else {
+            return;
+        };
+        let now = event.timestamp;
+        let mut awards = Vec::new();
+        {
+            let mut activity = self.activity.lock().unwrap();
+            activity.seen.insert(key.clone(), (viewer.clone(), now));
+            match event.kind {
+                EventKind::Danmaku => {
+                    let message = event.message.trim();
+                    if !config.checkin_command.is_empty() && message == config.checkin_command {
+                        awards.push((config.checkin_points, PointsReason::Checkin));
+                    } else if config.danmaku_points != 0 {
+                        let cooldown = config.danmaku_cooldown_secs as i64 * 1000;
+                        let last = activity.last_danmaku.get(&key).copied();
+                        if last.map_or(true, |last| now - last >= cooldown) {
+                            activity.last_danmaku.insert(key.clone(), now);
+                            awards.push((config.danmaku_points, PointsReason::Danmaku));
+                        }
+                    }
+                }
+                EventKind::Gift | EventKind::SuperChat | EventKind::Guard => {
+                    let points = (event.price * config.gift_points_per_yuan).floor() as i64;
+                    if points > 0 {
+                        awards.push((points, PointsReason::Gift));
+                    }
+                }
+                EventKind::Like | EventKind::Enter => {}
+            }
+        }
+
+        for (points, reason) in awards {
+            if reason == PointsReason::Checkin {
+                // 每天只能签到一次，按本地时间计算
+                let checked_in = self
+                    .balance(&key)
+                    .ok()
+                    .flatten()
+                    .and_then(|balance| balance.last_checkin)
+                    .is_some_and(|last| last >= today_start());
+                if checked_in || points == 0 {
+                    continue;
+                }
+            }
+            if let Err(err) = self.apply(&key, &viewer, points, reason, None, now) {
+                log::warn!("发放积分失败: {}", err);
+            }
+        }
+    }
+
+    // 给最近活跃的用户发放观看积分
+    fn award_watch(&self) {
+        let config = self.config.read().unwrap().clone();
+        let now = chrono::Utc::now().timestamp_millis();
+        let window = config.watch_window_minutes as i64 * 60_000;
+        let viewers: Vec<(String, Viewer)> = {
+            let mut activity = self.activity.lock().unwrap();
+            activity
+                .seen
+                .retain(|_, (_, seen_at)| now - *seen_at <= window);
+            let cooldown = config.danmaku_cooldown_secs as i64 * 1000;
+            activity
+                .last_danmaku
+                .retain(|_, last| now - *last < cooldown);
+            activity
+                .seen
+                .iter()
+                .map(|(key, (viewer, _))| (key.clone(), viewer.clone()))
+                .collect()
+        };
+        if !config.enabled || config.watch_points_per_minute == 0 {
+            return;
+        }
+        for (key, viewer) in viewers {
+            if let Err(err) = self.apply(
+                &key,
+                &viewer,
+                config.watch_points_per_minute,
+                PointsReason::Watch,
+                None,
+                now,
+            ) {
+                log::warn!("发放观看积分失败: {}", err);
+            }
+        }
+    }
+}
+
+// 观众积分，根据弹幕、观看时长、礼物和签到发放，保存在本地数据库
+pub struct PointsManager {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl PointsManager {
+    pub fn open(app: &AppHandle, path: &Path, events: Subscriber<DanmakuEvent>) -> AppResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        let store = app
+            .store(STORE_FILE)
+            .map_err(|err| AppError::InvalidConfig(err.to_string()))?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            conn: Mutex::new(conn),
+            config: RwLock::new(config),
+            activity: Mutex::new(Activity::default()),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(PointsManager { shared, store })
+    }
+
+    pub fn get_config(&self) -> PointsConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: PointsConfig) -> AppResult<PointsConfig> {
+        let config = PointsConfig {
+            checkin_command: config.checkin_command.trim().to_string(),
+            ..config
+        };
+        if config.danmaku_points < 0
+            || config.watch_points_per_minute < 0
+            || config.checkin_points < 0
+            || config.gift_points_per_yuan.is_nan()
+            || config.gift_points_per_yuan < 0.0
+        {
+            return Err(AppError::InvalidConfig("积分数量不能为负数".to_string()));
+        }
+        if config.watch_window_minutes == 0 {
+            return Err(AppError::InvalidConfig(
+                "观看判定时间至少为 1 分钟".to_string(),
+            ));
+        }
+        *self.shared.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存积分设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化积分设置失败: {}", err),
+        }
+        Ok(config)
+    }
+
+    pub fn find(&self, lookup: &PointsLookup) -> AppResult<Option<PointsBalance>> {
+        if let Some(uid) = lookup.uid {
+            return self.shared.balance(&uid.to_string());
+        }
+        if let Some(open_id) = &lookup.open_id {
+            return self.shared.balance(open_id);
+        }
+        let Some(uname) = &lookup.uname else {
+            return Err(AppError::InvalidConfig(
+                "需要提供 uid、open_id 或用户名".to_string(),
+            ));
+        };
+        let conn = self.shared.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM points_balances WHERE uname = ?1 ORDER BY updated_at DESC LIMIT 1",
+                    BALANCE_COLUMNS
+                ),
+                params![uname],
+                PointsBalance::from_row,
+            )
+            .optional()?)
+    }
+
+    // 按余额从高到低排列
+    pub fn top(&self, limit: u32, offset: u32) -> AppResult<Vec<PointsBalance>> {
+        let conn = self.shared.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM points_balances ORDER BY balance DESC, updated_at DESC LIMIT ?1 OFFSET ?2",
+            BALANCE_COLUMNS
+        ))?;
+        let rows = stmt.query_map(
+            params![limit.clamp(1, MAX_QUERY_LIMIT), offset],
+            PointsBalance::from_row,
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // 用户的积分流水，按时间倒序排列
+    pub fn ledger(&self, user_key: &str, limit: u32) -> AppResult<Vec<PointsTransaction>> {
+        let conn = self.shared.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, user_key, room_id, delta, reason, note, timestamp FROM points_ledger
+             WHERE user_key = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![user_key, limit.clamp(1, MAX_QUERY_LIMIT)], |row| {
+            Ok(PointsTransaction {
+                id: row.get(0)?,
+                user_key: row.get(1)?,
+                room_id: row.get::<_, Option<i64>>(2)?.map(|id| id as u64),
+                delta: row.get(3)?,
+                reason: row.get(4)?,
+                note: row.get(5)?,
+                timestamp: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // 手动增加或扣除积分，余额不能变为负数
+    pub fn adjust(
+        &self,
+        user_key: &str,
+        delta: i64,
+        note: Option<String>,
+    ) -> AppResult<PointsBalance> {
+        let current = self
+            .shared
+            .balance(user_key)?
+            .ok_or_else(|| AppError::ViewerNotFound(user_key.to_string()))?;
+        if current.balance + delta < 0 {
+            return Err(AppError::InvalidConfig(format!(
+                "积分不足，当前余额为 {}",
+                current.balance
+            )));
+        }
+        let viewer = Viewer {
+            uid: current.uid,
+            open_id: current.open_id,
+            uname: current.uname,
+            room_id: 0,
+        };
+        let note = note
+            .map(|note| note.trim().to_string())
+            .filter(|note| !note.is_empty());
+        self.shared.apply(
+            user_key,
+            &viewer,
+            delta,
+            PointsReason::Adjust,
+            note.as_deref(),
+            chrono::Utc::now().timestamp_millis(),
+        )?;
+        self.shared
+            .balance(user_key)?
+            .ok_or_else(|| AppError::ViewerNotFound(user_key.to_string()))
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    let mut ticker = tokio::time::interval(WATCH_INTERVAL);
+    // 第一次立即触发，跳过
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => shared.on_event(&event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("积分处理不及时，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = ticker.tick() => shared.award_watch(),
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存投票记录的文件，位于应用数据目录
+const STORE_FILE: &str = "polls.json";
+const HISTORY_KEY: &str = "history";
+
+// 票数变化时发送给前端的事件，内容为当前的 Poll
+pub const PROGRESS_EVENT: &str = "poll://progress";
+// 投票结束时发送给前端的事件，内容为最终结果
+pub const ENDED_EVENT: &str = "poll://ended";
+
+// 保留的投票记录数量
+const MAX_HISTORY: usize = 50;
+// 选项数量的范围
+const MIN_OPTIONS: usize = 2;
+const MAX_OPTIONS: usize = 10;
+// 投票时长的上限，为 0 时需要手动结束
+const MAX_DURATION_SECS: u64 = 24 * 60 * 60;
+// 合并票数变化的间隔，弹幕密集时避免频繁推送
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+// SSE 广播通道容量，订阅者落后时只会错过中间的进度
+const CHANNEL_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollOption {
+    // 弹幕内容与该关键词相同时投给该选项，不区分大小写，例如 "1" 或 "A"
+    pub key: String,
+    // 为空时显示关键词
+    #[serde(default)]
+    pub label: String,
+}
+
+// 发起投票的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct StartPoll {
+    pub title: String,
+    pub options: Vec<PollOption>,
+    // 投票时长，为 0 时需要手动结束
+    #[serde(default)]
+    pub duration_secs: u64,
+    // 只统计该直播间的弹幕，未设置时统计所有直播间
+    #[serde(default)]
+    pub room_id: Option<u64>,
+    // 只统计不低于该大航海等级的用户，0 为所有人，3 为舰长，1 为总督
+    #[serde(default)]
+    pub min_guard_level: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollOptionResult {
+    pub key: String,
+    pub label: String,
+    pub votes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Poll {
+    pub id: String,
+    pub title: String,
+    pub room_id: Option<u64>,
+    pub min_guard_level: u8,
+    pub options: Vec<PollOptionResult>,
+    pub total_votes: u64,
+    // Unix 毫秒时间戳
+    pub started_at: i64,
+    // 预定结束的时间，需要手动结束时为空
+    pub ends_at: Option<i64>,
+    // 实际结束的时间，进行中时为空
+    pub ended_at: Option<i64>,
+}
+
+impl Poll {
+    pub fn is_active(&self) -> bool {
+        self.ended_at.is_none()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // 进行中或最近结束的投票
+    current: Option<Poll>,
+    // 已经投过票的用户
+    voters: HashSet<String>,
+    // 票数有变化但还没有推送
+    dirty: bool,
+    // 最近结束的在前
+    history: VecDeque<Poll>,
+}
+
+impl State {
+    fn active(&mut self) -> Option<&mut Poll> {
+        self.current.as_mut().filter(|poll| poll.is_active())
+    }
+
+    // 统计投票弹幕，每个用户只有第一票有效，票数有变化时返回 true
+    fn on_danmaku(&mut self, event: &DanmakuEvent) -> bool {
+        if event.kind != EventKind::Danmaku || event.replay {
+            return false;
+        }
+        let Some(voter) = event.user_key() else {
+            return false;
+        };
+        let Some(poll) = self.current.as_mut().filter(|poll| poll.is_active()) else {
+            return false;
+        };
+        if poll.room_id.is_some_and(|room_id| room_id != event.room_id) {
+            return false;
+        }
+        // 大航海等级数字越小等级越高，0 表示不是大航海
+        if poll.min_guard_level > 0
+            && (event.guard_level == 0 || event.guard_level > poll.min_guard_level)
+        {
+            return false;
+        }
+        let message = normalize(&event.message);
+        let Some(option) = poll
+            .options
+            .iter_mut()
+            .find(|option| normalize(&option.key) == message)
+        else {
+            return false;
+        };
+        if !self.voters.insert(voter) {
+            return false;
+        }
+        option.votes += 1;
+        poll.total_votes += 1;
+        true
+    }
+}
+
+struct Shared {
+    app: AppHandle,
+    store: Arc<Store<Wry>>,
+    state: Mutex<State>,
+    updates: broadcast::Sender<Poll>,
+}
+
+impl Shared {
+    // 推送当前投票的进度
+    fn publish(&self, poll: &Poll) {
+        let event = if poll.is_active() {
+            PROGRESS_EVENT
+        } else {
+            ENDED_EVENT
+        };
+        let _ = self.app.emit(event, poll);
+        // 没有 SSE 连接时发送会失败，直接忽略
+        let _ = self.updates.send(poll.clone());
+    }
+
+    // 结束进行中的投票，保存到投票记录并推送最终结果
+    fn end(&self, state: &mut State) -> Option<Poll> {
+        let poll = state.active()?;
+        poll.ended_at = Some(chrono::Utc::now().timestamp_millis());
+        let poll = poll.clone();
+        state.voters.clear();
+        state.dirty = false;
+        state.history.push_front(poll.clone());
+        state.history.truncate(MAX_HISTORY);
+        self.save(state);
+        log::info!("投票结束: {}，共 {} 票", poll.title, poll.total_votes);
+        self.publish(&poll);
+        Some(poll)
+    }
+
+    fn save(&self, state: &State) {
+        match serde_json::to_value(&state.history) {
+            Ok(value) => {
+                self.store.set(HISTORY_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存投票记录失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化投票记录失败: {}", err),
+        }
+    }
+}
+
+// 根据弹幕关键词统计投票，实时推送票数并保存投票记录
+pub struct PollManager {
+    shared: Arc<Shared>,
+}
+
+impl PollManager {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let history: VecDeque<Poll> = store
+            .get(HISTORY_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let state = State {
+            current: history.front().cloned(),
+            history,
+            ..Default::default()
+        };
+        let (updates, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            store,
+            state: Mutex::new(state),
+            updates,
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(PollManager { shared })
+    }
+
+    pub fn start(&self, poll: StartPoll) -> AppResult<Poll> {
+        let title = poll.title.trim().to_string();
+        if title.is_empty() {
+            return Err(AppError::InvalidConfig("投票标题不能为空".to_string()));
+        }
+        if !(MIN_OPTIONS..=MAX_OPTIONS).contains(&poll.options.len()) {
+            return Err(AppError::InvalidConfig(format!(
+                "投票选项数量应在 {} 到 {} 之间",
+                MIN_OPTIONS, MAX_OPTIONS
+            )));
+        }
+        if poll.duration_secs > MAX_DURATION_SECS {
+            return Err(AppError::InvalidConfig(
+                "投票时长不能超过 24 小时".to_string(),
+            ));
+        }
+        let mut keys = HashSet::new();
+        let mut options = Vec::with_capacity(poll.options.len());
+        for option in poll.options {
+            let key = option.key.trim().to_string();
+            if key.is_empty() {
+                return Err(AppError::InvalidConfig(
+                    "投票选项的关键词不能为空".to_string(),
+                ));
+            }
+            if !keys.insert(normalize(&key)) {
+                return Err(AppError::InvalidConfig(format!(
+                    "投票选项的关键词重复: {}",
+                    key
+                )));
+            }
+            let label = match option.label.trim() {
+                "" => key.clone(),
+                label => label.to_string(),
+            };
+            options.push(PollOptionResult {
+                key,
+                label,
+                votes: 0,
+            });
+        }
+
+        let mut state = self.shared.state.lock().unwrap();
+        if state.active().is_some() {
+            return Err(AppError::PollInProgress);
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        let poll = Poll {
+            id: uuid::Uuid::new_v4().to_string(),
+            title,
+            room_id: poll.room_id,
+            min_guard_level: poll.min_guard_level,
+            options,
+            total_votes: 0,
+            started_at: now,
+            ends_at: (poll.duration_secs > 0).then(|| now + poll.duration_secs as i64 * 1000),
+            ended_at: None,
+        };
+        state.current = Some(poll.clone());
+        state.voters.clear();
+        state.dirty = false;
+        log::info!("开始投票: {}", poll.title);
+        self.shared.publish(&poll);
+        Ok(poll)
+    }
+
+    // 提前结束进行中的投票
+    pub fn end(&self) -> AppResult<Poll> {
+        let mut state = self.shared.state.lock().unwrap();
+        self.shared.end(&mut state).ok_or(AppError::NoActivePoll)
+    }
+
+    // 进行中或最近结束的投票
+    pub fn current(&self) -> Option<Poll> {
+        self.shared.state.lock().unwrap().current.clone()
+    }
+
+    // 已结束的投票，按结束时间倒序排列
+    pub fn history(&self, limit: usize) -> Vec<Poll> {
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .history
+            .iter()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear_history(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.history.clear();
+        if state.active().is_none() {
+            state.current = None;
+        }
+        self.shared.save(&state);
+    }
+
+    // 订阅投票进度，用于 SSE 推送
+    pub fn subscribe(&self) -> broadcast::Receiver<Poll> {
+        self.shared.updates.subscribe()
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let mut state = shared.state.lock().unwrap();
+                    if state.on_danmaku(&event) {
+                        state.dirty = true;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("投票统计不及时，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = ticker.tick() => {
+                let mut state = shared.state.lock().unwrap();
+                let now = chrono::Utc::now().timestamp_millis();
+                let expired = state
+                    .active()
+                    .and_then(|poll| poll.ends_at)
+                    .is_some_and(|ends_at| ends_at <= now);
+                if expired {
+                    shared.end(&mut state);
+                } else if state.dirty {
+                    state.dirty = false;
+                    if let Some(poll) = state.active() {
+                        let poll = poll.clone();
+                        shared.publish(&poll);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// 比较关键词时忽略大小写和首尾空白
+fn normalize(text: &str) -> String {
+    text.trim().to_lowercase()
+}
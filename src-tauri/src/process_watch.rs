@@ -0,0 +1,279 @@
+use crate::danmaku::RoomManager;
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::Notify;
+
+// 保存进程监控配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "processes.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 监控的进程启动或退出时发送给前端的事件
+pub const CHANGED_EVENT: &str = "process://changed";
+
+// 检查间隔的范围，单位为秒
+const MIN_INTERVAL_SECS: u64 = 1;
+const MAX_INTERVAL_SECS: u64 = 300;
+
+// 一个需要监控的程序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedProcess {
+    // 显示给用户的名称
+    pub label: String,
+    // 进程名，不区分大小写，可以省略 .exe 后缀
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessWatchConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    pub processes: Vec<WatchedProcess>,
+}
+
+impl Default for ProcessWatchConfig {
+    fn default() -> Self {
+        ProcessWatchConfig {
+            enabled: true,
+            interval_secs: 5,
+            processes: vec![
+                WatchedProcess {
+                    label: "OBS".to_string(),
+                    names: vec!["obs64".to_string(), "obs32".to_string(), "obs".to_string()],
+                },
+                WatchedProcess {
+                    label: "直播姬".to_string(),
+                    names: vec!["livehime".to_string()],
+                },
+            ],
+        }
+    }
+}
+
+// 监控的程序当前是否在运行
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchedStatus {
+    pub label: String,
+    pub running: bool,
+}
+
+// 程序启动或退出的通知
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessChange {
+    pub label: String,
+    pub running: bool,
+    // 是否有正在获取弹幕的直播间，前端据此提示“获取弹幕时 OBS 已关闭”
+    pub fetching: bool,
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub exe: Option<String>,
+    // 使用率百分比，按单个核心计算，多核时可能超过 100
+    pub cpu_usage: f32,
+    // 内存，单位为字节
+    pub memory: u64,
+}
+
+struct Shared {
+    app: AppHandle,
+    config: RwLock<ProcessWatchConfig>,
+    system: Mutex<System>,
+    // 按 label 记录上一次检查时是否在运行
+    running: Mutex<HashMap<String, bool>>,
+    // 修改配置后唤醒监控任务立即检查
+    wake: Notify,
+}
+
+impl Shared {
+    fn refresh(&self) {
+        self.system.lock().unwrap().refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_cpu()
+                .with_memory()
+                .with_exe(UpdateKind::OnlyIfNotSet),
+        );
+    }
+
+    // 检查监控的程序，和上一次的结果不同时通知前端
+    fn check(&self) {
+        self.refresh();
+        let names: Vec<String> = self
+            .system
+            .lock()
+            .unwrap()
+            .processes()
+            .values()
+            .map(|process| normalize(&process.name().to_string_lossy()))
+            .collect();
+        let config = self.config.read().unwrap().clone();
+        let fetching = self
+            .app
+            .try_state::<RoomManager>()
+            .is_some_and(|rooms| !rooms.list_rooms().is_empty());
+
+        let mut running = self.running.lock().unwrap();
+        let mut current = HashMap::new();
+        for process in &config.processes {
+            let is_running = process
+                .names
+                .iter()
+                .map(|name| normalize(name))
+                .any(|name| names.contains(&name));
+            // 第一次检查只记录状态
+            if let Some(was_running) = running.get(&process.label) {
+                if *was_running != is_running {
+                    log::info!(
+                        "{} {}",
+                        process.label,
+                        if is_running { "已启动" } else { "已退出" }
+                    );
+                    let _ = self.app.emit(
+                        CHANGED_EVENT,
+                        &ProcessChange {
+                            label: process.label.clone(),
+                            running: is_running,
+                            fetching,
+                            timestamp: chrono::Utc::now().timestamp_millis(),
+                        },
+                    );
+                }
+            }
+            current.insert(process.label.clone(), is_running);
+        }
+        *running = current;
+    }
+}
+
+// 列出系统进程，并监控 OBS、直播姬等直播软件的启动和退出
+pub struct ProcessWatcher {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl ProcessWatcher {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: ProcessWatchConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            config: RwLock::new(config),
+            system: Mutex::new(System::new()),
+            running: Mutex::new(HashMap::new()),
+            wake: Notify::new(),
+        });
+        tauri::async_runtime::spawn(run(shared.clone()));
+        Ok(ProcessWatcher { shared, store })
+    }
+
+    pub fn get_config(&self) -> ProcessWatchConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: ProcessWatchConfig) -> AppResult<ProcessWatchConfig> {
+        if !(MIN_INTERVAL_SECS..=MAX_INTERVAL_SECS).contains(&config.interval_secs) {
+            return Err(AppError::InvalidConfig(format!(
+                "检查间隔必须在 {} 到 {} 秒之间",
+                MIN_INTERVAL_SECS, MAX_INTERVAL_SECS
+            )));
+        }
+        if config
+            .processes
+            .iter()
+            .any(|process| process.label.trim().is_empty() || process.names.is_empty())
+        {
+            return Err(AppError::InvalidConfig(
+                "监控的程序必须填写名称和进程名".to_string(),
+            ));
+        }
+
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存进程监控配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化进程监控配置失败: {}", err),
+        }
+        *self.shared.config.write().unwrap() = config.clone();
+        // 配置变化后重新记录状态，避免把新增的程序当作刚启动
+        self.shared.running.lock().unwrap().clear();
+        self.shared.wake.notify_one();
+        Ok(config)
+    }
+
+    // 上一次检查时各监控程序的状态
+    pub fn status(&self) -> Vec<WatchedStatus> {
+        let running = self.shared.running.lock().unwrap();
+        self.shared
+            .config
+            .read()
+            .unwrap()
+            .processes
+            .iter()
+            .map(|process| WatchedStatus {
+                label: process.label.clone(),
+                running: running.get(&process.label).copied().unwrap_or(false),
+            })
+            .collect()
+    }
+
+    // 所有进程，按内存占用从大到小排列
+    // CPU 使用率是和上一次刷新之间的平均值，第一次查询时为 0
+    pub fn list_processes(&self) -> Vec<ProcessInfo> {
+        self.shared.refresh();
+        let system = self.shared.system.lock().unwrap();
+        let mut processes: Vec<ProcessInfo> = system
+            .processes()
+            .values()
+            .map(|process| ProcessInfo {
+                pid: process.pid().as_u32(),
+                name: process.name().to_string_lossy().to_string(),
+                exe: process.exe().map(|exe| exe.to_string_lossy().to_string()),
+                cpu_usage: process.cpu_usage(),
+                memory: process.memory(),
+            })
+            .collect();
+        processes.sort_by(|a, b| b.memory.cmp(&a.memory));
+        processes
+    }
+}
+
+async fn run(shared: Arc<Shared>) {
+    loop {
+        let config = shared.config.read().unwrap().clone();
+        if config.enabled {
+            shared.check();
+        } else {
+            shared.running.lock().unwrap().clear();
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(config.interval_secs)) => {}
+            _ = shared.wake.notified() => {}
+        }
+    }
+}
+
+// 进程名统一为小写并去掉 .exe 后缀，方便在不同系统上比较
+fn normalize(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_suffix(".exe")
+        .map(str::to_string)
+        .unwrap_or(name)
+}
@@ -0,0 +1,418 @@
+use crate::error::{AppError, AppResult};
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_http::reqwest::{Client, ClientBuilder, Proxy, Url};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::handshake::client::Response;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+// 保存代理配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "proxy.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 测试代理时请求的地址
+const TEST_URL: &str = "https://api.live.bilibili.com/";
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 当前生效的代理地址，所有出站连接在建立时读取
+static CURRENT: RwLock<Option<Url>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyMode {
+    // 不使用代理
+    Direct,
+    // 使用系统代理设置，Windows 读取 Internet 选项，其他系统读取 HTTPS_PROXY 等环境变量
+    #[default]
+    System,
+    // 使用手动填写的代理
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyKind {
+    #[default]
+    Http,
+    Socks5,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    pub mode: ProxyMode,
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    // 代理需要认证时填写
+    pub username: String,
+    pub password: String,
+}
+
+impl ProxyConfig {
+    // 按配置得到代理地址，不使用代理时为空
+    pub fn url(&self) -> AppResult<Option<Url>> {
+        match self.mode {
+            ProxyMode::Direct => Ok(None),
+            ProxyMode::System => Ok(system_proxy()),
+            ProxyMode::Manual => {
+                let host = self.host.trim();
+                if host.is_empty() || self.port == 0 {
+                    return Err(AppError::InvalidConfig("请填写代理地址和端口".to_string()));
+                }
+                // socks5h 由代理服务器解析域名，可以绕过本地 DNS 污染
+                let scheme = match self.kind {
+                    ProxyKind::Http => "http",
+                    ProxyKind::Socks5 => "socks5h",
+                };
+                let invalid = || AppError::InvalidConfig(format!("无效的代理地址: {}", host));
+                let mut url = Url::parse(&format!("{}://{}:{}", scheme, host, self.port))
+                    .map_err(|_| invalid())?;
+                if !self.username.is_empty() {
+                    url.set_username(&self.username).map_err(|_| invalid())?;
+                    url.set_password(Some(&self.password))
+                        .map_err(|_| invalid())?;
+                }
+                Ok(Some(url))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyTestResult {
+    pub success: bool,
+    pub latency_ms: Option<u64>,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+// 全局代理设置，作用于 B 站接口、弹幕连接、事件上传、webhook 和更新检查
+pub struct ProxySettings {
+    config: RwLock<ProxyConfig>,
+    store: Arc<Store<Wry>>,
+}
+
+impl ProxySettings {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: ProxyConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        match config.url() {
+            Ok(url) => *CURRENT.write().unwrap() = url,
+            Err(err) => log::warn!("代理配置无效，不使用代理: {}", err),
+        }
+        Ok(ProxySettings {
+            config: RwLock::new(config),
+            store,
+        })
+    }
+
+    pub fn get_config(&self) -> ProxyConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    // 保存并立即生效，已经建立的连接在重连后使用新的代理
+    pub fn update_config(&self, config: ProxyConfig) -> AppResult<ProxyConfig> {
+        let url = config.url()?;
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存代理配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化代理配置失败: {}", err),
+        }
+        *CURRENT.write().unwrap() = url;
+        *self.config.write().unwrap() = config.clone();
+        Ok(config)
+    }
+}
+
+// 当前生效的代理地址
+pub fn current() -> Option<Url> {
+    CURRENT.read().unwrap().clone()
+}
+
+// 使用全局代理和 DNS 设置的 HTTP 客户端，设置在每次请求时读取，修改后不需要重新创建客户端
+pub fn client_builder() -> ClientBuilder {
+    Client::builder()
+        .proxy(Proxy::custom(|url| {
+            if is_local(url.host_str().unwrap_or_default()) {
+                return None;
+            }
+            current()
+        }))
+        .dns_resolver(Arc::new(crate::dns::Resolver))
+}
+
+// 用指定的配置请求 B 站接口，检查代理是否可用
+pub async fn test(config: &ProxyConfig) -> AppResult<ProxyTestResult> {
+    let mut builder = Client::builder().timeout(TEST_TIMEOUT);
+    builder = match config.url()? {
+        Some(url) => builder.proxy(Proxy::all(url)?),
+        None => builder.no_proxy(),
+    };
+    let client = builder.build()?;
+    let started = Instant::now();
+    Ok(match client.get(TEST_URL).send().await {
+        Ok(response) => ProxyTestResult {
+            success: true,
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            status: Some(response.status().as_u16()),
+            error: None,
+        },
+        Err(err) => ProxyTestResult {
+            success: false,
+            latency_ms: None,
+            status: None,
+            error: Some(err.to_string()),
+        },
+    })
+}
+
+// 通过当前代理建立 WebSocket 连接，不使用代理时按 DNS 设置解析后直接连接
+pub async fn connect_websocket(
+    url: &str,
+) -> Result<
+    (WebSocketStream<MaybeTlsStream<TcpStream>>, Response),
+    tokio_tungstenite::tungstenite::Error,
+> {
+    let target = Url::parse(url).map_err(|_| invalid_input(format!("无效的地址: {}", url)))?;
+    let host = target
+        .host_str()
+        .ok_or_else(|| invalid_input(format!("无效的地址: {}", url)))?;
+    let port = target.port_or_known_default().unwrap_or(443);
+    let stream = match current() {
+        Some(proxy) if !is_local(host) => connect_tcp(&proxy, host, port).await?,
+        _ => connect_direct(host, port).await?,
+    };
+    tokio_tungstenite::client_async_tls(url, stream).await
+}
+
+// 依次尝试解析到的地址
+async fn connect_direct(host: &str, port: u16) -> io::Result<TcpStream> {
+    let addrs = crate::dns::resolve(host, port)
+        .await
+        .map_err(|err| io::Error::new(io::ErrorKind::NotFound, err.to_string()))?;
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect(addr).await {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "没有可用的地址")))
+}
+
+// 连接到代理服务器并建立到目标地址的隧道
+async fn connect_tcp(proxy: &Url, host: &str, port: u16) -> io::Result<TcpStream> {
+    let proxy_host = proxy
+        .host_str()
+        .ok_or_else(|| invalid_input("代理地址缺少主机名".to_string()))?;
+    let proxy_port = proxy.port_or_known_default().unwrap_or(1080);
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+    let username = percent_decode_str(proxy.username()).decode_utf8_lossy();
+    let password = percent_decode_str(proxy.password().unwrap_or_default()).decode_utf8_lossy();
+    match proxy.scheme() {
+        "http" => http_connect(&mut stream, host, port, &username, &password).await?,
+        "socks5" | "socks5h" => {
+            socks5_connect(&mut stream, host, port, &username, &password).await?
+        }
+        scheme => return Err(invalid_input(format!("不支持的代理类型: {}", scheme))),
+    }
+    Ok(stream)
+}
+
+// HTTP 代理使用 CONNECT 方法建立隧道
+async fn http_connect(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+) -> io::Result<()> {
+    let mut request = format!(
+        "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n",
+        host = host,
+        port = port
+    );
+    if !username.is_empty() {
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // 逐字节读取响应头，避免读走隧道中的数据
+    let mut header = Vec::new();
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() > 8192 {
+            return Err(proxy_error("代理服务器响应头过长".to_string()));
+        }
+        header.push(stream.read_u8().await?);
+    }
+    let header = String::from_utf8_lossy(&header);
+    let status = header
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or_default();
+    if status != "200" {
+        return Err(proxy_error(format!(
+            "代理服务器拒绝连接: {}",
+            header.lines().next().unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
+
+// SOCKS5 握手，支持无认证和用户名密码认证，域名由代理服务器解析
+async fn socks5_connect(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+) -> io::Result<()> {
+    let methods: &[u8] = if username.is_empty() {
+        &[0x00]
+    } else {
+        &[0x00, 0x02]
+    };
+    let mut greeting = vec![0x05, methods.len() as u8];
+    greeting.extend_from_slice(methods);
+    stream.write_all(&greeting).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    match reply {
+        [0x05, 0x00] => {}
+        [0x05, 0x02] => {
+            if username.len() > 255 || password.len() > 255 {
+                return Err(invalid_input("代理用户名或密码过长".to_string()));
+            }
+            let mut auth = vec![0x01, username.len() as u8];
+            auth.extend_from_slice(username.as_bytes());
+            auth.push(password.len() as u8);
+            auth.extend_from_slice(password.as_bytes());
+            stream.write_all(&auth).await?;
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0x00 {
+                return Err(proxy_error("代理认证失败".to_string()));
+            }
+        }
+        _ => return Err(proxy_error("代理服务器不支持的认证方式".to_string())),
+    }
+
+    if host.len() > 255 {
+        return Err(invalid_input("目标主机名过长".to_string()));
+    }
+    let mut request = vec![0x05, 0x01, 0x00, 0x03, host.len() as u8];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await?;
+    if head[1] != 0x00 {
+        return Err(proxy_error(format!(
+            "代理服务器拒绝连接 (错误码 {})",
+            head[1]
+        )));
+    }
+    // 读完代理返回的绑定地址和端口
+    let address_len = match head[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        _ => return Err(proxy_error("代理服务器返回了无效的地址类型".to_string())),
+    };
+    let mut rest = vec![0u8; address_len + 2];
+    stream.read_exact(&mut rest).await?;
+    Ok(())
+}
+
+// 本机地址不经过代理，例如本地的 webhook 接收端
+fn is_local(host: &str) -> bool {
+    matches!(host, "localhost" | "127.0.0.1" | "::1" | "[::1]")
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn proxy_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionRefused, message)
+}
+
+// 读取系统代理，没有设置时为空
+fn system_proxy() -> Option<Url> {
+    #[cfg(windows)]
+    if let Some(url) = windows_proxy() {
+        return Some(url);
+    }
+    [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+    ]
+    .iter()
+    .filter_map(|name| std::env::var(name).ok())
+    .find_map(|value| parse_proxy(&value))
+}
+
+// Internet 选项中的代理，格式为 host:port 或 http=host:port;https=host:port
+#[cfg(windows)]
+fn windows_proxy() -> Option<Url> {
+    let key = windows_registry::CURRENT_USER
+        .open(r"Software\Microsoft\Windows\CurrentVersion\Internet Settings")
+        .ok()?;
+    if key.get_u32("ProxyEnable").ok()? == 0 {
+        return None;
+    }
+    let server = key.get_string("ProxyServer").ok()?;
+    if !server.contains('=') {
+        return parse_proxy(&server);
+    }
+    let entries: Vec<(&str, &str)> = server
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .collect();
+    ["https", "http", "socks"].iter().find_map(|kind| {
+        let (_, address) = entries
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(kind))?;
+        if *kind == "socks" {
+            parse_proxy(&format!("socks5://{}", address))
+        } else {
+            parse_proxy(address)
+        }
+    })
+}
+
+fn parse_proxy(value: &str) -> Option<Url> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if value.contains("://") {
+        Url::parse(value).ok()
+    } else {
+        Url::parse(&format!("http://{}", value)).ok()
+    }
+}
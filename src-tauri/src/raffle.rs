@@ -0,0 +1,541 @@
+use crate::danmaku::{self, DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::tts::TtsManager;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存抽奖配置和开奖记录的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "raffles.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+const HISTORY_KEY: &str = "history";
+
+// 参与人数变化或抽奖开始、取消时发送给前端的事件，内容为当前的 RaffleStatus
+pub const STATE_EVENT: &str = "raffle://state";
+// 开奖时发送给前端的事件，内容为 RaffleDraw
+pub const WINNERS_EVENT: &str = "raffle://winners";
+
+// 开奖记录中写明的随机数算法，使用相同的种子和参与者列表可以复现结果
+const RNG_ALGORITHM: &str = "rand 0.8 StdRng (ChaCha12), seed_from_u64, partial Fisher-Yates";
+
+// 保留的开奖记录数量
+const MAX_HISTORY: usize = 50;
+// 单次抽取的人数上限
+const MAX_WINNERS: u32 = 100;
+// 抽奖时长的上限，为 0 时需要手动开奖
+const MAX_DURATION_SECS: u64 = 24 * 60 * 60;
+// 检查是否到时间开奖以及合并参与人数变化的间隔
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+// 抽奖配置，对所有抽奖生效
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RaffleConfig {
+    // 不能参与抽奖的用户，可以填写 uid、open_id 或用户名
+    pub blocklist: Vec<String>,
+    // 开奖后朗读中奖名单
+    pub announce_tts: bool,
+    // 朗读的模板，{{winners}} 为中奖用户名，{{title}} 为抽奖标题
+    pub announce_template: String,
+}
+
+impl Default for RaffleConfig {
+    fn default() -> Self {
+        RaffleConfig {
+            blocklist: Vec::new(),
+            announce_tts: false,
+            announce_template: "恭喜 {{winners}} 在{{title}}中奖".to_string(),
+        }
+    }
+}
+
+// 参与抽奖的条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntryRule {
+    // 发送与关键词相同的弹幕，不区分大小写
+    Keyword { keyword: String },
+    // 抽奖期间送出的礼物、醒目留言和大航海累计不低于该金额，单位为元
+    Gift { min_price: f64 },
+}
+
+// 发起抽奖的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct StartRaffle {
+    pub title: String,
+    pub rule: EntryRule,
+    // 中奖人数
+    pub winners: u32,
+    // 抽奖时长，为 0 时需要手动开奖
+    #[serde(default)]
+    pub duration_secs: u64,
+    // 只统计该直播间的事件，未设置时统计所有直播间
+    #[serde(default)]
+    pub room_id: Option<u64>,
+    // 只接受不低于该大航海等级的用户，0 为所有人，3 为舰长，1 为总督
+    #[serde(default)]
+    pub min_guard_level: u8,
+    // 指定随机数种子，未设置时随机生成，用于复现开奖结果
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entrant {
+    pub uid: u64,
+    pub open_id: Option<String>,
+    pub uname: String,
+    // 第一次满足或尝试满足参与条件的 Unix 毫秒时间戳
+    pub joined_at: i64,
+    // 抽奖期间累计送出的金额，关键词抽奖中为 0
+    pub value: f64,
+}
+
+impl Entrant {
+    fn same_user(&self, event: &DanmakuEvent) -> bool {
+        event
+            .user_key()
+            .is_some_and(|key| danmaku::user_key(self.uid, self.open_id.as_deref()) == Some(key))
+    }
+
+    fn is_blocked(&self, blocklist: &HashSet<String>) -> bool {
+        (self.uid != 0 && blocklist.contains(&self.uid.to_string()))
+            || self
+                .open_id
+                .as_ref()
+                .is_some_and(|open_id| blocklist.contains(open_id))
+            || blocklist.contains(&self.uname)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Raffle {
+    pub id: String,
+    pub title: String,
+    pub rule: EntryRule,
+    pub winners: u32,
+    pub room_id: Option<u64>,
+    pub min_guard_level: u8,
+    // Unix 毫秒时间戳
+    pub started_at: i64,
+    // 预定开奖的时间，需要手动开奖时为空
+    pub ends_at: Option<i64>,
+    // 按参与顺序排列
+    pub entrants: Vec<Entrant>,
+    #[serde(skip)]
+    seed: Option<u64>,
+}
+
+impl Raffle {
+    fn on_event(&mut self, event: &DanmakuEvent) -> bool {
+        if event.replay || self.room_id.is_some_and(|room_id| room_id != event.room_id) {
+            return false;
+        }
+        if event.uid == 0 && event.open_id.is_none() {
+            return false;
+        }
+        // 大航海等级数字越小等级越高，0 表示不是大航海
+        if self.min_guard_level > 0
+            && (event.guard_level == 0 || event.guard_level > self.min_guard_level)
+        {
+            return false;
+        }
+        let value = match &self.rule {
+            EntryRule::Keyword { keyword } => {
+                if event.kind != EventKind::Danmaku
+                    || event.message.trim().to_lowercase() != keyword.to_lowercase()
+                {
+                    return false;
+                }
+                0.0
+            }
+            EntryRule::Gift { .. } => {
+                if !matches!(
+                    event.kind,
+                    EventKind::Gift | EventKind::SuperChat | EventKind::Guard
+                ) || event.price <= 0.0
+                {
+                    return false;
+                }
+                event.price
+            }
+        };
+        match self
+            .entrants
+            .iter_mut()
+            .find(|entrant| entrant.same_user(event))
+        {
+            Some(entrant) => {
+                if value <= 0.0 {
+                    return false;
+                }
+                entrant.value += value;
+                entrant.uname = event.uname.clone();
+            }
+            None => self.entrants.push(Entrant {
+                uid: event.uid,
+                open_id: event.open_id.clone(),
+                uname: event.uname.clone(),
+                joined_at: event.timestamp,
+                value,
+            }),
+        }
+        true
+    }
+
+    // 满足条件的参与者，保持参与顺序
+    fn eligible(&self, blocklist: &HashSet<String>) -> (Vec<Entrant>, usize) {
+        let mut excluded = 0;
+        let eligible = self
+            .entrants
+            .iter()
+            .filter(|entrant| {
+                let qualified = match &self.rule {
+                    EntryRule::Keyword { .. } => true,
+                    EntryRule::Gift { min_price } => entrant.value + f64::EPSILON >= *min_price,
+                };
+                let blocked = entrant.is_blocked(blocklist);
+                if qualified && blocked {
+                    excluded += 1;
+                }
+                qualified && !blocked
+            })
+            .cloned()
+            .collect();
+        (eligible, excluded)
+    }
+}
+
+// 一次开奖的完整记录，包含复现结果需要的全部信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaffleDraw {
+    pub id: String,
+    pub title: String,
+    pub rule: EntryRule,
+    pub room_id: Option<u64>,
+    pub started_at: i64,
+    pub drawn_at: i64,
+    pub seed: u64,
+    pub algorithm: String,
+    // 参与抽签的用户，按参与顺序排列，已去掉黑名单和未满足条件的用户
+    pub entrants: Vec<Entrant>,
+    // 因黑名单排除的人数
+    pub excluded: usize,
+    // 按抽中的顺序排列
+    pub winners: Vec<Entrant>,
+}
+
+// 进行中的抽奖和最近一次开奖结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RaffleStatus {
+    pub current: Option<Raffle>,
+    pub last_draw: Option<RaffleDraw>,
+}
+
+#[derive(Default)]
+struct State {
+    config: RaffleConfig,
+    current: Option<Raffle>,
+    // 参与人数有变化但还没有推送
+    dirty: bool,
+    // 最近开奖的在前
+    history: VecDeque<RaffleDraw>,
+}
+
+impl State {
+    fn status(&self) -> RaffleStatus {
+        RaffleStatus {
+            current: self.current.clone(),
+            last_draw: self.history.front().cloned(),
+        }
+    }
+}
+
+struct Shared {
+    app: AppHandle,
+    store: Arc<Store<Wry>>,
+    state: Mutex<State>,
+}
+
+impl Shared {
+    fn emit_state(&self, state: &State) {
+        let _ = self.app.emit(STATE_EVENT, &state.status());
+    }
+
+    // 结束进行中的抽奖并开奖，保存开奖记录
+    fn draw(&self, state: &mut State) -> Option<RaffleDraw> {
+        let raffle = state.current.take()?;
+        state.dirty = false;
+        let blocklist: HashSet<String> = state
+            .config
+            .blocklist
+            .iter()
+            .map(|entry| entry.trim().to_string())
+            .collect();
+        let (entrants, excluded) = raffle.eligible(&blocklist);
+        let seed = raffle.seed.unwrap_or_else(rand::random);
+
+        // 部分 Fisher-Yates 洗牌，前 n 个即为中奖者
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut order = entrants.clone();
+        let count = (raffle.winners as usize).min(order.len());
+        for i in 0..count {
+            let j = rng.gen_range(i..order.len());
+            order.swap(i, j);
+        }
+        order.truncate(count);
+
+        let draw = RaffleDraw {
+            id: raffle.id,
+            title: raffle.title,
+            rule: raffle.rule,
+            room_id: raffle.room_id,
+            started_at: raffle.started_at,
+            drawn_at: chrono::Utc::now().timestamp_millis(),
+            seed,
+            algorithm: RNG_ALGORITHM.to_string(),
+            entrants,
+            excluded,
+            winners: order,
+        };
+        state.history.push_front(draw.clone());
+        state.history.truncate(MAX_HISTORY);
+        self.save_history(state);
+        log::info!(
+            "抽奖开奖: {}，{} 人参与，中奖: {}，种子: {}",
+            draw.title,
+            draw.entrants.len(),
+            winner_names(&draw),
+            draw.seed
+        );
+        let _ = self.app.emit(WINNERS_EVENT, &draw);
+        self.emit_state(state);
+        if state.config.announce_tts && !draw.winners.is_empty() {
+            if let Some(tts) = self.app.try_state::<TtsManager>() {
+                let text = state
+                    .config
+                    .announce_template
+                    .replace("{{winners}}", &winner_names(&draw))
+                    .replace("{{title}}", &draw.title);
+                tts.speak(text);
+            }
+        }
+        Some(draw)
+    }
+
+    fn save_history(&self, state: &State) {
+        match serde_json::to_value(&state.history) {
+            Ok(value) => {
+                self.store.set(HISTORY_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存开奖记录失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化开奖记录失败: {}", err),
+        }
+    }
+}
+
+// 从弹幕关键词或礼物收集抽奖参与者，按可复现的随机数开奖并保存开奖记录
+pub struct RaffleManager {
+    shared: Arc<Shared>,
+}
+
+impl RaffleManager {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let state = State {
+            config: load(&store, CONFIG_KEY),
+            history: load(&store, HISTORY_KEY),
+            ..Default::default()
+        };
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            store,
+            state: Mutex::new(state),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(RaffleManager { shared })
+    }
+
+    pub fn get_config(&self) -> RaffleConfig {
+        self.shared.state.lock().unwrap().config.clone()
+    }
+
+    pub fn update_config(&self, config: RaffleConfig) -> AppResult<RaffleConfig> {
+        let mut blocklist: Vec<String> = Vec::new();
+        for entry in config.blocklist {
+            let entry = entry.trim().to_string();
+            if !entry.is_empty() && !blocklist.contains(&entry) {
+                blocklist.push(entry);
+            }
+        }
+        let config = RaffleConfig {
+            blocklist,
+            ..config
+        };
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.shared.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.shared.store.save() {
+                    log::warn!("保存抽奖配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化抽奖配置失败: {}", err),
+        }
+        self.shared.state.lock().unwrap().config = config.clone();
+        Ok(config)
+    }
+
+    pub fn start(&self, raffle: StartRaffle) -> AppResult<RaffleStatus> {
+        let title = raffle.title.trim().to_string();
+        if title.is_empty() {
+            return Err(AppError::InvalidConfig("抽奖标题不能为空".to_string()));
+        }
+        if !(1..=MAX_WINNERS).contains(&raffle.winners) {
+            return Err(AppError::InvalidConfig(format!(
+                "中奖人数应在 1 到 {} 之间",
+                MAX_WINNERS
+            )));
+        }
+        if raffle.duration_secs > MAX_DURATION_SECS {
+            return Err(AppError::InvalidConfig(
+                "抽奖时长不能超过 24 小时".to_string(),
+            ));
+        }
+        let rule = match raffle.rule {
+            EntryRule::Keyword { keyword } => {
+                let keyword = keyword.trim().to_string();
+                if keyword.is_empty() {
+                    return Err(AppError::InvalidConfig("抽奖关键词不能为空".to_string()));
+                }
+                EntryRule::Keyword { keyword }
+            }
+            EntryRule::Gift { min_price } => {
+                if min_price.is_nan() || min_price <= 0.0 {
+                    return Err(AppError::InvalidConfig(
+                        "礼物抽奖的金额门槛应大于 0".to_string(),
+                    ));
+                }
+                EntryRule::Gift { min_price }
+            }
+        };
+
+        let mut state = self.shared.state.lock().unwrap();
+        if state.current.is_some() {
+            return Err(AppError::RaffleInProgress);
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        state.current = Some(Raffle {
+            id: uuid::Uuid::new_v4().to_string(),
+            title,
+            rule,
+            winners: raffle.winners,
+            room_id: raffle.room_id,
+            min_guard_level: raffle.min_guard_level,
+            started_at: now,
+            ends_at: (raffle.duration_secs > 0).then(|| now + raffle.duration_secs as i64 * 1000),
+            entrants: Vec::new(),
+            seed: raffle.seed,
+        });
+        state.dirty = false;
+        self.shared.emit_state(&state);
+        Ok(state.status())
+    }
+
+    // 提前结束收集并开奖
+    pub fn draw(&self) -> AppResult<RaffleDraw> {
+        let mut state = self.shared.state.lock().unwrap();
+        self.shared.draw(&mut state).ok_or(AppError::NoActiveRaffle)
+    }
+
+    // 取消进行中的抽奖，不开奖也不保存记录
+    pub fn cancel(&self) -> AppResult<RaffleStatus> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.current.take().is_none() {
+            return Err(AppError::NoActiveRaffle);
+        }
+        state.dirty = false;
+        self.shared.emit_state(&state);
+        Ok(state.status())
+    }
+
+    pub fn status(&self) -> RaffleStatus {
+        self.shared.state.lock().unwrap().status()
+    }
+
+    // 开奖记录，按开奖时间倒序排列
+    pub fn history(&self, limit: usize) -> Vec<RaffleDraw> {
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .history
+            .iter()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    let mut ticker = tokio::time::interval(TICK_INTERVAL);
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let mut state = shared.state.lock().unwrap();
+                    if let Some(raffle) = state.current.as_mut() {
+                        if raffle.on_event(&event) {
+                            state.dirty = true;
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("抽奖统计不及时，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = ticker.tick() => {
+                let mut state = shared.state.lock().unwrap();
+                let now = chrono::Utc::now().timestamp_millis();
+                let expired = state
+                    .current
+                    .as_ref()
+                    .and_then(|raffle| raffle.ends_at)
+                    .is_some_and(|ends_at| ends_at <= now);
+                if expired {
+                    shared.draw(&mut state);
+                } else if state.dirty {
+                    state.dirty = false;
+                    shared.emit_state(&state);
+                }
+            }
+        }
+    }
+}
+
+fn winner_names(draw: &RaffleDraw) -> String {
+    draw.winners
+        .iter()
+        .map(|winner| winner.uname.as_str())
+        .collect::<Vec<_>>()
+        .join("、")
+}
+
+fn load<T: serde::de::DeserializeOwned + Default>(store: &Store<Wry>, key: &str) -> T {
+    store
+        .get(key)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
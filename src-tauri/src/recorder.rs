@@ -0,0 +1,213 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+mod ass;
+mod xml;
+
+use ass::AssWriter;
+use xml::XmlWriter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    Xml,
+    Ass,
+}
+
+// 开始录制的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordingOptions {
+    pub room_id: u64,
+    // 直播录像开始的 Unix 毫秒时间戳，弹幕时间相对它计算，为空时使用当前时间
+    #[serde(default)]
+    pub start_time: Option<i64>,
+    // 保存弹幕文件的文件夹
+    pub dir: String,
+    pub formats: Vec<RecordFormat>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingInfo {
+    pub id: String,
+    pub room_id: u64,
+    pub start_time: i64,
+    pub xml_path: Option<String>,
+    pub ass_path: Option<String>,
+    // 已写入的弹幕数量
+    pub count: u64,
+}
+
+struct Recording {
+    info: RecordingInfo,
+    xml: Option<XmlWriter>,
+    ass: Option<AssWriter>,
+}
+
+impl Recording {
+    fn write(&mut self, event: &DanmakuEvent) {
+        // 录像开始之前的弹幕无法对齐，直接跳过
+        let offset = event.timestamp - self.info.start_time;
+        if offset < 0 {
+            return;
+        }
+        if let Some(xml) = &mut self.xml {
+            if let Err(err) = xml.write(event, offset) {
+                log::warn!("写入弹幕 XML 失败: {}", err);
+            }
+        }
+        if let Some(ass) = &mut self.ass {
+            if let Err(err) = ass.write(event, offset) {
+                log::warn!("写入弹幕 ASS 失败: {}", err);
+            }
+        }
+        self.info.count += 1;
+    }
+
+    fn finish(self) -> RecordingInfo {
+        if let Some(xml) = self.xml {
+            if let Err(err) = xml.finish() {
+                log::warn!("写入弹幕 XML 失败: {}", err);
+            }
+        }
+        if let Some(ass) = self.ass {
+            if let Err(err) = ass.finish() {
+                log::warn!("写入弹幕 ASS 失败: {}", err);
+            }
+        }
+        self.info
+    }
+}
+
+// 把直播间的弹幕和醒目留言写入 B 站 XML 或 ASS 字幕文件，配合直播录像回放
+pub struct DanmakuRecorder {
+    recordings: Arc<Mutex<HashMap<String, Recording>>>,
+}
+
+impl DanmakuRecorder {
+    pub fn new(events: Subscriber<DanmakuEvent>) -> Self {
+        let recordings = Arc::new(Mutex::new(HashMap::new()));
+        tauri::async_runtime::spawn(run(recordings.clone(), events));
+        DanmakuRecorder { recordings }
+    }
+
+    pub fn start(&self, options: RecordingOptions) -> AppResult<RecordingInfo> {
+        if options.formats.is_empty() {
+            return Err(AppError::InvalidConfig(
+                "至少选择一种弹幕文件格式".to_string(),
+            ));
+        }
+        let dir = Path::new(&options.dir);
+        if !dir.is_dir() {
+            return Err(AppError::FolderNotFound(options.dir.clone()));
+        }
+        let start_time = options
+            .start_time
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        let name = format!(
+            "{}_{}",
+            options.room_id,
+            Local
+                .timestamp_millis_opt(start_time)
+                .single()
+                .unwrap_or_else(Local::now)
+                .format("%Y%m%d_%H%M%S")
+        );
+
+        let mut recording = Recording {
+            info: RecordingInfo {
+                id: uuid::Uuid::new_v4().to_string(),
+                room_id: options.room_id,
+                start_time,
+                xml_path: None,
+                ass_path: None,
+                count: 0,
+            },
+            xml: None,
+            ass: None,
+        };
+        if options.formats.contains(&RecordFormat::Xml) {
+            let path = dir.join(format!("{}.xml", name));
+            recording.xml = Some(XmlWriter::create(&path, options.room_id)?);
+            recording.info.xml_path = Some(path.to_string_lossy().to_string());
+        }
+        if options.formats.contains(&RecordFormat::Ass) {
+            let path = dir.join(format!("{}.ass", name));
+            recording.ass = Some(AssWriter::create(&path)?);
+            recording.info.ass_path = Some(path.to_string_lossy().to_string());
+        }
+
+        let info = recording.info.clone();
+        self.recordings
+            .lock()
+            .unwrap()
+            .insert(info.id.clone(), recording);
+        Ok(info)
+    }
+
+    // 结束录制并写入文件结尾
+    pub fn stop(&self, id: &str) -> AppResult<RecordingInfo> {
+        let recording = self
+            .recordings
+            .lock()
+            .unwrap()
+            .remove(id)
+            .ok_or_else(|| AppError::RecordingNotFound(id.to_string()))?;
+        Ok(recording.finish())
+    }
+
+    // 结束所有录制，退出程序前调用，保证 XML 文件完整
+    pub fn stop_all(&self) -> Vec<RecordingInfo> {
+        self.recordings
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, recording)| recording.finish())
+            .collect()
+    }
+
+    pub fn list(&self) -> Vec<RecordingInfo> {
+        let mut recordings: Vec<RecordingInfo> = self
+            .recordings
+            .lock()
+            .unwrap()
+            .values()
+            .map(|recording| recording.info.clone())
+            .collect();
+        recordings.sort_by_key(|info| info.start_time);
+        recordings
+    }
+}
+
+async fn run(
+    recordings: Arc<Mutex<HashMap<String, Recording>>>,
+    mut events: Subscriber<DanmakuEvent>,
+) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                log::warn!("弹幕录制处理不及时，跳过了 {} 个事件", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if event.replay
+            || event.simulated
+            || !matches!(event.kind, EventKind::Danmaku | EventKind::SuperChat)
+        {
+            continue;
+        }
+        for recording in recordings.lock().unwrap().values_mut() {
+            if recording.info.room_id == event.room_id {
+                recording.write(&event);
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// 按 1080p 画面布局，播放器会按比例缩放
+const WIDTH: i64 = 1920;
+const HEIGHT: i64 = 1080;
+const FONT_SIZE: i64 = 48;
+const LINE_HEIGHT: i64 = 56;
+// 滚动弹幕只占用画面上半部分，避免遮挡直播内容
+const SCROLL_LANES: usize = (HEIGHT as usize / 2) / LINE_HEIGHT as usize;
+const TOP_LANES: usize = 4;
+// 滚动弹幕从右侧进入到完全离开画面的时间
+const SCROLL_DURATION_MS: i64 = 8000;
+// 顶部弹幕（醒目留言）的显示时间
+const TOP_DURATION_MS: i64 = 10000;
+const SUPER_CHAT_COLOR: &str = "&H0000D7FF";
+
+const HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+Collisions: Normal
+PlayResX: 1920
+PlayResY: 1080
+WrapStyle: 2
+ScaledBorderAndShadow: yes
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Danmaku,Microsoft YaHei,48,&H30FFFFFF,&H30FFFFFF,&H30000000,&H00000000,1,0,0,0,100,100,0,0,1,1.5,0,7,0,0,0,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+// ASS 字幕格式，滚动弹幕用 \move 实现，按轨道排列避免重叠
+pub(super) struct AssWriter {
+    out: BufWriter<File>,
+    // 每条滚动轨道上一条弹幕尾部完全进入画面的时间
+    scroll_lanes: [i64; SCROLL_LANES],
+    // 每条顶部轨道上一条弹幕消失的时间
+    top_lanes: [i64; TOP_LANES],
+    count: u64,
+}
+
+impl AssWriter {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        // 写入 BOM，部分播放器需要它识别 UTF-8 编码
+        out.write_all(b"\xEF\xBB\xBF")?;
+        out.write_all(HEADER.as_bytes())?;
+        out.flush()?;
+        Ok(AssWriter {
+            out,
+            scroll_lanes: [i64::MIN; SCROLL_LANES],
+            top_lanes: [i64::MIN; TOP_LANES],
+            count: 0,
+        })
+    }
+
+    // offset 为相对直播开始的毫秒数
+    pub fn write(&mut self, event: &DanmakuEvent, offset: i64) -> io::Result<()> {
+        let text = escape(&event.message);
+        let line = match event.kind {
+            EventKind::SuperChat => {
+                let lane = pick_lane(&mut self.top_lanes, offset, offset + TOP_DURATION_MS);
+                let y = lane as i64 * LINE_HEIGHT;
+                format!(
+                    "Dialogue: 1,{},{},Danmaku,,0,0,0,,{{\\an8\\pos({},{})\\c{}}}{}: {}",
+                    format_time(offset),
+                    format_time(offset + TOP_DURATION_MS),
+                    WIDTH / 2,
+                    y,
+                    SUPER_CHAT_COLOR,
+                    escape(&event.uname),
+                    text,
+                )
+            }
+            _ => {
+                let width = text_width(&text);
+                // 弹幕尾部完全进入画面后，同一轨道才能放下一条
+                let distance = WIDTH + width;
+                let entered = offset + width * SCROLL_DURATION_MS / distance;
+                let lane = pick_lane(&mut self.scroll_lanes, offset, entered);
+                let y = lane as i64 * LINE_HEIGHT;
+                format!(
+                    "Dialogue: 0,{},{},Danmaku,,0,0,0,,{{\\move({},{},{},{})}}{}",
+                    format_time(offset),
+                    format_time(offset + SCROLL_DURATION_MS),
+                    WIDTH,
+                    y,
+                    -width,
+                    y,
+                    text,
+                )
+            }
+        };
+        writeln!(self.out, "{}", line)?;
+        self.count += 1;
+        self.out.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.out.flush()?;
+        Ok(self.count)
+    }
+}
+
+// 选择第一条空闲的轨道，都被占用时选择最早空闲的一条
+fn pick_lane(lanes: &mut [i64], start: i64, busy_until: i64) -> usize {
+    let lane = lanes
+        .iter()
+        .position(|free_at| *free_at <= start)
+        .unwrap_or_else(|| {
+            lanes
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, free_at)| **free_at)
+                .map(|(index, _)| index)
+                .unwrap_or(0)
+        });
+    lanes[lane] = busy_until;
+    lane
+}
+
+// 估算文字宽度，全角字符按字号计算，半角字符按一半计算
+fn text_width(text: &str) -> i64 {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii() {
+                FONT_SIZE / 2
+            } else {
+                FONT_SIZE
+            }
+        })
+        .sum()
+}
+
+// ASS 时间格式 H:MM:SS.cc
+fn format_time(ms: i64) -> String {
+    let cs = ms.max(0) / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+// 花括号和反斜杠在 ASS 中有特殊含义，替换为全角字符
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '{' => '｛',
+            '}' => '｝',
+            '\\' => '＼',
+            '\n' | '\r' | '\t' => ' ',
+            c => c,
+        })
+        .collect()
+}
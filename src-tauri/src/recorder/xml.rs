@@ -0,0 +1,84 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// 弹幕字号和颜色使用 B 站默认值
+const FONT_SIZE: u32 = 25;
+const WHITE: u32 = 0xFFFFFF;
+// 醒目留言显示为金色的顶部弹幕
+const SUPER_CHAT_COLOR: u32 = 0xFFD700;
+const MODE_SCROLL: u8 = 1;
+const MODE_TOP: u8 = 5;
+
+// B 站弹幕 XML 格式，与网页端下载的弹幕文件兼容
+// 每条弹幕写入后立即刷新，程序意外退出时只缺少结尾的 </i>
+pub(super) struct XmlWriter {
+    out: BufWriter<File>,
+    count: u64,
+}
+
+impl XmlWriter {
+    pub fn create(path: &Path, room_id: u64) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, "<i>")?;
+        writeln!(out, "<chatserver>chat.bilibili.com</chatserver>")?;
+        writeln!(out, "<chatid>{}</chatid>", room_id)?;
+        writeln!(out, "<mission>0</mission>")?;
+        writeln!(out, "<maxlimit>0</maxlimit>")?;
+        writeln!(out, "<state>0</state>")?;
+        writeln!(out, "<real_name>0</real_name>")?;
+        writeln!(out, "<source>k-v</source>")?;
+        out.flush()?;
+        Ok(XmlWriter { out, count: 0 })
+    }
+
+    // offset 为相对直播开始的毫秒数
+    pub fn write(&mut self, event: &DanmakuEvent, offset: i64) -> io::Result<()> {
+        let (mode, color) = match event.kind {
+            EventKind::SuperChat => (MODE_TOP, SUPER_CHAT_COLOR),
+            _ => (MODE_SCROLL, WHITE),
+        };
+        self.count += 1;
+        // p 属性依次为：出现时间(秒)、模式、字号、颜色、发送时间、弹幕池、用户 ID 哈希、弹幕 ID
+        writeln!(
+            self.out,
+            r#"<d p="{:.3},{},{},{},{},0,{:x},{}" user="{}">{}</d>"#,
+            offset as f64 / 1000.0,
+            mode,
+            FONT_SIZE,
+            color,
+            event.timestamp / 1000,
+            event.uid,
+            self.count,
+            escape(&event.uname),
+            escape(&event.message),
+        )?;
+        self.out.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<u64> {
+        writeln!(self.out, "</i>")?;
+        self.out.flush()?;
+        Ok(self.count)
+    }
+}
+
+// 转义 XML 特殊字符并去掉 XML 不允许的控制字符
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(' '),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,442 @@
+use crate::danmaku::DanmakuEvent;
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::event_store::EventStore;
+use crate::watchdog::Heartbeat;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_http::reqwest::Client;
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::{broadcast, Notify};
+use tokio::time::Instant;
+
+mod queue;
+mod reconcile;
+
+pub use reconcile::ReconcileReport;
+
+// 保存上传配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "relay.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+// 离线队列文件名
+const QUEUE_FILE: &str = "relay_queue.ndjson";
+
+// 检查队列并上传的间隔
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+// 每次请求最多上传的事件数量
+const BATCH_SIZE: usize = 200;
+// 上传失败后的重试间隔，每次失败翻倍直到上限
+const RETRY_BASE: Duration = Duration::from_secs(2);
+const RETRY_MAX: Duration = Duration::from_secs(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+// 离线队列体积上限的范围
+const MIN_QUEUE_MB: u64 = 1;
+const MAX_QUEUE_MB: u64 = 1024;
+
+// 上传配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayConfig {
+    pub enabled: bool,
+    // vtsuru 接收事件的接口地址
+    pub endpoint: String,
+    // vtsuru 的身份令牌，以 Bearer 形式放在请求头中
+    pub token: String,
+    // 离线队列的体积上限，超过时丢弃最旧的事件
+    pub max_queue_mb: u64,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        RelayConfig {
+            enabled: false,
+            endpoint: String::new(),
+            token: String::new(),
+            max_queue_mb: 64,
+        }
+    }
+}
+
+// 上传状态
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RelayStatus {
+    pub enabled: bool,
+    // 等待上传的事件数量
+    pub queue_depth: usize,
+    pub queue_bytes: u64,
+    // 本次运行中因离线队列已满丢弃的事件数量
+    pub dropped: u64,
+    // 最近一次成功上传的 Unix 毫秒时间戳
+    pub last_upload_at: Option<i64>,
+    // 最近一次成功上传的事件数量
+    pub last_upload_count: usize,
+    // 最近一次上传失败的原因，成功后清空
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    // 下一次重试的 Unix 毫秒时间戳
+    pub next_retry_at: Option<i64>,
+}
+
+// 离线队列中积压的事件
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayBacklog {
+    pub count: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub dropped: u64,
+    // 最早和最新事件的 Unix 毫秒时间戳
+    pub oldest_at: Option<i64>,
+    pub newest_at: Option<i64>,
+    // 最早的若干事件，即下一批要上传的事件
+    pub events: Vec<DanmakuEvent>,
+}
+
+// 上传任务与管理器共享的状态
+struct Shared {
+    http: Client,
+    config: RwLock<RelayConfig>,
+    queue: Mutex<queue::EventQueue>,
+    status: Mutex<RelayStatus>,
+    // 记录上传成功的事件，便于用户核对
+    store: EventStore,
+    // 修改配置后唤醒上传任务立即重试
+    wake: Notify,
+    // 上传任务的心跳，用于发现卡住的任务
+    heartbeat: Heartbeat,
+}
+
+// 将抓取到的事件转发到 vtsuru 服务端
+pub struct Relay {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+    task: Mutex<JoinHandle<()>>,
+}
+
+impl Relay {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+        store: EventStore,
+        data_dir: PathBuf,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: RelayConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let max_bytes = config.max_queue_mb * 1024 * 1024;
+        let shared = Arc::new(Shared {
+            http: crate::proxy::client_builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            config: RwLock::new(config),
+            queue: Mutex::new(queue::EventQueue::load(
+                data_dir.join(QUEUE_FILE),
+                max_bytes,
+            )),
+            status: Mutex::new(RelayStatus::default()),
+            store,
+            wake: Notify::new(),
+            heartbeat: Heartbeat::new(),
+        });
+        let task = spawn_run(shared.clone(), events);
+        Ok(Relay {
+            shared,
+            store,
+            task: Mutex::new(task),
+        })
+    }
+
+    // 上传任务超过 timeout 没有心跳时返回经过的时间
+    pub fn stalled(&self, timeout: Duration) -> Option<Duration> {
+        self.shared.heartbeat.stalled(timeout)
+    }
+
+    // 终止上传任务并用新的订阅重新开始，离线队列中的事件会继续上传
+    pub fn restart(&self, events: Subscriber<DanmakuEvent>) {
+        let mut task = self.task.lock().unwrap();
+        task.abort();
+        self.shared.heartbeat.beat();
+        *task = spawn_run(self.shared.clone(), events);
+    }
+
+    pub fn get_config(&self) -> RelayConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    // 修改上传配置，保存后立即尝试上传积压的事件
+    pub fn update_config(&self, config: RelayConfig) -> AppResult<RelayStatus> {
+        let config = RelayConfig {
+            endpoint: config.endpoint.trim().to_string(),
+            token: config.token.trim().to_string(),
+            ..config
+        };
+        if config.enabled
+            && !config.endpoint.starts_with("https://")
+            && !config.endpoint.starts_with("http://")
+        {
+            return Err(AppError::InvalidConfig(
+                "上传地址必须以 http:// 或 https:// 开头".to_string(),
+            ));
+        }
+        if !(MIN_QUEUE_MB..=MAX_QUEUE_MB).contains(&config.max_queue_mb) {
+            return Err(AppError::InvalidConfig(format!(
+                "离线队列上限应在 {} 到 {} MB 之间",
+                MIN_QUEUE_MB, MAX_QUEUE_MB
+            )));
+        }
+
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存上传配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化上传配置失败: {}", err),
+        }
+        self.shared
+            .queue
+            .lock()
+            .unwrap()
+            .set_max_bytes(config.max_queue_mb * 1024 * 1024);
+        *self.shared.config.write().unwrap() = config;
+        {
+            let mut status = self.shared.status.lock().unwrap();
+            status.consecutive_failures = 0;
+            status.next_retry_at = None;
+        }
+        self.shared.wake.notify_one();
+        Ok(self.status())
+    }
+
+    // 丢弃所有未上传的事件
+    pub fn clear_queue(&self) -> RelayStatus {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.clear();
+        if let Err(err) = queue.compact() {
+            log::warn!("清空离线队列失败: {}", err);
+        }
+        drop(queue);
+        self.status()
+    }
+
+    // 重写离线队列中残留的已上传事件，下次启动时继续上传剩余部分
+    pub fn persist(&self) {
+        if let Err(err) = self.shared.queue.lock().unwrap().compact() {
+            log::warn!("保存离线队列失败: {}", err);
+        }
+    }
+
+    pub fn status(&self) -> RelayStatus {
+        let mut status = self.shared.status.lock().unwrap().clone();
+        status.enabled = self.shared.config.read().unwrap().enabled;
+        let queue = self.shared.queue.lock().unwrap();
+        status.queue_depth = queue.len();
+        status.queue_bytes = queue.bytes();
+        status.dropped = queue.dropped();
+        status
+    }
+
+    // 对比一段时间内本地已上传的事件数量与服务端收到的数量
+    pub async fn reconcile(&self, start: i64, end: i64) -> AppResult<ReconcileReport> {
+        let config = self.get_config();
+        let pending = self.shared.queue.lock().unwrap().len();
+        reconcile::reconcile(
+            &self.shared.http,
+            &config,
+            &self.shared.store,
+            start,
+            end,
+            pending,
+        )
+        .await
+    }
+
+    // 查看积压的事件，limit 为返回的最早事件数量
+    pub fn backlog(&self, limit: usize) -> RelayBacklog {
+        let max_bytes = self.shared.config.read().unwrap().max_queue_mb * 1024 * 1024;
+        let queue = self.shared.queue.lock().unwrap();
+        let events = queue.peek(limit.max(1));
+        RelayBacklog {
+            count: queue.len(),
+            bytes: queue.bytes(),
+            max_bytes,
+            dropped: queue.dropped(),
+            oldest_at: events.first().map(|event| event.timestamp),
+            newest_at: queue.last().map(|event| event.timestamp),
+            events: events.into_iter().take(limit).collect(),
+        }
+    }
+}
+
+fn spawn_run(shared: Arc<Shared>, events: Subscriber<DanmakuEvent>) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(crate::crash::guard(
+        "事件上传".to_string(),
+        run(shared, events),
+    ))
+}
+
+// 接收事件放入队列，并定时批量上传
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+    let mut retry_at: Option<Instant> = None;
+
+    loop {
+        shared.heartbeat.beat();
+        tokio::select! {
+            event = events.recv() => {
+                match event {
+                    Ok(event) => {
+                        if !event.replay
+                            && !event.simulated
+                            && shared.config.read().unwrap().enabled
+                        {
+                            if let Err(err) = shared.queue.lock().unwrap().push(&event) {
+                                log::warn!("写入离线队列失败: {}", err);
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        log::warn!("上传任务处理不及时，丢弃了 {} 条事件", count);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+                continue;
+            }
+            _ = ticker.tick() => {}
+            _ = shared.wake.notified() => retry_at = None,
+        }
+
+        if retry_at.is_none_or(|at| Instant::now() >= at) {
+            retry_at = flush(&shared).await;
+        }
+        if let Err(err) = shared.queue.lock().unwrap().compact() {
+            log::warn!("保存离线队列失败: {}", err);
+        }
+    }
+}
+
+// 上传队列中的一批事件，失败时返回下一次重试的时间
+async fn flush(shared: &Shared) -> Option<Instant> {
+    let config = shared.config.read().unwrap().clone();
+    if !config.enabled || config.endpoint.is_empty() {
+        return None;
+    }
+    let batch = shared.queue.lock().unwrap().peek(BATCH_SIZE);
+    if batch.is_empty() {
+        return None;
+    }
+
+    match upload(&shared.http, &config, &batch).await {
+        Ok(acked) => {
+            let acked: HashSet<&str> = acked.iter().map(String::as_str).collect();
+            let removed = shared
+                .queue
+                .lock()
+                .unwrap()
+                .acknowledge(batch.len(), &acked);
+            // 一条都没有确认时按失败处理，避免不退避地反复上传同一批
+            if removed == 0 {
+                return upload_failed(
+                    shared,
+                    AppError::Relay("服务端没有确认收到这一批中的任何事件".to_string()),
+                );
+            }
+            if removed < batch.len() {
+                log::warn!(
+                    "服务端只确认了 {} 条事件中的 {} 条，其余的稍后重新上传",
+                    batch.len(),
+                    removed
+                );
+            }
+            let now = chrono::Utc::now().timestamp_millis();
+            let ids: Vec<&str> = batch
+                .iter()
+                .map(|event| event.id.as_str())
+                .filter(|id| acked.contains(id))
+                .collect();
+            if let Err(err) = shared.store.mark_uploaded(&ids, now) {
+                log::warn!("记录上传状态失败: {}", err);
+            }
+            let mut status = shared.status.lock().unwrap();
+            status.last_upload_at = Some(now);
+            status.last_upload_count = removed;
+            status.last_error = None;
+            status.consecutive_failures = 0;
+            status.next_retry_at = None;
+            None
+        }
+        Err(err) => upload_failed(shared, err),
+    }
+}
+
+// 记录上传失败，返回按失败次数退避后的重试时间
+fn upload_failed(shared: &Shared, err: AppError) -> Option<Instant> {
+    let mut status = shared.status.lock().unwrap();
+    status.consecutive_failures += 1;
+    let delay = retry_delay(status.consecutive_failures);
+    log::warn!("上传事件失败，{} 秒后重试: {}", delay.as_secs(), err);
+    status.last_error = Some(err.to_string());
+    status.next_retry_at = Some(chrono::Utc::now().timestamp_millis() + delay.as_millis() as i64);
+    Some(Instant::now() + delay)
+}
+
+// 上传一批事件，返回服务端确认收到的事件 ID
+// 同一批事件重试时使用相同的幂等键，服务端据此和事件 ID 去重
+// 服务端返回 acked 和 duplicates 时只有其中的事件算作已上传，返回的 JSON 对象中没有这两个字段时整批都算作已上传
+// 返回内容不是 JSON 对象时（例如代理或认证页面）按失败处理，事件保留在队列中
+async fn upload(
+    http: &Client,
+    config: &RelayConfig,
+    events: &[DanmakuEvent],
+) -> AppResult<Vec<String>> {
+    let mut request = http
+        .post(&config.endpoint)
+        .header("Idempotency-Key", idempotency_key(events))
+        .json(&json!({ "events": events }));
+    if !config.token.is_empty() {
+        request = request.bearer_auth(&config.token);
+    }
+    let text = request.send().await?.error_for_status()?.text().await?;
+    let body: Value = match serde_json::from_str(&text) {
+        Ok(body @ Value::Object(_)) => body,
+        _ => {
+            return Err(AppError::Relay(
+                "服务端返回的内容不是 JSON 对象".to_string(),
+            ))
+        }
+    };
+    if body["acked"].is_null() && body["duplicates"].is_null() {
+        return Ok(events.iter().map(|event| event.id.clone()).collect());
+    }
+    let acked = ["acked", "duplicates"]
+        .iter()
+        .flat_map(|field| body[*field].as_array().into_iter().flatten())
+        .filter_map(|id| id.as_str().map(str::to_string))
+        .collect();
+    Ok(acked)
+}
+
+fn idempotency_key(events: &[DanmakuEvent]) -> String {
+    let first = events.first().map(|event| event.id.as_str());
+    let last = events.last().map(|event| event.id.as_str());
+    format!(
+        "{}-{}-{}",
+        first.unwrap_or_default(),
+        last.unwrap_or_default(),
+        events.len()
+    )
+}
+
+fn retry_delay(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    RETRY_BASE.saturating_mul(1 << exponent).min(RETRY_MAX)
+}
@@ -0,0 +1,388 @@
+use crate::danmaku::DanmakuEvent;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+// 队列中最多保留的事件数量，离线太久时丢弃最旧的事件
+const MAX_LEN: usize = 50_000;
+
+// 队列中的一个事件
+struct Entry {
+    id: String,
+    // 序列化后的一行，不含换行符
+    line: String,
+}
+
+impl Entry {
+    fn size(&self) -> u64 {
+        self.line.len() as u64 + 1
+    }
+}
+
+// 等待上传的事件队列
+// 以 NDJSON 格式保存在磁盘上，每收到一个事件立即追加到文件末尾，程序崩溃时也不会丢失
+// 上传成功或丢弃事件后文件中会残留旧的行，之后整体重写一次
+pub(super) struct EventQueue {
+    path: PathBuf,
+    entries: VecDeque<Entry>,
+    bytes: u64,
+    max_bytes: u64,
+    // 因超过上限丢弃的事件数量
+    dropped: u64,
+    // 追加写入的文件，写入失败后关闭，下次重写时重新打开
+    file: Option<File>,
+    // 磁盘上的文件与内存中的队列不一致，需要重写
+    stale: bool,
+}
+
+impl EventQueue {
+    // 读取磁盘上未上传的事件，无法解析的行会被跳过
+    pub fn load(path: PathBuf, max_bytes: u64) -> Self {
+        let mut entries = VecDeque::new();
+        let mut stale = false;
+        if let Ok(file) = File::open(&path) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // 崩溃时可能只写入了半行
+                match serde_json::from_str::<DanmakuEvent>(&line) {
+                    Ok(event) => entries.push_back(Entry { id: event.id, line }),
+                    Err(err) => {
+                        log::warn!("读取待上传事件失败: {}", err);
+                        stale = true;
+                    }
+                }
+            }
+        }
+        if !entries.is_empty() {
+            log::info!("从磁盘恢复了 {} 条待上传事件", entries.len());
+        }
+        let bytes = entries.iter().map(Entry::size).sum();
+        let mut queue = EventQueue {
+            path,
+            entries,
+            bytes,
+            max_bytes,
+            dropped: 0,
+            file: None,
+            stale,
+        };
+        queue.trim();
+        if let Err(err) = queue.compact() {
+            log::warn!("整理离线队列失败: {}", err);
+        }
+        queue
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn set_max_bytes(&mut self, max_bytes: u64) {
+        self.max_bytes = max_bytes;
+        self.trim();
+    }
+
+    // 加入队列并追加到磁盘，写入失败时事件仍保留在内存中
+    pub fn push(&mut self, event: &DanmakuEvent) -> io::Result<()> {
+        let entry = Entry {
+            id: event.id.clone(),
+            line: serde_json::to_string(event)?,
+        };
+        let line = entry.line.clone();
+        self.bytes += entry.size();
+        self.entries.push_back(entry);
+        if self.trim() {
+            // 丢弃了旧事件，下次重写时会写入新事件
+            return Ok(());
+        }
+        self.append(&line).inspect_err(|_| {
+            self.file = None;
+            self.stale = true;
+        })
+    }
+
+    // 取出队首的一批事件但不移除，上传成功后再调用 remove
+    pub fn peek(&self, count: usize) -> Vec<DanmakuEvent> {
+        self.entries
+            .iter()
+            .take(count)
+            .filter_map(|entry| serde_json::from_str(&entry.line).ok())
+            .collect()
+    }
+
+    // 队尾最新的一个事件
+    pub fn last(&self) -> Option<DanmakuEvent> {
+        self.entries
+            .back()
+            .and_then(|entry| serde_json::from_str(&entry.line).ok())
+    }
+
+    // 移除队首 count 个事件中服务端已确认的部分，未确认的事件保持原有顺序留在队首
+    // 返回移除的数量
+    pub fn acknowledge(&mut self, count: usize, acked: &HashSet<&str>) -> usize {
+        let count = count.min(self.entries.len());
+        let mut kept = VecDeque::new();
+        let mut removed = 0;
+        for entry in self.entries.drain(..count) {
+            if acked.contains(entry.id.as_str()) {
+                self.bytes -= entry.size();
+                removed += 1;
+            } else {
+                kept.push_back(entry);
+            }
+        }
+        while let Some(entry) = kept.pop_back() {
+            self.entries.push_front(entry);
+        }
+        self.stale |= removed > 0;
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+        self.stale = true;
+    }
+
+    // 文件中残留已上传或丢弃的事件时重写，队列为空时删除文件
+    pub fn compact(&mut self) -> io::Result<()> {
+        if !self.stale {
+            return Ok(());
+        }
+        self.file = None;
+        if self.entries.is_empty() {
+            match fs::remove_file(&self.path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        } else {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // 先写临时文件再替换，避免写到一半时退出导致队列损坏
+            let temp_path = self.path.with_extension("tmp");
+            let mut writer = io::BufWriter::new(File::create(&temp_path)?);
+            for entry in &self.entries {
+                writer.write_all(entry.line.as_bytes())?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+            drop(writer);
+            fs::rename(&temp_path, &self.path)?;
+        }
+        self.stale = false;
+        Ok(())
+    }
+
+    fn append(&mut self, line: &str) -> io::Result<()> {
+        if self.stale {
+            return Ok(());
+        }
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file = File::options().create(true).append(true).open(&self.path)?;
+            self.file = Some(file);
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        // 一次写入整行，减少崩溃时只写入半行的可能
+        file.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    // 超过数量或体积上限时丢弃最旧的事件，返回是否丢弃了事件
+    fn trim(&mut self) -> bool {
+        let mut dropped = 0;
+        while self.entries.len() > MAX_LEN
+            || (self.bytes > self.max_bytes && self.entries.len() > 1)
+        {
+            if let Some(entry) = self.entries.pop_front() {
+                self.bytes -= entry.size();
+                dropped += 1;
+            }
+        }
+        if dropped > 0 {
+            if self.dropped == 0 {
+                log::warn!("离线队列已满，开始丢弃最旧的事件");
+            }
+            self.dropped += dropped;
+            self.stale = true;
+        }
+        dropped > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, EventQueue, MAX_LEN};
+    use crate::danmaku::DanmakuEvent;
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // 每个测试使用独立的临时目录，队列文件为 <dir>/queue.ndjson
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "vtsuru-queue-test-{}",
+                uuid::Uuid::new_v4().simple()
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            Fixture { dir }
+        }
+
+        fn path(&self) -> PathBuf {
+            self.dir.join("queue.ndjson")
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn event(id: &str) -> DanmakuEvent {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "kind": "danmaku",
+            "room_id": 1,
+            "timestamp": 0,
+            "uid": 1,
+            "uname": "test",
+            "message": "hello",
+            "num": 1,
+            "price": 0.0,
+            "guard_level": 0,
+            "fans_medal_level": 0,
+            "fans_medal_name": "",
+        }))
+        .unwrap()
+    }
+
+    fn ids(queue: &EventQueue) -> Vec<String> {
+        queue.entries.iter().map(|entry| entry.id.clone()).collect()
+    }
+
+    fn line(id: &str) -> String {
+        serde_json::to_string(&event(id)).unwrap()
+    }
+
+    #[test]
+    fn load_skips_truncated_line() {
+        let fixture = Fixture::new();
+        let truncated = &line("c")[..20];
+        fs::write(
+            fixture.path(),
+            format!("{}\n{}\n{}", line("a"), line("b"), truncated),
+        )
+        .unwrap();
+
+        let queue = EventQueue::load(fixture.path(), u64::MAX);
+        assert_eq!(ids(&queue), ["a", "b"]);
+        // 半行被标记为过期后在加载时重写掉
+        assert!(!queue.stale);
+        assert_eq!(
+            fs::read_to_string(fixture.path()).unwrap(),
+            format!("{}\n{}\n", line("a"), line("b"))
+        );
+    }
+
+    #[test]
+    fn acknowledge_keeps_unacked_entries_in_order() {
+        let fixture = Fixture::new();
+        let mut queue = EventQueue::load(fixture.path(), u64::MAX);
+        for id in ["a", "b", "c", "d", "e"] {
+            queue.push(&event(id)).unwrap();
+        }
+        let bytes = queue.bytes();
+
+        let acked: HashSet<&str> = ["b", "d", "e"].into_iter().collect();
+        // 只处理队首 4 个事件，e 不在范围内
+        assert_eq!(queue.acknowledge(4, &acked), 2);
+        assert_eq!(ids(&queue), ["a", "c", "e"]);
+        assert_eq!(bytes - queue.bytes(), 2 * (line("b").len() as u64 + 1));
+        assert!(queue.stale);
+
+        assert_eq!(queue.acknowledge(10, &HashSet::new()), 0);
+        assert_eq!(ids(&queue), ["a", "c", "e"]);
+    }
+
+    #[test]
+    fn trim_respects_max_bytes() {
+        let fixture = Fixture::new();
+        let size = line("a").len() as u64 + 1;
+        let mut queue = EventQueue::load(fixture.path(), size * 2);
+        for id in ["a", "b", "c"] {
+            queue.push(&event(id)).unwrap();
+        }
+        assert_eq!(ids(&queue), ["b", "c"]);
+        assert_eq!(queue.bytes(), size * 2);
+        assert_eq!(queue.dropped(), 1);
+
+        // 至少保留一个事件
+        queue.set_max_bytes(1);
+        assert_eq!(ids(&queue), ["c"]);
+        assert_eq!(queue.dropped(), 2);
+    }
+
+    #[test]
+    fn trim_respects_max_len() {
+        let fixture = Fixture::new();
+        let mut queue = EventQueue::load(fixture.path(), u64::MAX);
+        for index in 0..MAX_LEN + 2 {
+            let entry = Entry {
+                id: index.to_string(),
+                line: String::new(),
+            };
+            queue.bytes += entry.size();
+            queue.entries.push_back(entry);
+        }
+        assert!(queue.trim());
+        assert_eq!(queue.len(), MAX_LEN);
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(queue.entries.front().unwrap().id, "2");
+        assert_eq!(queue.bytes(), MAX_LEN as u64);
+    }
+
+    #[test]
+    fn compact_round_trips() {
+        let fixture = Fixture::new();
+        let mut queue = EventQueue::load(fixture.path(), u64::MAX);
+        for id in ["a", "b", "c"] {
+            queue.push(&event(id)).unwrap();
+        }
+        let acked: HashSet<&str> = ["b"].into_iter().collect();
+        queue.acknowledge(3, &acked);
+        queue.compact().unwrap();
+        assert!(!queue.stale);
+
+        let mut reloaded = EventQueue::load(fixture.path(), u64::MAX);
+        assert_eq!(ids(&reloaded), ["a", "c"]);
+        assert_eq!(reloaded.bytes(), queue.bytes());
+        assert_eq!(reloaded.last().unwrap().id, "c");
+
+        // 队列清空后删除文件
+        reloaded.clear();
+        reloaded.compact().unwrap();
+        assert!(!fixture.path().exists());
+    }
+}
@@ -0,0 +1,90 @@
+use super::RelayConfig;
+use crate::error::{AppError, AppResult};
+use crate::event_store::EventStore;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri_plugin_http::reqwest::Client;
+
+// 上传地址下返回服务端统计的接口
+const COUNTS_PATH: &str = "/counts";
+
+// 某一类型事件在本地和服务端的数量
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileEntry {
+    pub kind: String,
+    // 本地保存的数量
+    pub local: u64,
+    // 本地记录为已上传的数量
+    pub uploaded: u64,
+    // 服务端收到的数量
+    pub server: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileReport {
+    // 对比范围，Unix 毫秒时间戳
+    pub start: i64,
+    pub end: i64,
+    pub kinds: Vec<ReconcileEntry>,
+    // 离线队列中还未上传的事件数量
+    pub pending: usize,
+    // 所有类型已上传的数量都与服务端一致
+    pub consistent: bool,
+}
+
+// 服务端按事件时间统计范围内收到的事件，返回 { "counts": { "<kind>": n } }
+pub(super) async fn reconcile(
+    http: &Client,
+    config: &RelayConfig,
+    store: &EventStore,
+    start: i64,
+    end: i64,
+    pending: usize,
+) -> AppResult<ReconcileReport> {
+    if config.endpoint.is_empty() {
+        return Err(AppError::InvalidConfig("没有设置上传地址".to_string()));
+    }
+    if start > end {
+        return Err(AppError::InvalidConfig(
+            "开始时间不能晚于结束时间".to_string(),
+        ));
+    }
+    let url = format!("{}{}", config.endpoint.trim_end_matches('/'), COUNTS_PATH);
+    let mut request = http.get(url).query(&[("start", start), ("end", end)]);
+    if !config.token.is_empty() {
+        request = request.bearer_auth(&config.token);
+    }
+    let body: Value = request.send().await?.error_for_status()?.json().await?;
+    let server: BTreeMap<String, u64> = body["counts"]
+        .as_object()
+        .ok_or_else(|| AppError::Relay("服务端没有返回事件统计".to_string()))?
+        .iter()
+        .map(|(kind, count)| (kind.clone(), count.as_u64().unwrap_or_default()))
+        .collect();
+    let local = store.upload_counts(start, end)?;
+
+    let mut kinds: Vec<String> = local.keys().chain(server.keys()).cloned().collect();
+    kinds.sort();
+    kinds.dedup();
+    let kinds: Vec<ReconcileEntry> = kinds
+        .into_iter()
+        .map(|kind| {
+            let (local, uploaded) = local.get(&kind).copied().unwrap_or_default();
+            ReconcileEntry {
+                server: server.get(&kind).copied().unwrap_or_default(),
+                kind,
+                local,
+                uploaded,
+            }
+        })
+        .collect();
+    let consistent = kinds.iter().all(|entry| entry.uploaded == entry.server);
+    Ok(ReconcileReport {
+        start,
+        end,
+        kinds,
+        pending,
+        consistent,
+    })
+}
@@ -0,0 +1,130 @@
+use crate::danmaku::RoomManager;
+use crate::error::{AppError, AppResult};
+use crate::event_store::EventStore;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+// 回放进度变化时发送给前端的事件
+pub const STATUS_EVENT: &str = "replay://status";
+
+// 回放倍速的范围
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 100.0;
+// 每回放多少个事件通知一次进度
+const PROGRESS_STEP: usize = 20;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplayStatus {
+    pub running: bool,
+    pub session_id: Option<i64>,
+    pub speed: f64,
+    // 已回放和总共的事件数量
+    pub position: usize,
+    pub total: usize,
+}
+
+struct Shared {
+    app: AppHandle,
+    status: Mutex<ReplayStatus>,
+    cancel: Mutex<Option<CancellationToken>>,
+}
+
+impl Shared {
+    fn update(&self, update: impl FnOnce(&mut ReplayStatus)) {
+        let status = {
+            let mut status = self.status.lock().unwrap();
+            update(&mut status);
+            status.clone()
+        };
+        let _ = self.app.emit(STATUS_EVENT, &status);
+    }
+}
+
+// 按原始间隔重新分发保存的直播事件，用于不开播时测试界面和自动化规则
+// 回放的事件标记为 replay，不会再次保存、上传或计入统计
+pub struct ReplayManager {
+    shared: Arc<Shared>,
+    store: EventStore,
+}
+
+impl ReplayManager {
+    pub fn new(app: &AppHandle, store: EventStore) -> Self {
+        ReplayManager {
+            shared: Arc::new(Shared {
+                app: app.clone(),
+                status: Mutex::new(ReplayStatus::default()),
+                cancel: Mutex::new(None),
+            }),
+            store,
+        }
+    }
+
+    // 同一时间只有一个回放，开始新的回放时停止正在进行的回放
+    pub fn replay_session(&self, session_id: i64, speed: f64) -> AppResult<ReplayStatus> {
+        if !speed.is_finite() {
+            return Err(AppError::InvalidConfig("回放倍速无效".to_string()));
+        }
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        let events = self.store.session_events(session_id)?;
+        self.stop();
+
+        let cancel = CancellationToken::new();
+        *self.shared.cancel.lock().unwrap() = Some(cancel.clone());
+        self.shared.update(|status| {
+            *status = ReplayStatus {
+                running: true,
+                session_id: Some(session_id),
+                speed,
+                position: 0,
+                total: events.len(),
+            }
+        });
+
+        let shared = self.shared.clone();
+        tauri::async_runtime::spawn(async move {
+            let started = Instant::now();
+            let first = events.first().map(|event| event.timestamp).unwrap_or(0);
+            let total = events.len();
+            for (index, mut event) in events.into_iter().enumerate() {
+                let delay = (event.timestamp - first).max(0) as f64 / speed;
+                let at = started + Duration::from_millis(delay as u64);
+                tokio::select! {
+                    _ = cancel.cancelled() => return,
+                    _ = tokio::time::sleep_until(at) => {}
+                }
+                let Some(rooms) = shared.app.try_state::<RoomManager>() else {
+                    break;
+                };
+                // 使用新的 ID 和当前时间，界面按新事件处理
+                event.id = ulid::Ulid::new().to_string();
+                event.timestamp = chrono::Utc::now().timestamp_millis();
+                event.replay = true;
+                rooms.publish_replay(event);
+                let position = index + 1;
+                if position % PROGRESS_STEP == 0 || position == total {
+                    shared.update(|status| status.position = position);
+                }
+            }
+            if !cancel.is_cancelled() {
+                shared.update(|status| status.running = false);
+            }
+        });
+        Ok(self.status())
+    }
+
+    pub fn stop(&self) -> ReplayStatus {
+        if let Some(cancel) = self.shared.cancel.lock().unwrap().take() {
+            cancel.cancel();
+            self.shared.update(|status| status.running = false);
+        }
+        self.status()
+    }
+
+    pub fn status(&self) -> ReplayStatus {
+        self.shared.status.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,270 @@
+use crate::error::{AppError, AppResult};
+use crate::event_store::{EventStore, RevenueBucket};
+use chrono::{Datelike, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存收益设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "revenue.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 1 元 = 10 电池 = 1000 金瓜子，事件中的金额已经换算为元
+const BATTERIES_PER_YUAN: f64 = 10.0;
+
+// CSV 表头，与 write_csv_row 中的字段顺序一致
+const CSV_HEADER: &str = "period,gift_count,gift_value,super_chat_count,super_chat_value,guard_count,guard_value,gross,batteries,income";
+
+// 合计行的时间段名称
+const TOTAL_PERIOD: &str = "total";
+
+// 主播实际到手的比例，按个人或公会的分成比例设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RevenueConfig {
+    pub gift_share: f64,
+    pub super_chat_share: f64,
+    pub guard_share: f64,
+}
+
+impl Default for RevenueConfig {
+    fn default() -> Self {
+        RevenueConfig {
+            gift_share: 0.5,
+            super_chat_share: 0.5,
+            guard_share: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    #[default]
+    Day,
+    Month,
+}
+
+impl Granularity {
+    // SQLite strftime 的格式
+    fn format(&self) -> &'static str {
+        match self {
+            Granularity::Day => "%Y-%m-%d",
+            Granularity::Month => "%Y-%m",
+        }
+    }
+}
+
+// 报表的范围，未设置时间范围时统计本地时间本月 1 日零点到现在
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RevenueRange {
+    pub room_id: Option<u64>,
+    // Unix 毫秒时间戳，包含边界
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub granularity: Granularity,
+}
+
+// 一个时间段的收益，金额单位为元
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RevenueRow {
+    // 本地时间的日期（2024-01-31）或月份（2024-01），合计行为 total
+    pub period: String,
+    pub gift_count: u64,
+    pub gift_value: f64,
+    pub super_chat_count: u64,
+    pub super_chat_value: f64,
+    pub guard_count: u64,
+    pub guard_value: f64,
+    // 观众支付的总金额
+    pub gross: f64,
+    // 总金额换算的电池数量
+    pub batteries: u64,
+    // 按分成比例计算的到手金额
+    pub income: f64,
+}
+
+impl RevenueRow {
+    fn new(period: &str) -> Self {
+        RevenueRow {
+            period: period.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, bucket: &RevenueBucket) {
+        match bucket.kind.as_str() {
+            "gift" => {
+                self.gift_count += bucket.count;
+                self.gift_value += bucket.value;
+            }
+            "super_chat" => {
+                self.super_chat_count += bucket.count;
+                self.super_chat_value += bucket.value;
+            }
+            "guard" => {
+                self.guard_count += bucket.count;
+                self.guard_value += bucket.value;
+            }
+            _ => {}
+        }
+    }
+
+    // 根据各类型的金额计算合计、电池和到手金额
+    fn finish(mut self, config: &RevenueConfig) -> Self {
+        self.gross = self.gift_value + self.super_chat_value + self.guard_value;
+        self.batteries = (self.gross * BATTERIES_PER_YUAN).round() as u64;
+        self.income = self.gift_value * config.gift_share
+            + self.super_chat_value * config.super_chat_share
+            + self.guard_value * config.guard_share;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevenueReport {
+    pub start: i64,
+    pub end: i64,
+    pub granularity: Granularity,
+    // 按时间顺序排列，没有收益的时间段不包含在内
+    pub rows: Vec<RevenueRow>,
+    pub total: RevenueRow,
+    // 计算到手金额使用的分成比例
+    pub config: RevenueConfig,
+}
+
+// 按日或按月统计礼物、醒目留言和大航海的收益，便于记账
+pub struct Revenue {
+    events: EventStore,
+    config: RwLock<RevenueConfig>,
+    store: Arc<Store<Wry>>,
+}
+
+impl Revenue {
+    pub fn new(app: &AppHandle, events: EventStore) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Ok(Revenue {
+            events,
+            config: RwLock::new(config),
+            store,
+        })
+    }
+
+    pub fn get_config(&self) -> RevenueConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: RevenueConfig) -> AppResult<RevenueConfig> {
+        for share in [
+            config.gift_share,
+            config.super_chat_share,
+            config.guard_share,
+        ] {
+            if !(0.0..=1.0).contains(&share) {
+                return Err(AppError::InvalidConfig(
+                    "分成比例应在 0 到 1 之间".to_string(),
+                ));
+            }
+        }
+        *self.config.write().unwrap() = config.clone();
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存收益设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化收益设置失败: {}", err),
+        }
+        Ok(config)
+    }
+
+    pub fn report(&self, range: &RevenueRange) -> AppResult<RevenueReport> {
+        let start = range.start.unwrap_or_else(month_start);
+        let end = range
+            .end
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        if start > end {
+            return Err(AppError::InvalidConfig(
+                "开始时间不能晚于结束时间".to_string(),
+            ));
+        }
+        let config = self.get_config();
+        let buckets =
+            self.events
+                .revenue_buckets(range.room_id, start, end, range.granularity.format())?;
+
+        let mut periods: BTreeMap<String, RevenueRow> = BTreeMap::new();
+        let mut total = RevenueRow::new(TOTAL_PERIOD);
+        for bucket in &buckets {
+            periods
+                .entry(bucket.period.clone())
+                .or_insert_with(|| RevenueRow::new(&bucket.period))
+                .add(bucket);
+            total.add(bucket);
+        }
+        Ok(RevenueReport {
+            start,
+            end,
+            granularity: range.granularity,
+            rows: periods
+                .into_values()
+                .map(|row| row.finish(&config))
+                .collect(),
+            total: total.finish(&config),
+            config,
+        })
+    }
+
+    // 导出报表为 CSV，最后一行为合计，返回时间段的数量
+    pub fn export_csv(&self, range: &RevenueRange, path: &Path) -> AppResult<usize> {
+        let report = self.report(range)?;
+        let mut out = BufWriter::new(File::create(path)?);
+        // 写入 BOM，Excel 才能正确识别 UTF-8 编码
+        out.write_all(b"\xEF\xBB\xBF")?;
+        writeln!(out, "{}", CSV_HEADER)?;
+        for row in report.rows.iter().chain([&report.total]) {
+            write_csv_row(&mut out, row)?;
+        }
+        out.flush()?;
+        Ok(report.rows.len())
+    }
+}
+
+fn write_csv_row(out: &mut impl Write, row: &RevenueRow) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "{},{},{:.2},{},{:.2},{},{:.2},{:.2},{},{:.2}",
+        row.period,
+        row.gift_count,
+        row.gift_value,
+        row.super_chat_count,
+        row.super_chat_value,
+        row.guard_count,
+        row.guard_value,
+        row.gross,
+        row.batteries,
+        row.income
+    )
+}
+
+// 本地时间本月 1 日零点的 Unix 毫秒时间戳
+fn month_start() -> i64 {
+    let now = Local::now();
+    now.date_naive()
+        .with_day(1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .unwrap_or(now)
+        .timestamp_millis()
+}
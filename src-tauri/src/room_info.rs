@@ -0,0 +1,241 @@
+use crate::bilibili::{self, get_api};
+use crate::danmaku::{ConnectionState, ConnectionStateChanged, RoomManager};
+use crate::error::{AppError, AppResult};
+use chrono::{FixedOffset, NaiveDateTime};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_http::reqwest::Client;
+use tokio::sync::broadcast;
+
+// 直播间信息，包含标题、分区、封面和开播状态
+const ROOM_INFO_URL: &str = "https://api.live.bilibili.com/room/v1/Room/get_info";
+// 查询直播间信息的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+const POLL_CHANNEL_CAPACITY: usize = 64;
+
+// 直播间信息变化时发送给前端的事件
+pub const CHANGED_EVENT: &str = "room-info://changed";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomInfo {
+    pub room_id: u64,
+    // 主播的 uid
+    pub uid: u64,
+    pub title: String,
+    pub area_name: String,
+    pub parent_area_name: String,
+    pub cover: String,
+    // 直播画面截图，未开播时为空
+    pub keyframe: String,
+    pub live: bool,
+    // 开播时间，Unix 毫秒时间戳，未开播时为空
+    pub live_time: Option<i64>,
+    pub online: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomInfoChanged {
+    pub room_id: u64,
+    // 变化的字段：title、area、cover、live，首次获取时为空
+    pub changes: Vec<&'static str>,
+    pub previous: Option<RoomInfo>,
+    pub info: RoomInfo,
+}
+
+// 每次查询的结果，查询失败时 info 为空
+#[derive(Debug, Clone)]
+pub struct RoomPoll {
+    pub room_id: u64,
+    pub info: Option<RoomInfo>,
+}
+
+struct Shared {
+    app: AppHandle,
+    http: Client,
+    cache: Mutex<HashMap<u64, RoomInfo>>,
+    polls: broadcast::Sender<RoomPoll>,
+}
+
+impl Shared {
+    async fn refresh(&self, room_id: u64) -> AppResult<RoomInfo> {
+        let result = fetch(&self.http, room_id).await;
+        let _ = self.polls.send(RoomPoll {
+            room_id,
+            info: result.as_ref().ok().cloned(),
+        });
+        let info = result?;
+        let previous = self.cache.lock().unwrap().insert(room_id, info.clone());
+        let changes = previous
+            .as_ref()
+            .map(|previous| changes(previous, &info))
+            .unwrap_or_default();
+        if previous.is_none() || !changes.is_empty() {
+            if let Some(previous) = &previous {
+                if previous.title != info.title {
+                    log::info!(room_id; "直播间 {} 标题修改为: {}", room_id, info.title);
+                }
+            }
+            let _ = self.app.emit(
+                CHANGED_EVENT,
+                RoomInfoChanged {
+                    room_id,
+                    changes,
+                    previous,
+                    info: info.clone(),
+                },
+            );
+        }
+        Ok(info)
+    }
+
+    async fn poll(&self) {
+        let Some(room_ids) = self
+            .app
+            .try_state::<RoomManager>()
+            .map(|rooms| rooms.list_rooms())
+        else {
+            return;
+        };
+        // 不再连接的直播间不保留缓存
+        self.cache
+            .lock()
+            .unwrap()
+            .retain(|room_id, _| room_ids.contains(room_id));
+        for room_id in room_ids {
+            if let Err(err) = self.refresh(room_id).await {
+                log::warn!(room_id; "获取直播间 {} 的信息失败: {}", room_id, err);
+            }
+        }
+    }
+}
+
+// 定时查询已连接直播间的信息并缓存，标题、分区等变化时通知前端和覆盖层
+pub struct RoomInfoCache {
+    shared: Arc<Shared>,
+}
+
+impl RoomInfoCache {
+    pub fn new(app: &AppHandle, states: broadcast::Receiver<ConnectionStateChanged>) -> Self {
+        let (polls, _) = broadcast::channel(POLL_CHANNEL_CAPACITY);
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            http: bilibili::client(),
+            cache: Mutex::new(HashMap::new()),
+            polls,
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), states));
+        RoomInfoCache { shared }
+    }
+
+    pub fn get(&self, room_id: u64) -> Option<RoomInfo> {
+        self.shared.cache.lock().unwrap().get(&room_id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<RoomInfo> {
+        let mut infos: Vec<RoomInfo> = self
+            .shared
+            .cache
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect();
+        infos.sort_by_key(|info| info.room_id);
+        infos
+    }
+
+    pub async fn refresh(&self, room_id: u64) -> AppResult<RoomInfo> {
+        self.shared.refresh(room_id).await
+    }
+
+    // 订阅之后每次查询的结果，直播场次根据开播状态划分
+    pub fn subscribe(&self) -> broadcast::Receiver<RoomPoll> {
+        self.shared.polls.subscribe()
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut states: broadcast::Receiver<ConnectionStateChanged>) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            change = states.recv() => match change {
+                // 新连接的直播间立即查询，不等下一次定时查询
+                Ok(change) if change.state == ConnectionState::Connected
+                    && change.previous == ConnectionState::Connecting =>
+                {
+                    let room_id = change.room_id;
+                    if shared.cache.lock().unwrap().contains_key(&room_id) {
+                        continue;
+                    }
+                    if let Err(err) = shared.refresh(room_id).await {
+                        log::warn!(room_id; "获取直播间 {} 的信息失败: {}", room_id, err);
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = interval.tick() => shared.poll().await,
+        }
+    }
+}
+
+fn changes(previous: &RoomInfo, info: &RoomInfo) -> Vec<&'static str> {
+    let mut changes = Vec::new();
+    if previous.title != info.title {
+        changes.push("title");
+    }
+    if previous.area_name != info.area_name || previous.parent_area_name != info.parent_area_name {
+        changes.push("area");
+    }
+    if previous.cover != info.cover {
+        changes.push("cover");
+    }
+    if previous.live != info.live {
+        changes.push("live");
+    }
+    changes
+}
+
+async fn fetch(http: &Client, room_id: u64) -> AppResult<RoomInfo> {
+    let data = get_api(
+        http,
+        &format!("{}?room_id={}", ROOM_INFO_URL, room_id),
+        None,
+    )
+    .await?;
+    let room_id = data["room_id"]
+        .as_u64()
+        .ok_or_else(|| AppError::BilibiliApi {
+            code: -1,
+            message: format!("无法获取直播间 {} 的信息", room_id),
+        })?;
+    let text = |key: &str| data[key].as_str().unwrap_or_default().to_string();
+    let live = data["live_status"].as_u64() == Some(1);
+    Ok(RoomInfo {
+        room_id,
+        uid: data["uid"].as_u64().unwrap_or_default(),
+        title: text("title"),
+        area_name: text("area_name"),
+        parent_area_name: text("parent_area_name"),
+        cover: text("user_cover"),
+        keyframe: text("keyframe"),
+        live,
+        live_time: live
+            .then(|| data["live_time"].as_str().and_then(parse_live_time))
+            .flatten(),
+        online: data["online"].as_u64().unwrap_or_default(),
+    })
+}
+
+// 接口返回北京时间，例如 "2024-01-01 20:00:00"，未开播时为 "0000-00-00 00:00:00"
+fn parse_live_time(text: &str) -> Option<i64> {
+    let beijing = FixedOffset::east_opt(8 * 3600)?;
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .ok()?
+        .and_local_timezone(beijing)
+        .single()
+        .map(|time| time.timestamp_millis())
+}
@@ -0,0 +1,493 @@
+use crate::danmaku::{DanmakuSource, RoomManager};
+use crate::error::{AppError, AppResult};
+use crate::event_store::{EventQuery, EventStore, ExportFormat};
+use crate::file_server::FileServerRegistry;
+use crate::logs::LogRetention;
+use chrono::{DateTime, Local};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::Notify;
+
+// 保存计划任务的文件，位于应用数据目录
+const STORE_FILE: &str = "scheduler.json";
+const TASKS_KEY: &str = "tasks";
+
+// 任务执行完成时发送给前端的事件
+pub const RUN_EVENT: &str = "scheduler://run";
+
+// 最长等待时间，修改系统时间后最多这么久就能按新的时间执行
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+const DAY_MS: i64 = 24 * 3600 * 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskAction {
+    // 恢复暂停的直播间，并连接列出的直播间
+    StartFetching {
+        #[serde(default)]
+        room_ids: Vec<u64>,
+    },
+    StopFetching,
+    // 把最近几天的事件导出到文件夹，文件名包含日期
+    ExportEvents {
+        directory: String,
+        format: ExportFormat,
+        #[serde(default = "default_export_days")]
+        days: u32,
+    },
+    // 按日志保留策略清理日志，并删除超过指定天数的缩略图缓存，为 0 时不清理缩略图
+    Cleanup {
+        #[serde(default)]
+        thumbnail_days: u32,
+    },
+}
+
+fn default_export_days() -> u32 {
+    1
+}
+
+// cron 表达式支持 5 段（分 时 日 月 周）或带秒的 6、7 段，使用本地时间
+// 5 段表达式的周字段与 crontab 相同，0 和 7 表示周日；6、7 段表达式按 cron 库的规则，1 表示周日
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    // 新建任务时可以为空，保存时生成
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub cron: String,
+    pub action: TaskAction,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskRun {
+    pub task_id: String,
+    // Unix 毫秒时间戳
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub success: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    #[serde(flatten)]
+    pub task: ScheduledTask,
+    // 下次执行的 Unix 毫秒时间戳，未启用时为空
+    pub next_run: Option<i64>,
+    pub last_run: Option<TaskRun>,
+}
+
+struct Entry {
+    task: ScheduledTask,
+    schedule: Schedule,
+    next_run: Option<DateTime<Local>>,
+}
+
+impl Entry {
+    fn new(task: ScheduledTask) -> AppResult<Self> {
+        let schedule = parse_cron(&task.cron)?;
+        let next_run = if task.enabled {
+            schedule.upcoming(Local).next()
+        } else {
+            None
+        };
+        Ok(Entry {
+            task,
+            schedule,
+            next_run,
+        })
+    }
+}
+
+struct Shared {
+    app: AppHandle,
+    entries: Mutex<Vec<Entry>>,
+    last_runs: Mutex<HashMap<String, TaskRun>>,
+    // 修改任务后唤醒后台任务重新计算等待时间
+    changed: Notify,
+}
+
+// 按 cron 表达式定时执行操作，例如开播前自动连接直播间、每晚导出事件
+pub struct Scheduler {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl Scheduler {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let tasks: Vec<ScheduledTask> = store
+            .get(TASKS_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let entries = tasks
+            .into_iter()
+            .filter_map(|task| match Entry::new(task) {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    log::warn!("跳过无效的计划任务: {}", err);
+                    None
+                }
+            })
+            .collect();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            entries: Mutex::new(entries),
+            last_runs: Mutex::new(HashMap::new()),
+            changed: Notify::new(),
+        });
+        tauri::async_runtime::spawn(run(shared.clone()));
+        Ok(Scheduler { shared, store })
+    }
+
+    pub fn list(&self) -> Vec<TaskStatus> {
+        let last_runs = self.shared.last_runs.lock().unwrap();
+        self.shared
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| TaskStatus {
+                task: entry.task.clone(),
+                next_run: entry.next_run.map(|at| at.timestamp_millis()),
+                last_run: last_runs.get(&entry.task.id).cloned(),
+            })
+            .collect()
+    }
+
+    // 新增或按 ID 替换任务
+    pub fn save(&self, task: ScheduledTask) -> AppResult<Vec<TaskStatus>> {
+        let mut task = task;
+        if task.id.is_empty() {
+            task.id = uuid::Uuid::new_v4().to_string();
+        }
+        if let TaskAction::ExportEvents { directory, .. } = &task.action {
+            if directory.trim().is_empty() {
+                return Err(AppError::InvalidConfig("请选择导出的文件夹".to_string()));
+            }
+        }
+        let entry = Entry::new(task)?;
+        {
+            let mut entries = self.shared.entries.lock().unwrap();
+            match entries
+                .iter_mut()
+                .find(|existing| existing.task.id == entry.task.id)
+            {
+                Some(existing) => *existing = entry,
+                None => entries.push(entry),
+            }
+        }
+        self.persist();
+        self.shared.changed.notify_one();
+        Ok(self.list())
+    }
+
+    pub fn delete(&self, id: &str) -> AppResult<Vec<TaskStatus>> {
+        {
+            let mut entries = self.shared.entries.lock().unwrap();
+            let index = entries
+                .iter()
+                .position(|entry| entry.task.id == id)
+                .ok_or_else(|| AppError::ScheduledTaskNotFound(id.to_string()))?;
+            entries.remove(index);
+        }
+        self.shared.last_runs.lock().unwrap().remove(id);
+        self.persist();
+        self.shared.changed.notify_one();
+        Ok(self.list())
+    }
+
+    // 立即执行一次，不影响下次执行的时间
+    pub async fn run_now(&self, id: &str) -> AppResult<TaskRun> {
+        let task = self
+            .shared
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.task.id == id)
+            .map(|entry| entry.task.clone())
+            .ok_or_else(|| AppError::ScheduledTaskNotFound(id.to_string()))?;
+        Ok(execute(&self.shared, task).await)
+    }
+
+    fn persist(&self) {
+        let tasks: Vec<ScheduledTask> = self
+            .shared
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.task.clone())
+            .collect();
+        match serde_json::to_value(&tasks) {
+            Ok(value) => {
+                self.store.set(TASKS_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存计划任务失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化计划任务失败: {}", err),
+        }
+    }
+}
+
+async fn run(shared: Arc<Shared>) {
+    loop {
+        let next = shared
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|entry| entry.next_run)
+            .min();
+        // 已经过了执行时间的任务立即执行
+        let wait = match next {
+            Some(at) => (at - Local::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+                .min(MAX_SLEEP),
+            None => MAX_SLEEP,
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shared.changed.notified() => continue,
+        }
+
+        // 取出到期的任务并计算下次执行的时间
+        let now = Local::now();
+        let due: Vec<ScheduledTask> = shared
+            .entries
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .filter(|entry| entry.next_run.is_some_and(|at| at <= now))
+            .map(|entry| {
+                entry.next_run = entry.schedule.after(&now).next();
+                entry.task.clone()
+            })
+            .collect();
+        for task in due {
+            let shared = shared.clone();
+            tauri::async_runtime::spawn(async move {
+                execute(&shared, task).await;
+            });
+        }
+    }
+}
+
+async fn execute(shared: &Shared, task: ScheduledTask) -> TaskRun {
+    let started_at = chrono::Utc::now().timestamp_millis();
+    log::info!("执行计划任务: {}", task.name);
+    let result = perform(&shared.app, &task.action).await;
+    let run = TaskRun {
+        task_id: task.id.clone(),
+        started_at,
+        finished_at: chrono::Utc::now().timestamp_millis(),
+        success: result.is_ok(),
+        message: match result {
+            Ok(message) => message,
+            Err(err) => {
+                log::warn!("计划任务 {} 执行失败: {}", task.name, err);
+                Some(err.to_string())
+            }
+        },
+    };
+    shared
+        .last_runs
+        .lock()
+        .unwrap()
+        .insert(task.id.clone(), run.clone());
+    let _ = shared.app.emit(RUN_EVENT, &run);
+    run
+}
+
+// 成功时返回给前端显示的结果说明
+async fn perform(app: &AppHandle, action: &TaskAction) -> AppResult<Option<String>> {
+    match action {
+        TaskAction::StartFetching { room_ids } => {
+            let rooms = app.state::<RoomManager>();
+            let mut errors = rooms.start_all().await;
+            let connected = rooms.list_rooms();
+            for room_id in room_ids.iter().filter(|id| !connected.contains(id)) {
+                let source = DanmakuSource::Direct {
+                    room_id: *room_id,
+                    cookie: None,
+                    credential_id: None,
+                };
+                if let Err(err) = rooms.add_room(source).await {
+                    errors.push(err);
+                }
+            }
+            // 返回第一个错误，其余的只记录日志
+            let mut errors = errors.into_iter();
+            match errors.next() {
+                None => Ok(Some(format!(
+                    "已连接 {} 个直播间",
+                    rooms.list_rooms().len()
+                ))),
+                Some(first) => {
+                    for err in errors {
+                        log::warn!("计划任务连接直播间失败: {}", err);
+                    }
+                    Err(first)
+                }
+            }
+        }
+        TaskAction::StopFetching => {
+            app.state::<RoomManager>().stop_all();
+            Ok(None)
+        }
+        TaskAction::ExportEvents {
+            directory,
+            format,
+            days,
+        } => {
+            let now = Local::now();
+            let query = EventQuery {
+                start: Some(now.timestamp_millis() - DAY_MS * (*days).max(1) as i64),
+                end: Some(now.timestamp_millis()),
+                ..Default::default()
+            };
+            let path = PathBuf::from(directory).join(format!(
+                "events-{}.{}",
+                now.format("%Y%m%d-%H%M"),
+                format.extension()
+            ));
+            std::fs::create_dir_all(directory)?;
+            let store = app.state::<EventStore>().inner().clone();
+            let format = *format;
+            let count = {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || store.export(&query, format, &path))
+                    .await
+                    .map_err(|err| AppError::Io(std::io::Error::other(err)))??
+            };
+            Ok(Some(format!(
+                "已导出 {} 条事件到 {}",
+                count,
+                path.display()
+            )))
+        }
+        TaskAction::Cleanup { thumbnail_days } => {
+            app.state::<LogRetention>().cleanup_now().await;
+            let mut removed = 0;
+            if *thumbnail_days > 0 {
+                let max_age = Duration::from_secs(*thumbnail_days as u64 * 24 * 3600);
+                let registry = app.state::<FileServerRegistry>().inner();
+                removed = registry.purge_thumbnails(max_age);
+            }
+            Ok(Some(format!("已清理旧日志，删除 {} 个缩略图", removed)))
+        }
+    }
+}
+
+fn parse_cron(expression: &str) -> AppResult<Schedule> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    // cron 库要求包含秒，常见的 5 段表达式在最前面补上 0 秒，并把周字段转换为 cron 库的编号
+    let expression = if fields.len() == 5 {
+        format!(
+            "0 {} {}",
+            fields[..4].join(" "),
+            convert_weekday_field(fields[4])?
+        )
+    } else {
+        fields.join(" ")
+    };
+    Schedule::from_str(&expression)
+        .map_err(|err| AppError::InvalidConfig(format!("无效的 cron 表达式: {}", err)))
+}
+
+// crontab 的周字段用 0 到 6 表示周日到周六，7 也表示周日，cron 库则用 1 到 7 表示周日到周六
+// 数字展开为具体的日期列表后再转换，使用 MON 等英文缩写时两者相同，不做转换
+fn convert_weekday_field(field: &str) -> AppResult<String> {
+    if field == "*" || field == "?" || field.chars().any(|c| c.is_ascii_alphabetic()) {
+        return Ok(field.to_string());
+    }
+    let invalid = || AppError::InvalidConfig(format!("无效的 cron 周字段: {}", field));
+    let parse_day = |value: &str| value.parse::<u8>().ok().filter(|day| *day <= 7);
+    let mut days = BTreeSet::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (
+                range,
+                Some(
+                    step.parse::<usize>()
+                        .ok()
+                        .filter(|step| *step > 0)
+                        .ok_or_else(invalid)?,
+                ),
+            ),
+            None => (item, None),
+        };
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (
+                parse_day(start).ok_or_else(invalid)?,
+                parse_day(end).ok_or_else(invalid)?,
+            ),
+            None if range == "*" => (0, 6),
+            // 带步长的单个数字表示从该日开始到周六，例如 5/1 为周五和周六
+            None => {
+                let start = parse_day(range).ok_or_else(invalid)?;
+                (start, if step.is_some() { start.max(6) } else { start })
+            }
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        days.extend(
+            (start..=end)
+                .step_by(step.unwrap_or(1))
+                .map(|day| day % 7 + 1),
+        );
+    }
+    Ok(days.iter().map(u8::to_string).collect::<Vec<_>>().join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::convert_weekday_field;
+
+    #[test]
+    fn converts_weekday_field() {
+        // 标准 cron 中 0 和 7 为周日，cron 库中 1 为周日、7 为周六
+        let cases = [
+            ("*", "*"),
+            ("?", "?"),
+            ("0", "1"),
+            ("7", "1"),
+            ("0,7", "1"),
+            ("1-5", "2,3,4,5,6"),
+            ("6-7", "1,7"),
+            ("*/2", "1,3,5,7"),
+            ("5/1", "6,7"),
+            ("5/2", "6"),
+            ("1-5/2", "2,4,6"),
+            ("MON-FRI", "MON-FRI"),
+        ];
+        for (field, expected) in cases {
+            assert_eq!(convert_weekday_field(field).unwrap(), expected, "{}", field);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_weekday_field() {
+        for field in ["", "8", "5-1", "1-8", "*/0", "1/", "-1", "1,,2"] {
+            assert!(convert_weekday_field(field).is_err(), "{}", field);
+        }
+    }
+}
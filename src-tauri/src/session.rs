@@ -0,0 +1,176 @@
+use crate::danmaku::DanmakuEvent;
+use crate::error::AppResult;
+use crate::event_bus::Subscriber;
+use crate::event_store::{EventStore, Session, SessionSource};
+use crate::room_info::{RoomInfo, RoomPoll};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast;
+
+// 接口不可用时，超过该时间没有事件视为下播
+const GAP_MS: i64 = 30 * 60 * 1000;
+
+// 开播或下播时发送给前端的事件，内容为对应的 Session
+pub const SESSION_EVENT: &str = "session://changed";
+
+#[derive(Default)]
+struct Track {
+    session: Option<Session>,
+    // 最近一次接口返回的开播状态，接口不可用时为空
+    live: Option<bool>,
+    last_event_at: Option<i64>,
+}
+
+struct Shared {
+    app: AppHandle,
+    store: EventStore,
+    tracks: Mutex<HashMap<u64, Track>>,
+}
+
+impl Shared {
+    fn start_session(
+        &self,
+        track: &mut Track,
+        room_id: u64,
+        start: i64,
+        source: SessionSource,
+        title: Option<&str>,
+    ) {
+        match self.store.start_session(room_id, start, source, title) {
+            Ok(id) => {
+                let session = Session {
+                    id,
+                    room_id,
+                    start,
+                    end: None,
+                    source,
+                    title: title.map(str::to_string),
+                };
+                log::info!("直播间 {} 开播，记录为第 {} 场直播", room_id, id);
+                let _ = self.app.emit(SESSION_EVENT, &session);
+                track.session = Some(session);
+            }
+            Err(err) => log::warn!("保存直播记录失败: {}", err),
+        }
+    }
+
+    fn end_session(&self, track: &mut Track, end: i64) {
+        let Some(mut session) = track.session.take() else {
+            return;
+        };
+        let end = end.max(session.start);
+        if let Err(err) = self.store.end_session(session.id, end) {
+            log::warn!("保存直播记录失败: {}", err);
+        }
+        log::info!("直播间 {} 下播", session.room_id);
+        session.end = Some(end);
+        let _ = self.app.emit(SESSION_EVENT, &session);
+    }
+
+    // 接口不可用且还没有进行中的直播时，收到事件视为开播
+    fn on_event(&self, event: &DanmakuEvent) {
+        let mut tracks = self.tracks.lock().unwrap();
+        let track = tracks.entry(event.room_id).or_default();
+        track.last_event_at = Some(event.timestamp);
+        if track.session.is_none() && track.live.is_none() {
+            self.start_session(
+                track,
+                event.room_id,
+                event.timestamp,
+                SessionSource::Gap,
+                None,
+            );
+        }
+    }
+
+    fn on_live_info(&self, info: &RoomInfo) {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut tracks = self.tracks.lock().unwrap();
+        let track = tracks.entry(info.room_id).or_default();
+        track.live = Some(info.live);
+        match (info.live, track.session.is_some()) {
+            (true, false) => {
+                let title = Some(info.title.as_str()).filter(|title| !title.is_empty());
+                let start = info.live_time.unwrap_or(now);
+                self.start_session(track, info.room_id, start, SessionSource::Api, title);
+            }
+            (false, true) => self.end_session(track, now),
+            _ => {}
+        }
+    }
+
+    // 接口不可用时，根据最后一个事件的时间判断是否下播
+    fn on_poll_failed(&self, room_id: u64) {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut tracks = self.tracks.lock().unwrap();
+        let Some(track) = tracks.get_mut(&room_id) else {
+            return;
+        };
+        track.live = None;
+        let Some(session) = &track.session else {
+            return;
+        };
+        let last = track.last_event_at.unwrap_or(session.start);
+        if now - last > GAP_MS {
+            self.end_session(track, last);
+        }
+    }
+
+    fn on_poll(&self, poll: &RoomPoll) {
+        match &poll.info {
+            Some(info) => self.on_live_info(info),
+            None => self.on_poll_failed(poll.room_id),
+        }
+    }
+}
+
+// 根据 RoomInfoCache 查询到的开播状态或事件间隔划分直播场次
+pub struct SessionTracker;
+
+impl SessionTracker {
+    pub fn start(
+        app: &AppHandle,
+        store: EventStore,
+        events: Subscriber<DanmakuEvent>,
+        polls: broadcast::Receiver<RoomPoll>,
+    ) -> AppResult<()> {
+        // 上次运行时未结束的直播继续跟踪，下播时补上结束时间
+        let mut tracks: HashMap<u64, Track> = HashMap::new();
+        for session in store.open_sessions()? {
+            let track = tracks.entry(session.room_id).or_default();
+            if let Some(previous) = track.session.replace(session) {
+                store.end_session(previous.id, previous.start)?;
+            }
+        }
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            store,
+            tracks: Mutex::new(tracks),
+        });
+        tauri::async_runtime::spawn(run(shared, events, polls));
+        Ok(())
+    }
+}
+
+async fn run(
+    shared: Arc<Shared>,
+    mut events: Subscriber<DanmakuEvent>,
+    mut polls: broadcast::Receiver<RoomPoll>,
+) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if event.replay || event.simulated => {}
+                Ok(event) => shared.on_event(&event),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            poll = polls.recv() => match poll {
+                Ok(poll) => shared.on_poll(&poll),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::admin_api::{AdminApi, AdminApiConfig};
+use crate::asset_cache::{AssetCache, AssetCacheConfig};
+use crate::auto_thank::{AutoThank, AutoThankConfig};
+use crate::automation::{AutomationEngine, AutomationRule};
+use crate::autostart::{Autostart, AutostartConfig};
+use crate::crash::{CrashConfig, CrashReporter};
+use crate::device_output::{DeviceOutput, DeviceOutputConfig};
+use crate::dns::{DnsConfig, DnsSettings};
+use crate::error::AppResult;
+use crate::hotkeys::{HotkeyBinding, Hotkeys};
+use crate::keep_awake::{KeepAwake, KeepAwakeConfig};
+use crate::live_control::{LiveControl, LiveControlConfig};
+use crate::logs::{LogRetention, LogRetentionConfig};
+use crate::notifications::{NotificationConfig, NotificationManager};
+use crate::obs::{ObsClient, ObsConfig};
+use crate::points::{PointsConfig, PointsManager};
+use crate::process_watch::{ProcessWatchConfig, ProcessWatcher};
+use crate::proxy::{ProxyConfig, ProxySettings};
+use crate::raffle::{RaffleConfig, RaffleManager};
+use crate::relay::{Relay, RelayConfig};
+use crate::revenue::{Revenue, RevenueConfig};
+use crate::song_request::{SongRequestConfig, SongRequestManager};
+use crate::sound::{SoundConfig, SoundPlayer};
+use crate::tray::{CloseSettings, WindowBehavior};
+use crate::tts::{TtsConfig, TtsManager};
+use crate::update::{UpdateConfig, UpdateManager};
+use crate::viewer_queue::{ViewerQueueConfig, ViewerQueueManager};
+use crate::ws_server::{WsServer, WsServerConfig};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+mod migrate;
+mod portable;
+mod profiles;
+
+pub use migrate::{migrate, SCHEMA_VERSION};
+pub use portable::{export, import};
+pub use profiles::{ProfileList, Profiles};
+
+// 通过 update_settings 修改设置后通知前端和其他窗口
+pub const CHANGED_EVENT: &str = "settings-changed";
+
+// 所有模块的设置，每一部分仍由对应的模块保存和校验，这里只负责统一读取和修改
+#[derive(Debug, Clone, Serialize)]
+pub struct Settings {
+    pub version: u32,
+    pub window: CloseSettings,
+    pub autostart: AutostartConfig,
+    pub keep_awake: KeepAwakeConfig,
+    pub proxy: ProxyConfig,
+    pub dns: DnsConfig,
+    pub update: UpdateConfig,
+    pub crash: CrashConfig,
+    pub log_retention: LogRetentionConfig,
+    pub process_watch: ProcessWatchConfig,
+    pub relay: RelayConfig,
+    pub ws_server: WsServerConfig,
+    pub device_output: DeviceOutputConfig,
+    pub admin_api: AdminApiConfig,
+    pub obs: ObsConfig,
+    pub tts: TtsConfig,
+    pub sound: SoundConfig,
+    pub song_request: SongRequestConfig,
+    pub viewer_queue: ViewerQueueConfig,
+    pub raffle: RaffleConfig,
+    pub points: PointsConfig,
+    pub notifications: NotificationConfig,
+    pub asset_cache: AssetCacheConfig,
+    pub auto_thank: AutoThankConfig,
+    pub live_control: LiveControlConfig,
+    pub revenue: RevenueConfig,
+    pub hotkeys: Vec<HotkeyBinding>,
+    pub automation: Vec<AutomationRule>,
+}
+
+// 需要修改的部分，未提供的部分保持不变，提供的部分中缺少的字段使用默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SettingsUpdate {
+    pub window: Option<CloseSettings>,
+    pub autostart: Option<AutostartConfig>,
+    pub keep_awake: Option<KeepAwakeConfig>,
+    pub proxy: Option<ProxyConfig>,
+    pub dns: Option<DnsConfig>,
+    pub update: Option<UpdateConfig>,
+    pub crash: Option<CrashConfig>,
+    pub log_retention: Option<LogRetentionConfig>,
+    pub process_watch: Option<ProcessWatchConfig>,
+    pub relay: Option<RelayConfig>,
+    pub ws_server: Option<WsServerConfig>,
+    pub device_output: Option<DeviceOutputConfig>,
+    pub admin_api: Option<AdminApiConfig>,
+    pub obs: Option<ObsConfig>,
+    pub tts: Option<TtsConfig>,
+    pub sound: Option<SoundConfig>,
+    pub song_request: Option<SongRequestConfig>,
+    pub viewer_queue: Option<ViewerQueueConfig>,
+    pub raffle: Option<RaffleConfig>,
+    pub points: Option<PointsConfig>,
+    pub notifications: Option<NotificationConfig>,
+    pub asset_cache: Option<AssetCacheConfig>,
+    pub auto_thank: Option<AutoThankConfig>,
+    pub live_control: Option<LiveControlConfig>,
+    pub revenue: Option<RevenueConfig>,
+    pub hotkeys: Option<Vec<HotkeyBinding>>,
+    pub automation: Option<Vec<AutomationRule>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsChanged {
+    // 本次修改的部分
+    pub sections: Vec<&'static str>,
+    pub settings: Settings,
+}
+
+pub fn get(app: &AppHandle) -> Settings {
+    Settings {
+        version: SCHEMA_VERSION,
+        window: app.state::<WindowBehavior>().get(),
+        autostart: app.state::<Autostart>().get_config(),
+        keep_awake: app.state::<KeepAwake>().get_config(),
+        proxy: app.state::<ProxySettings>().get_config(),
+        dns: app.state::<DnsSettings>().get_config(),
+        update: app.state::<UpdateManager>().get_config(),
+        crash: app.state::<CrashReporter>().get_config(),
+        log_retention: app.state::<LogRetention>().get_config(),
+        process_watch: app.state::<ProcessWatcher>().get_config(),
+        relay: app.state::<Relay>().get_config(),
+        ws_server: app.state::<WsServer>().status().config,
+        device_output: app.state::<DeviceOutput>().status().config,
+        admin_api: app.state::<AdminApi>().status().config,
+        obs: app.state::<ObsClient>().get_config(),
+        tts: app.state::<TtsManager>().get_config(),
+        sound: app.state::<SoundPlayer>().get_config(),
+        song_request: app.state::<SongRequestManager>().get_config(),
+        viewer_queue: app.state::<ViewerQueueManager>().get_config(),
+        raffle: app.state::<RaffleManager>().get_config(),
+        points: app.state::<PointsManager>().get_config(),
+        notifications: app.state::<NotificationManager>().get_config(),
+        asset_cache: app.state::<AssetCache>().get_config(),
+        auto_thank: app.state::<AutoThank>().get_config(),
+        live_control: app.state::<LiveControl>().get_config(),
+        revenue: app.state::<Revenue>().get_config(),
+        hotkeys: app.state::<Hotkeys>().bindings(),
+        automation: app.state::<AutomationEngine>().rules(),
+    }
+}
+
+// 按顺序交给各模块校验并保存，某一部分校验失败时返回错误，之前的部分已经生效，
+// 这些部分同样会通知前端，避免其他窗口显示旧的设置
+pub async fn update(app: &AppHandle, update: SettingsUpdate) -> AppResult<Settings> {
+    let mut sections = Vec::new();
+    let result = apply(app, update, &mut sections).await;
+    let settings = get(app);
+    if !sections.is_empty() {
+        let _ = app.emit(
+            CHANGED_EVENT,
+            SettingsChanged {
+                sections,
+                settings: settings.clone(),
+            },
+        );
+    }
+    result.map(|()| settings)
+}
+
+// 依次应用提供的部分，已经生效的部分记录在 sections 中
+async fn apply(
+    app: &AppHandle,
+    update: SettingsUpdate,
+    sections: &mut Vec<&'static str>,
+) -> AppResult<()> {
+    if let Some(window) = update.window {
+        app.state::<WindowBehavior>().set(window);
+        sections.push("window");
+    }
+    if let Some(autostart) = update.autostart {
+        app.state::<Autostart>().update_config(autostart)?;
+        sections.push("autostart");
+    }
+    if let Some(keep_awake) = update.keep_awake {
+        app.state::<KeepAwake>().update_config(keep_awake)?;
+        sections.push("keep_awake");
+    }
+    if let Some(proxy) = update.proxy {
+        app.state::<ProxySettings>().update_config(proxy)?;
+        sections.push("proxy");
+    }
+    if let Some(dns) = update.dns {
+        app.state::<DnsSettings>().update_config(dns)?;
+        sections.push("dns");
+    }
+    if let Some(config) = update.update {
+        app.state::<UpdateManager>().update_config(config);
+        sections.push("update");
+    }
+    if let Some(crash) = update.crash {
+        app.state::<CrashReporter>().update_config(crash)?;
+        sections.push("crash");
+    }
+    if let Some(retention) = update.log_retention {
+        app.state::<LogRetention>().update_config(retention)?;
+        sections.push("log_retention");
+    }
+    if let Some(process_watch) = update.process_watch {
+        app.state::<ProcessWatcher>().update_config(process_watch)?;
+        sections.push("process_watch");
+    }
+    if let Some(relay) = update.relay {
+        app.state::<Relay>().update_config(relay)?;
+        sections.push("relay");
+    }
+    if let Some(ws_server) = update.ws_server {
+        app.state::<WsServer>().update_config(ws_server).await?;
+        sections.push("ws_server");
+    }
+    if let Some(device_output) = update.device_output {
+        app.state::<DeviceOutput>()
+            .update_config(device_output)
+            .await?;
+        sections.push("device_output");
+    }
+    if let Some(admin_api) = update.admin_api {
+        app.state::<AdminApi>().update_config(admin_api).await?;
+        sections.push("admin_api");
+    }
+    if let Some(obs) = update.obs {
+        app.state::<ObsClient>().update_config(obs)?;
+        sections.push("obs");
+    }
+    if let Some(tts) = update.tts {
+        app.state::<TtsManager>().update_config(tts)?;
+        sections.push("tts");
+    }
+    if let Some(sound) = update.sound {
+        app.state::<SoundPlayer>().update_config(sound)?;
+        sections.push("sound");
+    }
+    if let Some(song_request) = update.song_request {
+        app.state::<SongRequestManager>()
+            .update_config(song_request)?;
+        sections.push("song_request");
+    }
+    if let Some(viewer_queue) = update.viewer_queue {
+        app.state::<ViewerQueueManager>()
+            .update_config(viewer_queue)?;
+        sections.push("viewer_queue");
+    }
+    if let Some(raffle) = update.raffle {
+        app.state::<RaffleManager>().update_config(raffle)?;
+        sections.push("raffle");
+    }
+    if let Some(points) = update.points {
+        app.state::<PointsManager>().update_config(points)?;
+        sections.push("points");
+    }
+    if let Some(notifications) = update.notifications {
+        app.state::<NotificationManager>()
+            .update_config(notifications)?;
+        sections.push("notifications");
+    }
+    if let Some(asset_cache) = update.asset_cache {
+        app.state::<AssetCache>().update_config(asset_cache).await?;
+        sections.push("asset_cache");
+    }
+    if let Some(auto_thank) = update.auto_thank {
+        app.state::<AutoThank>().update_config(auto_thank)?;
+        sections.push("auto_thank");
+    }
+    if let Some(live_control) = update.live_control {
+        app.state::<LiveControl>().update_config(live_control)?;
+        sections.push("live_control");
+    }
+    if let Some(revenue) = update.revenue {
+        app.state::<Revenue>().update_config(revenue)?;
+        sections.push("revenue");
+    }
+    if let Some(hotkeys) = update.hotkeys {
+        app.state::<Hotkeys>().update(hotkeys)?;
+        sections.push("hotkeys");
+    }
+    if let Some(rules) = update.automation {
+        app.state::<AutomationEngine>().replace_rules(rules)?;
+        sections.push("automation");
+    }
+    Ok(())
+}
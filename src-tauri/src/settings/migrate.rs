@@ -0,0 +1,81 @@
+use crate::{
+    admin_api, asset_cache, auto_thank, autostart, crash, device_output, dns, keep_awake,
+    live_control, logs, notifications, obs, points, process_watch, proxy, raffle, relay, revenue,
+    song_request, sound, tray, tts, update, viewer_queue, ws_server,
+};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+// 保存设置版本号的文件，位于应用数据目录
+const STORE_FILE: &str = "settings.json";
+const VERSION_KEY: &str = "version";
+
+// 当前的设置版本，等于迁移步骤的数量
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+// 各模块保存设置的文件和键，使用模块自己的常量，避免文件名不一致
+const SECTIONS: &[(&str, &str)] = &[
+    (tray::STORE_FILE, tray::CONFIG_KEY),
+    (autostart::STORE_FILE, autostart::CONFIG_KEY),
+    (keep_awake::STORE_FILE, keep_awake::CONFIG_KEY),
+    (proxy::STORE_FILE, proxy::CONFIG_KEY),
+    (dns::STORE_FILE, dns::CONFIG_KEY),
+    (update::STORE_FILE, update::CONFIG_KEY),
+    (crash::STORE_FILE, crash::CONFIG_KEY),
+    (logs::retention::STORE_FILE, logs::retention::CONFIG_KEY),
+    (process_watch::STORE_FILE, process_watch::CONFIG_KEY),
+    (relay::STORE_FILE, relay::CONFIG_KEY),
+    (ws_server::STORE_FILE, ws_server::CONFIG_KEY),
+    (device_output::STORE_FILE, device_output::CONFIG_KEY),
+    (admin_api::STORE_FILE, admin_api::CONFIG_KEY),
+    (obs::STORE_FILE, obs::CONFIG_KEY),
+    (tts::STORE_FILE, tts::CONFIG_KEY),
+    (sound::STORE_FILE, sound::CONFIG_KEY),
+    (song_request::STORE_FILE, song_request::CONFIG_KEY),
+    (viewer_queue::STORE_FILE, viewer_queue::CONFIG_KEY),
+    (raffle::STORE_FILE, raffle::CONFIG_KEY),
+    (points::STORE_FILE, points::CONFIG_KEY),
+    (notifications::STORE_FILE, notifications::CONFIG_KEY),
+    (asset_cache::STORE_FILE, asset_cache::CONFIG_KEY),
+    (auto_thank::STORE_FILE, auto_thank::CONFIG_KEY),
+    (live_control::STORE_FILE, live_control::CONFIG_KEY),
+    (revenue::STORE_FILE, revenue::CONFIG_KEY),
+];
+
+type Migration = fn(&AppHandle) -> tauri_plugin_store::Result<()>;
+
+// 第 n 项把设置从版本 n 升级到 n + 1，只能在末尾追加
+const MIGRATIONS: &[Migration] = &[drop_invalid_sections];
+
+// 在各模块读取设置之前调用，依次执行尚未执行的迁移
+pub fn migrate(app: &AppHandle) -> tauri_plugin_store::Result<()> {
+    let store = app.store(STORE_FILE)?;
+    let version = store
+        .get(VERSION_KEY)
+        .and_then(|value| value.as_u64())
+        .unwrap_or(0) as usize;
+    if version > MIGRATIONS.len() {
+        log::warn!("设置来自更新的版本 {}，跳过迁移", version);
+        return Ok(());
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        migration(app)?;
+        store.set(VERSION_KEY, index + 1);
+        store.save()?;
+        log::info!("设置已升级到版本 {}", index + 1);
+    }
+    Ok(())
+}
+
+// 版本 1：删除格式不是对象的设置，例如手动编辑后保存的 null，加载时改用默认值
+fn drop_invalid_sections(app: &AppHandle) -> tauri_plugin_store::Result<()> {
+    for (file, key) in SECTIONS {
+        let store = app.store(*file)?;
+        if store.get(key).is_some_and(|value| !value.is_object()) {
+            log::warn!("{} 中的设置 {} 格式无效，已重置", file, key);
+            store.delete(key);
+            store.save()?;
+        }
+    }
+    Ok(())
+}
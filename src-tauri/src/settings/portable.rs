@@ -0,0 +1,101 @@
+use super::{Settings, SettingsUpdate, SCHEMA_VERSION};
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+// 导出文件的标识，导入时据此拒绝其他 JSON 文件
+const FORMAT: &str = "vtsuru-fetcher-config";
+
+// 密码和令牌所在的部分和字段，导出时可以排除
+const SECRETS: &[(&str, &str)] = &[
+    ("proxy", "password"),
+    ("relay", "token"),
+    ("ws_server", "access_token"),
+    ("admin_api", "token"),
+    ("obs", "password"),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PortableConfig {
+    format: String,
+    version: u32,
+    exported_at: i64,
+    // 为 true 时导入不覆盖本机已有的密码和令牌
+    #[serde(default)]
+    secrets_excluded: bool,
+    settings: serde_json::Value,
+}
+
+// 把所有设置写入一个 JSON 文件，便于备份或在其他电脑上导入
+pub fn export(app: &AppHandle, path: &Path, include_secrets: bool) -> AppResult<()> {
+    let mut settings = serde_json::to_value(super::get(app))
+        .map_err(|err| AppError::InvalidConfig(format!("序列化设置失败: {}", err)))?;
+    if let Some(settings) = settings.as_object_mut() {
+        settings.remove("version");
+        if !include_secrets {
+            for (section, field) in SECRETS {
+                if let Some(section) = settings.get_mut(*section).and_then(|v| v.as_object_mut()) {
+                    section.remove(*field);
+                }
+            }
+        }
+    }
+    let config = PortableConfig {
+        format: FORMAT.to_string(),
+        version: SCHEMA_VERSION,
+        exported_at: chrono::Utc::now().timestamp_millis(),
+        secrets_excluded: !include_secrets,
+        settings,
+    };
+    let content = serde_json::to_vec_pretty(&config)
+        .map_err(|err| AppError::InvalidConfig(format!("序列化设置失败: {}", err)))?;
+    std::fs::write(path, content)?;
+    log::info!("设置已导出到 {}", path.display());
+    Ok(())
+}
+
+// 导入文件中包含的部分，其余设置保持不变
+pub async fn import(app: &AppHandle, path: &Path) -> AppResult<Settings> {
+    let content = std::fs::read(path)?;
+    let config: PortableConfig = serde_json::from_slice(&content)
+        .map_err(|err| AppError::InvalidConfig(format!("配置文件格式无效: {}", err)))?;
+    if config.format != FORMAT {
+        return Err(AppError::InvalidConfig(
+            "不是本程序导出的配置文件".to_string(),
+        ));
+    }
+    if config.version > SCHEMA_VERSION {
+        return Err(AppError::InvalidConfig(format!(
+            "配置文件来自更新的版本 {}，请先更新客户端",
+            config.version
+        )));
+    }
+    let mut update: SettingsUpdate = serde_json::from_value(config.settings)
+        .map_err(|err| AppError::InvalidConfig(format!("配置文件格式无效: {}", err)))?;
+    if config.secrets_excluded {
+        keep_secrets(&mut update, &super::get(app));
+    }
+    let settings = super::update(app, update).await?;
+    log::info!("已从 {} 导入设置", path.display());
+    Ok(settings)
+}
+
+// 导出时排除的字段在导入时为空，改用本机当前的值
+fn keep_secrets(update: &mut SettingsUpdate, current: &Settings) {
+    if let Some(proxy) = update.proxy.as_mut() {
+        proxy.password = current.proxy.password.clone();
+    }
+    if let Some(relay) = update.relay.as_mut() {
+        relay.token = current.relay.token.clone();
+    }
+    if let Some(ws_server) = update.ws_server.as_mut() {
+        ws_server.access_token = current.ws_server.access_token.clone();
+    }
+    if let Some(admin_api) = update.admin_api.as_mut() {
+        admin_api.token = current.admin_api.token.clone();
+    }
+    if let Some(obs) = update.obs.as_mut() {
+        obs.password = current.obs.password.clone();
+    }
+}
@@ -0,0 +1,176 @@
+use super::{Settings, SettingsUpdate};
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// 保存配置方案的文件，位于应用数据目录
+const STORE_FILE: &str = "profiles.json";
+const PROFILES_KEY: &str = "profiles";
+const ACTIVE_KEY: &str = "active";
+
+// 方案中保存的部分，窗口、更新、日志和快捷键等与直播内容无关的设置不随方案切换
+const SECTIONS: &[&str] = &[
+    "proxy",
+    "dns",
+    "relay",
+    "process_watch",
+    "ws_server",
+    "device_output",
+    "admin_api",
+    "obs",
+    "tts",
+    "sound",
+    "song_request",
+    "viewer_queue",
+    "raffle",
+    "points",
+    "notifications",
+    "auto_thank",
+    "revenue",
+    "automation",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Profile {
+    name: String,
+    updated_at: i64,
+    settings: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileList {
+    // 最近保存或切换到的方案，之后修改的设置不会自动保存到方案中
+    pub active: Option<String>,
+    pub profiles: Vec<ProfileSummary>,
+}
+
+// 配置方案，例如“游戏直播”和“杂谈直播”使用不同的朗读、点歌和自动化设置
+pub struct Profiles {
+    app: AppHandle,
+    store: Arc<Store<Wry>>,
+    profiles: Mutex<Vec<Profile>>,
+}
+
+impl Profiles {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let profiles = store
+            .get(PROFILES_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Ok(Profiles {
+            app: app.clone(),
+            store,
+            profiles: Mutex::new(profiles),
+        })
+    }
+
+    pub fn list(&self) -> ProfileList {
+        ProfileList {
+            active: self
+                .store
+                .get(ACTIVE_KEY)
+                .and_then(|value| value.as_str().map(str::to_string)),
+            profiles: self
+                .profiles
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|profile| ProfileSummary {
+                    name: profile.name.clone(),
+                    updated_at: profile.updated_at,
+                })
+                .collect(),
+        }
+    }
+
+    // 把当前设置保存为方案，同名方案会被覆盖
+    pub fn save(&self, name: &str) -> AppResult<ProfileList> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::InvalidConfig("方案名称不能为空".to_string()));
+        }
+        let settings = match serde_json::to_value(super::get(&self.app)) {
+            Ok(serde_json::Value::Object(settings)) => settings
+                .into_iter()
+                .filter(|(section, _)| SECTIONS.contains(&section.as_str()))
+                .collect(),
+            Ok(_) => serde_json::Map::new(),
+            Err(err) => {
+                return Err(AppError::InvalidConfig(format!("序列化设置失败: {}", err)));
+            }
+        };
+        let profile = Profile {
+            name: name.to_string(),
+            updated_at: chrono::Utc::now().timestamp_millis(),
+            settings,
+        };
+        {
+            let mut profiles = self.profiles.lock().unwrap();
+            match profiles.iter_mut().find(|existing| existing.name == name) {
+                Some(existing) => *existing = profile,
+                None => profiles.push(profile),
+            }
+        }
+        self.store.set(ACTIVE_KEY, name);
+        self.persist();
+        Ok(self.list())
+    }
+
+    // 切换到方案，方案中保存的部分立即生效
+    pub async fn apply(&self, name: &str) -> AppResult<Settings> {
+        let profile = self
+            .profiles
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|profile| profile.name == name)
+            .cloned()
+            .ok_or_else(|| AppError::ProfileNotFound(name.to_string()))?;
+        let update: SettingsUpdate =
+            serde_json::from_value(serde_json::Value::Object(profile.settings))
+                .map_err(|err| AppError::InvalidConfig(format!("方案格式无效: {}", err)))?;
+        let settings = super::update(&self.app, update).await?;
+        self.store.set(ACTIVE_KEY, name);
+        self.persist();
+        log::info!("已切换到配置方案 {}", name);
+        Ok(settings)
+    }
+
+    pub fn delete(&self, name: &str) -> AppResult<ProfileList> {
+        {
+            let mut profiles = self.profiles.lock().unwrap();
+            let index = profiles
+                .iter()
+                .position(|profile| profile.name == name)
+                .ok_or_else(|| AppError::ProfileNotFound(name.to_string()))?;
+            profiles.remove(index);
+        }
+        if self.list().active.as_deref() == Some(name) {
+            self.store.delete(ACTIVE_KEY);
+        }
+        self.persist();
+        Ok(self.list())
+    }
+
+    fn persist(&self) {
+        let profiles = self.profiles.lock().unwrap().clone();
+        match serde_json::to_value(&profiles) {
+            Ok(value) => {
+                self.store.set(PROFILES_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存配置方案失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化配置方案失败: {}", err),
+        }
+    }
+}
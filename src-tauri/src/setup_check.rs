@@ -0,0 +1,331 @@
+use crate::admin_api::AdminApi;
+use crate::credentials::CredentialManager;
+use crate::diagnostics::NetworkDiagnostics;
+use crate::file_server::{port, FileServerRegistry, BIND_ALL_INTERFACES, BIND_LOCALHOST};
+use crate::ws_server::WsServer;
+use serde::Serialize;
+use std::net::IpAddr;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::ManagerExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    // 不影响使用，但建议处理
+    Warning,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckCategory {
+    Network,
+    Port,
+    Credential,
+    Autostart,
+    Firewall,
+}
+
+// 检查清单中的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupCheck {
+    pub category: CheckCategory,
+    pub title: String,
+    pub status: CheckStatus,
+    pub message: String,
+    // 未通过时建议的处理方法
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupReport {
+    pub timestamp: i64,
+    // 没有未通过的项
+    pub passed: bool,
+    pub checks: Vec<SetupCheck>,
+}
+
+// 需要检查端口的服务器
+struct Listener {
+    title: String,
+    bind_address: String,
+    port: u16,
+    active_port: Option<u16>,
+}
+
+impl SetupCheck {
+    fn new(category: CheckCategory, title: impl Into<String>, status: CheckStatus) -> Self {
+        SetupCheck {
+            category,
+            title: title.into(),
+            status,
+            message: String::new(),
+            hint: None,
+        }
+    }
+
+    fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+// 首次运行引导时检查运行环境，每一项都不会因为其他项失败而中断
+pub async fn run(app: &AppHandle) -> SetupReport {
+    let listeners = listeners(app);
+    let (network, ports, credentials) = tokio::join!(
+        check_network(app),
+        check_ports(&listeners),
+        check_credentials(app)
+    );
+    let mut checks = network;
+    checks.extend(ports);
+    checks.extend(credentials);
+    checks.push(check_autostart(app));
+    checks.extend(check_firewall(&listeners));
+    SetupReport {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        passed: checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Failed),
+        checks,
+    }
+}
+
+async fn check_network(app: &AppHandle) -> Vec<SetupCheck> {
+    let Some(diagnostics) = app.try_state::<NetworkDiagnostics>() else {
+        return Vec::new();
+    };
+    let report = match diagnostics.run_check(Vec::new()).await {
+        Ok(report) => report,
+        Err(err) => {
+            return vec![
+                SetupCheck::new(CheckCategory::Network, "网络", CheckStatus::Failed)
+                    .message(err.to_string()),
+            ]
+        }
+    };
+    report
+        .results
+        .into_iter()
+        .map(|result| match result.error {
+            None => SetupCheck::new(CheckCategory::Network, result.name, CheckStatus::Passed)
+                .message(format!(
+                    "连接正常，耗时 {} 毫秒",
+                    result.tcp_ms.unwrap_or_default() + result.tls_ms.unwrap_or_default()
+                )),
+            Some(err) => SetupCheck::new(CheckCategory::Network, result.name, CheckStatus::Failed)
+                .message(err)
+                .hint("请检查网络连接，使用代理时确认代理设置正确"),
+        })
+        .collect()
+}
+
+// 文件服务器、事件广播服务器和管理接口，未运行的服务器只检查会随应用启动的
+fn listeners(app: &AppHandle) -> Vec<Listener> {
+    let mut listeners = Vec::new();
+    if let Some(registry) = app.try_state::<FileServerRegistry>() {
+        for status in registry.list() {
+            let auto_start = registry
+                .get(Some(&status.name))
+                .map(|server| server.get_config().auto_start)
+                .unwrap_or(false);
+            if status.running || auto_start {
+                listeners.push(Listener {
+                    title: format!("文件服务器 {}", status.name),
+                    bind_address: status.bind_address,
+                    port: status.port,
+                    active_port: status.active_port,
+                });
+            }
+        }
+    }
+    if let Some(ws_server) = app.try_state::<WsServer>() {
+        let status = ws_server.status();
+        if status.running || status.config.enabled {
+            listeners.push(Listener {
+                title: "事件广播服务器".to_string(),
+                bind_address: if status.config.allow_lan {
+                    BIND_ALL_INTERFACES
+                } else {
+                    BIND_LOCALHOST
+                }
+                .to_string(),
+                port: status.config.port,
+                active_port: status.active_port,
+            });
+        }
+    }
+    if let Some(admin_api) = app.try_state::<AdminApi>() {
+        let status = admin_api.status();
+        if status.running || status.config.enabled {
+            listeners.push(Listener {
+                title: "管理接口".to_string(),
+                bind_address: BIND_LOCALHOST.to_string(),
+                port: status.config.port,
+                active_port: status.active_port,
+            });
+        }
+    }
+    listeners
+}
+
+async fn check_ports(listeners: &[Listener]) -> Vec<SetupCheck> {
+    let mut checks = Vec::new();
+    for listener in listeners {
+        let check = SetupCheck::new(CheckCategory::Port, &listener.title, CheckStatus::Passed);
+        if let Some(active_port) = listener.active_port {
+            checks.push(check.message(format!("正在监听端口 {}", active_port)));
+            continue;
+        }
+        let Ok(ip) = listener.bind_address.parse::<IpAddr>() else {
+            checks.push(
+                SetupCheck::new(CheckCategory::Port, &listener.title, CheckStatus::Failed)
+                    .message(format!("无效的监听地址: {}", listener.bind_address)),
+            );
+            continue;
+        };
+        // 绑定成功后立即释放端口
+        checks.push(match port::bind(ip, listener.port, false).await {
+            Ok(_) => check.message(format!("端口 {} 可用", listener.port)),
+            Err(err) => SetupCheck::new(CheckCategory::Port, &listener.title, CheckStatus::Failed)
+                .message(err.to_string())
+                .hint("请关闭占用端口的程序，或在设置中修改端口"),
+        });
+    }
+    checks
+}
+
+async fn check_credentials(app: &AppHandle) -> Vec<SetupCheck> {
+    let Some(credentials) = app.try_state::<CredentialManager>() else {
+        return Vec::new();
+    };
+    let list = credentials.list();
+    if list.is_empty() {
+        return vec![
+            SetupCheck::new(CheckCategory::Credential, "B 站账号", CheckStatus::Warning)
+                .message("未添加账号，将以游客身份连接直播间")
+                .hint("游客身份收到的弹幕中用户名会被隐藏，建议扫码登录"),
+        ];
+    }
+    let mut checks = Vec::new();
+    for credential in list {
+        let title = if credential.label.is_empty() {
+            credential.masked.clone()
+        } else {
+            credential.label.clone()
+        };
+        checks.push(match credentials.validate(&credential.id).await {
+            Ok(validation) if validation.valid => {
+                SetupCheck::new(CheckCategory::Credential, title, CheckStatus::Passed).message(
+                    validation
+                        .uname
+                        .map(|uname| format!("已登录: {}", uname))
+                        .unwrap_or_else(|| "凭据有效".to_string()),
+                )
+            }
+            Ok(validation) => {
+                SetupCheck::new(CheckCategory::Credential, title, CheckStatus::Failed)
+                    .message(
+                        validation
+                            .message
+                            .unwrap_or_else(|| "凭据已失效".to_string()),
+                    )
+                    .hint("请重新登录该账号")
+            }
+            // 网络错误时无法判断凭据是否有效
+            Err(err) => SetupCheck::new(CheckCategory::Credential, title, CheckStatus::Warning)
+                .message(format!("无法验证: {}", err)),
+        });
+    }
+    checks
+}
+
+fn check_autostart(app: &AppHandle) -> SetupCheck {
+    let check = SetupCheck::new(CheckCategory::Autostart, "开机启动", CheckStatus::Passed);
+    match app.autolaunch().is_enabled() {
+        Ok(true) => check.message("已开启"),
+        Ok(false) => SetupCheck::new(CheckCategory::Autostart, "开机启动", CheckStatus::Warning)
+            .message("未开启")
+            .hint("开启后无需每次直播前手动打开客户端"),
+        Err(err) => SetupCheck::new(CheckCategory::Autostart, "开机启动", CheckStatus::Warning)
+            .message(format!("读取开机启动状态失败: {}", err)),
+    }
+}
+
+// 允许局域网访问的服务器需要 Windows 防火墙放行，否则其他设备无法连接
+#[cfg(windows)]
+fn check_firewall(listeners: &[Listener]) -> Vec<SetupCheck> {
+    use std::os::windows::process::CommandExt;
+
+    // 不显示命令行窗口
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let lan: Vec<&Listener> = listeners
+        .iter()
+        .filter(|listener| {
+            listener
+                .bind_address
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| !ip.is_loopback())
+        })
+        .collect();
+    if lan.is_empty() {
+        return Vec::new();
+    }
+    let title = "Windows 防火墙";
+    let Ok(exe) = std::env::current_exe() else {
+        return Vec::new();
+    };
+    let output = std::process::Command::new("netsh")
+        .args([
+            "advfirewall",
+            "firewall",
+            "show",
+            "rule",
+            "name=all",
+            "dir=in",
+        ])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+    let rules = match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).to_lowercase(),
+        Err(err) => {
+            return vec![
+                SetupCheck::new(CheckCategory::Firewall, title, CheckStatus::Warning)
+                    .message(format!("无法读取防火墙规则: {}", err)),
+            ]
+        }
+    };
+    let names = lan
+        .iter()
+        .map(|listener| listener.title.as_str())
+        .collect::<Vec<_>>()
+        .join("、");
+    // 规则内容随系统语言变化，只查找程序路径
+    if rules.contains(&exe.to_string_lossy().to_lowercase()) {
+        vec![
+            SetupCheck::new(CheckCategory::Firewall, title, CheckStatus::Passed).message(format!(
+                "已有放行本程序的入站规则，{} 可以被局域网访问",
+                names
+            )),
+        ]
+    } else {
+        vec![
+            SetupCheck::new(CheckCategory::Firewall, title, CheckStatus::Warning)
+                .message(format!("没有放行本程序的入站规则，{} 可能无法被局域网访问", names))
+                .hint("启动服务器时在 Windows 弹出的提示中选择允许访问，或在防火墙设置中手动添加本程序"),
+        ]
+    }
+}
+
+#[cfg(not(windows))]
+fn check_firewall(_listeners: &[Listener]) -> Vec<SetupCheck> {
+    Vec::new()
+}
@@ -0,0 +1,96 @@
+use crate::admin_api::AdminApi;
+use crate::danmaku::RoomManager;
+use crate::event_store::EventStore;
+use crate::file_server::FileServerRegistry;
+use crate::recorder::DanmakuRecorder;
+use crate::relay::Relay;
+use crate::replay::ReplayManager;
+use crate::stats::StatsRecorder;
+use crate::update::UpdateManager;
+use crate::ws_server::WsServer;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+// 清理步骤的总超时时间，超时后跳过剩余步骤直接退出
+const CLEANUP_TIMEOUT: Duration = Duration::from_secs(10);
+// 断开直播间后等待订阅者处理完已广播的事件
+const DRAIN_DELAY: Duration = Duration::from_millis(300);
+// 调用 app.exit 后事件循环仍未退出时强制结束进程
+const EXIT_FALLBACK: Duration = Duration::from_secs(3);
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+// 依次停止服务、保存数据后退出程序，重复调用时只执行一次
+pub async fn shutdown(app: AppHandle) {
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    log::info!("正在退出程序");
+    if tokio::time::timeout(CLEANUP_TIMEOUT, cleanup(&app))
+        .await
+        .is_err()
+    {
+        log::warn!("退出前的清理超时，直接退出");
+    }
+
+    std::thread::spawn(|| {
+        std::thread::sleep(EXIT_FALLBACK);
+        log::warn!("程序未能正常退出，强制结束进程");
+        std::process::exit(0);
+    });
+    app.exit(0);
+}
+
+async fn cleanup(app: &AppHandle) {
+    // 先停止产生新事件
+    if let Some(replay) = app.try_state::<ReplayManager>() {
+        replay.stop();
+    }
+    if let Some(rooms) = app.try_state::<RoomManager>() {
+        rooms.stop_all();
+    }
+    tokio::time::sleep(DRAIN_DELAY).await;
+
+    if let Some(registry) = app.try_state::<FileServerRegistry>() {
+        registry.stop_all().await;
+    }
+    if let Some(admin_api) = app.try_state::<AdminApi>() {
+        if admin_api.status().running {
+            if let Err(err) = admin_api.stop().await {
+                log::warn!("停止管理接口失败: {}", err);
+            }
+        }
+    }
+    if let Some(ws_server) = app.try_state::<WsServer>() {
+        if ws_server.status().running {
+            if let Err(err) = ws_server.stop().await {
+                log::warn!("停止事件广播服务器失败: {}", err);
+            }
+        }
+    }
+
+    if let Some(recorder) = app.try_state::<DanmakuRecorder>() {
+        for info in recorder.stop_all() {
+            log::info!("已结束直播间 {} 的弹幕录制", info.room_id);
+        }
+    }
+    if let Some(relay) = app.try_state::<Relay>() {
+        relay.persist();
+    }
+    if let Some(stats) = app.try_state::<StatsRecorder>() {
+        stats.flush();
+    }
+    if let Some(store) = app.try_state::<EventStore>() {
+        if let Err(err) = store.checkpoint() {
+            log::warn!("保存事件数据库失败: {}", err);
+        }
+    }
+
+    // 数据保存完成后再安装已下载的更新，Windows 上安装程序会结束当前进程
+    if let Some(updates) = app.try_state::<UpdateManager>() {
+        if let Err(err) = updates.install_pending() {
+            log::warn!("安装更新失败: {}", err);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use crate::danmaku::{self, DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存点歌配置、队列和黑名单的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "song_requests.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+const QUEUE_KEY: &str = "queue";
+const PLAYED_KEY: &str = "played";
+const BLACKLIST_KEY: &str = "blacklist";
+
+// 队列变化时发送给前端的事件，内容为当前的 SongQueue
+pub const QUEUE_EVENT: &str = "song-request://queue";
+
+// 保留的已播放记录数量
+const MAX_PLAYED: usize = 100;
+
+// 点歌配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SongRequestConfig {
+    pub enabled: bool,
+    // 弹幕以该前缀开头时视为点歌，例如 "点歌 晴天"
+    pub command: String,
+    // 发送该弹幕时取消自己最近一次点的歌
+    pub cancel_command: String,
+    // 每个用户同时在队列中的最大数量，为 0 时不限制
+    pub max_per_user: usize,
+    // 队列的最大长度，为 0 时不限制
+    pub max_queue: usize,
+    // 只接受不低于该大航海等级的用户点歌，0 为所有人，3 为舰长，1 为总督
+    pub min_guard_level: u8,
+}
+
+impl Default for SongRequestConfig {
+    fn default() -> Self {
+        SongRequestConfig {
+            enabled: false,
+            command: "点歌".to_string(),
+            cancel_command: "取消点歌".to_string(),
+            max_per_user: 2,
+            max_queue: 100,
+            min_guard_level: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongRequest {
+    pub id: String,
+    // 歌名，去掉点歌前缀后的弹幕内容
+    pub song: String,
+    pub room_id: u64,
+    pub uid: u64,
+    pub open_id: Option<String>,
+    pub uname: String,
+    pub guard_level: u8,
+    // Unix 毫秒时间戳
+    pub requested_at: i64,
+    // 标记已播放的 Unix 毫秒时间戳
+    #[serde(default)]
+    pub played_at: Option<i64>,
+}
+
+impl SongRequest {
+    fn same_user(&self, event: &DanmakuEvent) -> bool {
+        event
+            .user_key()
+            .is_some_and(|key| danmaku::user_key(self.uid, self.open_id.as_deref()) == Some(key))
+    }
+}
+
+// 当前队列和最近播放的歌曲，队首为正在播放或下一首
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SongQueue {
+    pub enabled: bool,
+    pub queue: Vec<SongRequest>,
+    pub played: Vec<SongRequest>,
+}
+
+#[derive(Default)]
+struct State {
+    config: SongRequestConfig,
+    queue: Vec<SongRequest>,
+    // 最近播放的在前
+    played: Vec<SongRequest>,
+    blacklist: Vec<String>,
+}
+
+impl State {
+    fn snapshot(&self) -> SongQueue {
+        SongQueue {
+            enabled: self.config.enabled,
+            queue: self.queue.clone(),
+            played: self.played.clone(),
+        }
+    }
+
+    fn is_blacklisted(&self, song: &str) -> bool {
+        let song = normalize(song);
+        self.blacklist.iter().any(|name| normalize(name) == song)
+    }
+
+    fn position(&self, id: &str) -> AppResult<usize> {
+        self.queue
+            .iter()
+            .position(|request| request.id == id)
+            .ok_or_else(|| AppError::SongRequestNotFound(id.to_string()))
+    }
+
+    // 处理点歌和取消点歌弹幕，队列有变化时返回 true
+    fn on_danmaku(&mut self, event: &DanmakuEvent) -> bool {
+        let config = &self.config;
+        if !config.enabled || event.kind != EventKind::Danmaku || event.replay {
+            return false;
+        }
+        let message = event.message.trim();
+
+        if !config.cancel_command.is_empty() && message == config.cancel_command {
+            return match self
+                .queue
+                .iter()
+                .rposition(|request| request.same_user(event))
+            {
+                Some(index) => {
+                    self.queue.remove(index);
+                    true
+                }
+                None => false,
+            };
+        }
+
+        let Some(song) = message.strip_prefix(config.command.as_str()) else {
+            return false;
+        };
+        let song = song
+            .trim_start_matches(|c: char| c.is_whitespace() || c == ':' || c == '：')
+            .trim();
+        if config.command.is_empty() || song.is_empty() {
+            return false;
+        }
+        // 大航海等级数字越小等级越高，0 表示不是大航海
+        if config.min_guard_level > 0
+            && (event.guard_level == 0 || event.guard_level > config.min_guard_level)
+        {
+            return false;
+        }
+        if config.max_queue > 0 && self.queue.len() >= config.max_queue {
+            return false;
+        }
+        if config.max_per_user > 0
+            && self
+                .queue
+                .iter()
+                .filter(|request| request.same_user(event))
+                .count()
+                >= config.max_per_user
+        {
+            return false;
+        }
+        let normalized = normalize(song);
+        if self.is_blacklisted(song)
+            || self
+                .queue
+                .iter()
+                .any(|request| normalize(&request.song) == normalized)
+        {
+            return false;
+        }
+
+        self.queue.push(SongRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            song: song.to_string(),
+            room_id: event.room_id,
+            uid: event.uid,
+            open_id: event.open_id.clone(),
+            uname: event.uname.clone(),
+            guard_level: event.guard_level,
+            requested_at: event.timestamp,
+            played_at: None,
+        });
+        true
+    }
+}
+
+struct Shared {
+    app: AppHandle,
+    store: Arc<Store<Wry>>,
+    state: Mutex<State>,
+}
+
+impl Shared {
+    // 保存队列并通知前端
+    fn changed(&self, state: &State) {
+        for (key, value) in [
+            (QUEUE_KEY, serde_json::to_value(&state.queue)),
+            (PLAYED_KEY, serde_json::to_value(&state.played)),
+            (BLACKLIST_KEY, serde_json::to_value(&state.blacklist)),
+        ] {
+            match value {
+                Ok(value) => self.store.set(key, value),
+                Err(err) => log::warn!("序列化点歌队列失败: {}", err),
+            }
+        }
+        if let Err(err) = self.store.save() {
+            log::warn!("保存点歌队列失败: {}", err);
+        }
+        let _ = self.app.emit(QUEUE_EVENT, &state.snapshot());
+    }
+}
+
+// 从弹幕中解析点歌，维护持久化的点歌队列
+pub struct SongRequestManager {
+    shared: Arc<Shared>,
+}
+
+impl SongRequestManager {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let state = State {
+            config: load(&store, CONFIG_KEY),
+            queue: load(&store, QUEUE_KEY),
+            played: load(&store, PLAYED_KEY),
+            blacklist: load(&store, BLACKLIST_KEY),
+        };
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            store,
+            state: Mutex::new(state),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(SongRequestManager { shared })
+    }
+
+    pub fn get_config(&self) -> SongRequestConfig {
+        self.shared.state.lock().unwrap().config.clone()
+    }
+
+    pub fn update_config(&self, config: SongRequestConfig) -> AppResult<SongRequestConfig> {
+        let config = SongRequestConfig {
+            command: config.command.trim().to_string(),
+            cancel_command: config.cancel_command.trim().to_string(),
+            ..config
+        };
+        if config.command.is_empty() {
+            return Err(AppError::InvalidConfig("点歌指令不能为空".to_string()));
+        }
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.shared.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.shared.store.save() {
+                    log::warn!("保存点歌配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化点歌配置失败: {}", err),
+        }
+        let mut state = self.shared.state.lock().unwrap();
+        state.config = config.clone();
+        let _ = self.shared.app.emit(QUEUE_EVENT, &state.snapshot());
+        Ok(config)
+    }
+
+    pub fn queue(&self) -> SongQueue {
+        self.shared.state.lock().unwrap().snapshot()
+    }
+
+    // 把点歌移动到队列中的指定位置，超出范围时移动到队尾
+    pub fn move_to(&self, id: &str, index: usize) -> AppResult<SongQueue> {
+        self.update(|state| {
+            let from = state.position(id)?;
+            let request = state.queue.remove(from);
+            let index = index.min(state.queue.len());
+            state.queue.insert(index, request);
+            Ok(())
+        })
+    }
+
+    // 从队列移到已播放记录
+    pub fn mark_played(&self, id: &str) -> AppResult<SongQueue> {
+        self.update(|state| {
+            let index = state.position(id)?;
+            let mut request = state.queue.remove(index);
+            request.played_at = Some(chrono::Utc::now().timestamp_millis());
+            state.played.insert(0, request);
+            state.played.truncate(MAX_PLAYED);
+            Ok(())
+        })
+    }
+
+    pub fn remove(&self, id: &str) -> AppResult<SongQueue> {
+        self.update(|state| {
+            let index = state.position(id)?;
+            state.queue.remove(index);
+            Ok(())
+        })
+    }
+
+    pub fn clear(&self) -> AppResult<SongQueue> {
+        self.update(|state| {
+            state.queue.clear();
+            Ok(())
+        })
+    }
+
+    pub fn blacklist(&self) -> Vec<String> {
+        self.shared.state.lock().unwrap().blacklist.clone()
+    }
+
+    // 加入黑名单，同时移除队列中同名的点歌
+    pub fn add_blacklist(&self, song: &str) -> AppResult<Vec<String>> {
+        let song = song.trim();
+        if song.is_empty() {
+            return Err(AppError::InvalidConfig("歌名不能为空".to_string()));
+        }
+        self.update(|state| {
+            if !state.is_blacklisted(song) {
+                state.blacklist.push(song.to_string());
+            }
+            let normalized = normalize(song);
+            state
+                .queue
+                .retain(|request| normalize(&request.song) != normalized);
+            Ok(())
+        })?;
+        Ok(self.blacklist())
+    }
+
+    pub fn remove_blacklist(&self, song: &str) -> AppResult<Vec<String>> {
+        let normalized = normalize(song);
+        self.update(|state| {
+            state.blacklist.retain(|name| normalize(name) != normalized);
+            Ok(())
+        })?;
+        Ok(self.blacklist())
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State) -> AppResult<()>) -> AppResult<SongQueue> {
+        let mut state = self.shared.state.lock().unwrap();
+        f(&mut state)?;
+        self.shared.changed(&state);
+        Ok(state.snapshot())
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let mut state = shared.state.lock().unwrap();
+        if state.on_danmaku(&event) {
+            shared.changed(&state);
+        }
+    }
+}
+
+fn load<T: serde::de::DeserializeOwned + Default>(store: &Store<Wry>, key: &str) -> T {
+    store
+        .get(key)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+// 比较歌名时忽略大小写和空白
+fn normalize(song: &str) -> String {
+    song.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
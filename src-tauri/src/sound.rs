@@ -0,0 +1,325 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存提示音配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "sounds.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 提示音配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundConfig {
+    pub enabled: bool,
+    // 输出设备名称，为空时使用系统默认设备
+    pub device: Option<String>,
+    // 总音量，与每条提示音的音量相乘
+    pub volume: f32,
+    // 按顺序匹配，一个事件只播放第一条匹配的提示音
+    pub alerts: Vec<SoundAlert>,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        SoundConfig {
+            enabled: true,
+            device: None,
+            volume: 1.0,
+            alerts: Vec::new(),
+        }
+    }
+}
+
+// 事件触发的提示音，例如收到醒目留言时播放某个音效
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundAlert {
+    // 新建时可以为空，保存时生成
+    #[serde(default)]
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub name: String,
+    // 为空时匹配所有类型
+    #[serde(default)]
+    pub kinds: Vec<EventKind>,
+    // 金额不低于该值时触发，单位为元
+    #[serde(default)]
+    pub min_price: f64,
+    // 本地音频文件路径，支持 mp3、wav、ogg、flac
+    pub path: String,
+    // 0 到 1
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    // 两次播放之间的最短间隔，为 0 时不限制
+    #[serde(default)]
+    pub cooldown_secs: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+impl SoundAlert {
+    fn matches(&self, event: &DanmakuEvent) -> bool {
+        self.enabled
+            && (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+            && event.price >= self.min_price
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioDevice {
+    pub name: String,
+    pub is_default: bool,
+}
+
+// 发送给播放线程的指令
+enum Command {
+    Play { path: String, volume: f32 },
+    Stop,
+    SetDevice(Option<String>),
+}
+
+struct Shared {
+    config: RwLock<SoundConfig>,
+    // 每条提示音最近一次播放的时间
+    last_played: Mutex<HashMap<String, Instant>>,
+    commands: Mutex<mpsc::Sender<Command>>,
+}
+
+impl Shared {
+    fn send(&self, command: Command) {
+        // 播放线程只会在程序退出时结束
+        let _ = self.commands.lock().unwrap().send(command);
+    }
+
+    fn on_event(&self, event: &DanmakuEvent) {
+        let config = self.config.read().unwrap();
+        if !config.enabled {
+            return;
+        }
+        let Some(alert) = config.alerts.iter().find(|alert| alert.matches(event)) else {
+            return;
+        };
+        let now = Instant::now();
+        {
+            let mut last_played = self.last_played.lock().unwrap();
+            if let Some(last) = last_played.get(&alert.id) {
+                if now.duration_since(*last) < Duration::from_secs(alert.cooldown_secs) {
+                    return;
+                }
+            }
+            last_played.insert(alert.id.clone(), now);
+        }
+        self.send(Command::Play {
+            path: alert.path.clone(),
+            volume: alert.volume * config.volume,
+        });
+    }
+}
+
+// 根据直播间事件播放本地提示音
+pub struct SoundPlayer {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl SoundPlayer {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: SoundConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        // 音频输出不能跨线程使用，由单独的线程打开和播放
+        let (sender, receiver) = mpsc::channel();
+        let device = config.device.clone();
+        std::thread::spawn(move || play_loop(receiver, device));
+        let shared = Arc::new(Shared {
+            config: RwLock::new(config),
+            last_played: Mutex::new(HashMap::new()),
+            commands: Mutex::new(sender),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(SoundPlayer { shared, store })
+    }
+
+    pub fn get_config(&self) -> SoundConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: SoundConfig) -> AppResult<SoundConfig> {
+        let mut config = SoundConfig {
+            device: config.device.filter(|device| !device.is_empty()),
+            volume: config.volume.clamp(0.0, 1.0),
+            ..config
+        };
+        if let Some(device) = &config.device {
+            if !list_devices()?
+                .iter()
+                .any(|existing| &existing.name == device)
+            {
+                return Err(AppError::Audio(format!("找不到音频输出设备: {}", device)));
+            }
+        }
+        for alert in &mut config.alerts {
+            if alert.id.is_empty() {
+                alert.id = uuid::Uuid::new_v4().to_string();
+            }
+            alert.volume = alert.volume.clamp(0.0, 1.0);
+        }
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存提示音配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化提示音配置失败: {}", err),
+        }
+        let device_changed = {
+            let mut current = self.shared.config.write().unwrap();
+            let changed = current.device != config.device;
+            *current = config.clone();
+            changed
+        };
+        if device_changed {
+            self.shared.send(Command::SetDevice(config.device.clone()));
+        }
+        Ok(config)
+    }
+
+    // 试听提示音，不受冷却时间限制
+    pub fn play(&self, path: String, volume: f32) -> AppResult<()> {
+        if !Path::new(&path).is_file() {
+            return Err(AppError::Audio(format!("音频文件不存在: {}", path)));
+        }
+        let master = self.shared.config.read().unwrap().volume;
+        self.shared.send(Command::Play {
+            path,
+            volume: volume.clamp(0.0, 1.0) * master,
+        });
+        Ok(())
+    }
+
+    // 停止所有正在播放的提示音
+    pub fn stop(&self) {
+        self.shared.send(Command::Stop);
+    }
+}
+
+// 系统中可用的音频输出设备
+pub fn list_devices() -> AppResult<Vec<AudioDevice>> {
+    let host = rodio::cpal::default_host();
+    let default = host
+        .default_output_device()
+        .and_then(|device| device.name().ok());
+    let devices = host
+        .output_devices()
+        .map_err(|err| AppError::Audio(err.to_string()))?;
+    Ok(devices
+        .filter_map(|device| device.name().ok())
+        .map(|name| AudioDevice {
+            is_default: default.as_deref() == Some(name.as_str()),
+            name,
+        })
+        .collect())
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(event) if event.muted => {}
+            Ok(event) => shared.on_event(&event),
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+fn play_loop(commands: mpsc::Receiver<Command>, mut device: Option<String>) {
+    // 第一次播放时才打开输出设备
+    let mut output: Option<(OutputStream, OutputStreamHandle)> = None;
+    let mut sinks: Vec<Sink> = Vec::new();
+
+    for command in commands {
+        sinks.retain(|sink| !sink.empty());
+        match command {
+            Command::Play { path, volume } => {
+                if output.is_none() {
+                    output = match open_output(device.as_deref()) {
+                        Ok(output) => Some(output),
+                        Err(err) => {
+                            log::warn!("打开音频输出失败: {}", err);
+                            continue;
+                        }
+                    };
+                }
+                let Some((_, handle)) = &output else {
+                    continue;
+                };
+                match play_file(handle, &path, volume) {
+                    Ok(sink) => sinks.push(sink),
+                    Err(err) => log::warn!("播放提示音 {} 失败: {}", path, err),
+                }
+            }
+            Command::Stop => sinks.clear(),
+            Command::SetDevice(name) => {
+                // 丢弃旧的输出，下次播放时打开新设备
+                sinks.clear();
+                output = None;
+                device = name;
+            }
+        }
+    }
+}
+
+fn open_output(device: Option<&str>) -> AppResult<(OutputStream, OutputStreamHandle)> {
+    if let Some(name) = device {
+        let found = rodio::cpal::default_host()
+            .output_devices()
+            .ok()
+            .and_then(|mut devices| {
+                devices.find(|device| device.name().ok().as_deref() == Some(name))
+            });
+        match found {
+            Some(device) => return OutputStream::try_from_device(&device).map_err(audio_error),
+            None => log::warn!("找不到音频输出设备 {}，使用默认设备", name),
+        }
+    }
+    OutputStream::try_default().map_err(audio_error)
+}
+
+fn play_file(handle: &OutputStreamHandle, path: &str, volume: f32) -> AppResult<Sink> {
+    let file = File::open(path)?;
+    let source = Decoder::new(BufReader::new(file)).map_err(audio_error)?;
+    let sink = Sink::try_new(handle).map_err(audio_error)?;
+    sink.set_volume(volume);
+    sink.append(source);
+    Ok(sink)
+}
+
+fn audio_error(err: impl std::fmt::Display) -> AppError {
+    AppError::Audio(err.to_string())
+}
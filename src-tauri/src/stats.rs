@@ -0,0 +1,341 @@
+use crate::danmaku::{DanmakuEvent, EventKind, RoomManager};
+use crate::error::AppResult;
+use crate::event_bus::Subscriber;
+use crate::event_store::EventStore;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::broadcast;
+
+const MINUTE_MS: i64 = 60_000;
+// 采样人气值并写入数据库的间隔
+const FLUSH_INTERVAL: Duration = Duration::from_secs(15);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS minute_stats (
+    room_id INTEGER NOT NULL,
+    minute INTEGER NOT NULL,
+    events INTEGER NOT NULL,
+    messages INTEGER NOT NULL,
+    chatters INTEGER NOT NULL,
+    gift_value REAL NOT NULL,
+    super_chat_value REAL NOT NULL,
+    guard_value REAL NOT NULL,
+    popularity INTEGER NOT NULL,
+    PRIMARY KEY (room_id, minute)
+);
+CREATE INDEX IF NOT EXISTS idx_minute_stats_minute ON minute_stats (minute);
+";
+
+// 一分钟内的统计数据
+#[derive(Debug, Clone, Serialize)]
+pub struct MinuteStats {
+    // 不区分直播间查询时为空
+    pub room_id: Option<u64>,
+    // 这一分钟开始的 Unix 毫秒时间戳
+    pub minute: i64,
+    // 所有类型的事件数量
+    pub events: u64,
+    // 弹幕数量
+    pub messages: u64,
+    // 发送过弹幕的不同用户数量，多个直播间合并时直接相加
+    pub chatters: u64,
+    // 金额，单位为元
+    pub gift_value: f64,
+    pub super_chat_value: f64,
+    pub guard_value: f64,
+    // 这一分钟内的最高人气值
+    pub popularity: u64,
+}
+
+// 一场直播结束后的汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamSummary {
+    pub room_id: u64,
+    // Unix 毫秒时间戳
+    pub start: i64,
+    pub end: i64,
+    // 有数据的分钟数
+    pub minutes: u64,
+    pub events: u64,
+    pub messages: u64,
+    // 整场直播中产生过事件的不同用户数量，需要本地事件记录
+    pub unique_users: Option<u64>,
+    pub gift_value: f64,
+    pub super_chat_value: f64,
+    pub guard_value: f64,
+    pub revenue: f64,
+    pub peak_popularity: u64,
+    pub average_popularity: u64,
+    // 平均每分钟弹幕数
+    pub messages_per_minute: f64,
+    // 弹幕最多的一分钟
+    pub busiest_minute: Option<MinuteStats>,
+}
+
+// 正在统计的一分钟
+struct Bucket {
+    minute: i64,
+    events: u64,
+    messages: u64,
+    chatters: HashSet<String>,
+    gift_value: f64,
+    super_chat_value: f64,
+    guard_value: f64,
+    popularity: u64,
+}
+
+impl Bucket {
+    fn new(minute: i64) -> Self {
+        Bucket {
+            minute,
+            events: 0,
+            messages: 0,
+            chatters: HashSet::new(),
+            gift_value: 0.0,
+            super_chat_value: 0.0,
+            guard_value: 0.0,
+            popularity: 0,
+        }
+    }
+
+    fn add(&mut self, event: &DanmakuEvent) {
+        self.events += 1;
+        match event.kind {
+            EventKind::Danmaku => {
+                self.messages += 1;
+                // 无法区分用户的弹幕不计入发言人数
+                if let Some(key) = event.user_key() {
+                    self.chatters.insert(key);
+                }
+            }
+            EventKind::Gift => self.gift_value += event.price,
+            EventKind::SuperChat => self.super_chat_value += event.price,
+            EventKind::Guard => self.guard_value += event.price,
+            EventKind::Like | EventKind::Enter => {}
+        }
+    }
+}
+
+struct Shared {
+    app: AppHandle,
+    conn: Mutex<Connection>,
+    // 每个直播间当前这一分钟的数据
+    buckets: Mutex<HashMap<u64, Bucket>>,
+}
+
+impl Shared {
+    fn bucket<'a>(
+        &self,
+        buckets: &'a mut HashMap<u64, Bucket>,
+        room_id: u64,
+        minute: i64,
+    ) -> &'a mut Bucket {
+        let bucket = buckets
+            .entry(room_id)
+            .or_insert_with(|| Bucket::new(minute));
+        // 进入新的一分钟时先写入上一分钟，延迟到达的事件计入当前分钟
+        if minute > bucket.minute {
+            self.write(room_id, bucket);
+            *bucket = Bucket::new(minute);
+        }
+        bucket
+    }
+
+    fn on_event(&self, event: &DanmakuEvent) {
+        let mut buckets = self.buckets.lock().unwrap();
+        self.bucket(&mut buckets, event.room_id, minute_of(event.timestamp))
+            .add(event);
+    }
+
+    // 采样人气值，把所有直播间的当前数据写入数据库
+    fn flush(&self) {
+        let minute = minute_of(chrono::Utc::now().timestamp_millis());
+        let statuses = self
+            .app
+            .try_state::<RoomManager>()
+            .map(|rooms| rooms.rooms_status())
+            .unwrap_or_default();
+        let mut buckets = self.buckets.lock().unwrap();
+        for status in statuses.iter().filter(|status| status.popularity > 0) {
+            let bucket = self.bucket(&mut buckets, status.room_id, minute);
+            bucket.popularity = bucket.popularity.max(status.popularity);
+        }
+        for (room_id, bucket) in buckets.iter() {
+            self.write(*room_id, bucket);
+        }
+        // 已经结束的分钟不会再有数据
+        buckets.retain(|_, bucket| bucket.minute >= minute);
+    }
+
+    fn write(&self, room_id: u64, bucket: &Bucket) {
+        let result = self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO minute_stats
+             (room_id, minute, events, messages, chatters, gift_value, super_chat_value, guard_value, popularity)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                room_id as i64,
+                bucket.minute,
+                bucket.events as i64,
+                bucket.messages as i64,
+                bucket.chatters.len() as i64,
+                bucket.gift_value,
+                bucket.super_chat_value,
+                bucket.guard_value,
+                bucket.popularity as i64,
+            ],
+        );
+        if let Err(err) = result {
+            log::warn!("保存分钟统计失败: {}", err);
+        }
+    }
+}
+
+// 把直播间事件按分钟汇总，保存到本地数据库供图表使用
+pub struct StatsRecorder {
+    shared: Arc<Shared>,
+}
+
+impl StatsRecorder {
+    pub fn open(app: &AppHandle, path: &Path, events: Subscriber<DanmakuEvent>) -> AppResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            conn: Mutex::new(conn),
+            buckets: Mutex::new(HashMap::new()),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(StatsRecorder { shared })
+    }
+
+    // 立即把当前分钟的统计写入数据库
+    pub fn flush(&self) {
+        self.shared.flush();
+    }
+
+    // 按分钟排列的统计数据，不指定直播间时合并所有直播间
+    pub fn series(
+        &self,
+        room_id: Option<u64>,
+        start: i64,
+        end: i64,
+    ) -> AppResult<Vec<MinuteStats>> {
+        // 包含尚未写入的最新数据
+        self.shared.flush();
+        let conn = self.shared.conn.lock().unwrap();
+        let sql = match room_id {
+            Some(_) => {
+                "SELECT room_id, minute, events, messages, chatters, gift_value, super_chat_value, guard_value, popularity
+                 FROM minute_stats WHERE room_id = ?1 AND minute >= ?2 AND minute <= ?3 ORDER BY minute"
+            }
+            None => {
+                "SELECT NULL, minute, SUM(events), SUM(messages), SUM(chatters), SUM(gift_value),
+                        SUM(super_chat_value), SUM(guard_value), SUM(popularity)
+                 FROM minute_stats WHERE ?1 IS NULL AND minute >= ?2 AND minute <= ?3
+                 GROUP BY minute ORDER BY minute"
+            }
+        };
+        let mut stmt = conn.prepare_cached(sql)?;
+        let rows = stmt.query_map(
+            params![room_id.map(|id| id as i64), minute_of(start), end],
+            |row| {
+                Ok(MinuteStats {
+                    room_id: row.get::<_, Option<i64>>(0)?.map(|id| id as u64),
+                    minute: row.get(1)?,
+                    events: row.get::<_, i64>(2)? as u64,
+                    messages: row.get::<_, i64>(3)? as u64,
+                    chatters: row.get::<_, i64>(4)? as u64,
+                    gift_value: row.get(5)?,
+                    super_chat_value: row.get(6)?,
+                    guard_value: row.get(7)?,
+                    popularity: row.get::<_, i64>(8)? as u64,
+                })
+            },
+        )?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    // 汇总一场直播的数据，不指定结束时间时统计到现在
+    pub fn summary(&self, room_id: u64, start: i64, end: Option<i64>) -> AppResult<StreamSummary> {
+        let end = end.unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        let series = self.series(Some(room_id), start, end)?;
+        let unique_users = match self.shared.app.try_state::<EventStore>() {
+            Some(store) => Some(store.stats(Some(room_id), start, end)?.unique_users),
+            None => None,
+        };
+
+        let mut summary = StreamSummary {
+            room_id,
+            start,
+            end,
+            minutes: series.len() as u64,
+            events: 0,
+            messages: 0,
+            unique_users,
+            gift_value: 0.0,
+            super_chat_value: 0.0,
+            guard_value: 0.0,
+            revenue: 0.0,
+            peak_popularity: 0,
+            average_popularity: 0,
+            messages_per_minute: 0.0,
+            busiest_minute: None,
+        };
+        let mut popularity_total = 0;
+        let mut popularity_samples = 0;
+        for minute in &series {
+            summary.events += minute.events;
+            summary.messages += minute.messages;
+            summary.gift_value += minute.gift_value;
+            summary.super_chat_value += minute.super_chat_value;
+            summary.guard_value += minute.guard_value;
+            summary.peak_popularity = summary.peak_popularity.max(minute.popularity);
+            if minute.popularity > 0 {
+                popularity_total += minute.popularity;
+                popularity_samples += 1;
+            }
+        }
+        summary.revenue = summary.gift_value + summary.super_chat_value + summary.guard_value;
+        if popularity_samples > 0 {
+            summary.average_popularity = popularity_total / popularity_samples;
+        }
+        let duration_minutes = ((end - start) as f64 / MINUTE_MS as f64).max(1.0);
+        summary.messages_per_minute = summary.messages as f64 / duration_minutes;
+        summary.busiest_minute = series
+            .into_iter()
+            .filter(|minute| minute.messages > 0)
+            .max_by_key(|minute| minute.messages);
+        Ok(summary)
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if event.replay || event.simulated => {}
+                Ok(event) => shared.on_event(&event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("统计处理不及时，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = interval.tick() => shared.flush(),
+        }
+    }
+    shared.flush();
+}
+
+fn minute_of(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(MINUTE_MS)
+}
@@ -0,0 +1,397 @@
+use crate::error::{AppError, AppResult};
+use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+use nvml_wrapper::Nvml;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use sysinfo::{
+    CpuRefreshKind, Disks, MemoryRefreshKind, Networks, ProcessRefreshKind, ProcessesToUpdate,
+    RefreshKind, System,
+};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio_util::sync::CancellationToken;
+
+// 定时推送系统指标的事件
+pub const METRICS_EVENT: &str = "system-metrics";
+
+// 推送间隔的范围，单位为毫秒
+const MIN_STREAM_INTERVAL_MS: u64 = 250;
+const MAX_STREAM_INTERVAL_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreInfo {
+    pub name: String,
+    // 使用率百分比
+    pub usage: f32,
+    // 频率，单位 MHz
+    pub frequency: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CpuInfo {
+    pub brand: String,
+    pub vendor: String,
+    pub physical_cores: Option<usize>,
+    // 所有核心的平均使用率百分比
+    pub usage: f32,
+    pub cores: Vec<CoreInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskInfo {
+    pub name: String,
+    pub mount_point: String,
+    pub file_system: String,
+    // 磁盘类型，例如 SSD、HDD
+    pub kind: String,
+    pub removable: bool,
+    // 单位为字节
+    pub total: u64,
+    pub available: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkInfo {
+    pub name: String,
+    // 自上次查询以来的平均速率，单位为字节每秒，首次查询时为 0
+    pub rx_rate: f64,
+    pub tx_rate: f64,
+    // 系统启动以来的累计流量，单位为字节
+    pub total_rx: u64,
+    pub total_tx: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UptimeInfo {
+    // 系统运行时间，单位为秒
+    pub system: u64,
+    // 系统启动的 Unix 秒时间戳
+    pub boot_time: u64,
+    // 本程序运行时间，单位为秒
+    pub app: u64,
+}
+
+// 单个 GPU 的状态，驱动不支持的项为空
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuInfo {
+    pub index: u32,
+    pub name: String,
+    // 使用率百分比
+    pub usage: Option<u32>,
+    // 硬件编码器使用率百分比，推流使用 NVENC 时有意义
+    pub encoder_usage: Option<u32>,
+    // 显存，单位为字节
+    pub memory_total: Option<u64>,
+    pub memory_used: Option<u64>,
+    // 温度，单位为摄氏度
+    pub temperature: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuReport {
+    // 是否能读取 GPU 信息，目前只支持安装了驱动的 NVIDIA 显卡
+    pub available: bool,
+    pub error: Option<String>,
+    pub gpus: Vec<GpuInfo>,
+}
+
+// 推送给前端的一次采样
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemMetrics {
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    // 内存，单位为字节
+    pub memory_total: u64,
+    pub memory_used: u64,
+    pub memory_available: u64,
+    // 所有核心的平均使用率百分比
+    pub cpu_usage: f32,
+    // 所有网卡合计的速率，单位为字节每秒
+    pub rx_rate: f64,
+    pub tx_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsStreamStatus {
+    pub running: bool,
+    pub interval_ms: Option<u64>,
+}
+
+struct MetricsStream {
+    cancel: CancellationToken,
+    interval_ms: u64,
+}
+
+// 上次查询网卡流量时的累计值，用于计算速率
+struct NetworkSample {
+    at: Instant,
+    totals: HashMap<String, (u64, u64)>,
+}
+
+// 查询主机的 CPU、磁盘、网络和运行时间
+// CPU 使用率和网络速率需要两次采样之间的差值，所以保留上次的采样结果
+pub struct SystemMonitor {
+    system: Mutex<System>,
+    network: Mutex<Option<NetworkSample>>,
+    stream: Mutex<Option<MetricsStream>>,
+    // 第一次查询 GPU 时加载 NVML，加载失败时保留错误信息
+    nvml: OnceLock<Result<Nvml, String>>,
+    started_at: Instant,
+}
+
+impl Default for SystemMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemMonitor {
+    pub fn new() -> Self {
+        // 先采样一次，之后的查询才能得到有效的使用率
+        let system = System::new_with_specifics(
+            RefreshKind::nothing().with_cpu(CpuRefreshKind::everything()),
+        );
+        SystemMonitor {
+            system: Mutex::new(system),
+            network: Mutex::new(None),
+            stream: Mutex::new(None),
+            nvml: OnceLock::new(),
+            started_at: Instant::now(),
+        }
+    }
+
+    pub fn cpu_info(&self) -> CpuInfo {
+        let mut system = self.system.lock().unwrap();
+        system.refresh_cpu_all();
+        let cpus = system.cpus();
+        CpuInfo {
+            brand: cpus
+                .first()
+                .map(|cpu| cpu.brand().trim().to_string())
+                .unwrap_or_default(),
+            vendor: cpus
+                .first()
+                .map(|cpu| cpu.vendor_id().to_string())
+                .unwrap_or_default(),
+            physical_cores: System::physical_core_count(),
+            usage: system.global_cpu_usage(),
+            cores: cpus
+                .iter()
+                .map(|cpu| CoreInfo {
+                    name: cpu.name().to_string(),
+                    usage: cpu.cpu_usage(),
+                    frequency: cpu.frequency(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn disk_info(&self) -> Vec<DiskInfo> {
+        Disks::new_with_refreshed_list()
+            .list()
+            .iter()
+            .map(|disk| DiskInfo {
+                name: disk.name().to_string_lossy().to_string(),
+                mount_point: disk.mount_point().to_string_lossy().to_string(),
+                file_system: disk.file_system().to_string_lossy().to_string(),
+                kind: format!("{:?}", disk.kind()),
+                removable: disk.is_removable(),
+                total: disk.total_space(),
+                available: disk.available_space(),
+            })
+            .collect()
+    }
+
+    pub fn network_info(&self) -> Vec<NetworkInfo> {
+        let networks = Networks::new_with_refreshed_list();
+        let now = Instant::now();
+        let mut last = self.network.lock().unwrap();
+        let elapsed = last
+            .as_ref()
+            .map(|sample| now.duration_since(sample.at).as_secs_f64())
+            .unwrap_or(0.0);
+
+        let mut totals = HashMap::new();
+        let mut interfaces: Vec<NetworkInfo> = networks
+            .iter()
+            .map(|(name, data)| {
+                let (total_rx, total_tx) = (data.total_received(), data.total_transmitted());
+                let (rx_rate, tx_rate) =
+                    match last.as_ref().and_then(|sample| sample.totals.get(name)) {
+                        Some((prev_rx, prev_tx)) if elapsed > 0.0 => (
+                            total_rx.saturating_sub(*prev_rx) as f64 / elapsed,
+                            total_tx.saturating_sub(*prev_tx) as f64 / elapsed,
+                        ),
+                        _ => (0.0, 0.0),
+                    };
+                totals.insert(name.clone(), (total_rx, total_tx));
+                NetworkInfo {
+                    name: name.clone(),
+                    rx_rate,
+                    tx_rate,
+                    total_rx,
+                    total_tx,
+                }
+            })
+            .collect();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+        *last = Some(NetworkSample { at: now, totals });
+        interfaces
+    }
+
+    pub fn gpu_info(&self) -> GpuReport {
+        let nvml = self
+            .nvml
+            .get_or_init(|| Nvml::init().map_err(|err| err.to_string()));
+        let nvml = match nvml {
+            Ok(nvml) => nvml,
+            Err(err) => {
+                return GpuReport {
+                    available: false,
+                    error: Some(format!("无法加载 NVIDIA 驱动: {}", err)),
+                    gpus: Vec::new(),
+                }
+            }
+        };
+        let count = match nvml.device_count() {
+            Ok(count) => count,
+            Err(err) => {
+                return GpuReport {
+                    available: false,
+                    error: Some(format!("读取 GPU 数量失败: {}", err)),
+                    gpus: Vec::new(),
+                }
+            }
+        };
+
+        let gpus = (0..count)
+            .filter_map(|index| match nvml.device_by_index(index) {
+                Ok(device) => {
+                    let memory = device.memory_info().ok();
+                    Some(GpuInfo {
+                        index,
+                        name: device.name().unwrap_or_default(),
+                        usage: device.utilization_rates().ok().map(|rates| rates.gpu),
+                        encoder_usage: device
+                            .encoder_utilization()
+                            .ok()
+                            .map(|info| info.utilization),
+                        memory_total: memory.as_ref().map(|memory| memory.total),
+                        memory_used: memory.as_ref().map(|memory| memory.used),
+                        temperature: device.temperature(TemperatureSensor::Gpu).ok(),
+                    })
+                }
+                Err(err) => {
+                    log::warn!("读取第 {} 个 GPU 失败: {}", index, err);
+                    None
+                }
+            })
+            .collect();
+        GpuReport {
+            available: true,
+            error: None,
+            gpus,
+        }
+    }
+
+    pub fn metrics(&self) -> SystemMetrics {
+        let (memory_total, memory_used, memory_available, cpu_usage) = {
+            let mut system = self.system.lock().unwrap();
+            system.refresh_specifics(
+                RefreshKind::nothing()
+                    .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+                    .with_memory(MemoryRefreshKind::nothing().with_ram()),
+            );
+            (
+                system.total_memory(),
+                system.used_memory(),
+                system.available_memory(),
+                system.global_cpu_usage(),
+            )
+        };
+        let networks = self.network_info();
+        SystemMetrics {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            memory_total,
+            memory_used,
+            memory_available,
+            cpu_usage,
+            rx_rate: networks.iter().map(|network| network.rx_rate).sum(),
+            tx_rate: networks.iter().map(|network| network.tx_rate).sum(),
+        }
+    }
+
+    // 本进程占用的物理内存，单位为字节
+    pub fn process_memory(&self) -> Option<u64> {
+        let pid = sysinfo::get_current_pid().ok()?;
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_memory(),
+        );
+        system.process(pid).map(|process| process.memory())
+    }
+
+    // 开始定时推送系统指标，已经在推送时按新的间隔重新开始
+    pub fn start_stream(
+        &self,
+        app: &AppHandle,
+        interval_ms: u64,
+    ) -> AppResult<MetricsStreamStatus> {
+        if !(MIN_STREAM_INTERVAL_MS..=MAX_STREAM_INTERVAL_MS).contains(&interval_ms) {
+            return Err(AppError::InvalidConfig(format!(
+                "推送间隔必须在 {} 到 {} 毫秒之间",
+                MIN_STREAM_INTERVAL_MS, MAX_STREAM_INTERVAL_MS
+            )));
+        }
+        self.stop_stream();
+        let cancel = CancellationToken::new();
+        *self.stream.lock().unwrap() = Some(MetricsStream {
+            cancel: cancel.clone(),
+            interval_ms,
+        });
+
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+                let Some(monitor) = app.try_state::<SystemMonitor>() else {
+                    break;
+                };
+                let metrics = monitor.metrics();
+                let _ = app.emit(METRICS_EVENT, &metrics);
+            }
+        });
+        Ok(self.stream_status())
+    }
+
+    pub fn stop_stream(&self) -> MetricsStreamStatus {
+        if let Some(stream) = self.stream.lock().unwrap().take() {
+            stream.cancel.cancel();
+        }
+        self.stream_status()
+    }
+
+    pub fn stream_status(&self) -> MetricsStreamStatus {
+        let stream = self.stream.lock().unwrap();
+        MetricsStreamStatus {
+            running: stream.is_some(),
+            interval_ms: stream.as_ref().map(|stream| stream.interval_ms),
+        }
+    }
+
+    pub fn uptime(&self) -> UptimeInfo {
+        UptimeInfo {
+            system: System::uptime(),
+            boot_time: System::boot_time(),
+            app: self.started_at.elapsed().as_secs(),
+        }
+    }
+}
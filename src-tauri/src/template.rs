@@ -0,0 +1,47 @@
+use crate::danmaku::DanmakuEvent;
+use serde_json::Value;
+
+// 用事件字段替换模板中的 {{字段名}}，例如 "{{uname}} 赠送了 {{message}}"
+// 未知的字段保持原样，空值替换为空字符串
+pub fn render(template: &str, event: &DanmakuEvent) -> String {
+    render_with(template, event, field_text)
+}
+
+// 生成 JSON 文本，字符串字段按 JSON 转义但不加引号，
+// 例如 {"text": "{{message}}", "price": {{price}}}
+pub fn render_json(template: &str, event: &DanmakuEvent) -> String {
+    render_with(template, event, |value| match value {
+        Value::String(text) => {
+            let quoted = serde_json::to_string(text).unwrap_or_default();
+            quoted[1..quoted.len() - 1].to_string()
+        }
+        other => other.to_string(),
+    })
+}
+
+fn render_with(template: &str, event: &DanmakuEvent, text: impl Fn(&Value) -> String) -> String {
+    let fields = serde_json::to_value(event).unwrap_or_default();
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        match fields.get(rest[start + 2..end].trim()) {
+            Some(value) => output.push_str(&text(value)),
+            None => output.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,297 @@
+use crate::danmaku::{ConnectionState, RoomManager};
+use crate::file_server::FileServerRegistry;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager, Window, WindowEvent, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+const TRAY_ID: &str = "main";
+const MAIN_WINDOW: &str = "main";
+
+// 保存窗口关闭行为的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "tray.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+const MENU_TOGGLE_WINDOW: &str = "toggle_window";
+const MENU_TOGGLE_FETCHING: &str = "toggle_fetching";
+const MENU_TOGGLE_FILE_SERVER: &str = "toggle_file_server";
+const MENU_QUIT: &str = "quit";
+
+// 刷新托盘提示和菜单文字的间隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseBehavior {
+    // 关闭主窗口时退出程序
+    Exit,
+    // 关闭主窗口时隐藏到托盘，弹幕获取继续在后台运行
+    #[default]
+    Tray,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CloseSettings {
+    pub close_behavior: CloseBehavior,
+    // 最小化时隐藏到托盘
+    pub minimize_to_tray: bool,
+}
+
+// 主窗口关闭和最小化时的行为
+pub struct WindowBehavior {
+    settings: RwLock<CloseSettings>,
+    store: Arc<Store<Wry>>,
+}
+
+impl WindowBehavior {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let settings = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Ok(WindowBehavior {
+            settings: RwLock::new(settings),
+            store,
+        })
+    }
+
+    pub fn get(&self) -> CloseSettings {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn set(&self, settings: CloseSettings) -> CloseSettings {
+        match serde_json::to_value(&settings) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存窗口设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化窗口设置失败: {}", err),
+        }
+        *self.settings.write().unwrap() = settings.clone();
+        settings
+    }
+}
+
+// 根据设置把主窗口的关闭和最小化改为隐藏到托盘
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() != MAIN_WINDOW {
+        return;
+    }
+    let Some(behavior) = window.try_state::<WindowBehavior>() else {
+        return;
+    };
+    let settings = behavior.get();
+    match event {
+        WindowEvent::CloseRequested { api, .. }
+            if settings.close_behavior == CloseBehavior::Tray =>
+        {
+            api.prevent_close();
+            let _ = window.hide();
+        }
+        // 没有单独的最小化事件，最小化时窗口会收到 Resized
+        WindowEvent::Resized(_)
+            if settings.minimize_to_tray && window.is_minimized().unwrap_or(false) =>
+        {
+            let _ = window.hide();
+        }
+        _ => {}
+    }
+}
+
+// 需要根据状态修改文字的菜单项
+struct TrayMenu {
+    fetching: MenuItem<Wry>,
+    file_server: MenuItem<Wry>,
+}
+
+// 创建托盘图标，左键单击显示或隐藏主窗口，右键打开菜单
+pub fn create(app: &AppHandle) -> tauri::Result<()> {
+    let toggle_window =
+        MenuItem::with_id(app, MENU_TOGGLE_WINDOW, "显示/隐藏窗口", true, None::<&str>)?;
+    let fetching = MenuItem::with_id(
+        app,
+        MENU_TOGGLE_FETCHING,
+        "停止获取弹幕",
+        true,
+        None::<&str>,
+    )?;
+    let file_server = MenuItem::with_id(
+        app,
+        MENU_TOGGLE_FILE_SERVER,
+        "启动文件服务器",
+        true,
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, MENU_QUIT, "退出", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &toggle_window,
+            &PredefinedMenuItem::separator(app)?,
+            &fetching,
+            &file_server,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("VTsuru 弹幕获取")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                toggle_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    app.manage(TrayMenu {
+        fetching,
+        file_server,
+    });
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            refresh(&app);
+        }
+    });
+    Ok(())
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        MENU_TOGGLE_WINDOW => toggle_window(app),
+        MENU_TOGGLE_FETCHING => toggle_fetching(app),
+        MENU_TOGGLE_FILE_SERVER => toggle_file_server(app),
+        MENU_QUIT => {
+            tauri::async_runtime::spawn(crate::shutdown::shutdown(app.clone()));
+        }
+        _ => {}
+    }
+}
+
+pub fn show_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+pub fn toggle_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+        return;
+    };
+    if window.is_visible().unwrap_or(false) && !window.is_minimized().unwrap_or(false) {
+        let _ = window.hide();
+    } else {
+        show_window(app);
+    }
+}
+
+// 有连接中的直播间时全部断开，否则重新连接上次断开的直播间
+pub fn toggle_fetching(app: &AppHandle) {
+    let rooms = app.state::<RoomManager>();
+    if !rooms.list_rooms().is_empty() {
+        rooms.stop_all();
+        refresh(app);
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let rooms = app.state::<RoomManager>();
+        for err in rooms.start_all().await {
+            log::warn!("重新连接直播间失败: {}", err);
+        }
+        refresh(&app);
+    });
+}
+
+fn toggle_file_server(app: &AppHandle) {
+    let server = match app.state::<FileServerRegistry>().get(None) {
+        Ok(server) => server,
+        Err(err) => {
+            log::warn!("{}", err);
+            return;
+        }
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = if server.get_status().running {
+            server.stop_server().await
+        } else {
+            server.start_server().await
+        };
+        if let Err(err) = result {
+            log::warn!("切换文件服务器状态失败: {}", err);
+        }
+        refresh(&app);
+    });
+}
+
+// 根据直播间连接和文件服务器状态更新提示文字和菜单
+fn refresh(app: &AppHandle) {
+    let (Some(menu), Some(tray)) = (app.try_state::<TrayMenu>(), app.tray_by_id(TRAY_ID)) else {
+        return;
+    };
+    let mut tooltip = String::from("VTsuru 弹幕获取");
+
+    if let Some(rooms) = app.try_state::<RoomManager>() {
+        let statuses = rooms.rooms_status();
+        let connected = statuses
+            .iter()
+            .filter(|status| status.state == ConnectionState::Connected)
+            .count();
+        if statuses.is_empty() {
+            tooltip.push_str("\n未连接直播间");
+            let _ = menu.fetching.set_text("开始获取弹幕");
+            let _ = menu.fetching.set_enabled(rooms.is_stopped());
+        } else {
+            tooltip.push_str(&format!(
+                "\n直播间: {}/{} 已连接",
+                connected,
+                statuses.len()
+            ));
+            let _ = menu.fetching.set_text("停止获取弹幕");
+            let _ = menu.fetching.set_enabled(true);
+        }
+    }
+
+    if let Some(server) = app
+        .try_state::<FileServerRegistry>()
+        .and_then(|registry| registry.get(None).ok())
+    {
+        let status = server.get_status();
+        match status.active_port.filter(|_| status.running) {
+            Some(port) => {
+                tooltip.push_str(&format!("\n文件服务器: 运行中 (端口 {})", port));
+                let _ = menu.file_server.set_text("停止文件服务器");
+            }
+            None => {
+                tooltip.push_str("\n文件服务器: 未运行");
+                let _ = menu.file_server.set_text("启动文件服务器");
+            }
+        }
+    }
+
+    let _ = tray.set_tooltip(Some(tooltip));
+}
@@ -0,0 +1,416 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::template;
+use ::tts::Tts;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rodio::{Decoder, OutputStream, Sink};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_http::reqwest::Client;
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存朗读配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "tts.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 队列或播放状态变化时发送给前端的事件
+pub const STATUS_EVENT: &str = "tts://status";
+
+// 检查朗读是否结束、是否需要跳过的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(15);
+
+// 朗读配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TtsConfig {
+    pub enabled: bool,
+    pub provider: TtsProvider,
+    // 语速倍数，1 为正常语速
+    pub rate: f32,
+    // 音量，0 到 1
+    pub volume: f32,
+    // 等待朗读的最大数量，超出时丢弃最早的
+    pub max_queue: usize,
+    // 单条朗读的最大字数，超出部分截断
+    pub max_length: usize,
+    // 各类型事件是否朗读以及朗读的文本模板
+    pub kinds: Vec<TtsKindConfig>,
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        TtsConfig {
+            enabled: false,
+            provider: TtsProvider::System,
+            rate: 1.0,
+            volume: 1.0,
+            max_queue: 50,
+            max_length: 100,
+            kinds: vec![
+                TtsKindConfig::new(EventKind::Danmaku, true, "{{uname}}说：{{message}}"),
+                TtsKindConfig::new(
+                    EventKind::Gift,
+                    true,
+                    "感谢{{uname}}赠送的{{num}}个{{message}}",
+                ),
+                TtsKindConfig::new(
+                    EventKind::SuperChat,
+                    true,
+                    "{{uname}}发送了醒目留言：{{message}}",
+                ),
+                TtsKindConfig::new(EventKind::Guard, true, "感谢{{uname}}开通{{message}}"),
+                TtsKindConfig::new(EventKind::Like, false, "{{uname}}点赞了"),
+                TtsKindConfig::new(EventKind::Enter, false, "欢迎{{uname}}进入直播间"),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TtsKindConfig {
+    pub kind: EventKind,
+    pub enabled: bool,
+    // 支持 {{uname}} 等事件字段
+    pub template: String,
+}
+
+impl TtsKindConfig {
+    fn new(kind: EventKind, enabled: bool, template: &str) -> Self {
+        TtsKindConfig {
+            kind,
+            enabled,
+            template: template.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TtsProvider {
+    // 系统语音，Windows 为 SAPI，macOS 为 AVSpeechSynthesizer
+    System,
+    // 返回音频文件的 HTTP 接口，地址中的 {{text}} 替换为编码后的文本
+    Http { url: String },
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TtsStatus {
+    pub paused: bool,
+    // 正在朗读的文本
+    pub current: Option<String>,
+    pub queue_length: usize,
+}
+
+#[derive(Default)]
+struct Queue {
+    items: VecDeque<String>,
+    paused: bool,
+    current: Option<String>,
+}
+
+struct Shared {
+    app: AppHandle,
+    http: Client,
+    config: RwLock<TtsConfig>,
+    queue: Mutex<Queue>,
+    // 有新文本或恢复播放时唤醒朗读线程
+    wake: Condvar,
+    // 请求跳过正在朗读的文本
+    skip: AtomicBool,
+}
+
+impl Shared {
+    fn status(&self) -> TtsStatus {
+        let queue = self.queue.lock().unwrap();
+        TtsStatus {
+            paused: queue.paused,
+            current: queue.current.clone(),
+            queue_length: queue.items.len(),
+        }
+    }
+
+    fn emit_status(&self) {
+        let _ = self.app.emit(STATUS_EVENT, &self.status());
+    }
+
+    fn enqueue(&self, text: String) {
+        let (max_queue, max_length) = {
+            let config = self.config.read().unwrap();
+            (config.max_queue.max(1), config.max_length.max(1))
+        };
+        let text: String = text.chars().take(max_length).collect();
+        if text.trim().is_empty() {
+            return;
+        }
+        {
+            let mut queue = self.queue.lock().unwrap();
+            while queue.items.len() >= max_queue {
+                queue.items.pop_front();
+            }
+            queue.items.push_back(text);
+        }
+        self.wake.notify_one();
+        self.emit_status();
+    }
+
+    // 是否需要停止正在朗读的文本
+    fn interrupted(&self) -> bool {
+        self.skip.load(Ordering::Relaxed) || self.queue.lock().unwrap().paused
+    }
+}
+
+// 朗读弹幕等事件，所有文本按顺序在单独的线程中播放
+pub struct TtsManager {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl TtsManager {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: TtsConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            http: crate::proxy::client_builder().build().unwrap_or_default(),
+            config: RwLock::new(config),
+            queue: Mutex::new(Queue::default()),
+            wake: Condvar::new(),
+            skip: AtomicBool::new(false),
+        });
+        // 系统语音和音频输出不能跨线程使用，由同一个线程创建和播放
+        let worker = shared.clone();
+        std::thread::spawn(move || speak_loop(worker));
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(TtsManager { shared, store })
+    }
+
+    pub fn get_config(&self) -> TtsConfig {
+        self.shared.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: TtsConfig) -> AppResult<TtsConfig> {
+        if let TtsProvider::Http { url } = &config.provider {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(AppError::InvalidConfig(
+                    "朗读接口地址必须以 http:// 或 https:// 开头".to_string(),
+                ));
+            }
+        }
+        let config = TtsConfig {
+            rate: config.rate.clamp(0.25, 4.0),
+            volume: config.volume.clamp(0.0, 1.0),
+            ..config
+        };
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存朗读配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化朗读配置失败: {}", err),
+        }
+        *self.shared.config.write().unwrap() = config.clone();
+        Ok(config)
+    }
+
+    pub fn status(&self) -> TtsStatus {
+        self.shared.status()
+    }
+
+    // 朗读任意文本，用于试听
+    pub fn speak(&self, text: String) -> TtsStatus {
+        self.shared.enqueue(text);
+        self.status()
+    }
+
+    // 暂停时停止正在朗读的文本，恢复后从这条重新开始
+    pub fn pause(&self) -> TtsStatus {
+        self.shared.queue.lock().unwrap().paused = true;
+        self.shared.emit_status();
+        self.status()
+    }
+
+    pub fn resume(&self) -> TtsStatus {
+        self.shared.queue.lock().unwrap().paused = false;
+        self.shared.wake.notify_one();
+        self.shared.emit_status();
+        self.status()
+    }
+
+    pub fn skip(&self) -> TtsStatus {
+        self.shared.skip.store(true, Ordering::Relaxed);
+        self.status()
+    }
+
+    // 清空等待中的文本并停止正在朗读的文本
+    pub fn clear(&self) -> TtsStatus {
+        self.shared.queue.lock().unwrap().items.clear();
+        self.shared.skip.store(true, Ordering::Relaxed);
+        self.shared.emit_status();
+        self.status()
+    }
+}
+
+// 把符合配置的事件转换为朗读文本
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    loop {
+        let event = match events.recv().await {
+            // 被审核规则静默的事件不朗读
+            Ok(event) if event.muted => continue,
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let text = {
+            let config = shared.config.read().unwrap();
+            if !config.enabled {
+                continue;
+            }
+            config
+                .kinds
+                .iter()
+                .find(|kind| kind.kind == event.kind && kind.enabled)
+                .map(|kind| template::render(&kind.template, &event))
+        };
+        if let Some(text) = text {
+            shared.enqueue(text);
+        }
+    }
+}
+
+fn speak_loop(shared: Arc<Shared>) {
+    let mut system = match Tts::default() {
+        Ok(tts) => Some(tts),
+        Err(err) => {
+            log::warn!("初始化系统语音失败: {}", err);
+            None
+        }
+    };
+    // 音频输出在第一次使用 HTTP 接口时打开
+    let mut output: Option<(OutputStream, rodio::OutputStreamHandle)> = None;
+
+    loop {
+        let text = {
+            let mut queue = shared.queue.lock().unwrap();
+            while queue.paused || queue.items.is_empty() {
+                queue = shared.wake.wait(queue).unwrap();
+            }
+            let text = queue.items.pop_front();
+            queue.current = text.clone();
+            text
+        };
+        let Some(text) = text else {
+            continue;
+        };
+        shared.skip.store(false, Ordering::Relaxed);
+        shared.emit_status();
+
+        let config = shared.config.read().unwrap().clone();
+        let result = match &config.provider {
+            TtsProvider::System => match system.as_mut() {
+                Some(tts) => speak_system(&shared, tts, &text, &config),
+                None => Err(AppError::Tts("系统语音不可用".to_string())),
+            },
+            TtsProvider::Http { url } => {
+                if output.is_none() {
+                    output = OutputStream::try_default()
+                        .map_err(|err| log::warn!("打开音频输出失败: {}", err))
+                        .ok();
+                }
+                match &output {
+                    Some((_, handle)) => speak_http(&shared, handle, url, &text, &config),
+                    None => Err(AppError::Tts("没有可用的音频输出设备".to_string())),
+                }
+            }
+        };
+        if let Err(err) = result {
+            log::warn!("朗读失败: {}", err);
+        }
+
+        {
+            let mut queue = shared.queue.lock().unwrap();
+            // 暂停打断的文本放回队首，恢复后重新朗读
+            if queue.paused && !shared.skip.load(Ordering::Relaxed) {
+                queue.items.push_front(text);
+            }
+            queue.current = None;
+        }
+        shared.emit_status();
+    }
+}
+
+fn speak_system(shared: &Shared, tts: &mut Tts, text: &str, config: &TtsConfig) -> AppResult<()> {
+    let rate = (tts.normal_rate() * config.rate).clamp(tts.min_rate(), tts.max_rate());
+    let volume = tts.min_volume() + (tts.max_volume() - tts.min_volume()) * config.volume;
+    tts.set_rate(rate).map_err(tts_error)?;
+    tts.set_volume(volume).map_err(tts_error)?;
+    tts.speak(text, true).map_err(tts_error)?;
+
+    // 部分平台刚开始朗读时仍会报告未在朗读，先等待一个间隔
+    std::thread::sleep(POLL_INTERVAL);
+    while tts.is_speaking().map_err(tts_error)? {
+        if shared.interrupted() {
+            tts.stop().map_err(tts_error)?;
+            break;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+fn speak_http(
+    shared: &Shared,
+    handle: &rodio::OutputStreamHandle,
+    url: &str,
+    text: &str,
+    config: &TtsConfig,
+) -> AppResult<()> {
+    let url = url.replace(
+        "{{text}}",
+        &utf8_percent_encode(text, NON_ALPHANUMERIC).to_string(),
+    );
+    let bytes = tauri::async_runtime::block_on(async {
+        shared
+            .http
+            .get(url)
+            .timeout(HTTP_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+    })?;
+    let source = Decoder::new(Cursor::new(bytes.to_vec()))
+        .map_err(|err| AppError::Tts(format!("无法解码音频: {}", err)))?;
+    let sink = Sink::try_new(handle).map_err(|err| AppError::Tts(err.to_string()))?;
+    sink.set_volume(config.volume);
+    sink.set_speed(config.rate);
+    sink.append(source);
+    while !sink.empty() {
+        if shared.interrupted() {
+            sink.stop();
+            break;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+fn tts_error(err: ::tts::Error) -> AppError {
+    AppError::Tts(err.to_string())
+}
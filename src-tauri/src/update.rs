@@ -0,0 +1,252 @@
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Url, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tauri_plugin_updater::{Update, Updater, UpdaterExt};
+
+mod download;
+
+// 保存更新设置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "update.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+// 测试版的更新地址，正式版使用 tauri.conf.json 中配置的地址
+const BETA_ENDPOINT: &str = "https://vtsuru.suki.club/api/vtsuru/client/latest-beta.json";
+
+// 下载进度事件，限制发送频率
+pub const PROGRESS_EVENT: &str = "update://progress";
+// 下载完成事件，延迟安装时表示将在退出时安装
+pub const DOWNLOADED_EVENT: &str = "update://downloaded";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    pub channel: UpdateChannel,
+    // 下载后等到退出程序时再安装，避免安装更新打断直播
+    pub install_on_quit: bool,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        UpdateConfig {
+            channel: UpdateChannel::Stable,
+            install_on_quit: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    // 更新说明
+    pub notes: Option<String>,
+    // 发布时间
+    pub date: Option<String>,
+}
+
+impl UpdateInfo {
+    fn new(update: &Update, channel: UpdateChannel) -> Self {
+        UpdateInfo {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            channel,
+            notes: update.body.clone(),
+            date: update.date.map(|date| date.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub version: String,
+    pub downloaded: u64,
+    // 服务器没有返回大小时为空
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum InstallResult {
+    // 已是最新版本
+    UpToDate,
+    // 已安装，需要重启程序
+    Installed { info: UpdateInfo },
+    // 已下载，退出程序时安装
+    Pending { info: UpdateInfo },
+}
+
+// 已下载但尚未安装的更新
+struct PendingUpdate {
+    update: Update,
+    info: UpdateInfo,
+    bytes: Vec<u8>,
+}
+
+pub struct UpdateManager {
+    app: AppHandle,
+    config: RwLock<UpdateConfig>,
+    store: Arc<Store<Wry>>,
+    pending: Mutex<Option<PendingUpdate>>,
+    downloading: AtomicBool,
+}
+
+impl UpdateManager {
+    pub fn new(app: &AppHandle) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: UpdateConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Ok(UpdateManager {
+            app: app.clone(),
+            config: RwLock::new(config),
+            store,
+            pending: Mutex::new(None),
+            downloading: AtomicBool::new(false),
+        })
+    }
+
+    pub fn get_config(&self) -> UpdateConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: UpdateConfig) -> UpdateConfig {
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存更新设置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化更新设置失败: {}", err),
+        }
+        *self.config.write().unwrap() = config.clone();
+        config
+    }
+
+    // 已下载等待安装的版本
+    pub fn pending(&self) -> Option<UpdateInfo> {
+        self.pending
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|pending| pending.info.clone())
+    }
+
+    // 检查是否有新版本，channel 为空时使用设置中的通道，已是最新版本时返回空
+    pub async fn check(&self, channel: Option<UpdateChannel>) -> AppResult<Option<UpdateInfo>> {
+        let channel = channel.unwrap_or(self.get_config().channel);
+        let update = self.fetch(channel).await?;
+        Ok(update.map(|update| UpdateInfo::new(&update, channel)))
+    }
+
+    // 下载新版本，按设置立即安装或等到退出时安装
+    pub async fn install(&self) -> AppResult<InstallResult> {
+        if self.downloading.swap(true, Ordering::SeqCst) {
+            return Err(AppError::Update("正在下载更新".to_string()));
+        }
+        let result = self.download_and_install().await;
+        self.downloading.store(false, Ordering::SeqCst);
+        result
+    }
+
+    async fn download_and_install(&self) -> AppResult<InstallResult> {
+        let config = self.get_config();
+        let Some(update) = self.fetch(config.channel).await? else {
+            return Ok(InstallResult::UpToDate);
+        };
+        let info = UpdateInfo::new(&update, config.channel);
+        if let Some(pending) = self.pending() {
+            if pending.version == info.version && config.install_on_quit {
+                return Ok(InstallResult::Pending { info: pending });
+            }
+        }
+
+        let app = self.app.clone();
+        let version = info.version.clone();
+        let mut last_emit = Instant::now() - PROGRESS_INTERVAL;
+        let bytes = download::download(&self.app, &update, move |downloaded, total| {
+            if last_emit.elapsed() >= PROGRESS_INTERVAL || total == Some(downloaded) {
+                last_emit = Instant::now();
+                let _ = app.emit(
+                    PROGRESS_EVENT,
+                    DownloadProgress {
+                        version: version.clone(),
+                        downloaded,
+                        total,
+                    },
+                );
+            }
+        })
+        .await?;
+        let _ = self.app.emit(DOWNLOADED_EVENT, &info);
+
+        if config.install_on_quit {
+            log::info!("已下载新版本 {}，将在退出程序时安装", info.version);
+            *self.pending.lock().unwrap() = Some(PendingUpdate {
+                update,
+                info: info.clone(),
+                bytes,
+            });
+            return Ok(InstallResult::Pending { info });
+        }
+        update
+            .install(bytes)
+            .map_err(|err| AppError::Update(err.to_string()))?;
+        download::clear(&self.app);
+        Ok(InstallResult::Installed { info })
+    }
+
+    // 安装已下载的更新，退出程序时调用，没有待安装的更新时返回 false
+    pub fn install_pending(&self) -> AppResult<bool> {
+        let Some(pending) = self.pending.lock().unwrap().take() else {
+            return Ok(false);
+        };
+        log::info!("正在安装新版本 {}", pending.info.version);
+        pending
+            .update
+            .install(pending.bytes)
+            .map_err(|err| AppError::Update(err.to_string()))?;
+        download::clear(&self.app);
+        Ok(true)
+    }
+
+    async fn fetch(&self, channel: UpdateChannel) -> AppResult<Option<Update>> {
+        updater(&self.app, channel)?
+            .check()
+            .await
+            .map_err(|err| AppError::Update(err.to_string()))
+    }
+}
+
+// 检查更新时使用全局代理设置
+fn updater(app: &AppHandle, channel: UpdateChannel) -> AppResult<Updater> {
+    let mut builder = app.updater_builder();
+    if let Some(proxy) = crate::proxy::current() {
+        builder = builder.proxy(proxy);
+    }
+    if channel == UpdateChannel::Beta {
+        let endpoint =
+            Url::parse(BETA_ENDPOINT).map_err(|err| AppError::Update(err.to_string()))?;
+        builder = builder
+            .endpoints(vec![endpoint])
+            .map_err(|err| AppError::Update(err.to_string()))?;
+    }
+    builder
+        .build()
+        .map_err(|err| AppError::Update(err.to_string()))
+}
@@ -0,0 +1,186 @@
+use crate::error::{AppError, AppResult};
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_http::reqwest::header::{CONTENT_RANGE, RANGE};
+use tauri_plugin_http::reqwest::StatusCode;
+use tauri_plugin_updater::Update;
+use tokio::io::AsyncWriteExt;
+
+// 网络中断后的最大重试次数，每次重试都从已下载的位置继续
+const MAX_ATTEMPTS: u32 = 8;
+const RETRY_BASE: Duration = Duration::from_secs(2);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+// 单次请求的连接超时，下载本身不设总超时，慢速网络下也能完成
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+// 两次收到数据之间的最长间隔，超过时视为连接中断
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+// 断点续传下载更新包，未完成的部分保存在缓存目录，下次下载时继续
+// 下载完成后校验签名，校验失败时删除文件
+pub async fn download(
+    app: &AppHandle,
+    update: &Update,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> AppResult<Vec<u8>> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|err| AppError::Update(err.to_string()))?
+        .join("updates");
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(part_name(update));
+    remove_stale(&dir, &path).await;
+
+    let http = crate::proxy::client_builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .build()?;
+    let mut attempts = 0;
+    loop {
+        match fetch(&http, update, &path, &mut on_progress).await {
+            Ok(()) => break,
+            Err(err) => {
+                attempts += 1;
+                if attempts >= MAX_ATTEMPTS {
+                    return Err(err);
+                }
+                let delay = RETRY_BASE
+                    .saturating_mul(1 << (attempts - 1).min(5))
+                    .min(RETRY_MAX);
+                log::warn!(
+                    "下载更新中断，{} 秒后第 {} 次重试: {}",
+                    delay.as_secs(),
+                    attempts,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+
+    let bytes = tokio::fs::read(&path).await?;
+    if let Err(err) = verify(app, &bytes, &update.signature) {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(err);
+    }
+    Ok(bytes)
+}
+
+// 安装完成或放弃更新后删除缓存的更新包
+pub fn clear(app: &AppHandle) {
+    if let Ok(dir) = app.path().app_cache_dir() {
+        let _ = std::fs::remove_dir_all(dir.join("updates"));
+    }
+}
+
+// 文件名包含版本号，不同版本的更新包不会混在一起续传
+fn part_name(update: &Update) -> String {
+    let file_name = update
+        .download_url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("update");
+    format!("{}-{}.part", update.version, file_name)
+}
+
+// 删除其他版本留下的未完成下载
+async fn remove_stale(dir: &Path, keep: &Path) {
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if entry.path().as_path() != keep {
+            let _ = tokio::fs::remove_file(entry.path()).await;
+        }
+    }
+}
+
+// 从文件已有的长度继续下载，服务器不支持 Range 时从头下载
+async fn fetch(
+    http: &tauri_plugin_http::reqwest::Client,
+    update: &Update,
+    path: &Path,
+    on_progress: &mut impl FnMut(u64, Option<u64>),
+) -> AppResult<()> {
+    let mut downloaded = tokio::fs::metadata(path)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let mut request = http.get(update.download_url.clone());
+    if downloaded > 0 {
+        request = request.header(RANGE, format!("bytes={}-", downloaded));
+    }
+    let mut response = request.send().await?;
+
+    let total = match response.status() {
+        StatusCode::PARTIAL_CONTENT => response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit('/').next())
+            .and_then(|total| total.parse::<u64>().ok()),
+        // 已下载的部分超出文件大小，说明之前已经下载完成
+        StatusCode::RANGE_NOT_SATISFIABLE => return Ok(()),
+        _ => {
+            response.error_for_status_ref()?;
+            downloaded = 0;
+            response.content_length()
+        }
+    };
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(downloaded > 0)
+        .truncate(downloaded == 0)
+        .open(path)
+        .await?;
+    on_progress(downloaded, total);
+
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+        on_progress(downloaded, total);
+    }
+    file.flush().await?;
+
+    if let Some(total) = total {
+        if downloaded < total {
+            return Err(AppError::Update(format!(
+                "下载不完整: {}/{} 字节",
+                downloaded, total
+            )));
+        }
+    }
+    Ok(())
+}
+
+// 使用 tauri.conf.json 中配置的公钥校验更新包的 minisign 签名
+fn verify(app: &AppHandle, data: &[u8], signature: &str) -> AppResult<()> {
+    let pubkey = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|config| config["pubkey"].as_str())
+        .ok_or_else(|| AppError::Update("没有配置更新公钥".to_string()))?;
+    let public_key = PublicKey::decode(&decode_base64(pubkey)?).map_err(invalid_signature)?;
+    let signature = Signature::decode(&decode_base64(signature)?).map_err(invalid_signature)?;
+    public_key
+        .verify(data, &signature, true)
+        .map_err(invalid_signature)
+}
+
+fn invalid_signature(err: impl std::fmt::Display) -> AppError {
+    AppError::Update(format!("更新包签名校验失败: {}", err))
+}
+
+fn decode_base64(value: &str) -> AppResult<String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(value.trim())
+        .map_err(invalid_signature)?;
+    String::from_utf8(bytes).map_err(invalid_signature)
+}
@@ -0,0 +1,278 @@
+use crate::asset_cache::AssetCache;
+use crate::bilibili::{self, get_api, ApiGroup};
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::inflight::{Inflight, InflightFuture};
+use futures_util::FutureExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_http::reqwest::Client;
+
+// 用户名片，包含头像和账号等级
+const USER_CARD_URL: &str = "https://api.bilibili.com/x/web-interface/card";
+
+// 通过接口查询到用户信息时发送给前端的事件，用于更新已显示事件的头像
+pub const UPDATED_EVENT: &str = "user-info://updated";
+
+// 接口查询结果的有效期
+const INFO_TTL: Duration = Duration::from_secs(3600);
+// 查询失败后再次由事件触发查询的间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+// 内存中最多缓存的用户数，超过时淘汰最久没有出现的用户
+const MAX_USERS: usize = 10_000;
+// 事件触发的后台查询最多同时排队的数量，超过时跳过，等下次收到该用户的事件再查询
+const MAX_BACKGROUND_FETCHES: usize = 5;
+// 接口剩余额度低于该值时不再发起后台查询，留给登录状态检查等请求
+const BACKGROUND_MIN_BUDGET: f64 = 5.0;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UserInfo {
+    pub uid: u64,
+    pub uname: String,
+    // 头像地址
+    pub face: String,
+    // 账号等级，没有通过接口查询过时为空
+    pub level: Option<u32>,
+    // 最近一条弹幕中佩戴的粉丝勋章
+    pub fans_medal_name: String,
+    pub fans_medal_level: u32,
+}
+
+struct CachedUser {
+    info: UserInfo,
+    // 上次请求接口的时间，失败时同样记录
+    fetched_at: Option<Instant>,
+    // 上次收到该用户事件或查询的时间，用于淘汰
+    used_at: Instant,
+}
+
+impl CachedUser {
+    fn new(uid: u64) -> Self {
+        CachedUser {
+            info: UserInfo {
+                uid,
+                ..UserInfo::default()
+            },
+            fetched_at: None,
+            used_at: Instant::now(),
+        }
+    }
+
+    // 记录事件中携带的用户信息
+    fn observe(&mut self, event: &DanmakuEvent) {
+        self.used_at = Instant::now();
+        if !event.uname.is_empty() {
+            self.info.uname = event.uname.clone();
+        }
+        if let Some(face) = event.uface.as_ref().filter(|face| !face.is_empty()) {
+            self.info.face = face.clone();
+        }
+        // 只有弹幕一定携带佩戴的勋章，勋章等级为 0 表示没有佩戴
+        if event.kind == EventKind::Danmaku {
+            self.info.fans_medal_name = event.fans_medal_name.clone();
+            self.info.fans_medal_level = event.fans_medal_level;
+        }
+        if event.user_level.is_some() {
+            self.info.level = event.user_level;
+        }
+    }
+
+    // 补全事件中缺少的头像和勋章
+    fn fill(&self, event: &mut DanmakuEvent) {
+        if event.uface.as_deref().is_none_or(str::is_empty) && !self.info.face.is_empty() {
+            event.uface = Some(self.info.face.clone());
+        }
+        if event.kind != EventKind::Danmaku
+            && event.fans_medal_level == 0
+            && self.info.fans_medal_level > 0
+        {
+            event.fans_medal_name = self.info.fans_medal_name.clone();
+            event.fans_medal_level = self.info.fans_medal_level;
+        }
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.info.level.is_some() && self.fetched_at.is_some_and(|at| at.elapsed() < INFO_TTL)
+    }
+
+    fn should_fetch(&self) -> bool {
+        self.info.face.is_empty()
+            && self
+                .fetched_at
+                .is_none_or(|at| at.elapsed() >= RETRY_INTERVAL)
+    }
+}
+
+struct Inner {
+    app: AppHandle,
+    http: Client,
+    // 头像与礼物图标等共用同一个磁盘缓存
+    assets: AssetCache,
+    users: Mutex<HashMap<u64, CachedUser>>,
+    user_fetches: Inflight<u64, UserInfo>,
+}
+
+impl Inner {
+    async fn fetch_user(&self, uid: u64) -> AppResult<UserInfo> {
+        if let Some(user) = self.users.lock().unwrap().get_mut(&uid) {
+            user.fetched_at = Some(Instant::now());
+        }
+        let data = get_api(&self.http, &format!("{}?mid={}", USER_CARD_URL, uid), None).await?;
+        let card = &data["card"];
+        if card.is_null() {
+            return Err(AppError::BilibiliApi {
+                code: -1,
+                message: format!("无法获取用户 {} 的信息", uid),
+            });
+        }
+        let info = {
+            let mut users = self.users.lock().unwrap();
+            let user = users.entry(uid).or_insert_with(|| CachedUser::new(uid));
+            user.fetched_at = Some(Instant::now());
+            user.used_at = Instant::now();
+            if let Some(uname) = card["name"].as_str().filter(|uname| !uname.is_empty()) {
+                user.info.uname = uname.to_string();
+            }
+            if let Some(face) = card["face"].as_str().filter(|face| !face.is_empty()) {
+                user.info.face = face.to_string();
+            }
+            user.info.level = card["level_info"]["current_level"]
+                .as_u64()
+                .map(|level| level as u32);
+            user.info.clone()
+        };
+        let _ = self.app.emit(UPDATED_EVENT, &info);
+        Ok(info)
+    }
+}
+
+// 同一用户的并发查询合并为一次，查询失败的原因记录在日志中
+fn fetch_user(inner: &Arc<Inner>, uid: u64) -> InflightFuture<UserInfo> {
+    inner.user_fetches.get_or_start(uid, || {
+        let inner = inner.clone();
+        async move {
+            let result = inner.fetch_user(uid).await;
+            inner.user_fetches.finish(&uid);
+            result
+                .inspect_err(|err| log::warn!("获取用户 {} 的信息失败: {}", uid, err))
+                .ok()
+        }
+        .boxed()
+    })
+}
+
+// 缓存用户的头像、勋章和等级，为缺少这些信息的事件补全
+// 头像保存在资源缓存中，覆盖层显示头像时不需要每个浏览器源分别请求 B 站
+#[derive(Clone)]
+pub struct UserInfoCache {
+    inner: Arc<Inner>,
+}
+
+impl UserInfoCache {
+    pub fn new(app: &AppHandle, assets: AssetCache) -> Self {
+        UserInfoCache {
+            inner: Arc::new(Inner {
+                app: app.clone(),
+                http: bilibili::client(),
+                assets,
+                users: Mutex::new(HashMap::new()),
+                user_fetches: Inflight::new(),
+            }),
+        }
+    }
+
+    // 用缓存补全事件，缓存中没有头像时在后台查询，之后的事件即可补全
+    pub fn enrich(&self, event: &mut DanmakuEvent) {
+        if event.uid == 0 {
+            return;
+        }
+        let should_fetch = {
+            let mut users = self.inner.users.lock().unwrap();
+            if users.len() >= MAX_USERS && !users.contains_key(&event.uid) {
+                evict_users(&mut users);
+            }
+            let user = users
+                .entry(event.uid)
+                .or_insert_with(|| CachedUser::new(event.uid));
+            user.observe(event);
+            user.fill(event);
+            user.should_fetch()
+        };
+        if should_fetch
+            && self.inner.user_fetches.len() < MAX_BACKGROUND_FETCHES
+            && bilibili::available(ApiGroup::Web) >= BACKGROUND_MIN_BUDGET
+        {
+            let fetch = fetch_user(&self.inner, event.uid);
+            tauri::async_runtime::spawn(fetch);
+        }
+    }
+
+    // 获取用户信息，缓存过期或 refresh 为 true 时重新查询
+    pub async fn get(&self, uid: u64, refresh: bool) -> AppResult<UserInfo> {
+        if !refresh {
+            let users = self.inner.users.lock().unwrap();
+            if let Some(user) = users.get(&uid).filter(|user| user.is_fresh()) {
+                return Ok(user.info.clone());
+            }
+        }
+        fetch_user(&self.inner, uid)
+            .await
+            .ok_or_else(|| AppError::BilibiliApi {
+                code: -1,
+                message: format!("无法获取用户 {} 的信息", uid),
+            })
+    }
+
+    // 事件的账号等级，弹幕自带等级，其他事件使用该用户之前的弹幕或用户卡片中的等级，不会查询接口
+    pub fn level_of(&self, event: &DanmakuEvent) -> Option<u32> {
+        if event.user_level.is_some() || event.uid == 0 {
+            return event.user_level;
+        }
+        self.inner.users.lock().unwrap().get(&event.uid)?.info.level
+    }
+
+    // 返回缓存在磁盘上的头像文件，不存在时下载
+    pub async fn avatar(&self, uid: u64) -> AppResult<PathBuf> {
+        let face = self
+            .inner
+            .users
+            .lock()
+            .unwrap()
+            .get(&uid)
+            .map(|user| user.info.face.clone())
+            .filter(|face| !face.is_empty());
+        let face = match face {
+            Some(face) => face,
+            None => self.get(uid, false).await?.face,
+        };
+        if face.is_empty() {
+            return Err(AppError::BilibiliApi {
+                code: -1,
+                message: format!("用户 {} 没有头像", uid),
+            });
+        }
+        self.inner.assets.fetch(&face).await
+    }
+
+    // 清空内存中的用户信息，头像由资源缓存统一清理
+    pub fn clear(&self) {
+        self.inner.users.lock().unwrap().clear();
+        log::info!("已清空用户信息缓存");
+    }
+}
+
+// 淘汰十分之一最久没有出现的用户
+fn evict_users(users: &mut HashMap<u64, CachedUser>) {
+    let mut used: Vec<(u64, Instant)> = users
+        .iter()
+        .map(|(uid, user)| (*uid, user.used_at))
+        .collect();
+    used.sort_by_key(|(_, used_at)| *used_at);
+    for (uid, _) in used.into_iter().take(MAX_USERS / 10) {
+        users.remove(&uid);
+    }
+}
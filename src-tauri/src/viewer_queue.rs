@@ -0,0 +1,351 @@
+use crate::danmaku::{self, DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存排队配置和队列的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "viewer_queue.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+const QUEUE_KEY: &str = "queue";
+const SERVED_KEY: &str = "served";
+
+// 队列变化时发送给前端的事件，内容为当前的 ViewerQueue
+pub const QUEUE_EVENT: &str = "viewer-queue://queue";
+
+// 保留的已叫号记录数量
+const MAX_SERVED: usize = 100;
+
+// 观众排队配置，用于连麦、上车等需要按顺序叫号的场景
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewerQueueConfig {
+    pub enabled: bool,
+    // 弹幕以该关键词开头时加入队列，关键词后的内容作为备注，例如 "排队 游戏ID"
+    pub join_command: String,
+    // 发送该弹幕时退出队列
+    pub leave_command: String,
+    // 大航海排在普通观众前面，等级高的排在前面，同等级按加入顺序
+    pub guard_priority: bool,
+    // 队列的最大长度，为 0 时不限制
+    pub max_queue: usize,
+    // 只接受不低于该大航海等级的用户排队，0 为所有人，3 为舰长，1 为总督
+    pub min_guard_level: u8,
+}
+
+impl Default for ViewerQueueConfig {
+    fn default() -> Self {
+        ViewerQueueConfig {
+            enabled: false,
+            join_command: "排队".to_string(),
+            leave_command: "取消排队".to_string(),
+            guard_priority: true,
+            max_queue: 50,
+            min_guard_level: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub id: String,
+    pub room_id: u64,
+    pub uid: u64,
+    pub open_id: Option<String>,
+    pub uname: String,
+    pub guard_level: u8,
+    // 排队关键词后的内容，可能为空
+    pub note: String,
+    // Unix 毫秒时间戳
+    pub joined_at: i64,
+    // 叫号的 Unix 毫秒时间戳
+    #[serde(default)]
+    pub served_at: Option<i64>,
+}
+
+impl QueueEntry {
+    fn same_user(&self, event: &DanmakuEvent) -> bool {
+        event
+            .user_key()
+            .is_some_and(|key| danmaku::user_key(self.uid, self.open_id.as_deref()) == Some(key))
+    }
+
+    // 排序用的优先级，数字越小越靠前，普通观众为 4
+    fn priority(&self, guard_priority: bool) -> u8 {
+        if guard_priority && self.guard_level > 0 {
+            self.guard_level
+        } else {
+            4
+        }
+    }
+}
+
+// 当前队列和最近叫号的观众，队首为下一位
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ViewerQueue {
+    pub enabled: bool,
+    pub join_command: String,
+    pub queue: Vec<QueueEntry>,
+    pub served: Vec<QueueEntry>,
+}
+
+#[derive(Default)]
+struct State {
+    config: ViewerQueueConfig,
+    queue: Vec<QueueEntry>,
+    // 最近叫号的在前
+    served: Vec<QueueEntry>,
+}
+
+impl State {
+    fn snapshot(&self) -> ViewerQueue {
+        ViewerQueue {
+            enabled: self.config.enabled,
+            join_command: self.config.join_command.clone(),
+            queue: self.queue.clone(),
+            served: self.served.clone(),
+        }
+    }
+
+    fn position(&self, id: &str) -> AppResult<usize> {
+        self.queue
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| AppError::QueueEntryNotFound(id.to_string()))
+    }
+
+    // 按优先级插入，排在所有优先级不低于自己的观众后面
+    fn insert(&mut self, entry: QueueEntry) {
+        let guard_priority = self.config.guard_priority;
+        let priority = entry.priority(guard_priority);
+        let index = self
+            .queue
+            .iter()
+            .position(|queued| queued.priority(guard_priority) > priority)
+            .unwrap_or(self.queue.len());
+        self.queue.insert(index, entry);
+    }
+
+    // 处理排队和退出排队弹幕，队列有变化时返回 true
+    fn on_danmaku(&mut self, event: &DanmakuEvent) -> bool {
+        let config = &self.config;
+        if !config.enabled || event.kind != EventKind::Danmaku || event.replay {
+            return false;
+        }
+        let message = event.message.trim();
+
+        if !config.leave_command.is_empty() && message == config.leave_command {
+            return match self.queue.iter().position(|entry| entry.same_user(event)) {
+                Some(index) => {
+                    self.queue.remove(index);
+                    true
+                }
+                None => false,
+            };
+        }
+
+        if config.join_command.is_empty() {
+            return false;
+        }
+        let Some(note) = message.strip_prefix(config.join_command.as_str()) else {
+            return false;
+        };
+        let note = note
+            .trim_start_matches(|c: char| c.is_whitespace() || c == ':' || c == '：')
+            .trim();
+        // 大航海等级数字越小等级越高，0 表示不是大航海
+        if config.min_guard_level > 0
+            && (event.guard_level == 0 || event.guard_level > config.min_guard_level)
+        {
+            return false;
+        }
+        if config.max_queue > 0 && self.queue.len() >= config.max_queue {
+            return false;
+        }
+        if self.queue.iter().any(|entry| entry.same_user(event)) {
+            return false;
+        }
+
+        self.insert(QueueEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            room_id: event.room_id,
+            uid: event.uid,
+            open_id: event.open_id.clone(),
+            uname: event.uname.clone(),
+            guard_level: event.guard_level,
+            note: note.to_string(),
+            joined_at: event.timestamp,
+            served_at: None,
+        });
+        true
+    }
+}
+
+struct Shared {
+    app: AppHandle,
+    store: Arc<Store<Wry>>,
+    state: Mutex<State>,
+}
+
+impl Shared {
+    // 保存队列并通知前端
+    fn changed(&self, state: &State) {
+        for (key, value) in [
+            (QUEUE_KEY, serde_json::to_value(&state.queue)),
+            (SERVED_KEY, serde_json::to_value(&state.served)),
+        ] {
+            match value {
+                Ok(value) => self.store.set(key, value),
+                Err(err) => log::warn!("序列化排队队列失败: {}", err),
+            }
+        }
+        if let Err(err) = self.store.save() {
+            log::warn!("保存排队队列失败: {}", err);
+        }
+        let _ = self.app.emit(QUEUE_EVENT, &state.snapshot());
+    }
+}
+
+// 从弹幕中解析排队指令，维护持久化的观众队列
+pub struct ViewerQueueManager {
+    shared: Arc<Shared>,
+}
+
+impl ViewerQueueManager {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let state = State {
+            config: load(&store, CONFIG_KEY),
+            queue: load(&store, QUEUE_KEY),
+            served: load(&store, SERVED_KEY),
+        };
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            store,
+            state: Mutex::new(state),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(ViewerQueueManager { shared })
+    }
+
+    pub fn get_config(&self) -> ViewerQueueConfig {
+        self.shared.state.lock().unwrap().config.clone()
+    }
+
+    pub fn update_config(&self, config: ViewerQueueConfig) -> AppResult<ViewerQueueConfig> {
+        let config = ViewerQueueConfig {
+            join_command: config.join_command.trim().to_string(),
+            leave_command: config.leave_command.trim().to_string(),
+            ..config
+        };
+        if config.join_command.is_empty() {
+            return Err(AppError::InvalidConfig("排队指令不能为空".to_string()));
+        }
+        if config.join_command == config.leave_command {
+            return Err(AppError::InvalidConfig(
+                "排队指令和退出指令不能相同".to_string(),
+            ));
+        }
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.shared.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.shared.store.save() {
+                    log::warn!("保存排队配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化排队配置失败: {}", err),
+        }
+        let mut state = self.shared.state.lock().unwrap();
+        state.config = config.clone();
+        let _ = self.shared.app.emit(QUEUE_EVENT, &state.snapshot());
+        Ok(config)
+    }
+
+    pub fn queue(&self) -> ViewerQueue {
+        self.shared.state.lock().unwrap().snapshot()
+    }
+
+    // 叫号：把队首移到已叫号记录，队列为空时不做任何操作
+    pub fn pop(&self) -> AppResult<ViewerQueue> {
+        self.update(|state| {
+            if state.queue.is_empty() {
+                return Ok(());
+            }
+            let mut entry = state.queue.remove(0);
+            entry.served_at = Some(chrono::Utc::now().timestamp_millis());
+            state.served.insert(0, entry);
+            state.served.truncate(MAX_SERVED);
+            Ok(())
+        })
+    }
+
+    // 跳过观众，移到队尾，适用于叫号时观众暂时不在的情况
+    pub fn skip(&self, id: &str) -> AppResult<ViewerQueue> {
+        self.update(|state| {
+            let index = state.position(id)?;
+            let entry = state.queue.remove(index);
+            state.queue.push(entry);
+            Ok(())
+        })
+    }
+
+    // 把观众移动到队列中的指定位置，超出范围时移动到队尾
+    pub fn move_to(&self, id: &str, index: usize) -> AppResult<ViewerQueue> {
+        self.update(|state| {
+            let from = state.position(id)?;
+            let entry = state.queue.remove(from);
+            let index = index.min(state.queue.len());
+            state.queue.insert(index, entry);
+            Ok(())
+        })
+    }
+
+    pub fn remove(&self, id: &str) -> AppResult<ViewerQueue> {
+        self.update(|state| {
+            let index = state.position(id)?;
+            state.queue.remove(index);
+            Ok(())
+        })
+    }
+
+    pub fn clear(&self) -> AppResult<ViewerQueue> {
+        self.update(|state| {
+            state.queue.clear();
+            Ok(())
+        })
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State) -> AppResult<()>) -> AppResult<ViewerQueue> {
+        let mut state = self.shared.state.lock().unwrap();
+        f(&mut state)?;
+        self.shared.changed(&state);
+        Ok(state.snapshot())
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let mut state = shared.state.lock().unwrap();
+        if state.on_danmaku(&event) {
+            shared.changed(&state);
+        }
+    }
+}
+
+fn load<T: serde::de::DeserializeOwned + Default>(store: &Store<Wry>, key: &str) -> T {
+    store
+        .get(key)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,237 @@
+use crate::danmaku::{RoomManager, RoomStatus};
+use crate::error::AppError;
+use crate::event_bus::{DropPolicy, EventBusMetrics, PERSIST_CAPACITY};
+use crate::file_server::{FileServerRegistry, FileServerStatus};
+use crate::relay::{Relay, RelayStatus};
+use crate::system_info::SystemMonitor;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+// 自动重启卡住的模块后发送给前端的事件
+pub const RECOVERED_EVENT: &str = "watchdog://subsystem-recovered";
+
+// 检查各模块心跳的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+// 直播间连接任务和上传任务超过该时间没有心跳时视为卡住
+const ROOM_STALL_TIMEOUT: Duration = Duration::from_secs(120);
+const RELAY_STALL_TIMEOUT: Duration = Duration::from_secs(120);
+// 文件服务器连续多次无法连接时才重启，避免偶尔的超时导致重启
+const FILE_SERVER_MAX_FAILURES: u32 = 2;
+// 保留的重启记录数量
+const MAX_RECOVERIES: usize = 100;
+
+// 后台任务的心跳，任务在每轮循环中调用 beat，主动等待时调用 idle 暂停检查
+#[derive(Clone)]
+pub struct Heartbeat(Arc<AtomicI64>);
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        let heartbeat = Heartbeat(Arc::new(AtomicI64::new(0)));
+        heartbeat.beat();
+        heartbeat
+    }
+
+    pub fn beat(&self) {
+        self.0
+            .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    pub fn idle(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+
+    // 距离上次心跳超过 timeout 时返回经过的时间，暂停检查时返回 None
+    pub fn stalled(&self, timeout: Duration) -> Option<Duration> {
+        let last = self.0.load(Ordering::Relaxed);
+        if last == 0 {
+            return None;
+        }
+        let elapsed =
+            Duration::from_millis((chrono::Utc::now().timestamp_millis() - last).max(0) as u64);
+        (elapsed > timeout).then_some(elapsed)
+    }
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    // 直播间的弹幕连接任务
+    Fetcher,
+    // 事件上传任务
+    Uploader,
+    FileServer,
+}
+
+// 重启时各模块的状态，用于排查卡住的原因
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticSnapshot {
+    pub rooms: Vec<RoomStatus>,
+    pub relay: Option<RelayStatus>,
+    pub file_servers: Vec<FileServerStatus>,
+    pub event_bus: Option<EventBusMetrics>,
+    // 进程占用的物理内存，单位为字节
+    pub memory_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemRecovered {
+    // Unix 毫秒时间戳
+    pub timestamp: i64,
+    pub subsystem: Subsystem,
+    // 直播间号或文件服务器名称，上传任务为空
+    pub target: Option<String>,
+    pub reason: String,
+    // 重启是否成功，失败时为原因
+    pub error: Option<String>,
+    pub snapshot: DiagnosticSnapshot,
+}
+
+// 定时检查直播间连接、事件上传和文件服务器是否卡住，卡住时自动重启
+pub struct Watchdog {
+    recoveries: Arc<Mutex<VecDeque<SubsystemRecovered>>>,
+}
+
+impl Watchdog {
+    pub fn new(app: &AppHandle) -> Self {
+        let recoveries = Arc::new(Mutex::new(VecDeque::new()));
+        tauri::async_runtime::spawn(run(app.clone(), recoveries.clone()));
+        Watchdog { recoveries }
+    }
+
+    // 最近的自动重启记录，按时间倒序排列
+    pub fn recoveries(&self, limit: usize) -> Vec<SubsystemRecovered> {
+        self.recoveries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+fn diagnostic_snapshot(app: &AppHandle) -> DiagnosticSnapshot {
+    let rooms = app.try_state::<RoomManager>();
+    DiagnosticSnapshot {
+        rooms: rooms
+            .as_ref()
+            .map(|rooms| rooms.rooms_status())
+            .unwrap_or_default(),
+        relay: app.try_state::<Relay>().map(|relay| relay.status()),
+        file_servers: app
+            .try_state::<FileServerRegistry>()
+            .map(|registry| registry.list())
+            .unwrap_or_default(),
+        event_bus: rooms.map(|rooms| rooms.bus_metrics()),
+        memory_bytes: app
+            .try_state::<SystemMonitor>()
+            .and_then(|monitor| monitor.process_memory()),
+    }
+}
+
+async fn run(app: AppHandle, recoveries: Arc<Mutex<VecDeque<SubsystemRecovered>>>) {
+    let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+    // 文件服务器连续无法连接的次数
+    let mut failures: HashMap<String, u32> = HashMap::new();
+    loop {
+        ticker.tick().await;
+        let mut recovered = Vec::new();
+        // 在第一次重启前记录，保留卡住时的状态
+        let mut snapshot = None;
+
+        if let Some(rooms) = app.try_state::<RoomManager>() {
+            for (room_id, elapsed) in rooms.stalled_rooms(ROOM_STALL_TIMEOUT) {
+                let reason = format!("直播间连接任务 {} 秒没有心跳", elapsed.as_secs());
+                snapshot.get_or_insert_with(|| diagnostic_snapshot(&app));
+                let result = rooms.restart_room(room_id);
+                recovered.push((
+                    Subsystem::Fetcher,
+                    Some(room_id.to_string()),
+                    reason,
+                    result,
+                ));
+            }
+        }
+
+        if let Some(relay) = app.try_state::<Relay>() {
+            if let Some(elapsed) = relay.stalled(RELAY_STALL_TIMEOUT) {
+                let reason = format!("上传任务 {} 秒没有心跳", elapsed.as_secs());
+                snapshot.get_or_insert_with(|| diagnostic_snapshot(&app));
+                let result = match app.try_state::<RoomManager>() {
+                    Some(rooms) => {
+                        relay.restart(rooms.subscribe_with(
+                            "relay",
+                            PERSIST_CAPACITY,
+                            DropPolicy::DropOldest,
+                        ));
+                        Ok(())
+                    }
+                    None => Err(AppError::InvalidConfig("弹幕连接未初始化".to_string())),
+                };
+                recovered.push((Subsystem::Uploader, None, reason, result));
+            }
+        }
+
+        if let Some(registry) = app.try_state::<FileServerRegistry>() {
+            let unresponsive = registry.unresponsive().await;
+            failures.retain(|name, _| unresponsive.contains(name));
+            for name in unresponsive {
+                let count = failures.entry(name.clone()).or_default();
+                *count += 1;
+                if *count < FILE_SERVER_MAX_FAILURES {
+                    continue;
+                }
+                failures.remove(&name);
+                let reason = format!("文件服务器连续 {} 次无法连接", FILE_SERVER_MAX_FAILURES);
+                snapshot.get_or_insert_with(|| diagnostic_snapshot(&app));
+                let result = registry.restart(&name).await.map(|_| ());
+                recovered.push((Subsystem::FileServer, Some(name), reason, result));
+            }
+        }
+
+        let Some(snapshot) = snapshot else {
+            continue;
+        };
+        let snapshot_json = serde_json::to_string(&snapshot).unwrap_or_default();
+        for (subsystem, target, reason, result) in recovered {
+            let error = result.err().map(|err| err.to_string());
+            log::warn!(
+                event_type = "subsystem_recovered";
+                "自动重启 {:?} {}: {}，重启{}，诊断信息: {}",
+                subsystem,
+                target.as_deref().unwrap_or_default(),
+                reason,
+                match &error {
+                    Some(err) => format!("失败 ({})", err),
+                    None => "成功".to_string(),
+                },
+                snapshot_json
+            );
+            let entry = SubsystemRecovered {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                subsystem,
+                target,
+                reason,
+                error,
+                snapshot: snapshot.clone(),
+            };
+            let _ = app.emit(RECOVERED_EVENT, &entry);
+            let mut recoveries = recoveries.lock().unwrap();
+            if recoveries.len() >= MAX_RECOVERIES {
+                recoveries.pop_front();
+            }
+            recoveries.push_back(entry);
+        }
+    }
+}
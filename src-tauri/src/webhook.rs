@@ -0,0 +1,347 @@
+use crate::danmaku::{DanmakuEvent, EventKind};
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::template;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Wry};
+use tauri_plugin_http::reqwest::header::CONTENT_TYPE;
+use tauri_plugin_http::reqwest::{Client, StatusCode};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::sync::broadcast;
+
+// 保存 webhook 配置的文件，位于应用数据目录
+const STORE_FILE: &str = "webhooks.json";
+const WEBHOOKS_KEY: &str = "webhooks";
+
+// 每次投递完成时发送给前端的事件
+pub const DELIVERY_EVENT: &str = "webhook://delivery";
+
+// 签名和时间戳使用的请求头
+const SIGNATURE_HEADER: &str = "X-Vtsuru-Signature";
+const TIMESTAMP_HEADER: &str = "X-Vtsuru-Timestamp";
+
+// 包含首次请求在内的最多尝试次数
+const MAX_ATTEMPTS: u32 = 5;
+// 重试间隔，每次失败翻倍直到上限
+const RETRY_BASE: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 内存中保留的投递记录数量
+const MAX_DELIVERIES: usize = 500;
+// 投递记录中保留的请求和响应内容长度
+const MAX_RECORDED_BODY: usize = 4096;
+
+// 一个 webhook 的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    // 新建时可以为空，保存时生成
+    #[serde(default)]
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub name: String,
+    pub url: String,
+    // 要发送的事件类型，为空时发送所有类型
+    #[serde(default)]
+    pub kinds: Vec<EventKind>,
+    // JSON 请求体模板，支持 {{uname}} 等事件字段，为空时直接发送事件 JSON
+    #[serde(default)]
+    pub body_template: String,
+    // 设置后使用 HMAC-SHA256 对 "时间戳.请求体" 签名
+    #[serde(default)]
+    pub secret: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 一次投递（包含重试）的记录
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryRecord {
+    pub webhook_id: String,
+    pub webhook_name: String,
+    pub event_id: String,
+    pub event_kind: EventKind,
+    pub url: String,
+    // Unix 毫秒时间戳
+    pub started_at: i64,
+    pub duration_ms: u64,
+    pub attempts: u32,
+    pub success: bool,
+    // 最后一次请求的响应状态码，请求未发出时为空
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub request_body: String,
+    pub response_body: Option<String>,
+}
+
+struct Shared {
+    app: AppHandle,
+    http: Client,
+    webhooks: RwLock<Vec<WebhookConfig>>,
+    deliveries: Mutex<VecDeque<DeliveryRecord>>,
+}
+
+impl Shared {
+    fn record(&self, record: DeliveryRecord) {
+        let _ = self.app.emit(DELIVERY_EVENT, &record);
+        let mut deliveries = self.deliveries.lock().unwrap();
+        if deliveries.len() >= MAX_DELIVERIES {
+            deliveries.pop_front();
+        }
+        deliveries.push_back(record);
+    }
+
+    // 发送事件，失败时按指数退避重试
+    async fn deliver(&self, webhook: &WebhookConfig, event: &DanmakuEvent) {
+        let started_at = chrono::Utc::now().timestamp_millis();
+        let started = Instant::now();
+        let mut record = DeliveryRecord {
+            webhook_id: webhook.id.clone(),
+            webhook_name: webhook.name.clone(),
+            event_id: event.id.clone(),
+            event_kind: event.kind,
+            url: webhook.url.clone(),
+            started_at,
+            duration_ms: 0,
+            attempts: 0,
+            success: false,
+            status: None,
+            error: None,
+            request_body: String::new(),
+            response_body: None,
+        };
+
+        match request_body(webhook, event) {
+            Ok(body) => {
+                record.request_body = truncate(&body);
+                let mut delay = RETRY_BASE;
+                loop {
+                    record.attempts += 1;
+                    let retry = match self.send(webhook, &body).await {
+                        Ok((status, response)) => {
+                            record.status = Some(status.as_u16());
+                            record.response_body = Some(truncate(&response));
+                            record.success = status.is_success();
+                            record.error = (!record.success).then(|| format!("HTTP {}", status));
+                            status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+                        }
+                        Err(err) => {
+                            record.status = None;
+                            record.response_body = None;
+                            record.error = Some(err.to_string());
+                            true
+                        }
+                    };
+                    if record.success || !retry || record.attempts >= MAX_ATTEMPTS {
+                        break;
+                    }
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(RETRY_MAX);
+                }
+            }
+            Err(err) => record.error = Some(err.to_string()),
+        }
+
+        if let Some(err) = &record.error {
+            log::warn!("webhook {} 投递失败: {}", webhook.name, err);
+        }
+        record.duration_ms = started.elapsed().as_millis() as u64;
+        self.record(record);
+    }
+
+    async fn send(&self, webhook: &WebhookConfig, body: &str) -> AppResult<(StatusCode, String)> {
+        let mut request = self
+            .http
+            .post(&webhook.url)
+            .timeout(REQUEST_TIMEOUT)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        if !webhook.secret.is_empty() {
+            let timestamp = chrono::Utc::now().timestamp().to_string();
+            request = request.header(TIMESTAMP_HEADER, &timestamp).header(
+                SIGNATURE_HEADER,
+                format!("sha256={}", sign(&webhook.secret, &timestamp, body)),
+            );
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        Ok((status, text))
+    }
+}
+
+// 把直播间事件推送到用户配置的地址
+pub struct WebhookDispatcher {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let webhooks: Vec<WebhookConfig> = store
+            .get(WEBHOOKS_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let shared = Arc::new(Shared {
+            app: app.clone(),
+            http: crate::proxy::client_builder().build().unwrap_or_default(),
+            webhooks: RwLock::new(webhooks),
+            deliveries: Mutex::new(VecDeque::new()),
+        });
+        tauri::async_runtime::spawn(run(shared.clone(), events));
+        Ok(WebhookDispatcher { shared, store })
+    }
+
+    pub fn list(&self) -> Vec<WebhookConfig> {
+        self.shared.webhooks.read().unwrap().clone()
+    }
+
+    // 新增或按 ID 替换 webhook
+    pub fn save_webhook(&self, webhook: WebhookConfig) -> AppResult<WebhookConfig> {
+        let mut webhook = WebhookConfig {
+            url: webhook.url.trim().to_string(),
+            ..webhook
+        };
+        if !webhook.url.starts_with("https://") && !webhook.url.starts_with("http://") {
+            return Err(AppError::InvalidConfig(
+                "webhook 地址必须以 http:// 或 https:// 开头".to_string(),
+            ));
+        }
+        if webhook.id.is_empty() {
+            webhook.id = uuid::Uuid::new_v4().to_string();
+        }
+        {
+            let mut webhooks = self.shared.webhooks.write().unwrap();
+            match webhooks
+                .iter_mut()
+                .find(|existing| existing.id == webhook.id)
+            {
+                Some(existing) => *existing = webhook.clone(),
+                None => webhooks.push(webhook.clone()),
+            }
+        }
+        self.save();
+        Ok(webhook)
+    }
+
+    pub fn delete_webhook(&self, id: &str) -> AppResult<()> {
+        {
+            let mut webhooks = self.shared.webhooks.write().unwrap();
+            let index = webhooks
+                .iter()
+                .position(|webhook| webhook.id == id)
+                .ok_or_else(|| AppError::WebhookNotFound(id.to_string()))?;
+            webhooks.remove(index);
+        }
+        self.save();
+        Ok(())
+    }
+
+    // 最近的投递记录，按时间倒序排列
+    pub fn deliveries(&self, webhook_id: Option<&str>, limit: usize) -> Vec<DeliveryRecord> {
+        self.shared
+            .deliveries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|record| webhook_id.is_none() || webhook_id == Some(record.webhook_id.as_str()))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn save(&self) {
+        let webhooks = self.list();
+        match serde_json::to_value(&webhooks) {
+            Ok(value) => {
+                self.store.set(WEBHOOKS_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存 webhook 配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化 webhook 配置失败: {}", err),
+        }
+    }
+}
+
+async fn run(shared: Arc<Shared>, mut events: Subscriber<DanmakuEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                log::warn!("webhook 处理不及时，跳过了 {} 个事件", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let webhooks: Vec<WebhookConfig> = shared
+            .webhooks
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|webhook| {
+                webhook.enabled && (webhook.kinds.is_empty() || webhook.kinds.contains(&event.kind))
+            })
+            .cloned()
+            .collect();
+        for webhook in webhooks {
+            let shared = shared.clone();
+            let event = event.clone();
+            tauri::async_runtime::spawn(async move {
+                shared.deliver(&webhook, &event).await;
+            });
+        }
+    }
+}
+
+// 根据模板生成请求体，模板结果不是合法的 JSON 时返回错误
+fn request_body(webhook: &WebhookConfig, event: &DanmakuEvent) -> AppResult<String> {
+    if webhook.body_template.trim().is_empty() {
+        return serde_json::to_string(event)
+            .map_err(|err| AppError::InvalidConfig(err.to_string()));
+    }
+    let body = template::render_json(&webhook.body_template, event);
+    serde_json::from_str::<serde_json::Value>(&body)
+        .map_err(|err| AppError::InvalidConfig(format!("请求体模板不是合法的 JSON: {}", err)))?;
+    Ok(body)
+}
+
+// HMAC-SHA256(secret, "timestamp.body")，以十六进制表示
+fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC 可以使用任意长度的密钥");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    let mut signature = String::with_capacity(64);
+    for byte in mac.finalize().into_bytes() {
+        let _ = write!(signature, "{:02x}", byte);
+    }
+    signature
+}
+
+fn truncate(text: &str) -> String {
+    if text.len() <= MAX_RECORDED_BODY {
+        return text.to_string();
+    }
+    let mut end = MAX_RECORDED_BODY;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
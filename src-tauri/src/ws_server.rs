@@ -0,0 +1,373 @@
+use crate::danmaku::DanmakuEvent;
+use crate::error::{AppError, AppResult};
+use crate::event_bus::Subscriber;
+use crate::file_server::port;
+use crate::file_server::{BIND_ALL_INTERFACES, BIND_LOCALHOST};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+// 保存配置的文件，位于应用数据目录
+pub(crate) const STORE_FILE: &str = "ws_server.json";
+pub(crate) const CONFIG_KEY: &str = "config";
+
+const DEFAULT_PORT: u16 = 23581;
+const CHANNEL_CAPACITY: usize = 1024;
+// 停止服务器时等待连接关闭的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 事件广播服务器的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WsServerConfig {
+    // 启用后随应用启动
+    pub enabled: bool,
+    pub port: u16,
+    // 允许局域网中的其他设备连接
+    pub allow_lan: bool,
+    // 设置后客户端需要在地址中携带 ?token=
+    pub access_token: Option<String>,
+}
+
+impl Default for WsServerConfig {
+    fn default() -> Self {
+        WsServerConfig {
+            enabled: false,
+            port: DEFAULT_PORT,
+            allow_lan: false,
+            access_token: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WsServerStatus {
+    #[serde(flatten)]
+    pub config: WsServerConfig,
+    pub running: bool,
+    pub active_port: Option<u16>,
+    // 当前连接的客户端数量
+    pub clients: usize,
+}
+
+// 客户端发送的消息
+// {"action":"subscribe","types":["danmaku","gift"]}，types 为空表示接收所有事件
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe {
+        #[serde(default)]
+        types: Vec<String>,
+    },
+}
+
+// 序列化一次后发给所有客户端
+struct Frame {
+    kind: &'static str,
+    json: String,
+}
+
+struct Running {
+    cancel: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+struct Shared {
+    config: RwLock<WsServerConfig>,
+    frames: broadcast::Sender<Arc<Frame>>,
+    clients: AtomicUsize,
+    running: tokio::sync::Mutex<Option<Running>>,
+    active_port: Mutex<Option<u16>>,
+}
+
+impl Shared {
+    async fn start(self: &Arc<Self>) -> AppResult<WsServerStatus> {
+        let mut running = self.running.lock().await;
+        if running.is_some() {
+            return Err(AppError::AlreadyRunning);
+        }
+        let config = self.config.read().unwrap().clone();
+        let ip: IpAddr = if config.allow_lan {
+            BIND_ALL_INTERFACES
+        } else {
+            BIND_LOCALHOST
+        }
+        .parse()
+        .map_err(|_| AppError::InvalidConfig("无效的监听地址".to_string()))?;
+        let listener = port::bind(ip, config.port, false).await?;
+        let addr = listener.local_addr().map_err(AppError::Bind)?;
+        log::info!("事件广播服务器启动在 ws://{}", addr);
+
+        let cancel = CancellationToken::new();
+        let task = tauri::async_runtime::spawn(serve(listener, self.clone(), cancel.clone()));
+        *running = Some(Running { cancel, task });
+        *self.active_port.lock().unwrap() = Some(addr.port());
+        drop(running);
+        Ok(self.status())
+    }
+
+    async fn stop(&self) -> AppResult<WsServerStatus> {
+        let Some(running) = self.running.lock().await.take() else {
+            return Err(AppError::NotRunning);
+        };
+        running.cancel.cancel();
+        let abort_handle = running.task.inner().abort_handle();
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, running.task)
+            .await
+            .is_err()
+        {
+            abort_handle.abort();
+        }
+        *self.active_port.lock().unwrap() = None;
+        log::info!("事件广播服务器已停止");
+        Ok(self.status())
+    }
+
+    fn status(&self) -> WsServerStatus {
+        let active_port = *self.active_port.lock().unwrap();
+        WsServerStatus {
+            config: self.config.read().unwrap().clone(),
+            running: active_port.is_some(),
+            active_port,
+            clients: self.clients.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// 本地 WebSocket 服务器，把抓取到的事件以 JSON 转发给 OBS 浮窗等第三方工具
+pub struct WsServer {
+    shared: Arc<Shared>,
+    store: Arc<Store<Wry>>,
+}
+
+impl WsServer {
+    pub fn new(
+        app: &AppHandle,
+        events: Subscriber<DanmakuEvent>,
+    ) -> tauri_plugin_store::Result<Self> {
+        let store = app.store(STORE_FILE)?;
+        let config: WsServerConfig = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let (frames, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let shared = Arc::new(Shared {
+            config: RwLock::new(config),
+            frames,
+            clients: AtomicUsize::new(0),
+            running: tokio::sync::Mutex::new(None),
+            active_port: Mutex::new(None),
+        });
+        tauri::async_runtime::spawn(forward(events, shared.frames.clone()));
+        Ok(WsServer { shared, store })
+    }
+
+    // 配置为启用时在后台启动
+    pub fn auto_start(&self) {
+        if !self.shared.config.read().unwrap().enabled {
+            return;
+        }
+        let shared = self.shared.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = shared.start().await {
+                log::warn!("事件广播服务器启动失败: {}", err);
+            }
+        });
+    }
+
+    pub async fn start(&self) -> AppResult<WsServerStatus> {
+        self.shared.start().await
+    }
+
+    pub async fn stop(&self) -> AppResult<WsServerStatus> {
+        self.shared.stop().await
+    }
+
+    // 保存配置，按新配置重新启动或停止服务器
+    pub async fn update_config(&self, config: WsServerConfig) -> AppResult<WsServerStatus> {
+        if config.port == 0 {
+            return Err(AppError::InvalidConfig("端口不能为 0".to_string()));
+        }
+        let config = WsServerConfig {
+            access_token: config
+                .access_token
+                .map(|token| token.trim().to_string())
+                .filter(|token| !token.is_empty()),
+            ..config
+        };
+        match serde_json::to_value(&config) {
+            Ok(value) => {
+                self.store.set(CONFIG_KEY, value);
+                if let Err(err) = self.store.save() {
+                    log::warn!("保存事件广播服务器配置失败: {}", err);
+                }
+            }
+            Err(err) => log::warn!("序列化事件广播服务器配置失败: {}", err),
+        }
+        let enabled = config.enabled;
+        *self.shared.config.write().unwrap() = config;
+
+        if self.shared.running.lock().await.is_some() {
+            self.shared.stop().await?;
+        }
+        if enabled {
+            self.shared.start().await
+        } else {
+            Ok(self.shared.status())
+        }
+    }
+
+    pub fn status(&self) -> WsServerStatus {
+        self.shared.status()
+    }
+}
+
+// 把直播间事件序列化后转发到服务器内部的广播通道
+async fn forward(mut events: Subscriber<DanmakuEvent>, frames: broadcast::Sender<Arc<Frame>>) {
+    loop {
+        let event = match events.recv().await {
+            // 被审核规则静默的事件不发送给覆盖层
+            Ok(event) if event.muted => continue,
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                log::warn!("事件广播服务器跳过了 {} 个事件", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        match serde_json::to_string(&event) {
+            Ok(json) => {
+                // 没有客户端时发送会失败，直接忽略
+                let _ = frames.send(Arc::new(Frame {
+                    kind: event.kind.as_str(),
+                    json,
+                }));
+            }
+            Err(err) => log::warn!("序列化事件失败: {}", err),
+        }
+    }
+}
+
+async fn serve(listener: TcpListener, shared: Arc<Shared>, cancel: CancellationToken) {
+    loop {
+        let accepted = tokio::select! {
+            _ = cancel.cancelled() => break,
+            accepted = listener.accept() => accepted,
+        };
+        match accepted {
+            Ok((stream, addr)) => {
+                tauri::async_runtime::spawn(handle_client(
+                    shared.clone(),
+                    stream,
+                    addr,
+                    cancel.child_token(),
+                ));
+            }
+            Err(err) => log::warn!("接受事件广播连接失败: {}", err),
+        }
+    }
+}
+
+async fn handle_client(
+    shared: Arc<Shared>,
+    stream: TcpStream,
+    addr: SocketAddr,
+    cancel: CancellationToken,
+) {
+    let token = shared.config.read().unwrap().access_token.clone();
+    // 握手时从地址中读取初始订阅，例如 ws://127.0.0.1:23581/?types=danmaku,gift
+    let mut types = HashSet::new();
+    let callback = |request: &Request, response: Response| {
+        let query = request.uri().query().unwrap_or_default();
+        let mut authorized = token.is_none();
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match key {
+                "token" => authorized |= token.as_deref() == Some(value),
+                "types" => types.extend(parse_types(value)),
+                _ => {}
+            }
+        }
+        if authorized {
+            Ok(response)
+        } else {
+            let mut error = ErrorResponse::new(Some("访问令牌无效".to_string()));
+            *error.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(error)
+        }
+    };
+    let ws = match tokio_tungstenite::accept_hdr_async(stream, callback).await {
+        Ok(ws) => ws,
+        Err(err) => {
+            log::warn!("事件广播客户端 {} 握手失败: {}", addr, err);
+            return;
+        }
+    };
+
+    shared.clients.fetch_add(1, Ordering::Relaxed);
+    let mut frames = shared.frames.subscribe();
+    let (mut sink, mut stream) = ws.split();
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => {
+                let _ = sink.send(Message::Close(None)).await;
+                break;
+            }
+            frame = frames.recv() => match frame {
+                Ok(frame) => {
+                    if !types.is_empty() && !types.contains(frame.kind) {
+                        continue;
+                    }
+                    if sink.send(Message::Text(frame.json.clone())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("事件广播客户端 {} 处理过慢，跳过了 {} 个事件", addr, skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Subscribe { types: requested }) => {
+                            types = requested
+                                .iter()
+                                .flat_map(|value| parse_types(value))
+                                .collect();
+                            json!({ "action": "subscribed", "types": types })
+                        }
+                        Err(err) => json!({ "action": "error", "message": err.to_string() }),
+                    };
+                    if sink.send(Message::Text(reply.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    shared.clients.fetch_sub(1, Ordering::Relaxed);
+}
+
+// 逗号分隔的事件类型，例如 "danmaku,super_chat"
+fn parse_types(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(|kind| kind.trim().to_lowercase())
+        .filter(|kind| !kind.is_empty())
+}